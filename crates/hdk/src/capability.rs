@@ -1,3 +1,4 @@
+pub mod commit_cap_claim;
 pub mod create_cap_claim;
 pub mod create_cap_grant;
 pub mod delete_cap_grant;
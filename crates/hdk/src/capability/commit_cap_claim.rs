@@ -0,0 +1,24 @@
+/// Commit a CapClaim to the local source chain so that `call_remote!` can find it.
+///
+/// The macro input must evaluate to a `CapClaim` struct.
+/// Unlike `create_cap_claim!` the host commits the claim itself, so there is no need to pass an
+/// entry def id.
+///
+/// A claim committed this way can be used by passing `CallRemoteCap::Auto` as the cap to
+/// `call_remote!`. The host will then send the secret of the most recent claim from the called
+/// agent that covers the called function. A newer claim with the same tag supersedes older ones,
+/// so committing a claim with the same tag is how a rotated secret replaces the old one.
+///
+/// @see CapClaim
+/// @see call_remote!
+#[macro_export]
+macro_rules! commit_cap_claim {
+    ( $input:expr ) => {{
+        $crate::prelude::host_externs!(__commit_cap_claim);
+        $crate::host_fn!(
+            __commit_cap_claim,
+            $crate::prelude::CommitCapClaimInput::new($input),
+            $crate::prelude::CommitCapClaimOutput
+        )
+    }};
+}
@@ -14,12 +14,28 @@
 /// create_entry!(Foo(50))?;
 /// ```
 ///
+/// With `guard = CommitGuard::new(head)` the entry is only created if the chain head is still
+/// `head`, as for `create!`.
+///
 /// @see get! and get_details! for more information on CRUD
 ///
 /// @todo do we need/want to expose an alternative pattern to match to allow manually setting the
 /// entry id directly?
 #[macro_export]
 macro_rules! create_entry {
+    ( $input:expr, guard = $guard:expr ) => {{
+        $crate::prelude::host_externs!(__create);
+
+        let try_sb = $crate::prelude::SerializedBytes::try_from($input);
+        match try_sb {
+            Ok(sb) => create!(
+                $input,
+                $crate::prelude::Entry::App(sb.try_into()?),
+                guard = $guard
+            ),
+            Err(e) => Err(e),
+        }
+    }};
     ( $input:expr ) => {{
         $crate::prelude::host_externs!(__create);
 
@@ -7,6 +7,9 @@
 /// ```
 #[macro_export]
 macro_rules! delete_entry {
+    ( $hash:expr, guard = $guard:expr ) => {{
+        delete!($hash, guard = $guard)
+    }};
     ( $hash:expr ) => {{
         delete!($hash)
     }};
@@ -19,6 +19,9 @@
 /// let foo_ten_update_header_hash: HeaderHash = update_entry!(foo_zero_header_hash, Foo(10))?;
 /// ```
 ///
+/// With `guard = CommitGuard::new(head)` the update is only committed if the chain head is still
+/// `head`, as for `create!`.
+///
 /// @todo in the future this will be true because we will have the concept of 'redirects':
 /// Works as an app entry delete+create.
 ///
@@ -27,6 +30,18 @@
 /// @see delete_entry!
 #[macro_export]
 macro_rules! update_entry {
+    ( $hash:expr, $input:expr, guard = $guard:expr ) => {{
+        let try_sb = $crate::prelude::SerializedBytes::try_from($input);
+        match try_sb {
+            Ok(sb) => update!(
+                $hash,
+                $input.into(),
+                $crate::prelude::Entry::App(sb.try_into()?),
+                guard = $guard
+            ),
+            Err(e) => Err(e),
+        }
+    }};
     ( $hash:expr, $input:expr ) => {{
         let try_sb = $crate::prelude::SerializedBytes::try_from($input);
         match try_sb {
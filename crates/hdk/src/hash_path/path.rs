@@ -291,11 +291,16 @@ impl Path {
     pub fn children(&self) -> Result<holochain_zome_types::link::Links, HdkError> {
         Self::ensure(&self)?;
         let links = get_links!(self.hash()?, holochain_zome_types::link::LinkTag::new(NAME))?;
+        let freshness = links.freshness();
         // Only need one of each hash to build the tree.
         let mut unwrapped: Vec<holochain_zome_types::link::Link> = links.into_inner();
         unwrapped.sort();
         unwrapped.dedup();
-        Ok(holochain_zome_types::link::Links::from(unwrapped))
+        let children = holochain_zome_types::link::Links::from(unwrapped);
+        Ok(match freshness {
+            Some(freshness) => children.with_freshness(freshness),
+            None => children,
+        })
     }
 
     pub fn children_details(&self) -> Result<holochain_zome_types::link::LinkDetails, HdkError> {
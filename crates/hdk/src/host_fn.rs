@@ -1,6 +1,7 @@
 pub mod agent_info;
 pub mod call;
 pub mod call_remote;
+pub mod chain_head;
 pub mod create;
 pub mod create_link;
 pub mod debug;
@@ -11,7 +12,9 @@ pub mod emit_signal;
 pub mod encrypt;
 pub mod entry_type_properties;
 pub mod get;
+pub mod get_all_live_headers;
 pub mod get_details;
+pub mod get_indexed;
 pub mod get_link_details;
 pub mod get_links;
 pub mod hash_entry;
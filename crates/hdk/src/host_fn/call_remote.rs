@@ -5,6 +5,9 @@
 /// - agent: The address of the agent to call the RPC style remote function on.
 /// - zome: The zome to call the remote function in. Use zome_info!() to get the current zome info.
 /// - fn_name: The name of the function in the zome to call.
+/// - cap: The `CallRemoteCap` to send. An `Option<CapSecret>` also works. Pass
+///   `CallRemoteCap::Auto` to have the host look up the secret from a claim committed with
+///   `commit_cap_claim!`, which fails with a `NoClaimFound` error if there is no such claim.
 /// - request: The payload to send to the remote function; receiver needs to deserialize cleanly.
 ///
 /// Response is ZomeCallResponse which can either return ZomeCallResponse::Ok or
@@ -32,7 +35,11 @@ macro_rules! call_remote {
         $crate::host_fn!(
             __call_remote,
             $crate::prelude::CallRemoteInput::new($crate::prelude::CallRemote::new(
-                $agent, $zome, $fn_name, $cap, $request
+                $agent,
+                $zome,
+                $fn_name,
+                $cap.into(),
+                $request
             )),
             $crate::prelude::CallRemoteOutput
         )
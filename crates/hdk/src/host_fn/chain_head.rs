@@ -0,0 +1,25 @@
+/// Get the current head of the agent's source chain.
+///
+/// Returns the header hash, sequence number and timestamp of the most recent header, including
+/// any committed earlier in the current zome call.
+///
+/// ```ignore
+/// let (head, seq, timestamp) = chain_head!()?;
+/// ```
+///
+/// Pass a `CommitGuard` on the head to `create!`, `update!`, `delete!`, `create_link!` or
+/// `delete_link!`, or the entry macros built on them, as `guard = CommitGuard::new(head)` to
+/// commit only if nothing else has been committed since.
+///
+/// @see create!
+#[macro_export]
+macro_rules! chain_head {
+    () => {{
+        $crate::prelude::host_externs!(__chain_head);
+        $crate::host_fn!(
+            __chain_head,
+            $crate::prelude::ChainHeadInput::new(()),
+            $crate::prelude::ChainHeadOutput
+        )
+    }};
+}
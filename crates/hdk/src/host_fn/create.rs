@@ -8,17 +8,44 @@
 /// Usually you don't need to use this macro directly but it is the most general way to create an
 /// entry and standardises the internals of higher level create macros.
 ///
+/// This is optimistic concurrency for zomes: read the head with `chain_head!`, decide what to
+/// commit, then commit with `guard = CommitGuard::new(head)`. If anything was committed in
+/// between, the inner result is a `HeadMoved` error naming the expected and actual heads, so the
+/// zome can read the chain again and retry its own logic. The same guard can be passed to
+/// `update!`, `delete!`, `create_link!`, `delete_link!` and the entry macros built on them.
+///
+/// ```ignore
+/// let (head, _, _) = chain_head!()?;
+/// match create!(entry_def_id, entry, guard = CommitGuard::new(head))? {
+///     Ok(header_hash) => ..,
+///     Err(HeadMoved { actual, .. }) => ..,
+/// }
+/// ```
+///
+/// The chain head is checked again when the zome call's commits are written, as for every
+/// commit, so a concurrent zome call which wrote first fails the whole call.
+///
 /// @see create_entry!
 /// @see create_cap_grant!
 /// @see create_cap_claim!
+/// @see chain_head!
 #[macro_export]
 macro_rules! create {
+    ( $type:expr, $entry:expr, guard = $guard:expr ) => {{
+        $crate::prelude::host_externs!(__create);
+        $crate::host_fn!(
+            __create,
+            $crate::prelude::CreateInput::new(($type.into(), $entry.into(), Some($guard))),
+            $crate::prelude::CreateOutput
+        )
+    }};
     ( $type:expr, $entry:expr ) => {{
         $crate::prelude::host_externs!(__create);
         $crate::host_fn!(
             __create,
-            $crate::prelude::CreateInput::new(($type.into(), $entry.into(),)),
+            $crate::prelude::CreateInput::new(($type.into(), $entry.into(), None)),
             $crate::prelude::CreateOutput
         )
+        .map($crate::prelude::unguarded)
     }};
 }
@@ -53,8 +53,23 @@
 /// If you have the hash of the identity entry you can get all the links, if you have the entry or
 /// header hash for any of the creates or updates you can lookup the identity entry hash out of the
 /// body of the create/update entry.
+///
+/// With `guard = CommitGuard::new(head)` the link is only committed if the chain head is still
+/// `head`, as for `create!`.
 #[macro_export]
 macro_rules! create_link {
+    ( $base:expr, $target:expr, guard = $guard:expr ) => {
+        $crate::create_link!($base, $target, vec![], guard = $guard)
+    };
+    ( $base:expr, $target:expr, $tag:expr, guard = $guard:expr ) => {{
+        $crate::prelude::host_externs!(__create_link);
+
+        $crate::host_fn!(
+            __create_link,
+            $crate::prelude::CreateLinkInput::new(($base, $target, $tag.into(), Some($guard))),
+            $crate::prelude::CreateLinkOutput
+        )
+    }};
     ( $base:expr, $target:expr ) => {
         $crate::create_link!($base, $target, vec![])
     };
@@ -63,8 +78,9 @@ macro_rules! create_link {
 
         $crate::host_fn!(
             __create_link,
-            $crate::prelude::CreateLinkInput::new(($base, $target, $tag.into())),
+            $crate::prelude::CreateLinkInput::new(($base, $target, $tag.into(), None)),
             $crate::prelude::CreateLinkOutput
         )
+        .map($crate::prelude::unguarded)
     }};
 }
@@ -7,15 +7,28 @@
 ///
 /// Usually you don't need to use this macro directly but it is the most general way to update an
 /// entry and standardises the internals of higher level create macros.
+///
+/// With `guard = CommitGuard::new(head)` the delete is only committed if the chain head is still
+/// `head`, as for `create!`.
 #[macro_export]
 macro_rules! delete {
+    ( $hash:expr, guard = $guard:expr ) => {{
+        $crate::prelude::host_externs!(__delete);
+
+        $crate::host_fn!(
+            __delete,
+            $crate::prelude::DeleteInput::new(($hash.into(), Some($guard))),
+            $crate::prelude::DeleteOutput
+        )
+    }};
     ( $hash:expr ) => {{
         $crate::prelude::host_externs!(__delete);
 
         $crate::host_fn!(
             __delete,
-            $crate::prelude::DeleteInput::new($hash.into()),
+            $crate::prelude::DeleteInput::new(($hash.into(), None)),
             $crate::prelude::DeleteOutput
         )
+        .map($crate::prelude::unguarded)
     }};
 }
@@ -19,15 +19,28 @@
 ///   link after any previous delete of any link.
 /// All of this is bad so link creates point to entries (@see link_entries!) and deletes point to
 /// creates.
+///
+/// With `guard = CommitGuard::new(head)` the delete is only committed if the chain head is still
+/// `head`, as for `create!`.
 #[macro_export]
 macro_rules! delete_link {
+    ( $add_link_header:expr, guard = $guard:expr ) => {{
+        $crate::prelude::host_externs!(__delete_link);
+
+        $crate::host_fn!(
+            __delete_link,
+            $crate::prelude::DeleteLinkInput::new(($add_link_header, Some($guard))),
+            $crate::prelude::DeleteLinkOutput
+        )
+    }};
     ( $add_link_header:expr ) => {{
         $crate::prelude::host_externs!(__delete_link);
 
         $crate::host_fn!(
             __delete_link,
-            $crate::prelude::DeleteLinkInput::new($add_link_header),
+            $crate::prelude::DeleteLinkInput::new(($add_link_header, None)),
             $crate::prelude::DeleteLinkOutput
         )
+        .map($crate::prelude::unguarded)
     }};
 }
@@ -0,0 +1,31 @@
+/// Get every live header which created an entry, along with the deletes on the entry.
+/// Returns None if the entry does not exist.
+///
+/// Unlike `get!`, which returns the oldest live element, this returns all of them, so it suits
+/// entries that many agents create independently, e.g. to list every author of an anchor.
+///
+/// ```ignore
+/// let live = get_all_live_headers!(hash_entry!(anchor)?)?;
+/// let authors: Vec<AgentPubKey> = live
+///     .map(|live| live.headers.iter().map(|h| h.header().author().clone()).collect())
+///     .unwrap_or_default();
+/// ```
+///
+/// Each authority returns all of its headers and deletes for the entry in one response, which
+/// are merged with this agent's own data. A header is live if none of the deletes target it.
+///
+/// @see get_details! for every header of the entry, live or not, and its updates.
+#[macro_export]
+macro_rules! get_all_live_headers {
+    ( $hash:expr, $options:expr ) => {{
+        $crate::prelude::host_externs!(__get_all_live_headers);
+        $crate::host_fn!(
+            __get_all_live_headers,
+            $crate::prelude::GetAllLiveHeadersInput::new(($hash, $options)),
+            $crate::prelude::GetAllLiveHeadersOutput
+        )
+    }};
+    ( $hash:expr ) => {
+        get_all_live_headers!($hash, $crate::prelude::GetOptions)
+    };
+}
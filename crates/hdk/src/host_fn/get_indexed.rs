@@ -0,0 +1,24 @@
+/// Get the hashes of the live entries of an entry def that are indexed under a key.
+///
+/// Keys are given to entries by the zome's `index_hint_<entry_def_id>` callback when the entry is
+/// validated, and must match exactly, unlike the prefix matching of link tags.
+///
+/// ```ignore
+/// let entry_hashes = get_indexed!("post", topic.as_bytes().to_vec())?;
+/// ```
+///
+/// Returns the hashes in hash order. Use `get!` to fetch the entries themselves.
+#[macro_export]
+macro_rules! get_indexed {
+    ( $entry_def_id:expr, $key:expr ) => {{
+        $crate::prelude::host_externs!(__get_indexed);
+        $crate::host_fn!(
+            __get_indexed,
+            $crate::prelude::GetIndexedInput::new((
+                $entry_def_id.into(),
+                $crate::prelude::bytes::Bytes::from($key)
+            )),
+            $crate::prelude::GetIndexedOutput
+        )
+    }};
+}
@@ -9,18 +9,31 @@
 /// Usually you don't need to use this macro directly but it is the most general way to update an
 /// entry and standardises the internals of higher level create macros.
 ///
+/// With `guard = CommitGuard::new(head)` the update is only committed if the chain head is still
+/// `head`, as for `create!`.
+///
 /// @see update_entry!
 /// @see update_cap_grant!
 /// @see update_cap_claim!
 #[macro_export]
 macro_rules! update {
+    ( $hash:expr, $type:expr, $input:expr, guard = $guard:expr ) => {{
+        $crate::prelude::host_externs!(__update);
+
+        $crate::host_fn!(
+            __update,
+            $crate::prelude::UpdateInput::new(($type, $input, $hash, Some($guard))),
+            $crate::prelude::UpdateOutput
+        )
+    }};
     ( $hash:expr, $type:expr, $input:expr ) => {{
         $crate::prelude::host_externs!(__update);
 
         $crate::host_fn!(
             __update,
-            $crate::prelude::UpdateInput::new(($type, $input, $hash)),
+            $crate::prelude::UpdateInput::new(($type, $input, $hash, None)),
             $crate::prelude::UpdateOutput
         )
+        .map($crate::prelude::unguarded)
     }};
 }
@@ -1,7 +1,9 @@
 /// Trivial macro to get the zome information.
 /// There are no inputs to zome_info.
 ///
-/// Zome information includes dna name, hash, zome name and properties.
+/// Zome information includes dna name, hash, zome name, properties and network seed.
+///
+/// Cells which share wasm but not a network seed can branch on `network_seed`.
 ///
 /// In general any holochain compatible wasm can be compiled and run in any zome so the zome info
 /// needs to be looked up at runtime to e.g. know where to send/receive call_remote rpc calls to.
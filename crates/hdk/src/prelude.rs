@@ -1,5 +1,7 @@
 pub use crate::agent_info;
 pub use crate::call_remote;
+pub use crate::chain_head;
+pub use crate::commit_cap_claim;
 pub use crate::create;
 pub use crate::create_cap_claim;
 pub use crate::create_cap_grant;
@@ -16,7 +18,9 @@ pub use crate::entry_defs;
 pub use crate::error::HdkError;
 pub use crate::generate_cap_secret;
 pub use crate::get;
+pub use crate::get_all_live_headers;
 pub use crate::get_details;
+pub use crate::get_indexed;
 pub use crate::get_link_details;
 pub use crate::get_links;
 pub use crate::hash_entry;
@@ -50,18 +54,25 @@ pub use holochain_wasmer_guest::*;
 pub use holochain_zome_types;
 pub use holochain_zome_types::agent_info::AgentInfo;
 pub use holochain_zome_types::call_remote::CallRemote;
+pub use holochain_zome_types::call_remote::CallRemoteCap;
 pub use holochain_zome_types::capability::*;
+pub use holochain_zome_types::commit_guard::unguarded;
+pub use holochain_zome_types::commit_guard::CommitGuard;
+pub use holochain_zome_types::commit_guard::HeadMoved;
 pub use holochain_zome_types::crdt::CrdtType;
 pub use holochain_zome_types::debug_msg;
 pub use holochain_zome_types::element::{Element, ElementVec};
 pub use holochain_zome_types::entry::*;
 pub use holochain_zome_types::entry_def::*;
 pub use holochain_zome_types::header::*;
+pub use holochain_zome_types::index_hint::IndexHintCallbackResult;
 pub use holochain_zome_types::init::InitCallbackResult;
 pub use holochain_zome_types::link::LinkDetails;
+pub use holochain_zome_types::link::LinkFreshness;
 pub use holochain_zome_types::link::LinkTag;
 pub use holochain_zome_types::link::Links;
 pub use holochain_zome_types::metadata::Details;
+pub use holochain_zome_types::metadata::LiveHeaders;
 pub use holochain_zome_types::migrate_agent::MigrateAgent;
 pub use holochain_zome_types::migrate_agent::MigrateAgentCallbackResult;
 pub use holochain_zome_types::post_commit::PostCommitCallbackResult;
@@ -17,4 +17,20 @@ pub enum HoloHashError {
 
     /// checksum validation failed
     BadChecksum,
+
+    /// the bytes were not the expected size for a prefixed holo hash
+    UnexpectedSize {
+        /// the size a prefixed hash must be
+        expected: usize,
+        /// the size of the bytes given
+        found: usize,
+    },
+
+    /// the type prefix of the bytes does not match the expected hash type
+    UnexpectedPrefix {
+        /// the name of the hash type which was expected
+        expected: &'static str,
+        /// the prefix found on the bytes
+        found: Vec<u8>,
+    },
 }
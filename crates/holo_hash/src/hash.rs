@@ -1,19 +1,32 @@
-use crate::{has_hash::HasHash, HashType, PrimitiveHashType};
+use crate::{error::HoloHashError, has_hash::HasHash, HashType, PrimitiveHashType};
 
+pub(crate) const HASH_PREFIX_LEN: usize = 3;
 pub(crate) const HASH_CORE_LEN: usize = 32;
 pub(crate) const HASH_LOC_LEN: usize = 4;
 
 /// Length of the a full HoloHash bytes
 pub const HOLO_HASH_SERIALIZED_LEN: usize = HASH_CORE_LEN + HASH_LOC_LEN;
 
+/// Length of a full HoloHash with its 3 byte type prefix in front
+pub const HOLO_HASH_PREFIXED_LEN: usize = HASH_PREFIX_LEN + HOLO_HASH_SERIALIZED_LEN;
+
+/// Whether these bytes begin with the type prefix of the expected HashType.
+/// Only the prefix is checked, not the length of the bytes.
+pub fn has_type_prefix(bytes: &[u8], expected: impl HashType) -> bool {
+    bytes.starts_with(expected.get_prefix())
+}
+
 /// A HoloHash contains a vector of 36 bytes representing a 32-byte blake2b hash
 /// plus 4 bytes representing a DHT location. It also contains a zero-sized
 /// type which specifies what it is a hash of.
 // TODO: make holochain_serial! / the derive able to deal with a type param
 // or if not, implement the TryFroms manually...
-#[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct HoloHash<T> {
-    #[serde(with = "serde_bytes")]
+    #[serde(
+        serialize_with = "serde_bytes::serialize",
+        deserialize_with = "deserialize_hash_bytes"
+    )]
     hash: Vec<u8>,
     hash_type: T,
 }
@@ -59,6 +72,11 @@ impl<T: HashType> HoloHash<T> {
     pub fn into_inner(self) -> Vec<u8> {
         self.hash
     }
+
+    /// Whether this is the zero hash, see [HoloHash::zero]
+    pub fn is_zero(&self) -> bool {
+        self.hash.iter().all(|b| *b == 0)
+    }
 }
 
 impl<P: PrimitiveHashType> HoloHash<P> {
@@ -66,6 +84,36 @@ impl<P: PrimitiveHashType> HoloHash<P> {
     pub fn from_raw_bytes(hash: Vec<u8>) -> Self {
         Self::from_raw_bytes_and_type(hash, P::new())
     }
+
+    /// The hash whose bytes are all zero, for use as a "no hash" sentinel
+    /// where an `Option` is awkward, e.g. across an FFI boundary.
+    /// No content hashes to all zeros, so this is never a real hash.
+    pub fn zero() -> Self {
+        Self::from_raw_bytes(vec![0; HOLO_HASH_SERIALIZED_LEN])
+    }
+
+    /// Construct from 39 prefixed bytes, e.g. received over the wire,
+    /// checking both the length and that the 3 byte type prefix matches
+    /// this PrimitiveHashType before the prefix is stripped.
+    pub fn try_from_bytes_checked(bytes: &[u8]) -> Result<Self, HoloHashError> {
+        let hash_type = P::new();
+        if bytes.len() != HOLO_HASH_PREFIXED_LEN {
+            return Err(HoloHashError::UnexpectedSize {
+                expected: HOLO_HASH_PREFIXED_LEN,
+                found: bytes.len(),
+            });
+        }
+        if !has_type_prefix(bytes, hash_type) {
+            return Err(HoloHashError::UnexpectedPrefix {
+                expected: HashType::hash_name(hash_type),
+                found: bytes[..HASH_PREFIX_LEN].to_vec(),
+            });
+        }
+        Ok(Self::from_raw_bytes_and_type(
+            bytes[HASH_PREFIX_LEN..].to_vec(),
+            hash_type,
+        ))
+    }
 }
 
 impl<T: HashType> AsRef<[u8]> for HoloHash<T> {
@@ -95,6 +143,24 @@ impl<T: HashType> HasHash<T> for HoloHash<T> {
     }
 }
 
+/// Hashes are ordered by their full bytes, so collections keyed by hashes,
+/// e.g. a `BTreeMap<HeaderHash, _>`, iterate in the same order everywhere.
+/// Composite hashes with the same bytes are then ordered by their type,
+/// so that the ordering agrees with `Eq`.
+impl<T: HashType> Ord for HoloHash<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.get_full_bytes()
+            .cmp(other.get_full_bytes())
+            .then_with(|| self.hash_type.cmp(&other.hash_type))
+    }
+}
+
+impl<T: HashType> PartialOrd for HoloHash<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 // NB: See encode/encode_raw module for Display impl
 impl<T: HashType> std::fmt::Debug for HoloHash<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -111,6 +177,22 @@ fn bytes_to_loc(bytes: &[u8]) -> u32 {
         + ((bytes[3] as u32) << 24)
 }
 
+/// Deserialize the hash bytes, rejecting any which are not a full HoloHash,
+/// so that a malformed hash is an error when it is decoded rather than a
+/// panic when it is later used.
+fn deserialize_hash_bytes<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<u8>, D::Error> {
+    let hash: Vec<u8> = serde_bytes::deserialize(deserializer)?;
+    if hash.len() != HOLO_HASH_SERIALIZED_LEN {
+        return Err(serde::de::Error::invalid_length(
+            hash.len(),
+            &"a 36 byte holo_hash",
+        ));
+    }
+    Ok(hash)
+}
+
 fn assert_length(hash: &[u8]) {
     if hash.len() != HOLO_HASH_SERIALIZED_LEN {
         panic!(
@@ -152,9 +234,94 @@ mod tests {
         assert_type("DhtOpHash", DhtOpHash::from_raw_bytes(vec![0xdb; 36]));
     }
 
+    #[test]
+    fn test_zero() {
+        let zero = HeaderHash::zero();
+        assert!(zero.is_zero());
+        assert_eq!(zero.get_full_bytes(), &[0; 36][..]);
+        assert_eq!(zero, HeaderHash::zero());
+        assert!(!HeaderHash::from_raw_bytes(vec![0xdb; 36]).is_zero());
+
+        // The zero hash keeps its type when it is converted
+        let zero: AnyDhtHash = EntryHash::zero().into();
+        assert!(zero.is_zero());
+        assert_eq!(*zero.hash_type(), hash_type::AnyDht::Entry);
+    }
+
+    #[test]
+    fn test_ordering_follows_bytes() {
+        let hashes = vec![
+            HeaderHash::from_raw_bytes(vec![2; 36]),
+            HeaderHash::from_raw_bytes(vec![0; 36]),
+            HeaderHash::from_raw_bytes([vec![1; 35], vec![2]].concat()),
+            HeaderHash::from_raw_bytes(vec![1; 36]),
+        ];
+        let mut sorted = hashes.clone();
+        sorted.sort();
+        let mut by_bytes = hashes.clone();
+        by_bytes.sort_by(|a, b| a.get_full_bytes().cmp(b.get_full_bytes()));
+        assert_eq!(sorted, by_bytes);
+
+        let mut reversed = hashes.into_iter().rev().collect::<Vec<_>>();
+        reversed.sort();
+        assert_eq!(reversed, sorted);
+
+        let set = sorted
+            .iter()
+            .cloned()
+            .collect::<std::collections::BTreeSet<_>>();
+        assert_eq!(set.into_iter().collect::<Vec<_>>(), sorted);
+
+        // Hashes of different types with the same bytes are still ordered
+        let entry: AnyDhtHash = EntryHash::from_raw_bytes(vec![1; 36]).into();
+        let header: AnyDhtHash = HeaderHash::from_raw_bytes(vec![1; 36]).into();
+        assert_ne!(entry.cmp(&header), std::cmp::Ordering::Equal);
+        assert_eq!(entry.cmp(&header), header.cmp(&entry).reverse());
+    }
+
     #[test]
     #[should_panic]
     fn test_fails_with_bad_size() {
         DnaHash::from_raw_bytes(vec![0xdb; 35]);
     }
+
+    fn prefixed<T: HashType>(hash_type: T, len: usize) -> Vec<u8> {
+        let mut bytes = hash_type.get_prefix().to_vec();
+        bytes.extend(vec![0xdb; len]);
+        bytes
+    }
+
+    #[test]
+    fn test_has_type_prefix() {
+        let bytes = prefixed(hash_type::Dna, 36);
+        assert!(has_type_prefix(&bytes, hash_type::Dna));
+        assert!(!has_type_prefix(&bytes, hash_type::Entry));
+        assert!(!has_type_prefix(&bytes[..2], hash_type::Dna));
+    }
+
+    #[test]
+    fn test_try_from_bytes_checked() {
+        let hash = DnaHash::try_from_bytes_checked(&prefixed(hash_type::Dna, 36)).unwrap();
+        assert_eq!(hash, DnaHash::from_raw_bytes(vec![0xdb; 36]));
+
+        assert!(matches!(
+            DnaHash::try_from_bytes_checked(&prefixed(hash_type::Dna, 35)),
+            Err(error::HoloHashError::UnexpectedSize {
+                expected: 39,
+                found: 38
+            })
+        ));
+        assert!(matches!(
+            DnaHash::try_from_bytes_checked(&[0xdb; 36]),
+            Err(error::HoloHashError::UnexpectedSize { .. })
+        ));
+        // A DnaHash must not be reinterpreted as a hash of another type
+        assert!(matches!(
+            AgentPubKey::try_from_bytes_checked(&prefixed(hash_type::Dna, 36)),
+            Err(error::HoloHashError::UnexpectedPrefix {
+                expected: "AgentPubKey",
+                ..
+            })
+        ));
+    }
 }
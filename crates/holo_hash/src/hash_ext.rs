@@ -76,6 +76,90 @@ impl<T: HashTypeSync> HoloHash<T> {
     }
 }
 
+/// Batches smaller than this are verified on the calling thread, since
+/// spreading them over threads would cost more than it saves
+const PARALLEL_VERIFY_MIN_BATCH: usize = 32;
+
+impl<T: HashTypeSync + Send + Sync> HoloHash<T> {
+    /// Check each hash is the hash of the content paired with it, as
+    /// [HoloHash::with_data_sync] computes it.
+    /// The results are in the same order as the pairs.
+    /// Large batches are hashed in parallel.
+    pub fn verify_many<C: HashableContent<HashType = T>>(
+        pairs: &[(&HoloHash<T>, &C)],
+    ) -> Vec<bool> {
+        let verify =
+            |(hash, content): &(&HoloHash<T>, &C)| **hash == Self::with_data_sync(*content);
+        if pairs.len() < PARALLEL_VERIFY_MIN_BATCH {
+            pairs.iter().map(verify).collect()
+        } else {
+            use rayon::prelude::*;
+            pairs.par_iter().map(verify).collect()
+        }
+    }
+}
+
+/// Builds a HoloHash from content which arrives in chunks, without holding
+/// all of it in memory at once.
+///
+/// The hash is the same as hashing every chunk joined together, so to match
+/// [HoloHash::with_data_sync] the chunks must join up to the content's
+/// serialized bytes.
+#[derive(Clone)]
+pub struct HoloHashBuilder<T: HashType> {
+    state: blake2b_simd::State,
+    hash_type: T,
+    len: usize,
+}
+
+impl<T: HashType> HoloHashBuilder<T> {
+    /// Start hashing content of the given type
+    pub fn new(hash_type: T) -> Self {
+        Self {
+            state: blake2b_simd::Params::new().hash_length(32).to_state(),
+            hash_type,
+            len: 0,
+        }
+    }
+
+    /// Hash the next chunk of the content
+    pub fn update(&mut self, bytes: &[u8]) -> &mut Self {
+        self.state.update(bytes);
+        self.len += bytes.len();
+        self
+    }
+
+    /// How many bytes have been hashed so far
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no bytes have been hashed yet
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The hash of every chunk given so far
+    pub fn finalize(self) -> HoloHash<T> {
+        HoloHash::with_pre_hashed_typed(self.state.finalize().as_bytes().to_vec(), self.hash_type)
+    }
+}
+
+impl<P: PrimitiveHashType> Default for HoloHashBuilder<P> {
+    fn default() -> Self {
+        Self::new(P::new())
+    }
+}
+
+impl<T: HashType> std::fmt::Debug for HoloHashBuilder<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HoloHashBuilder")
+            .field("hash_type", &self.hash_type)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
 impl<T, C> HoloHashed<C>
 where
     T: HashTypeSync,
@@ -114,3 +198,76 @@ where
         Self { content, hash }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use holochain_serialized_bytes::prelude::*;
+    use std::convert::TryFrom;
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, SerializedBytes)]
+    struct TestEntry(Vec<u8>);
+
+    impl HashableContent for TestEntry {
+        type HashType = hash_type::Entry;
+
+        fn hash_type(&self) -> Self::HashType {
+            hash_type::Entry
+        }
+
+        fn hashable_content(&self) -> HashableContentBytes {
+            HashableContentBytes::Content(SerializedBytes::try_from(self.clone()).unwrap())
+        }
+    }
+
+    #[test]
+    fn verify_many_lines_up_with_the_pairs() {
+        let entries = (0..100u8).map(|i| TestEntry(vec![i])).collect::<Vec<_>>();
+        let mut hashes = entries
+            .iter()
+            .map(EntryHash::with_data_sync)
+            .collect::<Vec<_>>();
+        // Every third hash belongs to the next entry
+        for i in (0..hashes.len() - 1).step_by(3) {
+            hashes[i] = EntryHash::with_data_sync(&entries[i + 1]);
+        }
+        let expected = (0..entries.len())
+            .map(|i| i % 3 != 0 || i == 99)
+            .collect::<Vec<_>>();
+
+        // Both a batch small enough to verify in turn and one verified in parallel
+        for len in &[3, entries.len()] {
+            let pairs = hashes
+                .iter()
+                .zip(entries.iter())
+                .take(*len)
+                .collect::<Vec<_>>();
+            assert_eq!(EntryHash::verify_many(&pairs), &expected[..*len]);
+        }
+        assert!(EntryHash::verify_many::<TestEntry>(&[]).is_empty());
+    }
+
+    #[test]
+    fn hashing_in_chunks_matches_hashing_the_whole() {
+        let entry = TestEntry((0..=255).cycle().take(10_000).collect());
+        let bytes: Vec<u8> =
+            UnsafeBytes::from(SerializedBytes::try_from(entry.clone()).unwrap()).into();
+
+        for chunk_size in &[1, 7, 1024, bytes.len()] {
+            let mut builder = HoloHashBuilder::<hash_type::Entry>::default();
+            for chunk in bytes.chunks(*chunk_size) {
+                builder.update(chunk);
+            }
+            assert_eq!(builder.len(), bytes.len());
+            assert_eq!(builder.finalize(), EntryHash::with_data_sync(&entry));
+        }
+
+        // Nothing hashed is the hash of no bytes
+        let builder = HoloHashBuilder::new(hash_type::Header);
+        assert!(builder.is_empty());
+        assert_eq!(
+            builder.finalize(),
+            HeaderHash::with_pre_hashed(encode::blake2b_256(&[]))
+        );
+    }
+}
@@ -58,6 +58,25 @@ mod tests {
         assert_eq!(*h.hash_type(), hash_type::Agent::new());
     }
 
+    #[test]
+    fn test_rmp_wrong_length_is_error() {
+        #[derive(serde::Serialize)]
+        struct ShortHash {
+            #[serde(with = "serde_bytes")]
+            hash: Vec<u8>,
+            hash_type: hash_type::Agent,
+        }
+        for len in &[0, 4, 35, 37] {
+            let buf = holochain_serialized_bytes::encode(&ShortHash {
+                hash: vec![0xdb; *len],
+                hash_type: hash_type::Agent::new(),
+            })
+            .unwrap();
+            let res: Result<AgentPubKey, _> = holochain_serialized_bytes::decode(&buf);
+            assert!(res.is_err());
+        }
+    }
+
     #[test]
     fn test_composite_hashtype_roundtrips() {
         {
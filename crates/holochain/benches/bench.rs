@@ -2,12 +2,24 @@ use ::fixt::prelude::*;
 use criterion::BenchmarkId;
 use criterion::Throughput;
 use criterion::{criterion_group, criterion_main, Criterion};
+use fallible_iterator::FallibleIterator;
 use hdk3::prelude::*;
 use holo_hash::fixt::AgentPubKeyFixturator;
+use holo_hash::fixt::HeaderHashFixturator;
 use holochain::core::ribosome::RibosomeT;
 use holochain::core::ribosome::ZomeCallInvocation;
+use holochain::core::state::source_chain::SourceChainBuf;
+use holochain_state::buffer::BufferedStore;
+use holochain_state::env::Durability;
+use holochain_state::env::WriteManager;
+use holochain_state::test_utils::test_cell_env;
 use holochain_types::fixt::CapSecretFixturator;
+use holochain_types::test_utils::fake_agent_pubkey_1;
+use holochain_types::Timestamp;
 use holochain_wasm_test_utils::TestWasm;
+use holochain_zome_types::entry_def::EntryVisibility;
+use holochain_zome_types::header::{AppEntryType, Create, EntryType, Header};
+use holochain_zome_types::Entry;
 use holochain_zome_types::ExternInput;
 use once_cell::sync::Lazy;
 use std::sync::Mutex;
@@ -82,6 +94,7 @@ pub fn wasm_call_n(c: &mut Criterion) {
                         fn_name: "echo_bytes".into(),
                         payload: ExternInput::new(sb.clone()),
                         provenance: AGENT_KEY.lock().unwrap().clone(),
+                        durability: Durability::Sync,
                     };
                     WASM_RIBOSOME
                         .lock()
@@ -97,6 +110,204 @@ pub fn wasm_call_n(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, wasm_call_n,);
+pub fn count_incomplete_dht_ops(c: &mut Criterion) {
+    let mut group = c.benchmark_group("count_incomplete_dht_ops");
+
+    for n in vec![10, 100, 1_000] {
+        group.throughput(Throughput::Elements(n as _));
+
+        TOKIO_RUNTIME.lock().unwrap().enter(|| {
+            let test_env = test_cell_env();
+            let env = test_env.env();
+            let author = fake_agent_pubkey_1();
+
+            // A chain of `n` elements, none of which have had their ops produced
+            let mut buf = SourceChainBuf::new(env.clone().into()).unwrap();
+            let mut prev_header = None;
+            for i in 0..n {
+                let header = Header::Create(Create {
+                    author: author.clone(),
+                    timestamp: Timestamp(i as i64, 0).into(),
+                    header_seq: i,
+                    prev_header: prev_header.take().unwrap_or_else(|| fixt!(HeaderHash)),
+                    entry_type: EntryType::AgentPubKey,
+                    entry_hash: author.clone().into(),
+                });
+                let entry = Some(Entry::Agent(author.clone().into()));
+                prev_header = Some(
+                    tokio_safe_block_on::tokio_safe_block_on(
+                        buf.put_raw(header, entry),
+                        std::time::Duration::from_secs(1),
+                    )
+                    .unwrap()
+                    .unwrap(),
+                );
+            }
+            env.guard()
+                .with_commit(|writer| buf.flush_to_txn(writer))
+                .unwrap();
+            let buf = SourceChainBuf::new(env.clone().into()).unwrap();
+
+            group.bench_function(BenchmarkId::new("count", n), |b| {
+                b.iter(|| assert_eq!(buf.count_incomplete_dht_ops().unwrap(), n as usize));
+            });
+            group.bench_function(BenchmarkId::new("get_len", n), |b| {
+                b.iter(|| {
+                    let len = tokio_safe_block_on::tokio_safe_block_on(
+                        buf.get_incomplete_dht_ops(),
+                        std::time::Duration::from_secs(10),
+                    )
+                    .unwrap()
+                    .unwrap()
+                    .len();
+                    assert_eq!(len, n as usize);
+                });
+            });
+        });
+    }
+
+    group.finish();
+}
+
+pub fn get_elements_with_entry_type(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_elements_with_entry_type");
+    let n = 1_000;
+    let types = 10;
+    let app_type = |i: u32| {
+        EntryType::App(AppEntryType::new(
+            ((i % types) as u8).into(),
+            0.into(),
+            EntryVisibility::Public,
+        ))
+    };
+
+    TOKIO_RUNTIME.lock().unwrap().enter(|| {
+        let test_env = test_cell_env();
+        let env = test_env.env();
+        let author = fake_agent_pubkey_1();
+        let entry = Entry::app(SerializedBytes::try_from(()).unwrap()).unwrap();
+        let entry_hash = EntryHash::with_data_sync(&entry);
+
+        // A chain of `n` elements, with 10% of them of each entry type
+        let mut buf = SourceChainBuf::new(env.clone().into()).unwrap();
+        let mut prev_header = None;
+        for i in 0..n {
+            let header = Header::Create(Create {
+                author: author.clone(),
+                timestamp: Timestamp(i as i64, 0).into(),
+                header_seq: i,
+                prev_header: prev_header.take().unwrap_or_else(|| fixt!(HeaderHash)),
+                entry_type: app_type(i),
+                entry_hash: entry_hash.clone(),
+            });
+            prev_header = Some(
+                tokio_safe_block_on::tokio_safe_block_on(
+                    buf.put_raw(header, Some(entry.clone())),
+                    std::time::Duration::from_secs(1),
+                )
+                .unwrap()
+                .unwrap(),
+            );
+        }
+        env.guard()
+            .with_commit(|writer| buf.flush_to_txn(writer))
+            .unwrap();
+        let buf = SourceChainBuf::new(env.clone().into()).unwrap();
+        let entry_type = app_type(0);
+        let k = (n / types) as usize;
+
+        group.throughput(Throughput::Elements(n as _));
+        group.bench_function(BenchmarkId::new("index", n), |b| {
+            b.iter(|| {
+                let elements = buf.get_elements_with_entry_type(&entry_type).unwrap();
+                assert_eq!(elements.len(), k);
+            });
+        });
+        group.bench_function(BenchmarkId::new("scan", n), |b| {
+            b.iter(|| {
+                let elements = buf
+                    .iter_back()
+                    .filter(|h| Ok(h.header().entry_data().map(|(_, t)| t) == Some(&entry_type)))
+                    .map(|h| Ok(buf.get_element(h.header_address())?.unwrap()))
+                    .collect::<Vec<_>>()
+                    .unwrap();
+                assert_eq!(elements.len(), k);
+            });
+        });
+    });
+
+    group.finish();
+}
+
+pub fn iter_back_with_entries(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iter_back_with_entries");
+    let n = 500;
+
+    TOKIO_RUNTIME.lock().unwrap().enter(|| {
+        let test_env = test_cell_env();
+        let env = test_env.env();
+        let author = fake_agent_pubkey_1();
+        let entry = Entry::app(SerializedBytes::try_from(()).unwrap()).unwrap();
+        let entry_hash = EntryHash::with_data_sync(&entry);
+
+        // A chain of `n` elements which all have an entry
+        let mut buf = SourceChainBuf::new(env.clone().into()).unwrap();
+        let mut prev_header = None;
+        for i in 0..n {
+            let header = Header::Create(Create {
+                author: author.clone(),
+                timestamp: Timestamp(i as i64, 0).into(),
+                header_seq: i,
+                prev_header: prev_header.take().unwrap_or_else(|| fixt!(HeaderHash)),
+                entry_type: EntryType::App(AppEntryType::new(
+                    0.into(),
+                    0.into(),
+                    EntryVisibility::Public,
+                )),
+                entry_hash: entry_hash.clone(),
+            });
+            prev_header = Some(
+                tokio_safe_block_on::tokio_safe_block_on(
+                    buf.put_raw(header, Some(entry.clone())),
+                    std::time::Duration::from_secs(1),
+                )
+                .unwrap()
+                .unwrap(),
+            );
+        }
+        env.guard()
+            .with_commit(|writer| buf.flush_to_txn(writer))
+            .unwrap();
+        let buf = SourceChainBuf::new(env.clone().into()).unwrap();
+
+        group.throughput(Throughput::Elements(n as _));
+        group.bench_function(BenchmarkId::new("single_pass", n), |b| {
+            b.iter(|| {
+                let elements = buf.iter_back_with_entries().count().unwrap();
+                assert_eq!(elements, n as usize);
+            });
+        });
+        group.bench_function(BenchmarkId::new("two_pass", n), |b| {
+            b.iter(|| {
+                let elements = buf
+                    .iter_back()
+                    .map(|h| Ok(buf.get_element(h.header_address())?.unwrap()))
+                    .count()
+                    .unwrap();
+                assert_eq!(elements, n as usize);
+            });
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    wasm_call_n,
+    count_incomplete_dht_ops,
+    get_elements_with_entry_type,
+    iter_back_with_entries,
+);
 
 criterion_main!(benches);
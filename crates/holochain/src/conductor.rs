@@ -13,6 +13,8 @@
 // TODO: clean up allows once parent is fully documented
 
 pub mod api;
+#[cfg(any(test, feature = "bench"))]
+pub mod benchmark;
 mod cell;
 #[allow(missing_docs)]
 pub mod compat;
@@ -29,12 +31,20 @@ pub mod handle;
 pub mod interactive;
 pub mod interface;
 pub mod manager;
+pub mod migration;
+pub mod network_info;
 pub mod p2p_store;
 pub mod paths;
 pub mod state;
 
-pub use cell::{error::CellError, Cell};
-pub use conductor::{Conductor, ConductorBuilder, ConductorStateDb};
+pub use cell::{
+    error::CellError, Cell, CountersigningPreflight, CountersigningRole, CountersigningSession,
+    CountersigningState,
+};
+pub use conductor::{
+    CellStatus, Conductor, ConductorBuilder, ConductorMetrics, ConductorStateDb,
+    EnvironmentSyncReport,
+};
 pub use handle::ConductorHandle;
 
 /// setup a tokio runtime that meets the conductor's needs
@@ -4,7 +4,8 @@ use super::error::{ConductorApiError, ConductorApiResult};
 use crate::conductor::{
     entry_def_store::EntryDefBufferKey, interface::SignalBroadcaster, ConductorHandle,
 };
-use crate::core::ribosome::ZomeCallInvocation;
+use crate::core::clock_skew::ClockSkew;
+use crate::core::ribosome::{module_cache::WasmModuleCache, ZomeCallInvocation};
 use crate::core::workflow::ZomeCallInvocationResult;
 use async_trait::async_trait;
 use holo_hash::DnaHash;
@@ -71,6 +72,14 @@ impl CellConductorApiT for CellConductorApi {
         self.conductor_handle.keystore()
     }
 
+    fn wasm_module_cache(&self) -> &WasmModuleCache {
+        self.conductor_handle.wasm_module_cache()
+    }
+
+    fn clock_skew(&self) -> &ClockSkew {
+        self.conductor_handle.clock_skew()
+    }
+
     async fn signal_broadcaster(&self) -> SignalBroadcaster {
         self.conductor_handle.signal_broadcaster().await
     }
@@ -113,6 +122,12 @@ pub trait CellConductorApiT: Clone + Send + Sync + Sized {
     /// Request access to this conductor's keystore
     fn keystore(&self) -> &KeystoreSender;
 
+    /// Request access to the compiled wasm modules shared by this conductor's ribosomes
+    fn wasm_module_cache(&self) -> &WasmModuleCache;
+
+    /// Request access to the conductor's clock skew tolerances and estimates
+    fn clock_skew(&self) -> &ClockSkew;
+
     /// Access the broadcast Sender which will send a Signal across every
     /// attached app interface
     async fn signal_broadcaster(&self) -> SignalBroadcaster;
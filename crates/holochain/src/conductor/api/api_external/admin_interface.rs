@@ -5,16 +5,26 @@ use crate::conductor::api::error::{
 use crate::conductor::{
     config::AdminInterfaceConfig,
     error::CreateAppError,
-    interface::error::{InterfaceError, InterfaceResult},
+    interface::{
+        error::{InterfaceError, InterfaceResult},
+        InterfaceInfo,
+    },
     ConductorHandle,
 };
+use crate::core::gossip_metrics::CellGossipMetrics;
+use crate::core::ribosome::metering::ZomeFnMetrics;
+use crate::core::state::integrity_audit::IntegrityAuditReport;
+use crate::core::state::source_chain::ForkReport;
+use crate::core::state::workflow_errors::WorkflowErrorRecord;
+use crate::core::validation_breaker::ValidationBreakerStatus;
 use holo_hash::*;
 use holochain_keystore::KeystoreSenderExt;
 use holochain_serialized_bytes::prelude::*;
 use holochain_types::{
     app::{AppId, InstallAppDnaPayload, InstallAppPayload, InstalledApp, InstalledCell},
     cell::CellId,
-    dna::{DnaFile, JsonProperties},
+    dna::{DnaFile, DnaFileBundle, JsonProperties},
+    validate::ValidationPackageReport,
 };
 use std::path::PathBuf;
 use tracing::*;
@@ -77,6 +87,7 @@ impl AdminInterfaceApi for RealAdminInterfaceApi {
                     app_id,
                     agent_key,
                     dnas,
+                    allow_multiple_agents,
                 } = *payload;
 
                 // Install Dnas
@@ -104,7 +115,11 @@ impl AdminInterfaceApi for RealAdminInterfaceApi {
                 // Call genesis
                 self.conductor_handle
                     .clone()
-                    .install_app(app_id.clone(), cell_ids_with_proofs.clone())
+                    .install_app(
+                        app_id.clone(),
+                        cell_ids_with_proofs.clone(),
+                        allow_multiple_agents,
+                    )
                     .await?;
 
                 let cell_data = cell_ids_with_proofs
@@ -175,6 +190,75 @@ impl AdminInterfaceApi for RealAdminInterfaceApi {
                 let state = self.conductor_handle.dump_cell_state(&cell_id).await?;
                 Ok(AdminResponse::JsonState(state))
             }
+            AuditCell { cell_id } => {
+                let report = self.conductor_handle.audit_cell(&cell_id).await?;
+                Ok(AdminResponse::CellAudited(report))
+            }
+            GetWorkflowErrors { cell_id } => {
+                let errors = self.conductor_handle.get_workflow_errors(&cell_id).await?;
+                Ok(AdminResponse::WorkflowErrors(errors))
+            }
+            ResolveForkKeep {
+                cell_id,
+                header_hash,
+            } => {
+                let fork = self
+                    .conductor_handle
+                    .clone()
+                    .resolve_fork_keep(&cell_id, &header_hash)
+                    .await?;
+                Ok(AdminResponse::ForkResolved(fork))
+            }
+            ListInterfaces => {
+                let interfaces = self.conductor_handle.list_app_interfaces().await?;
+                Ok(AdminResponse::InterfacesListed(interfaces))
+            }
+            PrecompileDna { dna_hash } => {
+                self.conductor_handle.precompile_dna(&dna_hash).await?;
+                Ok(AdminResponse::DnaPrecompiled)
+            }
+            ExportDna { hash } => {
+                let bundle = self.conductor_handle.export_dna(&hash).await?;
+                Ok(AdminResponse::DnaExported(bundle))
+            }
+            GetZomeCallMetrics => {
+                let metrics = self.conductor_handle.metrics().await?;
+                Ok(AdminResponse::ZomeCallMetrics(metrics.zome_fns))
+            }
+            GetCellGossipMetrics { cell_id } => {
+                let metrics = self
+                    .conductor_handle
+                    .get_cell_gossip_metrics(&cell_id)
+                    .await?;
+                Ok(AdminResponse::CellGossipMetrics(metrics))
+            }
+            ForceValidationCircuit { cell_id, open } => {
+                let status = self
+                    .conductor_handle
+                    .force_validation_circuit(&cell_id, open)
+                    .await?;
+                Ok(AdminResponse::ValidationCircuitForced(status))
+            }
+            BuildValidationPackage {
+                cell_id,
+                header_hash,
+            } => {
+                let report = self
+                    .conductor_handle
+                    .build_validation_package(&cell_id, header_hash)
+                    .await?;
+                Ok(AdminResponse::ValidationPackage(Box::new(report)))
+            }
+            FetchValidationPackageFromAuthor {
+                cell_id,
+                header_hash,
+            } => {
+                let report = self
+                    .conductor_handle
+                    .fetch_validation_package_from_author(&cell_id, header_hash)
+                    .await?;
+                Ok(AdminResponse::ValidationPackage(Box::new(report)))
+            }
         }
     }
 }
@@ -259,6 +343,74 @@ pub enum AdminRequest {
         /// The CellId for which to dump state
         cell_id: Box<CellId>,
     },
+    /// Audit the integrity of a cell's persisted data.
+    /// A cell with corrupt authored or integrated data will be quarantined.
+    AuditCell {
+        /// The CellId to audit
+        cell_id: Box<CellId>,
+    },
+    /// Get the journal of errors returned by a cell's workflows
+    GetWorkflowErrors {
+        /// The CellId for which to get the errors
+        cell_id: Box<CellId>,
+    },
+    /// Resolve a fork in a quarantined cell's source chain by keeping the
+    /// branch which begins with one of the fork's competing headers.
+    /// The cell is started again if its chain is then intact.
+    ResolveForkKeep {
+        /// The CellId whose chain has forked
+        cell_id: Box<CellId>,
+        /// The competing header to keep
+        header_hash: HeaderHash,
+    },
+    /// List the open interfaces, their connected clients
+    /// and the state of each app interface's signal queue
+    ListInterfaces,
+    /// Compile the wasm of an installed Dna ahead of time and store the
+    /// compiled modules, so that its Cells start without compiling
+    PrecompileDna {
+        /// The Dna to compile
+        dna_hash: DnaHash,
+    },
+    /// Export an installed Dna as the content of a dna file,
+    /// which can be installed on another conductor
+    ExportDna {
+        /// The Dna to export
+        hash: DnaHash,
+    },
+    /// Get the resources used by zome calls to the running Cells,
+    /// added up per Dna, zome and function
+    GetZomeCallMetrics,
+    /// Get how many ops a running Cell has published and gossiped with its peers
+    GetCellGossipMetrics {
+        /// The CellId to get the metrics of
+        cell_id: Box<CellId>,
+    },
+    /// Force a running Cell's app validation circuit breaker open, pausing
+    /// app validation until it is forced closed, or force it closed,
+    /// resuming app validation straight away
+    ForceValidationCircuit {
+        /// The CellId whose breaker to force
+        cell_id: Box<CellId>,
+        /// Whether to open the breaker rather than close it
+        open: bool,
+    },
+    /// Build the validation package a cell sends for one of its own headers,
+    /// for debugging authorities which disagree about an op's validity
+    BuildValidationPackage {
+        /// The CellId of the header's author
+        cell_id: Box<CellId>,
+        /// The header to build the package for
+        header_hash: HeaderHash,
+    },
+    /// Fetch the validation package for a header from its author over the
+    /// network, as an authority would, to see exactly what it sends
+    FetchValidationPackageFromAuthor {
+        /// The CellId which holds the header and makes the request
+        cell_id: Box<CellId>,
+        /// The header to fetch the package for
+        header_hash: HeaderHash,
+    },
 }
 
 /// Responses to messages received on an Admin interface
@@ -293,12 +445,33 @@ pub enum AdminResponse {
     AppDeactivated,
     /// State of a cell
     JsonState(String),
+    /// The report of a cell's integrity audit
+    CellAudited(IntegrityAuditReport),
+    /// The journal of errors returned by a cell's workflows
+    WorkflowErrors(Vec<WorkflowErrorRecord>),
+    /// The source chain fork which was resolved
+    ForkResolved(ForkReport),
+    /// The open interfaces, admin interfaces first
+    InterfacesListed(Vec<InterfaceInfo>),
+    /// The Dna's compiled modules have been stored
+    DnaPrecompiled,
+    /// The exported Dna, as the content of a dna file
+    DnaExported(DnaFileBundle),
+    /// The resources used by zome calls, per Dna, zome and function
+    ZomeCallMetrics(Vec<ZomeFnMetrics>),
+    /// How many ops a Cell has published and gossiped with its peers
+    CellGossipMetrics(CellGossipMetrics),
+    /// The state of a Cell's app validation circuit breaker once forced
+    ValidationCircuitForced(ValidationBreakerStatus),
+    /// A validation package and a summary of what went into it
+    ValidationPackage(Box<ValidationPackageReport>),
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::conductor::Conductor;
+    use crate::test_utils::test_conductor::{test_dna_file, TestConductorBatch};
     use anyhow::Result;
     use holochain_state::test_utils::{
         test_conductor_env, test_p2p_env, test_wasm_env, TestEnvironment,
@@ -306,10 +479,14 @@ mod test {
     use holochain_types::{
         app::InstallAppDnaPayload,
         observability,
-        test_utils::{fake_agent_pubkey_1, fake_dna_file, fake_dna_zomes, write_fake_dna_file},
+        test_utils::{
+            fake_agent_pubkey_1, fake_agent_pubkey_2, fake_dna_file, fake_dna_zomes,
+            write_fake_dna_file,
+        },
     };
     use holochain_wasm_test_utils::TestWasm;
     use matches::assert_matches;
+    use test_wasm_common::TestString;
     use uuid::Uuid;
 
     #[tokio::test(threaded_scheduler)]
@@ -348,6 +525,7 @@ mod test {
             dnas: vec![dna_payload],
             app_id: "test".to_string(),
             agent_key,
+            allow_multiple_agents: false,
         };
 
         let install_response = admin_api
@@ -388,6 +566,124 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn install_app_payload_defaults_to_a_single_agent() {
+        /// What a client which predates allow_multiple_agents sends
+        #[derive(serde::Serialize)]
+        struct OldInstallAppPayload {
+            app_id: AppId,
+            agent_key: AgentPubKey,
+            dnas: Vec<InstallAppDnaPayload>,
+        }
+        let old = OldInstallAppPayload {
+            app_id: "test".to_string(),
+            agent_key: fake_agent_pubkey_1(),
+            dnas: Vec::new(),
+        };
+        let bytes = holochain_serialized_bytes::encode(&old).unwrap();
+        let payload: InstallAppPayload = holochain_serialized_bytes::decode(&bytes).unwrap();
+        assert!(!payload.allow_multiple_agents);
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn precompile_dna_stores_modules() -> Result<()> {
+        observability::test_run().ok();
+        let test_env = test_conductor_env();
+        let TestEnvironment {
+            env: wasm_env,
+            tmpdir: _tmpdir,
+        } = test_wasm_env();
+        let TestEnvironment {
+            env: p2p_env,
+            tmpdir: _p2p_tmpdir,
+        } = test_p2p_env();
+        let _tmpdir = test_env.tmpdir.clone();
+        let handle = Conductor::builder()
+            .test(test_env, wasm_env, p2p_env)
+            .await?;
+        let admin_api = RealAdminInterfaceApi::new(handle.clone());
+        let dna = fake_dna_zomes(
+            &Uuid::new_v4().to_string(),
+            vec![(TestWasm::Foo.into(), TestWasm::Foo.into())],
+        );
+        let dna_hash = dna.dna_hash().clone();
+
+        let res = admin_api
+            .handle_admin_request(AdminRequest::PrecompileDna {
+                dna_hash: dna_hash.clone(),
+            })
+            .await;
+        assert_matches!(res, AdminResponse::Error(_));
+
+        handle.install_dna(dna).await?;
+        for _ in 0..2 {
+            let res = admin_api
+                .handle_admin_request(AdminRequest::PrecompileDna {
+                    dna_hash: dna_hash.clone(),
+                })
+                .await;
+            assert_matches!(res, AdminResponse::DnaPrecompiled);
+        }
+        // The second request finds the module already compiled
+        assert_eq!(handle.wasm_module_cache().loads().compiled, 1);
+
+        handle.shutdown().await;
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn exported_dna_installs_on_another_conductor() -> Result<()> {
+        observability::test_run().ok();
+        let conductors = TestConductorBatch::new(2).await;
+        let dna = test_dna_file(vec![TestWasm::Foo]).await;
+        let dna_hash = dna.dna_hash().clone();
+        conductors[0]
+            .setup_app("alice", fake_agent_pubkey_1(), &[dna.clone()])
+            .await;
+
+        let admin_api = RealAdminInterfaceApi::new(conductors[0].handle().clone());
+        let bundle = match admin_api
+            .handle_admin_request(AdminRequest::ExportDna {
+                hash: dna_hash.clone(),
+            })
+            .await
+        {
+            AdminResponse::DnaExported(bundle) => bundle,
+            other => panic!("Expected the exported Dna, got {:?}", other),
+        };
+        assert_eq!(bundle.dna_hash, dna_hash);
+        let exported = bundle.dna_file().await?;
+        assert_eq!(exported, dna);
+        for (wasm_hash, wasm) in exported.code() {
+            assert_eq!(&WasmHash::with_data(wasm).await, wasm_hash);
+        }
+
+        // The bundle is a dna file another conductor can install
+        let tmpdir = tempdir::TempDir::new("exported_dna")?;
+        let dna_path = tmpdir.path().join("exported.dna.gz");
+        tokio::fs::write(&dna_path, &bundle.content).await?;
+        let handle = conductors[1].handle();
+        assert_eq!(handle.install_dna_from_path(dna_path).await?, dna_hash);
+        assert_eq!(handle.list_dnas().await?, vec![dna_hash]);
+
+        let bob = conductors[1]
+            .setup_app("bob", fake_agent_pubkey_2(), &[exported])
+            .await;
+        let response: TestString = bob.cells()[0].call(TestWasm::Foo, "foo", ()).await;
+        assert_eq!(response.0, "foo");
+
+        // A Dna which isn't installed can't be exported
+        let res = admin_api
+            .handle_admin_request(AdminRequest::ExportDna {
+                hash: fake_dna_file("missing").dna_hash().clone(),
+            })
+            .await;
+        assert_matches!(res, AdminResponse::Error(_));
+
+        conductors.shutdown().await;
+        Ok(())
+    }
+
     #[tokio::test(threaded_scheduler)]
     async fn dna_read_parses() -> Result<()> {
         let uuid = Uuid::new_v4();
@@ -5,13 +5,21 @@ use crate::conductor::{
 };
 use crate::conductor::{
     interface::error::{InterfaceError, InterfaceResult},
-    ConductorHandle,
+    network_info::NetworkInfo,
+    ConductorHandle, CountersigningPreflight, CountersigningSession,
 };
 use crate::core::ribosome::ZomeCallInvocation;
+use holo_hash::AgentPubKey;
 use holochain_serialized_bytes::prelude::*;
-use holochain_types::app::{AppId, InstalledApp};
+use holochain_types::{
+    app::{AppId, InstalledApp},
+    cell::CellId,
+};
+use holochain_zome_types::signature::Signature;
+use holochain_zome_types::zome::{FunctionName, ZomeName};
 use holochain_zome_types::ExternOutput;
 use holochain_zome_types::ZomeCallResponse;
+use std::collections::BTreeMap;
 
 /// The interface that a Conductor exposes to the outside world.
 #[async_trait::async_trait]
@@ -64,8 +72,11 @@ impl AppInterfaceApi for RealAppInterfaceApi {
             AppRequest::AppInfo { app_id } => Ok(AppResponse::AppInfo(
                 self.conductor_handle.get_app_info(&app_id).await?,
             )),
-            AppRequest::SignalSubscription(_subscription) => {
-                todo!("Signal pubsub not yet implemented")
+            AppRequest::SignalSubscription(subscription) => {
+                self.conductor_handle
+                    .update_signal_subscription(self.interface_id.clone(), subscription)
+                    .await?;
+                Ok(AppResponse::SignalSubscriptionUpdated)
             }
             AppRequest::ZomeCallInvocation(request) => {
                 match self.conductor_handle.call_zome(*request).await? {
@@ -76,6 +87,36 @@ impl AppInterfaceApi for RealAppInterfaceApi {
                     Err(e) => Ok(AppResponse::Error(e.into())),
                 }
             }
+            AppRequest::NetworkInfo { cells } => Ok(AppResponse::NetworkInfo(
+                self.conductor_handle.network_info(cells).await?,
+            )),
+            AppRequest::ListZomeFunctions { cell_id } => Ok(AppResponse::ZomeFunctionsListed(
+                self.conductor_handle.list_zome_functions(&cell_id).await?,
+            )),
+            AppRequest::CountersigningRequest {
+                cell_id,
+                to_agent,
+                payload,
+            } => Ok(AppResponse::CountersigningRequested(
+                self.conductor_handle
+                    .countersigning_request(&cell_id, to_agent, payload)
+                    .await?,
+            )),
+            AppRequest::CountersigningRespond { cell_id, initiator } => {
+                Ok(AppResponse::CountersigningResponded(
+                    self.conductor_handle
+                        .countersigning_respond(&cell_id, initiator)
+                        .await?,
+                ))
+            }
+            AppRequest::CountersigningSession {
+                cell_id,
+                counterparty,
+            } => Ok(AppResponse::CountersigningSession(
+                self.conductor_handle
+                    .countersigning_session(&cell_id, &counterparty)
+                    .await?,
+            )),
             AppRequest::Crypto(_) => unimplemented!("Crypto methods currently unimplemented"),
         }
     }
@@ -121,6 +162,46 @@ pub enum AppRequest {
 
     /// Update signal subscriptions
     SignalSubscription(SignalSubscription),
+
+    /// Get the gossip progress of some Cells.
+    /// Cheap enough to poll.
+    NetworkInfo {
+        /// The Cells to report on
+        cells: Vec<CellId>,
+    },
+
+    /// List the functions each zome of a Cell can be called with,
+    /// so that clients can discover them
+    ListZomeFunctions {
+        /// The Cell whose zomes to list
+        cell_id: CellId,
+    },
+
+    /// Ask another agent to countersign a payload with a Cell's agent
+    CountersigningRequest {
+        /// The Cell starting the session
+        cell_id: CellId,
+        /// The agent asked to countersign
+        to_agent: AgentPubKey,
+        /// What the agents are agreeing to
+        payload: SerializedBytes,
+    },
+
+    /// Countersign the preflight another agent sent a Cell
+    CountersigningRespond {
+        /// The Cell which received the preflight
+        cell_id: CellId,
+        /// The agent which sent it
+        initiator: AgentPubKey,
+    },
+
+    /// Get a Cell's countersigning session with another agent
+    CountersigningSession {
+        /// The Cell taking part in the session
+        cell_id: CellId,
+        /// The agent on the other side of the session
+        counterparty: AgentPubKey,
+    },
 }
 
 /// Responses to requests received on an App interface
@@ -139,6 +220,22 @@ pub enum AppResponse {
     /// The response to a SignalSubscription message
     SignalSubscriptionUpdated,
 
+    /// The response to a NetworkInfo request, in the same order as the requested Cells
+    NetworkInfo(Vec<NetworkInfo>),
+
+    /// The response to a ListZomeFunctions request, by zome
+    ZomeFunctionsListed(BTreeMap<ZomeName, Vec<FunctionName>>),
+
+    /// The response to a CountersigningRequest, with the preflight sent
+    CountersigningRequested(CountersigningPreflight),
+
+    /// The response to a CountersigningRespond request,
+    /// with the partial signature returned to the initiator
+    CountersigningResponded(Signature),
+
+    /// The response to a CountersigningSession request
+    CountersigningSession(Option<CountersigningSession>),
+
     /// The zome call is unauthorized
     // TODO: I think this should be folded into ExternalApiWireError -MD
     ZomeCallUnauthorized,
@@ -152,3 +249,144 @@ pub enum CryptoRequest {
     Decrypt(String),
     Encrypt(String),
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conductor::{CountersigningRole, CountersigningState};
+    use crate::test_utils::test_conductor::{test_dna_file, TestConductor};
+    use holochain_keystore::AgentPubKeyExt;
+    use holochain_types::test_utils::{fake_agent_pubkey_1, fake_agent_pubkey_2, fake_cell_id};
+    use holochain_wasm_test_utils::TestWasm;
+    use matches::assert_matches;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn list_zome_functions_leaves_out_callbacks() {
+        observability::test_run().ok();
+        let conductor = TestConductor::new().await;
+        let dna = test_dna_file(vec![TestWasm::Validate]).await;
+        let app = conductor
+            .setup_app("app", fake_agent_pubkey_1(), &[dna])
+            .await;
+        let cell_id = app.cells()[0].cell_id().clone();
+        let handle = conductor.handle().clone();
+        let app_api = RealAppInterfaceApi::new(handle.clone(), "test-interface".into());
+
+        // The wasm also exports entry_defs and validate
+        let mut expected = BTreeMap::new();
+        expected.insert(
+            ZomeName::from(TestWasm::Validate),
+            vec![
+                FunctionName::from("always_validates"),
+                FunctionName::from("never_validates"),
+            ],
+        );
+        for _ in 0..2 {
+            let res = app_api
+                .handle_app_request(AppRequest::ListZomeFunctions {
+                    cell_id: cell_id.clone(),
+                })
+                .await;
+            assert_matches!(res, AppResponse::ZomeFunctionsListed(listed) if listed == expected);
+        }
+        // The second request doesn't read the module's exports again
+        assert_eq!(handle.wasm_module_cache().loads().export_scans, 1);
+
+        let res = app_api
+            .handle_app_request(AppRequest::ListZomeFunctions {
+                cell_id: fake_cell_id(2),
+            })
+            .await;
+        assert_matches!(res, AppResponse::Error(_));
+
+        conductor.shutdown().await;
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn agents_countersign_through_the_app_interface() {
+        observability::test_run().ok();
+        let conductor = TestConductor::new().await;
+        let dna = test_dna_file(vec![TestWasm::Foo]).await;
+        let alice = conductor
+            .setup_app("alice", fake_agent_pubkey_1(), &[dna.clone()])
+            .await
+            .cells()[0]
+            .cell_id()
+            .clone();
+        let bob = conductor
+            .setup_app("bob", fake_agent_pubkey_2(), &[dna])
+            .await
+            .cells()[0]
+            .cell_id()
+            .clone();
+        let app_api = RealAppInterfaceApi::new(conductor.handle().clone(), "test-interface".into());
+        let payload: SerializedBytes = UnsafeBytes::from(vec![1, 2, 3]).into();
+
+        // Alice sends Bob a preflight naming them both
+        let preflight = match app_api
+            .handle_app_request(AppRequest::CountersigningRequest {
+                cell_id: alice.clone(),
+                to_agent: bob.agent_pubkey().clone(),
+                payload: payload.clone(),
+            })
+            .await
+        {
+            AppResponse::CountersigningRequested(preflight) => preflight,
+            other => panic!("Expected the preflight sent, got {:?}", other),
+        };
+        assert_eq!(&preflight.dna_hash, alice.dna_hash());
+        assert_eq!(&preflight.initiator, alice.agent_pubkey());
+        assert_eq!(&preflight.responder, bob.agent_pubkey());
+        assert_eq!(preflight.payload, payload);
+
+        // Bob holds the same preflight until he responds
+        let session = |cell_id: &CellId, counterparty: &CellId| {
+            app_api.handle_app_request(AppRequest::CountersigningSession {
+                cell_id: cell_id.clone(),
+                counterparty: counterparty.agent_pubkey().clone(),
+            })
+        };
+        assert_matches!(
+            session(&bob, &alice).await,
+            AppResponse::CountersigningSession(Some(session))
+                if session.role == CountersigningRole::Responder
+                    && session.preflight == preflight
+                    && session.state == CountersigningState::AwaitingSignature
+        );
+
+        let signature = match app_api
+            .handle_app_request(AppRequest::CountersigningRespond {
+                cell_id: bob.clone(),
+                initiator: alice.agent_pubkey().clone(),
+            })
+            .await
+        {
+            AppResponse::CountersigningResponded(signature) => signature,
+            other => panic!("Expected Bob's partial signature, got {:?}", other),
+        };
+        assert!(bob
+            .agent_pubkey()
+            .verify_signature_raw(&signature, &preflight.signing_hash().unwrap())
+            .await
+            .unwrap());
+
+        // Alice has received it, and Bob can't be asked to sign again
+        assert_matches!(
+            session(&alice, &bob).await,
+            AppResponse::CountersigningSession(Some(session))
+                if session.role == CountersigningRole::Initiator
+                    && session.state == CountersigningState::Signed(signature.clone())
+        );
+        assert_matches!(
+            app_api
+                .handle_app_request(AppRequest::CountersigningRespond {
+                    cell_id: bob.clone(),
+                    initiator: alice.agent_pubkey().clone(),
+                })
+                .await,
+            AppResponse::Error(_)
+        );
+
+        conductor.shutdown().await;
+    }
+}
@@ -13,6 +13,26 @@ pub struct SignalSubscription {
     filters: SignalFilterSet,
 }
 
+impl SignalSubscription {
+    /// Constructor
+    pub fn new(app_id: AppId, filters: SignalFilterSet) -> Self {
+        Self { app_id, filters }
+    }
+
+    /// The app for which to manage subscription
+    pub fn app_id(&self) -> &AppId {
+        &self.app_id
+    }
+
+    /// Whether this subscription blocks all of the App's signals
+    pub fn blocks_all(&self) -> bool {
+        match &self.filters {
+            SignalFilterSet::Include(filters) => filters.is_empty(),
+            SignalFilterSet::Exclude(_) => false,
+        }
+    }
+}
+
 /// Associate a SignalFilter with each Cell in an App.
 /// The filtering can be interpreted as inclusive or exclusive,
 /// depending on the use case.
@@ -12,9 +12,11 @@ use crate::{
         workflow::error::WorkflowError,
     },
 };
+use holo_hash::AgentPubKey;
 use holochain_serialized_bytes::prelude::*;
 use holochain_state::error::DatabaseError;
 use holochain_types::cell::CellId;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Errors occurring during a [CellConductorApi] or [InterfaceApi] call
@@ -81,6 +83,23 @@ pub enum ConductorApiError {
 
     #[error(transparent)]
     SourceChainError(#[from] SourceChainError),
+
+    /// A benchmark was asked for too few iterations to time any
+    #[error(
+        "A benchmark needs at least 2 iterations, one to warm up and one to time, but got {0}"
+    )]
+    BenchmarkTooFewIterations(u32),
+
+    /// A remote call got no response in time on any of its attempts
+    #[error("Remote call to agent {to_agent} got no response within {timeout:?} on any of {attempts} attempts")]
+    RemoteCallTimeout {
+        /// The agent which was called
+        to_agent: AgentPubKey,
+        /// How long each attempt waited
+        timeout: Duration,
+        /// How many attempts were made
+        attempts: u32,
+    },
 }
 
 /// All the serialization errors that can occur
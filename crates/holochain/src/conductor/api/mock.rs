@@ -6,7 +6,8 @@ use crate::conductor::{
     api::error::ConductorApiResult, entry_def_store::EntryDefBufferKey,
     interface::SignalBroadcaster,
 };
-use crate::core::ribosome::ZomeCallInvocation;
+use crate::core::clock_skew::ClockSkew;
+use crate::core::ribosome::{module_cache::WasmModuleCache, ZomeCallInvocation};
 use crate::core::workflow::ZomeCallInvocationResult;
 use async_trait::async_trait;
 use holo_hash::DnaHash;
@@ -36,6 +37,8 @@ mock! {
         fn sync_dpki_request(&self, method: String, args: String) -> ConductorApiResult<String>;
 
         fn mock_keystore(&self) -> &KeystoreSender;
+        fn mock_wasm_module_cache(&self) -> &WasmModuleCache;
+        fn mock_clock_skew(&self) -> &ClockSkew;
         fn mock_signal_broadcaster(&self) -> SignalBroadcaster;
         fn sync_get_dna(&self, dna_hash: &DnaHash) -> Option<DnaFile>;
         fn sync_get_this_dna(&self) -> Option<DnaFile>;
@@ -73,6 +76,14 @@ impl CellConductorApiT for MockCellConductorApi {
         self.mock_keystore()
     }
 
+    fn wasm_module_cache(&self) -> &WasmModuleCache {
+        self.mock_wasm_module_cache()
+    }
+
+    fn clock_skew(&self) -> &ClockSkew {
+        self.mock_clock_skew()
+    }
+
     async fn signal_broadcaster(&self) -> SignalBroadcaster {
         self.mock_signal_broadcaster()
     }
@@ -0,0 +1,71 @@
+//! Timing statistics for repeated zome calls, see
+//! [ConductorHandleT::benchmark_zome_call](super::handle::ConductorHandleT::benchmark_zome_call)
+
+use std::time::Duration;
+
+/// Wall-clock statistics over the timed runs of a benchmarked zome call,
+/// in milliseconds
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BenchmarkResult {
+    /// How many runs were timed, not counting the warm-up run
+    pub runs: u32,
+    /// The mean run time
+    pub mean_ms: f64,
+    /// The median run time
+    pub p50_ms: f64,
+    /// The 95th percentile run time
+    pub p95_ms: f64,
+    /// The 99th percentile run time
+    pub p99_ms: f64,
+    /// The slowest run time
+    pub max_ms: f64,
+}
+
+impl BenchmarkResult {
+    /// Compute the statistics of some run times.
+    /// Returns None if there are no run times.
+    pub fn from_timings(mut timings: Vec<Duration>) -> Option<Self> {
+        if timings.is_empty() {
+            return None;
+        }
+        timings.sort();
+        let ms = |d: &Duration| d.as_secs_f64() * 1000.0;
+        // Nearest-rank percentile
+        let percentile = |p: f64| {
+            let rank = (p * timings.len() as f64).ceil() as usize;
+            ms(&timings[rank.max(1) - 1])
+        };
+        Some(Self {
+            runs: timings.len() as u32,
+            mean_ms: timings.iter().map(ms).sum::<f64>() / timings.len() as f64,
+            p50_ms: percentile(0.50),
+            p95_ms: percentile(0.95),
+            p99_ms: percentile(0.99),
+            max_ms: ms(timings.last().expect("timings are not empty")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn benchmark_stats() {
+        assert_eq!(BenchmarkResult::from_timings(vec![]), None);
+
+        // 100ms down to 1ms, out of order
+        let timings = (1..=100).rev().map(Duration::from_millis).collect();
+        let result = BenchmarkResult::from_timings(timings).unwrap();
+        assert_eq!(result.runs, 100);
+        assert!((result.mean_ms - 50.5).abs() < 1e-9);
+        assert!((result.p50_ms - 50.0).abs() < 1e-9);
+        assert!((result.p95_ms - 95.0).abs() < 1e-9);
+        assert!((result.p99_ms - 99.0).abs() < 1e-9);
+        assert!((result.max_ms - 100.0).abs() < 1e-9);
+
+        let result = BenchmarkResult::from_timings(vec![Duration::from_millis(7)]).unwrap();
+        assert!((result.p50_ms - 7.0).abs() < 1e-9);
+        assert!((result.p99_ms - 7.0).abs() < 1e-9);
+    }
+}
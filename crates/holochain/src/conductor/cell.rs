@@ -4,54 +4,76 @@
 //! Elements can be added. A constructed Cell is guaranteed to have a valid
 //! SourceChain which has already undergone Genesis.
 
-use super::{interface::SignalBroadcaster, manager::ManagedTaskAdd};
+use super::{interface::SignalBroadcaster, manager::ManagedTaskAdd, network_info::NetworkInfo};
 use crate::conductor::api::CellConductorApiT;
+use crate::conductor::config::{QueueBackoffConfig, ValidationCircuitBreakerConfig};
 use crate::conductor::handle::ConductorHandle;
 use crate::conductor::{api::error::ConductorApiError, entry_def_store::get_entry_def_from_ids};
 use crate::core::queue_consumer::{spawn_queue_consumer_tasks, InitialQueueTriggers};
 use crate::core::ribosome::ZomeCallInvocation;
-use holochain_zome_types::header::EntryType;
-use holochain_zome_types::query::ChainQueryFilter;
+use holochain_zome_types::header::{EntryType, Header};
 use holochain_zome_types::validate::ValidationPackage;
 use holochain_zome_types::zome::FunctionName;
 use validation_package::ValidationPackageDb;
 
 use crate::{
     conductor::{api::CellConductorApi, cell::error::CellResult},
-    core::ribosome::{guest_callback::init::InitResult, wasm_ribosome::WasmRibosome},
+    core::ribosome::{
+        guest_callback::init::InitResult,
+        metering::{ZomeCallMeter, ZomeCallMetrics, ZomeCallResources},
+        validation_pool::ExecutionContext,
+        wasm_ribosome::WasmRibosome,
+        zome_info_cache::ZomeInfoCache,
+    },
     core::{
+        gossip_metrics::{CellGossipMetrics, GossipMeter},
         state::{
-            dht_op_integration::IntegratedDhtOpsBuf,
+            chain_sequence::backfill_incomplete_dht_ops_index,
+            dht_op_integration::{backfill_location_index, IntegratedDhtOpsBuf},
             element_buf::ElementBuf,
             metadata::{LinkMetaKey, MetadataBuf, MetadataBufT},
-            source_chain::{SourceChain, SourceChainBuf},
+            source_chain::{
+                backfill_entry_type_index, SourceChain, SourceChainBuf, SourceChainError,
+            },
         },
+        validation_breaker::{ValidationBreaker, ValidationBreakerStatus},
         workflow::{
-            call_zome_workflow, error::WorkflowError, genesis_workflow::genesis_workflow,
-            incoming_dht_ops_workflow::incoming_dht_ops_workflow, initialize_zomes_workflow,
+            call_zome_workflow, call_zome_workflow_dry_run,
+            error::WorkflowError,
+            genesis_workflow::genesis_workflow,
+            incoming_dht_ops_workflow::incoming_dht_ops_workflow_with_requirements,
+            initialize_zomes_workflow,
+            validation_receipt_workflow::{
+                receive_validation_receipts, request_validation_receipts,
+            },
             CallZomeWorkflowArgs, CallZomeWorkspace, GenesisWorkflowArgs, GenesisWorkspace,
-            InitializeZomesWorkflowArgs, ZomeCallInvocationResult,
+            InitializeZomesWorkflowArgs, MeteredZomeCallInvocationResult, ZomeCallInvocationResult,
         },
     },
 };
-use error::{AuthorityDataError, CellError};
+use countersigning::CountersigningSessions;
+use error::{AuthorityDataError, CellError, CountersigningError};
 use fallible_iterator::FallibleIterator;
 use futures::future::FutureExt;
 use hash_type::AnyDht;
 use holo_hash::*;
-use holochain_p2p::HolochainP2pCellT;
+use holochain_keystore::AgentPubKeyExt;
+use holochain_p2p::{kitsune_p2p::event::ArcCoverage, HolochainP2pCellT};
 use holochain_serialized_bytes::SerializedBytes;
 use holochain_state::{
+    buffer::BufferedStore,
     db::GetDb,
-    env::{EnvironmentRead, EnvironmentWrite, ReadManager},
+    env::{Durability, EnvironmentRead, EnvironmentWrite, ReadManager, WriteManager},
 };
 use holochain_types::{
     autonomic::AutonomicProcess,
     cell::CellId,
-    element::{GetElementResponse, WireElement},
+    dht_op::OpRequirements,
+    element::GetElementResponse,
+    index::{GetIndexedResponse, IndexKey, IndexOp},
     link::{GetLinksResponse, WireLinkMetaKey},
     metadata::{MetadataSet, TimedHeaderHash},
-    validate::ValidationPackageResponse,
+    validate::{ValidationPackageReport, ValidationPackageResponse},
     Timestamp,
 };
 use holochain_zome_types::capability::CapSecret;
@@ -60,21 +82,28 @@ use holochain_zome_types::signature::Signature;
 use holochain_zome_types::validate::RequiredValidationType;
 use holochain_zome_types::zome::ZomeName;
 use holochain_zome_types::ExternInput;
+use parking_lot::Mutex;
 use std::{
     collections::{BTreeMap, BTreeSet},
-    convert::TryInto,
+    convert::{TryFrom, TryInto},
     hash::{Hash, Hasher},
+    sync::atomic::{AtomicUsize, Ordering},
 };
 use tokio::sync;
 use tracing::*;
 use tracing_futures::Instrument;
 
 mod authority;
+mod countersigning;
 mod validation_package;
 
 #[allow(missing_docs)]
 pub mod error;
 
+pub use countersigning::{
+    CountersigningPreflight, CountersigningRole, CountersigningSession, CountersigningState,
+};
+
 impl Hash for Cell {
     fn hash<H>(&self, state: &mut H)
     where
@@ -112,6 +141,23 @@ where
     env: EnvironmentWrite,
     holochain_p2p_cell: P2pCell,
     queue_triggers: InitialQueueTriggers,
+    /// How many bytes each zome call may commit before it is flushed
+    scratch_size_limit: usize,
+    /// The most bytes the last zome call held before flushing
+    last_call_peak_scratch_size: AtomicUsize,
+    /// The info of each zome, shared by the ribosome of every call
+    zome_info_cache: ZomeInfoCache,
+    /// The resources the last zome call to reach the guest used
+    last_call_resources: Mutex<Option<ZomeCallResources>>,
+    /// The resources used by all zome calls, per zome function
+    zome_call_metrics: ZomeCallMetrics,
+    /// The ops exchanged with peers, shared with the publish workflow
+    gossip_meter: GossipMeter,
+    /// Pauses app validation while validation callbacks keep erroring,
+    /// shared with the app validation workflow
+    validation_breaker: ValidationBreaker,
+    /// The countersigning sessions with other agents, as initiator or responder
+    countersigning: CountersigningSessions,
 }
 
 impl Cell {
@@ -125,23 +171,65 @@ impl Cell {
         mut holochain_p2p_cell: holochain_p2p::HolochainP2pCell,
         managed_task_add_sender: sync::mpsc::Sender<ManagedTaskAdd>,
         managed_task_stop_broadcaster: sync::broadcast::Sender<()>,
+        queue_backoff: QueueBackoffConfig,
+        receipt_flush_interval: std::time::Duration,
+        scratch_size_limit: usize,
+        validation_context: ExecutionContext,
+        validation_circuit_breaker: ValidationCircuitBreakerConfig,
     ) -> CellResult<Self> {
         let conductor_api = CellConductorApi::new(conductor_handle.clone(), id.clone());
 
+        // Build the chain indexes of a chain written before they existed
+        backfill_incomplete_dht_ops_index(&env)?;
+        backfill_entry_type_index(&env)?;
+
+        // Finish any writes to a shared DHT env cut short by a crash
+        env.apply_shared_dht_journal()?;
+
         // check if genesis has been run
-        let has_genesis = {
+        let (has_genesis, fork) = {
             // check if genesis ran on source chain buf
-            SourceChainBuf::new(env.clone().into())?.has_genesis()
+            let source_chain = SourceChainBuf::new(env.clone().into())?;
+            let has_genesis = source_chain.has_genesis();
+            let fork = if has_genesis {
+                source_chain.detect_forks()?
+            } else {
+                None
+            };
+            (has_genesis, fork)
         };
 
+        // A forked chain can't be built on until the fork is resolved
+        if let Some(fork) = fork {
+            return Err(SourceChainError::ChainForked(fork).into());
+        }
+
         if has_genesis {
-            holochain_p2p_cell.join().await?;
+            // If the Dna is missing the infos are built by the first call which finds it
+            let zome_info_cache = match conductor_handle.get_dna(id.dna_hash()).await {
+                Some(dna_file) => ZomeInfoCache::new(&dna_file),
+                None => ZomeInfoCache::default(),
+            };
+            // Cells sharing their dna's dht env hold the same data,
+            // so the space is joined once for all of them
+            if env.dht_env().is_some() {
+                holochain_p2p_cell.join_shared().await?;
+            } else {
+                holochain_p2p_cell.join().await?;
+            }
+            let gossip_meter = GossipMeter::default();
+            let validation_breaker = ValidationBreaker::new(validation_circuit_breaker);
             let queue_triggers = spawn_queue_consumer_tasks(
                 &env,
                 holochain_p2p_cell.clone(),
                 conductor_api.clone(),
                 managed_task_add_sender,
                 managed_task_stop_broadcaster,
+                queue_backoff,
+                receipt_flush_interval,
+                gossip_meter.clone(),
+                validation_context,
+                validation_breaker.clone(),
             )
             .await;
 
@@ -151,6 +239,14 @@ impl Cell {
                 env,
                 holochain_p2p_cell,
                 queue_triggers,
+                scratch_size_limit,
+                last_call_peak_scratch_size: AtomicUsize::new(0),
+                zome_info_cache,
+                last_call_resources: Mutex::new(None),
+                zome_call_metrics: ZomeCallMetrics::default(),
+                gossip_meter,
+                validation_breaker,
+                countersigning: CountersigningSessions::default(),
             })
         } else {
             Err(CellError::CellWithoutGenesis(id))
@@ -166,12 +262,16 @@ impl Cell {
 
     /// Performs the Genesis workflow the Cell, ensuring that its initial
     /// elements are committed. This is a prerequisite for any other interaction
-    /// with the SourceChain
+    /// with the SourceChain.
+    ///
+    /// With `self_check` the genesis elements are validated before they are
+    /// committed, and nothing is written if they are rejected.
     pub async fn genesis(
         id: CellId,
         conductor_handle: ConductorHandle,
         cell_env: EnvironmentWrite,
         membrane_proof: Option<SerializedBytes>,
+        self_check: bool,
     ) -> CellResult<()> {
         // get the dna
         let dna_file = conductor_handle
@@ -179,6 +279,15 @@ impl Cell {
             .await
             .ok_or(CellError::DnaMissing)?;
 
+        let network = if self_check {
+            Some(
+                conductor_handle
+                    .holochain_p2p()
+                    .to_cell(id.dna_hash().clone(), id.agent_pubkey().clone()),
+            )
+        } else {
+            None
+        };
         let conductor_api = CellConductorApi::new(conductor_handle, id.clone());
 
         // run genesis
@@ -186,14 +295,22 @@ impl Cell {
             .await
             .map_err(ConductorApiError::from)
             .map_err(Box::new)?;
-        let args = GenesisWorkflowArgs::new(dna_file, id.agent_pubkey().clone(), membrane_proof);
+        let mut args =
+            GenesisWorkflowArgs::new(dna_file, id.agent_pubkey().clone(), membrane_proof);
+        if let Some(network) = network {
+            args = args.with_self_check(network);
+        }
 
-        genesis_workflow(workspace, cell_env.clone().into(), conductor_api, args)
-            .await
-            .map_err(Box::new)
-            .map_err(ConductorApiError::from)
-            .map_err(Box::new)?;
-        Ok(())
+        match genesis_workflow(workspace, cell_env.clone().into(), conductor_api, args).await {
+            Ok(()) => Ok(()),
+            Err(WorkflowError::GenesisSelfCheckFailed { reason }) => {
+                Err(CellError::GenesisSelfCheckFailed {
+                    cell_id: id,
+                    reason,
+                })
+            }
+            Err(e) => Err(Box::new(ConductorApiError::from(Box::new(e))).into()),
+        }
     }
 
     fn dna_hash(&self) -> &DnaHash {
@@ -227,11 +344,19 @@ impl Cell {
     ) -> CellResult<()> {
         use holochain_p2p::event::HolochainP2pEvent::*;
         match evt {
-            PutAgentInfoSigned { .. } | GetAgentInfoSigned { .. } => {
+            PutAgentInfoSigned { .. } | GetAgentInfoSigned { .. } | RequestArcSync { .. } => {
                 // PutAgentInfoSigned needs to be handled at the conductor level where the p2p
                 // store lives.
                 unreachable!()
             }
+            PutPeerMeta { .. } | GetPeerMeta { .. } | GetSpacePeerMeta { .. } => {
+                // Peer metadata is kept in the p2p store alongside agent info.
+                unreachable!()
+            }
+            PeerConnected { .. } | PeerDisconnected { .. } => {
+                // Peers are reported to interfaces by the conductor.
+                unreachable!()
+            }
             CallRemote {
                 span: _span,
                 from_agent,
@@ -259,11 +384,18 @@ impl Cell {
                 request_validation_receipt,
                 dht_hash,
                 ops,
+                requirements,
                 ..
             } => {
                 async {
                     let res = self
-                        .handle_publish(from_agent, request_validation_receipt, dht_hash, ops)
+                        .handle_publish(
+                            from_agent,
+                            request_validation_receipt,
+                            dht_hash,
+                            ops,
+                            requirements,
+                        )
                         .await
                         .map_err(holochain_p2p::HolochainP2pError::other);
                     respond.respond(Ok(async move { res }.boxed().into()));
@@ -271,6 +403,21 @@ impl Cell {
                 .instrument(debug_span!("cell_handle_publish"))
                 .await;
             }
+            PublishIndex {
+                span: _span,
+                respond,
+                index_op,
+                ..
+            } => {
+                async {
+                    let res = self
+                        .handle_publish_index(index_op)
+                        .map_err(holochain_p2p::HolochainP2pError::other);
+                    respond.respond(Ok(async move { res }.boxed().into()));
+                }
+                .instrument(debug_span!("cell_handle_publish_index"))
+                .await;
+            }
             GetValidationPackage {
                 span: _span,
                 respond,
@@ -304,6 +451,23 @@ impl Cell {
                 .instrument(debug_span!("cell_handle_get"))
                 .await;
             }
+            GetElementByHeader {
+                span: _span,
+                respond,
+                header_hash,
+                options,
+                ..
+            } => {
+                async {
+                    let res = self
+                        .handle_get_element_by_header(header_hash, options)
+                        .await
+                        .map_err(holochain_p2p::HolochainP2pError::other);
+                    respond.respond(Ok(async move { res }.boxed().into()));
+                }
+                .instrument(debug_span!("cell_handle_get_element_by_header"))
+                .await;
+            }
             GetMeta {
                 span: _span,
                 respond,
@@ -337,6 +501,22 @@ impl Cell {
                 .instrument(debug_span!("cell_handle_get_links"))
                 .await;
             }
+            GetIndexed {
+                span: _span,
+                respond,
+                index_key,
+                options,
+                ..
+            } => {
+                async {
+                    let res = self
+                        .handle_get_indexed(index_key, options)
+                        .map_err(holochain_p2p::HolochainP2pError::other);
+                    respond.respond(Ok(async move { res }.boxed().into()));
+                }
+                .instrument(debug_span!("cell_handle_get_indexed"))
+                .await;
+            }
             ValidationReceiptReceived {
                 span: _span,
                 respond,
@@ -353,6 +533,39 @@ impl Cell {
                 .instrument(debug_span!("cell_handle_validation_receipt_received"))
                 .await;
             }
+            CountersigningRequest {
+                span: _span,
+                respond,
+                from_agent,
+                preflight,
+                ..
+            } => {
+                async {
+                    let res = self
+                        .handle_countersigning_request(from_agent, preflight)
+                        .map_err(holochain_p2p::HolochainP2pError::other);
+                    respond.respond(Ok(async move { res }.boxed().into()));
+                }
+                .instrument(debug_span!("cell_handle_countersigning_request"))
+                .await;
+            }
+            CountersigningResponse {
+                span: _span,
+                respond,
+                from_agent,
+                partial_signature,
+                ..
+            } => {
+                async {
+                    let res = self
+                        .handle_countersigning_response(from_agent, partial_signature)
+                        .await
+                        .map_err(holochain_p2p::HolochainP2pError::other);
+                    respond.respond(Ok(async move { res }.boxed().into()));
+                }
+                .instrument(debug_span!("cell_handle_countersigning_response"))
+                .await;
+            }
             FetchOpHashesForConstraints {
                 span: _span,
                 respond,
@@ -364,6 +577,7 @@ impl Cell {
                 async {
                     let res = self
                         .handle_fetch_op_hashes_for_constraints(dht_arc, since, until)
+                        .await
                         .map_err(holochain_p2p::HolochainP2pError::other);
                     respond.respond(Ok(async move { res }.boxed().into()));
                 }
@@ -405,20 +619,37 @@ impl Cell {
         Ok(())
     }
 
-    #[instrument(skip(self, _request_validation_receipt, _dht_hash, ops))]
+    #[instrument(skip(self, request_validation_receipt, _dht_hash, ops))]
     /// we are receiving a "publish" event from the network
     async fn handle_publish(
         &self,
-        _from_agent: AgentPubKey,
-        _request_validation_receipt: bool,
+        from_agent: AgentPubKey,
+        request_validation_receipt: bool,
         _dht_hash: holo_hash::AnyDhtHash,
         ops: Vec<(holo_hash::DhtOpHash, holochain_types::dht_op::DhtOp)>,
+        requirements: Vec<(holo_hash::DhtOpHash, OpRequirements)>,
     ) -> CellResult<()> {
-        incoming_dht_ops_workflow(&self.env, self.queue_triggers.sys_validation.clone(), ops)
-            .await
-            .map_err(Box::new)
-            .map_err(ConductorApiError::from)
-            .map_err(Box::new)?;
+        self.gossip_meter
+            .record_received(&from_agent, ops.iter().map(|(_, op)| op));
+        if request_validation_receipt {
+            // The receipts are sent together once the ops are integrated.
+            // They are requested first so validation can annotate them.
+            let op_hashes = ops.iter().map(|(op_hash, _)| op_hash.clone());
+            request_validation_receipts(&self.env, from_agent, op_hashes)
+                .map_err(Box::new)
+                .map_err(ConductorApiError::from)
+                .map_err(Box::new)?;
+        }
+        incoming_dht_ops_workflow_with_requirements(
+            &self.env,
+            self.queue_triggers.sys_validation.clone(),
+            ops,
+            requirements,
+        )
+        .await
+        .map_err(Box::new)
+        .map_err(ConductorApiError::from)
+        .map_err(Box::new)?;
         Ok(())
     }
 
@@ -430,26 +661,95 @@ impl Cell {
         let env: EnvironmentRead = self.env.clone().into();
 
         // Get the header
-        let databases = ValidationPackageDb::create(env.clone())?;
-        let mut cascade = databases.cascade();
-        let header = match cascade
-            .retrieve_header(header_hash, Default::default())
-            .await?
-        {
-            Some(shh) => shh.into_header_and_signature().0.into_content(),
+        let header = match self.get_validation_package_header(header_hash).await? {
+            Some(header) => header,
             None => return Ok(None.into()),
         };
 
         // This agent is the author so get the validation package from the source chain
         if header.author() == self.id.agent_pubkey() {
             let ribosome = self.get_ribosome().await?;
-            validation_package::get_as_author(header, env, &ribosome.dna_file, &self.conductor_api)
-                .await
+            // Only public entries are sent over the network
+            let source_chain = SourceChain::public_only(env)?;
+            validation_package::get_as_author(
+                header,
+                &source_chain,
+                &ribosome.dna_file,
+                &self.conductor_api,
+            )
+            .await
         } else {
             todo!("Implement authority returning validation package")
         }
     }
 
+    /// Get a header this Cell holds, to build or fetch its validation package
+    async fn get_validation_package_header(
+        &self,
+        header_hash: HeaderHash,
+    ) -> CellResult<Option<Header>> {
+        let databases = ValidationPackageDb::create(self.env.clone().into())?;
+        let mut cascade = databases.cascade();
+        Ok(cascade
+            .retrieve_header(header_hash, Default::default())
+            .await?
+            .map(|shh| shh.into_header_and_signature().0.into_content()))
+    }
+
+    /// Build the validation package for one of this Cell's own headers,
+    /// as the author would when asked over the network, but keeping the
+    /// private entries which only the author can see
+    pub(crate) async fn build_validation_package(
+        &self,
+        header_hash: HeaderHash,
+    ) -> CellResult<ValidationPackageReport> {
+        let header = self
+            .get_validation_package_header(header_hash.clone())
+            .await?
+            .ok_or_else(|| AuthorityDataError::missing_data(&header_hash))?;
+        if header.author() != self.id.agent_pubkey() {
+            return Err(CellError::NotAuthor(header_hash));
+        }
+        let ribosome = self.get_ribosome().await?;
+        let required = validation_package::required_validation_type(
+            &header,
+            &ribosome.dna_file,
+            &self.conductor_api,
+        )
+        .await?
+        .map(|(_, _, required)| required);
+        let source_chain = SourceChain::new(self.env.clone().into())?;
+        let package = validation_package::get_as_author(
+            header,
+            &source_chain,
+            &ribosome.dna_file,
+            &self.conductor_api,
+        )
+        .await?;
+        Ok(ValidationPackageReport::new(package.0, required, true))
+    }
+
+    /// The author of a header this Cell holds, who an authority asks for
+    /// its validation package, and the validation type its entry def requires
+    pub(crate) async fn validation_package_author(
+        &self,
+        header_hash: HeaderHash,
+    ) -> CellResult<(AgentPubKey, Option<RequiredValidationType>)> {
+        let header = self
+            .get_validation_package_header(header_hash.clone())
+            .await?
+            .ok_or_else(|| AuthorityDataError::missing_data(&header_hash))?;
+        let ribosome = self.get_ribosome().await?;
+        let required = validation_package::required_validation_type(
+            &header,
+            &ribosome.dna_file,
+            &self.conductor_api,
+        )
+        .await?
+        .map(|(_, _, required)| required);
+        Ok((header.author().clone(), required))
+    }
+
     #[instrument(skip(self, options))]
     /// a remote node is asking us for entry data
     async fn handle_get(
@@ -461,6 +761,15 @@ impl Cell {
         // we can just have these defaults depending on whether or not
         // the hash is an entry or header.
         // In the future we should use GetOptions to choose which get to run.
+        let storage_arc = self
+            .holochain_p2p_cell
+            .clone()
+            .network_info()
+            .await?
+            .storage_arc;
+        if !storage_arc.contains(dht_hash.get_loc()) {
+            return Ok(GetElementResponse::NotHeld);
+        }
         let r = match *dht_hash.hash_type() {
             AnyDht::Entry => self.handle_get_entry(dht_hash.into(), options).await,
             AnyDht::Header => self.handle_get_element(dht_hash.into()).await,
@@ -482,52 +791,31 @@ impl Cell {
     }
 
     async fn handle_get_element(&self, hash: HeaderHash) -> CellResult<GetElementResponse> {
-        // Get the vaults
-        let env_ref = self.env.guard();
-        let reader = env_ref.reader()?;
-        let element_vault = ElementBuf::vault(self.env.clone().into(), false)?;
-        let meta_vault = MetadataBuf::vault(self.env.clone().into())?;
-
-        // Check that we have the authority to serve this request because we have
-        // done the StoreElement validation
-        if !meta_vault.has_registered_store_element(&hash)? {
-            return Ok(GetElementResponse::GetHeader(None));
-        }
-
-        // Look for a delete on the header and collect it
-        let deleted = meta_vault
-            .get_deletes_on_header(&reader, hash.clone())?
-            .next()?;
-        let deleted = match deleted {
-            Some(delete_header) => {
-                let delete = delete_header.header_hash;
-                match element_vault.get_header(&delete)? {
-                    Some(delete) => Some(delete.try_into().map_err(AuthorityDataError::from)?),
-                    None => {
-                        return Err(AuthorityDataError::missing_data(delete));
-                    }
-                }
-            }
-            None => None,
-        };
-
-        // Get the actual header and return it with proof of deleted if there is any
-        let r = element_vault
-            .get_element(&hash)?
-            .map(|e| WireElement::from_element(e, deleted))
-            .map(Box::new);
+        let env = self.env.clone();
+        authority::handle_get_element(env, hash).await
+    }
 
-        Ok(GetElementResponse::GetHeader(r))
+    #[instrument(skip(self, options))]
+    /// a remote node is asking us for the element a header hash refers to
+    async fn handle_get_element_by_header(
+        &self,
+        header_hash: HeaderHash,
+        options: holochain_p2p::event::GetOptions,
+    ) -> CellResult<GetElementResponse> {
+        let env = self.env.clone();
+        let network = self.holochain_p2p_cell.clone();
+        authority::handle_get_element_by_header(env, network, header_hash, options).await
     }
 
-    #[instrument(skip(self, _dht_hash, _options))]
+    #[instrument(skip(self, options))]
     /// a remote node is asking us for metadata
     async fn handle_get_meta(
         &self,
-        _dht_hash: holo_hash::AnyDhtHash,
-        _options: holochain_p2p::event::GetMetaOptions,
+        dht_hash: holo_hash::AnyDhtHash,
+        options: holochain_p2p::event::GetMetaOptions,
     ) -> CellResult<MetadataSet> {
-        unimplemented!()
+        let env = self.env.clone();
+        authority::handle_get_meta(env, dht_hash, options).await
     }
 
     #[instrument(skip(self, _options))]
@@ -541,10 +829,10 @@ impl Cell {
         _options: holochain_p2p::event::GetLinksOptions,
     ) -> CellResult<GetLinksResponse> {
         // Get the vaults
-        let env_ref = self.env.guard();
-        let reader = env_ref.reader()?;
         let element_vault = ElementBuf::vault(self.env.clone().into(), false)?;
         let meta_vault = MetadataBuf::vault(self.env.clone().into())?;
+        let env_ref = meta_vault.env().guard();
+        let reader = env_ref.reader()?;
         debug!(id = ?self.id());
 
         let links = meta_vault
@@ -588,34 +876,144 @@ impl Cell {
             }
         }
 
-        // Return the links
-        Ok(GetLinksResponse {
+        // Return the links in their canonical order
+        let mut response = GetLinksResponse {
             link_adds: result_adds,
             link_removes: result_removes,
+        };
+        response.sort();
+        Ok(response)
+    }
+
+    #[instrument(skip(self, _options))]
+    /// we are an authority being asked for the entries we hold under an index key
+    fn handle_get_indexed(
+        &self,
+        index_key: IndexKey,
+        _options: holochain_p2p::event::GetIndexedOptions,
+    ) -> CellResult<GetIndexedResponse> {
+        let meta_vault = MetadataBuf::vault(self.env.clone().into())?;
+        let env_ref = meta_vault.env().guard();
+        let reader = env_ref.reader()?;
+        let entry_hashes = meta_vault
+            .get_indexed(&reader, &index_key)?
+            .collect::<BTreeSet<_>>()?;
+        Ok(GetIndexedResponse {
+            entry_hashes: entry_hashes.into_iter().collect(),
         })
     }
 
-    /// a remote agent is sending us a validation receipt.
-    async fn handle_validation_receipt(&self, _receipt: SerializedBytes) -> CellResult<()> {
-        unimplemented!()
+    #[instrument(skip(self, index_op))]
+    /// we are an authority for an index key, receiving a change to its index.
+    /// The rows only say where to look, the entries they point to
+    /// are fetched from their own authorities.
+    fn handle_publish_index(&self, index_op: IndexOp) -> CellResult<()> {
+        let mut meta_vault = MetadataBuf::vault(self.env.clone().into())?;
+        meta_vault.register_index_op(&index_op)?;
+        self.env
+            .guard()
+            .with_commit(|writer| meta_vault.flush_to_txn(writer))?;
+        Ok(())
+    }
+
+    /// a remote agent is sending us a validation receipt,
+    /// or a bundle of receipts for several of our ops.
+    async fn handle_validation_receipt(&self, receipt: SerializedBytes) -> CellResult<()> {
+        receive_validation_receipts(&self.env, receipt)
+            .await
+            .map_err(Box::new)
+            .map_err(ConductorApiError::from)
+            .map_err(Box::new)?;
+        Ok(())
+    }
+
+    #[instrument(skip(self, preflight))]
+    /// an initiator is asking us to countersign a preflight,
+    /// which we hold until we are asked to respond.
+    /// The preflight must be a session between the initiator and us.
+    fn handle_countersigning_request(
+        &self,
+        from_agent: AgentPubKey,
+        preflight: SerializedBytes,
+    ) -> CellResult<SerializedBytes> {
+        let preflight = CountersigningPreflight::try_from(preflight)
+            .map_err(|e| CountersigningError::MalformedPreflight(from_agent.clone(), e))?;
+        if preflight.initiator != from_agent
+            || &preflight.responder != self.id.agent_pubkey()
+            || &preflight.dna_hash != self.id.dna_hash()
+        {
+            return Err(CountersigningError::WrongPreflight(from_agent).into());
+        }
+        self.countersigning
+            .start(CountersigningRole::Responder, from_agent, preflight)?;
+        Ok(SerializedBytes::try_from(())?)
+    }
+
+    #[instrument(skip(self, partial_signature))]
+    /// the agent we sent a preflight to has returned its partial signature
+    async fn handle_countersigning_response(
+        &self,
+        from_agent: AgentPubKey,
+        partial_signature: Signature,
+    ) -> CellResult<()> {
+        let preflight = self
+            .countersigning
+            .awaiting_signature(CountersigningRole::Initiator, &from_agent)?;
+        if !from_agent
+            .verify_signature_raw(&partial_signature, &preflight.signing_hash()?)
+            .await?
+        {
+            return Err(CountersigningError::InvalidSignature(from_agent).into());
+        }
+        self.countersigning.signed(
+            CountersigningRole::Initiator,
+            &from_agent,
+            partial_signature,
+        )
     }
 
     #[instrument(skip(self, dht_arc, since, until))]
-    /// the network module is requesting a list of dht op hashes
-    fn handle_fetch_op_hashes_for_constraints(
+    /// the network module is requesting a list of dht op hashes,
+    /// which we can only answer for an arc we store some of
+    async fn handle_fetch_op_hashes_for_constraints(
         &self,
         dht_arc: holochain_p2p::dht_arc::DhtArc,
         since: Timestamp,
         until: Timestamp,
-    ) -> CellResult<Vec<DhtOpHash>> {
+    ) -> CellResult<ArcCoverage<Vec<DhtOpHash>>> {
+        trace!(dht_arc = %dht_arc.coverage_summary(), ?since, ?until);
+        let storage_arc = self
+            .holochain_p2p_cell
+            .clone()
+            .network_info()
+            .await?
+            .storage_arc;
+        if !storage_arc.overlaps(&dht_arc) {
+            trace!(storage_arc = %storage_arc.coverage_summary(), "arc not covered");
+            return Ok(ArcCoverage::NotCovered);
+        }
+        backfill_location_index(&self.env)?;
         let env_ref = self.env.guard();
         let reader = env_ref.reader()?;
         let integrated_dht_ops = IntegratedDhtOpsBuf::new(self.env().clone().into())?;
         let result: Vec<DhtOpHash> = integrated_dht_ops
-            .query(&reader, Some(since), Some(until), Some(dht_arc))?
-            .map(|(k, _)| Ok(k))
+            .ops_in_arc(&reader, dht_arc, Some(since), Some(until))?
             .collect()?;
-        Ok(result)
+        self.gossip_meter.record_round();
+        Ok(ArcCoverage::Covered(result))
+    }
+
+    /// Report how far this cell has got syncing with the rest of its network
+    pub(super) async fn network_info(&self) -> CellResult<NetworkInfo> {
+        let info = self.holochain_p2p_cell.clone().network_info().await?;
+        backfill_location_index(&self.env)?;
+        let env_ref = self.env.guard();
+        let reader = env_ref.reader()?;
+        let integrated_dht_ops = IntegratedDhtOpsBuf::new(self.env().clone().into())?;
+        let integrated_op_count = integrated_dht_ops
+            .ops_in_arc(&reader, info.storage_arc, None, None)?
+            .count()? as u64;
+        Ok(NetworkInfo::new(self.id.clone(), info, integrated_op_count))
     }
 
     #[instrument(skip(self, op_hashes))]
@@ -644,6 +1042,8 @@ impl Cell {
                 out.push((basis, op_hash, full_op));
             }
         }
+        self.gossip_meter
+            .record_sent(out.iter().map(|(_, _, op)| op));
         Ok(out)
     }
 
@@ -678,6 +1078,7 @@ impl Cell {
             payload: ExternInput::new(payload),
             provenance: from_agent,
             fn_name,
+            durability: Durability::Sync,
         };
         // double ? because
         // - ConductorApiResult
@@ -686,20 +1087,33 @@ impl Cell {
     }
 
     /// Function called by the Conductor
-    #[instrument(skip(self, invocation))]
     pub async fn call_zome(
         &self,
         invocation: ZomeCallInvocation,
     ) -> CellResult<ZomeCallInvocationResult> {
+        Ok(self.call_zome_metered(invocation).await?.result)
+    }
+
+    /// Call a zome function and return the resources the guest used
+    /// along with the result
+    #[instrument(skip(self, invocation))]
+    pub async fn call_zome_metered(
+        &self,
+        invocation: ZomeCallInvocation,
+    ) -> CellResult<MeteredZomeCallInvocationResult> {
         // Check if init has run if not run it
         self.check_or_run_zome_init().await?;
 
         let arc = self.env();
         let keystore = arc.keystore().clone();
-        let workspace = CallZomeWorkspace::new(arc.clone().into())?;
+        let workspace = CallZomeWorkspace::new(arc.clone().into())?
+            .with_scratch_size_limit(self.scratch_size_limit);
         let conductor_api = self.conductor_api.clone();
         let signal_tx = self.signal_broadcaster().await;
-        let ribosome = self.get_ribosome().await?;
+        let meter = ZomeCallMeter::default();
+        let ribosome = self.get_ribosome().await?.with_meter(meter.clone());
+        let zome_name = invocation.zome_name.clone();
+        let fn_name = invocation.fn_name.clone();
 
         let args = CallZomeWorkflowArgs {
             ribosome,
@@ -707,7 +1121,7 @@ impl Cell {
             conductor_api,
             signal_tx,
         };
-        Ok(call_zome_workflow(
+        let result = call_zome_workflow(
             workspace,
             self.holochain_p2p_cell.clone(),
             keystore,
@@ -715,8 +1129,155 @@ impl Cell {
             args,
             self.queue_triggers.produce_dht_ops.clone(),
         )
-        .await
-        .map_err(Box::new)?)
+        .await;
+
+        // A call which reached the guest is measured even if it then failed
+        let resources = meter.take();
+        if let Some(resources) = &resources {
+            self.zome_call_metrics
+                .record(self.dna_hash(), &zome_name, &fn_name, resources);
+            *self.last_call_resources.lock() = Some(*resources);
+        }
+
+        let (result, peak_scratch_size) = result.map_err(Box::new)?;
+        self.last_call_peak_scratch_size
+            .store(peak_scratch_size, Ordering::Relaxed);
+        Ok(MeteredZomeCallInvocationResult { result, resources })
+    }
+
+    /// The most bytes the last successful zome call to this Cell held
+    /// in its workspace before flushing
+    pub(crate) fn last_call_peak_scratch_size(&self) -> usize {
+        self.last_call_peak_scratch_size.load(Ordering::Relaxed)
+    }
+
+    /// The resources the last zome call to this Cell which reached the guest used
+    pub(crate) fn last_call_resources(&self) -> Option<ZomeCallResources> {
+        *self.last_call_resources.lock()
+    }
+
+    /// The resources used by all zome calls to this Cell, per zome function
+    pub(crate) fn zome_call_metrics(&self) -> &ZomeCallMetrics {
+        &self.zome_call_metrics
+    }
+
+    /// The ops this Cell has exchanged with its peers since it was started
+    pub(crate) fn gossip_metrics(&self) -> CellGossipMetrics {
+        self.gossip_meter.snapshot()
+    }
+
+    /// The state of this Cell's app validation circuit breaker
+    pub(crate) fn validation_breaker_status(&self) -> ValidationBreakerStatus {
+        self.validation_breaker.snapshot(std::time::Instant::now())
+    }
+
+    /// Force this Cell's app validation circuit breaker open, pausing app
+    /// validation until it is forced closed, or force it closed,
+    /// resuming app validation straight away
+    pub(crate) fn force_validation_circuit(&self, open: bool) -> ValidationBreakerStatus {
+        if open {
+            self.validation_breaker.force_open();
+        } else {
+            self.validation_breaker.force_close();
+        }
+        self.validation_breaker_status()
+    }
+
+    /// Send another agent a preflight of the payload to countersign, as the
+    /// initiator of a session with them. The session waits for their partial
+    /// signature, and is dropped if the preflight can't be delivered.
+    pub async fn countersigning_request(
+        &self,
+        to_agent: AgentPubKey,
+        payload: SerializedBytes,
+    ) -> CellResult<CountersigningPreflight> {
+        let preflight = CountersigningPreflight::new(
+            self.id.dna_hash().clone(),
+            self.id.agent_pubkey().clone(),
+            to_agent.clone(),
+            payload,
+        );
+        let request = SerializedBytes::try_from(preflight.clone())?;
+        self.countersigning.start(
+            CountersigningRole::Initiator,
+            to_agent.clone(),
+            preflight.clone(),
+        )?;
+        let response = self
+            .holochain_p2p_cell
+            .clone()
+            .countersigning_request(to_agent.clone(), request)
+            .await;
+        if response.is_err() {
+            self.countersigning.abandon(&to_agent);
+        }
+        response?;
+        Ok(preflight)
+    }
+
+    /// Sign the preflight an initiator sent us and return the partial
+    /// signature to them, completing our side of the session
+    pub async fn countersigning_respond(&self, initiator: AgentPubKey) -> CellResult<Signature> {
+        let preflight = self
+            .countersigning
+            .awaiting_signature(CountersigningRole::Responder, &initiator)?;
+        let partial_signature = self
+            .id
+            .agent_pubkey()
+            .sign_raw(self.env.keystore(), &preflight.signing_hash()?)
+            .await?;
+        self.holochain_p2p_cell
+            .clone()
+            .countersigning_response(initiator.clone(), partial_signature.clone())
+            .await?;
+        self.countersigning.signed(
+            CountersigningRole::Responder,
+            &initiator,
+            partial_signature.clone(),
+        )?;
+        Ok(partial_signature)
+    }
+
+    /// The countersigning session with another agent, if there is one
+    pub fn countersigning_session(
+        &self,
+        counterparty: &AgentPubKey,
+    ) -> Option<CountersigningSession> {
+        self.countersigning.get(counterparty)
+    }
+
+    /// Run a zome call without committing anything to the source chain.
+    /// Returns the result along with the headers which would have been written.
+    ///
+    /// NB: zome initialization is still run (and committed) if it has not run yet,
+    /// since no zome function can be called before init.
+    #[instrument(skip(self, invocation))]
+    pub async fn call_zome_dry_run(
+        &self,
+        invocation: ZomeCallInvocation,
+    ) -> CellResult<(ZomeCallInvocationResult, Vec<Header>)> {
+        // Check if init has run if not run it
+        self.check_or_run_zome_init().await?;
+
+        let arc = self.env();
+        let keystore = arc.keystore().clone();
+        let workspace = CallZomeWorkspace::new(arc.clone().into())?
+            .with_scratch_size_limit(self.scratch_size_limit);
+        let conductor_api = self.conductor_api.clone();
+        let signal_tx = self.signal_broadcaster().await;
+        let ribosome = self.get_ribosome().await?;
+
+        let args = CallZomeWorkflowArgs {
+            ribosome,
+            invocation,
+            conductor_api,
+            signal_tx,
+        };
+        Ok(
+            call_zome_workflow_dry_run(workspace, self.holochain_p2p_cell.clone(), keystore, args)
+                .await
+                .map_err(Box::new)?,
+        )
     }
 
     /// Check if each Zome's init callback has been run, and if not, run it.
@@ -745,7 +1306,9 @@ impl Cell {
         let dna_def = dna_file.dna().clone();
 
         // Get the ribosome
-        let ribosome = WasmRibosome::new(dna_file);
+        let ribosome = WasmRibosome::new(dna_file)
+            .with_module_cache(conductor_api.wasm_module_cache().clone())
+            .with_zome_info_cache(self.zome_info_cache.clone());
 
         // Run the workflow
         let args = InitializeZomesWorkflowArgs { dna_def, ribosome };
@@ -783,7 +1346,9 @@ impl Cell {
     // TODO: reevaluate once Workflows are fully implemented (after B-01567)
     pub(crate) async fn get_ribosome(&self) -> CellResult<WasmRibosome> {
         match self.conductor_api.get_dna(self.dna_hash()).await {
-            Some(dna) => Ok(WasmRibosome::new(dna)),
+            Some(dna) => Ok(WasmRibosome::new(dna)
+                .with_module_cache(self.conductor_api.wasm_module_cache().clone())
+                .with_zome_info_cache(self.zome_info_cache.clone())),
             None => Err(CellError::DnaMissing),
         }
     }
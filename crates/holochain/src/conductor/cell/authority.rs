@@ -1,18 +1,27 @@
 use super::error::{AuthorityDataError, CellResult};
 use crate::core::state::{
+    cascade::Cascade,
     element_buf::ElementBuf,
-    metadata::{MetadataBuf, MetadataBufT},
+    metadata::{ChainItemKey, LinkMetaKey, MetadataBuf, MetadataBufT},
 };
 use fallible_iterator::FallibleIterator;
 
-use holo_hash::EntryHash;
-use holochain_state::{env::EnvironmentWrite, fresh_reader};
+use holo_hash::{hash_type::AnyDht, AgentPubKey, AnyDhtHash, EntryHash, HeaderHash};
+use holochain_p2p::{actor, HolochainP2pCellT};
+use holochain_state::{
+    env::{EnvironmentWrite, ReadManager},
+    fresh_reader,
+};
 use holochain_types::{
-    element::{GetElementResponse, RawGetEntryResponse},
+    element::{GetElementResponse, RawGetEntryResponse, WireElement},
     header::WireUpdateRelationship,
-    metadata::TimedHeaderHash,
+    metadata::{MetadataSet, TimedHeaderHash},
+};
+use holochain_zome_types::{
+    element::{Element, SignedHeaderHashed},
+    entry_def::EntryVisibility,
+    header::conversions::WrongHeaderError,
 };
-use holochain_zome_types::{element::SignedHeaderHashed, header::conversions::WrongHeaderError};
 use std::{collections::BTreeSet, convert::TryInto};
 use tracing::*;
 
@@ -24,7 +33,7 @@ pub async fn handle_get_entry(
 ) -> CellResult<GetElementResponse> {
     // Get the vaults
     let element_vault = ElementBuf::vault(state_env.clone().into(), false)?;
-    let meta_vault = MetadataBuf::vault(state_env.clone().into())?;
+    let meta_vault = MetadataBuf::vault(state_env.into())?;
 
     // ## Helper closures to DRY and make more readable
 
@@ -58,8 +67,17 @@ pub async fn handle_get_entry(
         CellResult::Ok(entry_data)
     };
 
+    // ### Public header closure
+    // An entry may be committed both publicly and privately,
+    // so visibility is decided per header
+    let is_public = |header: &SignedHeaderHashed| {
+        header.header().entry_type().map_or(false, |et| {
+            matches!(et.visibility(), EntryVisibility::Public)
+        })
+    };
+
     // ### Gather headers closure
-    // This gathers the headers and deletes we want
+    // This gathers the public headers and their deletes we want
     let gather_headers = |reader| {
         let mut deletes = Vec::new();
         let mut updates = Vec::new();
@@ -71,12 +89,15 @@ pub async fn handle_get_entry(
         // We want all the live headers and deletes
         if options.all_live_headers_with_metadata {
             for hash in headers {
+                let header = render_header(hash)?;
+                if !is_public(&header) {
+                    continue;
+                }
                 deletes.extend(
                     meta_vault
-                        .get_deletes_on_header(&reader, hash.header_hash.clone())?
+                        .get_deletes_on_header(&reader, header.header_address().clone())?
                         .iterator(),
                 );
-                let header = render_header(hash)?;
                 live_headers.insert(header.try_into()?);
             }
             let updates_returns = meta_vault
@@ -93,9 +114,14 @@ pub async fn handle_get_entry(
         // We only want the headers if they are live and all deletes
         } else {
             for hash in headers {
+                let header = render_header(hash)?;
+                if !is_public(&header) {
+                    continue;
+                }
+
                 // Check for a delete
                 let is_deleted = meta_vault
-                    .get_deletes_on_header(&reader, hash.header_hash.clone())?
+                    .get_deletes_on_header(&reader, header.header_address().clone())?
                     .next()?
                     .is_some();
 
@@ -103,13 +129,12 @@ pub async fn handle_get_entry(
                 if is_deleted {
                     deletes.extend(
                         meta_vault
-                            .get_deletes_on_header(&reader, hash.header_hash.clone())?
+                            .get_deletes_on_header(&reader, header.header_address().clone())?
                             .iterator(),
                     );
 
                 // Otherwise gather the header
                 } else {
-                    let header = render_header(hash)?;
                     live_headers.insert(header.try_into()?);
                 }
             }
@@ -126,16 +151,26 @@ pub async fn handle_get_entry(
     // ## Gather the entry and header data to return
 
     // ### Gather the entry
-    // Get the entry from the first header
-
-    fresh_reader!(state_env, |reader| {
-        let first_header = meta_vault.get_headers(&reader, hash.clone())?.next()?;
-        let entry_data = match first_header {
-            Some(first_header) => {
-                let header = render_header(first_header)?;
-                Some(get_entry(header)?)
+    // Get the entry from the first public header.
+    // Never serve a private entry, even if one was stored by mistake
+
+    fresh_reader!(meta_vault.env(), |reader| {
+        let entry_data = {
+            let mut headers = meta_vault.get_headers(&reader, hash.clone())?;
+            let mut entry_data = None;
+            let mut private_only = false;
+            while let Some(timed_header_hash) = headers.next()? {
+                let header = render_header(timed_header_hash)?;
+                if is_public(&header) {
+                    entry_data = Some(get_entry(header)?);
+                    break;
+                }
+                private_only = true;
+            }
+            if entry_data.is_none() && private_only {
+                warn!(entry_hash = ?hash, "Refusing to serve a private entry");
             }
-            None => None,
+            entry_data
         };
 
         let r = match entry_data {
@@ -158,3 +193,186 @@ pub async fn handle_get_entry(
         Ok(GetElementResponse::GetEntryFull(r))
     })
 }
+
+/// Get an element we are an authority for, with proof of its deletion if
+/// there is one. A private entry is never served with its header.
+#[instrument(skip(state_env))]
+pub async fn handle_get_element(
+    state_env: EnvironmentWrite,
+    hash: HeaderHash,
+) -> CellResult<GetElementResponse> {
+    // Get the vaults
+    let element_vault = ElementBuf::vault(state_env.clone().into(), false)?;
+    let meta_vault = MetadataBuf::vault(state_env.into())?;
+    let env_ref = meta_vault.env().guard();
+    let reader = env_ref.reader()?;
+
+    // Check that we have the authority to serve this request because we have
+    // done the StoreElement validation
+    if !meta_vault.has_registered_store_element(&hash)? {
+        return Ok(GetElementResponse::GetHeader(None));
+    }
+
+    // Look for a delete on the header and collect it
+    let deleted = meta_vault
+        .get_deletes_on_header(&reader, hash.clone())?
+        .next()?;
+    let deleted = match deleted {
+        Some(delete_header) => {
+            let delete = delete_header.header_hash;
+            match element_vault.get_header(&delete)? {
+                Some(delete) => Some(delete.try_into().map_err(AuthorityDataError::from)?),
+                None => {
+                    return Err(AuthorityDataError::missing_data(delete));
+                }
+            }
+        }
+        None => None,
+    };
+
+    // Get the actual header and return it with proof of deleted if there is any
+    let r = element_vault
+        .get_element(&hash)?
+        .map(hide_private_entry)
+        .map(|e| WireElement::from_element(e, deleted))
+        .map(Box::new);
+
+    Ok(GetElementResponse::GetHeader(r))
+}
+
+/// Get the element a header hash refers to, looking in this cell's own
+/// stores before falling through to the DHT via `network`.
+#[instrument(skip(state_env, network, options))]
+pub async fn handle_get_element_by_header<N: HolochainP2pCellT>(
+    state_env: EnvironmentWrite,
+    network: N,
+    header_hash: HeaderHash,
+    options: holochain_p2p::event::GetOptions,
+) -> CellResult<GetElementResponse> {
+    let element_authored = ElementBuf::authored(state_env.clone().into(), false)?;
+    let meta_authored = MetadataBuf::authored(state_env.clone().into())?;
+    let element_vault = ElementBuf::vault(state_env.clone().into(), false)?;
+    let meta_vault = MetadataBuf::vault(state_env.clone().into())?;
+    // Anything fetched from the network only lives in this buffer
+    // for the duration of the request
+    let mut element_cache = ElementBuf::cache(state_env.clone().into())?;
+    let mut meta_cache = MetadataBuf::cache(state_env.into())?;
+    let mut cascade = Cascade::new(
+        &element_authored,
+        &meta_authored,
+        &element_vault,
+        &meta_vault,
+        &mut element_cache,
+        &mut meta_cache,
+        network,
+    );
+
+    let options = actor::GetOptions {
+        follow_redirects: options.follow_redirects,
+        all_live_headers_with_metadata: options.all_live_headers_with_metadata,
+        ..Default::default()
+    };
+    let r = cascade
+        .retrieve(header_hash.into(), options)
+        .await?
+        .map(hide_private_entry)
+        .map(|e| WireElement::from_element(e, None))
+        .map(Box::new);
+
+    Ok(GetElementResponse::GetHeader(r))
+}
+
+/// Drop the entry of an element whose entry is private, so that it is
+/// received as hidden. Private entries should never reach an authority's
+/// stores, but if one does it must still not be served.
+fn hide_private_entry(element: Element) -> Element {
+    let is_private = matches!(
+        element.header().entry_data().map(|(_, et)| et.visibility()),
+        Some(EntryVisibility::Private)
+    );
+    if !is_private || element.entry().as_option().is_none() {
+        return element;
+    }
+    let (signed_header, _) = element.into_inner();
+    warn!(
+        header_hash = ?signed_header.header_address(),
+        "Refusing to serve a private entry"
+    );
+    Element::new(signed_header, None)
+}
+
+/// Gather the metadata on a basis, only building the sections
+/// the caller asked for in the options.
+#[instrument(skip(state_env))]
+pub async fn handle_get_meta(
+    state_env: EnvironmentWrite,
+    dht_hash: AnyDhtHash,
+    options: holochain_p2p::event::GetMetaOptions,
+) -> CellResult<MetadataSet> {
+    let meta_vault = MetadataBuf::vault(state_env.into())?;
+    let entry_hash = match *dht_hash.hash_type() {
+        AnyDht::Entry => Some(EntryHash::from(dht_hash.clone())),
+        AnyDht::Header => None,
+    };
+
+    fresh_reader!(meta_vault.env(), |r| {
+        let mut set = MetadataSet::default();
+        if options.include_headers {
+            set.headers = Some(match &entry_hash {
+                Some(entry_hash) => meta_vault.get_headers(&r, entry_hash.clone())?.collect()?,
+                None => BTreeSet::new(),
+            });
+        }
+        if options.include_deletes {
+            set.deletes = Some(match &entry_hash {
+                Some(entry_hash) => meta_vault
+                    .get_deletes_on_entry(&r, entry_hash.clone())?
+                    .collect()?,
+                None => meta_vault
+                    .get_deletes_on_header(&r, HeaderHash::from(dht_hash.clone()))?
+                    .collect()?,
+            });
+        }
+        if options.include_updates {
+            set.updates = Some(meta_vault.get_updates(&r, dht_hash.clone())?.collect()?);
+        }
+        if options.include_links {
+            let mut links = BTreeSet::new();
+            let mut link_removes = BTreeSet::new();
+            if let Some(entry_hash) = &entry_hash {
+                let link_adds = meta_vault
+                    .get_links_all(&r, &LinkMetaKey::Base(entry_hash))?
+                    .collect::<Vec<_>>()?;
+                for link_add in link_adds {
+                    let removes = meta_vault
+                        .get_link_removes_on_link_add(&r, link_add.link_add_hash.clone())?
+                        .collect::<Vec<_>>()?;
+                    link_removes.extend(removes);
+                    links.insert(TimedHeaderHash {
+                        timestamp: link_add.timestamp,
+                        header_hash: link_add.link_add_hash,
+                    });
+                }
+            }
+            set.links = Some(links);
+            set.link_removes = Some(link_removes);
+        }
+        if options.include_activity {
+            set.activity = Some(match &entry_hash {
+                Some(entry_hash) => meta_vault
+                    .get_activity(
+                        &r,
+                        ChainItemKey::Agent(AgentPubKey::from(entry_hash.clone())),
+                    )?
+                    .collect()?,
+                None => BTreeSet::new(),
+            });
+        }
+        if let Some(entry_hash) = &entry_hash {
+            if options.include_headers || options.include_deletes {
+                set.entry_dht_status = Some(meta_vault.get_dht_status(&r, entry_hash)?);
+            }
+        }
+        CellResult::Ok(set)
+    })
+}
@@ -0,0 +1,373 @@
+//! The countersigning sessions a Cell is taking part in.
+//!
+//! The initiator of a session sends a [CountersigningPreflight] to its
+//! counterparty and records that it is waiting for the counterparty's
+//! partial signature. The counterparty records the preflight as the
+//! responder, but only if it names the initiator as the sender, the
+//! responder's own agent and the responder's Dna. Once the responder
+//! has signed the preflight's [CountersigningPreflight::signing_hash]
+//! it returns the partial signature to the initiator, which checks it
+//! against the same hash.
+//! Each Cell has at most one unsigned session with any one agent.
+//!
+//! Preflights arrive from remote agents, so a Cell holds a limited number
+//! of sessions. A session is forgotten once it has gone unchanged for
+//! [SESSION_TIMEOUT], and when the Cell is full a new session replaces
+//! the longest-signed one. If every session is unsigned and recent, the
+//! new one is refused.
+
+use super::error::{CellResult, CountersigningError};
+use holo_hash::{encode::blake2b_256, AgentPubKey, DnaHash};
+use holochain_serialized_bytes::prelude::*;
+use holochain_zome_types::signature::Signature;
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// The most countersigning sessions a Cell holds at once
+pub const MAX_SESSIONS: usize = 64;
+
+/// How long a session is kept after it was started or signed
+pub const SESSION_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Prefixed to a preflight before it is hashed for signing, so that a
+/// partial signature can't be passed off as a signature of anything else
+const PREFLIGHT_SIGNING_DOMAIN: &[u8] = b"holochain:countersigning:preflight:v1\0";
+
+/// What both agents of a countersigning session sign
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, SerializedBytes)]
+pub struct CountersigningPreflight {
+    /// The Dna of both agents' Cells
+    pub dna_hash: DnaHash,
+    /// The agent which sent the preflight
+    pub initiator: AgentPubKey,
+    /// The agent asked to countersign
+    pub responder: AgentPubKey,
+    /// Chosen at random by the initiator,
+    /// so that no two sessions sign the same bytes
+    pub session_id: u64,
+    /// What the agents are agreeing to, which the conductor doesn't interpret
+    pub payload: SerializedBytes,
+}
+
+impl CountersigningPreflight {
+    /// A preflight for a new session
+    pub fn new(
+        dna_hash: DnaHash,
+        initiator: AgentPubKey,
+        responder: AgentPubKey,
+        payload: SerializedBytes,
+    ) -> Self {
+        Self {
+            dna_hash,
+            initiator,
+            responder,
+            session_id: rand::random(),
+            payload,
+        }
+    }
+
+    /// What each agent's partial signature signs
+    pub fn signing_hash(&self) -> CellResult<Vec<u8>> {
+        let preflight = SerializedBytes::try_from(self.clone())?;
+        let mut data = PREFLIGHT_SIGNING_DOMAIN.to_vec();
+        data.extend_from_slice(preflight.bytes());
+        Ok(blake2b_256(&data))
+    }
+}
+
+/// Which side of a countersigning session a Cell is on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CountersigningRole {
+    /// The Cell sent the preflight
+    Initiator,
+    /// The Cell received the preflight
+    Responder,
+}
+
+/// How far a countersigning session has got
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CountersigningState {
+    /// The preflight has not been signed by the responder yet
+    AwaitingSignature,
+    /// The responder's partial signature of the preflight,
+    /// sent by the responder or received by the initiator
+    Signed(Signature),
+}
+
+/// A countersigning session with another agent
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CountersigningSession {
+    /// Which side of the session this Cell is on
+    pub role: CountersigningRole,
+    /// The agent on the other side of the session
+    pub counterparty: AgentPubKey,
+    /// The preflight both agents sign
+    pub preflight: CountersigningPreflight,
+    /// How far the session has got
+    pub state: CountersigningState,
+}
+
+/// The sessions of a Cell, by counterparty,
+/// with when each was started or signed
+pub(super) struct CountersigningSessions {
+    sessions: Mutex<HashMap<AgentPubKey, (Instant, CountersigningSession)>>,
+    max_sessions: usize,
+    timeout: Duration,
+}
+
+impl Default for CountersigningSessions {
+    fn default() -> Self {
+        Self::with_limits(MAX_SESSIONS, SESSION_TIMEOUT)
+    }
+}
+
+impl CountersigningSessions {
+    /// Hold at most `max_sessions`, each for `timeout` after it last changed
+    pub fn with_limits(max_sessions: usize, timeout: Duration) -> Self {
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+            max_sessions,
+            timeout,
+        }
+    }
+
+    /// Record a new session, unless there is an unsigned one with the counterparty
+    /// or there is no room for it
+    pub fn start(
+        &self,
+        role: CountersigningRole,
+        counterparty: AgentPubKey,
+        preflight: CountersigningPreflight,
+    ) -> CellResult<()> {
+        let mut sessions = self.sessions.lock();
+        let now = Instant::now();
+        let timeout = self.timeout;
+        sessions.retain(|_, (changed, _)| now.duration_since(*changed) < timeout);
+        match sessions.get(&counterparty) {
+            Some((
+                _,
+                CountersigningSession {
+                    state: CountersigningState::AwaitingSignature,
+                    ..
+                },
+            )) => return Err(CountersigningError::SessionInProgress(counterparty).into()),
+            // The new session replaces the signed one
+            Some(_) => (),
+            None if sessions.len() < self.max_sessions => (),
+            None => {
+                let longest_signed = sessions
+                    .iter()
+                    .filter(|(_, (_, session))| {
+                        session.state != CountersigningState::AwaitingSignature
+                    })
+                    .min_by_key(|(_, (changed, _))| *changed)
+                    .map(|(agent, _)| agent.clone());
+                match longest_signed {
+                    Some(agent) => {
+                        sessions.remove(&agent);
+                    }
+                    None => return Err(CountersigningError::TooManySessions(counterparty).into()),
+                }
+            }
+        }
+        sessions.insert(
+            counterparty.clone(),
+            (
+                now,
+                CountersigningSession {
+                    role,
+                    counterparty,
+                    preflight,
+                    state: CountersigningState::AwaitingSignature,
+                },
+            ),
+        );
+        Ok(())
+    }
+
+    /// The preflight of the unsigned session on this side with the counterparty
+    pub fn awaiting_signature(
+        &self,
+        role: CountersigningRole,
+        counterparty: &AgentPubKey,
+    ) -> CellResult<CountersigningPreflight> {
+        match self.sessions.lock().get(counterparty) {
+            Some((_, session))
+                if session.role == role
+                    && session.state == CountersigningState::AwaitingSignature =>
+            {
+                Ok(session.preflight.clone())
+            }
+            _ => Err(CountersigningError::NoSession(counterparty.clone()).into()),
+        }
+    }
+
+    /// Record the partial signature of the unsigned session with the counterparty
+    pub fn signed(
+        &self,
+        role: CountersigningRole,
+        counterparty: &AgentPubKey,
+        partial_signature: Signature,
+    ) -> CellResult<()> {
+        match self.sessions.lock().get_mut(counterparty) {
+            Some((changed, session))
+                if session.role == role
+                    && session.state == CountersigningState::AwaitingSignature =>
+            {
+                *changed = Instant::now();
+                session.state = CountersigningState::Signed(partial_signature);
+                Ok(())
+            }
+            _ => Err(CountersigningError::NoSession(counterparty.clone()).into()),
+        }
+    }
+
+    /// Forget the session with the counterparty
+    pub fn abandon(&self, counterparty: &AgentPubKey) {
+        self.sessions.lock().remove(counterparty);
+    }
+
+    /// The session with the counterparty, if there is one
+    pub fn get(&self, counterparty: &AgentPubKey) -> Option<CountersigningSession> {
+        self.sessions
+            .lock()
+            .get(counterparty)
+            .map(|(_, session)| session.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conductor::cell::error::CellError;
+    use ::fixt::prelude::*;
+    use holo_hash::fixt::AgentPubKeyFixturator;
+    use holochain_serialized_bytes::UnsafeBytes;
+    use holochain_types::test_utils::{fake_agent_pubkey_1, fake_agent_pubkey_2, fake_dna_hash};
+    use matches::assert_matches;
+
+    fn preflight() -> CountersigningPreflight {
+        CountersigningPreflight {
+            dna_hash: fake_dna_hash(1),
+            initiator: fake_agent_pubkey_1(),
+            responder: fake_agent_pubkey_2(),
+            session_id: 0,
+            payload: UnsafeBytes::from(vec![1, 2, 3]).into(),
+        }
+    }
+
+    #[test]
+    fn sessions_are_signed_once_per_counterparty() {
+        let sessions = CountersigningSessions::default();
+        let (alice, bob) = (fixt!(AgentPubKey), fixt!(AgentPubKey));
+        let signature = Signature(vec![7; 64]);
+
+        sessions
+            .start(CountersigningRole::Initiator, alice.clone(), preflight())
+            .unwrap();
+        sessions
+            .start(CountersigningRole::Responder, bob.clone(), preflight())
+            .unwrap();
+
+        // Only one unsigned session with each agent
+        assert_matches!(
+            sessions.start(CountersigningRole::Responder, alice.clone(), preflight()),
+            Err(CellError::CountersigningError(
+                CountersigningError::SessionInProgress(_)
+            ))
+        );
+
+        // Signatures only arrive on the initiator's side
+        assert_matches!(
+            sessions.signed(CountersigningRole::Initiator, &bob, signature.clone()),
+            Err(CellError::CountersigningError(
+                CountersigningError::NoSession(_)
+            ))
+        );
+        assert_eq!(
+            sessions
+                .awaiting_signature(CountersigningRole::Initiator, &alice)
+                .unwrap(),
+            preflight()
+        );
+        sessions
+            .signed(CountersigningRole::Initiator, &alice, signature.clone())
+            .unwrap();
+        assert_eq!(
+            sessions.get(&alice).unwrap().state,
+            CountersigningState::Signed(signature.clone())
+        );
+
+        // A signed session can't be signed again, but a new one can start
+        assert_matches!(
+            sessions.awaiting_signature(CountersigningRole::Initiator, &alice),
+            Err(CellError::CountersigningError(
+                CountersigningError::NoSession(_)
+            ))
+        );
+        sessions
+            .start(CountersigningRole::Responder, alice.clone(), preflight())
+            .unwrap();
+
+        sessions.abandon(&bob);
+        assert_eq!(sessions.get(&bob), None);
+    }
+
+    #[test]
+    fn sessions_are_limited() {
+        let sessions = CountersigningSessions::with_limits(2, SESSION_TIMEOUT);
+        let (alice, bob, carol) = (fixt!(AgentPubKey), fixt!(AgentPubKey), fixt!(AgentPubKey));
+
+        sessions
+            .start(CountersigningRole::Responder, alice.clone(), preflight())
+            .unwrap();
+        sessions
+            .start(CountersigningRole::Responder, bob.clone(), preflight())
+            .unwrap();
+
+        // No room while every session is unsigned
+        assert_matches!(
+            sessions.start(CountersigningRole::Responder, carol.clone(), preflight()),
+            Err(CellError::CountersigningError(
+                CountersigningError::TooManySessions(_)
+            ))
+        );
+
+        // A signed session makes way for a new one
+        sessions
+            .signed(
+                CountersigningRole::Responder,
+                &alice,
+                Signature(vec![7; 64]),
+            )
+            .unwrap();
+        sessions
+            .start(CountersigningRole::Responder, carol.clone(), preflight())
+            .unwrap();
+        assert_eq!(sessions.get(&alice), None);
+        assert!(sessions.get(&bob).is_some());
+        assert!(sessions.get(&carol).is_some());
+    }
+
+    #[test]
+    fn sessions_expire() {
+        let sessions = CountersigningSessions::with_limits(MAX_SESSIONS, Duration::from_millis(0));
+        let (alice, bob) = (fixt!(AgentPubKey), fixt!(AgentPubKey));
+
+        sessions
+            .start(CountersigningRole::Responder, alice.clone(), preflight())
+            .unwrap();
+        // Starting another session forgets the expired one
+        sessions
+            .start(CountersigningRole::Responder, bob.clone(), preflight())
+            .unwrap();
+        assert_eq!(sessions.get(&alice), None);
+        // Even an unsigned session with the same agent can be started again
+        sessions
+            .start(CountersigningRole::Responder, bob.clone(), preflight())
+            .unwrap();
+    }
+}
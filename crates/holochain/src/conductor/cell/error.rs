@@ -10,7 +10,9 @@ use crate::{
         SourceChainError,
     },
 };
+use holo_hash::{AgentPubKey, HeaderHash};
 use holochain_p2p::HolochainP2pError;
+use holochain_serialized_bytes::SerializedBytesError;
 use holochain_state::error::DatabaseError;
 use holochain_types::{cell::CellId, header::error::HeaderError};
 use holochain_zome_types::header::conversions::WrongHeaderError;
@@ -29,6 +31,10 @@ pub enum CellError {
     JoinError(#[from] tokio::task::JoinError),
     #[error("Genesis failed: {0}")]
     Genesis(#[from] Box<ConductorApiError>),
+    #[error(
+        "The genesis elements of cell {cell_id:?} were rejected by its own validation: {reason}"
+    )]
+    GenesisSelfCheckFailed { cell_id: CellId, reason: String },
     #[error(transparent)]
     HeaderError(#[from] HeaderError),
     #[error("This cell has not had a successful genesis and cannot be created")]
@@ -55,12 +61,34 @@ pub enum CellError {
     DhtOpConvertError(#[from] DhtOpConvertError),
     #[error("Cell is an authority for is missing or incorrect: {0}")]
     AuthorityDataError(#[from] AuthorityDataError),
+    #[error("This cell's agent is not the author of the header {0:?}")]
+    NotAuthor(HeaderHash),
+    #[error(transparent)]
+    CountersigningError(#[from] CountersigningError),
+    #[error(transparent)]
+    KeystoreError(#[from] holochain_keystore::KeystoreError),
     #[error("Todo")]
     Todo,
 }
 
 pub type CellResult<T> = Result<T, CellError>;
 
+#[derive(Error, Debug)]
+pub enum CountersigningError {
+    #[error("A countersigning session with {0:?} has not been signed yet")]
+    SessionInProgress(AgentPubKey),
+    #[error("There is no countersigning session with {0:?} waiting for this signature")]
+    NoSession(AgentPubKey),
+    #[error("The partial signature from {0:?} does not sign the preflight")]
+    InvalidSignature(AgentPubKey),
+    #[error("There is no room for a countersigning session with {0:?}")]
+    TooManySessions(AgentPubKey),
+    #[error("The preflight from {0:?} can't be read: {1}")]
+    MalformedPreflight(AgentPubKey, SerializedBytesError),
+    #[error("The preflight from {0:?} is not a session between it and this Cell")]
+    WrongPreflight(AgentPubKey),
+}
+
 #[derive(Error, Debug)]
 pub enum AuthorityDataError {
     #[error(transparent)]
@@ -1,19 +1,52 @@
+use super::{
+    error::{CellError, CountersigningError},
+    CountersigningPreflight, CountersigningRole, CountersigningState,
+};
 use crate::{
     conductor::manager::spawn_task_manager,
-    core::workflow::incoming_dht_ops_workflow::IncomingDhtOpsWorkspace,
-    fixt::{DnaFileFixturator, SignatureFixturator},
+    core::{
+        state::{
+            element_buf::ElementBuf,
+            metadata::{MetadataBuf, MetadataBufT},
+        },
+        workflow::{
+            call_zome_workflow::DEFAULT_SCRATCH_SIZE_LIMIT,
+            incoming_dht_ops_workflow::IncomingDhtOpsWorkspace,
+            validation_receipt_workflow::DEFAULT_RECEIPT_FLUSH_INTERVAL,
+        },
+    },
+    fixt::{
+        AppEntryBytesFixturator, CreateFixturator, CreateLinkFixturator, DeleteFixturator,
+        DnaFileFixturator, EntryHashFixturator, SignatureFixturator,
+    },
 };
 use ::fixt::prelude::*;
-use holo_hash::HasHash;
-use holochain_p2p::actor::HolochainP2pRefToCell;
-use holochain_state::test_utils::{test_cell_env, TestEnvironment};
+use holo_hash::{
+    fixt::AgentPubKeyFixturator, AgentPubKey, AnyDhtHash, DnaHash, HasHash, HeaderHash,
+};
+use holochain_keystore::AgentPubKeyExt;
+use holochain_p2p::{
+    actor::{GetMetaOptions, GetOptions, HolochainP2pRefToCell},
+    MockHolochainP2pCellT,
+};
+use holochain_serialized_bytes::{SerializedBytes, UnsafeBytes};
+use holochain_state::{
+    prelude::*,
+    test_utils::{test_cell_env, test_dht_env, TestEnvironment},
+};
 use holochain_types::{
     dht_op::{DhtOp, DhtOpHashed},
+    element::{Element, ElementEntry, GetElementResponse, SignedHeaderHashed, WireElement},
+    header::NewEntryHeader,
     test_utils::{fake_agent_pubkey_2, fake_cell_id},
-    HeaderHashed, Timestamp,
+    Entry, EntryHashed, HeaderHashed, Timestamp,
+};
+use holochain_zome_types::{
+    entry_def::EntryVisibility,
+    header::{self, AppEntryType, EntryType},
 };
-use holochain_zome_types::header;
-use std::sync::Arc;
+use matches::assert_matches;
+use std::{convert::TryFrom, sync::Arc};
 use tokio::sync;
 
 #[tokio::test(threaded_scheduler)]
@@ -22,7 +55,9 @@ async fn test_cell_handle_publish() {
         env,
         tmpdir: _tmpdir,
     } = test_cell_env();
-    let (holochain_p2p, _p2p_evt) = holochain_p2p::spawn_holochain_p2p().await.unwrap();
+    let (holochain_p2p, _p2p_evt) = holochain_p2p::spawn_holochain_p2p(Default::default())
+        .await
+        .unwrap();
     let cell_id = fake_cell_id(1);
     let dna = cell_id.dna_hash().clone();
     let agent = cell_id.agent_pubkey().clone();
@@ -36,9 +71,15 @@ async fn test_cell_handle_publish() {
 
     let mock_handler: crate::conductor::handle::ConductorHandle = Arc::new(mock_handler);
 
-    super::Cell::genesis(cell_id.clone(), mock_handler.clone(), env.clone(), None)
-        .await
-        .unwrap();
+    super::Cell::genesis(
+        cell_id.clone(),
+        mock_handler.clone(),
+        env.clone(),
+        None,
+        false,
+    )
+    .await
+    .unwrap();
 
     let (add_task_sender, shutdown) = spawn_task_manager();
     let (stop_tx, _) = sync::broadcast::channel(1);
@@ -50,6 +91,11 @@ async fn test_cell_handle_publish() {
         holochain_p2p_cell,
         add_task_sender,
         stop_tx.clone(),
+        Default::default(),
+        DEFAULT_RECEIPT_FLUSH_INTERVAL,
+        DEFAULT_SCRATCH_SIZE_LIMIT,
+        Default::default(),
+        Default::default(),
     )
     .await
     .unwrap();
@@ -81,3 +127,504 @@ async fn test_cell_handle_publish() {
     stop_tx.send(()).unwrap();
     shutdown.await.unwrap();
 }
+
+#[tokio::test(threaded_scheduler)]
+async fn test_handle_get_meta_content_selection() {
+    let TestEnvironment {
+        env,
+        tmpdir: _tmpdir,
+    } = test_cell_env();
+    let entry_hash = fixt!(EntryHash);
+
+    // Create an entry, delete it and link from it
+    let mut create = fixt!(Create);
+    create.entry_hash = entry_hash.clone();
+    let create_hash =
+        HeaderHashed::from_content_sync(header::Header::Create(create.clone())).into_hash();
+    let mut delete = fixt!(Delete);
+    delete.deletes_address = create_hash;
+    delete.deletes_entry_address = entry_hash.clone();
+    let mut link_add = fixt!(CreateLink);
+    link_add.base_address = entry_hash.clone();
+
+    {
+        let mut meta_buf = MetadataBuf::vault(env.clone().into()).unwrap();
+        meta_buf
+            .register_header(NewEntryHeader::Create(create))
+            .unwrap();
+        meta_buf.register_delete(delete).unwrap();
+        meta_buf.add_link(link_add).unwrap();
+        env.guard()
+            .with_commit(|writer| meta_buf.flush_to_txn(writer))
+            .unwrap();
+    }
+
+    let full = super::authority::handle_get_meta(
+        env.clone(),
+        entry_hash.clone().into(),
+        (&GetMetaOptions::default()).into(),
+    )
+    .await
+    .unwrap();
+    let deletes_only = GetMetaOptions {
+        include_updates: false,
+        ..GetMetaOptions::crud()
+    };
+    let deletes_only = super::authority::handle_get_meta(
+        env.clone(),
+        entry_hash.clone().into(),
+        (&deletes_only).into(),
+    )
+    .await
+    .unwrap();
+    let crud = super::authority::handle_get_meta(
+        env.clone(),
+        entry_hash.into(),
+        (&GetMetaOptions::crud()).into(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(full.links.as_ref().map(|l| l.len()), Some(1));
+    assert_eq!(full.headers.as_ref().map(|h| h.len()), Some(1));
+
+    // Link data is not built or sent for a deletes only request
+    assert_eq!(deletes_only.links, None);
+    assert_eq!(deletes_only.link_removes, None);
+    assert_eq!(deletes_only.headers, None);
+    assert_eq!(deletes_only.updates, None);
+    assert_eq!(deletes_only.deletes, full.deletes);
+    let full_bytes = SerializedBytes::try_from(full.clone()).unwrap();
+    let deletes_only_bytes = SerializedBytes::try_from(deletes_only).unwrap();
+    assert!(deletes_only_bytes.bytes().len() < full_bytes.bytes().len());
+
+    // CRUD resolution sees the same data as the full fetch
+    assert_eq!(crud.deletes.as_ref().map(|d| d.len()), Some(1));
+    assert_eq!(crud.deletes, full.deletes);
+    assert_eq!(crud.updates, full.updates);
+    assert_eq!(crud.entry_dht_status, full.entry_dht_status);
+    assert_eq!(crud.links, None);
+    assert_eq!(crud.activity, None);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn authority_never_serves_private_entries() {
+    let TestEnvironment {
+        env,
+        tmpdir: _tmpdir,
+    } = test_cell_env();
+    let entry = EntryHashed::from_content_sync(Entry::App(fixt!(AppEntryBytes)));
+    let create = |visibility| {
+        let mut create = fixt!(Create);
+        create.entry_type = EntryType::App(AppEntryType::new(0.into(), 0.into(), visibility));
+        create.entry_hash = entry.as_hash().clone();
+        create
+    };
+    let public = create(EntryVisibility::Public);
+    let private = create(EntryVisibility::Private);
+    let private_hash =
+        HeaderHashed::from_content_sync(header::Header::Create(private.clone())).into_hash();
+    let register = |create: header::Create| {
+        let mut meta_buf = MetadataBuf::vault(env.clone().into()).unwrap();
+        meta_buf
+            .register_element_header(&header::Header::Create(create.clone()))
+            .unwrap();
+        meta_buf
+            .register_header(NewEntryHeader::Create(create))
+            .unwrap();
+        env.guard()
+            .with_commit(|writer| meta_buf.flush_to_txn(writer))
+            .unwrap();
+    };
+    let get_entry = || {
+        super::authority::handle_get_entry(
+            env.clone(),
+            entry.as_hash().clone(),
+            (&GetOptions::default()).into(),
+        )
+    };
+
+    // The private entry's bytes end up in the authority's public entry store,
+    // because the same entry was also committed publicly
+    {
+        let mut element_buf = ElementBuf::vault(env.clone().into(), false).unwrap();
+        for (create, entry) in vec![
+            (public.clone(), Some(entry.clone())),
+            (private.clone(), None),
+        ] {
+            let header = HeaderHashed::from_content_sync(header::Header::Create(create));
+            element_buf
+                .put(
+                    SignedHeaderHashed::with_presigned(header, fixt!(Signature)),
+                    entry,
+                )
+                .unwrap();
+        }
+        env.guard()
+            .with_commit(|writer| element_buf.flush_to_txn_ref(writer))
+            .unwrap();
+    }
+    register(private);
+
+    // The header is served, but its entry is hidden
+    let response = super::authority::handle_get_element(env.clone(), private_hash.clone())
+        .await
+        .unwrap();
+    let element = match response {
+        GetElementResponse::GetHeader(Some(wire_element)) => {
+            wire_element.into_element_and_delete().await.0
+        }
+        r => panic!("Expected the private header, got {:?}", r),
+    };
+    assert_eq!(element.header_address(), &private_hash);
+    assert_eq!(element.entry(), &ElementEntry::Hidden);
+
+    // The entry is not served by its hash while it only has a private header
+    let response = get_entry().await.unwrap();
+    assert_matches!(response, GetElementResponse::GetEntryFull(None));
+
+    // Once the public header is held the entry is served,
+    // along with only the public header
+    register(public);
+    let response = get_entry().await.unwrap();
+    let response = match response {
+        GetElementResponse::GetEntryFull(Some(response)) => response,
+        r => panic!("Expected the public entry, got {:?}", r),
+    };
+    assert_eq!(response.entry, *entry.as_content());
+    assert_eq!(response.entry_type.visibility(), &EntryVisibility::Public);
+    assert_eq!(response.live_headers.len(), 1);
+}
+
+/// A public Create of a fresh entry, as an element
+fn public_create_element() -> (HeaderHash, Element) {
+    let entry = Entry::App(fixt!(AppEntryBytes));
+    let mut create = fixt!(Create);
+    create.entry_type = EntryType::App(AppEntryType::new(
+        0.into(),
+        0.into(),
+        EntryVisibility::Public,
+    ));
+    create.entry_hash = EntryHashed::from_content_sync(entry.clone()).into_hash();
+    let header = HeaderHashed::from_content_sync(header::Header::Create(create));
+    let header_hash = header.as_hash().clone();
+    let element = Element::new(
+        SignedHeaderHashed::with_presigned(header, fixt!(Signature)),
+        Some(entry),
+    );
+    (header_hash, element)
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn get_element_by_header_hits_local_store() {
+    let TestEnvironment {
+        env,
+        tmpdir: _tmpdir,
+    } = test_cell_env();
+    let (header_hash, element) = public_create_element();
+    {
+        let mut element_buf = ElementBuf::authored(env.clone().into(), false).unwrap();
+        let (shh, entry) = element.clone().into_inner();
+        element_buf
+            .put(shh, entry.into_option().map(EntryHashed::from_content_sync))
+            .unwrap();
+        env.guard()
+            .with_commit(|writer| element_buf.flush_to_txn_ref(writer))
+            .unwrap();
+    }
+
+    // The element is found locally so the network is never asked
+    let mut network = MockHolochainP2pCellT::new();
+    network.expect_get().never();
+
+    let response = super::authority::handle_get_element_by_header(
+        env.clone(),
+        network,
+        header_hash,
+        (&GetOptions::default()).into(),
+    )
+    .await
+    .unwrap();
+    match response {
+        GetElementResponse::GetHeader(Some(wire_element)) => {
+            assert_eq!(wire_element.into_element_and_delete().await.0, element);
+        }
+        r => panic!("Expected the local element, got {:?}", r),
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn bob_serves_alices_get_from_a_shared_dht_env() {
+    let test_dht_env = test_dht_env();
+    let alice_test_env = test_cell_env();
+    let bob_test_env = test_cell_env();
+    let alice_env = alice_test_env.env().with_dht_env(test_dht_env.env());
+    let bob_env = bob_test_env.env().with_dht_env(test_dht_env.env());
+    let (header_hash, element) = public_create_element();
+
+    // Alice integrates the element
+    {
+        let mut element_buf = ElementBuf::vault(alice_env.clone().into(), false).unwrap();
+        let mut meta_buf = MetadataBuf::vault(alice_env.clone().into()).unwrap();
+        meta_buf.register_element_header(element.header()).unwrap();
+        let (shh, entry) = element.clone().into_inner();
+        element_buf
+            .put(shh, entry.into_option().map(EntryHashed::from_content_sync))
+            .unwrap();
+        alice_env
+            .guard()
+            .with_commit(|writer| {
+                element_buf.flush_to_txn_ref(writer)?;
+                meta_buf.flush_to_txn(writer)
+            })
+            .unwrap();
+    }
+
+    // Bob is an authority for it too, as he holds the same DHT data
+    let response = super::authority::handle_get_element(bob_env, header_hash.clone())
+        .await
+        .unwrap();
+    match response {
+        GetElementResponse::GetHeader(Some(wire_element)) => {
+            assert_eq!(wire_element.into_element_and_delete().await.0, element);
+        }
+        r => panic!("Expected Bob to serve the element, got {:?}", r),
+    }
+
+    // Without the shared env Bob holds nothing
+    let response = super::authority::handle_get_element(bob_test_env.env(), header_hash)
+        .await
+        .unwrap();
+    assert_matches!(response, GetElementResponse::GetHeader(None));
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn get_element_by_header_falls_through_to_network() {
+    let TestEnvironment {
+        env,
+        tmpdir: _tmpdir,
+    } = test_cell_env();
+    let (header_hash, element) = public_create_element();
+
+    // Nothing is held locally so the element is fetched by its header hash
+    let mut network = MockHolochainP2pCellT::new();
+    let wire_element = WireElement::from_element(element.clone(), None);
+    let expected_hash: AnyDhtHash = header_hash.clone().into();
+    network
+        .expect_get()
+        .withf(move |hash, _| *hash == expected_hash)
+        .times(1)
+        .return_once(move |_, _| {
+            Ok(vec![GetElementResponse::GetHeader(Some(Box::new(
+                wire_element,
+            )))])
+        });
+
+    let response = super::authority::handle_get_element_by_header(
+        env.clone(),
+        network,
+        header_hash,
+        (&GetOptions::default()).into(),
+    )
+    .await
+    .unwrap();
+    match response {
+        GetElementResponse::GetHeader(Some(wire_element)) => {
+            assert_eq!(wire_element.into_element_and_delete().await.0, element);
+        }
+        r => panic!("Expected the fetched element, got {:?}", r),
+    }
+
+    // An element nobody holds is not found
+    let mut network = MockHolochainP2pCellT::new();
+    network
+        .expect_get()
+        .times(1)
+        .return_once(|_, _| Ok(vec![GetElementResponse::GetHeader(None)]));
+    let response = super::authority::handle_get_element_by_header(
+        env.clone(),
+        network,
+        public_create_element().0,
+        (&GetOptions::default()).into(),
+    )
+    .await
+    .unwrap();
+    assert_matches!(response, GetElementResponse::GetHeader(None));
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn countersigning_responses_must_sign_the_preflight() {
+    let TestEnvironment {
+        env,
+        tmpdir: _tmpdir,
+    } = test_cell_env();
+    let (holochain_p2p, _p2p_evt) = holochain_p2p::spawn_holochain_p2p(Default::default())
+        .await
+        .unwrap();
+    let cell_id = fake_cell_id(1);
+    let alice = cell_id.agent_pubkey().clone();
+    let bob = fake_agent_pubkey_2();
+    let holochain_p2p_cell = holochain_p2p.to_cell(cell_id.dna_hash().clone(), alice.clone());
+
+    let mut mock_handler = crate::conductor::handle::MockConductorHandleT::new();
+    mock_handler
+        .expect_get_dna()
+        .returning(|_| Some(fixt!(DnaFile)));
+    let mock_handler: crate::conductor::handle::ConductorHandle = Arc::new(mock_handler);
+
+    super::Cell::genesis(
+        cell_id.clone(),
+        mock_handler.clone(),
+        env.clone(),
+        None,
+        false,
+    )
+    .await
+    .unwrap();
+
+    let (add_task_sender, shutdown) = spawn_task_manager();
+    let (stop_tx, _) = sync::broadcast::channel(1);
+
+    let cell = super::Cell::create(
+        cell_id.clone(),
+        mock_handler,
+        env.clone(),
+        holochain_p2p_cell,
+        add_task_sender,
+        stop_tx.clone(),
+        Default::default(),
+        DEFAULT_RECEIPT_FLUSH_INTERVAL,
+        DEFAULT_SCRATCH_SIZE_LIMIT,
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+
+    let payload: SerializedBytes = UnsafeBytes::from(vec![1, 2, 3]).into();
+    let preflight = CountersigningPreflight::new(
+        cell_id.dna_hash().clone(),
+        alice.clone(),
+        bob.clone(),
+        payload.clone(),
+    );
+    let signing_hash = preflight.signing_hash().unwrap();
+    let signature = bob.sign_raw(env.keystore(), &signing_hash).await.unwrap();
+
+    // Nothing was sent to Bob, so there is nothing for him to sign
+    assert_matches!(
+        cell.handle_countersigning_response(bob.clone(), signature.clone())
+            .await,
+        Err(CellError::CountersigningError(
+            CountersigningError::NoSession(_)
+        ))
+    );
+
+    // Alice sends Bob a preflight
+    cell.countersigning
+        .start(
+            CountersigningRole::Initiator,
+            bob.clone(),
+            preflight.clone(),
+        )
+        .unwrap();
+
+    // A signature of anything else, or by anyone else, is rejected.
+    // That includes the preflight itself without the signing domain.
+    let other_bytes = bob.sign_raw(env.keystore(), &[4, 5, 6]).await.unwrap();
+    let untagged = bob
+        .sign_raw(
+            env.keystore(),
+            SerializedBytes::try_from(preflight.clone())
+                .unwrap()
+                .bytes(),
+        )
+        .await
+        .unwrap();
+    let by_alice = alice.sign_raw(env.keystore(), &signing_hash).await.unwrap();
+    for wrong_signature in vec![other_bytes, untagged, by_alice] {
+        assert_matches!(
+            cell.handle_countersigning_response(bob.clone(), wrong_signature)
+                .await,
+            Err(CellError::CountersigningError(
+                CountersigningError::InvalidSignature(agent)
+            )) if agent == bob
+        );
+    }
+    assert_eq!(
+        cell.countersigning_session(&bob).unwrap().state,
+        CountersigningState::AwaitingSignature
+    );
+
+    // Bob's signature completes the session, once
+    cell.handle_countersigning_response(bob.clone(), signature.clone())
+        .await
+        .unwrap();
+    assert_eq!(
+        cell.countersigning_session(&bob).unwrap().state,
+        CountersigningState::Signed(signature.clone())
+    );
+    assert_matches!(
+        cell.handle_countersigning_response(bob.clone(), signature)
+            .await,
+        Err(CellError::CountersigningError(
+            CountersigningError::NoSession(_)
+        ))
+    );
+
+    // Bob can then ask Alice to countersign, but only with a preflight
+    // of a session between Bob and Alice in this Dna
+    let preflight = |initiator: &AgentPubKey, responder: &AgentPubKey, dna_hash: &DnaHash| {
+        SerializedBytes::try_from(CountersigningPreflight::new(
+            dna_hash.clone(),
+            initiator.clone(),
+            responder.clone(),
+            payload.clone(),
+        ))
+        .unwrap()
+    };
+    let carol = fixt!(AgentPubKey);
+    let other_dna = fake_cell_id(2).dna_hash().clone();
+    assert_matches!(
+        cell.handle_countersigning_request(bob.clone(), payload.clone()),
+        Err(CellError::CountersigningError(
+            CountersigningError::MalformedPreflight(agent, _)
+        )) if agent == bob
+    );
+    for wrong_preflight in vec![
+        preflight(&carol, &alice, cell_id.dna_hash()),
+        preflight(&bob, &carol, cell_id.dna_hash()),
+        preflight(&bob, &alice, &other_dna),
+    ] {
+        assert_matches!(
+            cell.handle_countersigning_request(bob.clone(), wrong_preflight),
+            Err(CellError::CountersigningError(
+                CountersigningError::WrongPreflight(agent)
+            )) if agent == bob
+        );
+    }
+    assert_eq!(
+        cell.countersigning_session(&bob).unwrap().role,
+        CountersigningRole::Initiator
+    );
+
+    // Only one preflight at a time
+    cell.handle_countersigning_request(bob.clone(), preflight(&bob, &alice, cell_id.dna_hash()))
+        .unwrap();
+    assert_eq!(
+        cell.countersigning_session(&bob).unwrap().role,
+        CountersigningRole::Responder
+    );
+    assert_matches!(
+        cell.handle_countersigning_request(
+            bob.clone(),
+            preflight(&bob, &alice, cell_id.dna_hash())
+        ),
+        Err(CellError::CountersigningError(
+            CountersigningError::SessionInProgress(_)
+        ))
+    );
+
+    stop_tx.send(()).unwrap();
+    shutdown.await.unwrap();
+}
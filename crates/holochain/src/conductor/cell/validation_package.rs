@@ -1,8 +1,11 @@
 use holochain_state::{env::EnvironmentRead, error::DatabaseResult, prelude::*};
-use holochain_types::dna::DnaFile;
-use holochain_zome_types::Header;
+use holochain_types::{dna::DnaFile, element::Element};
+use holochain_zome_types::{header::AppEntryType, Header};
 
-use crate::core::state::cascade::{Cascade, DbPair};
+use crate::core::state::{
+    cascade::{Cascade, DbPair},
+    source_chain::SourceChainResult,
+};
 
 use super::*;
 
@@ -36,15 +39,14 @@ impl ValidationPackageDb {
     }
 }
 
-pub(super) async fn get_as_author(
-    header: Header,
-    env: EnvironmentRead,
+/// The app entry type of a header's entry, its position in the chain,
+/// and the validation package its entry def requires.
+/// `None` if the header has no app entry or its entry def can't be found.
+pub(super) async fn required_validation_type(
+    header: &Header,
     dna_file: &DnaFile,
     conductor_api: &impl CellConductorApiT,
-) -> CellResult<ValidationPackageResponse> {
-    // Get the source chain with public data only
-    let source_chain = SourceChain::public_only(env)?;
-
+) -> CellResult<Option<(AppEntryType, u32, RequiredValidationType)>> {
     // Get the header data
     let (app_entry_type, header_seq) = match header
         .entry_type()
@@ -52,7 +54,7 @@ pub(super) async fn get_as_author(
         .map(|et| (et, header.header_seq()))
     {
         Some((EntryType::App(aet), header_seq)) => (aet, header_seq),
-        _ => return Ok(None.into()),
+        _ => return Ok(None),
     };
 
     //Get entry def
@@ -65,10 +67,22 @@ pub(super) async fn get_as_author(
     .await?;
 
     // Get the required validation package
-    let required_validation_type = match entry_def {
-        Some(ed) => ed.required_validation_type,
-        None => return Ok(None.into()),
-    };
+    Ok(entry_def.map(|ed| (app_entry_type, header_seq, ed.required_validation_type)))
+}
+
+/// The validation package for one of the author's own headers,
+/// gathered from its source chain
+pub(super) async fn get_as_author(
+    header: Header,
+    source_chain: &SourceChain,
+    dna_file: &DnaFile,
+    conductor_api: &impl CellConductorApiT,
+) -> CellResult<ValidationPackageResponse> {
+    let (app_entry_type, header_seq, required_validation_type) =
+        match required_validation_type(&header, dna_file, conductor_api).await? {
+            Some(required) => required,
+            None => return Ok(None.into()),
+        };
 
     // Gather the package
     match required_validation_type {
@@ -78,22 +92,24 @@ pub(super) async fn get_as_author(
         }
         RequiredValidationType::SubChain => {
             // Collect and return the sub chain
-            let elements = source_chain.query(
-                &ChainQueryFilter::default()
-                    .include_entries(true)
-                    .entry_type(EntryType::App(app_entry_type))
-                    // Range is exclusive but we want to include this header
-                    .sequence_range(0..header_seq + 1),
-            )?;
+            let entry_type = EntryType::App(app_entry_type);
+            let elements = chain_up_to(source_chain, header_seq)?
+                .into_iter()
+                .filter(|el| el.header().entry_type() == Some(&entry_type))
+                .collect();
             Ok(Some(ValidationPackage::new(elements)).into())
         }
         RequiredValidationType::Full => {
-            let elements = source_chain.query(
-                &ChainQueryFilter::default()
-                    .include_entries(true)
-                    .sequence_range(0..header_seq + 1),
-            )?;
+            let elements = chain_up_to(source_chain, header_seq)?;
             Ok(Some(ValidationPackage::new(elements)).into())
         }
     }
 }
+
+/// The chain from genesis up to and including this header, latest first
+fn chain_up_to(source_chain: &SourceChain, header_seq: u32) -> SourceChainResult<Vec<Element>> {
+    // Range is exclusive but we want to include this header
+    let mut elements = source_chain.get_chain_section(0, header_seq + 1)?;
+    elements.reverse();
+    Ok(elements)
+}
@@ -102,7 +102,7 @@ pub async fn load_conductor_from_legacy_config(
     let app_id = "LEGACY".to_string();
     conductor
         .clone()
-        .install_app(app_id.clone(), app_install_payload)
+        .install_app(app_id.clone(), app_install_payload, true)
         .await
         .map_err(Box::new)?;
     conductor
@@ -370,9 +370,10 @@ pub mod tests {
                         && data[1].0.as_id().dna_hash() == dna1a.clone().dna_hash()
                         && data[1].0.as_nick() == "i2"
                 }),
+                predicate::eq(true),
             )
             .times(1)
-            .returning(|_, _| Ok(()));
+            .returning(|_, _, _| Ok(()));
         handle
             .expect_activate_app()
             .with(predicate::eq("LEGACY".to_string()))
@@ -10,26 +10,31 @@
 //! users in a testing environment.
 use super::{
     api::{CellConductorApi, CellConductorApiT, RealAdminInterfaceApi, RealAppInterfaceApi},
-    config::{AdminInterfaceConfig, InterfaceDriver},
+    config::{
+        AdminInterfaceConfig, DurabilityConfig, InterfaceDriver, QueueBackoffConfig,
+        ValidationCircuitBreakerConfig,
+    },
     dna_store::{DnaDefBuf, DnaStore, RealDnaStore},
     entry_def_store::{get_entry_defs, EntryDefBuf, EntryDefBufferKey},
     error::{ConductorError, CreateAppError},
     handle::ConductorHandleImpl,
     interface::{
         error::InterfaceResult,
+        signal_queue::{SignalQueue, DEFAULT_SIGNAL_QUEUE_DEPTH},
         websocket::{
             spawn_admin_interface_task, spawn_app_interface_task, spawn_websocket_listener,
-            SIGNAL_BUFFER_SIZE,
+            ConnectionCounter,
         },
-        SignalBroadcaster,
+        InterfaceInfo, InterfaceType, SignalBroadcaster,
     },
     manager::{
-        keep_alive_task, spawn_task_manager, ManagedTaskAdd, ManagedTaskHandle,
+        keep_alive_task, spawn_task_manager, ManagedTaskAdd, ManagedTaskHandle, ManagedTaskResult,
         TaskManagerRunHandle,
     },
+    migration::{StateMigration, StateMigrations},
     paths::EnvironmentRootPath,
     state::AppInterfaceId,
-    state::ConductorState,
+    state::{AppMetadataKey, ConductorState},
     CellError,
 };
 use crate::{
@@ -37,18 +42,33 @@ use crate::{
         api::error::ConductorApiResult, cell::Cell, config::ConductorConfig,
         dna_store::MockDnaStore, error::ConductorResult, handle::ConductorHandle,
     },
-    core::signal::Signal,
-    core::state::{source_chain::SourceChainBuf, wasm::WasmBuf},
+    core::clock_skew::ClockSkew,
+    core::gossip_metrics::CellGossipMetrics,
+    core::ribosome::metering::{ZomeCallMetrics, ZomeCallResources, ZomeFnMetrics},
+    core::ribosome::module_cache::WasmModuleCache,
+    core::ribosome::validation_pool::{
+        default_validation_threads, ExecutionContext, DEFAULT_VALIDATION_QUEUE_DEPTH,
+    },
+    core::state::{
+        integrity_audit::IntegrityAuditReport,
+        source_chain::{ForkReport, SourceChainBuf, SourceChainError},
+        wasm::WasmBuf,
+        workflow_errors,
+    },
+    core::validation_breaker::ValidationBreakerStatus,
+    core::workflow::call_zome_workflow::DEFAULT_SCRATCH_SIZE_LIMIT,
+    core::workflow::validation_receipt_workflow::DEFAULT_RECEIPT_FLUSH_INTERVAL,
 };
 use holochain_keystore::{
-    lair_keystore::spawn_lair_keystore, test_keystore::spawn_test_keystore, KeystoreSender,
-    KeystoreSenderExt,
+    lair_keystore::spawn_lair_keystore, test_keystore::spawn_test_keystore, AgentPubKeyExt,
+    KeystoreSender, KeystoreSenderExt,
 };
 use holochain_state::{
     buffer::BufferedStore,
     buffer::{KvStore, KvStoreT},
     db,
     env::{EnvironmentKind, EnvironmentWrite, ReadManager},
+    error::DatabaseResult,
     exports::SingleStore,
     fresh_reader,
     prelude::*,
@@ -56,23 +76,27 @@ use holochain_state::{
 use holochain_types::{
     app::{AppId, InstalledApp, InstalledCell, MembraneProof},
     cell::CellId,
-    dna::{wasm::DnaWasmHashed, DnaFile},
+    dna::{wasm::DnaWasmHashed, DnaFile, DnaFileBundle},
 };
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::{mpsc, RwLock};
 use tracing::*;
 
-use crate::conductor::p2p_store::AgentKv;
+use crate::conductor::p2p_store::{
+    self, agent_info_is_expired, agent_info_signing_bytes, AgentInfoRejection, AgentKv, AgentKvKey,
+    PeerMetaKv,
+};
 pub use builder::*;
 use futures::future::{self, TryFutureExt};
-use holo_hash::DnaHash;
+use holo_hash::{AgentPubKey, DnaHash, HeaderHash, WasmHash};
+use holochain_p2p::compression::PeerMeta;
 use kitsune_p2p::agent_store::AgentInfoSigned;
 
 #[cfg(test)]
 use super::handle::MockConductorHandleT;
 use fallible_iterator::FallibleIterator;
-use holochain_zome_types::entry_def::EntryDef;
+use holochain_zome_types::{entry_def::EntryDef, signature::Signature};
 
 /// Conductor-specific Cell state, this can probably be stored in a database.
 /// Hypothesis: If nothing remains in this struct, then the Conductor state is
@@ -116,18 +140,30 @@ where
     /// The database for persisting [ConductorState]
     state_db: ConductorStateDb,
 
+    /// The database for persisting metadata annotations on installed apps
+    app_metadata_db: AppMetadataDb,
+
     /// Set to true when `conductor.shutdown()` has been called, so that other
     /// tasks can check on the shutdown status
     shutting_down: bool,
 
-    /// The admin websocket ports this conductor has open.
+    /// The admin websocket ports this conductor has open, along with the
+    /// clients connected to each.
     /// This exists so that we can run tests and bind to port 0, and find out
     /// the dynamically allocated port later.
-    admin_websocket_ports: Vec<u16>,
+    admin_websocket_ports: Vec<(u16, ConnectionCounter)>,
+
+    /// The port each app interface is listening on
+    app_interface_ports: HashMap<AppInterfaceId, u16>,
+
+    /// The queue of outgoing signals of each app interface, keyed by id
+    app_interface_signal_queues: HashMap<AppInterfaceId, SignalQueue>,
+
+    /// How many signals each app interface's queue holds
+    signal_queue_depth: usize,
 
-    /// Collection of signal broadcasters per app interface, keyed by id
-    app_interface_signal_broadcasters:
-        HashMap<AppInterfaceId, tokio::sync::broadcast::Sender<Signal>>,
+    /// The apps whose signals each app interface is subscribed to
+    app_interface_signal_apps: HashMap<AppInterfaceId, HashSet<AppId>>,
 
     /// Channel on which to send info about tasks we want to manage
     managed_task_add_sender: mpsc::Sender<ManagedTaskAdd>,
@@ -150,6 +186,78 @@ where
 
     /// Handle to the network actor.
     holochain_p2p: holochain_p2p::HolochainP2pRef,
+
+    /// Whether Cells of the same DNA share one DHT environment
+    shared_dht_spaces: bool,
+
+    /// How durably each kind of environment writes to disk
+    durability: DurabilityConfig,
+
+    /// How each Cell's queue consumers back off after transient errors
+    queue_backoff: QueueBackoffConfig,
+
+    /// How long each Cell waits after integrating ops before sending their receipts
+    receipt_flush_interval: std::time::Duration,
+
+    /// How many bytes each zome call may commit before it is flushed
+    scratch_size_limit: usize,
+
+    /// Where every Cell's app validation callbacks run
+    validation_context: ExecutionContext,
+
+    /// When each Cell pauses app validation because its callbacks keep erroring
+    validation_circuit_breaker: ValidationCircuitBreakerConfig,
+
+    /// Whether genesis elements are validated before they are committed
+    genesis_self_check: bool,
+
+    /// The migrations which can upgrade the schema of this Conductor's environment
+    state_migrations: StateMigrations,
+}
+
+/// The time taken to flush one environment to disk
+#[derive(Clone, Debug)]
+pub struct EnvironmentSyncReport {
+    /// The path of the environment
+    pub path: std::path::PathBuf,
+    /// How long the sync took
+    pub duration: std::time::Duration,
+}
+
+/// The status of one of a Conductor's running Cells
+#[derive(Clone, Debug, PartialEq)]
+pub struct CellStatus {
+    /// The Cell this status is about
+    pub cell_id: CellId,
+    /// Roughly how many bytes the Cell takes on disk,
+    /// see [SourceChainBuf::estimated_disk_size]
+    pub estimated_disk_size: u64,
+    /// The most bytes the last zome call to the Cell held before flushing,
+    /// for tuning the scratch size limit
+    pub last_call_peak_scratch_size: usize,
+    /// The resources the last zome call to the Cell which reached the guest used
+    pub last_call_resources: Option<ZomeCallResources>,
+    /// The state of the Cell's app validation circuit breaker
+    pub validation_breaker: ValidationBreakerStatus,
+}
+
+/// Measures of the resources a Conductor's Cells are using
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConductorMetrics {
+    /// The status of every running Cell, in no particular order
+    pub cells: Vec<CellStatus>,
+    /// The resources used by zome calls to all the running Cells,
+    /// added up per Dna, zome and function
+    pub zome_fns: Vec<ZomeFnMetrics>,
+    /// How many app validation callbacks are waiting for a validation thread
+    pub validation_queue_depth: usize,
+}
+
+impl ConductorMetrics {
+    /// Roughly how many bytes all the running Cells take on disk
+    pub fn estimated_disk_size(&self) -> u64 {
+        self.cells.iter().map(|c| c.estimated_disk_size).sum()
+    }
 }
 
 impl Conductor {
@@ -169,7 +277,38 @@ where
     /// Returns a port which is guaranteed to have a websocket listener with an Admin interface
     /// on it. Useful for specifying port 0 and letting the OS choose a free port.
     pub fn get_arbitrary_admin_websocket_port(&self) -> Option<u16> {
-        self.admin_websocket_ports.get(0).copied()
+        self.admin_websocket_ports.get(0).map(|(port, _)| *port)
+    }
+
+    /// Every open interface, admin interfaces first, each in port order
+    pub fn list_app_interfaces(&self) -> Vec<InterfaceInfo> {
+        let mut admin: Vec<_> = self
+            .admin_websocket_ports
+            .iter()
+            .map(|(port, connections)| InterfaceInfo {
+                port: *port,
+                interface_type: InterfaceType::Admin,
+                connected_client_count: connections.count(),
+                signal_queue: None,
+            })
+            .collect();
+        let mut app: Vec<_> = self
+            .app_interface_ports
+            .iter()
+            .filter_map(|(interface_id, port)| {
+                let signal_queue = self.app_interface_signal_queues.get(interface_id)?;
+                Some(InterfaceInfo {
+                    port: *port,
+                    interface_type: InterfaceType::App,
+                    connected_client_count: signal_queue.connections().count(),
+                    signal_queue: Some(signal_queue.stats()),
+                })
+            })
+            .collect();
+        admin.sort_by_key(|info| info.port);
+        app.sort_by_key(|info| info.port);
+        admin.append(&mut app);
+        admin
     }
 }
 
@@ -208,6 +347,10 @@ where
 
     pub(super) fn shutdown(&mut self) {
         self.shutting_down = true;
+        // Environments which don't sync on commit must not lose data on a clean exit
+        if let Err(e) = self.sync_environments() {
+            error!(?e, "Couldn't sync environments before shutdown!");
+        }
         self.managed_task_stop_broadcaster
             .send(())
             .map(|_| ())
@@ -216,6 +359,38 @@ where
             })
     }
 
+    /// Every environment this conductor has open, each listed once
+    fn environments(&self) -> Vec<EnvironmentWrite> {
+        let mut envs = vec![
+            self.env.clone(),
+            self.wasm_env.clone(),
+            self.p2p_env.clone(),
+        ];
+        for item in self.cells.values() {
+            let env = item.cell.env();
+            envs.extend(env.dht_env().cloned());
+            envs.push(env.clone());
+        }
+        let mut paths = HashSet::new();
+        envs.retain(|env| paths.insert(env.path().clone()));
+        envs
+    }
+
+    /// Flush every open environment to disk, timing each sync
+    pub(super) fn sync_environments(&self) -> ConductorResult<Vec<EnvironmentSyncReport>> {
+        self.environments()
+            .into_iter()
+            .map(|env| {
+                let start = std::time::Instant::now();
+                env.sync()?;
+                Ok(EnvironmentSyncReport {
+                    path: env.path().clone(),
+                    duration: start.elapsed(),
+                })
+            })
+            .collect()
+    }
+
     pub(super) fn take_shutdown_handle(&mut self) -> Option<TaskManagerRunHandle> {
         self.task_manager_run_handle.take()
     }
@@ -242,12 +417,14 @@ where
                     InterfaceDriver::Websocket { port } => {
                         let listener = spawn_websocket_listener(port).await?;
                         let port = listener.local_addr().port().unwrap_or(port);
+                        let connections = ConnectionCounter::default();
                         let handle: ManagedTaskHandle = spawn_admin_interface_task(
                             listener,
                             admin_api.clone(),
+                            connections.clone(),
                             stop_tx.subscribe(),
                         )?;
-                        InterfaceResult::Ok((port, handle))
+                        InterfaceResult::Ok((port, connections, handle))
                     }
                 }
             }
@@ -274,8 +451,8 @@ where
             .await?;
 
             // Now that tasks are spawned, register them with the TaskManager
-            for (port, handle) in handles {
-                ports.push(port);
+            for (port, connections, handle) in handles {
+                ports.push((port, connections));
                 self.manage_task(ManagedTaskAdd::new(
                     handle,
                     Box::new(|result| {
@@ -287,8 +464,8 @@ where
                 ))
                 .await?
             }
-            for p in ports {
-                self.add_admin_port(p);
+            for (p, connections) in ports {
+                self.add_admin_port(p, connections);
             }
         }
         Ok(())
@@ -301,34 +478,85 @@ where
     ) -> ConductorResult<u16> {
         let interface_id: AppInterfaceId = format!("interface-{}", port).into();
         let app_api = RealAppInterfaceApi::new(handle, interface_id.clone());
-        // This receiver is thrown away because we can produce infinite new
-        // receivers from the Sender
-        let (signal_broadcaster, _r) = tokio::sync::broadcast::channel(SIGNAL_BUFFER_SIZE);
+        let signal_queue = SignalQueue::new(
+            interface_id.clone(),
+            self.signal_queue_depth,
+            ConnectionCounter::default(),
+        );
         let stop_rx = self.managed_task_stop_broadcaster.subscribe();
-        let (port, task) =
-            spawn_app_interface_task(port, app_api, signal_broadcaster.clone(), stop_rx)
-                .await
-                .map_err(Box::new)?;
+        let (port, task) = spawn_app_interface_task(port, app_api, signal_queue.clone(), stop_rx)
+            .await
+            .map_err(Box::new)?;
         // TODO: RELIABILITY: Handle this task by restarting it if it fails and log the error
         self.manage_task(ManagedTaskAdd::dont_handle(task)).await?;
-        self.app_interface_signal_broadcasters
-            .insert(interface_id, signal_broadcaster);
+        self.app_interface_signal_queues
+            .insert(interface_id.clone(), signal_queue);
+        self.app_interface_ports.insert(interface_id, port);
         Ok(port)
     }
 
     pub(super) fn signal_broadcaster(&self) -> SignalBroadcaster {
-        SignalBroadcaster::new(
-            self.app_interface_signal_broadcasters
-                .values()
-                .cloned()
-                .collect(),
-        )
+        let mut app_queues: HashMap<AppId, Vec<_>> = HashMap::new();
+        for (interface_id, app_ids) in self.app_interface_signal_apps.iter() {
+            if let Some(queue) = self.app_interface_signal_queues.get(interface_id) {
+                for app_id in app_ids {
+                    app_queues
+                        .entry(app_id.clone())
+                        .or_default()
+                        .push(queue.clone());
+                }
+            }
+        }
+        SignalBroadcaster::new(self.app_interface_signal_queues.values().cloned().collect())
+            .with_app_routes(app_queues)
+    }
+
+    /// The Apps with a subscribed app interface which each Cell belongs to,
+    /// so that the signals the Cell emits are routed to those interfaces
+    pub(super) async fn signal_cell_apps(&self) -> ConductorResult<HashMap<CellId, Vec<AppId>>> {
+        let mut cell_apps: HashMap<CellId, Vec<AppId>> = HashMap::new();
+        let subscribed: HashSet<&AppId> =
+            self.app_interface_signal_apps.values().flatten().collect();
+        // Don't read the state while nothing is subscribed
+        if subscribed.is_empty() {
+            return Ok(cell_apps);
+        }
+        let state = self.get_state().await?;
+        for app_id in subscribed {
+            for cell in state.active_apps.get(app_id).into_iter().flatten() {
+                cell_apps
+                    .entry(cell.as_id().clone())
+                    .or_default()
+                    .push(app_id.clone());
+            }
+        }
+        Ok(cell_apps)
+    }
+
+    /// Subscribe an app interface to the signals of an App, or unsubscribe it
+    pub(super) fn update_signal_subscription(
+        &mut self,
+        interface_id: AppInterfaceId,
+        app_id: AppId,
+        subscribe: bool,
+    ) {
+        let app_ids = self
+            .app_interface_signal_apps
+            .entry(interface_id)
+            .or_default();
+        if subscribe {
+            app_ids.insert(app_id);
+        } else {
+            app_ids.remove(&app_id);
+        }
     }
 
     /// Perform Genesis on the source chains for each of the specified CellIds.
     ///
     /// If genesis fails for any cell, this entire function fails, and all other
     /// partial or complete successes are rolled back.
+    /// If a cell's genesis elements fail its own validation, the typed
+    /// [ConductorError::GenesisSelfCheckFailed] is returned.
     pub(super) async fn genesis_cells(
         &self,
         cell_ids_with_proofs: Vec<(CellId, Option<MembraneProof>)>,
@@ -336,19 +564,31 @@ where
     ) -> ConductorResult<()> {
         let root_env_dir = std::path::PathBuf::from(self.root_env_dir.clone());
         let keystore = self.keystore.clone();
+        let shared_dht_spaces = self.shared_dht_spaces;
+        let genesis_self_check = self.genesis_self_check;
 
         let cells_tasks = cell_ids_with_proofs.into_iter().map(|(cell_id, proof)| {
             let root_env_dir = root_env_dir.clone();
             let keystore = self.keystore.clone();
+            let durability = self.durability.clone();
             let conductor_handle = conductor_handle.clone();
             let cell_id_inner = cell_id.clone();
             tokio::spawn(async move {
-                let env = EnvironmentWrite::new(
+                let env = open_cell_env(
                     &root_env_dir,
-                    EnvironmentKind::Cell(cell_id_inner.clone()),
+                    cell_id_inner.clone(),
                     keystore.clone(),
+                    shared_dht_spaces,
+                    &durability,
                 )?;
-                Cell::genesis(cell_id_inner, conductor_handle, env, proof).await
+                Cell::genesis(
+                    cell_id_inner,
+                    conductor_handle,
+                    env,
+                    proof,
+                    genesis_self_check,
+                )
+                .await
             })
             .map_err(CellError::from)
             .and_then(|result| async move { result.map(|_| cell_id) })
@@ -373,7 +613,7 @@ where
             }
 
             // match needed to avoid Debug requirement on unwrap_err
-            let errors = errors
+            let mut errors: Vec<_> = errors
                 .into_iter()
                 .map(|e| match e {
                     Err(e) => e,
@@ -381,7 +621,15 @@ where
                 })
                 .collect();
 
-            Err(ConductorError::GenesisFailed { errors })
+            let self_check_failed = errors
+                .iter()
+                .position(|e| matches!(e, CellError::GenesisSelfCheckFailed { .. }));
+            match self_check_failed.map(|i| errors.swap_remove(i)) {
+                Some(CellError::GenesisSelfCheckFailed { cell_id, reason }) => {
+                    Err(ConductorError::GenesisSelfCheckFailed { cell_id, reason })
+                }
+                _ => Err(ConductorError::GenesisFailed { errors }),
+            }
         } else {
             // No errors so return the cells
             Ok(())
@@ -393,8 +641,10 @@ where
         &self,
         conductor_handle: ConductorHandle,
     ) -> ConductorResult<Vec<Result<Vec<Cell>, CreateAppError>>> {
-        // Only create the active apps
-        let active_apps = self.get_state().await?.active_apps;
+        // Only create the active apps, leaving out any quarantined cells
+        let state = self.get_state().await?;
+        let active_apps = state.active_apps;
+        let quarantined_cells = &state.quarantined_cells;
 
         // Data required to create apps
         let root_env_dir = self.root_env_dir.clone();
@@ -415,7 +665,10 @@ where
                     async move {
                         // Only create cells not already created
                         let cells_to_create = cell_ids
-                            .filter(|cell_id| !self.cells.contains_key(cell_id))
+                            .filter(|cell_id| {
+                                !self.cells.contains_key(cell_id)
+                                    && !quarantined_cells.contains_key(cell_id)
+                            })
                             .map(|cell_id| {
                                 (
                                     cell_id,
@@ -435,20 +688,41 @@ where
                                     cell_id.agent_pubkey().clone(),
                                 );
 
-                                let env = EnvironmentWrite::new_cell(
+                                let env = open_cell_env(
                                     &dir,
                                     cell_id.clone(),
                                     keystore.clone(),
+                                    self.shared_dht_spaces,
+                                    &self.durability,
                                 )?;
-                                Cell::create(
+                                let cell = Cell::create(
                                     cell_id.clone(),
                                     conductor_handle.clone(),
                                     env,
                                     holochain_p2p_cell,
                                     self.managed_task_add_sender.clone(),
                                     self.managed_task_stop_broadcaster.clone(),
+                                    self.queue_backoff.clone(),
+                                    self.receipt_flush_interval,
+                                    self.scratch_size_limit,
+                                    self.validation_context.clone(),
+                                    self.validation_circuit_breaker,
                                 )
-                                .await
+                                .await;
+                                // Don't try to start a forked cell again
+                                if let Err(CellError::SourceChainError(
+                                    SourceChainError::ChainForked(fork),
+                                )) = &cell
+                                {
+                                    error!(msg = "Quarantining cell with a forked chain", ?cell_id);
+                                    let report = IntegrityAuditReport::forked(fork.clone());
+                                    if let Err(e) =
+                                        self.record_quarantine(cell_id.clone(), report).await
+                                    {
+                                        error!(msg = "Failed to quarantine cell", ?cell_id, ?e);
+                                    }
+                                }
+                                cell
                             },
                         );
 
@@ -575,6 +849,8 @@ where
         impl IntoIterator<Item = (EntryDefBufferKey, EntryDef)>,
     )> {
         let environ = &self.wasm_env;
+        // Only export what has reached the disk
+        environ.sync()?;
         let wasm = environ.get_db(&*holochain_state::db::WASM)?;
         let dna_def_db = environ.get_db(&*holochain_state::db::DNA_DEF)?;
         let entry_def_db = environ.get_db(&*holochain_state::db::ENTRY_DEF)?;
@@ -619,6 +895,85 @@ where
         }
     }
 
+    /// Stop running a Cell whose persisted data failed an integrity audit,
+    /// and record it in the ConductorState so it is not started again.
+    /// The Cell's data is left in place so it can be inspected and repaired.
+    pub(super) async fn quarantine_cell(
+        &mut self,
+        cell_id: CellId,
+        report: IntegrityAuditReport,
+    ) -> ConductorResult<()> {
+        self.record_quarantine(cell_id.clone(), report).await?;
+        self.cells.remove(&cell_id);
+        Ok(())
+    }
+
+    /// Record a Cell as quarantined in the ConductorState
+    async fn record_quarantine(
+        &self,
+        cell_id: CellId,
+        report: IntegrityAuditReport,
+    ) -> ConductorResult<()> {
+        self.update_state(move |mut state| {
+            state.quarantined_cells.insert(cell_id, report);
+            Ok(state)
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Resolve the fork in a quarantined Cell's source chain by keeping the
+    /// branch beginning with `keep`, see [SourceChainBuf::resolve_fork_keep].
+    /// If the chain is then found to be intact the Cell is taken out of
+    /// quarantine, ready to be started again.
+    pub(super) async fn resolve_fork_keep(
+        &self,
+        cell_id: &CellId,
+        keep: &HeaderHash,
+    ) -> ConductorApiResult<ForkReport> {
+        let report = self
+            .get_state()
+            .await?
+            .quarantined_cells
+            .remove(cell_id)
+            .ok_or_else(|| ConductorError::CellMissing(cell_id.clone()))?;
+        let env = self.quarantined_cell_env(cell_id)?;
+        let mut source_chain = SourceChainBuf::new(env.clone().into())?;
+        let fork = source_chain.resolve_fork_keep(keep)?;
+        env.guard()
+            .with_commit(|writer| source_chain.flush_to_txn(writer))?;
+
+        // Re-validate the resolved chain
+        let remaining_fork = SourceChainBuf::new(env.clone().into())?.detect_forks()?;
+        if report.corrupt.is_empty() && remaining_fork.is_none() {
+            let id = cell_id.clone();
+            self.update_state(move |mut state| {
+                state.quarantined_cells.remove(&id);
+                Ok(state)
+            })
+            .await?;
+        } else {
+            let report = IntegrityAuditReport {
+                fork: remaining_fork,
+                ..report
+            };
+            self.record_quarantine(cell_id.clone(), report).await?;
+        }
+        Ok(fork)
+    }
+
+    /// Open the environment of a Cell which isn't running because it is
+    /// quarantined
+    fn quarantined_cell_env(&self, cell_id: &CellId) -> ConductorResult<EnvironmentWrite> {
+        Ok(open_cell_env(
+            &std::path::PathBuf::from(self.root_env_dir.clone()),
+            cell_id.clone(),
+            self.keystore.clone(),
+            self.shared_dht_spaces,
+            &self.durability,
+        )?)
+    }
+
     pub(super) fn put_agent_info_signed(
         &self,
         agent_info_signed: kitsune_p2p::agent_store::AgentInfoSigned,
@@ -647,7 +1002,157 @@ where
 
         Ok(p2p_kv
             .as_store_ref()
-            .get(&reader, &(&*kitsune_space, &*kitsune_agent).into())?)
+            .get(&reader, &(&*kitsune_space, &*kitsune_agent).into())?
+            .filter(|info| !agent_info_is_expired(info, p2p_store::now_ms())))
+    }
+
+    /// Keep what we have learned about a peer of a space in its peer metadata.
+    /// Metadata which hasn't changed isn't written again.
+    pub(super) fn put_peer_meta(
+        &self,
+        dna_hash: DnaHash,
+        peer: AgentPubKey,
+        peer_meta: PeerMeta,
+    ) -> ConductorResult<()> {
+        let environ = self.p2p_env.clone();
+        let peer_meta_kv = PeerMetaKv::new(environ.clone().into())?;
+        let key = peer_meta_key(dna_hash, peer);
+        let env = environ.guard();
+        let held = peer_meta_kv.as_store_ref().get(&env.reader()?, &key)?;
+        if held.as_ref() == Some(&peer_meta) {
+            return Ok(());
+        }
+        Ok(env.with_commit(|writer| peer_meta_kv.as_store_ref().put(writer, &key, &peer_meta))?)
+    }
+
+    pub(super) fn get_peer_meta(
+        &self,
+        dna_hash: DnaHash,
+        peer: AgentPubKey,
+    ) -> ConductorResult<Option<PeerMeta>> {
+        let environ = self.p2p_env.clone();
+        let peer_meta_kv = PeerMetaKv::new(environ.clone().into())?;
+        let env = environ.guard();
+        let reader = env.reader()?;
+        Ok(peer_meta_kv
+            .as_store_ref()
+            .get(&reader, &peer_meta_key(dna_hash, peer))?)
+    }
+
+    /// What we have learned about every peer of a space we hold unexpired
+    /// agent info for, or `None` for a peer we know nothing about
+    pub(super) fn get_space_peer_meta(
+        &self,
+        dna_hash: DnaHash,
+    ) -> ConductorResult<Vec<(AgentPubKey, Option<PeerMeta>)>> {
+        let environ = self.p2p_env.clone();
+        let p2p_kv = AgentKv::new(environ.clone().into())?;
+        let peer_meta_kv = PeerMetaKv::new(environ.clone().into())?;
+        let env = environ.guard();
+        let reader = env.reader()?;
+
+        let space = kitsune_p2p::KitsuneSpace(dna_hash.into_inner());
+        let now_ms = p2p_store::now_ms();
+        let agents: Vec<kitsune_p2p::KitsuneAgent> = p2p_kv
+            .as_store_ref()
+            .iter(&reader)?
+            .filter(|(_, info)| {
+                Ok(*info.as_agent_info_ref().as_space_ref() == space
+                    && !agent_info_is_expired(info, now_ms))
+            })
+            .map(|(_, info)| Ok(info.as_agent_info_ref().as_agent_ref().clone()))
+            .collect()?;
+        agents
+            .into_iter()
+            .map(|agent| {
+                let peer_meta = peer_meta_kv
+                    .as_store_ref()
+                    .get(&reader, &(&space, &agent).into())?;
+                Ok((AgentPubKey::from_raw_bytes(agent.0), peer_meta))
+            })
+            .collect()
+    }
+
+    /// Store the agent info a remote agent sent about itself in a space,
+    /// if it is signed by that agent and newer than the info already held.
+    /// Remote agents can't write the info of this conductor's own agents.
+    pub(super) async fn put_remote_agent_info_signed(
+        &self,
+        dna_hash: &DnaHash,
+        from_agent: &AgentPubKey,
+        agent_info_signed: AgentInfoSigned,
+    ) -> ConductorResult<()> {
+        let reject = |reason| {
+            Err(ConductorError::AgentInfoRejected(
+                from_agent.clone(),
+                reason,
+            ))
+        };
+        let agent_info = agent_info_signed.as_agent_info_ref();
+        if self
+            .keystore
+            .list_sign_pub_keys()
+            .await?
+            .contains(from_agent)
+        {
+            return reject(AgentInfoRejection::LocalAgent);
+        }
+        if agent_info.as_agent_ref().0[..] != *from_agent.get_full_bytes() {
+            return reject(AgentInfoRejection::NotSender);
+        }
+        if agent_info.as_space_ref().0[..] != *dna_hash.get_full_bytes() {
+            return reject(AgentInfoRejection::WrongSpace);
+        }
+        let signature = Signature(agent_info_signed.as_signature_ref().0.clone());
+        if !from_agent
+            .verify_signature_raw(&signature, &agent_info_signing_bytes(agent_info)?)
+            .await?
+        {
+            return reject(AgentInfoRejection::BadSignature);
+        }
+        if agent_info_is_expired(&agent_info_signed, p2p_store::now_ms()) {
+            return reject(AgentInfoRejection::Expired);
+        }
+
+        let environ = self.p2p_env.clone();
+        let p2p_kv = AgentKv::new(environ.clone().into())?;
+        let env = environ.guard();
+        let key = (&agent_info_signed).into();
+        let held = p2p_kv.as_store_ref().get(&env.reader()?, &key)?;
+        match held {
+            // The same info sent again is already stored
+            Some(held) if held == agent_info_signed => return Ok(()),
+            Some(held) if held.as_agent_info_ref().signed_at_ms() >= agent_info.signed_at_ms() => {
+                return reject(AgentInfoRejection::NotNewer);
+            }
+            _ => (),
+        }
+        Ok(env.with_commit(|writer| p2p_kv.as_store_ref().put(writer, &key, &agent_info_signed))?)
+    }
+
+    /// Delete every expired agent info from the p2p store,
+    /// returning how many were deleted
+    pub(super) fn prune_expired_agent_infos(&self, now_ms: u64) -> ConductorResult<usize> {
+        let environ = self.p2p_env.clone();
+
+        let p2p_kv = AgentKv::new(environ.clone().into())?;
+        let env = environ.guard();
+        let expired: Vec<AgentInfoSigned> = {
+            let reader = env.reader()?;
+            p2p_kv
+                .as_store_ref()
+                .iter(&reader)?
+                .filter(|(_, info)| Ok(agent_info_is_expired(info, now_ms)))
+                .map(|(_, info)| Ok(info))
+                .collect()?
+        };
+        env.with_commit(|writer| -> DatabaseResult<()> {
+            for info in expired.iter() {
+                p2p_kv.as_store_ref().delete(writer, &info.into())?;
+            }
+            Ok(())
+        })?;
+        Ok(expired.len())
     }
 
     pub(super) async fn put_wasm(
@@ -692,6 +1197,73 @@ where
         Ok(zome_defs)
     }
 
+    /// Rebuild an installed Dna from the DnaDef and wasm stores, as the content
+    /// of a dna file which can be installed on another conductor.
+    /// Every wasm and the rebuilt Dna are hashed again,
+    /// so a corrupt store is never exported.
+    pub(super) async fn export_dna(&self, dna_hash: &DnaHash) -> ConductorResult<DnaFileBundle> {
+        let environ = &self.wasm_env;
+        let wasm = environ.get_db(&*holochain_state::db::WASM)?;
+        let dna_def_db = environ.get_db(&*holochain_state::db::DNA_DEF)?;
+        let wasm_buf = WasmBuf::new(environ.clone().into(), wasm)?;
+        let dna_def_buf = DnaDefBuf::new(environ.clone().into(), dna_def_db)?;
+
+        let dna_def = dna_def_buf
+            .get(dna_hash)
+            .await?
+            .ok_or_else(|| ConductorError::DnaMissing(dna_hash.clone()))?
+            .into_content();
+        let mut wasms = Vec::with_capacity(dna_def.zomes.len());
+        for (_, zome) in dna_def.zomes.iter() {
+            let wasm = wasm_buf
+                .get(&zome.wasm_hash)
+                .await?
+                .ok_or(ConductorError::WasmMissing)?
+                .into_content();
+            let found = WasmHash::with_data(&wasm).await;
+            if found != zome.wasm_hash {
+                return Err(ConductorError::WasmHashMismatch {
+                    expected: zome.wasm_hash.clone(),
+                    found,
+                });
+            }
+            wasms.push(wasm);
+        }
+        let dna_file = DnaFile::new(dna_def, wasms).await?;
+        if dna_file.dna_hash() != dna_hash {
+            return Err(ConductorError::DnaHashMismatch {
+                expected: dna_hash.clone(),
+                found: dna_file.dna_hash().clone(),
+            });
+        }
+        Ok(DnaFileBundle::new(&dna_file).await?)
+    }
+
+    /// List the entry defs declared by the zomes of an installed Dna
+    pub(super) async fn list_entry_defs(
+        &self,
+        dna_hash: &DnaHash,
+    ) -> ConductorResult<Vec<(EntryDefBufferKey, EntryDef)>> {
+        let dna = self
+            .dna_store
+            .get(dna_hash)
+            .ok_or_else(|| ConductorError::DnaMissing(dna_hash.clone()))?;
+        let zomes: Vec<_> = dna.dna().zomes.iter().map(|(_, zome)| zome).collect();
+
+        let environ = &self.wasm_env;
+        let entry_def_db = environ.get_db(&*holochain_state::db::ENTRY_DEF)?;
+        let entry_def_buf = EntryDefBuf::new(environ.clone().into(), entry_def_db)?;
+        let mut defs = fresh_reader!(environ, |r| entry_def_buf
+            .get_all(&r)?
+            .filter(|(key, _)| Ok(zomes.contains(&key.zome())))
+            .collect::<Vec<_>>())?;
+        defs.sort_by_key(|(key, _)| {
+            let zome_index = zomes.iter().position(|zome| *zome == key.zome());
+            (zome_index, key.entry_def_position())
+        });
+        Ok(defs)
+    }
+
     pub(super) async fn list_cell_ids(&self) -> ConductorResult<Vec<CellId>> {
         Ok(self.cells.keys().cloned().collect())
     }
@@ -701,11 +1273,123 @@ where
         Ok(active_apps.keys().cloned().collect())
     }
 
-    pub(super) async fn dump_cell_state(&self, cell_id: &CellId) -> ConductorApiResult<String> {
+    /// Set a piece of metadata on an installed app, overwriting any
+    /// existing value for the key
+    pub(super) async fn set_app_metadata(
+        &self,
+        app_id: &AppId,
+        key: &str,
+        value: SerializedBytes,
+    ) -> ConductorResult<()> {
+        self.check_running()?;
+        if self.get_state().await?.get_app_info(app_id).is_none() {
+            return Err(ConductorError::AppNotInstalled);
+        }
+        let key = AppMetadataKey::new(app_id, key);
+        self.env
+            .guard()
+            .with_commit(|writer| self.app_metadata_db.put(writer, &key, &value))?;
+        Ok(())
+    }
+
+    pub(super) async fn get_app_metadata(
+        &self,
+        app_id: &AppId,
+        key: &str,
+    ) -> ConductorResult<Option<SerializedBytes>> {
+        let key = AppMetadataKey::new(app_id, key);
+        let guard = self.env.guard();
+        let reader = guard.reader()?;
+        Ok(self.app_metadata_db.get(&reader, &key)?)
+    }
+
+    /// Remove a piece of metadata from an app. Removing a key which
+    /// isn't set is not an error.
+    pub(super) async fn delete_app_metadata(
+        &self,
+        app_id: &AppId,
+        key: &str,
+    ) -> ConductorResult<()> {
+        self.check_running()?;
+        let key = AppMetadataKey::new(app_id, key);
+        self.env.guard().with_commit(|writer| {
+            if self.app_metadata_db.get(writer, &key)?.is_some() {
+                self.app_metadata_db.delete(writer, &key)?;
+            }
+            Result::<_, ConductorError>::Ok(())
+        })?;
+        Ok(())
+    }
+
+    pub(super) fn get_cell_status(&self, cell_id: &CellId) -> ConductorApiResult<CellStatus> {
         let cell = self.cell_by_id(cell_id)?;
-        let arc = cell.env();
+        let source_chain = SourceChainBuf::new(cell.env().clone().into())?;
+        Ok(CellStatus {
+            cell_id: cell_id.clone(),
+            estimated_disk_size: source_chain.estimated_disk_size()?,
+            last_call_peak_scratch_size: cell.last_call_peak_scratch_size(),
+            last_call_resources: cell.last_call_resources(),
+            validation_breaker: cell.validation_breaker_status(),
+        })
+    }
+
+    pub(super) fn get_cell_gossip_metrics(
+        &self,
+        cell_id: &CellId,
+    ) -> ConductorApiResult<CellGossipMetrics> {
+        Ok(self.cell_by_id(cell_id)?.gossip_metrics())
+    }
+
+    pub(super) fn force_validation_circuit(
+        &self,
+        cell_id: &CellId,
+        open: bool,
+    ) -> ConductorApiResult<ValidationBreakerStatus> {
+        Ok(self.cell_by_id(cell_id)?.force_validation_circuit(open))
+    }
+
+    pub(super) fn metrics(&self) -> ConductorApiResult<ConductorMetrics> {
+        let cells = self
+            .cells
+            .keys()
+            .map(|cell_id| self.get_cell_status(cell_id))
+            .collect::<ConductorApiResult<_>>()?;
+        let zome_fns = ZomeCallMetrics::default();
+        for cell in self.cells.values() {
+            zome_fns.merge(cell.cell.zome_call_metrics());
+        }
+        Ok(ConductorMetrics {
+            cells,
+            zome_fns: zome_fns.snapshot(),
+            validation_queue_depth: self.validation_context.queue_depth(),
+        })
+    }
+
+    pub(super) async fn dump_cell_state(&self, cell_id: &CellId) -> ConductorApiResult<String> {
+        // Quarantined cells can be dumped too, so they can be repaired
+        let quarantine = self.get_state().await?.quarantined_cells.remove(cell_id);
+        // Only a running cell has queue consumers which can be backing off
+        let (arc, workflow_backoff) = match (self.cell_by_id(cell_id), &quarantine) {
+            (Ok(cell), _) => (cell.env().clone(), cell.triggers().workflow_backoffs()),
+            (Err(_), Some(_)) => (self.quarantined_cell_env(cell_id)?, Vec::new()),
+            (Err(e), None) => return Err(e.into()),
+        };
+        // Only dump what has reached the disk
+        arc.sync()?;
         let source_chain = SourceChainBuf::new(arc.clone().into())?;
-        Ok(source_chain.dump_as_json().await?)
+        let fork = source_chain.detect_forks()?;
+        let source_chain: serde_json::Value =
+            serde_json::from_str(&source_chain.dump_as_json().await?)
+                .map_err(SourceChainError::from)?;
+        let workflow_errors = workflow_errors::get_workflow_errors(&arc.clone().into())?;
+        let dump = serde_json::json!({
+            "source_chain": source_chain,
+            "fork": fork,
+            "quarantine": quarantine,
+            "workflow_errors": workflow_errors,
+            "workflow_backoff": workflow_backoff,
+        });
+        Ok(serde_json::to_string_pretty(&dump).map_err(SourceChainError::from)?)
     }
 
     #[cfg(test)]
@@ -732,6 +1416,7 @@ where
         holochain_p2p: holochain_p2p::HolochainP2pRef,
     ) -> ConductorResult<Self> {
         let db: SingleStore = env.get_db(&db::CONDUCTOR_STATE)?;
+        let app_metadata_db: SingleStore = env.get_db(&db::APP_METADATA)?;
         let (task_tx, task_manager_run_handle) = spawn_task_manager();
         let task_manager_run_handle = Some(task_manager_run_handle);
         let (stop_tx, _) = tokio::sync::broadcast::channel::<()>(1);
@@ -740,20 +1425,39 @@ where
             wasm_env,
             p2p_env,
             state_db: KvStore::new(db),
+            app_metadata_db: KvStore::new(app_metadata_db),
             cells: HashMap::new(),
             shutting_down: false,
-            app_interface_signal_broadcasters: HashMap::new(),
+            app_interface_signal_queues: HashMap::new(),
+            signal_queue_depth: DEFAULT_SIGNAL_QUEUE_DEPTH,
+            app_interface_signal_apps: HashMap::new(),
             managed_task_add_sender: task_tx,
             managed_task_stop_broadcaster: stop_tx,
             task_manager_run_handle,
             admin_websocket_ports: Vec::new(),
+            app_interface_ports: HashMap::new(),
             dna_store,
             keystore,
             root_env_dir,
             holochain_p2p,
+            shared_dht_spaces: false,
+            durability: DurabilityConfig::default(),
+            queue_backoff: QueueBackoffConfig::default(),
+            receipt_flush_interval: DEFAULT_RECEIPT_FLUSH_INTERVAL,
+            scratch_size_limit: DEFAULT_SCRATCH_SIZE_LIMIT,
+            validation_context: ExecutionContext::default(),
+            validation_circuit_breaker: ValidationCircuitBreakerConfig::default(),
+            genesis_self_check: true,
+            state_migrations: StateMigrations::default(),
         })
     }
 
+    /// Apply the registered migrations from one schema version to a later one
+    pub(super) fn migrate_state(&self, from_version: u32, to_version: u32) -> ConductorResult<()> {
+        self.state_migrations
+            .apply(&self.env, from_version, to_version)
+    }
+
     pub(super) async fn get_state(&self) -> ConductorResult<ConductorState> {
         let guard = self.env.guard();
         let reader = guard.reader()?;
@@ -775,8 +1479,8 @@ where
         Ok(new_state)
     }
 
-    fn add_admin_port(&mut self, port: u16) {
-        self.admin_websocket_ports.push(port);
+    fn add_admin_port(&mut self, port: u16, connections: ConnectionCounter) {
+        self.admin_websocket_ports.push((port, connections));
     }
 
     /// Sends a JoinHandle to the TaskManager task to be managed
@@ -791,6 +1495,9 @@ where
 /// The database used to store ConductorState. It has only one key-value pair.
 pub type ConductorStateDb = KvStore<UnitDbKey, ConductorState>;
 
+/// The database used to store metadata annotations on installed apps
+pub type AppMetadataDb = KvStore<AppMetadataKey, SerializedBytes>;
+
 mod builder {
 
     use super::*;
@@ -803,6 +1510,7 @@ mod builder {
         config: ConductorConfig,
         dna_store: DS,
         keystore: Option<KeystoreSender>,
+        migrations: StateMigrations,
         #[cfg(test)]
         state: Option<ConductorState>,
         #[cfg(test)]
@@ -866,27 +1574,45 @@ mod builder {
                 spawn_lair_keystore(self.config.keystore_path.as_deref()).await?
             };
             let env_path = self.config.environment_path.clone();
+            let durability = &self.config.durability;
+            durability.validate()?;
 
-            let environment = EnvironmentWrite::new(
+            let environment = EnvironmentWrite::new_with_durability(
                 env_path.as_ref(),
                 EnvironmentKind::Conductor,
                 keystore.clone(),
+                durability.conductor,
             )?;
 
-            let wasm_environment =
-                EnvironmentWrite::new(env_path.as_ref(), EnvironmentKind::Wasm, keystore.clone())?;
+            let wasm_environment = EnvironmentWrite::new_with_durability(
+                env_path.as_ref(),
+                EnvironmentKind::Wasm,
+                keystore.clone(),
+                durability.wasm,
+            )?;
 
-            let p2p_environment =
-                EnvironmentWrite::new(env_path.as_ref(), EnvironmentKind::P2P, keystore.clone())?;
+            let p2p_environment = EnvironmentWrite::new_with_durability(
+                env_path.as_ref(),
+                EnvironmentKind::P2P,
+                keystore.clone(),
+                durability.p2p,
+            )?;
 
             #[cfg(test)]
             let state = self.state;
 
             let Self {
-                dna_store, config, ..
+                dna_store,
+                config,
+                migrations,
+                ..
             } = self;
 
-            let (holochain_p2p, p2p_evt) = holochain_p2p::spawn_holochain_p2p().await?;
+            let (holochain_p2p, p2p_evt) = holochain_p2p::spawn_holochain_p2p_with_compression(
+                config.kitsune_p2p.clone(),
+                config.wire_compression,
+            )
+            .await?;
 
             let conductor = Conductor::new(
                 environment,
@@ -902,23 +1628,60 @@ mod builder {
             #[cfg(test)]
             let conductor = Self::update_fake_state(state, conductor).await?;
 
-            Self::finish(conductor, config, p2p_evt).await
+            Self::finish(conductor, config, migrations, p2p_evt).await
         }
 
         async fn finish(
-            conductor: Conductor<DS>,
+            mut conductor: Conductor<DS>,
             conductor_config: ConductorConfig,
+            migrations: StateMigrations,
             p2p_evt: holochain_p2p::event::HolochainP2pEventReceiver,
         ) -> ConductorResult<ConductorHandle> {
+            conductor_config.durability.validate()?;
+            conductor_config.queue_backoff.validate()?;
+            conductor_config.clock_skew.validate()?;
+            conductor_config.validation_circuit_breaker.validate()?;
+            conductor.shared_dht_spaces = conductor_config.shared_dht_spaces;
+            conductor.durability = conductor_config.durability.clone();
+            if let Some(depth) = conductor_config.signal_queue_depth {
+                conductor.signal_queue_depth = depth;
+            }
+            conductor.queue_backoff = conductor_config.queue_backoff.clone();
+            if let Some(interval_ms) = conductor_config.receipt_flush_interval_ms {
+                conductor.receipt_flush_interval = std::time::Duration::from_millis(interval_ms);
+            }
+            if let Some(limit) = conductor_config.scratch_size_limit {
+                conductor.scratch_size_limit = limit;
+            }
+            conductor.validation_context = ExecutionContext::validation(
+                conductor_config
+                    .validation_threads
+                    .unwrap_or_else(default_validation_threads),
+                conductor_config
+                    .validation_queue_depth
+                    .unwrap_or(DEFAULT_VALIDATION_QUEUE_DEPTH),
+            );
+            conductor.validation_circuit_breaker = conductor_config.validation_circuit_breaker;
+            conductor.genesis_self_check = !conductor_config.skip_genesis_self_check;
+            conductor.state_migrations = migrations;
+
             // Get data before handle
             let keystore = conductor.keystore.clone();
             let holochain_p2p = conductor.holochain_p2p.clone();
+            let wasm_module_cache = WasmModuleCache::new(conductor.wasm_env.clone())
+                .with_metering(conductor_config.wasm_metering);
+            let clock_skew = ClockSkew::new(conductor_config.clock_skew);
+            let mut task_tx = conductor.managed_task_add_sender.clone();
+            let stop_rx = conductor.managed_task_stop_broadcaster.subscribe();
+            let sync_stop_rx = conductor.managed_task_stop_broadcaster.subscribe();
 
             // Create handle
             let handle: ConductorHandle = Arc::new(ConductorHandleImpl {
                 conductor: RwLock::new(conductor),
                 keystore,
                 holochain_p2p,
+                wasm_module_cache,
+                clock_skew,
             });
 
             handle.add_dnas().await?;
@@ -938,6 +1701,29 @@ mod builder {
                 handle.clone().add_admin_interfaces(configs).await?;
             }
 
+            // Periodically audit the integrity of all cells, if configured
+            if let Some(secs) = conductor_config.integrity_audit_interval_secs {
+                let interval = std::time::Duration::from_secs(secs);
+                let task = tokio::spawn(integrity_audit_task(handle.clone(), interval, stop_rx));
+                task_tx
+                    .send(ManagedTaskAdd::dont_handle(task))
+                    .await
+                    .map_err(|e| ConductorError::SubmitTaskError(format!("{}", e)))?;
+            }
+
+            // Periodically sync the environments which don't sync on commit
+            if let Some(interval) = conductor_config.durability.sync_interval() {
+                let sync_handle = handle.clone();
+                let task = tokio::spawn(periodic_sync_task(interval, sync_stop_rx, move || {
+                    let handle = sync_handle.clone();
+                    async move { handle.sync_all().await }
+                }));
+                task_tx
+                    .send(ManagedTaskAdd::dont_handle(task))
+                    .await
+                    .map_err(|e| ConductorError::SubmitTaskError(format!("{}", e)))?;
+            }
+
             tokio::task::spawn(p2p_event_task(p2p_evt, handle.clone()));
 
             Ok(handle)
@@ -950,6 +1736,13 @@ mod builder {
             self
         }
 
+        /// Register a migration of the Conductor environment, to be applied by
+        /// [ConductorHandleT::migrate_conductor_state](crate::conductor::handle::ConductorHandleT::migrate_conductor_state)
+        pub fn with_migration<M: StateMigration>(mut self) -> Self {
+            self.migrations.register::<M>();
+            self
+        }
+
         #[cfg(test)]
         /// Sets some fake conductor state for tests
         pub fn fake_state(mut self, state: ConductorState) -> Self {
@@ -988,7 +1781,11 @@ mod builder {
                 tmpdir,
             } = test_env;
             let keystore = environment.keystore();
-            let (holochain_p2p, p2p_evt) = holochain_p2p::spawn_holochain_p2p().await?;
+            let (holochain_p2p, p2p_evt) = holochain_p2p::spawn_holochain_p2p_with_compression(
+                self.config.kitsune_p2p.clone(),
+                self.config.wire_compression,
+            )
+            .await?;
             let conductor = Conductor::new(
                 environment,
                 test_wasm_env,
@@ -1003,11 +1800,50 @@ mod builder {
             #[cfg(test)]
             let conductor = Self::update_fake_state(self.state, conductor).await?;
 
-            Self::finish(conductor, self.config, p2p_evt).await
+            Self::finish(conductor, self.config, self.migrations, p2p_evt).await
         }
     }
 }
 
+/// Open the environment of a Cell. In shared DHT mode, the DHT environment
+/// of the Cell's DNA is attached, so that all Cells of the DNA read and write
+/// their integrated and cached data in the same place.
+/// The key a peer's metadata is kept under in a space
+fn peer_meta_key(dna_hash: DnaHash, peer: AgentPubKey) -> AgentKvKey {
+    (
+        &kitsune_p2p::KitsuneSpace(dna_hash.into_inner()),
+        &kitsune_p2p::KitsuneAgent(peer.into_inner()),
+    )
+        .into()
+}
+
+fn open_cell_env(
+    root_env_dir: &std::path::Path,
+    cell_id: CellId,
+    keystore: KeystoreSender,
+    shared_dht_spaces: bool,
+    durability: &DurabilityConfig,
+) -> DatabaseResult<EnvironmentWrite> {
+    let dna_hash = cell_id.dna_hash().clone();
+    let env = EnvironmentWrite::new_with_durability(
+        root_env_dir,
+        EnvironmentKind::Cell(cell_id),
+        keystore.clone(),
+        durability.cell,
+    )?;
+    if shared_dht_spaces {
+        let dht_env = EnvironmentWrite::new_with_durability(
+            root_env_dir,
+            EnvironmentKind::Dht(dna_hash),
+            keystore,
+            durability.dht,
+        )?;
+        Ok(env.with_dht_env(dht_env))
+    } else {
+        Ok(env)
+    }
+}
+
 async fn p2p_event_task(
     mut p2p_evt: holochain_p2p::event::HolochainP2pEventReceiver,
     handle: ConductorHandle,
@@ -1025,20 +1861,77 @@ async fn p2p_event_task(
     tracing::warn!("p2p_event_task has ended");
 }
 
-#[cfg(test)]
-pub mod tests {
-    use super::*;
-    use super::{Conductor, ConductorState};
-    use crate::conductor::dna_store::MockDnaStore;
-    use holochain_state::test_utils::{
-        test_conductor_env, test_p2p_env, test_wasm_env, TestEnvironment,
-    };
-    use holochain_types::test_utils::fake_cell_id;
-
-    #[tokio::test(threaded_scheduler)]
-    async fn can_update_state() {
-        let TestEnvironment {
-            env: environment,
+/// Sync environments on a fixed interval until the conductor shuts down,
+/// for environments which don't sync on every commit.
+async fn periodic_sync_task<F, Fut>(
+    interval: std::time::Duration,
+    mut stop_rx: StopReceiver,
+    sync: F,
+) -> ManagedTaskResult
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = ConductorResult<Vec<EnvironmentSyncReport>>>,
+{
+    loop {
+        tokio::select! {
+            _ = stop_rx.recv() => break,
+            _ = tokio::time::delay_for(interval) => (),
+        }
+        if let Err(e) = sync().await {
+            tracing::error!(message = "error syncing environments", error = ?e);
+        }
+    }
+    Ok(())
+}
+
+/// Audit every running cell on a fixed interval until the conductor shuts down.
+/// Cells with corrupt data are quarantined by the handle as they are found.
+async fn integrity_audit_task(
+    handle: ConductorHandle,
+    interval: std::time::Duration,
+    mut stop_rx: StopReceiver,
+) -> ManagedTaskResult {
+    loop {
+        tokio::select! {
+            _ = stop_rx.recv() => break,
+            _ = tokio::time::delay_for(interval) => (),
+        }
+        for cell_id in handle.list_cell_ids().await? {
+            if let Err(e) = handle.audit_cell(&cell_id).await {
+                tracing::error!(
+                    message = "error auditing cell integrity",
+                    ?cell_id,
+                    error = ?e,
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use super::{Conductor, ConductorState};
+    use crate::conductor::config::{ConductorConfig, Durability};
+    use crate::conductor::dna_store::MockDnaStore;
+    use holochain_serialized_bytes::SerializedBytes;
+    use holochain_state::buffer::CasBufFreshAsync;
+    use holochain_state::test_utils::{
+        test_conductor_env, test_p2p_env, test_wasm_env, TestEnvironment,
+    };
+    use holochain_types::dna::wasm::DnaWasm;
+    use holochain_types::dna::{DnaDef, DnaDefHashed, JsonProperties};
+    use holochain_types::test_utils::{fake_cell_id, fake_dna_file, fake_dna_zomes};
+    use holochain_wasm_test_utils::TestWasm;
+    use holochain_zome_types::zome::ZomeName;
+    use matches::assert_matches;
+    use std::convert::TryFrom;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn can_update_state() {
+        let TestEnvironment {
+            env: environment,
             tmpdir,
         } = test_conductor_env();
         let TestEnvironment {
@@ -1051,7 +1944,9 @@ pub mod tests {
         } = test_p2p_env();
         let dna_store = MockDnaStore::new();
         let keystore = environment.keystore().clone();
-        let (holochain_p2p, _p2p_evt) = holochain_p2p::spawn_holochain_p2p().await.unwrap();
+        let (holochain_p2p, _p2p_evt) = holochain_p2p::spawn_holochain_p2p(Default::default())
+            .await
+            .unwrap();
         let conductor = Conductor::new(
             environment,
             wasm_env,
@@ -1109,4 +2004,644 @@ pub mod tests {
             .unwrap();
         assert_eq!(state, conductor.get_state_from_handle().await.unwrap());
     }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn can_get_dna_properties() {
+        let test_env = test_conductor_env();
+        let _tmpdir = test_env.tmpdir.clone();
+        let TestEnvironment {
+            env: wasm_env,
+            tmpdir: _tmpdir,
+        } = test_wasm_env();
+        let TestEnvironment {
+            env: p2p_env,
+            tmpdir: _p2p_env,
+        } = test_p2p_env();
+        let handle = ConductorBuilder::new()
+            .test(test_env, wasm_env, p2p_env)
+            .await
+            .unwrap();
+        let dna = fake_dna_file("properties");
+        let other = fake_dna_file("not installed");
+        handle.install_dna(dna.clone()).await.unwrap();
+
+        assert_eq!(
+            handle.get_dna_properties(dna.dna_hash()).await.unwrap(),
+            Some(dna.dna().properties.clone())
+        );
+        assert_eq!(
+            handle.get_dna_properties(other.dna_hash()).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn can_compare_dnas() {
+        let test_env = test_conductor_env();
+        let _tmpdir = test_env.tmpdir.clone();
+        let TestEnvironment {
+            env: wasm_env,
+            tmpdir: _tmpdir,
+        } = test_wasm_env();
+        let TestEnvironment {
+            env: p2p_env,
+            tmpdir: _p2p_env,
+        } = test_p2p_env();
+        let handle = ConductorBuilder::new()
+            .test(test_env, wasm_env, p2p_env)
+            .await
+            .unwrap();
+        let a = fake_dna_zomes(
+            "compare",
+            vec![
+                ("one".into(), vec![1].into()),
+                ("two".into(), vec![2].into()),
+            ],
+        );
+        let b = fake_dna_zomes(
+            "compare",
+            vec![
+                ("one".into(), vec![1].into()),
+                ("two".into(), vec![3].into()),
+                ("three".into(), vec![4].into()),
+            ],
+        )
+        .with_properties(
+            SerializedBytes::try_from(JsonProperties::new(serde_json::json!({"p": "bye"})))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+        handle.install_dna(a.clone()).await.unwrap();
+        handle.install_dna(b.clone()).await.unwrap();
+
+        let diff = handle
+            .compare_dnas(a.dna_hash(), b.dna_hash())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!diff.is_empty());
+        assert!(diff.properties_changed);
+        assert!(!diff.uuid_changed);
+        assert_eq!(diff.zomes_added, vec![ZomeName::from("three")]);
+        assert!(diff.zomes_removed.is_empty());
+        assert_eq!(diff.wasms_changed, vec![ZomeName::from("two")]);
+        assert!(!diff.zome_order_changed);
+
+        let same = handle
+            .compare_dnas(a.dna_hash(), a.dna_hash())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(same.is_empty());
+
+        let other = fake_dna_file("not installed");
+        assert_eq!(
+            handle
+                .compare_dnas(a.dna_hash(), other.dna_hash())
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn can_install_dna_from_path() {
+        let test_env = test_conductor_env();
+        let _tmpdir = test_env.tmpdir.clone();
+        let TestEnvironment {
+            env: wasm_env,
+            tmpdir: _tmpdir,
+        } = test_wasm_env();
+        let TestEnvironment {
+            env: p2p_env,
+            tmpdir: _p2p_env,
+        } = test_p2p_env();
+        let handle = ConductorBuilder::new()
+            .test(test_env, wasm_env, p2p_env)
+            .await
+            .unwrap();
+        let dna = fake_dna_file("from path");
+        let dir = tempdir::TempDir::new("install_dna_from_path").unwrap();
+        let path = dir.path().join("test.dna.gz");
+        tokio::fs::write(&path, dna.to_file_content().await.unwrap())
+            .await
+            .unwrap();
+
+        let dna_hash = handle.install_dna_from_path(path).await.unwrap();
+        assert_eq!(&dna_hash, dna.dna_hash());
+        assert_eq!(handle.get_dna(&dna_hash).await, Some(dna));
+
+        let bad_path = dir.path().join("bad.dna.gz");
+        tokio::fs::write(&bad_path, b"not a dna").await.unwrap();
+        assert_matches!(
+            handle.install_dna_from_path(bad_path.clone()).await,
+            Err(ConductorError::DnaDecodeError { path, .. }) if path == bad_path
+        );
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn export_dna_rejects_a_corrupt_store() {
+        let test_env = test_conductor_env();
+        let _tmpdir = test_env.tmpdir.clone();
+        let TestEnvironment {
+            env: wasm_env,
+            tmpdir: _tmpdir,
+        } = test_wasm_env();
+        let TestEnvironment {
+            env: p2p_env,
+            tmpdir: _p2p_env,
+        } = test_p2p_env();
+        let handle = ConductorBuilder::new()
+            .test(test_env, wasm_env.clone(), p2p_env)
+            .await
+            .unwrap();
+        let dna = fake_dna_zomes("export", vec![(TestWasm::Foo.into(), TestWasm::Foo.into())]);
+        handle.install_dna(dna.clone()).await.unwrap();
+        let bundle = handle.export_dna(dna.dna_hash()).await.unwrap();
+        assert_eq!(&bundle.dna_hash, dna.dna_hash());
+
+        // A DnaDef stored under the wrong hash
+        let wrong_hash = fake_dna_file("wrong hash").dna_hash().clone();
+        let mut dna_defs: CasBufFreshAsync<DnaDef> = CasBufFreshAsync::new(
+            wasm_env.clone().into(),
+            wasm_env.get_db(&*db::DNA_DEF).unwrap(),
+        );
+        dna_defs.put(DnaDefHashed::with_pre_hashed(
+            dna.dna().clone(),
+            wrong_hash.clone(),
+        ));
+        {
+            let env = wasm_env.guard();
+            env.with_commit(|writer| dna_defs.flush_to_txn(writer))
+                .unwrap();
+        }
+        assert_matches!(
+            handle.export_dna(&wrong_hash).await,
+            Err(ConductorError::DnaHashMismatch { expected, found })
+                if expected == wrong_hash && &found == dna.dna_hash()
+        );
+
+        // A wasm whose bytes no longer match its hash
+        let (wasm_hash, _) = dna.code().iter().next().unwrap();
+        let mut wasm_buf = WasmBuf::new(
+            wasm_env.clone().into(),
+            wasm_env.get_db(&*db::WASM).unwrap(),
+        )
+        .unwrap();
+        wasm_buf.put(DnaWasmHashed::with_pre_hashed(
+            DnaWasm::from(vec![0, 1, 2]),
+            wasm_hash.clone(),
+        ));
+        {
+            let env = wasm_env.guard();
+            env.with_commit(|writer| wasm_buf.flush_to_txn(writer))
+                .unwrap();
+        }
+        assert_matches!(
+            handle.export_dna(dna.dna_hash()).await,
+            Err(ConductorError::WasmHashMismatch { expected, .. }) if &expected == wasm_hash
+        );
+
+        handle.shutdown().await;
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn sync_all_syncs_every_environment() {
+        let test_env = test_conductor_env();
+        let _tmpdir = test_env.tmpdir.clone();
+        let TestEnvironment {
+            env: wasm_env,
+            tmpdir: _tmpdir,
+        } = test_wasm_env();
+        let TestEnvironment {
+            env: p2p_env,
+            tmpdir: _p2p_env,
+        } = test_p2p_env();
+        let mut expected = vec![
+            test_env.env.path().clone(),
+            wasm_env.path().clone(),
+            p2p_env.path().clone(),
+        ];
+        let handle = ConductorBuilder::new()
+            .test(test_env, wasm_env, p2p_env)
+            .await
+            .unwrap();
+
+        let mut synced: Vec<_> = handle
+            .sync_all()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|report| report.path)
+            .collect();
+        synced.sort();
+        expected.sort();
+        assert_eq!(synced, expected);
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn conductor_rejects_lossy_cell_envs() {
+        let test_env = test_conductor_env();
+        let _tmpdir = test_env.tmpdir.clone();
+        let TestEnvironment {
+            env: wasm_env,
+            tmpdir: _tmpdir,
+        } = test_wasm_env();
+        let TestEnvironment {
+            env: p2p_env,
+            tmpdir: _p2p_env,
+        } = test_p2p_env();
+        let config = ConductorConfig {
+            durability: DurabilityConfig {
+                cell: Some(Durability::NoSyncPeriodic { interval_ms: 100 }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let result = ConductorBuilder::new()
+            .config(config)
+            .test(test_env, wasm_env, p2p_env)
+            .await;
+        assert!(matches!(result, Err(ConductorError::ConfigError(_))));
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn periodic_sync_task_syncs_until_stopped() {
+        let count = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let (stop_tx, stop_rx) = tokio::sync::broadcast::channel(1);
+        let task = {
+            let count = count.clone();
+            tokio::spawn(periodic_sync_task(
+                std::time::Duration::from_millis(10),
+                stop_rx,
+                move || {
+                    count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    async { Ok(vec![]) }
+                },
+            ))
+        };
+        tokio::time::delay_for(std::time::Duration::from_millis(100)).await;
+        stop_tx.send(()).unwrap();
+        task.await.unwrap().unwrap();
+
+        let synced = count.load(std::sync::atomic::Ordering::SeqCst);
+        assert!(synced >= 2);
+        tokio::time::delay_for(std::time::Duration::from_millis(50)).await;
+        assert_eq!(count.load(std::sync::atomic::Ordering::SeqCst), synced);
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn expired_agent_infos_are_hidden_and_pruned() {
+        use fixt::prelude::*;
+        use holochain_p2p::kitsune_p2p::{agent_store::AgentInfo, fixt::AgentInfoSignedFixturator};
+
+        let TestEnvironment {
+            env: environment,
+            tmpdir,
+        } = test_conductor_env();
+        let TestEnvironment {
+            env: wasm_env,
+            tmpdir: _tmpdir,
+        } = test_wasm_env();
+        let TestEnvironment {
+            env: p2p_env,
+            tmpdir: _p2p_tmpdir,
+        } = test_p2p_env();
+        let keystore = environment.keystore().clone();
+        let (holochain_p2p, _p2p_evt) = holochain_p2p::spawn_holochain_p2p(Default::default())
+            .await
+            .unwrap();
+        let conductor = Conductor::new(
+            environment,
+            wasm_env,
+            p2p_env,
+            MockDnaStore::new(),
+            keystore,
+            tmpdir.path().to_path_buf().into(),
+            holochain_p2p,
+        )
+        .await
+        .unwrap();
+
+        let signed_at = |signed_at_ms| {
+            let info = fixt!(AgentInfoSigned);
+            AgentInfoSigned::try_new(
+                info.as_signature_ref().clone(),
+                AgentInfo::new(
+                    info.as_agent_info_ref().as_space_ref().clone(),
+                    info.as_agent_info_ref().as_agent_ref().clone(),
+                    vec![],
+                    signed_at_ms,
+                ),
+            )
+            .unwrap()
+        };
+        let expired = signed_at(0);
+        let fresh = signed_at(p2p_store::now_ms());
+        let get = |info: &AgentInfoSigned| {
+            conductor
+                .get_agent_info_signed(
+                    Arc::new(info.as_agent_info_ref().as_space_ref().clone()),
+                    Arc::new(info.as_agent_info_ref().as_agent_ref().clone()),
+                )
+                .unwrap()
+        };
+        conductor.put_agent_info_signed(expired.clone()).unwrap();
+        conductor.put_agent_info_signed(fresh.clone()).unwrap();
+
+        assert_eq!(get(&expired), None);
+        assert_eq!(get(&fresh), Some(fresh.clone()));
+
+        assert_eq!(
+            conductor
+                .prune_expired_agent_infos(p2p_store::now_ms())
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            conductor
+                .prune_expired_agent_infos(p2p_store::now_ms())
+                .unwrap(),
+            0
+        );
+        assert_eq!(get(&fresh), Some(fresh));
+    }
+
+    /// An agent's info signed by that agent for a space
+    async fn signed_agent_info(
+        keystore: &KeystoreSender,
+        agent: &AgentPubKey,
+        dna_hash: &DnaHash,
+        signed_at_ms: u64,
+    ) -> AgentInfoSigned {
+        use holochain_p2p::kitsune_p2p::{
+            agent_store::AgentInfo, KitsuneAgent, KitsuneSignature, KitsuneSpace,
+        };
+        let agent_info = AgentInfo::new(
+            KitsuneSpace(dna_hash.clone().into_inner()),
+            KitsuneAgent(agent.clone().into_inner()),
+            vec![],
+            signed_at_ms,
+        );
+        let signature = agent
+            .sign_raw(keystore, &agent_info_signing_bytes(&agent_info).unwrap())
+            .await
+            .unwrap();
+        AgentInfoSigned::try_new(KitsuneSignature(signature.0), agent_info).unwrap()
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn remote_agent_infos_are_checked_before_they_are_stored() {
+        use fixt::prelude::*;
+        use holo_hash::fixt::DnaHashFixturator;
+        use holochain_p2p::kitsune_p2p::KitsuneSignature;
+
+        let TestEnvironment {
+            env: environment,
+            tmpdir,
+        } = test_conductor_env();
+        let TestEnvironment {
+            env: wasm_env,
+            tmpdir: _tmpdir,
+        } = test_wasm_env();
+        let TestEnvironment {
+            env: p2p_env,
+            tmpdir: _p2p_tmpdir,
+        } = test_p2p_env();
+        let keystore = environment.keystore().clone();
+        let (holochain_p2p, _p2p_evt) = holochain_p2p::spawn_holochain_p2p(Default::default())
+            .await
+            .unwrap();
+        let conductor = Conductor::new(
+            environment,
+            wasm_env,
+            p2p_env,
+            MockDnaStore::new(),
+            keystore.clone(),
+            tmpdir.path().to_path_buf().into(),
+            holochain_p2p,
+        )
+        .await
+        .unwrap();
+
+        let dna_hash = fixt!(DnaHash);
+        let now = p2p_store::now_ms();
+        let remote_keystore = holochain_state::test_utils::test_keystore();
+        let alice = AgentPubKey::new_from_pure_entropy(&remote_keystore)
+            .await
+            .unwrap();
+        let bob = AgentPubKey::new_from_pure_entropy(&remote_keystore)
+            .await
+            .unwrap();
+        let local = AgentPubKey::new_from_pure_entropy(&keystore).await.unwrap();
+        let held = || {
+            conductor
+                .get_agent_info_signed(
+                    Arc::new(kitsune_p2p::KitsuneSpace(dna_hash.clone().into_inner())),
+                    Arc::new(kitsune_p2p::KitsuneAgent(alice.clone().into_inner())),
+                )
+                .unwrap()
+        };
+        let rejected = |result: ConductorResult<()>| match result {
+            Err(ConductorError::AgentInfoRejected(_, reason)) => Some(reason),
+            _ => None,
+        };
+
+        // Alice's own info is stored, and can be sent again
+        let info = signed_agent_info(&remote_keystore, &alice, &dna_hash, now).await;
+        for _ in 0..2 {
+            conductor
+                .put_remote_agent_info_signed(&dna_hash, &alice, info.clone())
+                .await
+                .unwrap();
+        }
+        assert_eq!(held(), Some(info.clone()));
+
+        // Older info doesn't replace it
+        let older = signed_agent_info(&remote_keystore, &alice, &dna_hash, now - 1).await;
+        assert_eq!(
+            rejected(
+                conductor
+                    .put_remote_agent_info_signed(&dna_hash, &alice, older)
+                    .await
+            ),
+            Some(AgentInfoRejection::NotNewer)
+        );
+
+        // Bob can't send Alice's info
+        let newer = signed_agent_info(&remote_keystore, &alice, &dna_hash, now + 1).await;
+        assert_eq!(
+            rejected(
+                conductor
+                    .put_remote_agent_info_signed(&dna_hash, &bob, newer.clone())
+                    .await
+            ),
+            Some(AgentInfoRejection::NotSender)
+        );
+
+        // Nor can Alice send her info for another space
+        let other_space = fixt!(DnaHash);
+        let elsewhere = signed_agent_info(&remote_keystore, &alice, &other_space, now + 1).await;
+        assert_eq!(
+            rejected(
+                conductor
+                    .put_remote_agent_info_signed(&dna_hash, &alice, elsewhere)
+                    .await
+            ),
+            Some(AgentInfoRejection::WrongSpace)
+        );
+
+        // The info must carry Alice's signature
+        let forged = AgentInfoSigned::try_new(
+            KitsuneSignature(vec![0; 64]),
+            newer.as_agent_info_ref().clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            rejected(
+                conductor
+                    .put_remote_agent_info_signed(&dna_hash, &alice, forged)
+                    .await
+            ),
+            Some(AgentInfoRejection::BadSignature)
+        );
+
+        // Expired info is never stored
+        let expired = signed_agent_info(&remote_keystore, &bob, &dna_hash, 0).await;
+        assert_eq!(
+            rejected(
+                conductor
+                    .put_remote_agent_info_signed(&dna_hash, &bob, expired)
+                    .await
+            ),
+            Some(AgentInfoRejection::Expired)
+        );
+
+        // Remote agents can't write the info of our own agents,
+        // even if it is signed properly
+        let ours = signed_agent_info(&keystore, &local, &dna_hash, now).await;
+        assert_eq!(
+            rejected(
+                conductor
+                    .put_remote_agent_info_signed(&dna_hash, &local, ours)
+                    .await
+            ),
+            Some(AgentInfoRejection::LocalAgent)
+        );
+
+        // None of which changed what is held for Alice, until she sends newer info
+        assert_eq!(held(), Some(info));
+        conductor
+            .put_remote_agent_info_signed(&dna_hash, &alice, newer.clone())
+            .await
+            .unwrap();
+        assert_eq!(held(), Some(newer));
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn app_metadata_lifecycle() {
+        let test_env = test_conductor_env();
+        let _tmpdir = test_env.tmpdir.clone();
+        let TestEnvironment {
+            env: wasm_env,
+            tmpdir: _tmpdir,
+        } = test_wasm_env();
+        let TestEnvironment {
+            env: p2p_env,
+            tmpdir: _p2p_env,
+        } = test_p2p_env();
+        let app_id: AppId = "app".into();
+        let mut state = ConductorState::default();
+        state.inactive_apps.insert(app_id.clone(), Vec::new());
+        let handle = ConductorBuilder::new()
+            .fake_state(state)
+            .test(test_env, wasm_env, p2p_env)
+            .await
+            .unwrap();
+        let first = SerializedBytes::try_from(JsonProperties::new(serde_json::json!(1))).unwrap();
+        let second = SerializedBytes::try_from(JsonProperties::new(serde_json::json!(2))).unwrap();
+
+        assert_eq!(handle.get_app_metadata(&app_id, "k").await.unwrap(), None);
+
+        // Set
+        handle
+            .set_app_metadata(&app_id, "k".into(), first.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            handle.get_app_metadata(&app_id, "k").await.unwrap(),
+            Some(first)
+        );
+
+        // Overwrite
+        handle
+            .set_app_metadata(&app_id, "k".into(), second.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            handle.get_app_metadata(&app_id, "k").await.unwrap(),
+            Some(second.clone())
+        );
+        assert_eq!(handle.get_app_metadata(&app_id, "j").await.unwrap(), None);
+
+        // Delete, twice
+        handle.delete_app_metadata(&app_id, "k").await.unwrap();
+        handle.delete_app_metadata(&app_id, "k").await.unwrap();
+        assert_eq!(handle.get_app_metadata(&app_id, "k").await.unwrap(), None);
+
+        // Apps which aren't installed can't be annotated
+        let missing: AppId = "missing".into();
+        assert_matches!(
+            handle.set_app_metadata(&missing, "k".into(), second).await,
+            Err(ConductorError::AppNotInstalled)
+        );
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn cells_of_a_dna_share_one_dht_env_and_space() {
+        use crate::conductor::handle::ConductorHandleT;
+        use crate::test_utils::test_conductor::{test_dna_file, TestConductor};
+        use holochain_types::test_utils::{fake_agent_pubkey_1, fake_agent_pubkey_2};
+        use holochain_zome_types::GetOutput;
+
+        holochain_types::observability::test_run().ok();
+        let conductor = TestConductor::new_shared_dht().await;
+        let dna = test_dna_file(vec![TestWasm::Crd]).await;
+        let alice = conductor
+            .setup_app("alice", fake_agent_pubkey_1(), &[dna.clone()])
+            .await;
+        let bob = conductor
+            .setup_app("bob", fake_agent_pubkey_2(), &[dna])
+            .await;
+        let (alice, bob) = (&alice.cells()[0], &bob.cells()[0]);
+
+        // Bob reads what alice integrates into the env they share
+        let header_hash: HeaderHash = alice.call(TestWasm::Crd, "create", ()).await;
+        let mut element = None;
+        for _ in 0..50 {
+            let output: GetOutput = bob.call(TestWasm::Crd, "read", header_hash.clone()).await;
+            element = output.into_inner();
+            if element.is_some() {
+                break;
+            }
+            tokio::time::delay_for(std::time::Duration::from_millis(100)).await;
+        }
+        assert_eq!(
+            element.as_ref().map(|e| e.header_address()),
+            Some(&header_hash)
+        );
+
+        // The space was joined once, so neither cell is a peer of the other
+        let infos = conductor
+            .handle()
+            .network_info(vec![alice.cell_id().clone(), bob.cell_id().clone()])
+            .await
+            .unwrap();
+        for info in infos {
+            assert_eq!(info.peer_count, 0);
+        }
+
+        conductor.shutdown().await;
+    }
 }
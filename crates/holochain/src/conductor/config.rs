@@ -1,12 +1,18 @@
 #![deny(missing_docs)]
 //! This module is used to configure the conductor
 
+use holochain_p2p::{compression::WireCompressionConfig, kitsune_p2p::config::KitsuneP2pConfig};
 use serde::{Deserialize, Serialize};
 
 mod admin_interface_config;
+mod clock_skew_config;
 mod dpki_config;
+mod durability_config;
 mod network_config;
 mod passphrase_service_config;
+mod queue_backoff_config;
+mod validation_circuit_breaker_config;
+mod wasm_metering_config;
 //mod logger_config;
 //mod signal_config;
 use super::{
@@ -16,10 +22,15 @@ use super::{
 
 pub use crate::conductor::interface::InterfaceDriver;
 pub use admin_interface_config::AdminInterfaceConfig;
+pub use clock_skew_config::ClockSkewConfig;
 pub use dpki_config::DpkiConfig;
+pub use durability_config::{Durability, DurabilityConfig};
 //pub use logger_config::LoggerConfig;
 pub use network_config::NetworkConfig;
 pub use passphrase_service_config::PassphraseServiceConfig;
+pub use queue_backoff_config::{BackoffPolicy, QueueBackoffConfig};
+pub use validation_circuit_breaker_config::ValidationCircuitBreakerConfig;
+pub use wasm_metering_config::WasmMeteringConfig;
 //pub use signal_config::SignalConfig;
 use std::path::{Path, PathBuf};
 
@@ -75,6 +86,90 @@ pub struct ConductorConfig {
 
     /// Setup admin interfaces to control this conductor through a websocket connection
     pub admin_interfaces: Option<Vec<AdminInterfaceConfig>>,
+
+    /// How often, in seconds, to audit the integrity of each cell's stored data.
+    /// If omitted, cells are only audited on demand via the admin interface.
+    pub integrity_audit_interval_secs: Option<u64>,
+
+    /// Enabling this makes all Cells of the same DNA share one store for
+    /// integrated and cached DHT data, instead of each Cell keeping its own copy.
+    /// Source chains, authored data and private entries stay separate per Cell.
+    /// The network space is joined once for all the Cells of a DNA: the first
+    /// Cell's agent gossips and is published to, and requests addressed to
+    /// any of the Cells are served from the shared data.
+    #[serde(default)]
+    pub shared_dht_spaces: bool,
+
+    /// How durably each kind of environment writes to disk.
+    /// If omitted, all environments use asynchronous write-back.
+    #[serde(default)]
+    pub durability: DurabilityConfig,
+
+    /// How many signals each app interface may have waiting to be sent.
+    /// When an interface's queue is full its oldest signals are dropped.
+    /// If omitted, each queue holds 1024 signals.
+    pub signal_queue_depth: Option<usize>,
+
+    /// How many bytes a zome call may commit before it is flushed.
+    /// A commit which takes the call over the limit fails the call,
+    /// so that one call can't use up the conductor's memory.
+    /// If omitted, each call may commit 64 MB.
+    pub scratch_size_limit: Option<usize>,
+
+    /// How many threads run app validation callbacks, apart from the
+    /// threads which run zome calls. Zero runs them alongside zome calls.
+    /// If omitted, half of the CPUs are used.
+    pub validation_threads: Option<usize>,
+
+    /// How many app validation callbacks may wait for a validation thread
+    /// before app validation waits for the queue to drain.
+    /// If omitted, 64 callbacks may wait.
+    pub validation_queue_depth: Option<usize>,
+
+    /// Skip validating each Cell's genesis elements against its own Dna
+    /// before they are committed, e.g. to speed up tests.
+    /// If omitted, the self-check runs and a rejected genesis fails the install.
+    #[serde(default)]
+    pub skip_genesis_self_check: bool,
+
+    /// Whether zome calls are metered, and how much fuel one may burn.
+    /// If omitted, calls are not metered.
+    #[serde(default)]
+    pub wasm_metering: WasmMeteringConfig,
+
+    /// How each kind of queue consumer workflow backs off before running
+    /// again after failing with a transient error.
+    /// If omitted, all workflows use the default [BackoffPolicy].
+    #[serde(default)]
+    pub queue_backoff: QueueBackoffConfig,
+
+    /// How long, in milliseconds, an authority waits after integrating ops
+    /// before sending their validation receipts, so that the receipts owed
+    /// to each author are sent together in one message.
+    /// If omitted, receipts are sent after 500 ms.
+    pub receipt_flush_interval_ms: Option<u64>,
+
+    /// How far ahead of this conductor's clock header timestamps may be
+    /// before sys validation holds their ops back.
+    /// If omitted, the default [ClockSkewConfig] is used.
+    #[serde(default)]
+    pub clock_skew: ClockSkewConfig,
+
+    /// When each Cell pauses app validation because its validation
+    /// callbacks keep failing with errors.
+    /// If omitted, the default [ValidationCircuitBreakerConfig] is used.
+    #[serde(default)]
+    pub validation_circuit_breaker: ValidationCircuitBreakerConfig,
+
+    /// When large network payloads are compressed.
+    /// If omitted, the default [WireCompressionConfig] is used.
+    #[serde(default)]
+    pub wire_compression: WireCompressionConfig,
+
+    /// Tuning of the networking module, such as how many ops gossip
+    /// fetches at once. If omitted, the default [KitsuneP2pConfig] is used.
+    #[serde(default)]
+    pub kitsune_p2p: KitsuneP2pConfig,
     //
     //
     // /// Which signals to emit
@@ -105,6 +200,13 @@ impl ConductorConfig {
         })?;
         config_from_toml(&config_toml)
     }
+
+    /// Write this config as a toml string which [ConductorConfig::load_toml] can read.
+    /// It goes through a [toml::Value] so that the tables of nested configs,
+    /// like `queue_backoff` or `kitsune_p2p`, come after the plain values.
+    pub fn to_toml(&self) -> ConductorResult<String> {
+        Ok(toml::to_string(&toml::Value::try_from(self)?)?)
+    }
 }
 
 #[cfg(test)]
@@ -126,6 +228,21 @@ pub mod tests {
         // successful load test in conductor/interactive
     }
 
+    #[test]
+    fn test_config_to_toml_round_trip() {
+        let config = ConductorConfig {
+            admin_interfaces: Some(vec![AdminInterfaceConfig {
+                driver: InterfaceDriver::Websocket { port: 1234 },
+            }]),
+            use_dangerous_test_keystore: true,
+            skip_genesis_self_check: true,
+            receipt_flush_interval_ms: Some(100),
+            ..Default::default()
+        };
+        let toml = config.to_toml().unwrap();
+        assert_eq!(config_from_toml::<ConductorConfig>(&toml).unwrap(), config);
+    }
+
     #[test]
     fn test_config_bad_toml() {
         let result: ConductorResult<ConductorConfig> = config_from_toml("this isn't toml");
@@ -154,6 +271,21 @@ pub mod tests {
                 keystore_path: None,
                 admin_interfaces: None,
                 use_dangerous_test_keystore: false,
+                integrity_audit_interval_secs: None,
+                shared_dht_spaces: false,
+                durability: Default::default(),
+                signal_queue_depth: None,
+                scratch_size_limit: None,
+                validation_threads: None,
+                validation_queue_depth: None,
+                skip_genesis_self_check: false,
+                wasm_metering: Default::default(),
+                queue_backoff: Default::default(),
+                receipt_flush_interval_ms: None,
+                clock_skew: Default::default(),
+                validation_circuit_breaker: Default::default(),
+                wire_compression: Default::default(),
+                kitsune_p2p: Default::default(),
             }
         );
     }
@@ -205,6 +337,21 @@ pub mod tests {
                     driver: InterfaceDriver::Websocket { port: 1234 }
                 }]),
                 use_dangerous_test_keystore: true,
+                integrity_audit_interval_secs: None,
+                shared_dht_spaces: false,
+                durability: Default::default(),
+                signal_queue_depth: None,
+                scratch_size_limit: None,
+                validation_threads: None,
+                validation_queue_depth: None,
+                skip_genesis_self_check: false,
+                wasm_metering: Default::default(),
+                queue_backoff: Default::default(),
+                receipt_flush_interval_ms: None,
+                clock_skew: Default::default(),
+                validation_circuit_breaker: Default::default(),
+                wire_compression: Default::default(),
+                kitsune_p2p: Default::default(),
             }
         );
     }
@@ -238,6 +385,39 @@ pub mod tests {
                 keystore_path: Some(PathBuf::from("/path/to/keystore").into()),
                 admin_interfaces: None,
                 use_dangerous_test_keystore: true,
+                integrity_audit_interval_secs: None,
+                shared_dht_spaces: false,
+                durability: Default::default(),
+                signal_queue_depth: None,
+                scratch_size_limit: None,
+                validation_threads: None,
+                validation_queue_depth: None,
+                skip_genesis_self_check: false,
+                wasm_metering: Default::default(),
+                queue_backoff: Default::default(),
+                receipt_flush_interval_ms: None,
+                clock_skew: Default::default(),
+                validation_circuit_breaker: Default::default(),
+                wire_compression: Default::default(),
+                kitsune_p2p: Default::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_config_gossip_fetch_batch_size() {
+        let toml = r#"
+    environment_path = "/path/to/env"
+
+    [kitsune_p2p]
+    gossip_fetch_batch_size = 10
+    "#;
+        let result: ConductorConfig = config_from_toml(toml).unwrap();
+        assert_eq!(
+            result.kitsune_p2p,
+            KitsuneP2pConfig {
+                gossip_fetch_batch_size: 10,
+                ..Default::default()
             }
         );
     }
@@ -0,0 +1,62 @@
+use crate::conductor::error::{ConductorError, ConductorResult};
+use serde::{Deserialize, Serialize};
+
+/// How far into the future a header's timestamp may be, compared with
+/// this conductor's clock, before sys validation holds it back.
+/// Headers received from peers are given more leeway than headers
+/// authored here, because peers' clocks are often a few minutes off.
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct ClockSkewConfig {
+    /// The tolerance for headers authored by this conductor's agents
+    pub authored_tolerance_s: u64,
+    /// The tolerance for headers received from peers.
+    /// An op which is further ahead than this is delayed until its
+    /// timestamp falls within the tolerance, rather than rejected.
+    pub received_tolerance_s: u64,
+}
+
+impl Default for ClockSkewConfig {
+    fn default() -> Self {
+        Self {
+            authored_tolerance_s: 10,
+            received_tolerance_s: 60,
+        }
+    }
+}
+
+impl ClockSkewConfig {
+    /// Check that received headers are allowed at least as much leeway
+    /// as authored ones
+    pub fn validate(&self) -> ConductorResult<()> {
+        if self.received_tolerance_s < self.authored_tolerance_s {
+            return Err(ConductorError::ConfigError(format!(
+                "A clock skew received_tolerance_s of {} is shorter than its authored_tolerance_s of {}",
+                self.received_tolerance_s, self.authored_tolerance_s
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matches::assert_matches;
+
+    #[test]
+    fn clock_skew_config_from_toml() {
+        let config: ClockSkewConfig = toml::from_str("received_tolerance_s = 300").unwrap();
+        assert_eq!(
+            config,
+            ClockSkewConfig {
+                authored_tolerance_s: 10,
+                received_tolerance_s: 300,
+            }
+        );
+        assert_matches!(config.validate(), Ok(()));
+
+        let config: ClockSkewConfig = toml::from_str("received_tolerance_s = 5").unwrap();
+        assert_matches!(config.validate(), Err(ConductorError::ConfigError(_)));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::conductor::error::{ConductorError, ConductorResult};
+pub use holochain_state::env::Durability;
+use holochain_state::env::EnvironmentKind;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// The [Durability] of each kind of environment.
+/// Kinds which are omitted use asynchronous write-back of the memory map.
+#[derive(Clone, Default, Deserialize, Serialize, Debug, PartialEq)]
+pub struct DurabilityConfig {
+    /// The environment of each Cell, which holds its source chain.
+    /// Authored data must not be lossy, so [Durability::NoSync] and
+    /// [Durability::NoSyncPeriodic] are rejected here.
+    pub cell: Option<Durability>,
+    /// The DHT environments shared by the Cells of a DNA
+    pub dht: Option<Durability>,
+    /// The environment holding the conductor's state
+    pub conductor: Option<Durability>,
+    /// The environment holding wasm
+    pub wasm: Option<Durability>,
+    /// The environment holding p2p agent info
+    pub p2p: Option<Durability>,
+}
+
+impl DurabilityConfig {
+    /// The mode for a kind of environment
+    pub fn mode(&self, kind: &EnvironmentKind) -> Option<Durability> {
+        match kind {
+            EnvironmentKind::Cell(_) => self.cell,
+            EnvironmentKind::Dht(_) => self.dht,
+            EnvironmentKind::Conductor => self.conductor,
+            EnvironmentKind::Wasm => self.wasm,
+            EnvironmentKind::P2P => self.p2p,
+        }
+    }
+
+    /// Check that no kind of environment is given a mode it can't use
+    pub fn validate(&self) -> ConductorResult<()> {
+        if let Some(mode @ Durability::NoSync) | Some(mode @ Durability::NoSyncPeriodic { .. }) =
+            self.cell
+        {
+            return Err(ConductorError::ConfigError(format!(
+                "Cell environments hold source chains and can't use the {:?} durability mode",
+                mode
+            )));
+        }
+        if self
+            .modes()
+            .any(|m| m == Durability::NoSyncPeriodic { interval_ms: 0 })
+        {
+            return Err(ConductorError::ConfigError(
+                "The NoSyncPeriodic durability mode needs a non-zero interval_ms".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// How often environments must be synced: the shortest interval of
+    /// any kind using [Durability::NoSyncPeriodic]
+    pub fn sync_interval(&self) -> Option<Duration> {
+        self.modes()
+            .filter_map(|m| match m {
+                Durability::NoSyncPeriodic { interval_ms } => Some(interval_ms),
+                _ => None,
+            })
+            .min()
+            .map(Duration::from_millis)
+    }
+
+    fn modes(&self) -> impl Iterator<Item = Durability> {
+        vec![self.cell, self.dht, self.conductor, self.wasm, self.p2p]
+            .into_iter()
+            .flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matches::assert_matches;
+
+    #[test]
+    fn cell_envs_reject_lossy_modes() {
+        for mode in &[
+            Durability::NoSync,
+            Durability::NoSyncPeriodic { interval_ms: 100 },
+        ] {
+            let config = DurabilityConfig {
+                cell: Some(*mode),
+                ..Default::default()
+            };
+            assert_matches!(config.validate(), Err(ConductorError::ConfigError(_)));
+        }
+
+        let config = DurabilityConfig {
+            cell: Some(Durability::NoMetaSync),
+            dht: Some(Durability::NoSyncPeriodic { interval_ms: 100 }),
+            p2p: Some(Durability::NoSyncPeriodic { interval_ms: 50 }),
+            ..Default::default()
+        };
+        assert_matches!(config.validate(), Ok(()));
+        assert_eq!(config.sync_interval(), Some(Duration::from_millis(50)));
+    }
+
+    #[test]
+    fn periodic_sync_needs_an_interval() {
+        let config = DurabilityConfig {
+            wasm: Some(Durability::NoSyncPeriodic { interval_ms: 0 }),
+            ..Default::default()
+        };
+        assert_matches!(config.validate(), Err(ConductorError::ConfigError(_)));
+        assert_eq!(DurabilityConfig::default().sync_interval(), None);
+    }
+
+    #[test]
+    fn durability_config_from_toml() {
+        let toml = r#"
+    cell = "Sync"
+    dht = { NoSyncPeriodic = { interval_ms = 1000 } }
+    "#;
+        let config: DurabilityConfig = toml::from_str(toml).unwrap();
+        assert_eq!(
+            config,
+            DurabilityConfig {
+                cell: Some(Durability::Sync),
+                dht: Some(Durability::NoSyncPeriodic { interval_ms: 1000 }),
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{
+    conductor::error::{ConductorError, ConductorResult},
+    core::state::workflow_errors::WorkflowName,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How long a queue consumer waits before running its workflow again,
+/// after a run failed with a transient error.
+/// The first retry waits `initial_delay_ms`, and each further consecutive
+/// failure multiplies the wait by `multiplier`, up to `max_delay_ms`.
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct BackoffPolicy {
+    /// The wait after the first failed run
+    pub initial_delay_ms: u64,
+    /// How much longer each wait is than the one before it
+    pub multiplier: f64,
+    /// The longest wait
+    pub max_delay_ms: u64,
+    /// The fraction of each wait, from 0 to 1, which is cut off at random
+    /// so that consumers which fail together don't all retry together
+    pub jitter: f64,
+}
+
+impl Default for BackoffPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay_ms: 100,
+            multiplier: 2.0,
+            max_delay_ms: 60_000,
+            jitter: 0.1,
+        }
+    }
+}
+
+impl BackoffPolicy {
+    /// The wait before the next run, after `failures` runs in a row have failed
+    pub fn delay(&self, failures: u32) -> Duration {
+        // Past 64 doublings every wait is capped anyway
+        let exponent = failures.saturating_sub(1).min(64) as i32;
+        let delay_ms = (self.initial_delay_ms as f64 * self.multiplier.powi(exponent))
+            .min(self.max_delay_ms as f64);
+        let jitter = if self.jitter > 0.0 {
+            delay_ms * self.jitter * rand::random::<f64>()
+        } else {
+            0.0
+        };
+        Duration::from_millis((delay_ms - jitter) as u64)
+    }
+
+    /// Check that the policy's waits never shrink and never exceed the maximum
+    pub fn validate(&self) -> ConductorResult<()> {
+        if self.multiplier.is_nan() || self.multiplier < 1.0 {
+            return Err(ConductorError::ConfigError(format!(
+                "A backoff multiplier must be at least 1, got {}",
+                self.multiplier
+            )));
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err(ConductorError::ConfigError(format!(
+                "A backoff jitter must be between 0 and 1, got {}",
+                self.jitter
+            )));
+        }
+        if self.initial_delay_ms > self.max_delay_ms {
+            return Err(ConductorError::ConfigError(format!(
+                "A backoff initial_delay_ms of {} is longer than its max_delay_ms of {}",
+                self.initial_delay_ms, self.max_delay_ms
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The [BackoffPolicy] of each kind of queue consumer workflow.
+/// Workflows which are omitted use the default policy.
+#[derive(Clone, Default, Deserialize, Serialize, Debug, PartialEq)]
+pub struct QueueBackoffConfig {
+    /// The SysValidation workflow
+    pub sys_validation: Option<BackoffPolicy>,
+    /// The AppValidation workflow
+    pub app_validation: Option<BackoffPolicy>,
+    /// The DhtOpIntegration workflow
+    pub integrate_dht_ops: Option<BackoffPolicy>,
+    /// The ProduceDhtOps workflow
+    pub produce_dht_ops: Option<BackoffPolicy>,
+    /// The Publish workflow
+    pub publish_dht_ops: Option<BackoffPolicy>,
+    /// The ValidationReceipt workflow
+    pub validation_receipt: Option<BackoffPolicy>,
+}
+
+impl QueueBackoffConfig {
+    /// The policy for a kind of workflow
+    pub fn policy(&self, workflow: WorkflowName) -> BackoffPolicy {
+        match workflow {
+            WorkflowName::SysValidation => self.sys_validation,
+            WorkflowName::AppValidation => self.app_validation,
+            WorkflowName::IntegrateDhtOps => self.integrate_dht_ops,
+            WorkflowName::ProduceDhtOps => self.produce_dht_ops,
+            WorkflowName::PublishDhtOps => self.publish_dht_ops,
+            WorkflowName::ValidationReceipt => self.validation_receipt,
+        }
+        .unwrap_or_default()
+    }
+
+    /// Check every configured policy
+    pub fn validate(&self) -> ConductorResult<()> {
+        vec![
+            self.sys_validation,
+            self.app_validation,
+            self.integrate_dht_ops,
+            self.produce_dht_ops,
+            self.publish_dht_ops,
+            self.validation_receipt,
+        ]
+        .into_iter()
+        .flatten()
+        .try_for_each(|p| p.validate())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matches::assert_matches;
+
+    #[test]
+    fn backoff_grows_to_its_maximum() {
+        let policy = BackoffPolicy {
+            initial_delay_ms: 10,
+            multiplier: 3.0,
+            max_delay_ms: 200,
+            jitter: 0.0,
+        };
+        let delays: Vec<_> = (1..=5).map(|f| policy.delay(f).as_millis()).collect();
+        assert_eq!(delays, vec![10, 30, 90, 200, 200]);
+        assert_eq!(policy.delay(u32::MAX), Duration::from_millis(200));
+
+        // Jitter only ever shortens the wait
+        let policy = BackoffPolicy {
+            jitter: 0.5,
+            ..policy
+        };
+        for _ in 0..20 {
+            let delay = policy.delay(2).as_millis();
+            assert!((15..=30).contains(&delay), "{}", delay);
+        }
+    }
+
+    #[test]
+    fn bad_policies_are_rejected() {
+        let shrinking = BackoffPolicy {
+            multiplier: 0.5,
+            ..Default::default()
+        };
+        assert_matches!(shrinking.validate(), Err(ConductorError::ConfigError(_)));
+        let config = QueueBackoffConfig {
+            publish_dht_ops: Some(BackoffPolicy {
+                jitter: 2.0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_matches!(config.validate(), Err(ConductorError::ConfigError(_)));
+        assert_matches!(QueueBackoffConfig::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn queue_backoff_config_from_toml() {
+        let toml = r#"
+    [sys_validation]
+    initial_delay_ms = 500
+    max_delay_ms = 10000
+    "#;
+        let config: QueueBackoffConfig = toml::from_str(toml).unwrap();
+        assert_eq!(
+            config.policy(WorkflowName::SysValidation),
+            BackoffPolicy {
+                initial_delay_ms: 500,
+                max_delay_ms: 10_000,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            config.policy(WorkflowName::PublishDhtOps),
+            BackoffPolicy::default()
+        );
+    }
+}
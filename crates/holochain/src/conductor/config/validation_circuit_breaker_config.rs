@@ -0,0 +1,87 @@
+use crate::conductor::error::{ConductorError, ConductorResult};
+use serde::{Deserialize, Serialize};
+
+/// When a Cell stops running app validation because its validation
+/// callbacks keep failing with errors, as opposed to rejecting ops.
+/// While the breaker is open, a small batch of ops is validated after
+/// each probe interval, and app validation resumes once a batch succeeds.
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct ValidationCircuitBreakerConfig {
+    /// How many seconds of validations the error rate is measured over
+    pub window_s: u64,
+    /// The share of validations in the window, between 0 and 1,
+    /// which must fail with an error to open the breaker
+    pub error_rate: f64,
+    /// How many validations the window must hold before the breaker can open,
+    /// so that a single error doesn't open it
+    pub min_executions: u32,
+    /// How many seconds an open breaker waits between probes
+    pub probe_interval_s: u64,
+    /// How many ops each probe validates
+    pub probe_batch: usize,
+}
+
+impl Default for ValidationCircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            window_s: 60,
+            error_rate: 0.5,
+            min_executions: 10,
+            probe_interval_s: 300,
+            probe_batch: 5,
+        }
+    }
+}
+
+impl ValidationCircuitBreakerConfig {
+    /// Check that the error rate is a share, and that probes wait
+    /// and validate something
+    pub fn validate(&self) -> ConductorResult<()> {
+        if !(self.error_rate > 0.0 && self.error_rate <= 1.0) {
+            return Err(ConductorError::ConfigError(format!(
+                "A validation circuit breaker error_rate of {} is not above 0 and at most 1",
+                self.error_rate
+            )));
+        }
+        if self.window_s == 0 || self.probe_interval_s == 0 || self.probe_batch == 0 {
+            return Err(ConductorError::ConfigError(
+                "A validation circuit breaker needs a window_s, probe_interval_s and probe_batch of at least 1"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matches::assert_matches;
+
+    #[test]
+    fn validation_circuit_breaker_config_from_toml() {
+        let config: ValidationCircuitBreakerConfig =
+            toml::from_str("error_rate = 0.9\nprobe_interval_s = 30").unwrap();
+        assert_eq!(
+            config,
+            ValidationCircuitBreakerConfig {
+                error_rate: 0.9,
+                probe_interval_s: 30,
+                ..Default::default()
+            }
+        );
+        assert_matches!(config.validate(), Ok(()));
+
+        let config: ValidationCircuitBreakerConfig = toml::from_str("error_rate = 1.5").unwrap();
+        assert_matches!(config.validate(), Err(ConductorError::ConfigError(_)));
+
+        let config: ValidationCircuitBreakerConfig = toml::from_str("probe_batch = 0").unwrap();
+        assert_matches!(config.validate(), Err(ConductorError::ConfigError(_)));
+
+        // A forced open breaker would otherwise wait for no time at all
+        let config: ValidationCircuitBreakerConfig =
+            toml::from_str("probe_interval_s = 0").unwrap();
+        assert_matches!(config.validate(), Err(ConductorError::ConfigError(_)));
+    }
+}
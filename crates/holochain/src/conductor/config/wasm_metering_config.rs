@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+/// Counting the fuel each zome call burns, to find which zome functions
+/// are expensive and to cap what one call may burn.
+/// Metered wasm is compiled by a slower backend, so metering is off unless enabled.
+/// That backend only builds on x86_64; elsewhere metered zomes fail to compile.
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct WasmMeteringConfig {
+    /// Compile zomes with fuel metering
+    pub enabled: bool,
+    /// How much fuel one call may burn before it is aborted.
+    /// Only applies if metering is enabled. If omitted, calls may burn any amount.
+    pub fuel_limit: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wasm_metering_config_from_toml() {
+        let config: WasmMeteringConfig = toml::from_str("enabled = true").unwrap();
+        assert_eq!(
+            config,
+            WasmMeteringConfig {
+                enabled: true,
+                fuel_limit: None,
+            }
+        );
+
+        let config: WasmMeteringConfig =
+            toml::from_str("enabled = true\nfuel_limit = 1000000").unwrap();
+        assert_eq!(config.fuel_limit, Some(1_000_000));
+    }
+}
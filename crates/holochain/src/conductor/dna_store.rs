@@ -9,7 +9,7 @@ use holochain_state::{
     prelude::*,
 };
 use holochain_types::{
-    dna::{DnaDef, DnaDefHashed, DnaFile},
+    dna::{DnaDef, DnaDefHashed, DnaDiff, DnaFile},
     prelude::*,
 };
 use holochain_zome_types::entry_def::EntryDef;
@@ -41,6 +41,9 @@ pub trait DnaStore: Default + Send + Sync {
     fn list(&self) -> Vec<DnaHash>;
     fn get(&self, hash: &DnaHash) -> Option<DnaFile>;
     fn get_entry_def(&self, k: &EntryDefBufferKey) -> Option<EntryDef>;
+    /// Describe how two Dnas differ.
+    /// Returns None if either Dna is not in the store.
+    fn diff(&self, a: &DnaHash, b: &DnaHash) -> Option<DnaDiff>;
 }
 
 impl DnaStore for RealDnaStore {
@@ -71,6 +74,11 @@ impl DnaStore for RealDnaStore {
     fn get_entry_def(&self, k: &EntryDefBufferKey) -> Option<EntryDef> {
         self.entry_defs.get(k).cloned()
     }
+    fn diff(&self, a: &DnaHash, b: &DnaHash) -> Option<DnaDiff> {
+        let a = self.dnas.get(a)?;
+        let b = self.dnas.get(b)?;
+        Some(DnaDiff::new(a.dna(), b.dna()))
+    }
 }
 
 impl RealDnaStore {
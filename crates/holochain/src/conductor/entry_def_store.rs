@@ -81,6 +81,16 @@ impl EntryDefBufferKey {
             entry_def_position,
         }
     }
+
+    /// The zome which declares the entry def
+    pub fn zome(&self) -> &Zome {
+        &self.zome
+    }
+
+    /// The position of the entry def in its zome's entry defs
+    pub fn entry_def_position(&self) -> EntryDefIndex {
+        self.entry_def_position
+    }
 }
 
 impl EntryDefBuf {
@@ -216,20 +226,21 @@ pub(crate) fn get_entry_defs(
 #[cfg(test)]
 mod tests {
     use super::EntryDefBufferKey;
-    use crate::conductor::Conductor;
+    use crate::conductor::{error::ConductorError, Conductor};
     use holo_hash::HasHash;
     use holochain_state::test_utils::{
         test_conductor_env, test_p2p_env, test_wasm_env, TestEnvironment,
     };
     use holochain_types::{
         dna::{wasm::DnaWasmHashed, zome::Zome},
-        test_utils::fake_dna_zomes,
+        test_utils::{fake_dna_hash, fake_dna_zomes},
     };
     use holochain_wasm_test_utils::TestWasm;
     use holochain_zome_types::{
         crdt::CrdtType,
         entry_def::{EntryDef, EntryVisibility},
     };
+    use matches::assert_matches;
 
     #[tokio::test(threaded_scheduler)]
     async fn test_store_entry_defs() {
@@ -288,6 +299,7 @@ mod tests {
             entry_def_position: 1.into(),
         };
 
+        let dna_hash = dna.dna_hash().clone();
         handle.install_dna(dna).await.unwrap();
         // Check entry defs are here
         assert_eq!(
@@ -298,6 +310,18 @@ mod tests {
             handle.get_entry_def(&comment_def_key).await,
             Some(comment_def.clone())
         );
+        // and can be listed for the dna
+        assert_eq!(
+            handle.list_entry_defs(&dna_hash).await.unwrap(),
+            vec![
+                (post_def_key.clone(), post_def.clone()),
+                (comment_def_key.clone(), comment_def.clone())
+            ]
+        );
+        assert_matches!(
+            handle.list_entry_defs(&fake_dna_hash(1)).await,
+            Err(ConductorError::DnaMissing(_))
+        );
 
         std::mem::drop(handle);
 
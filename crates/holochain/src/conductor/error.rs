@@ -1,5 +1,9 @@
-use super::{entry_def_store::error::EntryDefStoreError, interface::error::InterfaceError};
+use super::{
+    entry_def_store::error::EntryDefStoreError, interface::error::InterfaceError,
+    p2p_store::AgentInfoRejection,
+};
 use crate::{conductor::cell::error::CellError, core::workflow::error::WorkflowError};
+use holo_hash::{AgentPubKey, DnaHash, WasmHash};
 use holochain_state::error::DatabaseError;
 use holochain_types::{app::AppId, cell::CellId};
 use std::path::PathBuf;
@@ -54,6 +58,12 @@ pub enum ConductorError {
     #[error("DnaError: {0}")]
     DnaError(#[from] holochain_types::dna::DnaError),
 
+    #[error("Could not decode the Dna file at {path:?}: {error}")]
+    DnaDecodeError {
+        path: PathBuf,
+        error: holochain_types::dna::DnaError,
+    },
+
     #[error("Workflow error: {0:?}")]
     WorkflowError(#[from] WorkflowError),
 
@@ -67,12 +77,38 @@ pub enum ConductorError {
     #[error("Failed to run genesis on the following cells in the app: {errors:?}")]
     GenesisFailed { errors: Vec<CellError> },
 
+    #[error(
+        "The genesis elements of cell {cell_id:?} were rejected by its own validation: {reason}"
+    )]
+    GenesisSelfCheckFailed { cell_id: CellId, reason: String },
+
     #[error(transparent)]
     SerializedBytesError(#[from] holochain_serialized_bytes::SerializedBytesError),
 
     #[error("Wasm code was not found in the wasm store")]
     WasmMissing,
 
+    #[error("The wasm stored for {expected} is corrupt: its bytes hash to {found}")]
+    WasmHashMismatch { expected: WasmHash, found: WasmHash },
+
+    #[error("The Dna stored for {expected} is corrupt: it hashes to {found}")]
+    DnaHashMismatch { expected: DnaHash, found: DnaHash },
+
+    #[error("Dna was referenced, but is not installed. DnaHash: {0}")]
+    DnaMissing(DnaHash),
+
+    #[error("The cells of app {app_id} belong to more than one agent: {agents:?}")]
+    AppAgentsDiffer {
+        app_id: AppId,
+        agents: Vec<AgentPubKey>,
+    },
+
+    #[error("The agent info sent by {0} was rejected because {1}")]
+    AgentInfoRejected(AgentPubKey, AgentInfoRejection),
+
+    #[error("The agent {0} has no keypair in this conductor's keystore")]
+    AgentKeyMissing(AgentPubKey),
+
     #[error("Tried to activate an app that was not installed")]
     AppNotInstalled,
 
@@ -87,6 +123,9 @@ pub enum ConductorError {
 
     #[error(transparent)]
     KeystoreError(#[from] holochain_keystore::KeystoreError),
+
+    #[error("Could not migrate the conductor state: {0}")]
+    MigrationError(String),
 }
 
 #[derive(Error, Debug)]
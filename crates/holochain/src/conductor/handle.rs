@@ -50,40 +50,163 @@
 //! code which interacted with the Conductor would also have to be highly generic.
 
 use super::{
-    api::error::ConductorApiResult,
+    api::{
+        error::{ConductorApiError, ConductorApiResult},
+        SignalSubscription,
+    },
     config::AdminInterfaceConfig,
     dna_store::DnaStore,
     entry_def_store::EntryDefBufferKey,
-    error::{ConductorResult, CreateAppError},
-    interface::SignalBroadcaster,
+    error::{ConductorError, ConductorResult, CreateAppError},
+    interface::{InterfaceInfo, SignalBroadcaster},
     manager::TaskManagerRunHandle,
-    Cell, Conductor,
+    network_info::NetworkInfo,
+    p2p_store,
+    state::AppInterfaceId,
+    Cell, CellError, CellStatus, Conductor, ConductorMetrics, CountersigningPreflight,
+    CountersigningSession, EnvironmentSyncReport,
 };
-use crate::core::ribosome::ZomeCallInvocation;
+use crate::core::clock_skew::ClockSkew;
+use crate::core::gossip_metrics::CellGossipMetrics;
+use crate::core::ribosome::{
+    module_cache::WasmModuleCache, wasm_ribosome::WasmRibosome, ZomeCallInvocation,
+};
+use crate::core::signal::SystemSignal;
+use crate::core::state::integrity_audit::{self, IntegrityAuditReport};
+use crate::core::state::source_chain::ForkReport;
+use crate::core::state::workflow_errors::{self, WorkflowErrorRecord};
+use crate::core::validation_breaker::ValidationBreakerStatus;
 use crate::core::workflow::ZomeCallInvocationResult;
 use derive_more::From;
 use holochain_types::{
-    app::{AppId, InstalledApp, InstalledCell, MembraneProof},
+    app::{AppId, CellNick, InstalledApp, InstalledCell, MembraneProof},
     autonomic::AutonomicCue,
     cell::CellId,
-    dna::DnaFile,
+    dna::{DnaDiff, DnaFile, DnaFileBundle},
     prelude::*,
+    validate::ValidationPackageReport,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
 };
-use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::*;
 
 use futures::future::FutureExt;
+use holochain_keystore::KeystoreSenderExt;
 use holochain_p2p::event::HolochainP2pEvent::GetAgentInfoSigned;
+use holochain_p2p::event::HolochainP2pEvent::GetPeerMeta;
+use holochain_p2p::event::HolochainP2pEvent::GetSpacePeerMeta;
+use holochain_p2p::event::HolochainP2pEvent::PeerConnected;
+use holochain_p2p::event::HolochainP2pEvent::PeerDisconnected;
 use holochain_p2p::event::HolochainP2pEvent::PutAgentInfoSigned;
+use holochain_p2p::event::HolochainP2pEvent::PutPeerMeta;
+use holochain_p2p::event::HolochainP2pEvent::RequestArcSync;
 
+#[cfg(any(test, feature = "bench"))]
+use super::benchmark::BenchmarkResult;
 #[cfg(test)]
 use super::state::ConductorState;
 #[cfg(test)]
 use crate::core::queue_consumer::InitialQueueTriggers;
+#[cfg(any(test, feature = "bench"))]
+use crate::core::workflow::error::WorkflowError;
+use holochain_p2p::HolochainP2pCellT;
 #[cfg(test)]
 use holochain_state::env::EnvironmentWrite;
+use holochain_zome_types::capability::CapSecret;
 use holochain_zome_types::entry_def::EntryDef;
+use holochain_zome_types::header::Header;
+use holochain_zome_types::signature::Signature;
+use holochain_zome_types::zome::{FunctionName, ZomeName};
+
+/// How long an outbound remote call waits for a response,
+/// and how many more times it is tried when it gets none
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CallRemoteOptions {
+    /// How long each attempt waits for a response
+    pub timeout: Duration,
+    /// How many times the call is tried again after the first attempt fails
+    pub retries: u8,
+}
+
+impl Default for CallRemoteOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            retries: 0,
+        }
+    }
+}
+
+/// Make attempts at a remote call until one succeeds or they run out.
+/// The error of the last attempt is returned, or [ConductorApiError::RemoteCallTimeout]
+/// if it timed out.
+async fn call_remote_with_retries<F, Fut>(
+    to_agent: &AgentPubKey,
+    options: CallRemoteOptions,
+    mut attempt: F,
+) -> ConductorApiResult<SerializedBytes>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = ConductorApiResult<SerializedBytes>>,
+{
+    let attempts = options.retries as u32 + 1;
+    for i in 1..=attempts {
+        match tokio::time::timeout(options.timeout, attempt()).await {
+            Ok(Ok(response)) => return Ok(response),
+            Ok(Err(e)) if i == attempts => return Err(e),
+            Ok(Err(e)) => warn!(msg = "Remote call failed", ?to_agent, attempt = i, ?e),
+            Err(_) => warn!(msg = "Remote call timed out", ?to_agent, attempt = i),
+        }
+    }
+    Err(ConductorApiError::RemoteCallTimeout {
+        to_agent: to_agent.clone(),
+        timeout: options.timeout,
+        attempts,
+    })
+}
+
+/// Check the cells of an app all belong to one agent
+fn check_single_agent<'a>(
+    app_id: &AppId,
+    cells: impl Iterator<Item = &'a InstalledCell>,
+) -> ConductorResult<()> {
+    let mut agents: Vec<AgentPubKey> = Vec::new();
+    for cell in cells {
+        let agent = cell.as_id().agent_pubkey();
+        if !agents.contains(agent) {
+            agents.push(agent.clone());
+        }
+    }
+    if agents.len() > 1 {
+        return Err(ConductorError::AppAgentsDiffer {
+            app_id: app_id.clone(),
+            agents,
+        });
+    }
+    Ok(())
+}
+
+/// Check the keystore holds the agent's keypair by signing with it
+/// and verifying the signature
+async fn check_agent_in_keystore(
+    keystore: &KeystoreSender,
+    agent: &AgentPubKey,
+) -> ConductorResult<()> {
+    let probe = agent.get_full_bytes().to_vec();
+    let verified = match agent.sign_raw(keystore, &probe).await {
+        Ok(signature) => agent.verify_signature_raw(&signature, &probe).await?,
+        Err(_) => false,
+    };
+    if !verified {
+        return Err(ConductorError::AgentKeyMissing(agent.clone()));
+    }
+    Ok(())
+}
 
 /// A handle to the Conductor that can easily be passed around and cheaply cloned
 pub type ConductorHandle = Arc<dyn ConductorHandleT>;
@@ -114,18 +237,65 @@ pub trait ConductorHandleT: Send + Sync {
     /// Install a [Dna] in this Conductor
     async fn install_dna(&self, dna: DnaFile) -> ConductorResult<()>;
 
+    /// Read and decode a [DnaFile] from disk, install it in this Conductor
+    /// and return its hash
+    async fn install_dna_from_path(&self, path: PathBuf) -> ConductorResult<DnaHash>;
+
     /// Get the list of hashes of installed Dnas in this Conductor
     async fn list_dnas(&self) -> ConductorResult<Vec<DnaHash>>;
 
     /// Get a [Dna] from the [DnaStore]
     async fn get_dna(&self, hash: &DnaHash) -> Option<DnaFile>;
 
+    /// Get the properties a [Dna] was installed with,
+    /// or None if the [Dna] is not installed
+    async fn get_dna_properties(
+        &self,
+        dna_hash: &DnaHash,
+    ) -> ConductorResult<Option<SerializedBytes>>;
+
+    /// Describe how two installed [Dna]s differ, to explain why they hash
+    /// differently. Returns None if either [Dna] is not installed.
+    async fn compare_dnas(&self, a: &DnaHash, b: &DnaHash) -> ConductorResult<Option<DnaDiff>>;
+
     /// Get a [EntryDef] from the [EntryDefBuffer]
     async fn get_entry_def(&self, key: &EntryDefBufferKey) -> Option<EntryDef>;
 
+    /// Compile the wasm of every zome of an installed [Dna] and store the
+    /// compiled modules, so that Cells of the [Dna] start without compiling
+    async fn precompile_dna(&self, dna_hash: &DnaHash) -> ConductorApiResult<()>;
+
+    /// Export an installed [DnaFile] as the content of a dna file, rebuilt from
+    /// the stored [Dna] and wasm, so it can be installed on another conductor
+    async fn export_dna(&self, hash: &DnaHash) -> ConductorResult<DnaFileBundle>;
+
+    /// List every [EntryDef] declared by the zomes of an installed [Dna],
+    /// in the order the [Dna] lists its zomes and then the zomes list their entry defs
+    async fn list_entry_defs(
+        &self,
+        dna_hash: &DnaHash,
+    ) -> ConductorResult<Vec<(EntryDefBufferKey, EntryDef)>>;
+
+    /// List the functions each zome of a Cell's [Dna] exposes to clients,
+    /// leaving out callbacks such as `init` and `validate`
+    async fn list_zome_functions(
+        &self,
+        cell_id: &CellId,
+    ) -> ConductorApiResult<BTreeMap<ZomeName, Vec<FunctionName>>>;
+
     /// Add the [DnaFile]s from the wasm and dna_def databases into memory
     async fn add_dnas(&self) -> ConductorResult<()>;
 
+    /// Delete the agent info of every peer whose info has expired from the
+    /// p2p store, returning how many were deleted.
+    /// Expired agent info is never returned from the store, so this only
+    /// reclaims space.
+    async fn prune_expired_agent_infos(&self) -> ConductorResult<usize>;
+
+    /// List the public keys of every signing keypair in the keystore,
+    /// e.g. to confirm a key was imported. Empty if the keystore holds none.
+    async fn inspect_keystore(&self) -> ConductorResult<Vec<AgentPubKey>>;
+
     /// Dispatch a network event to the correct cell.
     async fn dispatch_holochain_p2p_event(
         &self,
@@ -139,12 +309,82 @@ pub trait ConductorHandleT: Send + Sync {
         invocation: ZomeCallInvocation,
     ) -> ConductorApiResult<ZomeCallInvocationResult>;
 
+    /// Invoke a zome function on a Cell without committing anything.
+    /// Returns the result of the call along with the headers which would
+    /// have been written to the source chain.
+    async fn call_zome_dry_run(
+        &self,
+        invocation: ZomeCallInvocation,
+    ) -> ConductorApiResult<(ZomeCallInvocationResult, Vec<Header>)>;
+
+    /// Invoke a zome function `iterations` times and time each call.
+    /// The first call warms up the Cell and is not timed.
+    /// Every call runs for real, so anything the function commits is
+    /// committed `iterations` times.
+    ///
+    /// ```ignore
+    /// let invocation = new_invocation(&cell_id, "get_entry", (), TestWasm::Create)?;
+    /// let result = handle.benchmark_zome_call(invocation, 101).await?;
+    /// println!("p95 over {} calls: {}ms", result.runs, result.p95_ms);
+    /// ```
+    #[cfg(any(test, feature = "bench"))]
+    async fn benchmark_zome_call(
+        &self,
+        invocation: ZomeCallInvocation,
+        iterations: u32,
+    ) -> ConductorApiResult<BenchmarkResult>;
+
+    /// Invoke a zome function on a remote agent's Cell, calling out over the
+    /// network from one of this conductor's Cells.
+    /// Returns the serialized response of the remote zome call.
+    #[allow(clippy::too_many_arguments)]
+    async fn call_remote(
+        &self,
+        from_cell: &CellId,
+        to_agent: AgentPubKey,
+        zome_name: ZomeName,
+        fn_name: FunctionName,
+        cap: Option<CapSecret>,
+        payload: SerializedBytes,
+        options: CallRemoteOptions,
+    ) -> ConductorApiResult<SerializedBytes>;
+
+    /// Start a countersigning session from one of this conductor's Cells,
+    /// sending another agent a preflight of the payload to sign.
+    /// Returns the preflight, whose signing hash both agents sign.
+    async fn countersigning_request(
+        &self,
+        cell_id: &CellId,
+        to_agent: AgentPubKey,
+        payload: SerializedBytes,
+    ) -> ConductorApiResult<CountersigningPreflight>;
+
+    /// Sign the preflight another agent sent one of this conductor's Cells,
+    /// and return the partial signature to them
+    async fn countersigning_respond(
+        &self,
+        cell_id: &CellId,
+        initiator: AgentPubKey,
+    ) -> ConductorApiResult<Signature>;
+
+    /// A Cell's countersigning session with another agent, if there is one,
+    /// e.g. to see whether a preflight has arrived or been signed
+    async fn countersigning_session(
+        &self,
+        cell_id: &CellId,
+        counterparty: &AgentPubKey,
+    ) -> ConductorApiResult<Option<CountersigningSession>>;
+
     /// Cue the autonomic system to perform some action early (experimental)
     async fn autonomic_cue(&self, cue: AutonomicCue, cell_id: &CellId) -> ConductorApiResult<()>;
 
     /// Get a Websocket port which will
     async fn get_arbitrary_admin_websocket_port(&self) -> Option<u16>;
 
+    /// List every open admin and app interface, with the number of clients
+    /// connected to each
+    async fn list_app_interfaces(&self) -> ConductorResult<Vec<InterfaceInfo>>;
+
     /// Return the JoinHandle for all managed tasks, which when resolved will
     /// signal that the Conductor has completely shut down.
     ///
@@ -155,19 +395,55 @@ pub trait ConductorHandleT: Send + Sync {
     /// Send a signal to all managed tasks asking them to end ASAP.
     async fn shutdown(&self);
 
+    /// Flush every open environment to disk, returning how long each took.
+    /// Use this before reading environment files from outside the conductor,
+    /// e.g. for a backup.
+    async fn sync_all(&self) -> ConductorResult<Vec<EnvironmentSyncReport>>;
+
+    /// Upgrade the schema of the Conductor environment from one version to
+    /// a later one, applying each migration registered with
+    /// [ConductorBuilder::with_migration](crate::conductor::ConductorBuilder::with_migration)
+    /// in turn. Every step is applied in one transaction, so if any step is
+    /// missing or fails, the environment is left unchanged.
+    async fn migrate_conductor_state(
+        &self,
+        from_version: u32,
+        to_version: u32,
+    ) -> ConductorResult<()>;
+
     /// Request access to this conductor's keystore
     fn keystore(&self) -> &KeystoreSender;
 
     /// Request access to this conductor's networking handle
     fn holochain_p2p(&self) -> &holochain_p2p::HolochainP2pRef;
 
+    /// Request access to the compiled wasm modules shared by this conductor's ribosomes
+    fn wasm_module_cache(&self) -> &WasmModuleCache;
+
+    /// Request access to this conductor's clock skew tolerances,
+    /// and its estimates of the clock skew of each space's peers
+    fn clock_skew(&self) -> &ClockSkew;
+
     /// Install Cells into ConductorState based on installation info, and run
-    /// genesis on all new source chains
+    /// genesis on all new source chains.
+    /// Every cell must belong to the same agent unless `allow_multiple_agents` is set.
     #[allow(clippy::ptr_arg)]
     async fn install_app(
         self: Arc<Self>,
         app_id: AppId,
         cell_data_with_proofs: Vec<(InstalledCell, Option<MembraneProof>)>,
+        allow_multiple_agents: bool,
+    ) -> ConductorResult<()>;
+
+    /// Install an app with a cell for `agent` in each of the given Dnas,
+    /// which must already be installed.
+    /// The agent's keypair must be in this conductor's keystore.
+    #[allow(clippy::ptr_arg)]
+    async fn install_app_for_agent(
+        self: Arc<Self>,
+        app_id: AppId,
+        agent: AgentPubKey,
+        dnas: Vec<(CellNick, DnaHash, Option<MembraneProof>)>,
     ) -> ConductorResult<()>;
 
     /// Setup the cells from the database
@@ -182,6 +458,28 @@ pub trait ConductorHandleT: Send + Sync {
     #[allow(clippy::ptr_arg)]
     async fn deactivate_app(&self, app_id: AppId) -> ConductorResult<()>;
 
+    /// Set a piece of metadata on an installed app, overwriting any existing
+    /// value for the key. Returns an error if the app is not installed.
+    #[allow(clippy::ptr_arg)]
+    async fn set_app_metadata(
+        &self,
+        app_id: &AppId,
+        key: String,
+        value: SerializedBytes,
+    ) -> ConductorResult<()>;
+
+    /// Get a piece of metadata from an app, if it has been set
+    #[allow(clippy::ptr_arg)]
+    async fn get_app_metadata(
+        &self,
+        app_id: &AppId,
+        key: &str,
+    ) -> ConductorResult<Option<SerializedBytes>>;
+
+    /// Remove a piece of metadata from an app
+    #[allow(clippy::ptr_arg)]
+    async fn delete_app_metadata(&self, app_id: &AppId, key: &str) -> ConductorResult<()>;
+
     /// List Cell Ids
     async fn list_cell_ids(&self) -> ConductorResult<Vec<CellId>>;
 
@@ -192,10 +490,92 @@ pub trait ConductorHandleT: Send + Sync {
     #[allow(clippy::ptr_arg)]
     async fn dump_cell_state(&self, cell_id: &CellId) -> ConductorApiResult<String>;
 
+    /// Get the status of a running cell, including roughly how much
+    /// disk space it takes, for enforcing per-agent storage budgets
+    async fn get_cell_status(&self, cell_id: &CellId) -> ConductorApiResult<CellStatus>;
+
+    /// Get the status of every running cell
+    async fn metrics(&self) -> ConductorApiResult<ConductorMetrics>;
+
+    /// Get how many ops a running cell has published and gossiped
+    /// with its peers, for diagnosing cells which are slow to sync
+    async fn get_cell_gossip_metrics(
+        &self,
+        cell_id: &CellId,
+    ) -> ConductorApiResult<CellGossipMetrics>;
+
+    /// Force a running cell's app validation circuit breaker open or closed,
+    /// to pause app validation of a misbehaving Dna or resume it once fixed
+    async fn force_validation_circuit(
+        &self,
+        cell_id: &CellId,
+        open: bool,
+    ) -> ConductorApiResult<ValidationBreakerStatus>;
+
+    /// Get the journal of errors returned by a cell's queue consumer
+    /// workflows, oldest first
+    async fn get_workflow_errors(
+        &self,
+        cell_id: &CellId,
+    ) -> ConductorApiResult<Vec<WorkflowErrorRecord>>;
+
+    /// Audit the integrity of a cell's persisted data, removing any corrupt
+    /// cache records. If corrupt authored or integrated data is found,
+    /// the cell is quarantined.
+    async fn audit_cell(&self, cell_id: &CellId) -> ConductorApiResult<IntegrityAuditReport>;
+
+    /// Build the validation package for one of a cell's own headers,
+    /// as its author does when an authority asks for it over the network,
+    /// but keeping the private entries which only the author can see
+    async fn build_validation_package(
+        &self,
+        cell_id: &CellId,
+        header_hash: HeaderHash,
+    ) -> ConductorApiResult<ValidationPackageReport>;
+
+    /// Ask the author of a header a cell holds for its validation package
+    /// over the network, as an authority does, and report what was sent.
+    /// The author only sends public entries, and any private entry which
+    /// arrives anyway is hidden unless the cell's agent is its author.
+    async fn fetch_validation_package_from_author(
+        &self,
+        cell_id: &CellId,
+        header_hash: HeaderHash,
+    ) -> ConductorApiResult<ValidationPackageReport>;
+
+    /// Resolve the fork in a quarantined cell's source chain by keeping the
+    /// branch beginning with `keep`, one of the fork's competing headers.
+    /// The other branches' sequence records are removed but their elements
+    /// are kept. If the chain is then intact the cell is started again.
+    async fn resolve_fork_keep(
+        self: Arc<Self>,
+        cell_id: &CellId,
+        keep: &HeaderHash,
+    ) -> ConductorApiResult<ForkReport>;
+
+    /// Report how far each of some cells has got syncing with the rest of
+    /// its network, in the same order as the cells
+    async fn network_info(&self, cells: Vec<CellId>) -> ConductorResult<Vec<NetworkInfo>>;
+
     /// Access the broadcast Sender which will send a Signal across every
     /// attached app interface
     async fn signal_broadcaster(&self) -> SignalBroadcaster;
 
+    /// The number of receivers currently listening for signals across all
+    /// attached app interfaces
+    async fn signal_subscriber_count(&self) -> ConductorResult<usize>;
+
+    /// Subscribe an app interface to the signals of an App, so that the
+    /// signals the App's Cells emit reach it through
+    /// [SignalBroadcaster::broadcast_to_app].
+    /// Per-cell filters are not yet supported: a subscription either
+    /// blocks all of the App's signals or allows all of them.
+    async fn update_signal_subscription(
+        &self,
+        interface_id: AppInterfaceId,
+        subscription: SignalSubscription,
+    ) -> ConductorResult<()>;
+
     /// Get info about an installed App, whether active or inactive
     #[allow(clippy::ptr_arg)]
     async fn get_app_info(&self, app_id: &AppId) -> ConductorResult<Option<InstalledApp>>;
@@ -224,6 +604,8 @@ pub struct ConductorHandleImpl<DS: DnaStore + 'static> {
     pub(crate) conductor: RwLock<Conductor<DS>>,
     pub(crate) keystore: KeystoreSender,
     pub(crate) holochain_p2p: holochain_p2p::HolochainP2pRef,
+    pub(crate) wasm_module_cache: WasmModuleCache,
+    pub(crate) clock_skew: ClockSkew,
 }
 
 #[async_trait::async_trait]
@@ -255,6 +637,16 @@ impl<DS: DnaStore + 'static> ConductorHandleT for ConductorHandleImpl<DS> {
         Ok(())
     }
 
+    async fn install_dna_from_path(&self, path: PathBuf) -> ConductorResult<DnaHash> {
+        let content = tokio::fs::read(&path).await?;
+        let dna = DnaFile::from_file_content(&content)
+            .await
+            .map_err(|error| ConductorError::DnaDecodeError { path, error })?;
+        let dna_hash = dna.dna_hash().clone();
+        self.install_dna(dna).await?;
+        Ok(dna_hash)
+    }
+
     async fn add_dnas(&self) -> ConductorResult<()> {
         let (dnas, entry_defs) = self
             .conductor
@@ -276,10 +668,86 @@ impl<DS: DnaStore + 'static> ConductorHandleT for ConductorHandleImpl<DS> {
         self.conductor.read().await.dna_store().get(hash)
     }
 
+    async fn get_dna_properties(
+        &self,
+        dna_hash: &DnaHash,
+    ) -> ConductorResult<Option<SerializedBytes>> {
+        let lock = self.conductor.read().await;
+        lock.check_running()?;
+        Ok(lock
+            .dna_store()
+            .get(dna_hash)
+            .map(|dna_file| dna_file.dna().properties.clone()))
+    }
+
+    async fn compare_dnas(&self, a: &DnaHash, b: &DnaHash) -> ConductorResult<Option<DnaDiff>> {
+        let lock = self.conductor.read().await;
+        lock.check_running()?;
+        Ok(lock.dna_store().diff(a, b))
+    }
+
     async fn get_entry_def(&self, key: &EntryDefBufferKey) -> Option<EntryDef> {
         self.conductor.read().await.dna_store().get_entry_def(key)
     }
 
+    async fn precompile_dna(&self, dna_hash: &DnaHash) -> ConductorApiResult<()> {
+        let dna = self
+            .get_dna(dna_hash)
+            .await
+            .ok_or_else(|| ConductorError::DnaMissing(dna_hash.clone()))?;
+        let wasm_module_cache = self.wasm_module_cache.clone();
+        // Compiling is slow and blocks the thread
+        tokio::task::spawn_blocking(move || wasm_module_cache.precompile(&dna))
+            .await
+            .map_err(CellError::from)?
+            .map_err(CellError::from)?;
+        Ok(())
+    }
+
+    async fn export_dna(&self, hash: &DnaHash) -> ConductorResult<DnaFileBundle> {
+        self.conductor.read().await.export_dna(hash).await
+    }
+
+    async fn list_entry_defs(
+        &self,
+        dna_hash: &DnaHash,
+    ) -> ConductorResult<Vec<(EntryDefBufferKey, EntryDef)>> {
+        let lock = self.conductor.read().await;
+        lock.check_running()?;
+        lock.list_entry_defs(dna_hash).await
+    }
+
+    async fn list_zome_functions(
+        &self,
+        cell_id: &CellId,
+    ) -> ConductorApiResult<BTreeMap<ZomeName, Vec<FunctionName>>> {
+        self.conductor.read().await.cell_by_id(cell_id)?;
+        let dna_hash = cell_id.dna_hash();
+        let dna = self
+            .get_dna(dna_hash)
+            .await
+            .ok_or_else(|| ConductorError::DnaMissing(dna_hash.clone()))?;
+        let ribosome = WasmRibosome::new(dna).with_module_cache(self.wasm_module_cache.clone());
+        // A module which isn't loaded yet may have to be compiled
+        Ok(
+            tokio::task::spawn_blocking(move || ribosome.list_zome_functions())
+                .await
+                .map_err(CellError::from)?
+                .map_err(CellError::from)?,
+        )
+    }
+
+    async fn prune_expired_agent_infos(&self) -> ConductorResult<usize> {
+        self.conductor
+            .read()
+            .await
+            .prune_expired_agent_infos(p2p_store::now_ms())
+    }
+
+    async fn inspect_keystore(&self) -> ConductorResult<Vec<AgentPubKey>> {
+        Ok(self.keystore.list_sign_pub_keys().await?)
+    }
+
     #[instrument(skip(self))]
     /// Warning: returning an error from this function kills the network for the conductor.
     async fn dispatch_holochain_p2p_event(
@@ -310,6 +778,95 @@ impl<DS: DnaStore + 'static> ConductorHandleT for ConductorHandleImpl<DS> {
                     .map_err(holochain_p2p::HolochainP2pError::other);
                 respond.respond(Ok(async move { res }.boxed().into()));
             }
+            PutPeerMeta {
+                dna_hash,
+                peer,
+                peer_meta,
+                respond,
+                ..
+            } => {
+                let res = lock
+                    .put_peer_meta(dna_hash, peer, peer_meta)
+                    .map_err(holochain_p2p::HolochainP2pError::other);
+                respond.respond(Ok(async move { res }.boxed().into()));
+            }
+            GetPeerMeta {
+                dna_hash,
+                peer,
+                respond,
+                ..
+            } => {
+                let res = lock
+                    .get_peer_meta(dna_hash, peer)
+                    .map_err(holochain_p2p::HolochainP2pError::other);
+                respond.respond(Ok(async move { res }.boxed().into()));
+            }
+            GetSpacePeerMeta {
+                dna_hash, respond, ..
+            } => {
+                let res = lock
+                    .get_space_peer_meta(dna_hash)
+                    .map_err(holochain_p2p::HolochainP2pError::other);
+                respond.respond(Ok(async move { res }.boxed().into()));
+            }
+            RequestArcSync {
+                dna_hash,
+                to_agent,
+                from_agent,
+                from_info,
+                respond,
+                ..
+            } => {
+                // Store the remote claim if it checks out, then reciprocate
+                // with our own agent info if we have published it.
+                let res = match lock
+                    .put_remote_agent_info_signed(&dna_hash, &from_agent, from_info)
+                    .await
+                {
+                    Ok(()) => lock.get_agent_info_signed(
+                        Arc::new(kitsune_p2p::KitsuneSpace(dna_hash.into_inner())),
+                        Arc::new(kitsune_p2p::KitsuneAgent(to_agent.into_inner())),
+                    ),
+                    Err(e) => Err(e),
+                }
+                .map_err(holochain_p2p::HolochainP2pError::other);
+                respond.respond(Ok(async move { res }.boxed().into()));
+            }
+            PeerConnected {
+                dna_hash,
+                to_agent,
+                urls,
+                respond,
+                ..
+            } => {
+                // Every interface hears about peers, as they aren't specific to an app
+                let signal = SystemSignal::PeerConnected {
+                    dna_hash,
+                    agent: to_agent,
+                    urls: urls.iter().map(|url| url.to_string()).collect(),
+                };
+                let res = lock
+                    .signal_broadcaster()
+                    .send(signal.into())
+                    .map_err(holochain_p2p::HolochainP2pError::other);
+                respond.respond(Ok(async move { res }.boxed().into()));
+            }
+            PeerDisconnected {
+                dna_hash,
+                to_agent,
+                respond,
+                ..
+            } => {
+                let signal = SystemSignal::PeerDisconnected {
+                    dna_hash,
+                    agent: to_agent,
+                };
+                let res = lock
+                    .signal_broadcaster()
+                    .send(signal.into())
+                    .map_err(holochain_p2p::HolochainP2pError::other);
+                respond.respond(Ok(async move { res }.boxed().into()));
+            }
             _ => {
                 let cell: &Cell = lock.cell_by_id(cell_id)?;
                 trace!(agent = ?cell_id.agent_pubkey(), event = ?event);
@@ -332,6 +889,109 @@ impl<DS: DnaStore + 'static> ConductorHandleT for ConductorHandleImpl<DS> {
         Ok(cell.call_zome(invocation).await?)
     }
 
+    async fn call_zome_dry_run(
+        &self,
+        invocation: ZomeCallInvocation,
+    ) -> ConductorApiResult<(ZomeCallInvocationResult, Vec<Header>)> {
+        let lock = self.conductor.read().await;
+        debug!(cell_id = ?invocation.cell_id);
+        let cell: &Cell = lock.cell_by_id(&invocation.cell_id)?;
+        Ok(cell.call_zome_dry_run(invocation).await?)
+    }
+
+    #[cfg(any(test, feature = "bench"))]
+    async fn benchmark_zome_call(
+        &self,
+        invocation: ZomeCallInvocation,
+        iterations: u32,
+    ) -> ConductorApiResult<BenchmarkResult> {
+        if iterations < 2 {
+            return Err(ConductorApiError::BenchmarkTooFewIterations(iterations));
+        }
+        let mut timings = Vec::with_capacity(iterations as usize - 1);
+        for i in 0..iterations {
+            let start = std::time::Instant::now();
+            self.call_zome(invocation.clone())
+                .await?
+                .map_err(|e| Box::new(WorkflowError::from(e)))?;
+            // The first call is a warm-up
+            if i > 0 {
+                timings.push(start.elapsed());
+            }
+        }
+        BenchmarkResult::from_timings(timings)
+            .ok_or(ConductorApiError::BenchmarkTooFewIterations(iterations))
+    }
+
+    async fn call_remote(
+        &self,
+        from_cell: &CellId,
+        to_agent: AgentPubKey,
+        zome_name: ZomeName,
+        fn_name: FunctionName,
+        cap: Option<CapSecret>,
+        payload: SerializedBytes,
+        options: CallRemoteOptions,
+    ) -> ConductorApiResult<SerializedBytes> {
+        // Don't hold the lock while waiting on the network
+        let network = self
+            .conductor
+            .read()
+            .await
+            .cell_by_id(from_cell)?
+            .holochain_p2p_cell()
+            .clone();
+        call_remote_with_retries(&to_agent, options, || {
+            let mut network = network.clone();
+            let to_agent = to_agent.clone();
+            let zome_name = zome_name.clone();
+            let fn_name = fn_name.clone();
+            let cap = cap.clone();
+            let payload = payload.clone();
+            async move {
+                Ok(network
+                    .call_remote(to_agent, zome_name, fn_name, cap, payload)
+                    .await
+                    .map_err(CellError::from)?)
+            }
+        })
+        .await
+    }
+
+    async fn countersigning_request(
+        &self,
+        cell_id: &CellId,
+        to_agent: AgentPubKey,
+        payload: SerializedBytes,
+    ) -> ConductorApiResult<CountersigningPreflight> {
+        let lock = self.conductor.read().await;
+        let cell: &Cell = lock.cell_by_id(cell_id)?;
+        Ok(cell.countersigning_request(to_agent, payload).await?)
+    }
+
+    async fn countersigning_respond(
+        &self,
+        cell_id: &CellId,
+        initiator: AgentPubKey,
+    ) -> ConductorApiResult<Signature> {
+        let lock = self.conductor.read().await;
+        let cell: &Cell = lock.cell_by_id(cell_id)?;
+        Ok(cell.countersigning_respond(initiator).await?)
+    }
+
+    async fn countersigning_session(
+        &self,
+        cell_id: &CellId,
+        counterparty: &AgentPubKey,
+    ) -> ConductorApiResult<Option<CountersigningSession>> {
+        Ok(self
+            .conductor
+            .read()
+            .await
+            .cell_by_id(cell_id)?
+            .countersigning_session(counterparty))
+    }
+
     async fn autonomic_cue(&self, cue: AutonomicCue, cell_id: &CellId) -> ConductorApiResult<()> {
         let lock = self.conductor.write().await;
         let cell = lock.cell_by_id(cell_id)?;
@@ -350,10 +1010,31 @@ impl<DS: DnaStore + 'static> ConductorHandleT for ConductorHandleImpl<DS> {
             .get_arbitrary_admin_websocket_port()
     }
 
+    async fn list_app_interfaces(&self) -> ConductorResult<Vec<InterfaceInfo>> {
+        let lock = self.conductor.read().await;
+        lock.check_running()?;
+        Ok(lock.list_app_interfaces())
+    }
+
     async fn shutdown(&self) {
         self.conductor.write().await.shutdown()
     }
 
+    async fn sync_all(&self) -> ConductorResult<Vec<EnvironmentSyncReport>> {
+        self.conductor.read().await.sync_environments()
+    }
+
+    async fn migrate_conductor_state(
+        &self,
+        from_version: u32,
+        to_version: u32,
+    ) -> ConductorResult<()> {
+        self.conductor
+            .read()
+            .await
+            .migrate_state(from_version, to_version)
+    }
+
     fn keystore(&self) -> &KeystoreSender {
         &self.keystore
     }
@@ -362,11 +1043,23 @@ impl<DS: DnaStore + 'static> ConductorHandleT for ConductorHandleImpl<DS> {
         &self.holochain_p2p
     }
 
+    fn wasm_module_cache(&self) -> &WasmModuleCache {
+        &self.wasm_module_cache
+    }
+
+    fn clock_skew(&self) -> &ClockSkew {
+        &self.clock_skew
+    }
+
     async fn install_app(
         self: Arc<Self>,
         app_id: AppId,
         cell_data: Vec<(InstalledCell, Option<MembraneProof>)>,
+        allow_multiple_agents: bool,
     ) -> ConductorResult<()> {
+        if !allow_multiple_agents {
+            check_single_agent(&app_id, cell_data.iter().map(|(cell, _)| cell))?;
+        }
         self.conductor
             .read()
             .await
@@ -390,6 +1083,23 @@ impl<DS: DnaStore + 'static> ConductorHandleT for ConductorHandleImpl<DS> {
             .await
     }
 
+    async fn install_app_for_agent(
+        self: Arc<Self>,
+        app_id: AppId,
+        agent: AgentPubKey,
+        dnas: Vec<(CellNick, DnaHash, Option<MembraneProof>)>,
+    ) -> ConductorResult<()> {
+        check_agent_in_keystore(self.keystore(), &agent).await?;
+        let cell_data = dnas
+            .into_iter()
+            .map(|(nick, dna_hash, proof)| {
+                let cell_id = CellId::new(dna_hash, agent.clone());
+                (InstalledCell::new(cell_id, nick), proof)
+            })
+            .collect();
+        self.install_app(app_id, cell_data, false).await
+    }
+
     async fn setup_cells(self: Arc<Self>) -> ConductorResult<Vec<CreateAppError>> {
         let cells = {
             let lock = self.conductor.read().await;
@@ -440,6 +1150,39 @@ impl<DS: DnaStore + 'static> ConductorHandleT for ConductorHandleImpl<DS> {
         Ok(())
     }
 
+    async fn set_app_metadata(
+        &self,
+        app_id: &AppId,
+        key: String,
+        value: SerializedBytes,
+    ) -> ConductorResult<()> {
+        self.conductor
+            .read()
+            .await
+            .set_app_metadata(app_id, &key, value)
+            .await
+    }
+
+    async fn get_app_metadata(
+        &self,
+        app_id: &AppId,
+        key: &str,
+    ) -> ConductorResult<Option<SerializedBytes>> {
+        self.conductor
+            .read()
+            .await
+            .get_app_metadata(app_id, key)
+            .await
+    }
+
+    async fn delete_app_metadata(&self, app_id: &AppId, key: &str) -> ConductorResult<()> {
+        self.conductor
+            .read()
+            .await
+            .delete_app_metadata(app_id, key)
+            .await
+    }
+
     async fn list_cell_ids(&self) -> ConductorResult<Vec<CellId>> {
         self.conductor.read().await.list_cell_ids().await
     }
@@ -452,8 +1195,157 @@ impl<DS: DnaStore + 'static> ConductorHandleT for ConductorHandleImpl<DS> {
         self.conductor.read().await.dump_cell_state(cell_id).await
     }
 
+    async fn get_cell_status(&self, cell_id: &CellId) -> ConductorApiResult<CellStatus> {
+        self.conductor.read().await.get_cell_status(cell_id)
+    }
+
+    async fn metrics(&self) -> ConductorApiResult<ConductorMetrics> {
+        self.conductor.read().await.metrics()
+    }
+
+    async fn get_cell_gossip_metrics(
+        &self,
+        cell_id: &CellId,
+    ) -> ConductorApiResult<CellGossipMetrics> {
+        self.conductor.read().await.get_cell_gossip_metrics(cell_id)
+    }
+
+    async fn force_validation_circuit(
+        &self,
+        cell_id: &CellId,
+        open: bool,
+    ) -> ConductorApiResult<ValidationBreakerStatus> {
+        self.conductor
+            .read()
+            .await
+            .force_validation_circuit(cell_id, open)
+    }
+
+    async fn get_workflow_errors(
+        &self,
+        cell_id: &CellId,
+    ) -> ConductorApiResult<Vec<WorkflowErrorRecord>> {
+        let env = self
+            .conductor
+            .read()
+            .await
+            .cell_by_id(cell_id)?
+            .env()
+            .clone();
+        Ok(workflow_errors::get_workflow_errors(&env.into())?)
+    }
+
+    async fn audit_cell(&self, cell_id: &CellId) -> ConductorApiResult<IntegrityAuditReport> {
+        // Don't hold the lock while auditing, which can take a while
+        let env = self
+            .conductor
+            .read()
+            .await
+            .cell_by_id(cell_id)?
+            .env()
+            .clone();
+        let report = integrity_audit::audit_cell_env(&env).await?;
+        if !report.is_intact() {
+            error!(msg = "Quarantining cell with corrupt data", ?cell_id);
+            self.conductor
+                .write()
+                .await
+                .quarantine_cell(cell_id.clone(), report.clone())
+                .await?;
+        }
+        Ok(report)
+    }
+
+    async fn build_validation_package(
+        &self,
+        cell_id: &CellId,
+        header_hash: HeaderHash,
+    ) -> ConductorApiResult<ValidationPackageReport> {
+        let lock = self.conductor.read().await;
+        let cell: &Cell = lock.cell_by_id(cell_id)?;
+        Ok(cell.build_validation_package(header_hash).await?)
+    }
+
+    async fn fetch_validation_package_from_author(
+        &self,
+        cell_id: &CellId,
+        header_hash: HeaderHash,
+    ) -> ConductorApiResult<ValidationPackageReport> {
+        // Don't hold the lock while waiting on the network
+        let (mut network, author, required) = {
+            let lock = self.conductor.read().await;
+            let cell: &Cell = lock.cell_by_id(cell_id)?;
+            let (author, required) = cell.validation_package_author(header_hash.clone()).await?;
+            (cell.holochain_p2p_cell().clone(), author, required)
+        };
+        let package = network
+            .get_validation_package(author.clone(), header_hash)
+            .await
+            .map_err(CellError::from)?;
+        Ok(ValidationPackageReport::new(
+            package.0,
+            required,
+            &author == cell_id.agent_pubkey(),
+        ))
+    }
+
+    async fn resolve_fork_keep(
+        self: Arc<Self>,
+        cell_id: &CellId,
+        keep: &HeaderHash,
+    ) -> ConductorApiResult<ForkReport> {
+        let fork = self
+            .conductor
+            .read()
+            .await
+            .resolve_fork_keep(cell_id, keep)
+            .await?;
+        for e in self.clone().setup_cells().await? {
+            error!(
+                msg = "Failed to restart cells after resolving a fork",
+                ?cell_id,
+                ?e
+            );
+        }
+        Ok(fork)
+    }
+
+    async fn network_info(&self, cells: Vec<CellId>) -> ConductorResult<Vec<NetworkInfo>> {
+        let lock = self.conductor.read().await;
+        let mut infos = Vec::with_capacity(cells.len());
+        for cell_id in cells {
+            let mut info = lock.cell_by_id(&cell_id)?.network_info().await?;
+            info.clock_skew_ms = self.clock_skew.estimate_ms(cell_id.dna_hash());
+            infos.push(info);
+        }
+        Ok(infos)
+    }
+
     async fn signal_broadcaster(&self) -> SignalBroadcaster {
-        self.conductor.read().await.signal_broadcaster()
+        let lock = self.conductor.read().await;
+        let cell_apps = lock.signal_cell_apps().await.unwrap_or_else(|err| {
+            error!(?err, "Could not read which Apps each Cell belongs to");
+            HashMap::new()
+        });
+        lock.signal_broadcaster().with_cell_apps(cell_apps)
+    }
+
+    async fn signal_subscriber_count(&self) -> ConductorResult<usize> {
+        let lock = self.conductor.read().await;
+        lock.check_running()?;
+        Ok(lock.signal_broadcaster().subscriber_count())
+    }
+
+    async fn update_signal_subscription(
+        &self,
+        interface_id: AppInterfaceId,
+        subscription: SignalSubscription,
+    ) -> ConductorResult<()> {
+        let mut lock = self.conductor.write().await;
+        lock.check_running()?;
+        let subscribe = !subscription.blocks_all();
+        lock.update_signal_subscription(interface_id, subscription.app_id().clone(), subscribe);
+        Ok(())
     }
 
     async fn get_app_info(&self, app_id: &AppId) -> ConductorResult<Option<InstalledApp>> {
@@ -489,3 +1381,218 @@ impl<DS: DnaStore + 'static> ConductorHandleT for ConductorHandleImpl<DS> {
         Ok(lock.get_state_from_handle().await?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conductor::{dna_store::MockDnaStore, ConductorBuilder};
+    use ::fixt::prelude::*;
+    use holo_hash::fixt::AgentPubKeyFixturator;
+    use holochain_state::test_utils::{
+        test_conductor_env, test_p2p_env, test_wasm_env, TestEnvironment,
+    };
+    use holochain_types::{
+        dna::DnaDef,
+        test_utils::{fake_agent_pubkey_1, fake_agent_pubkey_2, fake_cell_id},
+    };
+    use holochain_wasm_test_utils::TestWasm;
+    use matches::assert_matches;
+    use std::{
+        convert::{TryFrom, TryInto},
+        sync::atomic::{AtomicU32, Ordering},
+    };
+
+    #[tokio::test(threaded_scheduler)]
+    async fn call_remote_retries_until_out_of_attempts() {
+        let to_agent = fake_agent_pubkey_1();
+        let options = CallRemoteOptions {
+            timeout: Duration::from_millis(10),
+            retries: 2,
+        };
+
+        // Every attempt hangs
+        let attempts = AtomicU32::new(0);
+        let result = call_remote_with_retries(&to_agent, options, || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            futures::future::pending()
+        })
+        .await;
+        assert_matches!(
+            result,
+            Err(ConductorApiError::RemoteCallTimeout { attempts: 3, .. })
+        );
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        // The last attempt gets through
+        let attempts = AtomicU32::new(0);
+        let result = call_remote_with_retries(&to_agent, options, || {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+            async move {
+                if attempt < 3 {
+                    futures::future::pending::<()>().await;
+                }
+                Ok(().try_into().unwrap())
+            }
+        })
+        .await;
+        assert!(result.is_ok());
+
+        // Every attempt fails, so the last failure is returned
+        let result = call_remote_with_retries(&to_agent, options, || async {
+            Err(ConductorApiError::CellMissing(fake_cell_id(1)))
+        })
+        .await;
+        assert_matches!(result, Err(ConductorApiError::CellMissing(_)));
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn inspect_keystore_lists_sign_keys() {
+        let test_env = test_conductor_env();
+        let wasm_env = test_wasm_env();
+        let p2p_env = test_p2p_env();
+        let handle = ConductorBuilder::with_mock_dna_store(MockDnaStore::new())
+            .test(test_env, wasm_env.env(), p2p_env.env())
+            .await
+            .unwrap();
+
+        // The test keystore holds the two fixture agents
+        assert_eq!(
+            handle.inspect_keystore().await.unwrap(),
+            vec![fake_agent_pubkey_1(), fake_agent_pubkey_2()]
+        );
+
+        let shutdown = handle.take_shutdown_handle().await.unwrap();
+        handle.shutdown().await;
+        shutdown.await.unwrap();
+    }
+
+    async fn test_dna_file(uuid: &str) -> DnaFile {
+        DnaFile::new(
+            DnaDef {
+                name: "install_app_test".to_string(),
+                uuid: uuid.to_string(),
+                properties: SerializedBytes::try_from(()).unwrap(),
+                zomes: vec![TestWasm::Foo.into()].into(),
+            },
+            vec![TestWasm::Foo.into()],
+        )
+        .await
+        .unwrap()
+    }
+
+    /// A conductor whose Dna store is the given Dnas,
+    /// or which panics if it is asked for any Dna when there are none
+    async fn test_handle(dna_files: Vec<DnaFile>) -> (ConductorHandle, Vec<TestEnvironment>) {
+        let mut dna_store = MockDnaStore::new();
+        if !dna_files.is_empty() {
+            dna_store.expect_get().returning(move |hash| {
+                dna_files
+                    .iter()
+                    .find(|dna_file| dna_file.dna_hash() == hash)
+                    .cloned()
+            });
+            dna_store.expect_add_dnas::<Vec<_>>().return_const(());
+            dna_store.expect_add_entry_defs::<Vec<_>>().return_const(());
+            dna_store.expect_get_entry_def().return_const(None);
+        }
+        let (wasm_env, p2p_env) = (test_wasm_env(), test_p2p_env());
+        let handle = ConductorBuilder::with_mock_dna_store(dna_store)
+            .test(test_conductor_env(), wasm_env.env(), p2p_env.env())
+            .await
+            .unwrap();
+        (handle, vec![wasm_env, p2p_env])
+    }
+
+    async fn shutdown(handle: ConductorHandle) {
+        let shutdown = handle.take_shutdown_handle().await.unwrap();
+        handle.shutdown().await;
+        shutdown.await.unwrap();
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn install_app_rejects_cells_of_different_agents() {
+        // No genesis may run, so the Dna store has nothing to give
+        let (handle, _envs) = test_handle(vec![]).await;
+        let cell_data = vec![
+            (InstalledCell::new(fake_cell_id(1), "alice".into()), None),
+            (
+                InstalledCell::new(
+                    CellId::new(fake_cell_id(1).dna_hash().clone(), fake_agent_pubkey_2()),
+                    "bob".into(),
+                ),
+                None,
+            ),
+        ];
+        let result = handle
+            .clone()
+            .install_app("split".to_string(), cell_data, false)
+            .await;
+        assert_matches!(
+            result,
+            Err(ConductorError::AppAgentsDiffer { app_id, agents })
+                if app_id == "split" && agents.len() == 2
+        );
+        assert_eq!(
+            handle.get_app_info(&"split".to_string()).await.unwrap(),
+            None
+        );
+        shutdown(handle).await;
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn install_app_for_agent_installs_every_dna_under_one_key() {
+        let dna_files = vec![test_dna_file("one").await, test_dna_file("two").await];
+        let (handle, _envs) = test_handle(dna_files.clone()).await;
+        let agent = fake_agent_pubkey_1();
+
+        handle
+            .clone()
+            .install_app_for_agent(
+                "both".to_string(),
+                agent.clone(),
+                dna_files
+                    .iter()
+                    .enumerate()
+                    .map(|(i, dna_file)| (i.to_string(), dna_file.dna_hash().clone(), None))
+                    .collect(),
+            )
+            .await
+            .unwrap();
+
+        let app = handle
+            .get_app_info(&"both".to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        let cell_ids = app
+            .cell_data
+            .iter()
+            .map(|cell| cell.as_id().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            cell_ids,
+            dna_files
+                .iter()
+                .map(|dna_file| CellId::new(dna_file.dna_hash().clone(), agent.clone()))
+                .collect::<Vec<_>>()
+        );
+        shutdown(handle).await;
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn install_app_for_agent_rejects_an_agent_without_a_keypair() {
+        // No genesis may run, so the Dna store has nothing to give
+        let (handle, _envs) = test_handle(vec![]).await;
+        let agent = fixt!(AgentPubKey);
+        let result = handle
+            .clone()
+            .install_app_for_agent(
+                "stranger".to_string(),
+                agent.clone(),
+                vec![("0".to_string(), fake_cell_id(1).dna_hash().clone(), None)],
+            )
+            .await;
+        assert_matches!(result, Err(ConductorError::AgentKeyMissing(a)) if a == agent);
+        shutdown(handle).await;
+    }
+}
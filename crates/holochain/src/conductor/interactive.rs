@@ -89,7 +89,7 @@ fn save_default_config_toml(path: &Path) -> ConductorResult<ConductorConfig> {
     })?;
     std::fs::create_dir_all(dir)?;
     let default = ConductorConfig::default();
-    let content_toml = toml::to_string(&default)?;
+    let content_toml = default.to_toml()?;
     std::fs::write(path, content_toml)?;
     Ok(default)
 }
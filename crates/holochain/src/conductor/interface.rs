@@ -5,41 +5,133 @@
 //! Currently the only InterfaceDriver is a Websocket-based one, whose
 //! implementation can be found in the `websocket` module here.
 
-use crate::{conductor::api::*, core::signal::Signal};
-use error::{InterfaceError, InterfaceResult};
+use crate::{
+    conductor::api::*,
+    core::signal::{Signal, SystemSignal},
+};
+use error::InterfaceResult;
+use holochain_types::{app::AppId, cell::CellId};
 use serde::{Deserialize, Serialize};
+use signal_queue::{SignalQueue, SignalQueueStats};
+use std::collections::HashMap;
 use std::convert::TryInto;
-use tokio::sync::broadcast;
+use tracing::*;
 
 #[allow(missing_docs)]
 pub mod error;
+pub mod signal_queue;
 pub mod websocket;
 
-/// A collection of Senders to be used for emitting Signals from a Cell.
-/// There is one Sender per attached Interface
+/// A collection of signal queues to be used for emitting Signals from a Cell.
+/// There is one queue per attached Interface, and each App may additionally
+/// be routed to the subset of Interfaces which are subscribed to it.
+/// The signals a Cell emits follow the routes of the Apps it belongs to.
+///
+/// Sending never waits on an interface, see [signal_queue].
 #[derive(Clone, Debug)]
-pub struct SignalBroadcaster(Vec<broadcast::Sender<Signal>>);
+pub struct SignalBroadcaster {
+    queues: Vec<SignalQueue>,
+    app_queues: HashMap<AppId, Vec<SignalQueue>>,
+    cell_apps: HashMap<CellId, Vec<AppId>>,
+}
 
 impl SignalBroadcaster {
     /// send the signal to the connected client
     pub fn send(&mut self, sig: Signal) -> InterfaceResult<()> {
-        self.0
-            .iter_mut()
-            .map(|tx| tx.send(sig.clone()))
-            .collect::<Result<Vec<_>, broadcast::SendError<Signal>>>()
-            .map_err(InterfaceError::SignalSend)?;
+        self.send_to(&self.queues, sig);
+        Ok(())
+    }
+
+    /// Send the signal only to the interfaces subscribed to signals from
+    /// this App. If no interface is subscribed, the signal is dropped.
+    #[allow(clippy::ptr_arg)]
+    pub fn broadcast_to_app(&self, app_id: &AppId, signal: Signal) -> InterfaceResult<()> {
+        if let Some(queues) = self.app_queues.get(app_id) {
+            self.send_to(queues, signal);
+        }
         Ok(())
     }
 
+    /// Send a signal emitted by a Cell to the interfaces subscribed to the
+    /// Apps the Cell belongs to. While none of those Apps has a subscribed
+    /// interface, the signal is sent to every interface, as with [send](Self::send).
+    pub fn send_from_cell(&mut self, cell_id: &CellId, sig: Signal) -> InterfaceResult<()> {
+        match self.cell_apps.get(cell_id) {
+            Some(app_ids) => {
+                for app_id in app_ids {
+                    self.broadcast_to_app(app_id, sig.clone())?;
+                }
+                Ok(())
+            }
+            None => self.send(sig),
+        }
+    }
+
+    /// Queue the signal on each interface. When an interface starts dropping
+    /// signals, every other interface is told with a SlowConsumer signal.
+    fn send_to(&self, queues: &[SignalQueue], sig: Signal) {
+        let mut slow: Vec<&SignalQueue> = queues
+            .iter()
+            .filter(|queue| queue.push(sig.clone()))
+            .collect();
+        while let Some(slow_queue) = slow.pop() {
+            let stats = slow_queue.stats();
+            warn!(
+                interface_id = ?stats.interface_id,
+                depth = stats.depth,
+                "App interface is not keeping up with its signals, dropping the oldest"
+            );
+            let warning: Signal = SystemSignal::SlowConsumer {
+                interface_id: stats.interface_id.clone(),
+                dropped: stats.dropped,
+            }
+            .into();
+            for queue in &self.queues {
+                if queue.interface_id() != &stats.interface_id && queue.push(warning.clone()) {
+                    slow.push(queue);
+                }
+            }
+        }
+    }
+
+    /// The number of clients listening for signals, across all interfaces
+    pub fn subscriber_count(&self) -> usize {
+        self.queues
+            .iter()
+            .map(|queue| queue.connections().count())
+            .sum()
+    }
+
+    /// The state of each interface's signal queue
+    pub fn queue_stats(&self) -> Vec<SignalQueueStats> {
+        self.queues.iter().map(|queue| queue.stats()).collect()
+    }
+
     /// internal constructor
-    pub fn new(senders: Vec<broadcast::Sender<Signal>>) -> Self {
-        Self(senders)
+    pub fn new(queues: Vec<SignalQueue>) -> Self {
+        Self {
+            queues,
+            app_queues: HashMap::new(),
+            cell_apps: HashMap::new(),
+        }
+    }
+
+    /// Set which interfaces' queues receive the signals of each App
+    pub fn with_app_routes(mut self, app_queues: HashMap<AppId, Vec<SignalQueue>>) -> Self {
+        self.app_queues = app_queues;
+        self
+    }
+
+    /// Set which of the Apps with subscribed interfaces each Cell belongs to
+    pub fn with_cell_apps(mut self, cell_apps: HashMap<CellId, Vec<AppId>>) -> Self {
+        self.cell_apps = cell_apps;
+        self
     }
 
     #[cfg(test)]
     /// A sender with nothing to send to. A placeholder for tests
     pub fn noop() -> Self {
-        Self(Vec::new())
+        Self::new(Vec::new())
     }
 }
 
@@ -58,3 +150,153 @@ pub enum InterfaceDriver {
         port: u16,
     },
 }
+
+/// The kind of API served by an interface
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterfaceType {
+    /// An [AdminInterfaceApi]
+    Admin,
+    /// An [AppInterfaceApi]
+    App,
+}
+
+/// An open interface and how many clients are connected to it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterfaceInfo {
+    /// The port the interface is listening on
+    pub port: u16,
+    /// The kind of API the interface serves
+    pub interface_type: InterfaceType,
+    /// How many clients are currently connected
+    pub connected_client_count: usize,
+    /// The state of the interface's signal queue, for app interfaces
+    pub signal_queue: Option<SignalQueueStats>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conductor::interface::websocket::ConnectionCounter;
+    use crate::core::signal::test_signal;
+    use holochain_types::test_utils::fake_cell_id;
+
+    fn queue(interface_id: &str, depth: usize) -> SignalQueue {
+        SignalQueue::new(interface_id.into(), depth, ConnectionCounter::default())
+    }
+
+    #[test]
+    fn subscriber_count_sums_all_interfaces() {
+        let (q1, q2, q3) = (queue("1", 1), queue("2", 1), queue("3", 1));
+        let _c1 = q1.connections().connect();
+        let _c2 = q2.connections().connect();
+        let _c3 = q2.connections().connect();
+        drop(q3.connections().connect());
+        let broadcaster = SignalBroadcaster::new(vec![q1, q2, q3]);
+        assert_eq!(broadcaster.subscriber_count(), 3);
+        assert_eq!(SignalBroadcaster::noop().subscriber_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn broadcast_to_app_only_reaches_subscribed_interfaces() {
+        let (q1, q2) = (queue("1", 1), queue("2", 1));
+        let mut app_queues = HashMap::new();
+        app_queues.insert("app-1".to_string(), vec![q1.clone()]);
+        let broadcaster =
+            SignalBroadcaster::new(vec![q1.clone(), q2.clone()]).with_app_routes(app_queues);
+
+        let signal = test_signal("hello");
+        broadcaster
+            .broadcast_to_app(&"app-1".to_string(), signal.clone())
+            .unwrap();
+        assert_eq!(q1.recv().await.unwrap(), signal);
+        assert_eq!(q2.stats().queued, 0);
+
+        // An app with no subscribed interfaces goes nowhere
+        broadcaster
+            .broadcast_to_app(&"app-2".to_string(), signal)
+            .unwrap();
+        assert_eq!(q1.stats().queued, 0);
+    }
+
+    #[tokio::test]
+    async fn cell_signals_follow_the_routes_of_their_apps() {
+        let (q1, q2) = (queue("1", 1), queue("2", 1));
+        let (routed, unrouted) = (fake_cell_id(1), fake_cell_id(2));
+        let mut app_queues = HashMap::new();
+        app_queues.insert("app-1".to_string(), vec![q1.clone()]);
+        let mut cell_apps = HashMap::new();
+        cell_apps.insert(routed.clone(), vec!["app-1".to_string()]);
+        let mut broadcaster = SignalBroadcaster::new(vec![q1.clone(), q2.clone()])
+            .with_app_routes(app_queues)
+            .with_cell_apps(cell_apps);
+
+        // A cell of a subscribed app only reaches the subscribed interface
+        let signal = Signal::App(routed.clone(), ().try_into().unwrap());
+        broadcaster.send_from_cell(&routed, signal.clone()).unwrap();
+        assert_eq!(q1.recv().await.unwrap(), signal);
+        assert_eq!(q2.stats().queued, 0);
+
+        // A cell with no subscribed app reaches every interface
+        let signal = Signal::App(unrouted.clone(), ().try_into().unwrap());
+        broadcaster
+            .send_from_cell(&unrouted, signal.clone())
+            .unwrap();
+        assert_eq!(q1.recv().await.unwrap(), signal);
+        assert_eq!(q2.recv().await.unwrap(), signal);
+    }
+
+    #[tokio::test]
+    async fn stalled_interface_does_not_hold_up_others() {
+        const SIGNALS: usize = 10_000;
+        let depth = 16;
+        let (healthy, stalled) = (queue("healthy", depth), queue("stalled", depth));
+        let mut broadcaster = SignalBroadcaster::new(vec![healthy.clone(), stalled.clone()]);
+
+        let drain = tokio::spawn(async move {
+            let mut received = Vec::new();
+            while let Some(signal) = healthy.recv().await {
+                received.push(signal);
+                if received.len() == SIGNALS + 1 {
+                    break;
+                }
+            }
+            received
+        });
+
+        tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            for i in 0..SIGNALS {
+                broadcaster.send(test_signal(&i.to_string())).unwrap();
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("Sending signals should never wait on a stalled interface");
+
+        let received = tokio::time::timeout(std::time::Duration::from_secs(10), drain)
+            .await
+            .unwrap()
+            .unwrap();
+        let (warnings, signals): (Vec<_>, Vec<_>) = received.into_iter().partition(|signal| {
+            matches!(signal, Signal::System(SystemSignal::SlowConsumer { .. }))
+        });
+        assert_eq!(signals.len(), SIGNALS);
+        assert_eq!(
+            warnings,
+            vec![Signal::System(SystemSignal::SlowConsumer {
+                interface_id: "stalled".into(),
+                dropped: 1,
+            })]
+        );
+        assert_eq!(
+            stalled.stats(),
+            SignalQueueStats {
+                interface_id: "stalled".into(),
+                depth,
+                queued: depth,
+                forwarded: 0,
+                dropped: (SIGNALS - depth) as u64,
+            }
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Bounded queues of outgoing Signals, one per app interface.
+//!
+//! Emitting a Signal never waits on an interface: it is pushed onto the queue
+//! of each interface it is sent to, and each queue is drained by its own
+//! forwarding task. A client which stops reading can only hold up the
+//! interface it is connected to. When an interface's queue is full, its
+//! oldest Signal is dropped to make room for the new one.
+
+use super::websocket::ConnectionCounter;
+use crate::{conductor::state::AppInterfaceId, core::signal::Signal};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, sync::Arc};
+use tokio::sync::Notify;
+
+/// How many Signals an interface's queue holds if no depth is configured
+pub const DEFAULT_SIGNAL_QUEUE_DEPTH: usize = 1024;
+
+/// The state of an interface's signal queue
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignalQueueStats {
+    /// The interface the queue belongs to
+    pub interface_id: AppInterfaceId,
+    /// How many Signals the queue can hold
+    pub depth: usize,
+    /// How many Signals are waiting to be forwarded
+    pub queued: usize,
+    /// How many Signals have been taken from the queue to be forwarded
+    pub forwarded: u64,
+    /// How many Signals have been dropped because the queue was full
+    pub dropped: u64,
+}
+
+/// The bounded queue of Signals waiting to be forwarded to the clients
+/// of one app interface. Clones share the same queue.
+#[derive(Clone)]
+pub struct SignalQueue {
+    interface_id: AppInterfaceId,
+    connections: ConnectionCounter,
+    inner: Arc<Mutex<QueueInner>>,
+    notify: Arc<Notify>,
+}
+
+struct QueueInner {
+    signals: VecDeque<Signal>,
+    depth: usize,
+    forwarded: u64,
+    dropped: u64,
+    closed: bool,
+}
+
+impl std::fmt::Debug for SignalQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SignalQueue").field(&self.stats()).finish()
+    }
+}
+
+impl SignalQueue {
+    /// Create an empty queue holding at most `depth` Signals,
+    /// for an interface whose clients are counted by `connections`
+    pub fn new(interface_id: AppInterfaceId, depth: usize, connections: ConnectionCounter) -> Self {
+        Self {
+            interface_id,
+            connections,
+            inner: Arc::new(Mutex::new(QueueInner {
+                signals: VecDeque::new(),
+                depth: depth.max(1),
+                forwarded: 0,
+                dropped: 0,
+                closed: false,
+            })),
+            notify: Arc::new(Notify::new()),
+        }
+    }
+
+    /// The interface this queue belongs to
+    pub fn interface_id(&self) -> &AppInterfaceId {
+        &self.interface_id
+    }
+
+    /// The clients connected to this queue's interface
+    pub fn connections(&self) -> &ConnectionCounter {
+        &self.connections
+    }
+
+    /// Queue a Signal without waiting, dropping the oldest queued Signal if
+    /// the queue is full. Signals pushed to a closed queue are discarded.
+    /// Returns true if this is the first Signal the queue has had to drop.
+    pub fn push(&self, signal: Signal) -> bool {
+        let drops_began = {
+            let mut inner = self.inner.lock();
+            if inner.closed {
+                return false;
+            }
+            let mut drops_began = false;
+            if inner.signals.len() >= inner.depth {
+                inner.signals.pop_front();
+                drops_began = inner.dropped == 0;
+                inner.dropped += 1;
+            }
+            inner.signals.push_back(signal);
+            drops_began
+        };
+        self.notify.notify();
+        drops_began
+    }
+
+    /// Wait for the next Signal to forward.
+    /// Returns None once the queue has been closed.
+    pub async fn recv(&self) -> Option<Signal> {
+        loop {
+            {
+                let mut inner = self.inner.lock();
+                if inner.closed {
+                    return None;
+                }
+                if let Some(signal) = inner.signals.pop_front() {
+                    inner.forwarded += 1;
+                    return Some(signal);
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    /// Release any queued Signals and stop the forwarding task
+    pub fn close(&self) {
+        {
+            let mut inner = self.inner.lock();
+            inner.closed = true;
+            inner.signals = VecDeque::new();
+        }
+        self.notify.notify();
+    }
+
+    /// The current state of the queue
+    pub fn stats(&self) -> SignalQueueStats {
+        let inner = self.inner.lock();
+        SignalQueueStats {
+            interface_id: self.interface_id.clone(),
+            depth: inner.depth,
+            queued: inner.signals.len(),
+            forwarded: inner.forwarded,
+            dropped: inner.dropped,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::signal::test_signal;
+
+    #[tokio::test]
+    async fn full_queue_drops_oldest_signal() {
+        let queue = SignalQueue::new("test".into(), 2, ConnectionCounter::default());
+        assert!(!queue.push(test_signal("1")));
+        assert!(!queue.push(test_signal("2")));
+        // Only the first drop is reported
+        assert!(queue.push(test_signal("3")));
+        assert!(!queue.push(test_signal("4")));
+
+        assert_eq!(queue.recv().await, Some(test_signal("3")));
+        assert_eq!(
+            queue.stats(),
+            SignalQueueStats {
+                interface_id: "test".into(),
+                depth: 2,
+                queued: 1,
+                forwarded: 1,
+                dropped: 2,
+            }
+        );
+
+        // Closing releases the queue and ends forwarding
+        queue.close();
+        assert_eq!(queue.stats().queued, 0);
+        assert_eq!(queue.recv().await, None);
+        assert!(!queue.push(test_signal("5")));
+        assert_eq!(queue.stats().queued, 0);
+    }
+}
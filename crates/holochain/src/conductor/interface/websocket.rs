@@ -2,12 +2,12 @@
 //! i.e. those configured with `InterfaceDriver::Websocket`
 
 use super::error::{InterfaceError, InterfaceResult};
+use super::signal_queue::SignalQueue;
 use crate::conductor::{
     conductor::StopReceiver,
     interface::*,
     manager::{ManagedTaskHandle, ManagedTaskResult},
 };
-use crate::core::signal::Signal;
 use holochain_serialized_bytes::SerializedBytes;
 use holochain_websocket::{
     websocket_bind, WebsocketConfig, WebsocketListener, WebsocketMessage, WebsocketReceiver,
@@ -15,17 +15,40 @@ use holochain_websocket::{
 };
 use std::convert::TryFrom;
 
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
 use tokio::stream::StreamExt;
-use tokio::sync::broadcast;
+use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 use tracing::*;
 use url2::url2;
 
-// TODO: This is arbitrary, choose reasonable size.
-/// Number of signals in buffer before applying
-/// back pressure.
-pub(crate) const SIGNAL_BUFFER_SIZE: usize = 50;
+/// Counts the clients currently connected to an interface
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionCounter(Arc<AtomicUsize>);
+
+impl ConnectionCounter {
+    /// How many clients are connected
+    pub fn count(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Count a new connection until the returned guard is dropped
+    pub(crate) fn connect(&self) -> ConnectionGuard {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        ConnectionGuard(self.0.clone())
+    }
+}
+
+pub(crate) struct ConnectionGuard(Arc<AtomicUsize>);
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
 
 /// Create a WebsocketListener to be used in interfaces
 pub async fn spawn_websocket_listener(port: u16) -> InterfaceResult<WebsocketListener> {
@@ -40,10 +63,11 @@ pub async fn spawn_websocket_listener(port: u16) -> InterfaceResult<WebsocketLis
 }
 
 /// Create an Admin Interface, which only receives AdminRequest messages
-/// from the external client
+/// from the external client. Connected clients are counted by `connections`.
 pub fn spawn_admin_interface_task<A: InterfaceApi>(
     mut listener: WebsocketListener,
     api: A,
+    connections: ConnectionCounter,
     mut stop_rx: StopReceiver,
 ) -> InterfaceResult<ManagedTaskHandle> {
     Ok(tokio::task::spawn(async move {
@@ -59,10 +83,12 @@ pub fn spawn_admin_interface_task<A: InterfaceApi>(
                     match connection {
                         Ok((tx_to_iface, rx_from_iface)) => {
                             send_sockets.push(tx_to_iface);
-                            listener_handles.push(tokio::task::spawn(recv_incoming_admin_msgs(
-                                api.clone(),
-                                rx_from_iface,
-                            )));
+                            let connection = connections.connect();
+                            let api = api.clone();
+                            listener_handles.push(tokio::task::spawn(async move {
+                                let _connection = connection;
+                                recv_incoming_admin_msgs(api, rx_from_iface).await
+                            }));
                         }
                         Err(err) => {
                             warn!("Admin socket connection failed: {}", err);
@@ -98,11 +124,12 @@ pub fn spawn_admin_interface_task<A: InterfaceApi>(
 }
 
 /// Create an App Interface, which includes the ability to receive signals
-/// from Cells via a broadcast channel
+/// from Cells via its signal queue. One task forwards the queued signals
+/// to every connected client.
 pub async fn spawn_app_interface_task<A: InterfaceApi>(
     port: u16,
     api: A,
-    signal_broadcaster: broadcast::Sender<Signal>,
+    signal_queue: SignalQueue,
     mut stop_rx: StopReceiver,
 ) -> InterfaceResult<(u16, ManagedTaskHandle)> {
     trace!("Initializing App interface");
@@ -118,16 +145,20 @@ pub async fn spawn_app_interface_task<A: InterfaceApi>(
         .ok_or(InterfaceError::PortError)?;
     let task = tokio::task::spawn(async move {
         let mut listener_handles = Vec::new();
+        let (new_clients_tx, new_clients_rx) = mpsc::unbounded_channel();
+        let forwarder = tokio::task::spawn(forward_signals(signal_queue.clone(), new_clients_rx));
 
         let mut handle_connection =
             |tx_to_iface: WebsocketSender, rx_from_iface: WebsocketReceiver| {
-                let rx_from_cell = signal_broadcaster.subscribe();
-                listener_handles.push(tokio::task::spawn(recv_incoming_msgs_and_outgoing_signals(
-                    api.clone(),
-                    rx_from_iface,
-                    rx_from_cell,
-                    tx_to_iface,
-                )));
+                if new_clients_tx.send(tx_to_iface).is_err() {
+                    warn!("App interface signal forwarder has stopped");
+                }
+                let connection = signal_queue.connections().connect();
+                let api = api.clone();
+                listener_handles.push(tokio::task::spawn(async move {
+                    let _connection = connection;
+                    recv_incoming_msgs(api, rx_from_iface).await
+                }));
             };
 
         loop {
@@ -151,12 +182,48 @@ pub async fn spawn_app_interface_task<A: InterfaceApi>(
             }
         }
 
+        signal_queue.close();
+        if tokio::time::timeout(std::time::Duration::from_secs(1), forwarder)
+            .await
+            .is_err()
+        {
+            warn!("App interface signal forwarder failed to stop");
+        }
         handle_shutdown(listener_handles).await;
         ManagedTaskResult::Ok(())
     });
     Ok((port, task))
 }
 
+/// Sends each signal taken from the queue to every connected client,
+/// until the queue is closed. Clients which can't be sent to are dropped.
+async fn forward_signals(
+    signal_queue: SignalQueue,
+    mut new_clients: mpsc::UnboundedReceiver<WebsocketSender>,
+) {
+    let mut clients = Vec::new();
+    while let Some(signal) = signal_queue.recv().await {
+        while let Ok(tx_to_iface) = new_clients.try_recv() {
+            clients.push(tx_to_iface);
+        }
+        let bytes = match SerializedBytes::try_from(signal) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                error!(error = ?e, "Could not serialize signal");
+                continue;
+            }
+        };
+        let mut connected = Vec::with_capacity(clients.len());
+        for mut tx_to_iface in clients {
+            match tx_to_iface.signal(bytes.clone()).await {
+                Ok(()) => connected.push(tx_to_iface),
+                Err(e) => debug!(error = ?e, "Dropping app interface client"),
+            }
+        }
+        clients = connected;
+    }
+}
+
 async fn handle_shutdown(listener_handles: Vec<JoinHandle<InterfaceResult<()>>>) {
     for h in listener_handles {
         // Show if these are actually finishing
@@ -179,46 +246,18 @@ async fn recv_incoming_admin_msgs<A: InterfaceApi>(api: A, mut rx_from_iface: We
     }
 }
 
-/// Polls for messages coming in from the external client while simultaneously
-/// polling for signals being broadcast from the Cells associated with this
-/// App interface.
-async fn recv_incoming_msgs_and_outgoing_signals<A: InterfaceApi>(
+/// Polls for messages coming in from the external client.
+/// Used by App interface, whose outgoing signals are sent by [forward_signals].
+async fn recv_incoming_msgs<A: InterfaceApi>(
     api: A,
     mut rx_from_iface: WebsocketReceiver,
-    mut rx_from_cell: broadcast::Receiver<Signal>,
-    mut tx_to_iface: WebsocketSender,
 ) -> InterfaceResult<()> {
     trace!("CONNECTION: {}", rx_from_iface.remote_addr());
 
-    loop {
-        tokio::select! {
-            // If we receive a Signal broadcasted from a Cell, push it out
-            // across the interface
-            // NOTE: we could just use futures::StreamExt::forward to hook this
-            // tx and rx together in a new spawned task
-            signal = rx_from_cell.next() => {
-                if let Some(signal) = signal {
-                    let bytes = SerializedBytes::try_from(
-                        signal.map_err(InterfaceError::SignalReceive)?,
-                    )?;
-                    tx_to_iface.signal(bytes).await?;
-                } else {
-                    debug!("Closing interface: signal stream empty");
-                    break;
-                }
-            },
-
-            // If we receive a message from outside, handle it
-            msg = rx_from_iface.next() => {
-                if let Some(msg) = msg {
-                    handle_incoming_message(msg, api.clone()).await?
-                } else {
-                    debug!("Closing interface: message stream empty");
-                    break;
-                }
-            },
-        }
+    while let Some(msg) = rx_from_iface.next().await {
+        handle_incoming_message(msg, api.clone()).await?
     }
+    debug!("Closing interface: message stream empty");
 
     Ok(())
 }
@@ -250,7 +289,14 @@ pub mod test {
         state::ConductorState,
         Conductor, ConductorHandle,
     };
-    use crate::core::state::source_chain::SourceChainBuf;
+    use crate::core::{
+        queue_consumer::QueueTriggerClosedError,
+        state::{
+            source_chain::SourceChainBuf,
+            workflow_errors::{self, record_workflow_error, TriggerReason, WorkflowName},
+        },
+        workflow::error::WorkflowError,
+    };
     use crate::fixt::WasmRibosomeFixturator;
     use futures::future::FutureExt;
     use holochain_serialized_bytes::prelude::*;
@@ -325,7 +371,7 @@ pub mod test {
 
         conductor_handle
             .clone()
-            .install_app("test app".to_string(), cell_data)
+            .install_app("test app".to_string(), cell_data, true)
             .await
             .unwrap();
 
@@ -367,7 +413,7 @@ pub mod test {
 
         conductor_handle
             .clone()
-            .install_app("test app".to_string(), cell_data)
+            .install_app("test app".to_string(), cell_data, true)
             .await
             .unwrap();
 
@@ -421,6 +467,7 @@ pub mod test {
             dnas: vec![dna_payload],
             app_id: "test app".to_string(),
             agent_key,
+            allow_multiple_agents: false,
         };
         let msg = AdminRequest::InstallApp(Box::new(payload));
         let msg = msg.try_into().unwrap();
@@ -679,7 +726,12 @@ pub mod test {
         // Get state
         let expected = {
             let source_chain = SourceChainBuf::new(cell_env.clone().into()).unwrap();
-            source_chain.dump_as_json().await.unwrap()
+            let source_chain: serde_json::Value =
+                serde_json::from_str(&source_chain.dump_as_json().await.unwrap()).unwrap();
+            serde_json::json!({
+                "source_chain": source_chain,
+                "workflow_errors": [],
+            })
         };
 
         let admin_api = RealAdminInterfaceApi::new(conductor_handle.clone());
@@ -689,7 +741,68 @@ pub mod test {
         let msg = msg.try_into().unwrap();
         let respond = move |bytes: SerializedBytes| {
             let response: AdminResponse = bytes.try_into().unwrap();
-            assert_matches!(response, AdminResponse::JsonState(s) if s == expected);
+            assert_matches!(
+                response,
+                AdminResponse::JsonState(s)
+                    if serde_json::from_str::<serde_json::Value>(&s).unwrap() == expected
+            );
+            async { Ok(()) }.boxed()
+        };
+        let respond = Box::new(respond);
+        let msg = WebsocketMessage::Request(msg, respond);
+        handle_incoming_message(msg, admin_api).await.unwrap();
+        conductor_handle.shutdown().await;
+        shutdown.await.unwrap();
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn get_workflow_errors() {
+        observability::test_run().ok();
+        let uuid = Uuid::new_v4();
+        let dna = fake_dna_zomes(
+            &uuid.to_string(),
+            vec![("zomey".into(), TestWasm::Foo.into())],
+        );
+        let cell_id = CellId::from((dna.dna_hash().clone(), fake_agent_pubkey_1()));
+
+        let mut dna_store = MockDnaStore::new();
+        dna_store.expect_get().returning(move |_| Some(dna.clone()));
+        dna_store
+            .expect_add_dnas::<Vec<_>>()
+            .times(1)
+            .return_const(());
+        dna_store
+            .expect_add_entry_defs::<Vec<_>>()
+            .times(1)
+            .return_const(());
+
+        let (_tmpdir, conductor_handle) =
+            setup_admin_fake_cells(vec![(cell_id.clone(), None)], dna_store).await;
+        let conductor_handle = activate(conductor_handle).await;
+        let shutdown = conductor_handle.take_shutdown_handle().await.unwrap();
+
+        // Record an error for the cell
+        let cell_env = conductor_handle.get_cell_env(&cell_id).await.unwrap();
+        let error = WorkflowError::from(QueueTriggerClosedError);
+        record_workflow_error(
+            &cell_env,
+            WorkflowName::IntegrateDhtOps,
+            TriggerReason::Triggered,
+            Some(1),
+            &error,
+        )
+        .unwrap();
+        let expected = workflow_errors::get_workflow_errors(&cell_env.into()).unwrap();
+        assert_eq!(expected.len(), 1);
+
+        let admin_api = RealAdminInterfaceApi::new(conductor_handle.clone());
+        let msg = AdminRequest::GetWorkflowErrors {
+            cell_id: Box::new(cell_id),
+        };
+        let msg = msg.try_into().unwrap();
+        let respond = move |bytes: SerializedBytes| {
+            let response: AdminResponse = bytes.try_into().unwrap();
+            assert_matches!(response, AdminResponse::WorkflowErrors(e) if e == expected);
             async { Ok(()) }.boxed()
         };
         let respond = Box::new(respond);
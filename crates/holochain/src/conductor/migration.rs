@@ -0,0 +1,151 @@
+//! Upgrades of the Conductor environment when the schema of its data changes.
+//!
+//! Each [StateMigration] upgrades the schema by one version. Migrations are
+//! registered with [ConductorBuilder::with_migration](super::ConductorBuilder::with_migration),
+//! and [ConductorHandleT::migrate_conductor_state](super::handle::ConductorHandleT::migrate_conductor_state)
+//! applies every step between two versions within a single transaction,
+//! so a failed step leaves the environment as it was.
+
+use super::error::{ConductorError, ConductorResult};
+use holochain_state::{env::EnvironmentWrite, error::DatabaseResult, prelude::*};
+use std::collections::BTreeMap;
+
+/// One step up in the schema of the Conductor environment.
+/// New LMDB sub-databases are created when the environment is opened,
+/// so a migration only has to move existing data into its new shape.
+pub trait StateMigration {
+    /// The schema version this migration upgrades to, from the version before it
+    const VERSION: u32;
+
+    /// Apply the migration, within the write transaction of the Conductor environment
+    fn up(env: &EnvironmentWrite, writer: &mut Writer) -> DatabaseResult<()>;
+}
+
+/// The function which applies a [StateMigration]
+pub type MigrationFn = fn(&EnvironmentWrite, &mut Writer) -> DatabaseResult<()>;
+
+/// The registered migrations of a Conductor, by the version they upgrade to
+#[derive(Clone, Debug, Default)]
+pub struct StateMigrations(BTreeMap<u32, MigrationFn>);
+
+impl StateMigrations {
+    /// Register a migration, replacing any other migration to the same version
+    pub fn register<M: StateMigration>(&mut self) {
+        self.0.insert(M::VERSION, M::up);
+    }
+
+    /// Apply the migrations from one version to a later one, in order.
+    /// Fails without changing anything if a step has no registered migration.
+    pub fn apply(
+        &self,
+        env: &EnvironmentWrite,
+        from_version: u32,
+        to_version: u32,
+    ) -> ConductorResult<()> {
+        if to_version < from_version {
+            return Err(ConductorError::MigrationError(format!(
+                "Can't migrate the conductor state down from version {} to {}",
+                from_version, to_version
+            )));
+        }
+        let steps = ((from_version + 1)..=to_version)
+            .map(|version| {
+                self.0.get(&version).copied().ok_or_else(|| {
+                    ConductorError::MigrationError(format!(
+                        "No migration to version {} is registered",
+                        version
+                    ))
+                })
+            })
+            .collect::<ConductorResult<Vec<_>>>()?;
+        env.guard()
+            .with_commit(|writer| steps.iter().try_for_each(|up| up(env, writer)))?;
+        tracing::info!(from_version, to_version, "Migrated the conductor state");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conductor::{
+        conductor::AppMetadataDb,
+        handle::ConductorHandleT,
+        state::{AppMetadataKey, ConductorState},
+        ConductorBuilder, ConductorStateDb,
+    };
+    use holochain_serialized_bytes::{SerializedBytes, UnsafeBytes};
+    use holochain_state::{
+        buffer::KvStore,
+        db::{APP_METADATA, CONDUCTOR_STATE},
+        key::UnitDbKey,
+        test_utils::{test_conductor_env, test_p2p_env, test_wasm_env},
+    };
+    use holochain_types::app::AppId;
+    use matches::assert_matches;
+
+    fn schema_marker() -> SerializedBytes {
+        UnsafeBytes::from(vec![2]).into()
+    }
+
+    /// Version 2 marks every installed app in the app metadata sub-database
+    struct MarkInstalledApps;
+
+    impl StateMigration for MarkInstalledApps {
+        const VERSION: u32 = 2;
+
+        fn up(env: &EnvironmentWrite, writer: &mut Writer) -> DatabaseResult<()> {
+            let state_db: ConductorStateDb = KvStore::new(env.get_db(&*CONDUCTOR_STATE)?);
+            let metadata_db: AppMetadataDb = KvStore::new(env.get_db(&*APP_METADATA)?);
+            let state = state_db.get(writer, &UnitDbKey)?.unwrap_or_default();
+            for app_id in state.inactive_apps.keys().chain(state.active_apps.keys()) {
+                let key = AppMetadataKey::new(app_id, "schema");
+                metadata_db.put(writer, &key, &schema_marker())?;
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn migrate_v1_to_v2() {
+        let test_env = test_conductor_env();
+        let wasm_env = test_wasm_env();
+        let p2p_env = test_p2p_env();
+        let app_id: AppId = "app".to_string();
+        let mut state = ConductorState::default();
+        state.inactive_apps.insert(app_id.clone(), vec![]);
+        let handle = ConductorBuilder::new()
+            .with_migration::<MarkInstalledApps>()
+            .fake_state(state)
+            .test(test_env, wasm_env.env(), p2p_env.env())
+            .await
+            .unwrap();
+        assert_eq!(
+            handle.get_app_metadata(&app_id, "schema").await.unwrap(),
+            None
+        );
+
+        // There is no migration to version 3
+        assert_matches!(
+            handle.migrate_conductor_state(1, 3).await,
+            Err(ConductorError::MigrationError(_))
+        );
+        assert_eq!(
+            handle.get_app_metadata(&app_id, "schema").await.unwrap(),
+            None
+        );
+        assert_matches!(
+            handle.migrate_conductor_state(2, 1).await,
+            Err(ConductorError::MigrationError(_))
+        );
+
+        handle.migrate_conductor_state(1, 2).await.unwrap();
+        assert_eq!(
+            handle.get_app_metadata(&app_id, "schema").await.unwrap(),
+            Some(schema_marker())
+        );
+        // Migrating to the same version does nothing
+        handle.migrate_conductor_state(2, 2).await.unwrap();
+        handle.shutdown().await;
+    }
+}
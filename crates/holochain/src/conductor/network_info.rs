@@ -0,0 +1,162 @@
+//! Gossip progress of a Cell, see
+//! [ConductorHandleT::network_info](super::handle::ConductorHandleT::network_info)
+
+use holochain_p2p::{actor, dht_arc::DhtArc};
+use holochain_types::{cell::CellId, Timestamp};
+use serde::{Deserialize, Serialize};
+
+/// The arc of the DHT a Cell holds ops for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageArc {
+    /// The location the arc is centered on
+    pub center_loc: u32,
+    /// The distance the arc extends on either side of its center
+    pub half_length: u32,
+}
+
+impl From<&DhtArc> for StorageArc {
+    fn from(arc: &DhtArc) -> Self {
+        Self {
+            center_loc: (arc.center_loc.0).0,
+            half_length: arc.half_length,
+        }
+    }
+}
+
+/// How far a Cell has got syncing with the rest of its network.
+/// Estimates which aren't available yet are None.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NetworkInfo {
+    /// The Cell this info is about
+    pub cell_id: CellId,
+    /// How many other agents are known in the space
+    pub peer_count: u32,
+    /// The arc of the DHT the Cell holds ops for
+    pub storage_arc: StorageArc,
+    /// How many ops within the arc the Cell has integrated
+    pub integrated_op_count: u64,
+    /// How many ops there are estimated to be within the arc,
+    /// from the op counts peers advertised during gossip
+    pub expected_op_count: Option<u64>,
+    /// When the Cell last completed a gossip round
+    pub last_gossip: Option<Timestamp>,
+    /// How many bytes of op data the Cell has received since joining
+    pub bytes_received: u64,
+    /// The median of how far ahead of this conductor's clock the timestamps
+    /// of recently received headers were, in milliseconds, which estimates
+    /// how far peers' clocks are off. Negative if they are behind.
+    pub clock_skew_ms: Option<i64>,
+}
+
+impl NetworkInfo {
+    /// Combine what the network knows about a Cell with how many ops it has integrated
+    pub fn new(cell_id: CellId, info: actor::NetworkInfo, integrated_op_count: u64) -> Self {
+        Self {
+            cell_id,
+            peer_count: info.peer_count,
+            storage_arc: (&info.storage_arc).into(),
+            integrated_op_count,
+            expected_op_count: info.expected_op_count,
+            last_gossip: info
+                .last_gossip_ms
+                .map(|ms| Timestamp((ms / 1000) as i64, ((ms % 1000) * 1_000_000) as u32)),
+            bytes_received: info.bytes_received,
+            clock_skew_ms: None,
+        }
+    }
+
+    /// The fraction of expected ops which have been integrated, from 0 to 1,
+    /// if the expected op count is known
+    pub fn sync_progress(&self) -> Option<f64> {
+        self.expected_op_count.map(|expected| {
+            if expected == 0 {
+                1.0
+            } else {
+                (self.integrated_op_count as f64 / expected as f64).min(1.0)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        conductor::handle::ConductorHandleT,
+        test_utils::{
+            host_fn_api::*,
+            test_conductor::{test_dna_file, TestConductor},
+        },
+    };
+    use holochain_types::test_utils::{fake_agent_pubkey_1, fake_agent_pubkey_2, fake_cell_id};
+    use holochain_wasm_test_utils::TestWasm;
+    use std::convert::TryInto;
+
+    #[test]
+    fn network_info_from_kitsune() {
+        let info = actor::NetworkInfo {
+            peer_count: 2,
+            storage_arc: DhtArc::new(7, 100),
+            expected_op_count: None,
+            last_gossip_ms: Some(1_500),
+            bytes_received: 500,
+        };
+        let mut info = NetworkInfo::new(fake_cell_id(1), info, 25);
+        assert_eq!(
+            info.storage_arc,
+            StorageArc {
+                center_loc: 7,
+                half_length: 100
+            }
+        );
+        assert_eq!(info.last_gossip, Some(Timestamp(1, 500_000_000)));
+        // Unknown until a peer advertises its op count
+        assert_eq!(info.sync_progress(), None);
+
+        info.expected_op_count = Some(50);
+        assert_eq!(info.sync_progress(), Some(0.5));
+        info.integrated_op_count = 60;
+        assert_eq!(info.sync_progress(), Some(1.0));
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn network_info_through_the_conductor() {
+        holochain_types::observability::test_run().ok();
+        let conductor = TestConductor::new().await;
+        let dna = test_dna_file(vec![TestWasm::Create]).await;
+        let alice = conductor
+            .setup_app("alice", fake_agent_pubkey_1(), &[dna.clone()])
+            .await;
+        let bob = conductor
+            .setup_app("bob", fake_agent_pubkey_2(), &[dna])
+            .await;
+        let (alice, bob) = (&alice.cells()[0], &bob.cells()[0]);
+
+        commit_entry(
+            alice.env(),
+            alice.call_data(TestWasm::Create),
+            Post("Hi there".into()).try_into().unwrap(),
+            POST_ID,
+        )
+        .await;
+        conductor.consistency().await;
+
+        let infos = conductor
+            .handle()
+            .network_info(vec![bob.cell_id().clone(), alice.cell_id().clone()])
+            .await
+            .unwrap();
+        // In the order asked for
+        assert_eq!(&infos[0].cell_id, bob.cell_id());
+        assert_eq!(&infos[1].cell_id, alice.cell_id());
+        for info in infos {
+            // Each cell knows of the other agent in the space
+            assert_eq!(info.peer_count, 1);
+            // and holds the whole DHT while full sync:
+            // the 3 ops of the commit plus 7 genesis ops for each agent
+            assert_eq!(info.integrated_op_count, 3 + 14);
+        }
+
+        conductor.shutdown().await;
+    }
+}
@@ -1,6 +1,7 @@
-//! A simple KvBuf for AgentInfoSigned.
+//! A simple KvBuf for AgentInfoSigned, and one for what we learn about each agent.
 
-use holochain_p2p::kitsune_p2p::agent_store::AgentInfoSigned;
+use holochain_p2p::compression::PeerMeta;
+use holochain_p2p::kitsune_p2p::agent_store::{AgentInfo, AgentInfoSigned};
 use holochain_state::buffer::KvStore;
 use holochain_state::db::GetDb;
 use holochain_state::env::EnvironmentRead;
@@ -10,6 +11,65 @@ use holochain_state::key::BufKey;
 const AGENT_KEY_LEN: usize = 64;
 const AGENT_KEY_COMPONENT_LEN: usize = 32;
 
+/// How long agent info stays valid after it was signed.
+pub const AGENT_INFO_EXPIRY_MS: u64 = 20 * 60 * 1000;
+
+/// Extra time agent info is kept past its expiry, in case the clock of
+/// the agent who signed it runs behind ours.
+pub const AGENT_INFO_SKEW_TOLERANCE_MS: u64 = 60 * 1000;
+
+/// The current time in ms since the unix epoch, by this machine's
+/// system wall clock. Expiry is always checked against this clock.
+pub fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("system clock is before the unix epoch")
+        .as_millis() as u64
+}
+
+/// Whether agent info had expired at `now_ms`.
+///
+/// The info's `signed_at_ms` was read from the signing agent's clock,
+/// not ours, so the info is only treated as expired once it is
+/// [AGENT_INFO_SKEW_TOLERANCE_MS] past [AGENT_INFO_EXPIRY_MS].
+pub fn agent_info_is_expired(agent_info_signed: &AgentInfoSigned, now_ms: u64) -> bool {
+    let signed_at_ms = agent_info_signed.as_agent_info_ref().signed_at_ms();
+    now_ms
+        > signed_at_ms
+            .saturating_add(AGENT_INFO_EXPIRY_MS)
+            .saturating_add(AGENT_INFO_SKEW_TOLERANCE_MS)
+}
+
+/// The bytes an agent signs to produce its [AgentInfoSigned]
+pub fn agent_info_signing_bytes(
+    agent_info: &AgentInfo,
+) -> Result<Vec<u8>, holochain_serialized_bytes::SerializedBytesError> {
+    holochain_serialized_bytes::encode(agent_info)
+}
+
+/// Why agent info sent by a remote agent was not stored
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum AgentInfoRejection {
+    /// The info is for one of this conductor's own agents
+    #[error("it is for an agent of this conductor")]
+    LocalAgent,
+    /// The info is for an agent other than the one who sent it
+    #[error("it is not the sender's own agent info")]
+    NotSender,
+    /// The info is for a different space than the one it was sent in
+    #[error("it is for a different space")]
+    WrongSpace,
+    /// The signature is not the agent's signature of the info
+    #[error("its signature does not verify")]
+    BadSignature,
+    /// The info has already expired
+    #[error("it has expired")]
+    Expired,
+    /// The info was not signed after the info already held for the agent
+    #[error("it is not newer than the agent info already held")]
+    NotNewer,
+}
+
 /// Required new type for KvBuf key.
 pub struct AgentKvKey([u8; AGENT_KEY_LEN]);
 
@@ -98,10 +158,27 @@ impl AgentKv {
     }
 }
 
+/// Defines the structure of the KvBuf for the PeerMeta of each agent,
+/// keyed like its AgentInfoSigned.
+pub struct PeerMetaKv(KvStore<AgentKvKey, PeerMeta>);
+
+impl PeerMetaKv {
+    /// Constructor.
+    pub fn new(env: EnvironmentRead) -> DatabaseResult<Self> {
+        let db = env.get_db(&*holochain_state::db::PEER_META)?;
+        Ok(Self(KvStore::new(db)))
+    }
+
+    /// Thin wrapper for the inner store.
+    pub fn as_store_ref(&self) -> &KvStore<AgentKvKey, PeerMeta> {
+        &self.0
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
-    use super::AgentKvKey;
+    use super::*;
     use fixt::prelude::*;
     use holochain_p2p::kitsune_p2p::fixt::AgentInfoSignedFixturator;
     use holochain_state::buffer::KvStoreT;
@@ -161,4 +238,39 @@ mod tests {
 
         assert_eq!(ret, &Some(agent_info_signed),);
     }
+
+    fn agent_info_signed_at(signed_at_ms: u64) -> AgentInfoSigned {
+        let info = fixt!(AgentInfoSigned);
+        AgentInfoSigned::try_new(
+            info.as_signature_ref().clone(),
+            AgentInfo::new(
+                info.as_agent_info_ref().as_space_ref().clone(),
+                info.as_agent_info_ref().as_agent_ref().clone(),
+                vec![],
+                signed_at_ms,
+            ),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn agent_info_expires_after_tolerance() {
+        let signed_at_ms = 1_000_000;
+        let info = agent_info_signed_at(signed_at_ms);
+        let expires_ms = signed_at_ms + AGENT_INFO_EXPIRY_MS;
+
+        assert!(!agent_info_is_expired(&info, signed_at_ms));
+        // Within the skew tolerance it is still valid
+        assert!(!agent_info_is_expired(&info, expires_ms + 1));
+        assert!(!agent_info_is_expired(
+            &info,
+            expires_ms + AGENT_INFO_SKEW_TOLERANCE_MS
+        ));
+        assert!(agent_info_is_expired(
+            &info,
+            expires_ms + AGENT_INFO_SKEW_TOLERANCE_MS + 1
+        ));
+        // Info signed in our future isn't expired
+        assert!(!agent_info_is_expired(&info, 0));
+    }
 }
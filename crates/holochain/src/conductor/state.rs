@@ -2,8 +2,13 @@
 //! startups and shutdowns
 
 use crate::conductor::interface::InterfaceDriver;
+use crate::core::state::integrity_audit::IntegrityAuditReport;
 
-use holochain_types::app::{AppId, InstalledApp, InstalledCell};
+use holochain_state::prelude::BufKey;
+use holochain_types::{
+    app::{AppId, InstalledApp, InstalledCell},
+    cell::CellId,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -26,6 +31,10 @@ pub struct ConductorState {
     /// List of interfaces any UI can use to access zome functions.
     #[serde(default)]
     pub app_interfaces: HashMap<AppInterfaceId, AppInterfaceConfig>,
+    /// Cells which failed an integrity audit, along with the report of that audit.
+    /// These will not be started until their data has been repaired.
+    #[serde(default)]
+    pub quarantined_cells: HashMap<CellId, IntegrityAuditReport>,
 }
 
 /// A unique identifier used to refer to an App Interface internally.
@@ -57,6 +66,36 @@ impl ConductorState {
     }
 }
 
+/// Key for the app metadata database: the length of the app id, the app id,
+/// then the metadata key. The length prefix keeps one app's keys from
+/// colliding with another's.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AppMetadataKey(Vec<u8>);
+
+impl AppMetadataKey {
+    /// Create the key for a piece of metadata on an app
+    #[allow(clippy::ptr_arg)]
+    pub fn new(app_id: &AppId, key: &str) -> Self {
+        let mut bytes = Vec::with_capacity(4 + app_id.len() + key.len());
+        bytes.extend_from_slice(&(app_id.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(app_id.as_bytes());
+        bytes.extend_from_slice(key.as_bytes());
+        Self(bytes)
+    }
+}
+
+impl AsRef<[u8]> for AppMetadataKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl BufKey for AppMetadataKey {
+    fn from_key_bytes_or_friendly_panic(bytes: &[u8]) -> Self {
+        Self(bytes.to_vec())
+    }
+}
+
 /// Here, interfaces are user facing and make available zome functions to
 /// GUIs, browser based web UIs, local native UIs, other local applications and scripts.
 /// We currently have:
@@ -2,6 +2,7 @@
 
 #![deny(missing_docs)]
 
+pub mod gossip_metrics;
 pub mod net;
 pub mod queue_consumer;
 #[allow(missing_docs)]
@@ -10,6 +11,7 @@ pub mod ribosome;
 pub mod signal;
 pub mod state;
 mod validation;
+pub mod validation_breaker;
 #[allow(missing_docs)]
 pub mod workflow;
 
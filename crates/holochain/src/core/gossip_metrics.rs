@@ -0,0 +1,111 @@
+//! Measures of the ops a Cell exchanges with its peers, for finding Cells
+//! which are slow to sync.
+//!
+//! The publish workflow records the ops it publishes, and the Cell records
+//! the ops it receives and the ops peers fetch from it during gossip,
+//! all in the Cell's [GossipMeter].
+
+use holo_hash::AgentPubKey;
+use holochain_types::dht_op::DhtOp;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// How much a Cell has gossiped and published since it was started
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CellGossipMetrics {
+    /// How many gossip rounds the Cell has taken part in,
+    /// counted each time it tells a peer which ops it holds in an arc
+    pub rounds_completed: u64,
+    /// How many ops the Cell has published or sent to peers during gossip
+    pub ops_sent: u64,
+    /// How many ops the Cell has received, published to it or by gossip
+    pub ops_received: u64,
+    /// The size of the ops sent
+    pub bytes_sent: u64,
+    /// The size of the ops received
+    pub bytes_received: u64,
+    /// The agent the Cell last received ops from
+    pub last_peer: Option<AgentPubKey>,
+}
+
+/// Where a Cell and its workflows record the ops they exchange.
+/// Clones share the same metrics.
+#[derive(Clone, Debug, Default)]
+pub struct GossipMeter(Arc<Mutex<CellGossipMetrics>>);
+
+impl GossipMeter {
+    /// Record ops sent to peers
+    pub fn record_sent<'a>(&self, ops: impl IntoIterator<Item = &'a DhtOp>) {
+        let (count, bytes) = count_ops(ops);
+        let mut metrics = self.0.lock();
+        metrics.ops_sent += count;
+        metrics.bytes_sent += bytes;
+    }
+
+    /// Record ops received from a peer
+    pub fn record_received<'a>(
+        &self,
+        from_agent: &AgentPubKey,
+        ops: impl IntoIterator<Item = &'a DhtOp>,
+    ) {
+        let (count, bytes) = count_ops(ops);
+        let mut metrics = self.0.lock();
+        metrics.ops_received += count;
+        metrics.bytes_received += bytes;
+        metrics.last_peer = Some(from_agent.clone());
+    }
+
+    /// Record a gossip round with a peer
+    pub fn record_round(&self) {
+        self.0.lock().rounds_completed += 1;
+    }
+
+    /// The metrics recorded so far
+    pub fn snapshot(&self) -> CellGossipMetrics {
+        self.0.lock().clone()
+    }
+}
+
+/// How many ops there are and how many bytes they take on the wire
+fn count_ops<'a>(ops: impl IntoIterator<Item = &'a DhtOp>) -> (u64, u64) {
+    ops.into_iter().fold((0, 0), |(count, bytes), op| {
+        let size = holochain_serialized_bytes::encode(op)
+            .map(|b| b.len() as u64)
+            .unwrap_or(0);
+        (count + 1, bytes + size)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixt::{CreateFixturator, SignatureFixturator};
+    use ::fixt::prelude::*;
+    use holo_hash::fixt::AgentPubKeyFixturator;
+
+    #[test]
+    fn ops_are_counted_in_both_directions() {
+        let ops = (0..3)
+            .map(|_| DhtOp::RegisterAgentActivity(fixt!(Signature), fixt!(Create).into()))
+            .collect::<Vec<_>>();
+        let (alice, bob) = (fixt!(AgentPubKey), fixt!(AgentPubKey));
+
+        let meter = GossipMeter::default();
+        meter.record_sent(&ops);
+        meter.record_received(&alice, &ops[..1]);
+        meter.record_received(&bob, &ops[1..]);
+        meter.record_round();
+
+        // Clones share the metrics
+        meter.clone().record_round();
+
+        let metrics = meter.snapshot();
+        assert_eq!(metrics.rounds_completed, 2);
+        assert_eq!(metrics.ops_sent, 3);
+        assert_eq!(metrics.ops_received, 3);
+        assert!(metrics.bytes_sent > 0);
+        assert_eq!(metrics.bytes_received, metrics.bytes_sent);
+        assert_eq!(metrics.last_peer, Some(bob));
+    }
+}
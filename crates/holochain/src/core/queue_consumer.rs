@@ -17,23 +17,36 @@
 //! | CallZome       | *n/a*            | ChainSequence    | ProduceDhtOps  |
 //! | ProduceDhtOps  | ChainSequence    | Auth'd + IntQ †  | DhtOpIntegr.   |
 //! |                 **integration, common to both paths**                 |
-//! | DhtOpIntegr.   | IntegrationLimbo | IntegratedDhtOps | Publish        |
+//! | DhtOpIntegr.   | IntegrationLimbo | IntegratedDhtOps | Publish, VR    |
 //! | Publish        | AuthoredDhtOps   | *n/a*            | *n/a*          |
+//! | ValidationRec. | PendingReceipts  | *n/a*            | *n/a*          |
 //!
 //! († Auth'd + IntQ is short for: AuthoredDhtOps + IntegrationLimbo)
+//! (VR is short for: ValidationReceipt)
 //!
 //! Implicitly, every workflow also writes to its own source queue, i.e. to
 //! remove the item it has just processed.
 
-use std::sync::{Arc, Once};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Once},
+    time::Duration,
+};
 
-use derive_more::{Constructor, Display, From};
+use derive_more::Display;
+use fallible_iterator::FallibleIterator;
 use futures::future::Either;
 use holochain_state::{
-    env::{EnvironmentWrite, WriteManager},
-    prelude::Writer,
+    buffer::KvBufFresh,
+    env::{Durability, EnvironmentWrite, WriteManager},
+    error::DatabaseResult,
+    fresh_reader,
+    prelude::{BufKey, BufVal, Writer},
 };
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 use tokio::sync::{self, mpsc};
+use tracing::*;
 
 // TODO: move these to workflow mod
 mod integrate_dht_ops_consumer;
@@ -45,10 +58,23 @@ use app_validation_consumer::*;
 mod produce_dht_ops_consumer;
 use produce_dht_ops_consumer::*;
 mod publish_dht_ops_consumer;
-use super::state::workspace::WorkspaceError;
-use crate::conductor::{api::CellConductorApiT, manager::ManagedTaskAdd};
+mod validation_receipt_consumer;
+use super::{
+    gossip_metrics::GossipMeter,
+    ribosome::validation_pool::ExecutionContext,
+    state::{
+        workflow_errors::{record_workflow_error, TriggerReason, WorkflowName},
+        workspace::WorkspaceError,
+    },
+    validation_breaker::ValidationBreaker,
+    workflow::error::{WorkflowError, WorkflowResult},
+};
+use crate::conductor::{
+    api::CellConductorApiT, config::QueueBackoffConfig, manager::ManagedTaskAdd,
+};
 use holochain_p2p::HolochainP2pCell;
 use publish_dht_ops_consumer::*;
+use validation_receipt_consumer::*;
 
 /// Spawns several long-running tasks which are responsible for processing work
 /// which shows up on various databases.
@@ -61,10 +87,35 @@ pub async fn spawn_queue_consumer_tasks(
     conductor_api: impl CellConductorApiT + 'static,
     mut task_sender: sync::mpsc::Sender<ManagedTaskAdd>,
     stop: sync::broadcast::Sender<()>,
+    backoff_config: QueueBackoffConfig,
+    receipt_flush_interval: Duration,
+    gossip_meter: GossipMeter,
+    validation_context: ExecutionContext,
+    validation_breaker: ValidationBreaker,
 ) -> InitialQueueTriggers {
+    let backoffs = WorkflowBackoffs::new(backoff_config);
+
     // Publish
-    let (tx_publish, handle) =
-        spawn_publish_dht_ops_consumer(env.clone(), stop.subscribe(), cell_network.clone());
+    let (tx_publish, handle) = spawn_publish_dht_ops_consumer(
+        env.clone(),
+        stop.subscribe(),
+        cell_network.clone(),
+        gossip_meter,
+        backoffs.clone(),
+    );
+    task_sender
+        .send(ManagedTaskAdd::dont_handle(handle))
+        .await
+        .expect("Failed to manage workflow handle");
+
+    // Validation receipts
+    let (tx_receipt, handle) = spawn_validation_receipt_consumer(
+        env.clone(),
+        stop.subscribe(),
+        cell_network.clone(),
+        receipt_flush_interval,
+        backoffs.clone(),
+    );
     task_sender
         .send(ManagedTaskAdd::dont_handle(handle))
         .await
@@ -73,8 +124,15 @@ pub async fn spawn_queue_consumer_tasks(
     let (create_tx_sys, get_tx_sys) = tokio::sync::oneshot::channel();
 
     // Integration
-    let (tx_integration, handle) =
-        spawn_integrate_dht_ops_consumer(env.clone(), stop.subscribe(), get_tx_sys);
+    let (integrated, _) = sync::broadcast::channel(1);
+    let (tx_integration, handle) = spawn_integrate_dht_ops_consumer(
+        env.clone(),
+        stop.subscribe(),
+        get_tx_sys,
+        tx_receipt.clone(),
+        integrated.clone(),
+        backoffs.clone(),
+    );
     task_sender
         .send(ManagedTaskAdd::dont_handle(handle))
         .await
@@ -87,6 +145,9 @@ pub async fn spawn_queue_consumer_tasks(
         tx_integration.clone(),
         conductor_api.clone(),
         cell_network.clone(),
+        validation_context,
+        backoffs.clone(),
+        validation_breaker,
     );
     task_sender
         .send(ManagedTaskAdd::dont_handle(handle))
@@ -99,7 +160,8 @@ pub async fn spawn_queue_consumer_tasks(
         stop.subscribe(),
         tx_app.clone(),
         cell_network,
-        conductor_api,
+        conductor_api.clone(),
+        backoffs.clone(),
     );
     task_sender
         .send(ManagedTaskAdd::dont_handle(handle))
@@ -110,14 +172,28 @@ pub async fn spawn_queue_consumer_tasks(
     }
 
     // Produce
-    let (tx_produce, handle) =
-        spawn_produce_dht_ops_consumer(env.clone(), stop.subscribe(), tx_publish.clone());
+    let (tx_produce, handle) = spawn_produce_dht_ops_consumer(
+        env.clone(),
+        stop.subscribe(),
+        tx_publish.clone(),
+        conductor_api,
+        backoffs.clone(),
+    );
     task_sender
         .send(ManagedTaskAdd::dont_handle(handle))
         .await
         .expect("Failed to manage workflow handle");
 
-    InitialQueueTriggers::new(tx_sys, tx_produce, tx_publish, tx_app, tx_integration)
+    InitialQueueTriggers::new(
+        tx_sys,
+        tx_produce,
+        tx_publish,
+        tx_app,
+        tx_integration,
+        tx_receipt,
+        integrated,
+        backoffs,
+    )
 }
 
 #[derive(Clone)]
@@ -133,6 +209,9 @@ pub struct InitialQueueTriggers {
     publish_dht_ops: TriggerSender,
     app_validation: TriggerSender,
     integrate_dht_ops: TriggerSender,
+    validation_receipt: TriggerSender,
+    integrated: sync::broadcast::Sender<()>,
+    backoffs: WorkflowBackoffs,
     init: Option<Arc<Once>>,
 }
 
@@ -143,6 +222,9 @@ impl InitialQueueTriggers {
         publish_dht_ops: TriggerSender,
         app_validation: TriggerSender,
         integrate_dht_ops: TriggerSender,
+        validation_receipt: TriggerSender,
+        integrated: sync::broadcast::Sender<()>,
+        backoffs: WorkflowBackoffs,
     ) -> Self {
         Self {
             sys_validation,
@@ -150,10 +232,24 @@ impl InitialQueueTriggers {
             publish_dht_ops,
             app_validation,
             integrate_dht_ops,
+            validation_receipt,
+            integrated,
+            backoffs,
             init: Some(Arc::new(Once::new())),
         }
     }
 
+    /// Get notified each time the DhtOpIntegration workflow has run,
+    /// so tests can wait for integration without polling
+    pub fn subscribe_to_integration(&self) -> sync::broadcast::Receiver<()> {
+        self.integrated.subscribe()
+    }
+
+    /// The workflows which are backing off after failed runs
+    pub fn workflow_backoffs(&self) -> Vec<WorkflowBackoff> {
+        self.backoffs.backing_off()
+    }
+
     /// Initialize all the workflows once.
     /// This will run only once even if called
     /// multiple times.
@@ -165,16 +261,32 @@ impl InitialQueueTriggers {
                 self.publish_dht_ops.trigger();
                 self.integrate_dht_ops.trigger();
                 self.produce_dht_ops.trigger();
+                self.validation_receipt.trigger();
             })
         }
     }
 }
 /// The means of nudging a queue consumer to tell it to look for more work
 #[derive(Clone)]
-pub struct TriggerSender(mpsc::Sender<()>);
+pub struct TriggerSender {
+    tx: mpsc::Sender<()>,
+    delayed: Arc<DelayedTrigger>,
+}
 
 /// The receiving end of a queue trigger channel
-pub struct TriggerReceiver(mpsc::Receiver<()>);
+pub struct TriggerReceiver {
+    rx: mpsc::Receiver<()>,
+    delayed: Arc<DelayedTrigger>,
+}
+
+/// The one delayed nudge a consumer is waiting for, if any.
+/// The consumer waits for it while it waits for triggers,
+/// so no timer outlives the consumer.
+#[derive(Default)]
+struct DelayedTrigger {
+    at: Mutex<Option<tokio::time::Instant>>,
+    changed: sync::Notify,
+}
 
 impl TriggerSender {
     /// Create a new channel for waking a consumer
@@ -183,13 +295,20 @@ impl TriggerSender {
     /// inconsistency from the perspective of any particular CPU thread
     pub fn new() -> (TriggerSender, TriggerReceiver) {
         let (tx, rx) = mpsc::channel(num_cpus::get());
-        (TriggerSender(tx), TriggerReceiver(rx))
+        let delayed = Arc::new(DelayedTrigger::default());
+        (
+            TriggerSender {
+                tx,
+                delayed: delayed.clone(),
+            },
+            TriggerReceiver { rx, delayed },
+        )
     }
 
     /// Lazily nudge the consumer task, ignoring the case where the consumer
     /// already has a pending trigger signal
     pub fn trigger(&mut self) {
-        match self.0.try_send(()) {
+        match self.tx.try_send(()) {
             Err(mpsc::error::TrySendError::Closed(_)) => {
                 tracing::warn!(
                     "Queue consumer trigger was sent while Cell is shutting down: ignoring."
@@ -199,26 +318,61 @@ impl TriggerSender {
             Ok(()) => (),
         };
     }
+
+    /// Nudge the consumer task once the delay has passed, without waiting for it.
+    /// Only the earliest of the pending delays is kept.
+    pub fn trigger_after(&self, delay: Duration) {
+        let at = tokio::time::Instant::now() + delay;
+        let mut pending = self.delayed.at.lock();
+        if pending.map_or(true, |pending| at < pending) {
+            *pending = Some(at);
+            self.delayed.changed.notify();
+        }
+    }
 }
 
 impl TriggerReceiver {
-    /// Listen for one or more items to come through, draining the channel
-    /// each time. Bubble up errors on empty channel.
+    /// Listen for one or more items to come through, or for a delayed
+    /// trigger to come due, draining the channel each time.
+    /// Bubble up errors on empty channel.
     pub async fn listen(&mut self) -> Result<(), QueueTriggerClosedError> {
         use tokio::sync::mpsc::error::TryRecvError;
 
         // wait for next item
-        if self.0.recv().await.is_some() {
-            // drain the channel
-            loop {
-                match self.0.try_recv() {
-                    Err(TryRecvError::Closed) => return Err(QueueTriggerClosedError),
-                    Err(TryRecvError::Empty) => return Ok(()),
-                    Ok(()) => (),
+        let delayed = self.delayed.clone();
+        loop {
+            let at = *delayed.at.lock();
+            let due = async move {
+                match at {
+                    Some(at) => tokio::time::delay_until(at).await,
+                    None => futures::future::pending().await,
                 }
+            };
+            tokio::select! {
+                item = self.rx.recv() => match item {
+                    Some(()) => break,
+                    None => return Err(QueueTriggerClosedError),
+                },
+                _ = due => {
+                    let mut pending = delayed.at.lock();
+                    // An earlier delay may have replaced this one
+                    if *pending == at {
+                        *pending = None;
+                        break;
+                    }
+                }
+                // Wait for the new delay instead
+                _ = delayed.changed.notified() => (),
+            }
+        }
+
+        // drain the channel
+        loop {
+            match self.rx.try_recv() {
+                Err(TryRecvError::Closed) => return Err(QueueTriggerClosedError),
+                Err(TryRecvError::Empty) => return Ok(()),
+                Ok(()) => (),
             }
-        } else {
-            Err(QueueTriggerClosedError)
         }
     }
 }
@@ -227,20 +381,45 @@ impl TriggerReceiver {
 ///
 /// This is a way of encapsulating an EnvironmentWrite so that it can only be
 /// used to create a single Writer before being consumed.
-#[derive(Constructor, From)]
-pub struct OneshotWriter(EnvironmentWrite);
+pub struct OneshotWriter {
+    env: EnvironmentWrite,
+    durability: Durability,
+}
+
+impl From<EnvironmentWrite> for OneshotWriter {
+    fn from(env: EnvironmentWrite) -> Self {
+        Self::new(env)
+    }
+}
 
 impl OneshotWriter {
+    /// Constructor, with the default [Durability]
+    pub fn new(env: EnvironmentWrite) -> Self {
+        Self {
+            env,
+            durability: Durability::default(),
+        }
+    }
+
+    /// Set whether the commit waits for its data to reach the disk
+    pub fn with_durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+
     /// Create the writer and pass it into a closure.
     pub fn with_writer<F>(self, f: F) -> Result<(), WorkspaceError>
     where
         F: FnOnce(&mut Writer) -> Result<(), WorkspaceError> + Send,
     {
-        let env_ref = self.0.guard();
+        let env_ref = self.env.guard();
         env_ref.with_commit::<WorkspaceError, (), _>(|w| {
             f(w)?;
             Ok(())
         })?;
+        if self.durability == Durability::Sync {
+            env_ref.sync()?;
+        }
         Ok(())
     }
 }
@@ -283,3 +462,401 @@ async fn next_job_or_exit(
         Job::Run
     }
 }
+
+/// The shortest time a consumer waits before retrying a workflow run which
+/// failed with an error that isn't transient. The wait doubles with each
+/// consecutive failure, up to [MAX_ERROR_BACKOFF].
+/// Transient errors are retried according to the workflow's
+/// [BackoffPolicy](crate::conductor::config::BackoffPolicy) instead.
+const MIN_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+/// The longest time a consumer waits before retrying a failed workflow run
+/// with an error that isn't transient
+const MAX_ERROR_BACKOFF: Duration = Duration::from_secs(60);
+
+/// The state of a workflow whose consumer is waiting to run it again
+/// after one or more failed runs
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowBackoff {
+    /// The workflow which is backing off
+    pub workflow: WorkflowName,
+    /// How many runs in a row have failed
+    pub consecutive_failures: u32,
+    /// Whether the last failure was transient, so the wait follows
+    /// the workflow's configured policy
+    pub transient: bool,
+    /// How long the consumer waits after the last failure
+    pub delay_ms: u64,
+}
+
+/// The backoff policies of a Cell's queue consumers, along with the state
+/// of each workflow which is backing off. Clones share the same state.
+#[derive(Clone, Default)]
+pub struct WorkflowBackoffs {
+    config: Arc<QueueBackoffConfig>,
+    backing_off: Arc<Mutex<BTreeMap<WorkflowName, WorkflowBackoff>>>,
+}
+
+impl WorkflowBackoffs {
+    /// Create the backoff state for consumers using these policies
+    pub fn new(config: QueueBackoffConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            backing_off: Default::default(),
+        }
+    }
+
+    /// The workflows which are backing off after failed runs
+    pub fn backing_off(&self) -> Vec<WorkflowBackoff> {
+        self.backing_off.lock().values().cloned().collect()
+    }
+
+    fn set(&self, backoff: WorkflowBackoff) {
+        self.backing_off.lock().insert(backoff.workflow, backoff);
+    }
+
+    fn clear(&self, workflow: WorkflowName) {
+        self.backing_off.lock().remove(&workflow);
+    }
+}
+
+/// Tracks the runs of a queue consumer's workflow, so that a failed run is
+/// recorded in the Cell's workflow error journal and retried after a backoff
+/// instead of taking the consumer down.
+struct WorkflowRuns {
+    workflow: WorkflowName,
+    trigger: TriggerReason,
+    backoffs: WorkflowBackoffs,
+    failures: u32,
+    backoff: Option<Duration>,
+}
+
+impl WorkflowRuns {
+    fn new(workflow: WorkflowName, backoffs: WorkflowBackoffs) -> Self {
+        Self {
+            workflow,
+            trigger: TriggerReason::Triggered,
+            backoffs,
+            failures: 0,
+            backoff: None,
+        }
+    }
+
+    /// Whether the next run retries an incomplete or failed run
+    fn is_retry(&self) -> bool {
+        self.trigger == TriggerReason::Retry
+    }
+
+    /// Forget any failures once a run succeeds
+    fn reset(&mut self) {
+        if self.failures > 0 {
+            self.backoffs.clear(self.workflow);
+        }
+        self.failures = 0;
+        self.backoff = None;
+    }
+
+    /// How long to wait before retrying a failed run
+    fn next_backoff(&mut self, err: &WorkflowError) -> Duration {
+        self.failures = self.failures.saturating_add(1);
+        let transient = err.is_transient();
+        let backoff = if transient {
+            self.backoffs
+                .config
+                .policy(self.workflow)
+                .delay(self.failures)
+        } else {
+            self.backoff
+                .map(|b| std::cmp::min(b * 2, MAX_ERROR_BACKOFF))
+                .unwrap_or(MIN_ERROR_BACKOFF)
+        };
+        self.backoff = Some(backoff);
+        self.backoffs.set(WorkflowBackoff {
+            workflow: self.workflow,
+            consecutive_failures: self.failures,
+            transient,
+            delay_ms: backoff.as_millis() as u64,
+        });
+        backoff
+    }
+
+    /// Handle the result of a workflow run, retriggering the consumer if
+    /// the queue was not exhausted or the run failed.
+    /// Returns [Job::Shutdown] if the Cell shut down while backing off.
+    async fn finish(
+        &mut self,
+        result: WorkflowResult<WorkComplete>,
+        items_attempted: Option<usize>,
+        env: &EnvironmentWrite,
+        trigger_self: &mut TriggerSender,
+        stop: &mut sync::broadcast::Receiver<()>,
+    ) -> Job {
+        match result {
+            Ok(WorkComplete::Complete) => {
+                self.trigger = TriggerReason::Triggered;
+                self.reset();
+            }
+            Ok(WorkComplete::Incomplete) => {
+                self.trigger = TriggerReason::Retry;
+                self.reset();
+                trigger_self.trigger();
+            }
+            Err(err) => {
+                error!(workflow = ?self.workflow, ?err, "Error running Workflow");
+                if let Err(e) =
+                    record_workflow_error(env, self.workflow, self.trigger, items_attempted, &err)
+                {
+                    error!(workflow = ?self.workflow, ?e, "Failed to record workflow error");
+                }
+                let backoff = self.next_backoff(&err);
+                self.trigger = TriggerReason::Retry;
+
+                // Back off, unless the Cell is shutting down
+                let delay = tokio::time::delay_for(backoff);
+                let kill = stop.recv();
+                tokio::pin!(delay);
+                tokio::pin!(kill);
+                if let Either::Right(_) = futures::future::select(delay, kill).await {
+                    return Job::Shutdown;
+                }
+                trigger_self.trigger();
+            }
+        }
+        Job::Run
+    }
+}
+
+/// Count the items waiting in a consumer's source queue, for the workflow
+/// error journal
+fn queue_len<K: BufKey, V: BufVal>(queue: &KvBufFresh<K, V>) -> Option<usize> {
+    let count =
+        || -> DatabaseResult<usize> { fresh_reader!(queue.env(), |r| queue.iter(&r)?.count()) };
+    count().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conductor::config::BackoffPolicy;
+    use crate::core::{
+        state::{
+            dht_op_integration::{IntegratedDhtOpsStore, IntegratedDhtOpsValue},
+            validation_receipts_db::decode_validation_receipts,
+        },
+        workflow::validation_receipt_workflow::request_validation_receipts,
+    };
+    use ::fixt::prelude::*;
+    use futures::future::FutureExt;
+    use ghost_actor::GhostControlSender;
+    use holo_hash::fixt::{
+        AnyDhtHashFixturator, DhtOpHashFixturator, DnaHashFixturator, HeaderHashFixturator,
+    };
+    use holochain_keystore::KeystoreSenderExt;
+    use holochain_p2p::{
+        actor::HolochainP2pRefToCell, spawn_holochain_p2p, HolochainP2pError, HolochainP2pRef,
+    };
+    use holochain_serialized_bytes::SerializedBytes;
+    use holochain_state::{
+        buffer::BufferedStore,
+        db::{GetDb, INTEGRATED_DHT_OPS},
+        test_utils::test_cell_env,
+    };
+    use holochain_types::{dht_op::DhtOpLight, validate::ValidationStatus, Timestamp};
+    use tokio::time::Instant;
+
+    #[tokio::test]
+    async fn transient_errors_back_off_exponentially() {
+        tokio::time::pause();
+        let test_env = test_cell_env();
+        let env = test_env.env();
+        let config = QueueBackoffConfig {
+            integrate_dht_ops: Some(BackoffPolicy {
+                initial_delay_ms: 20,
+                multiplier: 2.0,
+                max_delay_ms: 1000,
+                jitter: 0.0,
+            }),
+            ..Default::default()
+        };
+        let backoffs = WorkflowBackoffs::new(config);
+        let mut runs = WorkflowRuns::new(WorkflowName::IntegrateDhtOps, backoffs.clone());
+        let (mut trigger_self, mut rx) = TriggerSender::new();
+        let (_stop_tx, mut stop) = sync::broadcast::channel(1);
+        let network_down =
+            || -> WorkflowResult<WorkComplete> { Err(HolochainP2pError::other("down").into()) };
+
+        // The network is down for the first three runs
+        let mut waits = Vec::new();
+        for _ in 0..3 {
+            let started = Instant::now();
+            let job = runs
+                .finish(network_down(), None, &env, &mut trigger_self, &mut stop)
+                .await;
+            assert!(matches!(job, Job::Run));
+            waits.push(started.elapsed());
+            // The consumer is retriggered once it has waited
+            rx.listen().await.unwrap();
+        }
+        let expected: Vec<_> = vec![20, 40, 80]
+            .into_iter()
+            .map(Duration::from_millis)
+            .collect();
+        assert_eq!(waits, expected);
+        assert_eq!(
+            backoffs.backing_off(),
+            vec![WorkflowBackoff {
+                workflow: WorkflowName::IntegrateDhtOps,
+                consecutive_failures: 3,
+                transient: true,
+                delay_ms: 80,
+            }]
+        );
+
+        // A successful run resets the backoff
+        runs.finish(
+            Ok(WorkComplete::Complete),
+            None,
+            &env,
+            &mut trigger_self,
+            &mut stop,
+        )
+        .await;
+        assert_eq!(backoffs.backing_off(), vec![]);
+        runs.finish(network_down(), None, &env, &mut trigger_self, &mut stop)
+            .await;
+        assert_eq!(backoffs.backing_off()[0].delay_ms, 20);
+
+        // Errors which aren't transient keep to the fixed schedule
+        runs.finish(
+            Ok(WorkComplete::Complete),
+            None,
+            &env,
+            &mut trigger_self,
+            &mut stop,
+        )
+        .await;
+        runs.finish(
+            Err(WorkflowError::CapabilityMissing),
+            None,
+            &env,
+            &mut trigger_self,
+            &mut stop,
+        )
+        .await;
+        let backoff = &backoffs.backing_off()[0];
+        assert!(!backoff.transient);
+        assert_eq!(backoff.delay_ms, MIN_ERROR_BACKOFF.as_millis() as u64);
+    }
+
+    #[tokio::test]
+    async fn only_the_earliest_delayed_trigger_is_kept() {
+        tokio::time::pause();
+        let (trigger, mut rx) = TriggerSender::new();
+        trigger.trigger_after(Duration::from_secs(60));
+        trigger.trigger_after(Duration::from_secs(10));
+        trigger.trigger_after(Duration::from_secs(30));
+
+        let started = Instant::now();
+        rx.listen().await.unwrap();
+        assert_eq!(started.elapsed(), Duration::from_secs(10));
+
+        // The later delays were dropped rather than left running
+        let next = tokio::time::timeout(Duration::from_secs(120), rx.listen()).await;
+        assert!(next.is_err());
+    }
+
+    /// - Receipts owed for ops integrated while a batch window is open
+    ///   reach their publisher in one message
+    /// - The triggers which came while the window was open don't
+    ///   lead to any more runs
+    #[tokio::test(threaded_scheduler)]
+    async fn receipts_triggered_within_a_window_are_sent_in_one_message() {
+        let test_env = test_cell_env();
+        let env = test_env.env();
+        let keystore = env.keystore().clone();
+        let validator = keystore
+            .generate_sign_keypair_from_pure_entropy()
+            .await
+            .unwrap();
+        let publisher = keystore
+            .generate_sign_keypair_from_pure_entropy()
+            .await
+            .unwrap();
+
+        // Both agents join a real network, which records the messages it delivers
+        let dna = fixt!(DnaHash);
+        let (network, mut recv) = spawn_holochain_p2p(Default::default()).await.unwrap();
+        let sent: Arc<Mutex<Vec<SerializedBytes>>> = Arc::new(Mutex::new(Vec::new()));
+        let recv_task = tokio::task::spawn({
+            let sent = sent.clone();
+            async move {
+                use tokio::stream::StreamExt;
+                while let Some(evt) = recv.next().await {
+                    use holochain_p2p::event::HolochainP2pEvent::*;
+                    if let ValidationReceiptReceived {
+                        respond, receipt, ..
+                    } = evt
+                    {
+                        sent.lock().push(receipt);
+                        respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+                    }
+                }
+            }
+        });
+        for agent in vec![validator.clone(), publisher.clone()] {
+            HolochainP2pRef::join(&network, dna.clone(), agent)
+                .await
+                .unwrap();
+        }
+        let cell_network = network.to_cell(dna, validator);
+
+        let flush_interval = Duration::from_millis(500);
+        let (stop_tx, stop) = sync::broadcast::channel(1);
+        let (mut trigger, handle) = spawn_validation_receipt_consumer(
+            env.clone(),
+            stop,
+            cell_network,
+            flush_interval,
+            WorkflowBackoffs::default(),
+        );
+
+        // Three integration passes inside the window each owe one receipt
+        let mut integrated: IntegratedDhtOpsStore = KvBufFresh::new(
+            env.clone().into(),
+            env.get_db(&*INTEGRATED_DHT_OPS).unwrap(),
+        );
+        let started = Instant::now();
+        for _ in 0..3 {
+            let op_hash = fixt!(DhtOpHash);
+            let value = IntegratedDhtOpsValue {
+                validation_status: ValidationStatus::Valid,
+                op: DhtOpLight::RegisterAgentActivity(fixt!(HeaderHash), fixt!(AnyDhtHash)),
+                when_integrated: Timestamp::now(),
+            };
+            integrated.put(op_hash.clone(), value).unwrap();
+            env.guard()
+                .with_commit(|writer| integrated.flush_to_txn_ref(writer))
+                .unwrap();
+            request_validation_receipts(&env, publisher.clone(), vec![op_hash]).unwrap();
+            trigger.trigger();
+            tokio::time::delay_for(Duration::from_millis(50)).await;
+        }
+        assert!(started.elapsed() < flush_interval);
+
+        // Wait out the window and then a few more
+        tokio::time::delay_for(flush_interval * 4).await;
+        stop_tx.send(()).unwrap();
+        handle.await.unwrap().unwrap();
+        tokio::time::timeout(Duration::from_secs(10), network.ghost_actor_shutdown())
+            .await
+            .ok();
+        tokio::time::timeout(Duration::from_secs(10), recv_task)
+            .await
+            .ok();
+
+        let sent = sent.lock();
+        assert_eq!(sent.len(), 1);
+        let receipts = decode_validation_receipts(sent[0].clone()).unwrap();
+        assert_eq!(receipts.len(), 3);
+    }
+}
@@ -3,25 +3,44 @@
 use super::*;
 use crate::{
     conductor::manager::ManagedTaskResult,
-    core::workflow::app_validation_workflow::{app_validation_workflow, AppValidationWorkspace},
+    core::{
+        ribosome::validation_pool::ExecutionContext,
+        signal::SystemSignal,
+        validation_breaker::{Admission, ValidationBreaker},
+        workflow::app_validation_workflow::{app_validation_workflow, AppValidationWorkspace},
+    },
 };
 use holochain_state::env::EnvironmentWrite;
 
+use std::time::Instant;
 use tokio::task::JoinHandle;
 use tracing::*;
 
 /// Spawn the QueueConsumer for AppValidation workflow
-#[instrument(skip(env, stop, trigger_integration, conductor_api, network))]
+#[instrument(skip(
+    env,
+    stop,
+    trigger_integration,
+    conductor_api,
+    network,
+    validation_context,
+    backoffs,
+    breaker
+))]
 pub fn spawn_app_validation_consumer(
     env: EnvironmentWrite,
     mut stop: sync::broadcast::Receiver<()>,
     mut trigger_integration: TriggerSender,
     conductor_api: impl CellConductorApiT + 'static,
     network: HolochainP2pCell,
+    validation_context: ExecutionContext,
+    backoffs: WorkflowBackoffs,
+    breaker: ValidationBreaker,
 ) -> (TriggerSender, JoinHandle<ManagedTaskResult>) {
     let (tx, mut rx) = TriggerSender::new();
     let mut trigger_self = tx.clone();
     let handle = tokio::spawn(async move {
+        let mut runs = WorkflowRuns::new(WorkflowName::AppValidation, backoffs);
         loop {
             // Wait for next job
             if let Job::Shutdown = next_job_or_exit(&mut rx, &mut stop).await {
@@ -31,23 +50,123 @@ pub fn spawn_app_validation_consumer(
                 break;
             }
 
+            // Hold off while the circuit breaker is open,
+            // unless it is forced open or closed in the meantime
+            let limit = match breaker.admit(Instant::now()) {
+                Admission::All => None,
+                Admission::Probe(batch) => Some(batch),
+                Admission::Wait(delay) => {
+                    let delay = tokio::time::delay_for(delay);
+                    let changed = breaker.changed();
+                    let kill = stop.recv();
+                    tokio::pin!(delay);
+                    tokio::pin!(changed);
+                    tokio::pin!(kill);
+                    let wake = futures::future::select(delay, changed);
+                    if let Either::Right(_) = futures::future::select(wake, kill).await {
+                        tracing::warn!(
+                            "Cell is shutting down: stopping app_validation_workflow queue consumer."
+                        );
+                        break;
+                    }
+                    trigger_self.trigger();
+                    continue;
+                }
+            };
+
             // Run the workflow
             let workspace = AppValidationWorkspace::new(env.clone().into())
                 .expect("Could not create Workspace");
-            if let WorkComplete::Incomplete = app_validation_workflow(
+            let items_attempted = queue_len(&workspace.validation_limbo.0);
+            let result = app_validation_workflow(
                 workspace,
                 env.clone().into(),
                 &mut trigger_integration,
                 conductor_api.clone(),
                 network.clone(),
+                &validation_context,
+                &breaker,
+                limit,
             )
-            .await
-            .expect("Error running Workflow")
+            .await;
+            breaker.end_run(Instant::now());
+            signal_if_opened(&breaker, &conductor_api).await;
+            if let Job::Shutdown = runs
+                .finish(result, items_attempted, &env, &mut trigger_self, &mut stop)
+                .await
             {
-                trigger_self.trigger()
-            };
+                tracing::warn!(
+                    "Cell is shutting down: stopping app_validation_workflow queue consumer."
+                );
+                break;
+            }
         }
         Ok(())
     });
     (tx, handle)
 }
+
+/// Tell the Cell's interfaces if the breaker opened during the last run
+async fn signal_if_opened(breaker: &ValidationBreaker, conductor_api: &impl CellConductorApiT) {
+    if let Some(sample_error) = breaker.take_opened() {
+        let cell_id = conductor_api.cell_id().clone();
+        warn!(?cell_id, %sample_error, "App validation circuit breaker opened");
+        let signal = SystemSignal::ValidationCircuitOpen {
+            cell_id,
+            sample_error,
+        };
+        if let Err(e) = conductor_api.signal_broadcaster().await.send(signal.into()) {
+            error!(?e, "Failed to send the validation circuit signal");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conductor::{
+        api::MockCellConductorApi,
+        config::ValidationCircuitBreakerConfig,
+        interface::{signal_queue::SignalQueue, websocket::ConnectionCounter, SignalBroadcaster},
+    };
+    use crate::core::signal::Signal;
+    use ::fixt::prelude::*;
+    use holochain_types::fixt::CellIdFixturator;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn opening_the_breaker_signals_once() {
+        let breaker = ValidationBreaker::new(ValidationCircuitBreakerConfig {
+            min_executions: 2,
+            ..Default::default()
+        });
+        let queue = SignalQueue::new("app".into(), 8, ConnectionCounter::default());
+        let cell_id = fixt!(CellId);
+        let mut conductor_api = MockCellConductorApi::new();
+        conductor_api.expect_cell_id().return_const(cell_id.clone());
+        let broadcaster = SignalBroadcaster::new(vec![queue.clone()]);
+        conductor_api
+            .expect_mock_signal_broadcaster()
+            .returning(move || broadcaster.clone());
+
+        // Nothing is sent while the breaker is closed
+        signal_if_opened(&breaker, &conductor_api).await;
+        assert_eq!(queue.stats().queued, 0);
+
+        let now = Instant::now();
+        breaker.record_error(now, "boom".into());
+        breaker.record_error(now, "boom again".into());
+        breaker.end_run(now);
+        signal_if_opened(&breaker, &conductor_api).await;
+        assert_eq!(
+            queue.recv().await.unwrap(),
+            Signal::System(SystemSignal::ValidationCircuitOpen {
+                cell_id,
+                sample_error: "boom again".into(),
+            })
+        );
+
+        // The same opening isn't signalled twice
+        signal_if_opened(&breaker, &conductor_api).await;
+        assert_eq!(queue.stats().queued, 0);
+    }
+}
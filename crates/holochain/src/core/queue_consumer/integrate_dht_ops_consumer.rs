@@ -14,16 +14,23 @@ use tokio::task::JoinHandle;
 use tracing::*;
 
 /// Spawn the QueueConsumer for DhtOpIntegration workflow
-#[instrument(skip(env, stop, trigger_sys))]
+/// A notification is sent on `integrated` after each run, and the
+/// ValidationReceipt workflow is triggered to send receipts for the newly
+/// integrated ops.
+#[instrument(skip(env, stop, trigger_sys, trigger_receipt, integrated, backoffs))]
 pub fn spawn_integrate_dht_ops_consumer(
     env: EnvironmentWrite,
     mut stop: sync::broadcast::Receiver<()>,
     trigger_sys: sync::oneshot::Receiver<TriggerSender>,
+    mut trigger_receipt: TriggerSender,
+    integrated: sync::broadcast::Sender<()>,
+    backoffs: WorkflowBackoffs,
 ) -> (TriggerSender, JoinHandle<ManagedTaskResult>) {
     let (tx, mut rx) = TriggerSender::new();
     let mut trigger_self = tx.clone();
     let handle = tokio::spawn(async move {
         let mut trigger_sys = trigger_sys.await.expect("failed to get tx sys");
+        let mut runs = WorkflowRuns::new(WorkflowName::IntegrateDhtOps, backoffs);
         loop {
             // Wait for next job
             if let Job::Shutdown = next_job_or_exit(&mut rx, &mut stop).await {
@@ -36,15 +43,96 @@ pub fn spawn_integrate_dht_ops_consumer(
             // Run the workflow
             let workspace = IntegrateDhtOpsWorkspace::new(env.clone().into())
                 .expect("Could not create Workspace");
-            if let WorkComplete::Incomplete =
-                integrate_dht_ops_workflow(workspace, env.clone().into(), &mut trigger_sys)
-                    .await
-                    .expect("Error running Workflow")
+            let items_attempted = queue_len(&workspace.integration_limbo);
+            let result =
+                integrate_dht_ops_workflow(workspace, env.clone().into(), &mut trigger_sys).await;
+            // No one may be listening
+            integrated.send(()).ok();
+            trigger_receipt.trigger();
+            if let Job::Shutdown = runs
+                .finish(result, items_attempted, &env, &mut trigger_self, &mut stop)
+                .await
             {
-                trigger_self.trigger()
-            };
+                tracing::warn!(
+                    "Cell is shutting down: stopping integrate_dht_ops_workflow queue consumer."
+                );
+                break;
+            }
         }
         Ok(())
     });
     (tx, handle)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::state::{
+        dht_op_integration::IntegrationLimboValue, workflow_errors::get_workflow_errors,
+        workspace::Workspace,
+    };
+    use ::fixt::prelude::*;
+    use holo_hash::fixt::{AnyDhtHashFixturator, DhtOpHashFixturator, HeaderHashFixturator};
+    use holochain_state::test_utils::test_cell_env;
+    use holochain_types::{dht_op::DhtOpLight, validate::ValidationStatus};
+
+    #[tokio::test(threaded_scheduler)]
+    async fn poisoned_op_is_journaled_across_retries() {
+        let test_env = test_cell_env();
+        let env = test_env.env();
+
+        // An op in the integration limbo whose header is missing
+        {
+            let mut workspace = IntegrateDhtOpsWorkspace::new(env.clone().into()).unwrap();
+            let val = IntegrationLimboValue {
+                validation_status: ValidationStatus::Valid,
+                op: DhtOpLight::RegisterAgentActivity(fixt!(HeaderHash), fixt!(AnyDhtHash)),
+            };
+            workspace
+                .integration_limbo
+                .put(fixt!(DhtOpHash), val)
+                .unwrap();
+            env.guard()
+                .with_commit(|writer| workspace.flush_to_txn(writer))
+                .unwrap();
+        }
+
+        let (stop_tx, _) = sync::broadcast::channel(1);
+        let (tx_sys, _rx_sys) = TriggerSender::new();
+        let (create_tx_sys, get_tx_sys) = sync::oneshot::channel();
+        if create_tx_sys.send(tx_sys).is_err() {
+            panic!("Failed to send tx_sys");
+        }
+        let (tx_receipt, _rx_receipt) = TriggerSender::new();
+        let (integrated, _) = sync::broadcast::channel(1);
+        let (mut trigger, handle) = spawn_integrate_dht_ops_consumer(
+            env.clone(),
+            stop_tx.subscribe(),
+            get_tx_sys,
+            tx_receipt,
+            integrated,
+            WorkflowBackoffs::default(),
+        );
+        trigger.trigger();
+
+        // Wait for the consumer to retry the op a few times
+        let mut records = Vec::new();
+        for _ in 0..50 {
+            tokio::time::delay_for(std::time::Duration::from_millis(100)).await;
+            records = get_workflow_errors(&env.clone().into()).unwrap();
+            if records.first().map(|r| r.count >= 3).unwrap_or(false) {
+                break;
+            }
+        }
+        stop_tx.send(()).unwrap();
+        handle.await.unwrap().unwrap();
+
+        // The retries are coalesced into a single record
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.workflow, WorkflowName::IntegrateDhtOps);
+        assert!(record.count >= 3);
+        assert_eq!(record.trigger, TriggerReason::Retry);
+        assert_eq!(record.items_attempted, Some(1));
+    }
+}
@@ -2,7 +2,7 @@
 
 use super::*;
 use crate::{
-    conductor::manager::ManagedTaskResult,
+    conductor::{api::CellConductorApiT, manager::ManagedTaskResult},
     core::workflow::produce_dht_ops_workflow::{produce_dht_ops_workflow, ProduceDhtOpsWorkspace},
 };
 use holochain_state::env::EnvironmentWrite;
@@ -11,15 +11,18 @@ use tokio::task::JoinHandle;
 use tracing::*;
 
 /// Spawn the QueueConsumer for Produce_dht_ops workflow
-#[instrument(skip(env, stop, trigger_publish))]
+#[instrument(skip(env, stop, trigger_publish, conductor_api, backoffs))]
 pub fn spawn_produce_dht_ops_consumer(
     env: EnvironmentWrite,
     mut stop: sync::broadcast::Receiver<()>,
     mut trigger_publish: TriggerSender,
+    conductor_api: impl CellConductorApiT + 'static,
+    backoffs: WorkflowBackoffs,
 ) -> (TriggerSender, JoinHandle<ManagedTaskResult>) {
     let (tx, mut rx) = TriggerSender::new();
     let mut trigger_self = tx.clone();
     let handle = tokio::spawn(async move {
+        let mut runs = WorkflowRuns::new(WorkflowName::ProduceDhtOps, backoffs);
         loop {
             if let Job::Shutdown = next_job_or_exit(&mut rx, &mut stop).await {
                 tracing::warn!(
@@ -30,13 +33,22 @@ pub fn spawn_produce_dht_ops_consumer(
 
             let workspace = ProduceDhtOpsWorkspace::new(env.clone().into())
                 .expect("Could not create Workspace");
-            if let WorkComplete::Incomplete =
-                produce_dht_ops_workflow(workspace, env.clone().into(), &mut trigger_publish)
-                    .await
-                    .expect("Error running Workflow")
+            let result = produce_dht_ops_workflow(
+                workspace,
+                env.clone().into(),
+                &mut trigger_publish,
+                &conductor_api,
+            )
+            .await;
+            if let Job::Shutdown = runs
+                .finish(result, None, &env, &mut trigger_self, &mut stop)
+                .await
             {
-                trigger_self.trigger()
-            };
+                tracing::warn!(
+                    "Cell is shutting down: stopping produce_dht_ops_workflow queue consumer."
+                );
+                break;
+            }
         }
         Ok(())
     });
@@ -4,7 +4,10 @@ use super::*;
 
 use crate::{
     conductor::manager::ManagedTaskResult,
-    core::workflow::publish_dht_ops_workflow::{publish_dht_ops_workflow, PublishDhtOpsWorkspace},
+    core::{
+        gossip_metrics::GossipMeter,
+        workflow::publish_dht_ops_workflow::{publish_dht_ops_workflow, PublishDhtOpsWorkspace},
+    },
 };
 use holochain_state::env::EnvironmentWrite;
 
@@ -12,15 +15,18 @@ use tokio::task::JoinHandle;
 use tracing::*;
 
 /// Spawn the QueueConsumer for Publish workflow
-#[instrument(skip(env, stop, cell_network))]
+#[instrument(skip(env, stop, cell_network, gossip_meter, backoffs))]
 pub fn spawn_publish_dht_ops_consumer(
     env: EnvironmentWrite,
     mut stop: sync::broadcast::Receiver<()>,
     mut cell_network: HolochainP2pCell,
+    gossip_meter: GossipMeter,
+    backoffs: WorkflowBackoffs,
 ) -> (TriggerSender, JoinHandle<ManagedTaskResult>) {
     let (tx, mut rx) = TriggerSender::new();
     let mut trigger_self = tx.clone();
     let handle = tokio::spawn(async move {
+        let mut runs = WorkflowRuns::new(WorkflowName::PublishDhtOps, backoffs);
         loop {
             // Wait for next job
             if let Job::Shutdown = next_job_or_exit(&mut rx, &mut stop).await {
@@ -33,13 +39,22 @@ pub fn spawn_publish_dht_ops_consumer(
             // Run the workflow
             let workspace = PublishDhtOpsWorkspace::new(env.clone().into())
                 .expect("Could not create Workspace");
-            if let WorkComplete::Incomplete =
-                publish_dht_ops_workflow(workspace, env.clone().into(), &mut cell_network)
-                    .await
-                    .expect("Error running Workflow")
+            let result = publish_dht_ops_workflow(
+                workspace,
+                env.clone().into(),
+                &mut cell_network,
+                &gossip_meter,
+            )
+            .await;
+            if let Job::Shutdown = runs
+                .finish(result, None, &env, &mut trigger_self, &mut stop)
+                .await
             {
-                trigger_self.trigger()
-            };
+                tracing::warn!(
+                    "Cell is shutting down: stopping publish_dht_ops_workflow queue consumer."
+                );
+                break;
+            }
         }
         Ok(())
     });
@@ -10,17 +10,19 @@ use tokio::task::JoinHandle;
 use tracing::*;
 
 /// Spawn the QueueConsumer for SysValidation workflow
-#[instrument(skip(env, stop, trigger_app_validation, network, conductor_api))]
+#[instrument(skip(env, stop, trigger_app_validation, network, conductor_api, backoffs))]
 pub fn spawn_sys_validation_consumer(
     env: EnvironmentWrite,
     mut stop: sync::broadcast::Receiver<()>,
     mut trigger_app_validation: TriggerSender,
     network: HolochainP2pCell,
     conductor_api: impl CellConductorApiT + 'static,
+    backoffs: WorkflowBackoffs,
 ) -> (TriggerSender, JoinHandle<ManagedTaskResult>) {
     let (tx, mut rx) = TriggerSender::new();
     let mut trigger_self = tx.clone();
     let handle = tokio::spawn(async move {
+        let mut runs = WorkflowRuns::new(WorkflowName::SysValidation, backoffs);
         loop {
             // Wait for next job
             if let Job::Shutdown = next_job_or_exit(&mut rx, &mut stop).await {
@@ -33,7 +35,8 @@ pub fn spawn_sys_validation_consumer(
             // Run the workflow
             let workspace = SysValidationWorkspace::new(env.clone().into())
                 .expect("Could not create Workspace");
-            if let WorkComplete::Incomplete = sys_validation_workflow(
+            let items_attempted = queue_len(&workspace.validation_limbo.0);
+            let result = sys_validation_workflow(
                 workspace,
                 env.clone().into(),
                 &mut trigger_app_validation,
@@ -41,11 +44,16 @@ pub fn spawn_sys_validation_consumer(
                 network.clone(),
                 conductor_api.clone(),
             )
-            .await
-            .expect("Error running Workflow")
+            .await;
+            if let Job::Shutdown = runs
+                .finish(result, items_attempted, &env, &mut trigger_self, &mut stop)
+                .await
             {
-                trigger_self.trigger()
-            };
+                tracing::warn!(
+                    "Cell is shutting down: stopping sys_validation_workflow queue consumer."
+                );
+                break;
+            }
         }
         Ok(())
     });
@@ -0,0 +1,83 @@
+//! The workflow and queue consumer for sending validation receipts
+
+use super::*;
+
+use crate::{
+    conductor::manager::ManagedTaskResult,
+    core::workflow::validation_receipt_workflow::{
+        validation_receipt_workflow, ValidationReceiptWorkspace,
+    },
+};
+use holochain_state::env::EnvironmentWrite;
+
+use tokio::task::JoinHandle;
+use tracing::*;
+
+/// Spawn the QueueConsumer for ValidationReceipt workflow.
+/// A trigger opens a batch window of `flush_interval`, and the workflow runs
+/// once when it closes, so that the receipts for ops integrated in the
+/// meantime are sent together. Triggers which come while the window is open
+/// join its run instead of opening another.
+#[instrument(skip(env, stop, cell_network, backoffs))]
+pub fn spawn_validation_receipt_consumer(
+    env: EnvironmentWrite,
+    mut stop: sync::broadcast::Receiver<()>,
+    mut cell_network: HolochainP2pCell,
+    flush_interval: Duration,
+    backoffs: WorkflowBackoffs,
+) -> (TriggerSender, JoinHandle<ManagedTaskResult>) {
+    let (tx, mut rx) = TriggerSender::new();
+    let mut trigger_self = tx.clone();
+    let handle = tokio::spawn(async move {
+        let mut runs = WorkflowRuns::new(WorkflowName::ValidationReceipt, backoffs);
+        // When the open batch window closes, if there is one
+        let mut window_closes = None;
+        loop {
+            // Wait for next job
+            if let Job::Shutdown = next_job_or_exit(&mut rx, &mut stop).await {
+                tracing::warn!(
+                    "Cell is shutting down: stopping validation_receipt_workflow queue consumer."
+                );
+                break;
+            }
+
+            // Let more ops be integrated before sending their receipts.
+            // A retry has already backed off, so it doesn't wait again.
+            if !runs.is_retry() {
+                let now = tokio::time::Instant::now();
+                match window_closes {
+                    None => {
+                        window_closes = Some(now + flush_interval);
+                        trigger_self.trigger_after(flush_interval);
+                        continue;
+                    }
+                    Some(closes) if now < closes => continue,
+                    Some(_) => (),
+                }
+            }
+            window_closes = None;
+
+            // Run the workflow
+            let workspace = ValidationReceiptWorkspace::new(env.clone().into())
+                .expect("Could not create Workspace");
+            let result = validation_receipt_workflow(
+                workspace,
+                env.clone().into(),
+                &mut cell_network,
+                env.keystore().clone(),
+            )
+            .await;
+            if let Job::Shutdown = runs
+                .finish(result, None, &env, &mut trigger_self, &mut stop)
+                .await
+            {
+                tracing::warn!(
+                    "Cell is shutting down: stopping validation_receipt_workflow queue consumer."
+                );
+                break;
+            }
+        }
+        Ok(())
+    });
+    (tx, handle)
+}
@@ -10,10 +10,16 @@
 pub mod error;
 pub mod guest_callback;
 pub mod host_fn;
+pub mod metering;
+pub mod module_cache;
+pub mod validation_pool;
 pub mod wasm_ribosome;
+pub mod zome_info_cache;
 
 use crate::core::ribosome::guest_callback::entry_defs::EntryDefsInvocation;
 use crate::core::ribosome::guest_callback::entry_defs::EntryDefsResult;
+use crate::core::ribosome::guest_callback::index_hint::IndexHintInvocation;
+use crate::core::ribosome::guest_callback::index_hint::IndexHintResult;
 use crate::core::ribosome::guest_callback::init::InitInvocation;
 use crate::core::ribosome::guest_callback::init::InitResult;
 use crate::core::ribosome::guest_callback::migrate_agent::MigrateAgentInvocation;
@@ -46,6 +52,7 @@ use holo_hash::AgentPubKey;
 use holochain_keystore::KeystoreSender;
 use holochain_p2p::HolochainP2pCell;
 use holochain_serialized_bytes::prelude::*;
+use holochain_state::env::Durability;
 use holochain_types::cell::CellId;
 use holochain_types::dna::zome::HostFnAccess;
 use holochain_types::dna::DnaFile;
@@ -55,11 +62,13 @@ use holochain_wasm_test_utils::TestWasm;
 use holochain_zome_types::capability::CapGrant;
 use holochain_zome_types::zome::FunctionName;
 use holochain_zome_types::zome::ZomeName;
+use holochain_zome_types::zome_info::ZomeInfo;
 use holochain_zome_types::ExternOutput;
 use holochain_zome_types::ZomeCallResponse;
 use holochain_zome_types::{capability::CapSecret, header::ZomeId, ExternInput};
 use mockall::automock;
 use std::iter::Iterator;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct CallContext {
@@ -301,6 +310,14 @@ pub struct ZomeCallInvocation {
     pub payload: ExternInput,
     /// the provenance of the call
     pub provenance: AgentPubKey,
+    /// Whether committing what the call wrote waits for it to reach the disk.
+    /// Calls which don't say are synced.
+    #[serde(default = "zome_call_durability_default")]
+    pub durability: Durability,
+}
+
+fn zome_call_durability_default() -> Durability {
+    Durability::Sync
 }
 
 fixturator!(
@@ -312,6 +329,7 @@ fixturator!(
         fn_name: FunctionNameFixturator::new(Empty).next().unwrap(),
         payload: ExternInputFixturator::new(Empty).next().unwrap(),
         provenance: AgentPubKeyFixturator::new(Empty).next().unwrap(),
+        durability: Durability::Sync,
     };
     curve Unpredictable ZomeCallInvocation {
         cell_id: CellIdFixturator::new(Unpredictable).next().unwrap(),
@@ -320,6 +338,7 @@ fixturator!(
         fn_name: FunctionNameFixturator::new(Unpredictable).next().unwrap(),
         payload: ExternInputFixturator::new(Unpredictable).next().unwrap(),
         provenance: AgentPubKeyFixturator::new(Unpredictable).next().unwrap(),
+        durability: Durability::Sync,
     };
     curve Predictable ZomeCallInvocation {
         cell_id: CellIdFixturator::new_indexed(Predictable, self.0.index)
@@ -340,6 +359,7 @@ fixturator!(
         provenance: AgentPubKeyFixturator::new_indexed(Predictable, self.0.index)
             .next()
             .unwrap(),
+        durability: Durability::Sync,
     };
 );
 
@@ -367,6 +387,86 @@ impl Iterator for ZomeCallInvocationFixturator<NamedInvocation> {
     }
 }
 
+/// Fixturator curve for a named zome invocation with some of its fields set,
+/// built with [ZomeCallInvocationFixturator::with_cap] and friends.
+/// The fields which aren't set are as for [NamedInvocation].
+pub struct CustomInvocation {
+    named: NamedInvocation,
+    cap: Option<Option<CapSecret>>,
+    provenance: Option<AgentPubKey>,
+    payload: Option<ExternInput>,
+}
+
+impl From<NamedInvocation> for CustomInvocation {
+    fn from(named: NamedInvocation) -> Self {
+        Self {
+            named,
+            cap: None,
+            provenance: None,
+            payload: None,
+        }
+    }
+}
+
+impl<C: Into<CustomInvocation>> ZomeCallInvocationFixturator<C> {
+    /// Call with this cap secret rather than none, as a remote caller would
+    pub fn with_cap(
+        self,
+        cap: Option<CapSecret>,
+    ) -> ZomeCallInvocationFixturator<CustomInvocation> {
+        self.customize(|custom| custom.cap = Some(cap))
+    }
+
+    /// Call as this agent rather than the agent of the cell
+    pub fn with_provenance(
+        self,
+        provenance: AgentPubKey,
+    ) -> ZomeCallInvocationFixturator<CustomInvocation> {
+        self.customize(|custom| custom.provenance = Some(provenance))
+    }
+
+    /// Call with this payload rather than the one the invocation was named with
+    pub fn with_payload(
+        self,
+        payload: SerializedBytes,
+    ) -> ZomeCallInvocationFixturator<CustomInvocation> {
+        self.customize(|custom| custom.payload = Some(ExternInput::new(payload)))
+    }
+
+    fn customize(
+        self,
+        f: impl FnOnce(&mut CustomInvocation),
+    ) -> ZomeCallInvocationFixturator<CustomInvocation> {
+        let index = self.0.index;
+        let mut custom = self.0.curve.into();
+        f(&mut custom);
+        ZomeCallInvocationFixturator::new_indexed(custom, index)
+    }
+}
+
+impl Iterator for ZomeCallInvocationFixturator<CustomInvocation> {
+    type Item = ZomeCallInvocation;
+    fn next(&mut self) -> Option<Self::Item> {
+        let custom = &self.0.curve;
+        let NamedInvocation(cell_id, test_wasm, fn_name, payload) = &custom.named;
+        let mut ret = ZomeCallInvocationFixturator::new(NamedInvocation(
+            cell_id.clone(),
+            *test_wasm,
+            fn_name.clone(),
+            custom.payload.clone().unwrap_or_else(|| payload.clone()),
+        ))
+        .next()
+        .unwrap();
+        if let Some(cap) = &custom.cap {
+            ret.cap = cap.clone();
+        }
+        if let Some(provenance) = &custom.provenance {
+            ret.provenance = provenance.clone();
+        }
+        Some(ret)
+    }
+}
+
 impl Invocation for ZomeCallInvocation {
     fn zomes(&self) -> ZomesToInvoke {
         ZomesToInvoke::One(self.zome_name.to_owned())
@@ -413,6 +513,9 @@ pub trait RibosomeT: Sized + std::fmt::Debug {
 
     fn zome_name_to_id(&self, zome_name: &ZomeName) -> RibosomeResult<ZomeId>;
 
+    /// The [ZomeInfo] of a zome of this ribosome's Dna
+    fn zome_info(&self, zome_name: &ZomeName) -> RibosomeResult<Arc<ZomeInfo>>;
+
     fn maybe_call<I: Invocation + 'static>(
         &self,
         access: HostAccess,
@@ -480,6 +583,14 @@ pub trait RibosomeT: Sized + std::fmt::Debug {
         invocation: ValidateLinkInvocation<I>,
     ) -> RibosomeResult<ValidateLinkResult>;
 
+    /// Get the keys to index an entry under, with the same
+    /// read only access as validation
+    fn run_index_hint(
+        &self,
+        access: ValidateHostAccess,
+        invocation: IndexHintInvocation,
+    ) -> RibosomeResult<IndexHintResult>;
+
     fn call_iterator<R: 'static + RibosomeT, I: 'static + Invocation>(
         &self,
         access: HostAccess,
@@ -502,6 +613,43 @@ impl std::fmt::Debug for MockRibosomeT {
     }
 }
 
+/// The zome calls a [MockRibosomeT] received, so tests can assert
+/// on what reached the ribosome once the workflow has run.
+/// Clones share the same calls.
+#[cfg(test)]
+#[derive(Clone)]
+pub struct RecordingRibosome {
+    calls: Arc<parking_lot::Mutex<Vec<(ZomeCallInvocation, ZomeCallHostAccess)>>>,
+}
+
+#[cfg(test)]
+impl RecordingRibosome {
+    /// Mock `call_zome_function` on the ribosome, recording each
+    /// call before answering it with `respond`
+    pub fn new<F>(ribosome: &mut MockRibosomeT, mut respond: F) -> Self
+    where
+        F: FnMut(&ZomeCallHostAccess, &ZomeCallInvocation) -> RibosomeResult<ZomeCallResponse>
+            + Send
+            + 'static,
+    {
+        let calls: Arc<parking_lot::Mutex<Vec<_>>> = Default::default();
+        let recorded = calls.clone();
+        ribosome
+            .expect_call_zome_function()
+            .returning(move |access, invocation| {
+                let response = respond(&access, &invocation);
+                recorded.lock().push((invocation, access));
+                response
+            });
+        Self { calls }
+    }
+
+    /// The invocations and host access of each call, in the order they were made
+    pub fn calls(&self) -> Vec<(ZomeCallInvocation, ZomeCallHostAccess)> {
+        self.calls.lock().clone()
+    }
+}
+
 #[cfg(test)]
 pub mod wasm_test {
     use crate::core::ribosome::FnComponents;
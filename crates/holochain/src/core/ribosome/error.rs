@@ -5,7 +5,7 @@ use crate::{
     conductor::interface::error::InterfaceError,
     core::state::{cascade::error::CascadeError, source_chain::SourceChainError},
 };
-use holo_hash::AnyDhtHash;
+use holo_hash::{AgentPubKey, AnyDhtHash};
 use holochain_serialized_bytes::prelude::SerializedBytesError;
 use holochain_types::dna::error::DnaError;
 use holochain_wasmer_host::prelude::WasmError;
@@ -38,6 +38,17 @@ pub enum RibosomeError {
     #[error("Attempted to call a zome function that doesn't exist: Zome: {0} Fn {1}")]
     ZomeFnNotExists(ZomeName, FunctionName),
 
+    /// A metered zome call burned more fuel than it may, so it was aborted
+    #[error("A call to Zome: {zome_name} Fn {fn_name} burned more than its fuel limit of {limit}")]
+    FuelExhausted {
+        /// The zome called
+        zome_name: ZomeName,
+        /// The function called
+        fn_name: FunctionName,
+        /// How much fuel the call could burn
+        limit: u64,
+    },
+
     /// a problem with entry defs
     #[error("An error with entry defs: {0}")]
     EntryDefs(ZomeName, String),
@@ -49,6 +60,11 @@ pub enum RibosomeError {
     #[error("A mandatory element is missing, dht hash: {0}")]
     ElementDeps(AnyDhtHash),
 
+    /// A remote call asked for its secret to be looked up, but there is no CapClaim for the
+    /// called agent and function on the caller's source chain
+    #[error("No CapClaim found for calling agent {0} Zome: {1} Fn {2}")]
+    NoClaimFound(AgentPubKey, ZomeName, FunctionName),
+
     /// ident
     #[error("Unspecified ring error")]
     RingUnspecified,
@@ -1,4 +1,5 @@
 pub mod entry_defs;
+pub mod index_hint;
 pub mod init;
 pub mod migrate_agent;
 pub mod post_commit;
@@ -14,6 +15,26 @@ use fallible_iterator::FallibleIterator;
 use holochain_zome_types::zome::ZomeName;
 use holochain_zome_types::ExternOutput;
 
+/// The externs the host calls back into, rather than clients calling them.
+/// A callback may also be defined more specifically, with further components
+/// joined by underscores, as in `validate_create_entry`.
+pub const CALLBACK_EXTERNS: [&str; 7] = [
+    "entry_defs",
+    "index_hint",
+    "init",
+    "migrate_agent",
+    "post_commit",
+    "validate",
+    "validation_package",
+];
+
+/// Check if an extern is one of the host's callbacks, by its name
+pub fn is_callback_extern(name: &str) -> bool {
+    CALLBACK_EXTERNS.iter().any(|callback| {
+        name == *callback || (name.starts_with(callback) && name[callback.len()..].starts_with('_'))
+    })
+}
+
 pub struct CallIterator<R: RibosomeT, I: Invocation> {
     host_access: HostAccess,
     ribosome: R,
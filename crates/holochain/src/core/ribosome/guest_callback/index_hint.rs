@@ -0,0 +1,126 @@
+use crate::core::ribosome::FnComponents;
+use crate::core::ribosome::Invocation;
+use crate::core::ribosome::ZomesToInvoke;
+use holochain_serialized_bytes::prelude::*;
+use holochain_zome_types::entry::Entry;
+use holochain_zome_types::index_hint::IndexHintCallbackResult;
+use holochain_zome_types::zome::ZomeName;
+use holochain_zome_types::ExternInput;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// Ask a zome for the keys to index an entry under.
+/// The callback is run with the read only access of validation,
+/// see [ValidateHostAccess](super::validate::ValidateHostAccess).
+#[derive(Clone)]
+pub struct IndexHintInvocation {
+    pub zome_name: ZomeName,
+    /// The id of the entry's def, which names the callback
+    pub entry_def_id: String,
+    // Arc here as entries may be large
+    pub entry: Arc<Entry>,
+}
+
+impl Invocation for IndexHintInvocation {
+    fn zomes(&self) -> ZomesToInvoke {
+        // Entries are specific to zomes so only the entry's zome can index it
+        ZomesToInvoke::One(self.zome_name.clone())
+    }
+    fn fn_components(&self) -> FnComponents {
+        vec!["index_hint".into(), self.entry_def_id.clone()].into()
+    }
+    fn host_input(self) -> Result<ExternInput, SerializedBytesError> {
+        Ok(ExternInput::new((&*self.entry).try_into()?))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum IndexHintResult {
+    /// The keys from every callback, without duplicates
+    Keys(BTreeSet<Vec<u8>>),
+    Fail(String),
+}
+
+impl From<Vec<(ZomeName, IndexHintCallbackResult)>> for IndexHintResult {
+    fn from(a: Vec<(ZomeName, IndexHintCallbackResult)>) -> Self {
+        a.into_iter().map(|(_, v)| v).collect::<Vec<_>>().into()
+    }
+}
+
+impl From<Vec<IndexHintCallbackResult>> for IndexHintResult {
+    fn from(callback_results: Vec<IndexHintCallbackResult>) -> Self {
+        // this is an optional callback so defaults to no keys
+        callback_results
+            .into_iter()
+            .fold(Self::Keys(BTreeSet::new()), |acc, x| match (acc, x) {
+                // fail overrides everything
+                (_, IndexHintCallbackResult::Fail(reason)) => Self::Fail(reason),
+                (acc @ Self::Fail(_), _) => acc,
+                // keys from every callback are indexed
+                (Self::Keys(mut keys), IndexHintCallbackResult::Keys(more)) => {
+                    keys.extend(more.into_iter().map(|key| key.into_vec()));
+                    Self::Keys(keys)
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::IndexHintInvocation;
+    use super::IndexHintResult;
+    use crate::core::ribosome::Invocation;
+    use crate::core::ribosome::ZomesToInvoke;
+    use holochain_zome_types::bytes::Bytes;
+    use holochain_zome_types::entry::Entry;
+    use holochain_zome_types::index_hint::IndexHintCallbackResult;
+    use holochain_zome_types::zome::ZomeName;
+    use std::sync::Arc;
+
+    #[test]
+    fn index_hint_callback_result_fold() {
+        let keys = |ks: &[&[u8]]| {
+            IndexHintCallbackResult::Keys(ks.iter().map(|k| Bytes::from(k.to_vec())).collect())
+        };
+        let fail = || IndexHintCallbackResult::Fail("".into());
+        let result_keys =
+            |ks: &[&[u8]]| IndexHintResult::Keys(ks.iter().map(|k| k.to_vec()).collect());
+
+        for (results, expected) in vec![
+            (vec![], result_keys(&[])),
+            (vec![keys(&[b"a"])], result_keys(&[b"a"])),
+            (
+                vec![keys(&[b"b", b"a"]), keys(&[b"a"])],
+                result_keys(&[b"a", b"b"]),
+            ),
+            (vec![fail()], IndexHintResult::Fail("".into())),
+            (
+                vec![keys(&[b"a"]), fail()],
+                IndexHintResult::Fail("".into()),
+            ),
+            (
+                vec![fail(), keys(&[b"a"])],
+                IndexHintResult::Fail("".into()),
+            ),
+        ] {
+            assert_eq!(expected, IndexHintResult::from(results));
+        }
+    }
+
+    #[test]
+    fn index_hint_invocation_is_per_entry_def() {
+        let zome_name = ZomeName::from("posts");
+        let agent = holo_hash::AgentPubKey::from_raw_bytes(vec![0; 36]);
+        let invocation = IndexHintInvocation {
+            zome_name: zome_name.clone(),
+            entry_def_id: "post".into(),
+            entry: Arc::new(Entry::Agent(agent)),
+        };
+        assert_eq!(ZomesToInvoke::One(zome_name), invocation.zomes());
+        let mut expected = vec!["index_hint", "index_hint_post"];
+        for fn_component in invocation.fn_components() {
+            assert_eq!(fn_component, expected.pop().unwrap());
+        }
+        assert!(expected.is_empty());
+    }
+}
@@ -294,9 +294,9 @@ mod slow_tests {
     use crate::fixt::*;
     use ::fixt::prelude::*;
     use holo_hash::fixt::AgentPubKeyFixturator;
+    use holo_hash::HeaderHash;
     use holochain_types::fixt::*;
     use holochain_wasm_test_utils::TestWasm;
-    use holochain_zome_types::CreateOutput;
     use holochain_zome_types::Entry;
     use std::sync::Arc;
 
@@ -390,7 +390,7 @@ mod slow_tests {
         let mut host_access = fixt!(ZomeCallHostAccess);
         host_access.workspace = workspace_lock.clone();
 
-        let output: CreateOutput =
+        let output: HeaderHash =
             crate::call_test_ribosome!(host_access, TestWasm::Validate, "always_validates", ());
 
         // the chain head should be the committed entry header
@@ -406,7 +406,7 @@ mod slow_tests {
         })
         .unwrap();
 
-        assert_eq!(chain_head, output.into_inner(),);
+        assert_eq!(chain_head, output,);
     }
 
     #[tokio::test(threaded_scheduler)]
@@ -426,7 +426,7 @@ mod slow_tests {
         let mut host_access = fixt!(ZomeCallHostAccess);
         host_access.workspace = workspace_lock.clone();
 
-        let output: CreateOutput =
+        let output: HeaderHash =
             crate::call_test_ribosome!(host_access, TestWasm::Validate, "never_validates", ());
 
         // the chain head should be the committed entry header
@@ -442,6 +442,6 @@ mod slow_tests {
         })
         .unwrap();
 
-        assert_eq!(chain_head, output.into_inner(),);
+        assert_eq!(chain_head, output,);
     }
 }
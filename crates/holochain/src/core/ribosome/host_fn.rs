@@ -4,6 +4,8 @@ pub mod call_remote;
 pub mod capability_claims;
 pub mod capability_grants;
 pub mod capability_info;
+pub mod chain_head;
+pub mod commit_cap_claim;
 pub mod create;
 pub mod create_link;
 pub mod debug;
@@ -14,7 +16,9 @@ pub mod emit_signal;
 pub mod encrypt;
 pub mod entry_type_properties;
 pub mod get;
+pub mod get_all_live_headers;
 pub mod get_details;
+pub mod get_indexed;
 pub mod get_link_details;
 pub mod get_links;
 pub mod hash_entry;
@@ -1,7 +1,10 @@
+use crate::core::ribosome::error::RibosomeError;
 use crate::core::ribosome::error::RibosomeResult;
 use crate::core::ribosome::CallContext;
 use crate::core::ribosome::RibosomeT;
+use crate::core::workflow::call_zome_workflow::CallZomeWorkspace;
 use holochain_p2p::HolochainP2pCellT;
+use holochain_zome_types::call_remote::CallRemoteCap;
 use holochain_zome_types::CallRemoteInput;
 use holochain_zome_types::CallRemoteOutput;
 use holochain_zome_types::ZomeCallResponse;
@@ -15,17 +18,40 @@ pub fn call_remote(
 ) -> RibosomeResult<CallRemoteOutput> {
     // it is the network's responsibility to handle timeouts and return an Err result in that case
     let result: ZomeCallResponse = tokio_safe_block_on::tokio_safe_block_forever_on(async move {
-        let mut network = call_context.host_access().network().clone();
         let call_remote = input.into_inner();
-        network
-            .call_remote(
-                call_remote.to_agent(),
-                call_remote.zome_name(),
-                call_remote.fn_name(),
-                call_remote.cap(),
-                call_remote.request(),
-            )
-            .await
+        let cap = match call_remote.cap() {
+            CallRemoteCap::None => None,
+            CallRemoteCap::Secret(secret) => Some(secret),
+            // look up the secret from the claims on the caller's own chain
+            CallRemoteCap::Auto => {
+                let function = (call_remote.zome_name(), call_remote.fn_name());
+                let guard = call_context.host_access().workspace().read().await;
+                let workspace: &CallZomeWorkspace = &guard;
+                let claim = workspace
+                    .source_chain
+                    .latest_cap_claim(&call_remote.to_agent(), &function)?
+                    .ok_or_else(|| {
+                        RibosomeError::NoClaimFound(
+                            call_remote.to_agent(),
+                            call_remote.zome_name(),
+                            call_remote.fn_name(),
+                        )
+                    })?;
+                Some(*claim.secret())
+            }
+        };
+        let mut network = call_context.host_access().network().clone();
+        RibosomeResult::Ok(
+            network
+                .call_remote(
+                    call_remote.to_agent(),
+                    call_remote.zome_name(),
+                    call_remote.fn_name(),
+                    cap,
+                    call_remote.request(),
+                )
+                .await?,
+        )
     })?
     .try_into()?;
 
@@ -37,14 +63,21 @@ pub fn call_remote(
 pub mod wasm_test {
 
     use crate::conductor::dna_store::MockDnaStore;
+    use crate::conductor::handle::CallRemoteOptions;
     use crate::conductor::interface::websocket::test::setup_app;
+    use crate::conductor::ConductorHandle;
+    use crate::core::ribosome::error::RibosomeError;
+    use crate::core::ribosome::error::RibosomeResult;
     use crate::core::ribosome::ZomeCallInvocation;
     use crate::core::ribosome::ZomeCallResponse;
+    use ::fixt::prelude::*;
     use hdk3::prelude::*;
+    use holochain_state::env::Durability;
     use holochain_types::app::InstalledCell;
     use holochain_types::cell::CellId;
     use holochain_types::dna::DnaDef;
     use holochain_types::dna::DnaFile;
+    use holochain_types::fixt::CapSecretFixturator;
     use holochain_types::test_utils::fake_agent_pubkey_1;
     use holochain_types::test_utils::fake_agent_pubkey_2;
     use holochain_wasm_test_utils::TestWasm;
@@ -132,6 +165,7 @@ pub mod wasm_test {
                 fn_name: "set_access".into(),
                 payload: ExternInput::new(().try_into().unwrap()),
                 provenance: bob_agent_id.clone(),
+                durability: Durability::Sync,
             })
             .await
             .unwrap();
@@ -140,12 +174,13 @@ pub mod wasm_test {
 
         let output = handle
             .call_zome(ZomeCallInvocation {
-                cell_id: alice_cell_id,
+                cell_id: alice_cell_id.clone(),
                 zome_name: TestWasm::WhoAmI.into(),
                 cap: None,
                 fn_name: "whoarethey".into(),
                 payload: ExternInput::new(bob_agent_id.clone().try_into().unwrap()),
                 provenance: alice_agent_id,
+                durability: Durability::Sync,
             })
             .await
             .unwrap()
@@ -159,13 +194,256 @@ pub mod wasm_test {
                     agent_info,
                     AgentInfo {
                         agent_initial_pubkey: bob_agent_id.clone(),
-                        agent_latest_pubkey: bob_agent_id,
+                        agent_latest_pubkey: bob_agent_id.clone(),
                     },
                 );
             }
             _ => unreachable!(),
         }
 
+        // ALICE CALLING BOB FROM OUTSIDE ANY ZOME
+
+        let response: ZomeCallResponse = handle
+            .call_remote(
+                &alice_cell_id,
+                bob_agent_id.clone(),
+                TestWasm::WhoAmI.into(),
+                "whoami".into(),
+                None,
+                ().try_into().unwrap(),
+                CallRemoteOptions::default(),
+            )
+            .await
+            .unwrap()
+            .try_into()
+            .unwrap();
+        match response {
+            ZomeCallResponse::Ok(guest_output) => {
+                let response: SerializedBytes = guest_output.into_inner();
+                let agent_info: AgentInfo = response.try_into().unwrap();
+                assert_eq!(agent_info.agent_latest_pubkey, bob_agent_id);
+            }
+            _ => unreachable!(),
+        }
+
+        let shutdown = handle.take_shutdown_handle().await.unwrap();
+        handle.shutdown().await;
+        shutdown.await.unwrap();
+    }
+
+    /// Bob calls needs_cap_claim on alice with the secret from his claims
+    async fn try_auto_cap_claim(
+        handle: &ConductorHandle,
+        bob_cell_id: &CellId,
+        alice_agent_id: &AgentPubKey,
+    ) -> RibosomeResult<ZomeCallResponse> {
+        let output = handle
+            .call_zome(ZomeCallInvocation {
+                cell_id: bob_cell_id.clone(),
+                zome_name: TestWasm::Capability.into(),
+                cap: None,
+                fn_name: "try_auto_cap_claim".into(),
+                payload: ExternInput::new(alice_agent_id.clone().try_into().unwrap()),
+                provenance: bob_cell_id.agent_pubkey().clone(),
+                durability: Durability::Sync,
+            })
+            .await
+            .unwrap()?;
+        // the _outer_ invocation response is to try_auto_cap_claim for bob
+        // the _inner_ invocation response is needs_cap_claim on alice
+        match output {
+            ZomeCallResponse::Ok(guest_output) => {
+                let response: SerializedBytes = guest_output.into_inner();
+                Ok(response.try_into().unwrap())
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    /// call_remote can find the secret in a committed claim
+    async fn call_remote_auto_cap_claim_test() {
+        let dna_def = DnaDef {
+            name: "call_remote_auto_cap_claim_test".to_string(),
+            uuid: "6f4e7fd5-3f2c-4e2a-9d3e-2a8f0b7c4d11".to_string(),
+            properties: SerializedBytes::try_from(()).unwrap(),
+            zomes: vec![TestWasm::Capability.into()].into(),
+        };
+        let dna_file = DnaFile::new(dna_def, vec![TestWasm::Capability.into()])
+            .await
+            .unwrap();
+
+        let alice_agent_id = fake_agent_pubkey_1();
+        let alice_cell_id = CellId::new(dna_file.dna_hash().to_owned(), alice_agent_id.clone());
+        let alice_installed_cell = InstalledCell::new(alice_cell_id.clone(), "alice_handle".into());
+
+        let bob_agent_id = fake_agent_pubkey_2();
+        let bob_cell_id = CellId::new(dna_file.dna_hash().to_owned(), bob_agent_id.clone());
+        let bob_installed_cell = InstalledCell::new(bob_cell_id.clone(), "bob_handle".into());
+
+        let mut dna_store = MockDnaStore::new();
+
+        dna_store.expect_get().return_const(Some(dna_file.clone()));
+        dna_store
+            .expect_add_dnas::<Vec<_>>()
+            .times(2)
+            .return_const(());
+        dna_store
+            .expect_add_entry_defs::<Vec<_>>()
+            .times(2)
+            .return_const(());
+
+        let (_tmpdir, _app_api, handle) = setup_app(
+            vec![(alice_installed_cell, None), (bob_installed_cell, None)],
+            dna_store,
+        )
+        .await;
+
+        let mut functions: GrantedFunctions = HashSet::new();
+        functions.insert((TestWasm::Capability.into(), "needs_cap_claim".into()));
+
+        // BOB HAS NO CLAIM YET
+
+        let result = try_auto_cap_claim(&handle, &bob_cell_id, &alice_agent_id).await;
+        match result {
+            Err(RibosomeError::WasmError(WasmError::Zome(error))) => {
+                assert!(error.contains("NoClaimFound"), "{}", error)
+            }
+            other => panic!("expected NoClaimFound, got {:?}", other),
+        }
+
+        // ALICE GRANTS BOB AND BOB COMMITS THE CLAIM
+
+        let original_secret = CapSecretFixturator::new(Unpredictable).next().unwrap();
+        let output = handle
+            .call_zome(ZomeCallInvocation {
+                cell_id: alice_cell_id.clone(),
+                zome_name: TestWasm::Capability.into(),
+                cap: None,
+                fn_name: "transferable_cap_grant".into(),
+                payload: ExternInput::new(original_secret.try_into().unwrap()),
+                provenance: alice_agent_id.clone(),
+                durability: Durability::Sync,
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        let original_grant_hash: HeaderHash = match output {
+            ZomeCallResponse::Ok(guest_output) => guest_output.into_inner().try_into().unwrap(),
+            _ => unreachable!(),
+        };
+
+        let claim = CapClaim::new(
+            "alice".into(),
+            alice_agent_id.clone(),
+            original_secret,
+            functions.clone(),
+        );
+        handle
+            .call_zome(ZomeCallInvocation {
+                cell_id: bob_cell_id.clone(),
+                zome_name: TestWasm::Capability.into(),
+                cap: None,
+                fn_name: "commit_claim".into(),
+                payload: ExternInput::new(claim.try_into().unwrap()),
+                provenance: bob_agent_id.clone(),
+                durability: Durability::Sync,
+            })
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            try_auto_cap_claim(&handle, &bob_cell_id, &alice_agent_id)
+                .await
+                .unwrap(),
+            ZomeCallResponse::Ok(ExternOutput::new(().try_into().unwrap())),
+        );
+
+        // ALICE ROTATES THE GRANT SO THE ORIGINAL CLAIM NO LONGER WORKS
+
+        let output = handle
+            .call_zome(ZomeCallInvocation {
+                cell_id: alice_cell_id.clone(),
+                zome_name: TestWasm::Capability.into(),
+                cap: None,
+                fn_name: "roll_cap_grant".into(),
+                payload: ExternInput::new(original_grant_hash.try_into().unwrap()),
+                provenance: alice_agent_id.clone(),
+                durability: Durability::Sync,
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        let new_grant_hash: HeaderHash = match output {
+            ZomeCallResponse::Ok(guest_output) => guest_output.into_inner().try_into().unwrap(),
+            _ => unreachable!(),
+        };
+
+        assert_eq!(
+            try_auto_cap_claim(&handle, &bob_cell_id, &alice_agent_id)
+                .await
+                .unwrap(),
+            ZomeCallResponse::Unauthorized,
+        );
+
+        let output = handle
+            .call_zome(ZomeCallInvocation {
+                cell_id: alice_cell_id,
+                zome_name: TestWasm::Capability.into(),
+                cap: None,
+                fn_name: "get_entry".into(),
+                payload: ExternInput::new(new_grant_hash.try_into().unwrap()),
+                provenance: alice_agent_id.clone(),
+                durability: Durability::Sync,
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        let new_secret: CapSecret = match output {
+            ZomeCallResponse::Ok(guest_output) => {
+                let get_output: GetOutput = guest_output.into_inner().try_into().unwrap();
+                match get_output
+                    .into_inner()
+                    .and_then(|element| element.entry().to_grant_option())
+                    .map(|grant| grant.access)
+                {
+                    Some(CapAccess::Transferable { secret, .. }) => secret,
+                    _ => unreachable!(),
+                }
+            }
+            _ => unreachable!(),
+        };
+
+        // BOB STORES THE NEW CLAIM UNDER THE SAME TAG AND AUTO PICKS IT
+
+        let claim = CapClaim::new(
+            "alice".into(),
+            alice_agent_id.clone(),
+            new_secret,
+            functions,
+        );
+        handle
+            .call_zome(ZomeCallInvocation {
+                cell_id: bob_cell_id.clone(),
+                zome_name: TestWasm::Capability.into(),
+                cap: None,
+                fn_name: "commit_claim".into(),
+                payload: ExternInput::new(claim.try_into().unwrap()),
+                provenance: bob_agent_id,
+                durability: Durability::Sync,
+            })
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            try_auto_cap_claim(&handle, &bob_cell_id, &alice_agent_id)
+                .await
+                .unwrap(),
+            ZomeCallResponse::Ok(ExternOutput::new(().try_into().unwrap())),
+        );
+
         let shutdown = handle.take_shutdown_handle().await.unwrap();
         handle.shutdown().await;
         shutdown.await.unwrap();
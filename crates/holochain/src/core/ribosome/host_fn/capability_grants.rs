@@ -26,6 +26,7 @@ pub mod wasm_test {
     use crate::fixt::ZomeCallHostAccessFixturator;
     use ::fixt::prelude::*;
     use hdk3::prelude::*;
+    use holochain_state::env::Durability;
     use holochain_types::app::InstalledCell;
     use holochain_types::cell::CellId;
     use holochain_types::dna::DnaDef;
@@ -184,6 +185,7 @@ pub mod wasm_test {
                         .unwrap(),
                 ),
                 provenance: alice_agent_id.clone(),
+                durability: Durability::Sync,
             })
             .await
             .unwrap()
@@ -211,6 +213,7 @@ pub mod wasm_test {
                 fn_name: "transferable_cap_grant".into(),
                 payload: ExternInput::new(original_secret.try_into().unwrap()),
                 provenance: bob_agent_id.clone(),
+                durability: Durability::Sync,
             })
             .await
             .unwrap()
@@ -235,6 +238,7 @@ pub mod wasm_test {
                         .unwrap(),
                 ),
                 provenance: alice_agent_id.clone(),
+                durability: Durability::Sync,
             })
             .await
             .unwrap()
@@ -265,6 +269,7 @@ pub mod wasm_test {
                 fn_name: "roll_cap_grant".into(),
                 payload: ExternInput::new(original_grant_hash.try_into().unwrap()),
                 provenance: bob_agent_id.clone(),
+                durability: Durability::Sync,
             })
             .await
             .unwrap()
@@ -283,6 +288,7 @@ pub mod wasm_test {
                 fn_name: "get_entry".into(),
                 payload: ExternInput::new(new_grant_header_hash.clone().try_into().unwrap()),
                 provenance: bob_agent_id.clone(),
+                durability: Durability::Sync,
             })
             .await
             .unwrap()
@@ -317,6 +323,7 @@ pub mod wasm_test {
                         .unwrap(),
                 ),
                 provenance: alice_agent_id.clone(),
+                durability: Durability::Sync,
             })
             .await
             .unwrap()
@@ -346,6 +353,7 @@ pub mod wasm_test {
                         .unwrap(),
                 ),
                 provenance: alice_agent_id.clone(),
+                durability: Durability::Sync,
             })
             .await
             .unwrap()
@@ -376,6 +384,7 @@ pub mod wasm_test {
                 fn_name: "delete_cap_grant".into(),
                 payload: ExternInput::new(new_grant_header_hash.try_into().unwrap()),
                 provenance: bob_agent_id.clone(),
+                durability: Durability::Sync,
             })
             .await
             .unwrap()
@@ -393,6 +402,7 @@ pub mod wasm_test {
                         .unwrap(),
                 ),
                 provenance: alice_agent_id.clone(),
+                durability: Durability::Sync,
             })
             .await
             .unwrap()
@@ -422,6 +432,7 @@ pub mod wasm_test {
                         .unwrap(),
                 ),
                 provenance: alice_agent_id.clone(),
+                durability: Durability::Sync,
             })
             .await
             .unwrap()
@@ -0,0 +1,77 @@
+use crate::core::ribosome::error::RibosomeResult;
+use crate::core::ribosome::CallContext;
+use crate::core::ribosome::RibosomeT;
+use crate::core::SourceChainError;
+use holochain_zome_types::ChainHeadInput;
+use holochain_zome_types::ChainHeadOutput;
+use std::sync::Arc;
+
+/// the head of the source chain, as seen by this zome call
+pub fn chain_head(
+    _ribosome: Arc<impl RibosomeT>,
+    call_context: Arc<CallContext>,
+    _input: ChainHeadInput,
+) -> RibosomeResult<ChainHeadOutput> {
+    tokio_safe_block_on::tokio_safe_block_forever_on(async move {
+        let guard = call_context.host_access.workspace().read().await;
+        let source_chain = &guard.source_chain;
+        // the scratch space is read first, so this includes commits earlier in the call
+        let head = source_chain.chain_head()?.clone();
+        let header = source_chain
+            .get_header(&head)?
+            .ok_or(SourceChainError::MissingHead)?;
+        let header = header.header();
+        Ok(ChainHeadOutput::new((
+            head,
+            header.header_seq(),
+            header.timestamp(),
+        )))
+    })
+}
+
+#[cfg(test)]
+#[cfg(feature = "slow_tests")]
+pub mod wasm_test {
+    use crate::core::workflow::CallZomeWorkspace;
+    use crate::fixt::ZomeCallHostAccessFixturator;
+    use ::fixt::prelude::*;
+    use hdk3::prelude::*;
+    use holochain_wasm_test_utils::TestWasm;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn chain_head_includes_this_call() {
+        let test_env = holochain_state::test_utils::test_cell_env();
+        let env = test_env.env();
+        let mut workspace = CallZomeWorkspace::new(env.clone().into()).unwrap();
+        crate::core::workflow::fake_genesis(&mut workspace.source_chain)
+            .await
+            .unwrap();
+        let workspace_lock = crate::core::workflow::CallZomeWorkspaceLock::new(workspace);
+        let mut host_access = fixt!(ZomeCallHostAccess);
+        host_access.workspace = workspace_lock.clone();
+
+        let output: ChainHeadOutput =
+            crate::call_test_ribosome!(host_access, TestWasm::Create, "get_chain_head", ());
+        let (head, seq, _) = output.into_inner();
+        // genesis is three headers
+        assert_eq!(seq, 2);
+
+        let created: HeaderHash =
+            crate::call_test_ribosome!(host_access, TestWasm::Create, "create_entry", ());
+        let output: ChainHeadOutput =
+            crate::call_test_ribosome!(host_access, TestWasm::Create, "get_chain_head", ());
+        let (new_head, new_seq, _) = output.into_inner();
+        assert_ne!(new_head, head);
+        assert_eq!(new_head, created);
+        assert_eq!(new_seq, 3);
+        assert_eq!(
+            workspace_lock
+                .read()
+                .await
+                .source_chain
+                .chain_head()
+                .unwrap(),
+            &created
+        );
+    }
+}
@@ -0,0 +1,25 @@
+use crate::core::ribosome::error::RibosomeResult;
+use crate::core::ribosome::host_fn::create::{create, unguarded};
+use crate::core::ribosome::CallContext;
+use crate::core::ribosome::RibosomeT;
+use holochain_zome_types::entry::Entry;
+use holochain_zome_types::entry_def::EntryDefId;
+use holochain_zome_types::CommitCapClaimInput;
+use holochain_zome_types::CommitCapClaimOutput;
+use holochain_zome_types::CreateInput;
+use std::sync::Arc;
+
+/// commit a cap claim as a private entry through the normal create path
+pub fn commit_cap_claim(
+    ribosome: Arc<impl RibosomeT>,
+    call_context: Arc<CallContext>,
+    input: CommitCapClaimInput,
+) -> RibosomeResult<CommitCapClaimOutput> {
+    let claim = input.into_inner();
+    let output = create(
+        ribosome,
+        call_context,
+        CreateInput::new((EntryDefId::CapClaim, Entry::CapClaim(claim), None)),
+    )?;
+    Ok(CommitCapClaimOutput::new(unguarded(output.into_inner())?))
+}
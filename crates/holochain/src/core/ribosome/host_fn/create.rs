@@ -5,12 +5,15 @@ use crate::core::ribosome::guest_callback::entry_defs::EntryDefsResult;
 use crate::core::ribosome::CallContext;
 use crate::core::ribosome::RibosomeT;
 use crate::core::{
+    state::source_chain::SourceChain,
     workflow::{
         call_zome_workflow::CallZomeWorkspace, integrate_dht_ops_workflow::integrate_to_authored,
     },
-    SourceChainError,
+    SourceChainError, SourceChainResult,
 };
-use holo_hash::HasHash;
+use holo_hash::{HasHash, HeaderHash};
+use holochain_zome_types::commit_guard::{CommitGuard, HeadMoved};
+use holochain_zome_types::entry::Entry;
 use holochain_zome_types::entry_def::{EntryDefId, EntryVisibility};
 use holochain_zome_types::header::builder;
 use holochain_zome_types::header::AppEntryType;
@@ -26,9 +29,55 @@ pub fn create<'a>(
     call_context: Arc<CallContext>,
     input: CreateInput,
 ) -> RibosomeResult<CreateOutput> {
-    // destructure the args out into an app type def id and entry
-    let (entry_def_id, entry) = input.into_inner();
+    // destructure the args out into an app type def id, entry and commit guard
+    let (entry_def_id, entry, commit_guard) = input.into_inner();
+    Ok(CreateOutput::new(create_element(
+        ribosome,
+        call_context,
+        entry_def_id,
+        entry,
+        commit_guard,
+    )?))
+}
 
+/// Check a commit's guard against the chain head, including anything
+/// committed earlier in this call. The workspace must stay locked from
+/// the check until the put, so that nothing can move the head in between.
+pub fn check_commit_guard(
+    source_chain: &SourceChain,
+    commit_guard: Option<CommitGuard>,
+) -> SourceChainResult<Result<(), HeadMoved>> {
+    if let Some(CommitGuard { expected_head }) = commit_guard {
+        let actual = source_chain.chain_head()?;
+        if actual != &expected_head {
+            return Ok(Err(HeadMoved {
+                expected: expected_head,
+                actual: actual.clone(),
+            }));
+        }
+    }
+    Ok(Ok(()))
+}
+
+/// The result of a commit made without a guard. The head can't be found
+/// to have moved without a guard, but if it somehow is the commit fails
+/// as it would have when flushed.
+pub fn unguarded<T>(result: Result<T, HeadMoved>) -> RibosomeResult<T> {
+    result.map_err(|HeadMoved { expected, actual }| {
+        SourceChainError::HeadMoved(Some(expected), Some(actual)).into()
+    })
+}
+
+/// Commit an element for an entry, shared by the create host functions.
+/// With a guard, nothing is committed if the chain head, including
+/// anything committed earlier in this call, isn't the expected one.
+pub fn create_element(
+    ribosome: Arc<impl RibosomeT>,
+    call_context: Arc<CallContext>,
+    entry_def_id: EntryDefId,
+    entry: Entry,
+    commit_guard: Option<CommitGuard>,
+) -> RibosomeResult<Result<HeaderHash, HeadMoved>> {
     // build the entry hash
     let async_entry = entry.clone();
     let entry_hash =
@@ -65,6 +114,9 @@ pub fn create<'a>(
         let mut guard = host_access.workspace().write().await;
         let workspace: &mut CallZomeWorkspace = &mut guard;
         let source_chain = &mut workspace.source_chain;
+        if let Err(head_moved) = check_commit_guard(source_chain, commit_guard)? {
+            return Ok(Err(head_moved));
+        }
         // push the header and the entry into the source chain
         let header_hash = source_chain.put(header_builder, Some(entry)).await?;
         // fetch the element we just added so we can integrate its DhtOps
@@ -79,7 +131,8 @@ pub fn create<'a>(
         .await
         .map_err(Box::new)
         .map_err(SourceChainError::from)?;
-        Ok(CreateOutput::new(header_hash))
+        workspace.check_scratch_size()?;
+        Ok(Ok(header_hash))
     })
 }
 
@@ -124,6 +177,10 @@ pub mod wasm_test {
     use super::create;
     use crate::conductor::dna_store::MockDnaStore;
     use crate::core::ribosome::error::RibosomeError;
+    use crate::core::ribosome::host_fn::create_link::create_link;
+    use crate::core::ribosome::host_fn::delete::delete;
+    use crate::core::ribosome::host_fn::delete_link::delete_link;
+    use crate::core::ribosome::host_fn::update::update;
     use crate::core::ribosome::ZomeCallInvocation;
     use crate::core::state::source_chain::ChainInvalidReason;
     use crate::core::state::source_chain::SourceChainError;
@@ -137,16 +194,22 @@ pub mod wasm_test {
     use ::fixt::prelude::*;
     use hdk3::prelude::*;
     use holo_hash::{AnyDhtHash, EntryHash};
+    use holochain_state::env::Durability;
     use holochain_types::{
         app::InstalledCell, cell::CellId, dna::DnaDef, dna::DnaFile, fixt::AppEntry, observability,
         test_utils::fake_agent_pubkey_1, test_utils::fake_agent_pubkey_2,
     };
     use holochain_wasm_test_utils::TestWasm;
+    use holochain_zome_types::commit_guard::{CommitGuard, HeadMoved};
     use holochain_zome_types::entry_def::EntryDefId;
     use holochain_zome_types::CreateInput;
+    use holochain_zome_types::CreateLinkInput;
     use holochain_zome_types::CreateOutput;
+    use holochain_zome_types::DeleteInput;
+    use holochain_zome_types::DeleteLinkInput;
     use holochain_zome_types::Entry;
     use holochain_zome_types::GetOutput;
+    use holochain_zome_types::UpdateInput;
     use holochain_zome_types::{entry::EntryError, ExternInput};
     use std::sync::Arc;
     use test_wasm_common::TestBytes;
@@ -173,7 +236,7 @@ pub mod wasm_test {
         call_context.host_access = host_access.into();
         let app_entry = EntryFixturator::new(AppEntry).next().unwrap();
         let entry_def_id = EntryDefId::App("post".into());
-        let input = CreateInput::new((entry_def_id, app_entry.clone()));
+        let input = CreateInput::new((entry_def_id, app_entry.clone(), None));
 
         let output = create(Arc::new(ribosome), Arc::new(call_context), input);
 
@@ -214,7 +277,7 @@ pub mod wasm_test {
         call_context.host_access = host_access.into();
         let app_entry = EntryFixturator::new(AppEntry).next().unwrap();
         let entry_def_id = EntryDefId::App("post".into());
-        let input = CreateInput::new((entry_def_id, app_entry.clone()));
+        let input = CreateInput::new((entry_def_id, app_entry.clone(), None));
 
         let output = create(Arc::new(ribosome), Arc::new(call_context), input).unwrap();
 
@@ -231,7 +294,150 @@ pub mod wasm_test {
         })
         .unwrap();
 
-        assert_eq!(chain_head, output.into_inner(),);
+        assert_eq!(chain_head, output.into_inner().unwrap(),);
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    /// every kind of commit is refused if anything was committed after the head it is guarded
+    /// on, and committed if it is guarded on the current head
+    async fn guarded_commits_need_the_expected_head() {
+        let test_env = holochain_state::test_utils::test_cell_env();
+        let env = test_env.env();
+        let mut workspace = CallZomeWorkspace::new(env.clone().into()).unwrap();
+        crate::core::workflow::fake_genesis(&mut workspace.source_chain)
+            .await
+            .unwrap();
+        let workspace_lock = crate::core::workflow::CallZomeWorkspaceLock::new(workspace);
+
+        let ribosome = Arc::new(
+            WasmRibosomeFixturator::new(crate::fixt::curve::Zomes(vec![TestWasm::Create]))
+                .next()
+                .unwrap(),
+        );
+        let (_network, _r, cell_network) =
+            crate::test_utils::test_network(Some(ribosome.dna_file().dna_hash().clone()), None)
+                .await;
+        let mut call_context = CallContextFixturator::new(Unpredictable).next().unwrap();
+        call_context.zome_name = TestWasm::Create.into();
+        let mut host_access = fixt!(ZomeCallHostAccess);
+        host_access.workspace = workspace_lock.clone();
+        host_access.network = cell_network;
+        call_context.host_access = host_access.clone().into();
+        let call_context = Arc::new(call_context);
+
+        let post = EntryFixturator::new(AppEntry).next().unwrap();
+        let post_hash = EntryHash::with_data_sync(&post);
+        // commit an op acting on the element at the subject header
+        let commit = |op: &str, subject: HeaderHash, guard: Option<CommitGuard>| {
+            let post_def = EntryDefId::App("post".into());
+            let (ribosome, call_context) = (ribosome.clone(), call_context.clone());
+            match op {
+                "create" => create(
+                    ribosome,
+                    call_context,
+                    CreateInput::new((post_def, post.clone(), guard)),
+                )
+                .unwrap()
+                .into_inner(),
+                "update" => update(
+                    ribosome,
+                    call_context,
+                    UpdateInput::new((post_def, post.clone(), subject, guard)),
+                )
+                .unwrap()
+                .into_inner(),
+                "delete" => delete(ribosome, call_context, DeleteInput::new((subject, guard)))
+                    .unwrap()
+                    .into_inner(),
+                "create_link" => create_link(
+                    ribosome,
+                    call_context,
+                    CreateLinkInput::new((
+                        post_hash.clone(),
+                        post_hash.clone(),
+                        LinkTag::new(vec![]),
+                        guard,
+                    )),
+                )
+                .unwrap()
+                .into_inner(),
+                "delete_link" => delete_link(
+                    ribosome,
+                    call_context,
+                    DeleteLinkInput::new((subject, guard)),
+                )
+                .unwrap()
+                .into_inner(),
+                _ => unreachable!(),
+            }
+        };
+        let chain_head = || {
+            let workspace_lock = workspace_lock.clone();
+            tokio_safe_block_on::tokio_safe_block_forever_on(async move {
+                workspace_lock
+                    .read()
+                    .await
+                    .source_chain
+                    .chain_head()
+                    .unwrap()
+                    .clone()
+            })
+        };
+
+        // each op, with the op which commits the element it acts on
+        let ops = vec![
+            ("create", "create"),
+            ("update", "create"),
+            ("delete", "create"),
+            ("create_link", "create"),
+            ("delete_link", "create_link"),
+        ];
+        for (op, subject_op) in ops {
+            // the zome reads the head, the element the op acts on
+            let read_head = commit(subject_op, chain_head(), None).unwrap();
+            // another call advances the chain
+            let moved_head = commit("create", read_head.clone(), None).unwrap();
+
+            // so an op guarded on the head that was read is refused
+            assert_eq!(
+                commit(
+                    op,
+                    read_head.clone(),
+                    Some(CommitGuard::new(read_head.clone()))
+                ),
+                Err(HeadMoved {
+                    expected: read_head,
+                    actual: moved_head.clone(),
+                }),
+                "{}",
+                op
+            );
+            // and nothing was committed
+            assert_eq!(chain_head(), moved_head, "{}", op);
+
+            // an op guarded on the current head is committed
+            let subject = commit(subject_op, moved_head, None).unwrap();
+            let committed = commit(op, subject.clone(), Some(CommitGuard::new(subject))).unwrap();
+            assert_eq!(chain_head(), committed, "{}", op);
+        }
+
+        // the hdk passes the guard to the host
+        let read_head = chain_head();
+        let moved_head: HeaderHash =
+            crate::call_test_ribosome!(host_access, TestWasm::Create, "create_entry", ());
+        let output: CreateOutput = crate::call_test_ribosome!(
+            host_access,
+            TestWasm::Create,
+            "create_entry_guarded",
+            read_head.clone()
+        );
+        assert_eq!(
+            output.into_inner(),
+            Err(HeadMoved {
+                expected: read_head,
+                actual: moved_head,
+            })
+        );
     }
 
     #[tokio::test(threaded_scheduler)]
@@ -251,7 +457,7 @@ pub mod wasm_test {
         host_access.workspace = workspace_lock.clone();
 
         // get the result of a commit entry
-        let output: CreateOutput =
+        let output: HeaderHash =
             crate::call_test_ribosome!(host_access, TestWasm::Create, "create_entry", ());
 
         // the chain head should be the committed entry header
@@ -267,7 +473,7 @@ pub mod wasm_test {
         })
         .unwrap();
 
-        assert_eq!(&chain_head, output.inner_ref());
+        assert_eq!(chain_head, output);
 
         let round: GetOutput =
             crate::call_test_ribosome!(host_access, TestWasm::Create, "get_entry", ());
@@ -361,6 +567,7 @@ pub mod wasm_test {
                 fn_name: "create_entry_multiple".into(),
                 payload: ExternInput::new(TestInt(n).try_into().unwrap()),
                 provenance: alice_agent_id.clone(),
+                durability: Durability::Sync,
             })
             .await
             .unwrap()
@@ -383,6 +590,7 @@ pub mod wasm_test {
                 fn_name: "get_entry_multiple".into(),
                 payload: ExternInput::new(TestInt(n).try_into().unwrap()),
                 provenance: alice_agent_id,
+                durability: Durability::Sync,
             })
             .await
             .unwrap()
@@ -1,3 +1,4 @@
+use super::create::check_commit_guard;
 use crate::core::ribosome::error::RibosomeResult;
 use crate::core::workflow::integrate_dht_ops_workflow::integrate_to_authored;
 use crate::core::{
@@ -5,7 +6,10 @@ use crate::core::{
     workflow::CallZomeWorkspace,
     SourceChainResult,
 };
+use holo_hash::{EntryHash, HeaderHash};
+use holochain_zome_types::commit_guard::{CommitGuard, HeadMoved};
 use holochain_zome_types::header::builder;
+use holochain_zome_types::link::LinkTag;
 use holochain_zome_types::CreateLinkInput;
 use holochain_zome_types::CreateLinkOutput;
 use std::sync::Arc;
@@ -16,8 +20,28 @@ pub fn create_link<'a>(
     call_context: Arc<CallContext>,
     input: CreateLinkInput,
 ) -> RibosomeResult<CreateLinkOutput> {
-    let (base_address, target_address, tag) = input.into_inner();
+    let (base_address, target_address, tag, commit_guard) = input.into_inner();
+    Ok(CreateLinkOutput::new(create_link_element(
+        ribosome,
+        call_context,
+        base_address,
+        target_address,
+        tag,
+        commit_guard,
+    )?))
+}
 
+/// Commit a link, shared by the create link host functions.
+/// With a guard, nothing is committed if the chain head, including
+/// anything committed earlier in this call, isn't the expected one.
+pub fn create_link_element(
+    ribosome: Arc<impl RibosomeT>,
+    call_context: Arc<CallContext>,
+    base_address: EntryHash,
+    target_address: EntryHash,
+    tag: LinkTag,
+    commit_guard: Option<CommitGuard>,
+) -> RibosomeResult<Result<HeaderHash, HeadMoved>> {
     // extract the zome position
     let zome_id = ribosome.zome_name_to_id(&call_context.zome_name)?;
 
@@ -28,6 +52,9 @@ pub fn create_link<'a>(
         tokio_safe_block_on::tokio_safe_block_forever_on(tokio::task::spawn(async move {
             let mut guard = call_context.host_access.workspace().write().await;
             let workspace: &mut CallZomeWorkspace = &mut guard;
+            if let Err(head_moved) = check_commit_guard(&workspace.source_chain, commit_guard)? {
+                return Ok(Err(head_moved));
+            }
             // push the header into the source chain
             let header_hash = workspace.source_chain.put(header_builder, None).await?;
             let element = workspace
@@ -41,14 +68,15 @@ pub fn create_link<'a>(
             )
             .await
             .map_err(Box::new)?;
-            SourceChainResult::Ok(header_hash)
+            workspace.check_scratch_size()?;
+            SourceChainResult::Ok(Ok(header_hash))
         }))??;
 
     // return the hash of the committed link
     // note that validation is handled by the workflow
     // if the validation fails this commit will be rolled back by virtue of the lmdb transaction
     // being atomic
-    Ok(CreateLinkOutput::new(header_hash))
+    Ok(header_hash)
 }
 
 // we rely on the tests for get_links and get_link_details
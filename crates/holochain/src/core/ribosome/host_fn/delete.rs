@@ -1,3 +1,4 @@
+use super::create::check_commit_guard;
 use crate::core::ribosome::error::{RibosomeError, RibosomeResult};
 use crate::core::ribosome::CallContext;
 use crate::core::ribosome::RibosomeT;
@@ -6,6 +7,7 @@ use crate::core::workflow::call_zome_workflow::CallZomeWorkspace;
 use crate::core::{workflow::integrate_dht_ops_workflow::integrate_to_authored, SourceChainError};
 use holo_hash::{EntryHash, HeaderHash};
 use holochain_p2p::actor::GetOptions;
+use holochain_zome_types::commit_guard::{CommitGuard, HeadMoved};
 use holochain_zome_types::header::builder;
 use holochain_zome_types::DeleteInput;
 use holochain_zome_types::{element::SignedHeaderHashed, DeleteOutput};
@@ -17,8 +19,22 @@ pub fn delete<'a>(
     call_context: Arc<CallContext>,
     input: DeleteInput,
 ) -> RibosomeResult<DeleteOutput> {
-    let deletes_address = input.into_inner();
+    let (deletes_address, commit_guard) = input.into_inner();
+    Ok(DeleteOutput::new(delete_element(
+        call_context,
+        deletes_address,
+        commit_guard,
+    )?))
+}
 
+/// Commit an element deleting another, shared by the delete host functions.
+/// With a guard, nothing is committed if the chain head, including
+/// anything committed earlier in this call, isn't the expected one.
+pub fn delete_element(
+    call_context: Arc<CallContext>,
+    deletes_address: HeaderHash,
+    commit_guard: Option<CommitGuard>,
+) -> RibosomeResult<Result<HeaderHash, HeadMoved>> {
     let deletes_entry_address =
         get_original_address(call_context.clone(), deletes_address.clone())?;
 
@@ -29,6 +45,9 @@ pub fn delete<'a>(
         let mut guard = host_access.workspace().write().await;
         let workspace: &mut CallZomeWorkspace = &mut guard;
         let source_chain = &mut workspace.source_chain;
+        if let Err(head_moved) = check_commit_guard(source_chain, commit_guard)? {
+            return Ok(Err(head_moved));
+        }
         let header_builder = builder::Delete {
             deletes_address,
             deletes_entry_address,
@@ -46,7 +65,8 @@ pub fn delete<'a>(
         .await
         .map_err(Box::new)
         .map_err(SourceChainError::from)?;
-        Ok(DeleteOutput::new(header_hash))
+        workspace.check_scratch_size()?;
+        Ok(Ok(header_hash))
     })
 }
 
@@ -1,3 +1,4 @@
+use super::create::check_commit_guard;
 use crate::core::ribosome::error::RibosomeError;
 use crate::core::ribosome::error::RibosomeResult;
 use crate::core::ribosome::CallContext;
@@ -5,8 +6,10 @@ use crate::core::ribosome::RibosomeT;
 use crate::core::state::cascade::error::CascadeResult;
 use crate::core::workflow::call_zome_workflow::CallZomeWorkspace;
 use crate::core::{workflow::integrate_dht_ops_workflow::integrate_to_authored, SourceChainError};
+use holo_hash::HeaderHash;
 use holochain_p2p::actor::GetOptions;
 use holochain_types::element::SignedHeaderHashed;
+use holochain_zome_types::commit_guard::{CommitGuard, HeadMoved};
 use holochain_zome_types::header::builder;
 use holochain_zome_types::DeleteLinkInput;
 use holochain_zome_types::DeleteLinkOutput;
@@ -19,8 +22,22 @@ pub fn delete_link<'a>(
     call_context: Arc<CallContext>,
     input: DeleteLinkInput,
 ) -> RibosomeResult<DeleteLinkOutput> {
-    let link_add_address = input.into_inner();
+    let (link_add_address, commit_guard) = input.into_inner();
+    Ok(DeleteLinkOutput::new(delete_link_element(
+        call_context,
+        link_add_address,
+        commit_guard,
+    )?))
+}
 
+/// Commit the removal of a link, shared by the delete link host functions.
+/// With a guard, nothing is committed if the chain head, including
+/// anything committed earlier in this call, isn't the expected one.
+pub fn delete_link_element(
+    call_context: Arc<CallContext>,
+    link_add_address: HeaderHash,
+    commit_guard: Option<CommitGuard>,
+) -> RibosomeResult<Result<HeaderHash, HeadMoved>> {
     // get the base address from the add link header
     // don't allow the wasm developer to get this wrong
     // it is never valid to have divergent base address for add/remove links
@@ -73,6 +90,9 @@ pub fn delete_link<'a>(
         let mut guard = workspace_lock.write().await;
         let workspace: &mut CallZomeWorkspace = &mut guard;
         let source_chain = &mut workspace.source_chain;
+        if let Err(head_moved) = check_commit_guard(source_chain, commit_guard)? {
+            return Ok(Err(head_moved));
+        }
         let header_builder = builder::DeleteLink {
             link_add_address,
             base_address,
@@ -89,7 +109,8 @@ pub fn delete_link<'a>(
         .await
         .map_err(Box::new)
         .map_err(SourceChainError::from)?;
-        Ok(DeleteLinkOutput::new(header_hash))
+        workspace.check_scratch_size()?;
+        Ok(Ok(header_hash))
     })
 }
 
@@ -102,7 +123,6 @@ pub mod slow_tests {
     use holo_hash::HeaderHash;
     use holochain_wasm_test_utils::TestWasm;
     use holochain_zome_types::link::Links;
-    use holochain_zome_types::DeleteLinkInput;
 
     #[tokio::test(threaded_scheduler)]
     async fn ribosome_delete_link_add_remove() {
@@ -139,24 +159,16 @@ pub mod slow_tests {
         assert!(links.into_inner().len() == 2);
 
         // remove a link
-        let _: HeaderHash = crate::call_test_ribosome!(
-            host_access,
-            TestWasm::Link,
-            "delete_link",
-            DeleteLinkInput::new(link_one)
-        );
+        let _: HeaderHash =
+            crate::call_test_ribosome!(host_access, TestWasm::Link, "delete_link", link_one);
 
         let links: Links = crate::call_test_ribosome!(host_access, TestWasm::Link, "get_links", ());
 
         assert!(links.into_inner().len() == 1);
 
         // remove a link
-        let _: HeaderHash = crate::call_test_ribosome!(
-            host_access,
-            TestWasm::Link,
-            "delete_link",
-            DeleteLinkInput::new(link_two)
-        );
+        let _: HeaderHash =
+            crate::call_test_ribosome!(host_access, TestWasm::Link, "delete_link", link_two);
 
         let links: Links = crate::call_test_ribosome!(host_access, TestWasm::Link, "get_links", ());
 
@@ -12,7 +12,10 @@ pub fn emit_signal(
 ) -> RibosomeResult<EmitSignalOutput> {
     let cell_id = call_context.host_access().cell_id().clone();
     let bytes = input.into_inner();
-    let signal = Signal::App(cell_id, bytes);
-    call_context.host_access().signal_tx().send(signal)?;
+    let signal = Signal::App(cell_id.clone(), bytes);
+    call_context
+        .host_access()
+        .signal_tx()
+        .send_from_cell(&cell_id, signal)?;
     Ok(EmitSignalOutput::new(()))
 }
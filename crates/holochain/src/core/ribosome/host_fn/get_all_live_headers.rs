@@ -0,0 +1,92 @@
+use crate::core::ribosome::error::RibosomeResult;
+use crate::core::ribosome::{CallContext, RibosomeT};
+use holochain_zome_types::{GetAllLiveHeadersInput, GetAllLiveHeadersOutput};
+use std::sync::Arc;
+
+#[allow(clippy::extra_unused_lifetimes)]
+pub fn get_all_live_headers<'a>(
+    _ribosome: Arc<impl RibosomeT>,
+    call_context: Arc<CallContext>,
+    input: GetAllLiveHeadersInput,
+) -> RibosomeResult<GetAllLiveHeadersOutput> {
+    let (entry_hash, options) = input.into_inner();
+
+    // Get the network from the context
+    let network = call_context.host_access.network().clone();
+
+    // timeouts must be handled by the network
+    tokio_safe_block_on::tokio_safe_block_forever_on(async move {
+        let maybe_live_headers = call_context
+            .host_access
+            .workspace()
+            .write()
+            .await
+            .cascade(network)
+            .get_all_live_headers(entry_hash, options.into())
+            .await?;
+        Ok(GetAllLiveHeadersOutput::new(maybe_live_headers))
+    })
+}
+
+#[cfg(test)]
+#[cfg(feature = "slow_tests")]
+pub mod slow_tests {
+    use crate::test_utils::test_conductor::{test_dna_file, TestConductorBatch};
+    use ::fixt::prelude::*;
+    use hdk3::prelude::*;
+    use holo_hash::fixt::AgentPubKeyFixturator;
+    use holochain_wasm_test_utils::TestWasm;
+
+    /// Three agents create the same entry and one deletes theirs,
+    /// so every conductor sees two live headers and one delete
+    #[tokio::test(threaded_scheduler)]
+    async fn get_all_live_headers_across_conductors() {
+        observability::test_run().ok();
+
+        let conductors = TestConductorBatch::new(3).await;
+        let dna = test_dna_file(vec![TestWasm::Crd]).await;
+        let mut apps = Vec::new();
+        let agents = AgentPubKeyFixturator::new(Predictable).take(3);
+        for (i, (conductor, agent)) in conductors.iter().zip(agents).enumerate() {
+            let app = conductor
+                .setup_app(&format!("app-{}", i), agent, &[dna.clone()])
+                .await;
+            apps.push(app);
+        }
+        conductors.exchange_peer_info().await;
+        let cells: Vec<_> = apps.iter().map(|app| &app.cells()[0]).collect();
+
+        let mut created = Vec::new();
+        for cell in cells.iter() {
+            let header_hash: HeaderHash = cell.call(TestWasm::Crd, "create", ()).await;
+            created.push(header_hash);
+        }
+        let delete_hash: HeaderHash = cells[2]
+            .call(TestWasm::Crd, "delete", created[2].clone())
+            .await;
+        conductors.consistency().await;
+
+        let mut expected_live = created[..2].to_vec();
+        expected_live.sort();
+        for cell in cells.iter() {
+            let output: GetAllLiveHeadersOutput =
+                cell.call(TestWasm::Crd, "read_all_live", ()).await;
+            let live = output.into_inner().expect("the entry was created");
+            let mut live_headers: Vec<_> = live
+                .headers
+                .iter()
+                .map(|h| h.header_address().clone())
+                .collect();
+            live_headers.sort();
+            assert_eq!(live_headers, expected_live);
+            let deletes: Vec<_> = live
+                .deletes
+                .iter()
+                .map(|h| h.header_address().clone())
+                .collect();
+            assert_eq!(deletes, vec![delete_hash.clone()]);
+        }
+
+        conductors.shutdown().await;
+    }
+}
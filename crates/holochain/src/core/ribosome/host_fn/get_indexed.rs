@@ -0,0 +1,158 @@
+use crate::core::ribosome::error::RibosomeResult;
+use crate::core::ribosome::host_fn::create::extract_entry_def;
+use crate::core::ribosome::{CallContext, RibosomeT};
+use holochain_p2p::actor::GetIndexedOptions;
+use holochain_types::index::IndexKey;
+use holochain_zome_types::header::AppEntryType;
+use holochain_zome_types::GetIndexedInput;
+use holochain_zome_types::GetIndexedOutput;
+use std::sync::Arc;
+
+#[allow(clippy::extra_unused_lifetimes)]
+pub fn get_indexed<'a>(
+    ribosome: Arc<impl RibosomeT>,
+    call_context: Arc<CallContext>,
+    input: GetIndexedInput,
+) -> RibosomeResult<GetIndexedOutput> {
+    let (entry_def_id, key) = input.into_inner();
+
+    // Get the zome id and the entry def index within it
+    let zome_id = ribosome.zome_name_to_id(&call_context.zome_name)?;
+    let (entry_def_index, entry_visibility) =
+        extract_entry_def(ribosome, call_context.clone(), entry_def_id)?;
+    let app_entry_type = AppEntryType::new(entry_def_index, zome_id, entry_visibility);
+    let index_key = IndexKey::new(&app_entry_type, key.into_vec());
+
+    // Get the network from the context
+    let network = call_context.host_access.network().clone();
+
+    tokio_safe_block_on::tokio_safe_block_forever_on(async move {
+        let entry_hashes = call_context
+            .host_access
+            .workspace()
+            .write()
+            .await
+            .cascade(network)
+            .get_indexed(&index_key, GetIndexedOptions::default())
+            .await?;
+
+        Ok(GetIndexedOutput::new(entry_hashes))
+    })
+}
+
+#[cfg(test)]
+#[cfg(feature = "slow_tests")]
+pub mod slow_tests {
+    use crate::test_utils::test_conductor::{test_dna_file, TestCell, TestConductorBatch};
+    use hdk3::prelude::*;
+    use holochain_p2p::HolochainP2pCellT;
+    use holochain_types::index::IndexKey;
+    use holochain_types::test_utils::{fake_agent_pubkey_1, fake_agent_pubkey_2};
+    use holochain_wasm_test_utils::TestWasm;
+    use std::time::Duration;
+    use test_wasm_common::TestString;
+
+    /// The post entry of the index test wasm
+    #[derive(Clone, Debug, Serialize, Deserialize, SerializedBytes)]
+    struct Post {
+        topic: String,
+        content: String,
+    }
+
+    fn post(topic: &str, content: &str) -> Post {
+        Post {
+            topic: topic.into(),
+            content: content.into(),
+        }
+    }
+
+    /// The deduplicated hashes every authority for a key returns for it
+    async fn get_indexed_from_authorities(cell: &TestCell, key: &str) -> Vec<EntryHash> {
+        let index_key = IndexKey {
+            zome_id: 0.into(),
+            entry_def_index: 0.into(),
+            key: key.as_bytes().to_vec(),
+        };
+        let responses = cell
+            .network()
+            .clone()
+            .get_indexed(index_key, Default::default())
+            .await
+            .unwrap();
+        let mut found: Vec<_> = responses
+            .into_iter()
+            .flat_map(|response| response.entry_hashes)
+            .collect();
+        found.sort();
+        found.dedup();
+        found
+    }
+
+    /// Index ops are notified to their authorities after validation,
+    /// so poll until the authorities return the expected hashes
+    async fn wait_for_index(cell: &TestCell, key: &str, expected: &[EntryHash]) {
+        for _ in 0..50 {
+            if get_indexed_from_authorities(cell, key).await == expected {
+                return;
+            }
+            tokio::time::delay_for(Duration::from_millis(100)).await;
+        }
+        assert_eq!(get_indexed_from_authorities(cell, key).await, expected);
+    }
+
+    /// Posts committed on one conductor are found by their topic from
+    /// another, until they are deleted
+    #[tokio::test(threaded_scheduler)]
+    async fn get_indexed_across_conductors() {
+        observability::test_run().ok();
+
+        let conductors = TestConductorBatch::new(2).await;
+        let dna = test_dna_file(vec![TestWasm::Index]).await;
+        let alice = conductors[0]
+            .setup_app("alice", fake_agent_pubkey_1(), &[dna.clone()])
+            .await;
+        let bob = conductors[1]
+            .setup_app("bob", fake_agent_pubkey_2(), &[dna])
+            .await;
+        conductors.exchange_peer_info().await;
+        let (alice, bob) = (&alice.cells()[0], &bob.cells()[0]);
+
+        let mut expected = Vec::new();
+        for content in &["first", "second"] {
+            let entry_hash: EntryHash = alice
+                .call(TestWasm::Index, "create_post", post("rust", content))
+                .await;
+            expected.push(entry_hash);
+        }
+        let _: EntryHash = alice
+            .call(TestWasm::Index, "create_post", post("wasm", "third"))
+            .await;
+        expected.sort();
+        conductors.consistency().await;
+
+        // Ask the authorities for the key with the p2p event
+        wait_for_index(bob, "rust", &expected).await;
+
+        // The host fn returns the same hashes
+        let EntryHashes(found) = bob
+            .call(TestWasm::Index, "get_by_topic", TestString("rust".into()))
+            .await;
+        assert_eq!(found, expected);
+        let EntryHashes(found) = bob
+            .call(TestWasm::Index, "get_by_topic", TestString("ru".into()))
+            .await;
+        assert!(found.is_empty());
+
+        // Deleting a post takes it out of the index
+        let deleted = expected.remove(0);
+        let _: HeaderHash = alice.call(TestWasm::Index, "delete_post", deleted).await;
+        conductors.consistency().await;
+        wait_for_index(bob, "rust", &expected).await;
+        let EntryHashes(found) = bob
+            .call(TestWasm::Index, "get_by_topic", TestString("rust".into()))
+            .await;
+        assert_eq!(found, expected);
+
+        conductors.shutdown().await;
+    }
+}
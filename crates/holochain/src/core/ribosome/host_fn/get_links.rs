@@ -4,6 +4,7 @@ use crate::core::{
     state::metadata::LinkMetaKey,
 };
 use holochain_p2p::actor::GetLinksOptions;
+use holochain_zome_types::link::Links;
 use holochain_zome_types::GetLinksInput;
 use holochain_zome_types::GetLinksOutput;
 use std::sync::Arc;
@@ -30,16 +31,18 @@ pub fn get_links<'a>(
         };
 
         // Get the links from the dht
-        let links = call_context
+        let (links, freshness) = call_context
             .host_access
             .workspace()
             .write()
             .await
             .cascade(network)
-            .dht_get_links(&key, GetLinksOptions::default())
+            .dht_get_links_with_freshness(&key, GetLinksOptions::default())
             .await?;
 
-        Ok(GetLinksOutput::new(links.into()))
+        Ok(GetLinksOutput::new(
+            Links::from(links).with_freshness(freshness),
+        ))
     })
 }
 
@@ -1,4 +1,7 @@
-use super::{create::extract_entry_def, delete::get_original_address};
+use super::{
+    create::{check_commit_guard, extract_entry_def},
+    delete::get_original_address,
+};
 use crate::core::ribosome::error::RibosomeResult;
 use crate::core::ribosome::CallContext;
 use crate::core::{
@@ -6,7 +9,9 @@ use crate::core::{
     workflow::{integrate_dht_ops_workflow::integrate_to_authored, CallZomeWorkspace},
     SourceChainError,
 };
-use holo_hash::HasHash;
+use holo_hash::{HasHash, HeaderHash};
+use holochain_zome_types::commit_guard::{CommitGuard, HeadMoved};
+use holochain_zome_types::entry::Entry;
 use holochain_zome_types::entry_def::EntryDefId;
 use holochain_zome_types::UpdateInput;
 use holochain_zome_types::{
@@ -21,9 +26,29 @@ pub fn update<'a>(
     call_context: Arc<CallContext>,
     input: UpdateInput,
 ) -> RibosomeResult<UpdateOutput> {
-    // destructure the args out into an app type def id and entry
-    let (entry_def_id, entry, original_header_address) = input.into_inner();
+    // destructure the args out into an app type def id, entry and commit guard
+    let (entry_def_id, entry, original_header_address, commit_guard) = input.into_inner();
+    Ok(UpdateOutput::new(update_element(
+        ribosome,
+        call_context,
+        entry_def_id,
+        entry,
+        original_header_address,
+        commit_guard,
+    )?))
+}
 
+/// Commit an element updating another, shared by the update host functions.
+/// With a guard, nothing is committed if the chain head, including
+/// anything committed earlier in this call, isn't the expected one.
+pub fn update_element(
+    ribosome: Arc<impl RibosomeT>,
+    call_context: Arc<CallContext>,
+    entry_def_id: EntryDefId,
+    entry: Entry,
+    original_header_address: HeaderHash,
+    commit_guard: Option<CommitGuard>,
+) -> RibosomeResult<Result<HeaderHash, HeadMoved>> {
     // build the entry hash
     let async_entry = entry.clone();
     let entry_hash =
@@ -66,6 +91,9 @@ pub fn update<'a>(
         let mut guard = workspace_lock.write().await;
         let workspace: &mut CallZomeWorkspace = &mut guard;
         let source_chain = &mut workspace.source_chain;
+        if let Err(head_moved) = check_commit_guard(source_chain, commit_guard)? {
+            return Ok(Err(head_moved));
+        }
         // push the header and the entry into the source chain
         let header_hash = source_chain.put(header_builder, Some(entry)).await?;
         // fetch the element we just added so we can integrate its DhtOps
@@ -80,7 +108,8 @@ pub fn update<'a>(
         .await
         .map_err(Box::new)
         .map_err(SourceChainError::from)?;
-        Ok(UpdateOutput::new(header_hash))
+        workspace.check_scratch_size()?;
+        Ok(Ok(header_hash))
     })
 }
 
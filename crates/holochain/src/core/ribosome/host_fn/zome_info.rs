@@ -1,11 +1,8 @@
 use crate::core::ribosome::error::RibosomeResult;
 use crate::core::ribosome::CallContext;
 use crate::core::ribosome::RibosomeT;
-use holochain_serialized_bytes::SerializedBytes;
-use holochain_zome_types::zome_info::ZomeInfo;
 use holochain_zome_types::ZomeInfoInput;
 use holochain_zome_types::ZomeInfoOutput;
-use std::convert::TryFrom;
 use std::sync::Arc;
 
 pub fn zome_info(
@@ -13,15 +10,8 @@ pub fn zome_info(
     call_context: Arc<CallContext>,
     _input: ZomeInfoInput,
 ) -> RibosomeResult<ZomeInfoOutput> {
-    Ok(ZomeInfoOutput::new(ZomeInfo {
-        dna_name: ribosome.dna_file().dna().name.clone(),
-        zome_name: call_context.zome_name.clone(),
-        dna_hash: ribosome.dna_file().dna_hash().clone(), // @TODO
-        zome_id: ribosome.zome_name_to_id(&call_context.zome_name)?,
-        properties: SerializedBytes::try_from(()).unwrap(), // @TODO
-                                                            // @todo
-                                                            // public_token: "".into(),                            // @TODO
-    }))
+    let zome_info = ribosome.zome_info(&call_context.zome_name)?;
+    Ok(ZomeInfoOutput::new((*zome_info).clone()))
 }
 
 #[cfg(test)]
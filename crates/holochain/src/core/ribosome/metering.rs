@@ -0,0 +1,191 @@
+//! Measures of the resources zome calls use, for finding which zome
+//! functions are expensive.
+//!
+//! The ribosome measures how much wasm memory each zome call grew to and how
+//! long the guest ran, and, if its modules were compiled with metering, how
+//! much fuel the guest burned. A Cell keeps the measures of its last call and
+//! adds them up per zome function in its [ZomeCallMetrics].
+
+use holo_hash::DnaHash;
+use holochain_zome_types::zome::{FunctionName, ZomeName};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+/// The resources one zome call used
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZomeCallResources {
+    /// The size of the guest's linear memory when the call returned.
+    /// Wasm memory never shrinks, so this is its high-water mark.
+    pub memory_bytes: u64,
+    /// The fuel the guest burned, if its module was compiled with metering
+    pub fuel: Option<u64>,
+    /// How long the guest ran, including the host functions it called
+    pub wall_time: Duration,
+}
+
+/// Where a ribosome puts the resources its zome call used,
+/// for the Cell which made the call. Clones share the same measures.
+#[derive(Clone, Debug, Default)]
+pub struct ZomeCallMeter(Arc<Mutex<Option<ZomeCallResources>>>);
+
+impl ZomeCallMeter {
+    /// Record the resources of a call, replacing any recorded before
+    pub fn record(&self, resources: ZomeCallResources) {
+        *self.0.lock() = Some(resources);
+    }
+
+    /// Take the resources of the call, if it reached the guest
+    pub fn take(&self) -> Option<ZomeCallResources> {
+        self.0.lock().take()
+    }
+}
+
+/// The resources all the calls to one zome function have used
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZomeFnMetrics {
+    /// The Dna of the zome
+    pub dna_hash: DnaHash,
+    /// The zome of the function
+    pub zome_name: ZomeName,
+    /// The function called
+    pub fn_name: FunctionName,
+    /// How many calls reached the guest
+    pub calls: u64,
+    /// The fuel burned by the calls which were metered
+    pub total_fuel: u64,
+    /// The largest linear memory of any call
+    pub peak_memory_bytes: u64,
+    /// How long the guest ran over all the calls
+    pub total_wall_time: Duration,
+}
+
+impl ZomeFnMetrics {
+    fn new(dna_hash: DnaHash, zome_name: ZomeName, fn_name: FunctionName) -> Self {
+        Self {
+            dna_hash,
+            zome_name,
+            fn_name,
+            calls: 0,
+            total_fuel: 0,
+            peak_memory_bytes: 0,
+            total_wall_time: Duration::default(),
+        }
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.calls += other.calls;
+        self.total_fuel = self.total_fuel.saturating_add(other.total_fuel);
+        self.peak_memory_bytes = self.peak_memory_bytes.max(other.peak_memory_bytes);
+        self.total_wall_time += other.total_wall_time;
+    }
+}
+
+type ZomeFnKey = (DnaHash, ZomeName, FunctionName);
+
+/// The resources used by zome calls, added up per Dna, zome and function.
+/// Clones share the same metrics.
+#[derive(Clone, Debug, Default)]
+pub struct ZomeCallMetrics(Arc<Mutex<HashMap<ZomeFnKey, ZomeFnMetrics>>>);
+
+impl ZomeCallMetrics {
+    /// Add the resources of one call to its function's metrics
+    pub fn record(
+        &self,
+        dna_hash: &DnaHash,
+        zome_name: &ZomeName,
+        fn_name: &FunctionName,
+        resources: &ZomeCallResources,
+    ) {
+        let call = ZomeFnMetrics {
+            calls: 1,
+            total_fuel: resources.fuel.unwrap_or(0),
+            peak_memory_bytes: resources.memory_bytes,
+            total_wall_time: resources.wall_time,
+            ..ZomeFnMetrics::new(dna_hash.clone(), zome_name.clone(), fn_name.clone())
+        };
+        self.add(call);
+    }
+
+    /// Add all of another set of metrics to these
+    pub fn merge(&self, other: &ZomeCallMetrics) {
+        for metrics in other.snapshot() {
+            self.add(metrics);
+        }
+    }
+
+    /// The metrics of every function called so far, in Dna, zome and function order
+    pub fn snapshot(&self) -> Vec<ZomeFnMetrics> {
+        let mut metrics: Vec<_> = self.0.lock().values().cloned().collect();
+        metrics.sort_by(|a, b| {
+            (&a.dna_hash, &a.zome_name, &a.fn_name).cmp(&(&b.dna_hash, &b.zome_name, &b.fn_name))
+        });
+        metrics
+    }
+
+    fn add(&self, metrics: ZomeFnMetrics) {
+        let key = (
+            metrics.dna_hash.clone(),
+            metrics.zome_name.clone(),
+            metrics.fn_name.clone(),
+        );
+        self.0
+            .lock()
+            .entry(key)
+            .or_insert_with(|| {
+                ZomeFnMetrics::new(
+                    metrics.dna_hash.clone(),
+                    metrics.zome_name.clone(),
+                    metrics.fn_name.clone(),
+                )
+            })
+            .add(&metrics);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use holochain_types::test_utils::fake_dna_hash;
+
+    #[test]
+    fn calls_are_added_up_per_function() {
+        let dna_hash = fake_dna_hash(1);
+        let zome_name: ZomeName = "zome".into();
+        let spin: FunctionName = "spin".into();
+        let echo: FunctionName = "echo".into();
+        let call = |memory_bytes, fuel, ms| ZomeCallResources {
+            memory_bytes,
+            fuel,
+            wall_time: Duration::from_millis(ms),
+        };
+
+        let metrics = ZomeCallMetrics::default();
+        metrics.record(&dna_hash, &zome_name, &spin, &call(100, Some(50), 2));
+        metrics.record(&dna_hash, &zome_name, &spin, &call(300, Some(70), 3));
+        metrics.record(&dna_hash, &zome_name, &echo, &call(200, None, 1));
+
+        // Another Cell of the same Dna adds to the same functions
+        let other = ZomeCallMetrics::default();
+        other.record(&dna_hash, &zome_name, &spin, &call(200, Some(80), 5));
+        metrics.merge(&other);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[0].fn_name, echo);
+        assert_eq!(snapshot[0].calls, 1);
+        assert_eq!(snapshot[0].total_fuel, 0);
+        assert_eq!(
+            snapshot[1],
+            ZomeFnMetrics {
+                dna_hash,
+                zome_name,
+                fn_name: spin,
+                calls: 3,
+                total_fuel: 200,
+                peak_memory_bytes: 300,
+                total_wall_time: Duration::from_millis(10),
+            }
+        );
+    }
+}
@@ -0,0 +1,434 @@
+//! Compiled wasm modules, shared by every ribosome of a conductor.
+//!
+//! Compiling a zome's wasm is the slowest part of a cold start, so each
+//! module is compiled once and its machine code serialized into the
+//! WasmArtifact database of the wasm environment. Later loads, including
+//! after a restart, deserialize that artifact instead. An artifact built by
+//! a different engine or target, or one which fails its checksum, is
+//! ignored and replaced by compiling the wasm again.
+//!
+//! The zome functions each module exports are read from its export table
+//! once, and kept alongside the module.
+//!
+//! If metering is enabled, modules are instead compiled with fuel metering
+//! by the singlepass backend. Their fuel limit is part of their machine code,
+//! so they are only kept in memory and compiled again on each start.
+//! The singlepass backend only builds on x86_64, so elsewhere metered
+//! modules fail to compile.
+
+use crate::{
+    conductor::config::WasmMeteringConfig,
+    core::{
+        ribosome::{error::RibosomeResult, guest_callback::is_callback_extern},
+        state::wasm::{WasmArtifact, WasmArtifactStore},
+    },
+};
+use holo_hash::{encode::blake2b_256, WasmHash};
+use holochain_state::{
+    buffer::{KvStore, KvStoreT},
+    db::{GetDb, WASM_ARTIFACT},
+    env::EnvironmentWrite,
+    error::DatabaseResult,
+    fresh_reader,
+    prelude::*,
+};
+use holochain_types::dna::DnaFile;
+use holochain_wasmer_host::prelude::{Module, WasmError};
+use holochain_zome_types::zome::FunctionName;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+use tracing::*;
+#[cfg(target_arch = "x86_64")]
+use wasmer_middleware_common::metering::Metering;
+#[cfg(target_arch = "x86_64")]
+use wasmer_runtime_core::codegen::{MiddlewareChain, StreamingCompiler};
+use wasmer_runtime_core::{cache::Artifact, module::ExportIndex};
+#[cfg(target_arch = "x86_64")]
+use wasmer_singlepass_backend::ModuleCodeGenerator as SinglePassMCG;
+
+/// How the modules of a [WasmModuleCache] have been loaded since it was created
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleLoads {
+    /// Modules compiled from wasm
+    pub compiled: u64,
+    /// Modules deserialized from a stored artifact
+    pub deserialized: u64,
+    /// Export tables read to list the zome functions of a module
+    pub export_scans: u64,
+}
+
+/// Compiled modules kept in memory, backed by their serialized artifacts
+/// in the wasm environment. Clones share the same modules.
+#[derive(Clone)]
+pub struct WasmModuleCache {
+    env: EnvironmentWrite,
+    metering: WasmMeteringConfig,
+    modules: Arc<RwLock<HashMap<WasmHash, Module>>>,
+    zome_functions: Arc<RwLock<HashMap<WasmHash, Vec<FunctionName>>>>,
+    loads: Arc<Mutex<ModuleLoads>>,
+}
+
+impl std::fmt::Debug for WasmModuleCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmModuleCache")
+            .field("metering", &self.metering)
+            .field("modules", &self.modules.read().len())
+            .field("loads", &self.loads())
+            .finish()
+    }
+}
+
+/// The engine version and target an artifact must have been built for
+/// to be loaded here
+pub fn artifact_engine() -> String {
+    format!(
+        "wasmer-{}-{}-{}",
+        wasmer_runtime_core::VERSION,
+        std::env::consts::ARCH,
+        std::env::consts::OS
+    )
+}
+
+/// The functions a module exports for clients to call, in name order:
+/// those with the extern calling convention of a pointer in and a pointer
+/// out, other than the host's callbacks and the guest's memory functions
+pub fn zome_functions(module: &Module) -> Vec<FunctionName> {
+    let info = module.info();
+    let mut names: Vec<_> = info
+        .exports
+        .iter()
+        .filter_map(|(name, export)| match export {
+            ExportIndex::Func(func_index) => Some((name, func_index)),
+            _ => None,
+        })
+        .filter(|(_, func_index)| {
+            let signature = &info.signatures[info.func_assoc[**func_index]];
+            signature.params().len() == 1 && signature.returns().len() == 1
+        })
+        .map(|(name, _)| name)
+        .filter(|name| !name.starts_with("__") && !is_callback_extern(name))
+        .cloned()
+        .collect();
+    names.sort();
+    names.into_iter().map(FunctionName::from).collect()
+}
+
+impl WasmModuleCache {
+    /// Create a cache with no modules in memory,
+    /// storing its artifacts in the wasm environment
+    pub fn new(env: EnvironmentWrite) -> Self {
+        Self {
+            env,
+            metering: WasmMeteringConfig::default(),
+            modules: Arc::new(RwLock::new(HashMap::new())),
+            zome_functions: Arc::new(RwLock::new(HashMap::new())),
+            loads: Arc::new(Mutex::new(ModuleLoads::default())),
+        }
+    }
+
+    /// Compile modules with fuel metering, if the config enables it
+    pub fn with_metering(mut self, metering: WasmMeteringConfig) -> Self {
+        self.metering = metering;
+        self
+    }
+
+    /// Whether this cache's modules are metered, and their fuel limit
+    pub fn metering(&self) -> WasmMeteringConfig {
+        self.metering
+    }
+
+    /// How modules have been loaded so far
+    pub fn loads(&self) -> ModuleLoads {
+        *self.loads.lock()
+    }
+
+    /// Get the compiled module of some wasm, from memory, from its stored
+    /// artifact, or by compiling it, in that order of preference
+    pub fn module(&self, wasm_hash: &WasmHash, wasm: &[u8]) -> RibosomeResult<Module> {
+        if let Some(module) = self.modules.read().get(wasm_hash) {
+            return Ok(module.clone());
+        }
+        let module = if self.metering.enabled {
+            self.compile_metered(wasm)?
+        } else {
+            match self.load_artifact(wasm_hash) {
+                Some(module) => module,
+                None => self.compile(wasm_hash, wasm)?,
+            }
+        };
+        self.modules
+            .write()
+            .insert(wasm_hash.clone(), module.clone());
+        Ok(module)
+    }
+
+    /// The zome functions a module exports, read from its export table
+    /// the first time they are asked for
+    pub fn zome_functions(
+        &self,
+        wasm_hash: &WasmHash,
+        wasm: &[u8],
+    ) -> RibosomeResult<Vec<FunctionName>> {
+        if let Some(zome_functions) = self.zome_functions.read().get(wasm_hash) {
+            return Ok(zome_functions.clone());
+        }
+        let zome_functions = zome_functions(&self.module(wasm_hash, wasm)?);
+        self.loads.lock().export_scans += 1;
+        self.zome_functions
+            .write()
+            .insert(wasm_hash.clone(), zome_functions.clone());
+        Ok(zome_functions)
+    }
+
+    /// Make sure every zome of a Dna has a stored artifact,
+    /// compiling any which don't
+    pub fn precompile(&self, dna_file: &DnaFile) -> RibosomeResult<()> {
+        for (zome_name, zome) in dna_file.dna().zomes.iter() {
+            let wasm = dna_file.get_wasm_for_zome(zome_name)?.code();
+            self.module(&zome.wasm_hash, &wasm)?;
+        }
+        Ok(())
+    }
+
+    fn store(&self) -> DatabaseResult<WasmArtifactStore> {
+        Ok(KvStore::new(self.env.get_db(&*WASM_ARTIFACT)?))
+    }
+
+    /// Deserialize a module from its stored artifact, if there is one
+    /// which this engine can load
+    fn load_artifact(&self, wasm_hash: &WasmHash) -> Option<Module> {
+        let artifact = match self
+            .store()
+            .and_then(|store| fresh_reader!(self.env, |r| store.get(&r, wasm_hash)))
+        {
+            Ok(Some(artifact)) => artifact,
+            Ok(None) => return None,
+            Err(error) => {
+                warn!(msg = "Could not read a wasm artifact", ?wasm_hash, ?error);
+                return None;
+            }
+        };
+        if artifact.engine != artifact_engine() {
+            debug!(
+                msg = "Ignoring a wasm artifact built by another engine",
+                engine = %artifact.engine
+            );
+            return None;
+        }
+        if artifact.checksum != blake2b_256(&artifact.bytes) {
+            warn!(msg = "Ignoring a corrupt wasm artifact", ?wasm_hash);
+            return None;
+        }
+        let loaded = Artifact::deserialize(&artifact.bytes).and_then(|artifact| {
+            // Safety: the artifact was serialized by this engine on this
+            // target, and its bytes are unchanged since
+            unsafe {
+                wasmer_runtime_core::load_cache_with(artifact, wasmer_runtime::default_compiler())
+            }
+        });
+        match loaded {
+            Ok(module) => {
+                self.loads.lock().deserialized += 1;
+                Some(module)
+            }
+            Err(error) => {
+                warn!(msg = "Could not load a wasm artifact", ?wasm_hash, ?error);
+                None
+            }
+        }
+    }
+
+    /// Compile a module which counts the fuel it burns,
+    /// and traps once it has burned more than the fuel limit
+    #[cfg(target_arch = "x86_64")]
+    fn compile_metered(&self, wasm: &[u8]) -> RibosomeResult<Module> {
+        let limit = self.metering.fuel_limit.unwrap_or(u64::MAX);
+        let compiler: StreamingCompiler<SinglePassMCG, _, _, _, _> =
+            StreamingCompiler::new(move || {
+                let mut chain = MiddlewareChain::new();
+                chain.push(Metering::new(limit));
+                chain
+            });
+        let module = wasmer_runtime_core::compile_with(wasm, &compiler)
+            .map_err(|e| WasmError::Compile(e.to_string()))?;
+        self.loads.lock().compiled += 1;
+        Ok(module)
+    }
+
+    /// Metered modules need the singlepass backend, which isn't built here
+    #[cfg(not(target_arch = "x86_64"))]
+    fn compile_metered(&self, _wasm: &[u8]) -> RibosomeResult<Module> {
+        Err(WasmError::Compile(format!(
+            "Wasm metering is only supported on x86_64, not {}",
+            std::env::consts::ARCH
+        ))
+        .into())
+    }
+
+    /// Compile a module and store its artifact.
+    /// Failing to store the artifact only means the next cold start compiles again.
+    fn compile(&self, wasm_hash: &WasmHash, wasm: &[u8]) -> RibosomeResult<Module> {
+        let module =
+            wasmer_runtime::compile(wasm).map_err(|e| WasmError::Compile(e.to_string()))?;
+        self.loads.lock().compiled += 1;
+        let bytes = match module.cache().and_then(|artifact| artifact.serialize()) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                warn!(
+                    msg = "Could not serialize a wasm module",
+                    ?wasm_hash,
+                    ?error
+                );
+                return Ok(module);
+            }
+        };
+        let artifact = WasmArtifact {
+            engine: artifact_engine(),
+            checksum: blake2b_256(&bytes),
+            bytes,
+        };
+        if let Err(error) = self.store().and_then(|store| {
+            self.env
+                .guard()
+                .with_commit(|writer| store.put(writer, wasm_hash, &artifact))
+        }) {
+            warn!(msg = "Could not store a wasm artifact", ?wasm_hash, ?error);
+        }
+        Ok(module)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::ribosome::{wasm_ribosome::WasmRibosome, CallContext},
+        fixt::{curve::Zomes, WasmRibosomeFixturator, ZomeCallHostAccessFixturator},
+    };
+    use ::fixt::prelude::*;
+    use holochain_state::{fresh_reader_test, test_utils::test_wasm_env};
+    use holochain_types::test_utils::fake_dna_zomes;
+    use holochain_wasm_test_utils::TestWasm;
+    use holochain_zome_types::{ExternInput, ExternOutput};
+    use std::convert::TryInto;
+    use test_wasm_common::TestString;
+
+    fn test_wasm(test_wasm: TestWasm) -> (WasmHash, Vec<u8>) {
+        let dna_file = fake_dna_zomes("", vec![(test_wasm.into(), test_wasm.into())]);
+        let (zome_name, zome) = dna_file.dna().zomes.first().unwrap().clone();
+        let wasm = dna_file.get_wasm_for_zome(&zome_name).unwrap().code();
+        (zome.wasm_hash, (*wasm).clone())
+    }
+
+    fn foo_wasm() -> (WasmHash, Vec<u8>) {
+        test_wasm(TestWasm::Foo)
+    }
+
+    fn stored_artifact(env: &EnvironmentWrite, wasm_hash: &WasmHash) -> Option<WasmArtifact> {
+        let store: WasmArtifactStore = KvStore::new(env.get_db(&*WASM_ARTIFACT).unwrap());
+        fresh_reader_test!(env, |r| store.get(&r, wasm_hash)).unwrap()
+    }
+
+    /// Call the foo zome function on a module the cache gives a ribosome
+    fn call_foo(cache: &WasmModuleCache) -> String {
+        let ribosome: WasmRibosome = WasmRibosomeFixturator::new(Zomes(vec![TestWasm::Foo]))
+            .next()
+            .unwrap()
+            .with_module_cache(cache.clone());
+        let host_access = fixt!(ZomeCallHostAccess);
+        let call_context = CallContext::new(TestWasm::Foo.into(), host_access.into());
+        let mut instance = ribosome.instance(call_context).unwrap();
+        let output: ExternOutput = holochain_wasmer_host::guest::call(
+            &mut instance,
+            "foo",
+            ExternInput::new(().try_into().unwrap()),
+        )
+        .unwrap();
+        let output: TestString = output.into_inner().try_into().unwrap();
+        output.0
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn artifacts_are_stored_and_reloaded() {
+        let test_env = test_wasm_env();
+        let env = test_env.env();
+        let (wasm_hash, wasm) = foo_wasm();
+
+        let cache = WasmModuleCache::new(env.clone());
+        cache.module(&wasm_hash, &wasm).unwrap();
+        let compiled_result = call_foo(&cache);
+        let artifact = stored_artifact(&env, &wasm_hash).unwrap();
+        assert_eq!(artifact.engine, artifact_engine());
+        // The second load is served from memory
+        cache.module(&wasm_hash, &wasm).unwrap();
+        assert_eq!(
+            cache.loads(),
+            ModuleLoads {
+                compiled: 1,
+                deserialized: 0,
+                export_scans: 0,
+            }
+        );
+
+        // A fresh cache stands in for a restarted conductor
+        let restarted = WasmModuleCache::new(env.clone());
+        let loaded_result = call_foo(&restarted);
+        assert_eq!(
+            restarted.loads(),
+            ModuleLoads {
+                compiled: 0,
+                deserialized: 1,
+                export_scans: 0,
+            }
+        );
+        // Calls through the deserialized module behave as through the compiled one
+        assert_eq!(loaded_result, compiled_result);
+        assert_eq!(loaded_result, "foo");
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn corrupt_artifacts_are_recompiled() {
+        let test_env = test_wasm_env();
+        let env = test_env.env();
+        let (wasm_hash, wasm) = foo_wasm();
+        WasmModuleCache::new(env.clone())
+            .module(&wasm_hash, &wasm)
+            .unwrap();
+
+        let mut artifact = stored_artifact(&env, &wasm_hash).unwrap();
+        let middle = artifact.bytes.len() / 2;
+        artifact.bytes[middle] ^= 0xff;
+        let store: WasmArtifactStore = KvStore::new(env.get_db(&*WASM_ARTIFACT).unwrap());
+        env.guard()
+            .with_commit(|writer| store.put(writer, &wasm_hash, &artifact))
+            .unwrap();
+
+        let restarted = WasmModuleCache::new(env.clone());
+        restarted.module(&wasm_hash, &wasm).unwrap();
+        assert_eq!(
+            restarted.loads(),
+            ModuleLoads {
+                compiled: 1,
+                deserialized: 0,
+                export_scans: 0,
+            }
+        );
+        // The corrupt artifact has been replaced
+        let artifact = stored_artifact(&env, &wasm_hash).unwrap();
+        assert_eq!(artifact.checksum, blake2b_256(&artifact.bytes));
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn zome_functions_exclude_callbacks() {
+        let test_env = test_wasm_env();
+        let (wasm_hash, wasm) = test_wasm(TestWasm::Validate);
+
+        let cache = WasmModuleCache::new(test_env.env());
+        let expected: Vec<FunctionName> = vec!["always_validates".into(), "never_validates".into()];
+        assert_eq!(cache.zome_functions(&wasm_hash, &wasm).unwrap(), expected);
+        // The export table is only read once
+        assert_eq!(cache.zome_functions(&wasm_hash, &wasm).unwrap(), expected);
+        assert_eq!(cache.loads().export_scans, 1);
+    }
+}
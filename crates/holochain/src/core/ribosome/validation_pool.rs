@@ -0,0 +1,405 @@
+//! A thread pool which runs validation callbacks apart from the tokio
+//! runtime, so that a burst of expensive app validation can't slow down
+//! zome calls and interface requests.
+//!
+//! Work is queued on a bounded channel. When the queue is full the pool
+//! refuses more work instead of blocking the caller, and the app validation
+//! workflow waits for the pool to free up before trying the op again.
+//! The [ExecutionContext] given to the app validation call paths decides
+//! whether the wasm runs on the pool or on the caller's thread.
+
+use parking_lot::Mutex;
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    time::Duration,
+};
+use tokio::sync::Notify;
+
+/// How many validation callbacks may wait for a thread before the
+/// pool refuses more work
+pub const DEFAULT_VALIDATION_QUEUE_DEPTH: usize = 64;
+
+/// The longest a caller waits to hear that the pool has freed up before
+/// trying it again, in case the wakeup raced with the caller
+const CAPACITY_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Half of the CPUs, so validation leaves the rest to the runtime
+pub fn default_validation_threads() -> usize {
+    (num_cpus::get() / 2).max(1)
+}
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Why the pool didn't run a validation callback
+#[derive(Debug, thiserror::Error)]
+pub enum ValidationPoolError {
+    /// The queue is full, try again once the pool has caught up
+    #[error("The validation queue is full")]
+    QueueFull,
+    /// The callback panicked, or the pool's threads are gone
+    #[error("The validation callback was dropped before it returned")]
+    Dropped,
+}
+
+/// Threads which only run validation callbacks.
+/// Clones share the same threads and queue.
+#[derive(Clone)]
+pub struct ValidationPool {
+    sender: mpsc::SyncSender<Job>,
+    queued: Arc<AtomicUsize>,
+    freed: Arc<Notify>,
+    threads: usize,
+}
+
+impl std::fmt::Debug for ValidationPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ValidationPool")
+            .field("threads", &self.threads)
+            .field("queue_depth", &self.queue_depth())
+            .finish()
+    }
+}
+
+impl ValidationPool {
+    /// Spawn the pool's threads.
+    /// They stop once every clone of the pool has been dropped.
+    pub fn new(threads: usize, queue_depth: usize) -> Self {
+        let threads = threads.max(1);
+        let (sender, receiver) = mpsc::sync_channel::<Job>(queue_depth);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let queued = Arc::new(AtomicUsize::new(0));
+        let freed = Arc::new(Notify::new());
+        // Host functions called by the callbacks expect to find the runtime
+        let runtime = tokio::runtime::Handle::try_current().ok();
+        for i in 0..threads {
+            let receiver = receiver.clone();
+            let queued = queued.clone();
+            let freed = freed.clone();
+            let runtime = runtime.clone();
+            std::thread::Builder::new()
+                .name(format!("validation-{}", i))
+                .spawn(move || loop {
+                    // The lock is only held while waiting for the next job
+                    let job = receiver.lock().recv();
+                    let job = match job {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    queued.fetch_sub(1, Ordering::SeqCst);
+                    freed.notify();
+                    // A panicking callback drops its result but not the thread
+                    let _ = catch_unwind(AssertUnwindSafe(|| match &runtime {
+                        Some(runtime) => runtime.enter(job),
+                        None => job(),
+                    }));
+                    freed.notify();
+                })
+                .expect("Failed to spawn a validation thread");
+        }
+        Self {
+            sender,
+            queued,
+            freed,
+            threads,
+        }
+    }
+
+    /// How many threads the pool runs callbacks on
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// How many callbacks are waiting for a thread
+    pub fn queue_depth(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    /// Run `f` on one of the pool's threads and wait for its result,
+    /// or return [ValidationPoolError::QueueFull] straight away
+    /// if too many callbacks are already waiting.
+    pub async fn run<R, F>(&self, f: F) -> Result<R, ValidationPoolError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let job: Job = Box::new(move || {
+            let _ = tx.send(f());
+        });
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        if let Err(e) = self.sender.try_send(job) {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+            return Err(match e {
+                mpsc::TrySendError::Full(_) => ValidationPoolError::QueueFull,
+                mpsc::TrySendError::Disconnected(_) => ValidationPoolError::Dropped,
+            });
+        }
+        rx.await.map_err(|_| ValidationPoolError::Dropped)
+    }
+
+    /// Wait until a thread has taken work off the queue or finished a callback,
+    /// so there may be room for more work
+    pub async fn capacity_freed(&self) {
+        let _ = tokio::time::timeout(CAPACITY_POLL_INTERVAL, self.freed.notified()).await;
+    }
+}
+
+/// Where a ribosome call path runs its wasm
+#[derive(Clone, Debug)]
+pub enum ExecutionContext {
+    /// On the calling thread, as zome calls do
+    Caller,
+    /// On the conductor's validation pool
+    Validation(ValidationPool),
+}
+
+impl Default for ExecutionContext {
+    fn default() -> Self {
+        Self::Caller
+    }
+}
+
+impl ExecutionContext {
+    /// The context for a conductor's validation callbacks.
+    /// With no threads they share the caller's runtime.
+    pub fn validation(threads: usize, queue_depth: usize) -> Self {
+        match threads {
+            0 => Self::Caller,
+            _ => Self::Validation(ValidationPool::new(threads, queue_depth)),
+        }
+    }
+
+    /// Run `f` in this context
+    pub async fn run<R, F>(&self, f: F) -> Result<R, ValidationPoolError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        match self {
+            Self::Caller => Ok(f()),
+            Self::Validation(pool) => pool.run(f).await,
+        }
+    }
+
+    /// How many callbacks are waiting for a validation thread
+    pub fn queue_depth(&self) -> usize {
+        match self {
+            Self::Caller => 0,
+            Self::Validation(pool) => pool.queue_depth(),
+        }
+    }
+
+    /// Wait until there may be room for more work after
+    /// [ValidationPoolError::QueueFull]
+    pub async fn capacity_freed(&self) {
+        match self {
+            Self::Caller => (),
+            Self::Validation(pool) => pool.capacity_freed().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Barrier;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn runs_on_the_pool_and_refuses_work_when_full() {
+        let pool = ValidationPool::new(1, 1);
+        assert_eq!(
+            pool.run(|| std::thread::current().name().map(String::from))
+                .await
+                .unwrap(),
+            Some("validation-0".to_string())
+        );
+
+        // Hold the only thread, then fill the queue
+        let barrier = Arc::new(Barrier::new(2));
+        let (started, is_started) = tokio::sync::oneshot::channel();
+        let busy = tokio::spawn({
+            let pool = pool.clone();
+            let barrier = barrier.clone();
+            async move {
+                pool.run(move || {
+                    started.send(()).unwrap();
+                    barrier.wait();
+                })
+                .await
+            }
+        });
+        is_started.await.unwrap();
+        let queued = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.run(|| 1).await }
+        });
+        while pool.queue_depth() == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(pool.queue_depth(), 1);
+        assert!(matches!(
+            pool.run(|| 2).await,
+            Err(ValidationPoolError::QueueFull)
+        ));
+
+        // Once the thread is free the queued work runs
+        barrier.wait();
+        busy.await.unwrap().unwrap();
+        assert_eq!(queued.await.unwrap().unwrap(), 1);
+        assert_eq!(pool.queue_depth(), 0);
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn a_panicking_callback_does_not_stop_the_pool() {
+        let pool = ValidationPool::new(1, 1);
+        assert!(matches!(
+            pool.run(|| panic!("bad callback")).await,
+            Err(ValidationPoolError::Dropped)
+        ));
+        assert_eq!(pool.run(|| 1).await.unwrap(), 1);
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "slow_tests")]
+mod wasm_test {
+    use super::*;
+    use crate::core::ribosome::NamedInvocation;
+    use crate::core::ribosome::RibosomeT;
+    use crate::core::ribosome::WasmRibosome;
+    use crate::core::ribosome::ZomeCallHostAccess;
+    use crate::core::ribosome::ZomeCallInvocationFixturator;
+    use crate::core::ribosome::ZomeCallResponse;
+    use crate::core::workflow::CallZomeWorkspace;
+    use crate::core::workflow::CallZomeWorkspaceLock;
+    use crate::fixt::WasmRibosomeFixturator;
+    use crate::fixt::ZomeCallHostAccessFixturator;
+    use crate::test_utils::test_network;
+    use ::fixt::prelude::*;
+    use holochain_p2p::{event::HolochainP2pEventReceiver, HolochainP2pCellT, HolochainP2pRef};
+    use holochain_state::test_utils::{test_cell_env, TestEnvironment};
+    use holochain_types::{cell::CellId, test_utils::fake_agent_pubkey_1};
+    use holochain_wasm_test_utils::TestWasm;
+    use holochain_zome_types::ExternInput;
+    use std::convert::TryInto;
+    use std::time::Instant;
+    use test_wasm_common::TestInt;
+
+    /// How many times a long call loops
+    const LONG_SPIN: u32 = 20_000_000;
+
+    /// Makes calls into the Metering zome of a genesis'd cell
+    #[derive(Clone)]
+    struct MeteringZome {
+        ribosome: WasmRibosome,
+        host_access: ZomeCallHostAccess,
+        cell_id: CellId,
+    }
+
+    impl MeteringZome {
+        /// The zome, and the env and network which must outlive it
+        async fn new() -> (
+            Self,
+            (TestEnvironment, HolochainP2pRef, HolochainP2pEventReceiver),
+        ) {
+            let test_env = test_cell_env();
+            let mut workspace = CallZomeWorkspace::new(test_env.env().into()).unwrap();
+            crate::core::workflow::fake_genesis(&mut workspace.source_chain)
+                .await
+                .unwrap();
+
+            let ribosome =
+                WasmRibosomeFixturator::new(crate::fixt::curve::Zomes(vec![TestWasm::Metering]))
+                    .next()
+                    .unwrap();
+            let (network, recv, cell_network) = test_network(
+                Some(ribosome.dna_file().dna_hash().clone()),
+                Some(fake_agent_pubkey_1()),
+            )
+            .await;
+            let cell_id = CellId::new(cell_network.dna_hash(), cell_network.from_agent());
+
+            let mut host_access = fixt!(ZomeCallHostAccess);
+            host_access.workspace = CallZomeWorkspaceLock::new(workspace);
+            host_access.network = cell_network;
+            host_access.cell_id = cell_id.clone();
+            (
+                Self {
+                    ribosome,
+                    host_access,
+                    cell_id,
+                },
+                (test_env, network, recv),
+            )
+        }
+
+        /// Call `spin(n)` on this thread
+        fn spin(&self, n: u32) {
+            let invocation = ZomeCallInvocationFixturator::new(NamedInvocation(
+                self.cell_id.clone(),
+                TestWasm::Metering.into(),
+                "spin".into(),
+                ExternInput::new(TestInt(n).try_into().unwrap()),
+            ))
+            .next()
+            .unwrap();
+            assert!(matches!(
+                self.ribosome
+                    .call_zome_function(self.host_access.clone(), invocation)
+                    .unwrap(),
+                ZomeCallResponse::Ok(_)
+            ));
+        }
+    }
+
+    /// The median time a short zome call spawned on the runtime takes
+    /// while `flood` long zome calls run in `context`
+    async fn zome_call_latency_under_flood(context: ExecutionContext, flood: usize) -> Duration {
+        let (zome, _guards) = MeteringZome::new().await;
+        let flooding = Arc::new(AtomicUsize::new(flood));
+        for _ in 0..flood {
+            let context = context.clone();
+            let zome = zome.clone();
+            let flooding = flooding.clone();
+            tokio::spawn(async move {
+                context.run(move || zome.spin(LONG_SPIN)).await.unwrap();
+                flooding.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+
+        // Only count the calls which started and finished during the flood
+        let mut latencies = Vec::new();
+        loop {
+            let zome = zome.clone();
+            let start = Instant::now();
+            tokio::spawn(async move { zome.spin(1) }).await.unwrap();
+            let latency = start.elapsed();
+            if flooding.load(Ordering::SeqCst) == 0 && !latencies.is_empty() {
+                break;
+            }
+            latencies.push(latency);
+        }
+        latencies.sort();
+        latencies[latencies.len() / 2]
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn a_wasm_flood_on_the_pool_slows_zome_calls_less_than_on_the_runtime() {
+        // Enough long calls to hold every runtime thread a few times over
+        let flood = 4 * num_cpus::get();
+
+        let shared = zome_call_latency_under_flood(ExecutionContext::Caller, flood).await;
+        let pooled =
+            zome_call_latency_under_flood(ExecutionContext::validation(2, flood), flood).await;
+        assert!(
+            pooled < shared,
+            "zome calls took {:?} with the flood on the pool and {:?} with it on the runtime",
+            pooled,
+            shared
+        );
+    }
+}
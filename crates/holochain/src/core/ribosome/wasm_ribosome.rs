@@ -10,6 +10,8 @@ use crate::core::ribosome::error::RibosomeError;
 use crate::core::ribosome::error::RibosomeResult;
 use crate::core::ribosome::guest_callback::entry_defs::EntryDefsInvocation;
 use crate::core::ribosome::guest_callback::entry_defs::EntryDefsResult;
+use crate::core::ribosome::guest_callback::index_hint::IndexHintInvocation;
+use crate::core::ribosome::guest_callback::index_hint::IndexHintResult;
 use crate::core::ribosome::guest_callback::init::InitInvocation;
 use crate::core::ribosome::guest_callback::init::InitResult;
 use crate::core::ribosome::guest_callback::migrate_agent::MigrateAgentInvocation;
@@ -30,6 +32,8 @@ use crate::core::ribosome::host_fn::call_remote::call_remote;
 use crate::core::ribosome::host_fn::capability_claims::capability_claims;
 use crate::core::ribosome::host_fn::capability_grants::capability_grants;
 use crate::core::ribosome::host_fn::capability_info::capability_info;
+use crate::core::ribosome::host_fn::chain_head::chain_head;
+use crate::core::ribosome::host_fn::commit_cap_claim::commit_cap_claim;
 use crate::core::ribosome::host_fn::create::create;
 use crate::core::ribosome::host_fn::create_link::create_link;
 use crate::core::ribosome::host_fn::debug::debug;
@@ -39,7 +43,9 @@ use crate::core::ribosome::host_fn::delete_link::delete_link;
 use crate::core::ribosome::host_fn::emit_signal::emit_signal;
 use crate::core::ribosome::host_fn::encrypt::encrypt;
 use crate::core::ribosome::host_fn::get::get;
+use crate::core::ribosome::host_fn::get_all_live_headers::get_all_live_headers;
 use crate::core::ribosome::host_fn::get_details::get_details;
+use crate::core::ribosome::host_fn::get_indexed::get_indexed;
 use crate::core::ribosome::host_fn::get_link_details::get_link_details;
 use crate::core::ribosome::host_fn::get_links::get_links;
 use crate::core::ribosome::host_fn::hash_entry::hash_entry;
@@ -54,6 +60,11 @@ use crate::core::ribosome::host_fn::unreachable::unreachable;
 use crate::core::ribosome::host_fn::update::update;
 use crate::core::ribosome::host_fn::verify_signature::verify_signature;
 use crate::core::ribosome::host_fn::zome_info::zome_info;
+use crate::core::ribosome::metering::{ZomeCallMeter, ZomeCallResources};
+use crate::core::ribosome::module_cache;
+use crate::core::ribosome::module_cache::WasmModuleCache;
+use crate::core::ribosome::zome_info_cache;
+use crate::core::ribosome::zome_info_cache::ZomeInfoCache;
 use crate::core::ribosome::CallContext;
 use crate::core::ribosome::Invocation;
 use crate::core::ribosome::RibosomeT;
@@ -67,6 +78,7 @@ use holochain_types::dna::{
 };
 use holochain_wasmer_host::prelude::*;
 use holochain_zome_types::entry_def::EntryDefsCallbackResult;
+use holochain_zome_types::index_hint::IndexHintCallbackResult;
 use holochain_zome_types::init::InitCallbackResult;
 use holochain_zome_types::migrate_agent::MigrateAgentCallbackResult;
 use holochain_zome_types::post_commit::PostCommitCallbackResult;
@@ -75,10 +87,14 @@ use holochain_zome_types::validate::ValidationPackageCallbackResult;
 use holochain_zome_types::validate_link::ValidateLinkCallbackResult;
 use holochain_zome_types::zome::FunctionName;
 use holochain_zome_types::zome::ZomeName;
+use holochain_zome_types::zome_info::ZomeInfo;
 use holochain_zome_types::CallbackResult;
 use holochain_zome_types::ZomeCallResponse;
 use holochain_zome_types::{header::ZomeId, ExternOutput};
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use wasmer_middleware_common::metering::get_points_used;
 
 /// Path to the wasm cache path
 const WASM_CACHE_PATH_ENV: &str = "HC_WASM_CACHE_PATH";
@@ -92,24 +108,101 @@ pub struct WasmRibosome {
     //      - is already in the wasm cache, and only include the DnaDef portion
     //      - here in the ribosome.
     pub dna_file: DnaFile,
+    /// Where compiled modules are kept, if not in the wasmer file cache
+    module_cache: Option<WasmModuleCache>,
+    /// The info of each zome, if it isn't built for every call
+    zome_info_cache: Option<ZomeInfoCache>,
+    /// Where the resources of zome calls are recorded, if they are wanted
+    meter: Option<ZomeCallMeter>,
 }
 
 impl WasmRibosome {
     /// Create a new instance
     pub fn new(dna_file: DnaFile) -> Self {
-        Self { dna_file }
+        Self {
+            dna_file,
+            module_cache: None,
+            zome_info_cache: None,
+            meter: None,
+        }
+    }
+
+    /// Load compiled modules from a [WasmModuleCache], which keeps them
+    /// in the wasm environment, rather than from the wasmer file cache
+    pub fn with_module_cache(mut self, module_cache: WasmModuleCache) -> Self {
+        self.module_cache = Some(module_cache);
+        self
+    }
+
+    /// Take the info of each zome from a [ZomeInfoCache] shared by a Cell's
+    /// ribosomes, rather than building it for every call
+    pub fn with_zome_info_cache(mut self, zome_info_cache: ZomeInfoCache) -> Self {
+        self.zome_info_cache = Some(zome_info_cache);
+        self
+    }
+
+    /// Record the resources each zome call uses in a [ZomeCallMeter].
+    /// Callbacks the host makes are not recorded.
+    pub fn with_meter(mut self, meter: ZomeCallMeter) -> Self {
+        self.meter = Some(meter);
+        self
+    }
+
+    /// The fuel limit of a call, if its module was compiled with metering
+    fn fuel_limit(&self) -> Option<u64> {
+        self.module_cache
+            .as_ref()
+            .map(|module_cache| module_cache.metering())
+            .filter(|metering| metering.enabled)
+            .map(|metering| metering.fuel_limit.unwrap_or(u64::MAX))
+    }
+
+    /// The resources a call used, read from its instance once the guest returned
+    fn measure(&self, instance: &Instance, wall_time: Duration) -> ZomeCallResources {
+        ZomeCallResources {
+            memory_bytes: instance.context().memory(0).size().bytes().0 as u64,
+            fuel: self.fuel_limit().map(|_| get_points_used(instance)),
+            wall_time,
+        }
     }
 
     pub fn module(&self, call_context: CallContext) -> RibosomeResult<Module> {
-        let zome_name: ZomeName = call_context.zome_name();
-        let wasm: Arc<Vec<u8>> = self.dna_file.get_wasm_for_zome(&zome_name)?.code();
+        self.zome_module(&call_context.zome_name())
+    }
+
+    fn zome_module(&self, zome_name: &ZomeName) -> RibosomeResult<Module> {
+        let wasm: Arc<Vec<u8>> = self.dna_file.get_wasm_for_zome(zome_name)?.code();
+        if let Some(module_cache) = &self.module_cache {
+            let wasm_hash = &self.dna_file.dna().get_zome(zome_name)?.wasm_hash;
+            return module_cache.module(wasm_hash, &wasm);
+        }
         Ok(holochain_wasmer_host::instantiate::module(
-            &self.wasm_cache_key(&zome_name)?,
+            &self.wasm_cache_key(zome_name)?,
             &wasm,
             std::env::var_os(WASM_CACHE_PATH_ENV),
         )?)
     }
 
+    /// The functions each zome exports for clients to call,
+    /// leaving out the callbacks the host calls into
+    pub fn list_zome_functions(&self) -> RibosomeResult<BTreeMap<ZomeName, Vec<FunctionName>>> {
+        self.dna_file
+            .dna()
+            .zomes
+            .iter()
+            .map(|(zome_name, zome)| {
+                let zome_functions = match &self.module_cache {
+                    Some(module_cache) => {
+                        let wasm = self.dna_file.get_wasm_for_zome(zome_name)?.code();
+                        module_cache.zome_functions(&zome.wasm_hash, &wasm)?
+                    }
+                    None => module_cache::zome_functions(&self.zome_module(zome_name)?),
+                };
+                Ok((zome_name.clone(), zome_functions))
+            })
+            .collect()
+    }
+
     pub fn wasm_cache_key(&self, zome_name: &ZomeName) -> Result<&[u8], DnaError> {
         // TODO: make this actually the hash of the wasm once we can do that
         // watch out for cache misses in the tests that make things slooow if you change this!
@@ -125,6 +218,13 @@ impl WasmRibosome {
     pub fn instance(&self, call_context: CallContext) -> RibosomeResult<Instance> {
         let zome_name: ZomeName = call_context.zome_name();
         let wasm: Arc<Vec<u8>> = self.dna_file.get_wasm_for_zome(&zome_name)?.code();
+        if self.module_cache.is_some() {
+            let module = self.module(call_context.clone())?;
+            let imports: ImportObject = Self::imports(self, call_context);
+            return Ok(module
+                .instantiate(&imports)
+                .map_err(|e| WasmError::Compile(e.to_string()))?);
+        }
         let imports: ImportObject = Self::imports(self, call_context);
         Ok(holochain_wasmer_host::instantiate::instantiate(
             self.wasm_cache_key(&zome_name)?,
@@ -268,8 +368,14 @@ impl WasmRibosome {
             ..
         } = host_fn_access
         {
+            ns.insert("__chain_head", func!(invoke_host_function!(chain_head)));
             ns.insert("__get", func!(invoke_host_function!(get)));
+            ns.insert(
+                "__get_all_live_headers",
+                func!(invoke_host_function!(get_all_live_headers)),
+            );
             ns.insert("__get_details", func!(invoke_host_function!(get_details)));
+            ns.insert("__get_indexed", func!(invoke_host_function!(get_indexed)));
             ns.insert("__get_links", func!(invoke_host_function!(get_links)));
             ns.insert(
                 "__get_link_details",
@@ -277,8 +383,14 @@ impl WasmRibosome {
             );
             ns.insert("__query", func!(invoke_host_function!(query)));
         } else {
+            ns.insert("__chain_head", func!(invoke_host_function!(unreachable)));
             ns.insert("__get", func!(invoke_host_function!(unreachable)));
+            ns.insert(
+                "__get_all_live_headers",
+                func!(invoke_host_function!(unreachable)),
+            );
             ns.insert("__get_details", func!(invoke_host_function!(unreachable)));
+            ns.insert("__get_indexed", func!(invoke_host_function!(unreachable)));
             ns.insert("__get_links", func!(invoke_host_function!(unreachable)));
             ns.insert(
                 "__get_link_details",
@@ -304,6 +416,10 @@ impl WasmRibosome {
         {
             ns.insert("__call", func!(invoke_host_function!(call)));
             ns.insert("__create", func!(invoke_host_function!(create)));
+            ns.insert(
+                "__commit_cap_claim",
+                func!(invoke_host_function!(commit_cap_claim)),
+            );
             ns.insert("__emit_signal", func!(invoke_host_function!(emit_signal)));
             ns.insert("__create_link", func!(invoke_host_function!(create_link)));
             ns.insert("__delete_link", func!(invoke_host_function!(delete_link)));
@@ -313,6 +429,10 @@ impl WasmRibosome {
         } else {
             ns.insert("__call", func!(invoke_host_function!(unreachable)));
             ns.insert("__create", func!(invoke_host_function!(unreachable)));
+            ns.insert(
+                "__commit_cap_claim",
+                func!(invoke_host_function!(unreachable)),
+            );
             ns.insert("__emit_signal", func!(invoke_host_function!(unreachable)));
             ns.insert("__create_link", func!(invoke_host_function!(unreachable)));
             ns.insert("__delete_link", func!(invoke_host_function!(unreachable)));
@@ -377,6 +497,16 @@ impl RibosomeT for WasmRibosome {
         }
     }
 
+    fn zome_info(&self, zome_name: &ZomeName) -> RibosomeResult<Arc<ZomeInfo>> {
+        match &self.zome_info_cache {
+            Some(zome_info_cache) => zome_info_cache.get(&self.dna_file, zome_name),
+            None => Ok(Arc::new(zome_info_cache::zome_info(
+                &self.dna_file,
+                zome_name,
+            )?)),
+        }
+    }
+
     /// call a function in a zome for an invocation if it exists
     /// if it does not exist then return Ok(None)
     fn maybe_call<I: Invocation>(
@@ -396,18 +526,48 @@ impl RibosomeT for WasmRibosome {
             // there is a callback to_call and it is implemented in the wasm
             // it is important to fully instantiate this (e.g. don't try to use the module above)
             // because it builds guards against memory leaks and handles imports correctly
+            let is_zome_call = matches!(call_context.host_access, HostAccess::ZomeCall(_));
             let mut instance = self.instance(call_context)?;
+            // be aware of this clone!
+            // the whole invocation is cloned!
+            // @todo - is this a problem for large payloads like entries?
+            let input = invocation.to_owned().host_input()?;
+
+            let span = tracing::debug_span!(
+                "guest_call",
+                zome = %zome_name,
+                function = %to_call,
+                memory_bytes = tracing::field::Empty,
+                fuel = tracing::field::Empty,
+                wall_time_us = tracing::field::Empty,
+            );
+            let started = Instant::now();
+            let result: Result<ExternOutput, WasmError> = span.in_scope(|| {
+                holochain_wasmer_host::guest::call(&mut instance, to_call.as_ref(), input)
+            });
+            let resources = self.measure(&instance, started.elapsed());
+            span.record("memory_bytes", &resources.memory_bytes);
+            if let Some(fuel) = resources.fuel {
+                span.record("fuel", &fuel);
+            }
+            span.record("wall_time_us", &(resources.wall_time.as_micros() as u64));
+            if let (true, Some(meter)) = (is_zome_call, &self.meter) {
+                meter.record(resources);
+            }
 
-            let result: ExternOutput = holochain_wasmer_host::guest::call(
-                &mut instance,
-                to_call.as_ref(),
-                // be aware of this clone!
-                // the whole invocation is cloned!
-                // @todo - is this a problem for large payloads like entries?
-                invocation.to_owned().host_input()?,
-            )?;
+            // A metered guest traps once it has burned all of its fuel
+            if let (Err(_), Some(limit), Some(fuel)) = (&result, self.fuel_limit(), resources.fuel)
+            {
+                if fuel >= limit {
+                    return Err(RibosomeError::FuelExhausted {
+                        zome_name: zome_name.clone(),
+                        fn_name: to_call.clone(),
+                        limit,
+                    });
+                }
+            }
 
-            Ok(Some(result))
+            Ok(Some(result?))
         } else {
             // the func doesn't exist
             // the callback is not implemented
@@ -466,6 +626,14 @@ impl RibosomeT for WasmRibosome {
         do_callback!(self, access, invocation, ValidateLinkCallbackResult)
     }
 
+    fn run_index_hint(
+        &self,
+        access: ValidateHostAccess,
+        invocation: IndexHintInvocation,
+    ) -> RibosomeResult<IndexHintResult> {
+        do_callback!(self, access, invocation, IndexHintCallbackResult)
+    }
+
     fn run_init(
         &self,
         access: InitHostAccess,
@@ -0,0 +1,141 @@
+//! The [ZomeInfo] of each zome, built once per Dna rather than on every
+//! zome call.
+//!
+//! A Cell builds its cache when it is created and hands it to the ribosome
+//! of every call. The infos are keyed by the hash of the Dna they were built
+//! from, so if a Cell's Dna changes they are rebuilt on the next call rather
+//! than served stale.
+
+use crate::core::ribosome::error::{RibosomeError, RibosomeResult};
+use holo_hash::DnaHash;
+use holochain_types::dna::DnaFile;
+use holochain_zome_types::{header::ZomeId, zome::ZomeName, zome_info::ZomeInfo};
+use parking_lot::RwLock;
+use std::{collections::HashMap, sync::Arc};
+
+/// The infos of every zome of a Dna, along with the hash of that Dna
+type ZomeInfos = (DnaHash, Arc<HashMap<ZomeName, Arc<ZomeInfo>>>);
+
+/// The [ZomeInfo] of each zome of a Cell's Dna. Clones share the same infos.
+#[derive(Clone, Debug, Default)]
+pub struct ZomeInfoCache(Arc<RwLock<Option<ZomeInfos>>>);
+
+impl ZomeInfoCache {
+    /// Build the info of every zome of a Dna
+    pub fn new(dna_file: &DnaFile) -> Self {
+        Self(Arc::new(RwLock::new(Some(build(dna_file)))))
+    }
+
+    /// The info of a zome of this Dna.
+    /// If the infos were built from a different Dna, or have been
+    /// invalidated, they are all built again first.
+    pub fn get(&self, dna_file: &DnaFile, zome_name: &ZomeName) -> RibosomeResult<Arc<ZomeInfo>> {
+        let infos = match &*self.0.read() {
+            Some((dna_hash, infos)) if dna_hash == dna_file.dna_hash() => Some(infos.clone()),
+            _ => None,
+        };
+        let infos = match infos {
+            Some(infos) => infos,
+            None => {
+                let (dna_hash, infos) = build(dna_file);
+                *self.0.write() = Some((dna_hash, infos.clone()));
+                infos
+            }
+        };
+        infos
+            .get(zome_name)
+            .cloned()
+            .ok_or_else(|| RibosomeError::ZomeNotExists(zome_name.to_owned()))
+    }
+
+    /// Forget the infos, so they are built again for the next call
+    pub fn invalidate(&self) {
+        *self.0.write() = None;
+    }
+}
+
+/// Build the info of a zome of a Dna
+pub fn zome_info(dna_file: &DnaFile, zome_name: &ZomeName) -> RibosomeResult<ZomeInfo> {
+    let zome_id = dna_file
+        .dna()
+        .zomes
+        .iter()
+        .position(|(name, _)| name == zome_name)
+        .ok_or_else(|| RibosomeError::ZomeNotExists(zome_name.to_owned()))?;
+    Ok(new_zome_info(
+        dna_file,
+        zome_name,
+        ZomeId::from(zome_id as u8),
+    ))
+}
+
+fn new_zome_info(dna_file: &DnaFile, zome_name: &ZomeName, zome_id: ZomeId) -> ZomeInfo {
+    ZomeInfo {
+        dna_name: dna_file.dna().name.clone(),
+        zome_name: zome_name.clone(),
+        dna_hash: dna_file.dna_hash().clone(),
+        zome_id,
+        properties: dna_file.dna().properties.clone(),
+        network_seed: Some(dna_file.dna().uuid.clone()).filter(|uuid| !uuid.is_empty()),
+        // @TODO
+        // public_token: "".into(),
+    }
+}
+
+fn build(dna_file: &DnaFile) -> ZomeInfos {
+    let infos = dna_file
+        .dna()
+        .zomes
+        .iter()
+        .enumerate()
+        .map(|(i, (zome_name, _))| {
+            let info = new_zome_info(dna_file, zome_name, ZomeId::from(i as u8));
+            (zome_name.clone(), Arc::new(info))
+        })
+        .collect();
+    (dna_file.dna_hash().clone(), Arc::new(infos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use holochain_types::test_utils::fake_dna_zomes;
+
+    #[test]
+    fn infos_are_shared_until_the_dna_changes() {
+        let zomes = vec![("a".into(), vec![].into()), ("b".into(), vec![].into())];
+        let dna_file = fake_dna_zomes("one", zomes.clone());
+        let cache = ZomeInfoCache::new(&dna_file);
+
+        let b = cache.get(&dna_file, &"b".into()).unwrap();
+        assert_eq!(*b, zome_info(&dna_file, &"b".into()).unwrap());
+        assert_eq!(b.zome_id, ZomeId::from(1));
+        assert_eq!(b.network_seed, Some("one".to_string()));
+        assert_eq!(b.properties, dna_file.dna().properties);
+        // The same info is handed out again
+        assert!(Arc::ptr_eq(&b, &cache.get(&dna_file, &"b".into()).unwrap()));
+        assert!(matches!(
+            cache.get(&dna_file, &"c".into()),
+            Err(RibosomeError::ZomeNotExists(_))
+        ));
+
+        // A different Dna rebuilds the infos
+        let other = fake_dna_zomes("two", zomes);
+        let other_b = cache.get(&other, &"b".into()).unwrap();
+        assert_eq!(&other_b.dna_hash, other.dna_hash());
+        assert_eq!(other_b.network_seed, Some("two".to_string()));
+
+        // As does invalidating them
+        cache.invalidate();
+        let b_again = cache.get(&other, &"b".into()).unwrap();
+        assert!(!Arc::ptr_eq(&other_b, &b_again));
+        assert_eq!(other_b, b_again);
+    }
+
+    #[test]
+    fn dna_without_a_seed_has_no_network_seed() {
+        let dna_file = fake_dna_zomes("", vec![("a".into(), vec![].into())]);
+        let a = zome_info(&dna_file, &"a".into()).unwrap();
+        assert_eq!(a.network_seed, None);
+    }
+}
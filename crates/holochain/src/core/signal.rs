@@ -3,6 +3,8 @@
 //! - App-defined signals are produced via the `emit_signal!` host function.
 //! - System-defined signals are produced in various places in the system
 
+use crate::conductor::state::AppInterfaceId;
+use holo_hash::{AgentPubKey, DnaHash};
 use holochain_serialized_bytes::prelude::*;
 use holochain_types::{cell::CellId, impl_from};
 
@@ -26,6 +28,39 @@ pub enum SystemSignal {
     /// Since we have no real system signals, we use a test signal for testing
     /// TODO: replace instances of this with something real
     Test(String),
+    /// An interface has stopped keeping up with the signals sent to it,
+    /// so its oldest signals are being dropped.
+    /// Sent once to every other interface when the drops begin.
+    SlowConsumer {
+        /// The interface which is dropping signals
+        interface_id: AppInterfaceId,
+        /// How many signals it had dropped when this was sent
+        dropped: u64,
+    },
+    /// An agent has connected to the network of a Dna this conductor runs
+    PeerConnected {
+        /// The Dna whose network the agent connected to
+        dna_hash: DnaHash,
+        /// The agent which connected
+        agent: AgentPubKey,
+        /// The urls it can be reached at, if known
+        urls: Vec<String>,
+    },
+    /// An agent has disconnected from the network of a Dna this conductor runs
+    PeerDisconnected {
+        /// The Dna whose network the agent disconnected from
+        dna_hash: DnaHash,
+        /// The agent which disconnected
+        agent: AgentPubKey,
+    },
+    /// The validation callbacks of a Cell keep failing with errors,
+    /// so its app validation is paused apart from occasional probes
+    ValidationCircuitOpen {
+        /// The Cell whose app validation is paused
+        cell_id: CellId,
+        /// The error which opened the circuit
+        sample_error: String,
+    },
 }
 
 pub fn test_signal(s: &str) -> Signal {
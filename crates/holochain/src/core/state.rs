@@ -39,6 +39,7 @@ pub mod chain_sequence;
 pub mod dht_op_integration;
 #[allow(missing_docs)]
 pub mod element_buf;
+pub mod integrity_audit;
 pub mod metadata;
 #[allow(missing_docs)]
 pub mod source_chain;
@@ -46,4 +47,5 @@ pub mod validation_db;
 pub mod validation_receipts_db;
 #[allow(missing_docs)]
 pub mod wasm;
+pub mod workflow_errors;
 pub mod workspace;
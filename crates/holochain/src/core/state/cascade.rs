@@ -6,15 +6,15 @@
 
 use super::{
     element_buf::ElementBuf,
-    metadata::{LinkMetaKey, MetadataBuf, MetadataBufT},
+    metadata::{LinkMetaKey, LinkMetaVal, MetadataBuf, MetadataBufT, SysMetaVal},
 };
 use crate::core::workflow::integrate_dht_ops_workflow::integrate_single_metadata;
-use error::CascadeResult;
+use error::{CascadeError, CascadeResult};
 use fallible_iterator::FallibleIterator;
 use holo_hash::{hash_type::AnyDht, AnyDhtHash, EntryHash, HeaderHash};
 use holochain_p2p::HolochainP2pCellT;
 use holochain_p2p::{
-    actor::{GetLinksOptions, GetMetaOptions, GetOptions},
+    actor::{GetIndexedOptions, GetLinksOptions, GetMetaOptions, GetOptions},
     HolochainP2pCell,
 };
 use holochain_state::{error::DatabaseResult, fresh_reader, prelude::*};
@@ -25,18 +25,20 @@ use holochain_types::{
         SignedHeaderHashedExt,
     },
     entry::option_entry_hashed,
-    link::{GetLinksResponse, WireLinkMetaKey},
+    index::IndexKey,
+    link::{link_order, GetLinksResponse, WireLinkMetaKey},
     metadata::{EntryDhtStatus, MetadataSet, TimedHeaderHash},
     EntryHashed,
 };
 use holochain_zome_types::{
     element::SignedHeader,
-    header::HeaderType,
-    link::Link,
-    metadata::{Details, ElementDetails, EntryDetails},
+    header::{Header, HeaderType},
+    link::{Link, LinkFreshness},
+    metadata::{Details, ElementDetails, EntryDetails, LiveHeaders},
+    Entry,
 };
-use std::collections::HashSet;
 use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{HashMap, HashSet};
 use tracing::*;
 use tracing_futures::Instrument;
 
@@ -50,6 +52,14 @@ mod test;
 
 pub mod error;
 
+/// The most [Update](holochain_zome_types::header::Update) pointers
+/// [Cascade::resolve_update_chain] will follow before giving up
+pub const MAX_UPDATE_CHAIN_DEPTH: usize = 32;
+
+/// How many more times a get is sent to the network when every peer which
+/// responded doesn't hold the data, each time asking twice as many peers
+pub const NOT_HELD_RETRIES: usize = 2;
+
 /////////////////
 // Helper macros
 /////////////////
@@ -150,7 +160,6 @@ pub struct Cascade<
     pending_data: Option<DbPair<'a, MetaPending, PendingPrefix>>,
     rejected_data: Option<DbPair<'a, MetaRejected, RejectedPrefix>>,
     cache_data: Option<DbPairMut<'a, MetaCache>>,
-    env: Option<EnvironmentRead>,
     network: Option<Network>,
 }
 
@@ -178,12 +187,14 @@ where
     Network: HolochainP2pCellT,
 {
     /// Constructs a [Cascade], for the default use case of
-    /// vault + cache + network
+    /// vault + cache + network.
+    /// No environment is passed in, because each store is read through
+    /// its own: the vault and cache may live in a DHT environment shared
+    /// with the other Cells of the DNA, apart from the authored data.
     // TODO: Probably should rename this function but want to
     // avoid refactoring
     #[allow(clippy::complexity)]
     pub fn new(
-        env: EnvironmentRead,
         element_authored: &'a ElementBuf<AuthoredPrefix>,
         meta_authored: &'a MetaAuthored,
         element_integrated: &'a ElementBuf,
@@ -205,7 +216,6 @@ where
             meta: meta_cache,
         });
         Self {
-            env: Some(env),
             network: Some(network),
             pending_data: None,
             rejected_data: None,
@@ -225,7 +235,6 @@ impl<'a> Cascade<'a> {
             pending_data: None,
             rejected_data: None,
             cache_data: None,
-            env: None,
             network: None,
         }
     }
@@ -246,14 +255,12 @@ where
         mut self,
         integrated_data: DbPair<'a, MetaVault, IntegratedPrefix>,
     ) -> Self {
-        self.env = Some(integrated_data.meta.env().clone());
         self.integrated_data = Some(integrated_data);
         self
     }
 
     /// Add the integrated [ElementBuf] and [MetadataBuf] to the cascade
     pub fn with_pending(mut self, pending_data: DbPair<'a, MetaPending, PendingPrefix>) -> Self {
-        self.env = Some(pending_data.meta.env().clone());
         self.pending_data = Some(pending_data);
         self
     }
@@ -263,7 +270,6 @@ where
         mut self,
         authored_data: DbPair<'a, MetaAuthored, AuthoredPrefix>,
     ) -> Self {
-        self.env = Some(authored_data.meta.env().clone());
         self.authored_data = Some(authored_data);
         self
     }
@@ -273,14 +279,12 @@ where
         mut self,
         rejected_data: DbPair<'a, MetaRejected, RejectedPrefix>,
     ) -> Self {
-        self.env = Some(rejected_data.meta.env().clone());
         self.rejected_data = Some(rejected_data);
         self
     }
 
     /// Add the cache [ElementBuf] and [MetadataBuf] to the cascade
     pub fn with_cache(mut self, cache_data: DbPairMut<'a, MetaCache>) -> Self {
-        self.env = Some(cache_data.meta.env().clone());
         self.cache_data = Some(cache_data);
         self
     }
@@ -296,7 +300,6 @@ where
             pending_data: self.pending_data,
             rejected_data: self.rejected_data,
             cache_data: self.cache_data,
-            env: self.env,
             network: Some(network),
         }
     }
@@ -326,13 +329,42 @@ where
         Ok(())
     }
 
+    /// Get from the network, asking more peers while none of those which
+    /// responded hold the data. Peers which don't cover the hash can't say
+    /// whether it exists, so their responses mustn't be taken as "not found".
+    async fn network_get(
+        &mut self,
+        hash: AnyDhtHash,
+        mut options: GetOptions,
+    ) -> CascadeResult<Vec<GetElementResponse>> {
+        let network = ok_or_return!(self.network.as_mut(), vec![]);
+        let mut retries = 0;
+        loop {
+            let results = network.get(hash.clone(), options.clone()).await?;
+            let none_held = !results.is_empty()
+                && results
+                    .iter()
+                    .all(|r| matches!(r, GetElementResponse::NotHeld));
+            if !none_held || retries == NOT_HELD_RETRIES {
+                return Ok(results);
+            }
+            retries += 1;
+            let count = options.remote_agent_count.unwrap_or(1).max(1);
+            options.remote_agent_count = Some(count.saturating_mul(2));
+            debug!(
+                msg = "No peer which responded holds the data, asking more",
+                ?hash,
+                remote_agent_count = ?options.remote_agent_count
+            );
+        }
+    }
+
     async fn fetch_element_via_header(
         &mut self,
         hash: HeaderHash,
         options: GetOptions,
     ) -> CascadeResult<()> {
-        let network = ok_or_return!(self.network.as_mut());
-        let results = network.get(hash.into(), options).await?;
+        let results = self.network_get(hash.into(), options).await?;
         // Search through the returns for the first delete
         for response in results.into_iter() {
             match response {
@@ -347,6 +379,8 @@ where
                 }
                 // Doesn't have header but not because it was deleted
                 GetElementResponse::GetHeader(None) => (),
+                // Doesn't cover the header, so another peer was asked
+                GetElementResponse::NotHeld => (),
                 r => {
                     error!(
                         msg = "Got an invalid response to fetch element via header",
@@ -364,9 +398,8 @@ where
         hash: EntryHash,
         options: GetOptions,
     ) -> CascadeResult<()> {
-        let network = ok_or_return!(self.network.as_mut());
-        let results = network
-            .get(hash.clone().into(), options.clone())
+        let results = self
+            .network_get(hash.clone().into(), options.clone())
             .instrument(debug_span!("fetch_element_via_entry::network_get"))
             .await?;
 
@@ -395,6 +428,8 @@ where
                 }
                 // Authority didn't have any headers for this entry
                 GetElementResponse::GetEntryFull(None) => (),
+                // Doesn't cover the entry, so another peer was asked
+                GetElementResponse::NotHeld => (),
                 r @ GetElementResponse::GetHeader(_) => {
                     error!(
                         msg = "Got an invalid response to fetch element via entry",
@@ -407,27 +442,20 @@ where
         Ok(())
     }
 
-    // TODO: Remove when used
-    #[allow(dead_code)]
-    async fn fetch_meta(
-        &mut self,
-        basis: AnyDhtHash,
-        options: GetMetaOptions,
-    ) -> CascadeResult<Vec<MetadataSet>> {
-        let network = ok_or_return!(self.network.as_mut(), vec![]);
-        Ok(network.get_meta(basis.clone(), options).await?)
-    }
-
+    /// Update the cache with the links the authorities hold.
+    /// Returns the hashes of every CreateLink and DeleteLink header they sent,
+    /// or None if the cascade has no network.
     #[instrument(skip(self, options))]
     async fn fetch_links(
         &mut self,
         link_key: WireLinkMetaKey,
         options: GetLinksOptions,
-    ) -> CascadeResult<()> {
+    ) -> CascadeResult<Option<HashSet<HeaderHash>>> {
         debug!("in get links");
-        let network = ok_or_return!(self.network.as_mut());
+        let network = ok_or_return!(self.network.as_mut(), None);
         let results = network.get_links(link_key, options).await?;
 
+        let mut fetched = HashSet::new();
         for links in results {
             let GetLinksResponse {
                 link_adds,
@@ -440,6 +468,7 @@ where
                     SignedHeaderHashed::from_content_sync(SignedHeader(link_add.into(), signature)),
                     None,
                 );
+                fetched.insert(element.header_address().clone());
                 self.update_stores(element).await?;
             }
             for (link_remove, signature) in link_removes {
@@ -451,10 +480,63 @@ where
                     )),
                     None,
                 );
+                fetched.insert(element.header_address().clone());
                 self.update_stores(element).await?;
             }
         }
-        Ok(())
+        Ok(Some(fetched))
+    }
+
+    /// Update the cache with the metadata the authorities hold on the basis,
+    /// fetching only the sections `options` asks for.
+    /// The headers, updates and deletes which come back are cached by hash.
+    /// Links and activity are only returned, because their full headers are
+    /// needed to cache them.
+    #[instrument(skip(self, options))]
+    pub async fn fetch_meta(
+        &mut self,
+        basis: AnyDhtHash,
+        options: GetMetaOptions,
+    ) -> CascadeResult<Vec<MetadataSet>> {
+        let network = ok_or_return!(self.network.as_mut(), vec![]);
+        let results = network.get_meta(basis.clone(), options).await?;
+        let cache_data = ok_or_return!(self.cache_data.as_mut(), results);
+
+        for metadata in &results {
+            let values = metadata
+                .headers
+                .iter()
+                .flatten()
+                .cloned()
+                .map(SysMetaVal::NewEntry)
+                .chain(
+                    metadata
+                        .updates
+                        .iter()
+                        .flatten()
+                        .cloned()
+                        .map(SysMetaVal::Update),
+                )
+                .chain(
+                    metadata
+                        .deletes
+                        .iter()
+                        .flatten()
+                        .cloned()
+                        .map(SysMetaVal::Delete),
+                );
+            for value in values {
+                match *basis.hash_type() {
+                    AnyDht::Entry => cache_data
+                        .meta
+                        .register_raw_on_entry(basis.clone().into(), value)?,
+                    AnyDht::Header => cache_data
+                        .meta
+                        .register_raw_on_header(basis.clone().into(), value),
+                }
+            }
+        }
+        Ok(results)
     }
 
     /// Get the element from any databases that the Cascade has been constructed with
@@ -549,60 +631,65 @@ where
         headers: &BTreeSet<TimedHeaderHash>,
         cache_data: &DbPairMut<'a, MetaCache>,
         authored_data: &DbPair<'a, MetaAuthored, AuthoredPrefix>,
-        env: &EnvironmentRead,
     ) -> CascadeResult<EntryDhtStatus> {
-        fresh_reader!(env, |r| {
-            for thh in headers {
-                // If we can find any header that has no
-                // deletes in either store then the entry is live
-                if cache_data
-                    .meta
-                    .get_deletes_on_header(&r, thh.header_hash.clone())?
-                    .next()?
-                    .is_none()
-                    && authored_data
+        fresh_reader!(cache_data.meta.env(), |r| {
+            fresh_reader!(authored_data.meta.env(), |ra| {
+                for thh in headers {
+                    // If we can find any header that has no
+                    // deletes in either store then the entry is live
+                    if cache_data
                         .meta
                         .get_deletes_on_header(&r, thh.header_hash.clone())?
                         .next()?
                         .is_none()
-                {
-                    return Ok(EntryDhtStatus::Live);
+                        && authored_data
+                            .meta
+                            .get_deletes_on_header(&ra, thh.header_hash.clone())?
+                            .next()?
+                            .is_none()
+                    {
+                        return Ok(EntryDhtStatus::Live);
+                    }
                 }
-            }
 
-            Ok(EntryDhtStatus::Dead)
+                Ok(EntryDhtStatus::Dead)
+            })
         })
     }
 
     async fn create_entry_details(&self, hash: EntryHash) -> CascadeResult<Option<EntryDetails>> {
         let cache_data = ok_or_return!(self.cache_data.as_ref(), None);
         let authored_data = ok_or_return!(self.authored_data.as_ref(), None);
-        let env = ok_or_return!(self.env.as_ref(), None);
         match self.get_entry_local_raw(&hash)? {
-            Some(entry) => fresh_reader!(env, |r| {
-                // Get the "headers that created this entry" hashes
-                let headers = cache_data
-                    .meta
-                    .get_headers(&r, hash.clone())?
-                    .chain(authored_data.meta.get_headers(&r, hash.clone())?)
-                    .collect::<BTreeSet<_>>()?;
+            Some(entry) => {
+                let (headers, deletes, updates) = fresh_reader!(cache_data.meta.env(), |r| {
+                    fresh_reader!(authored_data.meta.env(), |ra| {
+                        // Get the "headers that created this entry" hashes
+                        let headers = cache_data
+                            .meta
+                            .get_headers(&r, hash.clone())?
+                            .chain(authored_data.meta.get_headers(&ra, hash.clone())?)
+                            .collect::<BTreeSet<_>>()?;
 
-                // Get the delete hashes
-                let deletes = cache_data
-                    .meta
-                    .get_deletes_on_entry(&r, hash.clone())?
-                    .chain(authored_data.meta.get_deletes_on_entry(&r, hash.clone())?)
-                    .collect::<BTreeSet<_>>()?;
+                        // Get the delete hashes
+                        let deletes = cache_data
+                            .meta
+                            .get_deletes_on_entry(&r, hash.clone())?
+                            .chain(authored_data.meta.get_deletes_on_entry(&ra, hash.clone())?)
+                            .collect::<BTreeSet<_>>()?;
 
-                // Get the update hashes
-                let updates = cache_data
-                    .meta
-                    .get_updates(&r, hash.clone().into())?
-                    .chain(authored_data.meta.get_updates(&r, hash.into())?)
-                    .collect::<BTreeSet<_>>()?;
+                        // Get the update hashes
+                        let updates = cache_data
+                            .meta
+                            .get_updates(&r, hash.clone().into())?
+                            .chain(authored_data.meta.get_updates(&ra, hash.into())?)
+                            .collect::<BTreeSet<_>>()?;
+                        CascadeResult::Ok((headers, deletes, updates))
+                    })
+                })?;
 
                 let entry_dht_status =
-                    Self::compute_entry_dht_status(&headers, &cache_data, &authored_data, &env)?;
+                    Self::compute_entry_dht_status(&headers, &cache_data, &authored_data)?;
 
                 // Render headers
                 let headers = self.render_headers(headers, |h| {
@@ -617,7 +704,7 @@ where
                     updates,
                     entry_dht_status,
                 }))
-            }),
+            }
             None => Ok(None),
         }
     }
@@ -625,15 +712,16 @@ where
     fn create_element_details(&self, hash: HeaderHash) -> CascadeResult<Option<ElementDetails>> {
         let cache_data = ok_or_return!(self.cache_data.as_ref(), None);
         let authored_data = ok_or_return!(self.authored_data.as_ref(), None);
-        let env = ok_or_return!(self.env.as_ref(), None);
         match self.get_element_local_raw(&hash)? {
             Some(element) => {
                 let hash = element.header_address().clone();
-                let deletes = fresh_reader!(env, |r| cache_data
-                    .meta
-                    .get_deletes_on_header(&r, hash.clone())?
-                    .chain(authored_data.meta.get_deletes_on_header(&r, hash)?)
-                    .collect::<BTreeSet<_>>())?;
+                let deletes = fresh_reader!(cache_data.meta.env(), |r| {
+                    fresh_reader!(authored_data.meta.env(), |ra| cache_data
+                        .meta
+                        .get_deletes_on_header(&r, hash.clone())?
+                        .chain(authored_data.meta.get_deletes_on_header(&ra, hash)?)
+                        .collect::<BTreeSet<_>>())
+                })?;
                 let deletes = self.render_headers(deletes, |h| h == HeaderType::Delete)?;
                 Ok(Some(ElementDetails { element, deletes }))
             }
@@ -743,44 +831,45 @@ where
         entry_hash: &EntryHash,
         authored_data: &DbPair<MA, AuthoredPrefix>,
         cache_data: &DbPair<MC>,
-        env: &EnvironmentRead,
     ) -> CascadeResult<Search> {
-        fresh_reader!(env, |r| {
-            let oldest_live_header = authored_data
-                .meta
-                .get_headers(&r, entry_hash.clone())?
-                .chain(cache_data.meta.get_headers(&r, entry_hash.clone())?)
-                .filter_map(|header| {
-                    if authored_data
-                        .meta
-                        .get_deletes_on_header(&r, header.header_hash.clone())?
-                        .next()?
-                        .is_none()
-                        && cache_data
+        let oldest_live_header = fresh_reader!(authored_data.meta.env(), |ra| {
+            fresh_reader!(cache_data.meta.env(), |r| {
+                authored_data
+                    .meta
+                    .get_headers(&ra, entry_hash.clone())?
+                    .chain(cache_data.meta.get_headers(&r, entry_hash.clone())?)
+                    .filter_map(|header| {
+                        if authored_data
                             .meta
-                            .get_deletes_on_header(&r, header.header_hash.clone())?
+                            .get_deletes_on_header(&ra, header.header_hash.clone())?
                             .next()?
                             .is_none()
-                    {
-                        Ok(Some(header))
-                    } else {
-                        Ok(None)
-                    }
-                })
-                .min()?;
-
-            match oldest_live_header {
-                Some(oldest_live_header) => {
-                    // We have an oldest live header now get the element
-                    Ok(self
-                        .get_element_local_raw(&oldest_live_header.header_hash)?
-                        .map(Search::Found)
-                        // It's not local so check the network
-                        .unwrap_or(Search::Continue(oldest_live_header.header_hash)))
-                }
-                None => Ok(Search::NotInCascade),
+                            && cache_data
+                                .meta
+                                .get_deletes_on_header(&r, header.header_hash.clone())?
+                                .next()?
+                                .is_none()
+                        {
+                            Ok(Some(header))
+                        } else {
+                            Ok(None)
+                        }
+                    })
+                    .min()
+            })
+        })?;
+
+        match oldest_live_header {
+            Some(oldest_live_header) => {
+                // We have an oldest live header now get the element
+                Ok(self
+                    .get_element_local_raw(&oldest_live_header.header_hash)?
+                    .map(Search::Found)
+                    // It's not local so check the network
+                    .unwrap_or(Search::Continue(oldest_live_header.header_hash)))
             }
-        })
+            None => Ok(Search::NotInCascade),
+        }
     }
 
     #[instrument(skip(self, options))]
@@ -798,15 +887,10 @@ where
 
         let cache_data = ok_or_return!(self.cache_data.as_ref(), None);
         let authored_data = ok_or_return!(self.authored_data.as_ref(), None);
-        let env = ok_or_return!(self.env.as_ref(), None);
 
         // Meta Cache and Meta Authored
-        let oldest_live_element = self.get_oldest_live_element(
-            &entry_hash,
-            authored_data,
-            &DbPair::from(cache_data),
-            &env,
-        )?;
+        let oldest_live_element =
+            self.get_oldest_live_element(&entry_hash, authored_data, &DbPair::from(cache_data))?;
 
         // Network
         match oldest_live_element {
@@ -846,38 +930,36 @@ where
         let cache_data = ok_or_return!(self.cache_data.as_ref(), None);
         let integrated_data = ok_or_return!(self.integrated_data.as_ref(), None);
         let authored_data = ok_or_return!(self.authored_data.as_ref(), None);
-        let env = ok_or_return!(self.env.as_ref(), None);
         debug!("in get header");
-        let found_local_delete = fresh_reader!(env, |r| {
-            let in_cache = || {
-                DatabaseResult::Ok({
-                    cache_data
-                        .meta
-                        .get_deletes_on_header(&r, header_hash.clone())?
-                        .next()?
-                        .is_some()
-                })
-            };
-            let in_authored = || {
-                DatabaseResult::Ok({
-                    authored_data
-                        .meta
-                        .get_deletes_on_header(&r, header_hash.clone())?
-                        .next()?
-                        .is_some()
-                })
-            };
-            let in_vault = || {
-                DatabaseResult::Ok({
-                    integrated_data
-                        .meta
-                        .get_deletes_on_header(&r, header_hash.clone())?
-                        .next()?
-                        .is_some()
-                })
-            };
-            DatabaseResult::Ok(in_cache()? || in_authored()? || in_vault()?)
-        })?;
+        // Each store may be in a different env so each check gets its own reader
+        let in_cache = || {
+            fresh_reader!(cache_data.meta.env(), |r| DatabaseResult::Ok(
+                cache_data
+                    .meta
+                    .get_deletes_on_header(&r, header_hash.clone())?
+                    .next()?
+                    .is_some()
+            ))
+        };
+        let in_authored = || {
+            fresh_reader!(authored_data.meta.env(), |r| DatabaseResult::Ok(
+                authored_data
+                    .meta
+                    .get_deletes_on_header(&r, header_hash.clone())?
+                    .next()?
+                    .is_some()
+            ))
+        };
+        let in_vault = || {
+            fresh_reader!(integrated_data.meta.env(), |r| DatabaseResult::Ok(
+                integrated_data
+                    .meta
+                    .get_deletes_on_header(&r, header_hash.clone())?
+                    .next()?
+                    .is_some()
+            ))
+        };
+        let found_local_delete = in_cache()? || in_authored()? || in_vault()?;
         if found_local_delete {
             return Ok(None);
         }
@@ -886,8 +968,7 @@ where
             .await?;
 
         let cache_data = ok_or_return!(self.cache_data.as_ref(), None);
-        let env = ok_or_return!(self.env.as_ref(), None);
-        fresh_reader!(env, |r| {
+        fresh_reader!(cache_data.meta.env(), |r| {
             // Check if header is alive after fetch
             let is_live = cache_data
                 .meta
@@ -1014,6 +1095,136 @@ where
         }
     }
 
+    #[instrument(skip(self, options))]
+    /// Every live [Create] or [Update] header for this [EntryHash],
+    /// along with the deletes on the entry.
+    /// Authorities return all of their headers and deletes in one response,
+    /// which are merged with the authored, integrated and cache stores.
+    /// Headers found in more than one place are only returned once.
+    /// Returns `None` if the entry can't be found.
+    pub async fn get_all_live_headers(
+        &mut self,
+        entry_hash: EntryHash,
+        mut options: GetOptions,
+    ) -> CascadeResult<Option<LiveHeaders>> {
+        options.all_live_headers_with_metadata = true;
+        self.fetch_element_via_entry(entry_hash.clone(), options)
+            .await?;
+        let entry = match self.get_entry_local_raw(&entry_hash)? {
+            Some(entry) => entry.into_content(),
+            None => return Ok(None),
+        };
+
+        fn gather<P: PrefixType, M: MetadataBufT<P>>(
+            db: &DbPair<M, P>,
+            hash: &EntryHash,
+            headers: &mut BTreeSet<TimedHeaderHash>,
+            deletes: &mut BTreeSet<TimedHeaderHash>,
+        ) -> CascadeResult<()> {
+            fresh_reader!(db.meta.env(), |r| {
+                headers.extend(db.meta.get_headers(&r, hash.clone())?.collect::<Vec<_>>()?);
+                deletes.extend(
+                    db.meta
+                        .get_deletes_on_entry(&r, hash.clone())?
+                        .collect::<Vec<_>>()?,
+                );
+                CascadeResult::Ok(())
+            })
+        }
+        // Sets dedup the headers from each store
+        let mut headers = BTreeSet::new();
+        let mut deletes = BTreeSet::new();
+        if let Some(db) = self.authored_data.as_ref() {
+            gather(db, &entry_hash, &mut headers, &mut deletes)?;
+        }
+        if let Some(db) = self.integrated_data.as_ref() {
+            gather(db, &entry_hash, &mut headers, &mut deletes)?;
+        }
+        if let Some(db) = self.cache_data.as_ref() {
+            gather(&DbPair::from(db), &entry_hash, &mut headers, &mut deletes)?;
+        }
+
+        let deletes = self.render_headers(deletes, |h| h == HeaderType::Delete)?;
+        // A header is live if no delete from any store targets it
+        let deleted: HashSet<_> = deletes
+            .iter()
+            .filter_map(|delete| match delete.header() {
+                Header::Delete(delete) => Some(delete.deletes_address.clone()),
+                _ => None,
+            })
+            .collect();
+        let headers = self
+            .render_headers(headers, |h| {
+                h == HeaderType::Update || h == HeaderType::Create
+            })?
+            .into_iter()
+            .filter(|header| !deleted.contains(header.header_address()))
+            .collect();
+        Ok(Some(LiveHeaders {
+            entry,
+            headers,
+            deletes,
+        }))
+    }
+
+    #[instrument(skip(self, options))]
+    /// Follows the chain of updates from this [EntryHash] to the current
+    /// version of the entry.
+    /// At each step the newest update whose header isn't deleted is followed.
+    /// Returns `None` if the entry is missing or the version the chain ends at is dead.
+    /// After [MAX_UPDATE_CHAIN_DEPTH] updates the version reached is returned.
+    pub async fn resolve_update_chain(
+        &mut self,
+        entry_hash: EntryHash,
+        options: GetOptions,
+    ) -> CascadeResult<Option<Entry>> {
+        let mut entry_hash = entry_hash;
+        let mut depth = 0;
+        loop {
+            let details = match self
+                .get_entry_details(entry_hash.clone(), options.clone())
+                .await?
+            {
+                Some(details) => details,
+                None => return Ok(None),
+            };
+            if details.entry_dht_status == EntryDhtStatus::Dead {
+                return Ok(None);
+            }
+            if depth == MAX_UPDATE_CHAIN_DEPTH {
+                warn!(?entry_hash, "Update chain is too long to resolve");
+                return Ok(Some(details.entry));
+            }
+
+            // Newest updates first
+            let mut updates = details.updates;
+            updates.sort_by_key(|update| std::cmp::Reverse(update.header().timestamp()));
+            let mut next = None;
+            for update in updates {
+                let new_entry_hash = match update.header().entry_data() {
+                    Some((new_entry_hash, _)) => new_entry_hash.clone(),
+                    None => continue,
+                };
+                // The update is live if its header isn't deleted
+                if self
+                    .dht_get_header(update.header_address().clone(), options.clone())
+                    .await?
+                    .is_some()
+                {
+                    next = Some(new_entry_hash);
+                    break;
+                }
+            }
+            match next {
+                Some(next) => {
+                    entry_hash = next;
+                    depth += 1;
+                }
+                None => return Ok(Some(details.entry)),
+            }
+        }
+    }
+
     #[instrument(skip(self, key, options))]
     /// Gets an links from the cas or cache depending on it's metadata
     // The default behavior is to skip deleted or replaced entries.
@@ -1023,36 +1234,97 @@ where
         key: &'link LinkMetaKey<'link>,
         options: GetLinksOptions,
     ) -> CascadeResult<Vec<Link>> {
+        Ok(self.dht_get_links_with_freshness(key, options).await?.0)
+    }
+
+    #[instrument(skip(self, key, options))]
+    /// Gets the live links on a base, and how complete they are.
+    ///
+    /// The links this agent authored on the base are always merged with the
+    /// authority's, so they can be seen before they have been published.
+    /// Link adds are deduplicated by their CreateLink hash, and a DeleteLink
+    /// from either source removes the link.
+    /// If the authority can't be reached the links held locally are returned
+    /// as [LinkFreshness::LocalOnly] rather than failing.
+    pub async fn dht_get_links_with_freshness<'link>(
+        &mut self,
+        key: &'link LinkMetaKey<'link>,
+        options: GetLinksOptions,
+    ) -> CascadeResult<(Vec<Link>, LinkFreshness)> {
         // Update the cache from the network
-        self.fetch_links(key.into(), options).await?;
+        let fetched = match self.fetch_links(key.into(), options).await {
+            Ok(fetched) => fetched,
+            Err(CascadeError::NetworkError(e)) => {
+                warn!(msg = "Returning local links as the network failed", ?e);
+                None
+            }
+            Err(e) => return Err(e),
+        };
+
+        let cache_data =
+            ok_or_return!(self.cache_data.as_ref(), (vec![], LinkFreshness::LocalOnly));
+        let authored_data = ok_or_return!(
+            self.authored_data.as_ref(),
+            (vec![], LinkFreshness::LocalOnly)
+        );
+        let (links, authored) = fresh_reader!(cache_data.meta.env(), |r| {
+            fresh_reader!(authored_data.meta.env(), |ra| {
+                // Every link add from either store, deduplicated by its hash
+                let mut link_adds = HashMap::new();
+                let mut authored = HashSet::new();
+                let mut cache_adds = cache_data.meta.get_links_all(&r, key)?;
+                while let Some(link_add) = cache_adds.next()? {
+                    link_adds.insert(link_add.link_add_hash.clone(), link_add);
+                }
+                let mut authored_adds = authored_data.meta.get_links_all(&ra, key)?;
+                while let Some(link_add) = authored_adds.next()? {
+                    authored.insert(link_add.link_add_hash.clone());
+                    link_adds.insert(link_add.link_add_hash.clone(), link_add);
+                }
 
-        let cache_data = ok_or_return!(self.cache_data.as_ref(), vec![]);
-        let authored_data = ok_or_return!(self.authored_data.as_ref(), vec![]);
-        let env = ok_or_return!(self.env.as_ref(), vec![]);
-        fresh_reader!(env, |r| {
-            // Meta Cache
-            // Return any links from the meta cache that don't have removes.
-            Ok(cache_data
-                .meta
-                .get_live_links(&r, key)?
-                .map(|l| Ok(l.into_link()))
-                .chain(
-                    authored_data
+                // A link is removed by a DeleteLink from either store
+                let mut links = Vec::new();
+                for (link_add_hash, link_add) in link_adds {
+                    let cache_removed = cache_data
                         .meta
-                        .get_live_links(&r, key)?
-                        .map(|l| Ok(l.into_link())),
-                )
-                // Need to collect into a Set first to remove
-                // duplicates from authored and cache
-                .collect::<HashSet<_>>()?
-                .into_iter()
-                .collect())
-        })
+                        .get_link_removes_on_link_add(&r, link_add_hash.clone())?
+                        .next()?
+                        .is_some();
+                    let authored_removes = authored_data
+                        .meta
+                        .get_link_removes_on_link_add(&ra, link_add_hash)?
+                        .map(|remove| Ok(remove.header_hash))
+                        .collect::<Vec<_>>()?;
+                    let removed = cache_removed || !authored_removes.is_empty();
+                    authored.extend(authored_removes);
+                    if !removed {
+                        links.push(link_add);
+                    }
+                }
+                DatabaseResult::Ok((links, authored))
+            })
+        })?;
+
+        let freshness = match fetched {
+            None => LinkFreshness::LocalOnly,
+            Some(fetched) if authored.is_subset(&fetched) => LinkFreshness::Complete,
+            Some(_) => LinkFreshness::Merged,
+        };
+
+        // Return the links in their canonical order,
+        // whichever source they came from
+        let mut links: Vec<_> = links.into_iter().map(|l| (timed_link_add(&l), l)).collect();
+        links.sort_by(|(a, _), (b, _)| link_order(a, b));
+        Ok((
+            links.into_iter().map(|(_, l)| l.into_link()).collect(),
+            freshness,
+        ))
     }
 
     #[instrument(skip(self, key, options))]
     /// Return all CreateLink headers
-    /// and DeleteLink headers ordered by time.
+    /// and DeleteLink headers ordered by time,
+    /// with the CreateLink headers in the order of [link_order].
     pub async fn get_link_details<'link>(
         &mut self,
         key: &'link LinkMetaKey<'link>,
@@ -1063,38 +1335,41 @@ where
 
         let cache_data = ok_or_return!(self.cache_data.as_ref(), vec![]);
         let authored_data = ok_or_return!(self.authored_data.as_ref(), vec![]);
-        let env = ok_or_return!(self.env.as_ref(), vec![]);
         // Get the links and collect the CreateLink / DeleteLink hashes by time.
         // Search authored and combine with cache_data
-        let links = fresh_reader!(env, |r| {
-            cache_data
-                .meta
-                .get_links_all(&r, key)?
-                .map(|link_add| {
-                    // Collect the link removes on this link add
-                    let link_removes = cache_data
-                        .meta
-                        .get_link_removes_on_link_add(&r, link_add.link_add_hash.clone())?
-                        .collect::<BTreeSet<_>>()?;
-                    // Return all link removes with this link add
-                    Ok((link_add.link_add_hash, link_removes))
-                })
-                .chain(authored_data.meta.get_links_all(&r, key)?.map(|link_add| {
-                    // Collect the link removes on this link add
-                    let link_removes = authored_data
-                        .meta
-                        .get_link_removes_on_link_add(&r, link_add.link_add_hash.clone())?
-                        .collect::<BTreeSet<_>>()?;
-                    // Return all link removes with this link add
-                    Ok((link_add.link_add_hash, link_removes))
-                }))
-                .collect::<BTreeMap<_, _>>()
+        let links = fresh_reader!(cache_data.meta.env(), |r| {
+            fresh_reader!(authored_data.meta.env(), |ra| {
+                cache_data
+                    .meta
+                    .get_links_all(&r, key)?
+                    .map(|link_add| {
+                        // Collect the link removes on this link add
+                        let link_removes = cache_data
+                            .meta
+                            .get_link_removes_on_link_add(&r, link_add.link_add_hash.clone())?
+                            .collect::<BTreeSet<_>>()?;
+                        // Return all link removes with this link add
+                        Ok((timed_link_add(&link_add), link_removes))
+                    })
+                    .chain(authored_data.meta.get_links_all(&ra, key)?.map(|link_add| {
+                        // Collect the link removes on this link add
+                        let link_removes = authored_data
+                            .meta
+                            .get_link_removes_on_link_add(&ra, link_add.link_add_hash.clone())?
+                            .collect::<BTreeSet<_>>()?;
+                        // Return all link removes with this link add
+                        Ok((timed_link_add(&link_add), link_removes))
+                    }))
+                    .collect::<BTreeMap<_, _>>()
+            })
         })?;
+        let mut links: Vec<_> = links.into_iter().collect();
+        links.sort_by(|(a, _), (b, _)| link_order(a, b));
         // Get the headers from the element stores
         fallible_iterator::convert(links.into_iter().map(Ok))
             .filter_map(|(create_link, delete_links)| {
                 // Get the create link data
-                match self.get_header_local_raw_with_sig(&create_link)? {
+                match self.get_header_local_raw_with_sig(&create_link.header_hash)? {
                     Some(create_link)
                         if create_link.header().header_type() == HeaderType::CreateLink =>
                     {
@@ -1111,6 +1386,42 @@ where
             })
             .collect()
     }
+
+    #[instrument(skip(self, options))]
+    /// Get the hashes of the live entries indexed under a key,
+    /// from the network and the integrated index, in hash order
+    pub async fn get_indexed(
+        &mut self,
+        index_key: &IndexKey,
+        options: GetIndexedOptions,
+    ) -> CascadeResult<Vec<EntryHash>> {
+        let mut entry_hashes = BTreeSet::new();
+        if let Some(network) = self.network.as_mut() {
+            for response in network.get_indexed(index_key.clone(), options).await? {
+                entry_hashes.extend(response.entry_hashes);
+            }
+        }
+        if let Some(integrated_data) = self.integrated_data.as_ref() {
+            fresh_reader!(integrated_data.meta.env(), |r| {
+                integrated_data
+                    .meta
+                    .get_indexed(&r, index_key)?
+                    .for_each(|entry_hash| {
+                        entry_hashes.insert(entry_hash);
+                        Ok(())
+                    })
+            })?;
+        }
+        Ok(entry_hashes.into_iter().collect())
+    }
+}
+
+/// The position of a link in [link_order]
+fn timed_link_add(link_add: &LinkMetaVal) -> TimedHeaderHash {
+    TimedHeaderHash {
+        timestamp: link_add.timestamp,
+        header_hash: link_add.link_add_hash.clone(),
+    }
 }
 
 impl<'a, M: MetadataBufT> From<&'a DbPairMut<'a, M>> for DbPair<'a, M> {
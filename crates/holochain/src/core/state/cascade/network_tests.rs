@@ -1,15 +1,19 @@
+use super::{Cascade, DbPair, DbPairMut};
 use crate::{
     conductor::{dna_store::MockDnaStore, interface::websocket::test::setup_app},
     core::{
         state::{
             element_buf::ElementBuf,
-            metadata::{MetadataBuf, MetadataBufT},
+            metadata::{LinkMetaKey, MetadataBuf, MetadataBufT},
         },
         workflow::integrate_dht_ops_workflow::integrate_single_metadata,
         workflow::produce_dht_ops_workflow::dht_op_light::error::DhtOpConvertResult,
         workflow::CallZomeWorkspace,
     },
-    test_utils::test_network,
+    test_utils::{
+        test_conductor::{test_dna_file, TestConductor},
+        test_network,
+    },
 };
 use ::fixt::prelude::*;
 use fallible_iterator::FallibleIterator;
@@ -22,11 +26,11 @@ use holo_hash::{
 };
 use holochain_p2p::{
     actor::{GetLinksOptions, GetMetaOptions, GetOptions},
-    HolochainP2pCell, HolochainP2pRef,
+    HolochainP2pCell, HolochainP2pRef, MockHolochainP2pCellT,
 };
 use holochain_serialized_bytes::SerializedBytes;
 use holochain_state::{
-    env::{EnvironmentWrite, ReadManager},
+    env::{EnvironmentRead, EnvironmentWrite, ReadManager},
     prelude::{BufferedStore, IntegratedPrefix, WriteManager},
     test_utils::test_cell_env,
 };
@@ -38,6 +42,7 @@ use holochain_types::{
     element::{Element, GetElementResponse, WireElement},
     entry::option_entry_hashed,
     fixt::*,
+    link::{link_order, GetLinksResponse},
     metadata::{MetadataSet, TimedHeaderHash},
     observability,
     test_utils::{fake_agent_pubkey_1, fake_agent_pubkey_2},
@@ -47,8 +52,9 @@ use holochain_wasm_test_utils::TestWasm;
 use holochain_zome_types::{
     element::SignedHeaderHashed,
     header::*,
-    link::Link,
+    link::{Link, LinkFreshness, LinkTag, Links},
     metadata::{Details, EntryDhtStatus},
+    signature::Signature,
 };
 use maplit::btreeset;
 use std::collections::BTreeMap;
@@ -102,7 +108,6 @@ async fn get_updates_cache() {
 }
 
 #[tokio::test(threaded_scheduler)]
-#[ignore]
 async fn get_meta_updates_meta_cache() {
     observability::test_run().ok();
     // Database setup
@@ -118,50 +123,59 @@ async fn get_meta_updates_meta_cache() {
         .next()
         .map(|(h, e)| (h.clone(), e.clone()))
         .unwrap();
+    let entry_hash: EntryHash = match expected.0.hash_type().clone() {
+        hash_type::AnyDht::Entry => expected.0.clone().into(),
+        _ => unreachable!(),
+    };
 
     // Create the cascade
     let mut workspace = CallZomeWorkspace::new(env.clone().into()).unwrap();
     let (network, shutdown) = run_fixt_network(BTreeMap::new(), meta_fixt_store).await;
 
-    let returned = {
-        // Construct the cascade with a network
-        let mut cascade = workspace.cascade(network);
+    // Headers which aren't requested aren't returned or cached
+    let returned = workspace
+        .cascade(network.clone())
+        .fetch_meta(expected.0.clone(), GetMetaOptions::crud())
+        .await
+        .unwrap();
+    assert_eq!(returned.first().unwrap().headers, None);
+    {
+        let reader = env_ref.reader().unwrap();
+        let cached = workspace
+            .meta_cache
+            .get_headers(&reader, entry_hash.clone())
+            .unwrap()
+            .count()
+            .unwrap();
+        assert_eq!(cached, 0);
+    }
 
-        // Create GetMetaOptions
-        let options = GetMetaOptions::default();
+    // Get all the metadata
+    let returned = workspace
+        .cascade(network)
+        .fetch_meta(expected.0.clone(), GetMetaOptions::default())
+        .await
+        .unwrap()
+        .first()
+        .cloned()
+        .unwrap();
 
-        // Call fetch element
-        cascade
-            .fetch_meta(expected.0.clone().into(), options)
-            .await
-            .unwrap()
-            .first()
-            .cloned()
-            .unwrap()
-    };
+    // Check the returned headers are correct
+    let headers = returned.headers.unwrap();
+    assert_eq!(headers.len(), 1);
+    assert_eq!(headers.into_iter().next().unwrap(), expected.1);
 
-    // Check the returned element is correct
-    assert_eq!(returned.headers.len(), 1);
-    assert_eq!(returned.headers.into_iter().next().unwrap(), expected.1);
+    // Check the cache has been updated
     let result = {
         let reader = env_ref.reader().unwrap();
-
-        // Check the cache has been updated
         workspace
             .meta_cache
-            .get_headers(
-                &reader,
-                match expected.0.hash_type().clone() {
-                    hash_type::AnyDht::Entry => expected.0.clone().into(),
-                    _ => unreachable!(),
-                },
-            )
+            .get_headers(&reader, entry_hash)
             .unwrap()
             .collect::<Vec<_>>()
             .unwrap()
     };
-    assert_eq!(result[0], expected.1);
-    assert_eq!(result.len(), 1);
+    assert_eq!(result, vec![expected.1]);
 
     shutdown.clean().await;
 }
@@ -334,6 +348,66 @@ async fn get_from_another_agent() {
     shutdown.await.unwrap();
 }
 
+#[tokio::test(threaded_scheduler)]
+async fn resolve_update_chain_from_another_agent() {
+    observability::test_run().ok();
+    let conductor = TestConductor::new().await;
+    let dna = test_dna_file(vec![TestWasm::Create]).await;
+    let alice = conductor
+        .setup_app("alice", fake_agent_pubkey_1(), &[dna.clone()])
+        .await;
+    let bob = conductor
+        .setup_app("bob", fake_agent_pubkey_2(), &[dna])
+        .await;
+    let (alice, bob) = (&alice.cells()[0], &bob.cells()[0]);
+
+    // Bob commits an entry and updates it twice
+    let first = Post("Bananas are good for you".into());
+    let second = Post("Bananas are bendy".into());
+    let third = Post("Bananas are yellow".into());
+    let first_hash = EntryHash::with_data_sync(&Entry::try_from(first.clone()).unwrap());
+    let first_header = commit_entry(
+        bob.env(),
+        bob.call_data(TestWasm::Create),
+        first.try_into().unwrap(),
+        POST_ID,
+    )
+    .await;
+    let second_header = update_entry(
+        bob.env(),
+        bob.call_data(TestWasm::Create),
+        second.clone().try_into().unwrap(),
+        POST_ID,
+        first_header,
+    )
+    .await;
+    let third_header = update_entry(
+        bob.env(),
+        bob.call_data(TestWasm::Create),
+        third.clone().try_into().unwrap(),
+        POST_ID,
+        second_header,
+    )
+    .await;
+    conductor.consistency().await;
+
+    // Alice follows the chain to the latest version
+    assert_eq!(
+        resolve_post(alice.env(), alice.network().clone(), first_hash.clone()).await,
+        Some(third)
+    );
+
+    // Once the last update is deleted the chain ends at the version before it
+    delete_entry(bob.env(), bob.call_data(TestWasm::Create), third_header).await;
+    conductor.consistency().await;
+    assert_eq!(
+        resolve_post(alice.env(), alice.network().clone(), first_hash).await,
+        Some(second)
+    );
+
+    conductor.shutdown().await;
+}
+
 #[tokio::test(threaded_scheduler)]
 // @todo this is flakey for some reason
 #[ignore]
@@ -493,6 +567,269 @@ async fn get_links_from_another_agent() {
     shutdown.await.unwrap();
 }
 
+#[tokio::test(threaded_scheduler)]
+async fn get_asks_again_when_not_held() {
+    observability::test_run().ok();
+    let test_env = test_cell_env();
+    let env = test_env.env();
+
+    let (element_fixt_store, _) = generate_fixt_store().await;
+    let (hash, expected) = element_fixt_store
+        .iter()
+        .next()
+        .map(|(h, e)| (h.clone(), e.clone()))
+        .unwrap();
+
+    // The first peer asked doesn't cover the header
+    let mut workspace = CallZomeWorkspace::new(env.clone().into()).unwrap();
+    let (network, shutdown) = run_partial_coverage_network(element_fixt_store, 1).await;
+    {
+        let mut cascade = workspace.cascade(network);
+        cascade
+            .fetch_element_via_header(hash.clone(), Default::default())
+            .await
+            .unwrap();
+    }
+    let result = workspace.element_cache.get_element(&hash).unwrap().unwrap();
+    assert_eq!(result.header(), expected.header());
+    shutdown.clean().await;
+
+    // No peer covers the header, so the cascade gives up
+    let mut workspace = CallZomeWorkspace::new(env.clone().into()).unwrap();
+    let (network, shutdown) =
+        run_partial_coverage_network(BTreeMap::new(), super::NOT_HELD_RETRIES + 1).await;
+    {
+        let mut cascade = workspace.cascade(network);
+        cascade
+            .fetch_element_via_header(hash.clone(), Default::default())
+            .await
+            .unwrap();
+    }
+    assert!(workspace
+        .element_cache
+        .get_element(&hash)
+        .unwrap()
+        .is_none());
+    shutdown.clean().await;
+}
+
+/// - Two authorities and the authored store hold overlapping sets of links
+/// - Some of the links were created at the same time
+/// - Fetching the links gives the same bytes whichever order the sources answer in
+#[tokio::test(threaded_scheduler)]
+async fn get_links_order_is_independent_of_sources() {
+    observability::test_run().ok();
+    let base = fixt!(EntryHash);
+    let target = fixt!(EntryHash);
+    let links: Vec<_> = [10, 10, 5, 10, 20, 10]
+        .iter()
+        .enumerate()
+        .map(|(seq, secs)| {
+            let create_link = CreateLink {
+                author: fake_agent_pubkey_1(),
+                timestamp: Timestamp(*secs, 0).into(),
+                header_seq: seq as u32,
+                prev_header: fixt!(HeaderHash),
+                base_address: base.clone(),
+                target_address: target.clone(),
+                zome_id: ZomeId::from(0),
+                tag: LinkTag::new(vec![seq as u8]),
+            };
+            (create_link, fixt!(Signature))
+        })
+        .collect();
+    let authority_a = GetLinksResponse {
+        link_adds: links[0..3].to_vec(),
+        link_removes: vec![],
+    };
+    let authority_b = GetLinksResponse {
+        link_adds: links[2..5].iter().rev().cloned().collect(),
+        link_removes: vec![],
+    };
+    let authored = vec![links[5].clone(), links[1].clone()];
+
+    let forwards = fetch_links_from(
+        vec![authority_a.clone(), authority_b.clone()],
+        authored.clone(),
+        &base,
+    )
+    .await;
+    let backwards = fetch_links_from(
+        vec![authority_b, authority_a],
+        authored.into_iter().rev().collect(),
+        &base,
+    )
+    .await;
+    assert_eq!(forwards, backwards);
+
+    let mut expected: Vec<_> = links
+        .into_iter()
+        .map(|(create_link, _)| {
+            let header = HeaderHashed::from_content_sync(Header::CreateLink(create_link));
+            TimedHeaderHash::from(header)
+        })
+        .collect();
+    expected.sort_by(link_order);
+    let expected: Vec<_> = expected.into_iter().map(|t| t.header_hash).collect();
+    assert_eq!(forwards.1, expected);
+}
+
+/// - This agent links to a base it isn't the authority for,
+///   and deletes a link the authority holds, without publishing either
+/// - Getting the links merges the authored data into the authority's answer
+/// - Without the authority the authored data is returned as LocalOnly
+/// - Once the authority holds the authored data the links are Complete
+#[tokio::test(threaded_scheduler)]
+async fn get_links_merges_unpublished_authored_links() {
+    observability::test_run().ok();
+    let base = fixt!(EntryHash);
+    let create_link = |author, seq: u32| CreateLink {
+        author,
+        timestamp: Timestamp(10 + seq as i64, 0).into(),
+        header_seq: seq,
+        prev_header: fixt!(HeaderHash),
+        base_address: base.clone(),
+        target_address: fixt!(EntryHash),
+        zome_id: ZomeId::from(0),
+        tag: LinkTag::new(vec![seq as u8]),
+    };
+    let hash_of = |header: Header| HeaderHashed::from_content_sync(header).into_hash();
+    // A link the authority already holds, from another agent
+    let theirs = (create_link(fake_agent_pubkey_2(), 0), fixt!(Signature));
+    let deleted = (create_link(fake_agent_pubkey_2(), 1), fixt!(Signature));
+    // Links this agent hasn't published yet
+    let ours = (create_link(fake_agent_pubkey_1(), 2), fixt!(Signature));
+    let delete = (
+        DeleteLink {
+            author: fake_agent_pubkey_1(),
+            timestamp: Timestamp(20, 0).into(),
+            header_seq: 3,
+            prev_header: fixt!(HeaderHash),
+            base_address: base.clone(),
+            link_add_address: hash_of(Header::CreateLink(deleted.0.clone())),
+        },
+        fixt!(Signature),
+    );
+
+    let test_env = test_cell_env();
+    let env: EnvironmentRead = test_env.env().into();
+    let mut element_authored = ElementBuf::authored(env.clone(), true).unwrap();
+    let mut meta_authored = MetadataBuf::authored(env.clone()).unwrap();
+    let mut element_cache = ElementBuf::cache(env.clone()).unwrap();
+    let mut meta_cache = MetadataBuf::cache(env).unwrap();
+    let authored = vec![
+        (Header::CreateLink(ours.0.clone()), ours.1.clone()),
+        (Header::DeleteLink(delete.0.clone()), delete.1.clone()),
+    ];
+    for (header, signature) in authored {
+        let header = HeaderHashed::from_content_sync(header);
+        let element = Element::new(SignedHeaderHashed::with_presigned(header, signature), None);
+        let op_lights = produce_op_lights_from_elements(vec![&element])
+            .await
+            .unwrap();
+        element_authored.put(element.into_inner().0, None).unwrap();
+        for op in op_lights {
+            integrate_single_metadata(op, &element_authored, &mut meta_authored).unwrap();
+        }
+    }
+
+    let key = LinkMetaKey::Base(&base);
+    let before_publish = GetLinksResponse {
+        link_adds: vec![theirs.clone(), deleted.clone()],
+        link_removes: vec![],
+    };
+    let after_publish = GetLinksResponse {
+        link_adds: vec![theirs.clone(), deleted, ours.clone()],
+        link_removes: vec![delete],
+    };
+    let mut results = Vec::new();
+    for answer in vec![None, Some(before_publish), Some(after_publish)] {
+        let mut network = MockHolochainP2pCellT::new();
+        network
+            .expect_get_links()
+            .returning(move |_, _| match &answer {
+                Some(answer) => Ok(vec![answer.clone()]),
+                None => Err(holochain_p2p::HolochainP2pError::other("unreachable")),
+            });
+        let mut cascade = Cascade::empty()
+            .with_authored(DbPair::new(&element_authored, &meta_authored))
+            .with_cache(DbPairMut::new(&mut element_cache, &mut meta_cache))
+            .with_network(network);
+        results.push(
+            cascade
+                .dht_get_links_with_freshness(&key, GetLinksOptions::default())
+                .await
+                .unwrap(),
+        );
+    }
+
+    let targets = |links: &Vec<Link>| {
+        links
+            .iter()
+            .map(|link| link.target.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(targets(&results[0].0), vec![ours.0.target_address.clone()]);
+    assert_eq!(results[0].1, LinkFreshness::LocalOnly);
+    let expected = vec![theirs.0.target_address, ours.0.target_address];
+    assert_eq!(targets(&results[1].0), expected);
+    assert_eq!(results[1].1, LinkFreshness::Merged);
+    assert_eq!(results[2].0, results[1].0);
+    assert_eq!(results[2].1, LinkFreshness::Complete);
+}
+
+/// Get links on a base through a cascade over some authored links
+/// and a network which answers with the given authorities' responses.
+/// Returns the serialized links and the hashes of the link details.
+async fn fetch_links_from(
+    authorities: Vec<GetLinksResponse>,
+    authored: Vec<(CreateLink, Signature)>,
+    base: &EntryHash,
+) -> (SerializedBytes, Vec<HeaderHash>) {
+    let test_env = test_cell_env();
+    let env: EnvironmentRead = test_env.env().into();
+    let mut element_authored = ElementBuf::authored(env.clone(), true).unwrap();
+    let mut meta_authored = MetadataBuf::authored(env.clone()).unwrap();
+    let mut element_cache = ElementBuf::cache(env.clone()).unwrap();
+    let mut meta_cache = MetadataBuf::cache(env).unwrap();
+    for (create_link, signature) in authored {
+        let header = HeaderHashed::from_content_sync(Header::CreateLink(create_link));
+        let element = Element::new(SignedHeaderHashed::with_presigned(header, signature), None);
+        let op_lights = produce_op_lights_from_elements(vec![&element])
+            .await
+            .unwrap();
+        element_authored.put(element.into_inner().0, None).unwrap();
+        for op in op_lights {
+            integrate_single_metadata(op, &element_authored, &mut meta_authored).unwrap();
+        }
+    }
+
+    let mut network = MockHolochainP2pCellT::new();
+    network
+        .expect_get_links()
+        .returning(move |_, _| Ok(authorities.clone()));
+    let mut cascade = Cascade::empty()
+        .with_authored(DbPair::new(&element_authored, &meta_authored))
+        .with_cache(DbPairMut::new(&mut element_cache, &mut meta_cache))
+        .with_network(network);
+    let key = LinkMetaKey::Base(base);
+    let links = cascade
+        .dht_get_links(&key, GetLinksOptions::default())
+        .await
+        .unwrap();
+    let details = cascade
+        .get_link_details(&key, GetLinksOptions::default())
+        .await
+        .unwrap();
+    (
+        Links::from(links).try_into().unwrap(),
+        details
+            .into_iter()
+            .map(|(create_link, _)| create_link.header_address().clone())
+            .collect(),
+    )
+}
+
 struct Shutdown {
     handle: JoinHandle<()>,
     kill: oneshot::Sender<()>,
@@ -566,18 +903,18 @@ async fn run_fixt_network(
                     }
                     GetMeta {
                         dht_hash,
-                        // TODO; Use options
-                        options: _options,
+                        options,
                         respond,
                         ..
                     } => {
                         let header_hash = meta_fixt_store.get(&dht_hash).cloned().unwrap();
                         let metadata = MetadataSet {
-                            headers: btreeset! {header_hash},
-                            deletes: btreeset! {},
-                            updates: btreeset! {},
-                            invalid_headers: btreeset! {},
-                            entry_dht_status: None,
+                            headers: if options.include_headers {
+                                Some(btreeset! {header_hash})
+                            } else {
+                                None
+                            },
+                            ..Default::default()
                         };
                         respond.respond(Ok(async move { Ok(metadata.try_into().unwrap()) }
                             .boxed()
@@ -598,6 +935,55 @@ async fn run_fixt_network(
     )
 }
 
+/// Run a test network handler which responds to the first `not_held` Gets
+/// as a peer which doesn't cover the requested hash, and to the rest
+/// with the corresponding Element from the `element_fixt_store`
+async fn run_partial_coverage_network(
+    element_fixt_store: BTreeMap<HeaderHash, Element>,
+    not_held: usize,
+) -> (HolochainP2pCell, Shutdown) {
+    let (network, mut recv, cell_network) = test_network(None, None).await;
+    let (kill, killed) = tokio::sync::oneshot::channel();
+
+    let handle = tokio::task::spawn({
+        async move {
+            use tokio::stream::StreamExt;
+            let mut killed = killed.into_stream();
+            let mut gets = 0;
+            while let Either::Right((Some(evt), _)) =
+                futures::future::select(killed.next(), recv.next()).await
+            {
+                use holochain_p2p::event::HolochainP2pEvent::*;
+                if let Get {
+                    dht_hash, respond, ..
+                } = evt
+                {
+                    gets += 1;
+                    let response = if gets <= not_held {
+                        GetElementResponse::NotHeld
+                    } else {
+                        let header_hash: HeaderHash = dht_hash.into();
+                        let element = element_fixt_store.get(&header_hash).cloned();
+                        GetElementResponse::GetHeader(
+                            element.map(|e| Box::new(WireElement::from_element(e, None))),
+                        )
+                    };
+                    let response = response.try_into().unwrap();
+                    respond.respond(Ok(async move { Ok(response) }.boxed().into()));
+                }
+            }
+        }
+    });
+    (
+        cell_network,
+        Shutdown {
+            handle,
+            kill,
+            network,
+        },
+    )
+}
+
 async fn generate_fixt_store() -> (
     BTreeMap<HeaderHash, Element>,
     BTreeMap<AnyDhtHash, TimedHeaderHash>,
@@ -660,6 +1046,20 @@ async fn fake_authority<'env>(env: &EnvironmentWrite, hash: AnyDhtHash, call_dat
         .unwrap();
 }
 
+async fn resolve_post(
+    env: &EnvironmentWrite,
+    network: HolochainP2pCell,
+    entry_hash: EntryHash,
+) -> Option<Post> {
+    let mut workspace = CallZomeWorkspace::new(env.clone().into()).unwrap();
+    let mut cascade = workspace.cascade(network);
+    cascade
+        .resolve_update_chain(entry_hash, GetOptions::default())
+        .await
+        .unwrap()
+        .map(|entry| Post::try_from(entry).unwrap())
+}
+
 async fn integrate_to_integrated<C: MetadataBufT<IntegratedPrefix>>(
     element: &Element,
     element_store: &ElementBuf<IntegratedPrefix>,
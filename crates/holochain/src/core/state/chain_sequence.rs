@@ -7,17 +7,27 @@
 ///
 /// When committing the ChainSequence db, a special step is taken to ensure source chain consistency.
 /// If the chain head has moved since the db was created, committing the transaction fails with a special error type.
-use crate::core::state::source_chain::{SourceChainError, SourceChainResult};
+use crate::core::state::{
+    element_buf::ElementBuf,
+    source_chain::{ChainInvalidReason, ForkReport, SourceChainError, SourceChainResult},
+};
 use fallible_iterator::DoubleEndedFallibleIterator;
 use holo_hash::HeaderHash;
 use holochain_state::{
-    buffer::{BufferedStore, KvIntBufFresh, KvIntStore},
-    db::{GetDb, CHAIN_SEQUENCE},
+    buffer::{BufferedStore, KvIntBufFresh, KvIntStore, KvStore},
+    db::{
+        GetDb, CHAIN_SEQUENCE, CHAIN_SEQUENCE_INCOMPLETE_DHT_OPS,
+        CHAIN_SEQUENCE_INCOMPLETE_DHT_OPS_BACKFILL,
+    },
+    env::EnvironmentWrite,
     error::{DatabaseError, DatabaseResult},
     fresh_reader,
     prelude::*,
 };
+use holochain_types::Timestamp;
+use holochain_zome_types::Header;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use tracing::*;
 
 /// A Value in the ChainSequence database.
@@ -30,29 +40,40 @@ pub struct ChainSequenceItem {
 
 type Store = KvIntBufFresh<ChainSequenceItem>;
 
+/// The indices of the items whose DhtOps are incomplete. Only the keys matter.
+type IncompleteStore = KvIntBufFresh<()>;
+
 /// A BufferedStore for interacting with the ChainSequence database
 pub struct ChainSequenceBuf {
     buf: Store,
+    incomplete_dht_ops: IncompleteStore,
     next_index: u32,
     tx_seq: u32,
     current_head: Option<HeaderHash>,
     persisted_head: Option<HeaderHash>,
+    persisted_len: u32,
 }
 
 impl ChainSequenceBuf {
     /// Create a new instance
     pub fn new(env: EnvironmentRead) -> DatabaseResult<Self> {
         let buf: Store = KvIntBufFresh::new(env.clone(), env.get_db(&*CHAIN_SEQUENCE)?);
+        let incomplete_dht_ops: IncompleteStore = KvIntBufFresh::new(
+            env.clone(),
+            env.get_db(&*CHAIN_SEQUENCE_INCOMPLETE_DHT_OPS)?,
+        );
         let (next_index, tx_seq, current_head) =
             fresh_reader!(env, |r| { Self::head_info(buf.store(), &r) })?;
         let persisted_head = current_head.clone();
 
         Ok(ChainSequenceBuf {
             buf,
+            incomplete_dht_ops,
             next_index,
             tx_seq,
             current_head,
             persisted_head,
+            persisted_len: next_index,
         })
     }
 
@@ -92,6 +113,39 @@ impl ChainSequenceBuf {
         self.next_index as usize
     }
 
+    /// How many headers were on the chain when this buffer was created
+    /// or last flushed
+    pub fn persisted_len(&self) -> usize {
+        self.persisted_len as usize
+    }
+
+    /// Take the headers which haven't been flushed yet off the chain,
+    /// from the newest back to `len`. Returns their addresses, newest first.
+    pub fn rewind_to(&mut self, len: usize) -> SourceChainResult<Vec<HeaderHash>> {
+        if len < self.persisted_len() || len > self.len() {
+            return Err(SourceChainError::InvalidCheckpoint {
+                checkpoint: len,
+                persisted_len: self.persisted_len(),
+                len: self.len(),
+            });
+        }
+        let mut rewound = Vec::with_capacity(self.len() - len);
+        while self.len() > len {
+            let i = self.next_index - 1;
+            if let Some(header_address) = self.get(i)? {
+                rewound.push(header_address);
+            }
+            self.buf.delete(i.into())?;
+            self.incomplete_dht_ops.delete(i.into())?;
+            self.next_index = i;
+        }
+        self.current_head = match self.next_index {
+            0 => None,
+            i => self.get(i - 1)?,
+        };
+        Ok(rewound)
+    }
+
     /// Get a header at an index
     pub fn get(&self, i: u32) -> DatabaseResult<Option<HeaderHash>> {
         self.buf
@@ -111,6 +165,7 @@ impl ChainSequenceBuf {
                 dht_transforms_complete: false,
             },
         )?;
+        self.incomplete_dht_ops.put(self.next_index.into(), ())?;
         trace!(self.next_index);
         self.next_index += 1;
         self.current_head = Some(header_address);
@@ -140,14 +195,161 @@ impl ChainSequenceBuf {
         })))
     }
 
+    /// Count the items whose DhtOps are incomplete, by scanning an index of
+    /// their keys rather than the items themselves
+    pub fn count_items_with_incomplete_dht_ops<R: Readable>(
+        &self,
+        r: &R,
+    ) -> SourceChainResult<usize> {
+        if !self.incomplete_dht_ops.is_scratch_fresh() {
+            return Err(SourceChainError::ScratchNotFresh);
+        }
+        Ok(self.incomplete_dht_ops.store().iter(r)?.count()?)
+    }
+
     pub fn complete_dht_op(&mut self, i: u32) -> SourceChainResult<()> {
         if let Some(mut c) = self.buf.get(&i.into())? {
             c.dht_transforms_complete = true;
             self.buf.put(i.into(), c)?;
+            self.incomplete_dht_ops.delete(i.into())?;
         }
         Ok(())
     }
 
+    /// Check the invariant that the sequence is a single chain: no two
+    /// headers may follow the same previous header or claim the same
+    /// position. Returns the earliest fork found, if any.
+    ///
+    /// PERF: This reads every header on the chain.
+    pub fn detect_forks(
+        &self,
+        elements: &ElementBuf<AuthoredPrefix>,
+    ) -> SourceChainResult<Option<ForkReport>> {
+        let mut by_prev: HashMap<Option<HeaderHash>, Vec<(u32, HeaderHash)>> = HashMap::new();
+        let mut by_seq: HashMap<u32, Vec<HeaderHash>> = HashMap::new();
+        for (_, item) in self.persisted_items()? {
+            let header = Self::header_of(elements, &item)?;
+            let header_seq = header.header_seq();
+            by_prev
+                .entry(header.prev_header().cloned())
+                .or_default()
+                .push((header_seq, item.header_address.clone()));
+            by_seq
+                .entry(header_seq)
+                .or_default()
+                .push(item.header_address);
+        }
+        let forks_by_prev = by_prev.into_iter().map(|(_, competing)| ForkReport {
+            header_seq: competing.iter().map(|(seq, _)| *seq).min().unwrap_or(0),
+            competing: competing.into_iter().map(|(_, hash)| hash).collect(),
+        });
+        let forks_by_seq = by_seq
+            .into_iter()
+            .map(|(header_seq, competing)| ForkReport {
+                header_seq,
+                competing,
+            });
+        Ok(forks_by_prev
+            .chain(forks_by_seq)
+            .filter(|fork| fork.competing.len() > 1)
+            .min_by(|a, b| {
+                a.header_seq
+                    .cmp(&b.header_seq)
+                    .then_with(|| a.competing.len().cmp(&b.competing.len()).reverse())
+            }))
+    }
+
+    /// Resolve the earliest fork in the sequence by keeping the branch which
+    /// begins with `keep`, one of the fork's competing headers.
+    ///
+    /// The sequence records of the other branches are removed and the
+    /// remaining records are renumbered. The headers and entries themselves
+    /// are never removed. Returns the fork that was resolved.
+    pub fn resolve_fork_keep(
+        &mut self,
+        elements: &ElementBuf<AuthoredPrefix>,
+        keep: &HeaderHash,
+    ) -> SourceChainResult<ForkReport> {
+        let fork = self
+            .detect_forks(elements)?
+            .filter(|fork| fork.competing.contains(keep))
+            .ok_or_else(|| SourceChainError::NotAForkHead(keep.clone()))?;
+
+        // Anything built on a losing header is also on the losing branch
+        let mut losing: HashSet<HeaderHash> = fork
+            .competing
+            .iter()
+            .filter(|hash| *hash != keep)
+            .cloned()
+            .collect();
+        let mut kept = Vec::new();
+        for (_, item) in self.persisted_items()? {
+            let header = Self::header_of(elements, &item)?;
+            let on_losing_branch = losing.contains(&item.header_address)
+                || header
+                    .prev_header()
+                    .map(|prev| losing.contains(prev))
+                    .unwrap_or(false);
+            if on_losing_branch {
+                losing.insert(item.header_address);
+            } else {
+                kept.push(item);
+            }
+        }
+
+        let len = kept.len() as u32;
+        for (i, item) in (0..len).zip(kept.iter()) {
+            self.buf.put(i.into(), item.clone())?;
+            if item.dht_transforms_complete {
+                self.incomplete_dht_ops.delete(i.into())?;
+            } else {
+                self.incomplete_dht_ops.put(i.into(), ())?;
+            }
+        }
+        for i in len..self.next_index {
+            self.buf.delete(i.into())?;
+            self.incomplete_dht_ops.delete(i.into())?;
+        }
+        self.next_index = len;
+        self.current_head = kept.last().map(|item| item.header_address.clone());
+        Ok(fork)
+    }
+
+    /// Every persisted item, in sequence order
+    fn persisted_items(&self) -> SourceChainResult<Vec<(u32, ChainSequenceItem)>> {
+        if !self.buf.is_scratch_fresh() {
+            return Err(SourceChainError::ScratchNotFresh);
+        }
+        let env = self.buf.env().clone();
+        Ok(fresh_reader!(env, |r| {
+            self.buf
+                .store()
+                .iter(&r)?
+                .map(|(i, item)| Ok((IntKey::from_key_bytes_or_friendly_panic(i).into(), item)))
+                .collect::<Vec<_>>()
+        })?)
+    }
+
+    fn header_of(
+        elements: &ElementBuf<AuthoredPrefix>,
+        item: &ChainSequenceItem,
+    ) -> SourceChainResult<Header> {
+        Ok(elements
+            .get_header(&item.header_address)?
+            .ok_or_else(|| {
+                SourceChainError::InvalidStructure(ChainInvalidReason::MissingHeader(
+                    item.header_address.clone(),
+                ))
+            })?
+            .header()
+            .clone())
+    }
+
+    /// Roughly how many bytes of the sequence are waiting to be flushed
+    pub fn scratch_size(&self) -> usize {
+        self.buf.scratch_size() + self.incomplete_dht_ops.scratch_size()
+    }
+
     /// If this transaction hasn't moved the chain
     /// we don't need to check for as at on write.
     /// This helps avoid failed writes when nothing
@@ -163,7 +365,7 @@ impl BufferedStore for ChainSequenceBuf {
     type Error = SourceChainError;
 
     fn is_clean(&self) -> bool {
-        self.buf.is_clean()
+        self.buf.is_clean() && self.incomplete_dht_ops.is_clean()
     }
 
     /// Commit to the source chain, performing an as-at check and returning a
@@ -185,18 +387,62 @@ impl BufferedStore for ChainSequenceBuf {
                 persisted_head,
             ))
         } else {
-            Ok(self.buf.flush_to_txn_ref(writer)?)
+            self.buf.flush_to_txn_ref(writer)?;
+            self.incomplete_dht_ops.flush_to_txn_ref(writer)?;
+            // The buffer may be used again after flushing in place
+            self.persisted_head = self.current_head.clone();
+            self.persisted_len = self.next_index;
+            Ok(())
         }
     }
 }
 
+/// Build the index of items with incomplete DhtOps for any headers put
+/// before the index existed. This only does any work the first time it is
+/// called for an environment, after which a marker records that the
+/// backfill is complete.
+pub fn backfill_incomplete_dht_ops_index(env: &EnvironmentWrite) -> DatabaseResult<()> {
+    let marker: KvStore<UnitDbKey, Timestamp> =
+        KvStore::new(env.get_db(&*CHAIN_SEQUENCE_INCOMPLETE_DHT_OPS_BACKFILL)?);
+    if fresh_reader!(env, |r| marker.get(&r, &UnitDbKey))?.is_some() {
+        return Ok(());
+    }
+    let items: KvIntStore<ChainSequenceItem> = KvIntStore::new(env.get_db(&*CHAIN_SEQUENCE)?);
+    let incomplete: KvIntStore<()> =
+        KvIntStore::new(env.get_db(&*CHAIN_SEQUENCE_INCOMPLETE_DHT_OPS)?);
+    env.guard().with_commit(|writer| {
+        // Another task may have finished the backfill while we waited to write
+        if marker.get(&*writer, &UnitDbKey)?.is_some() {
+            return Ok(());
+        }
+        let keys = items
+            .iter(&*writer)?
+            .filter_map(|(i, item)| {
+                Ok(Some(IntKey::from_key_bytes_or_friendly_panic(i))
+                    .filter(|_| !item.dht_transforms_complete))
+            })
+            .collect::<Vec<_>>()?;
+        for key in keys {
+            incomplete.put(writer, &key, &())?;
+        }
+        marker.put(writer, &UnitDbKey, &Timestamp::now())
+    })
+}
+
 #[cfg(test)]
 pub mod tests {
 
-    use super::{BufferedStore, ChainSequenceBuf, SourceChainError};
+    use super::{
+        backfill_incomplete_dht_ops_index, BufferedStore, ChainSequenceBuf, ChainSequenceItem,
+        SourceChainError,
+    };
     use crate::core::state::source_chain::SourceChainResult;
-    use holo_hash::HeaderHash;
+    use ::fixt::prelude::*;
+    use fallible_iterator::FallibleIterator;
+    use holo_hash::{fixt::HeaderHashFixturator, HeaderHash};
     use holochain_state::{
+        buffer::KvIntStore,
+        db::CHAIN_SEQUENCE,
         env::{ReadManager, WriteManager},
         error::DatabaseResult,
         prelude::*,
@@ -549,4 +795,81 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn chain_sequence_counts_incomplete_dht_ops() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+        let env = arc.guard();
+
+        {
+            let mut buf = ChainSequenceBuf::new(arc.clone().into())?;
+            for _ in 0..3 {
+                buf.put_header(fixt!(HeaderHash))?;
+            }
+            env.with_commit(|writer| buf.flush_to_txn(writer))?;
+        }
+        {
+            let mut buf = ChainSequenceBuf::new(arc.clone().into())?;
+            let reader = env.reader()?;
+            assert_eq!(buf.count_items_with_incomplete_dht_ops(&reader)?, 3);
+            buf.complete_dht_op(1)?;
+            // The count can't see the scratch space
+            assert_matches!(
+                buf.count_items_with_incomplete_dht_ops(&reader),
+                Err(SourceChainError::ScratchNotFresh)
+            );
+            env.with_commit(|writer| buf.flush_to_txn(writer))?;
+        }
+        {
+            let buf = ChainSequenceBuf::new(arc.clone().into())?;
+            let reader = env.reader()?;
+            assert_eq!(buf.count_items_with_incomplete_dht_ops(&reader)?, 2);
+            let incomplete = buf
+                .get_items_with_incomplete_dht_ops(&reader)?
+                .map(|(i, _)| Ok(i))
+                .collect::<Vec<_>>()?;
+            assert_eq!(incomplete, vec![0, 2]);
+        }
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn backfill_indexes_items_put_before_the_index() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+        let env = arc.guard();
+
+        // Items written the way they were before the index existed
+        let items: KvIntStore<ChainSequenceItem> = KvIntStore::new(arc.get_db(&*CHAIN_SEQUENCE)?);
+        env.with_commit(|writer| {
+            for i in 0..3u32 {
+                let item = ChainSequenceItem {
+                    header_address: fixt!(HeaderHash),
+                    tx_seq: i,
+                    dht_transforms_complete: i == 1,
+                };
+                items.put(writer, &i.into(), &item)?;
+            }
+            DatabaseResult::Ok(())
+        })?;
+        {
+            let buf = ChainSequenceBuf::new(arc.clone().into())?;
+            assert_eq!(buf.count_items_with_incomplete_dht_ops(&env.reader()?)?, 0);
+        }
+
+        backfill_incomplete_dht_ops_index(&arc)?;
+        let buf = ChainSequenceBuf::new(arc.clone().into())?;
+        let reader = env.reader()?;
+        assert_eq!(buf.count_items_with_incomplete_dht_ops(&reader)?, 2);
+
+        // Once backfilled the index isn't rebuilt, so completing an item sticks
+        let mut buf = ChainSequenceBuf::new(arc.clone().into())?;
+        buf.complete_dht_op(0)?;
+        env.with_commit(|writer| buf.flush_to_txn(writer))?;
+        backfill_incomplete_dht_ops_index(&arc)?;
+        let buf = ChainSequenceBuf::new(arc.clone().into())?;
+        assert_eq!(buf.count_items_with_incomplete_dht_ops(&env.reader()?)?, 1);
+        Ok(())
+    }
 }
@@ -2,15 +2,26 @@
 
 use fallible_iterator::FallibleIterator;
 use holo_hash::*;
-use holochain_p2p::dht_arc::DhtArc;
+use holochain_p2p::dht_arc::{DhtArc, MAX_HALF_LENGTH};
 use holochain_serialized_bytes::prelude::*;
 use holochain_state::{
-    buffer::KvBufFresh,
-    db::INTEGRATED_DHT_OPS,
+    buffer::{KvBufFresh, KvStore},
+    db::{INTEGRATED_DHT_OPS, INTEGRATED_DHT_OPS_LOCATIONS, INTEGRATED_DHT_OPS_LOCATIONS_BACKFILL},
+    env::EnvironmentWrite,
     error::{DatabaseError, DatabaseResult},
-    prelude::{BufferedStore, EnvironmentRead, GetDb, Readable},
+    fresh_reader,
+    prelude::{
+        BufKey, BufferedStore, EnvironmentRead, GetDb, KvStoreT, Readable, UnitDbKey, WriteManager,
+        Writer,
+    },
 };
-use holochain_types::{dht_op::DhtOpLight, validate::ValidationStatus, Timestamp};
+use holochain_types::{
+    dht_op::{DhtOpLight, OpRequirements},
+    validate::ValidationStatus,
+    Timestamp,
+};
+use std::convert::TryInto;
+use std::ops::Bound;
 
 /// Database type for AuthoredDhtOps
 /// Buffer for accessing [DhtOp]s that you authored and finding the amount of validation receipts
@@ -28,6 +39,10 @@ pub struct AuthoredDhtOpsValue {
     pub receipt_count: u32,
     /// Time last published, None if never published
     pub last_publish_time: Option<Timestamp>,
+    /// What the entry def of the op's app entry requires of it,
+    /// None if the op has no entry def requirements
+    #[serde(default)]
+    pub requirements: Option<OpRequirements>,
 }
 
 impl AuthoredDhtOpsValue {
@@ -37,6 +52,7 @@ impl AuthoredDhtOpsValue {
             op,
             receipt_count: 0,
             last_publish_time: None,
+            requirements: None,
         }
     }
 }
@@ -48,9 +64,73 @@ pub type IntegrationLimboStore = KvBufFresh<IntegrationLimboKey, IntegrationLimb
 /// [DhtOp]s that have already been integrated
 pub type IntegratedDhtOpsStore = KvBufFresh<DhtOpHash, IntegratedDhtOpsValue>;
 
-/// Buffer that adds query logic to the IntegratedDhtOpsStore
+/// Database type for the location index of IntegratedDhtOps.
+/// Maps each integrated op to the time it was integrated.
+pub type IntegratedDhtOpsLocationsStore = KvBufFresh<DhtOpLocationKey, Timestamp>;
+
+/// Database type for the marker which records that the location index has
+/// been backfilled. It has only one key-value pair, the time the backfill completed.
+pub type IntegratedDhtOpsLocationsBackfillStore = KvStore<UnitDbKey, Timestamp>;
+
+/// The key type for the location index of IntegratedDhtOps:
+/// the big-endian location of the op's basis, followed by the op's hash,
+/// so that a range of locations is a contiguous range of keys.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DhtOpLocationKey(Vec<u8>);
+
+const LOCATION_LEN: usize = 4;
+
+impl DhtOpLocationKey {
+    /// Create the index key for an op whose basis is at this location
+    pub fn new(loc: u32, op_hash: &DhtOpHash) -> Self {
+        let mut bytes = Vec::with_capacity(LOCATION_LEN + op_hash.get_full_bytes().len());
+        bytes.extend_from_slice(&loc.to_be_bytes());
+        bytes.extend_from_slice(op_hash.get_full_bytes());
+        Self(bytes)
+    }
+
+    /// The key which sorts before every key at this location,
+    /// for seeking to the start of a range
+    fn start_of(loc: u32) -> Self {
+        Self(loc.to_be_bytes().to_vec())
+    }
+
+    fn loc_from_bytes(bytes: &[u8]) -> u32 {
+        u32::from_be_bytes(
+            bytes[..LOCATION_LEN]
+                .try_into()
+                .expect("Holochain detected database corruption.\n\nInvalid DhtOpLocationKey"),
+        )
+    }
+
+    fn op_hash_from_bytes(bytes: &[u8]) -> DhtOpHash {
+        DhtOpHash::from_raw_bytes(bytes[LOCATION_LEN..].to_vec())
+    }
+}
+
+impl AsRef<[u8]> for DhtOpLocationKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl BufKey for DhtOpLocationKey {
+    fn from_key_bytes_or_friendly_panic(bytes: &[u8]) -> Self {
+        assert!(
+            bytes.len() > LOCATION_LEN,
+            "Holochain detected database corruption.\n\nInvalid DhtOpLocationKey: expected more than {} bytes but got {}",
+            LOCATION_LEN,
+            bytes.len()
+        );
+        Self(bytes.to_vec())
+    }
+}
+
+/// Buffer that adds query logic to the IntegratedDhtOpsStore,
+/// and keeps the location index of the integrated ops up to date.
 pub struct IntegratedDhtOpsBuf {
     store: IntegratedDhtOpsStore,
+    locations: IntegratedDhtOpsLocationsStore,
 }
 
 impl std::ops::Deref for IntegratedDhtOpsBuf {
@@ -72,7 +152,8 @@ impl BufferedStore for IntegratedDhtOpsBuf {
         &mut self,
         writer: &mut holochain_state::prelude::Writer,
     ) -> Result<(), Self::Error> {
-        self.store.flush_to_txn_ref(writer)
+        self.store.flush_to_txn_ref(writer)?;
+        self.locations.flush_to_txn_ref(writer)
     }
 }
 
@@ -103,8 +184,10 @@ impl IntegratedDhtOpsBuf {
     /// Create a new buffer for the IntegratedDhtOpsStore
     pub fn new(env: EnvironmentRead) -> DatabaseResult<Self> {
         let db = env.get_db(&*INTEGRATED_DHT_OPS).unwrap();
+        let locations = env.get_db(&*INTEGRATED_DHT_OPS_LOCATIONS)?;
         Ok(Self {
-            store: IntegratedDhtOpsStore::new(env, db),
+            store: IntegratedDhtOpsStore::new(env.clone(), db),
+            locations: IntegratedDhtOpsLocationsStore::new(env, locations),
         })
     }
 
@@ -113,6 +196,66 @@ impl IntegratedDhtOpsBuf {
         self.store.get(op_hash)
     }
 
+    /// Put an integrated op, indexing it by the location of its basis
+    pub fn put(&mut self, op_hash: DhtOpHash, value: IntegratedDhtOpsValue) -> DatabaseResult<()> {
+        let key = DhtOpLocationKey::new(value.op.dht_basis().get_loc(), &op_hash);
+        self.locations.put(key, value.when_integrated.clone())?;
+        self.store.put(op_hash, value)
+    }
+
+    /// Delete an integrated op and its location index entry
+    pub fn delete(&mut self, op_hash: DhtOpHash) -> DatabaseResult<()> {
+        if let Some(value) = self.store.get(&op_hash)? {
+            let key = DhtOpLocationKey::new(value.op.dht_basis().get_loc(), &op_hash);
+            self.locations.delete(key)?;
+        }
+        self.store.delete(op_hash)
+    }
+
+    /// Delete all integrated ops and the location index
+    pub fn clear_all(&mut self, writer: &mut Writer) -> DatabaseResult<()> {
+        self.store.clear_all(writer)?;
+        self.locations.clear_all(writer)
+    }
+
+    /// Get the hashes of the ops whose basis is within the arc, using
+    /// the location index, and optionally:
+    /// - from a time (Inclusive)
+    /// - to a time (Exclusive)
+    ///
+    /// The index must have been backfilled with [backfill_location_index]
+    /// to include ops integrated before the index existed.
+    pub fn ops_in_arc<'r, R: Readable>(
+        &'r self,
+        r: &'r R,
+        dht_arc: DhtArc,
+        from: Option<Timestamp>,
+        to: Option<Timestamp>,
+    ) -> DatabaseResult<Box<dyn FallibleIterator<Item = DhtOpHash, Error = DatabaseError> + 'r>>
+    {
+        let mut iter: Box<
+            dyn FallibleIterator<Item = (&'r [u8], Timestamp), Error = DatabaseError> + 'r,
+        > = Box::new(fallible_iterator::empty());
+        for (start, end) in location_ranges(&dht_arc) {
+            let scan = self
+                .locations
+                .iter_from(r, DhtOpLocationKey::start_of(start))?
+                .inspect(|_| {
+                    scan_counter::row_scanned();
+                    Ok(())
+                })
+                .take_while(move |(k, _)| Ok(DhtOpLocationKey::loc_from_bytes(k) <= end));
+            iter = Box::new(iter.chain(scan));
+        }
+        Ok(Box::new(
+            iter.filter(move |(_, when_integrated)| {
+                Ok(from.as_ref().map_or(true, |from| when_integrated >= from)
+                    && to.as_ref().map_or(true, |to| when_integrated < to))
+            })
+            .map(|(k, _)| Ok(DhtOpLocationKey::op_hash_from_bytes(k))),
+        ))
+    }
+
     /// Get ops that match optional queries:
     /// - from a time (Inclusive)
     /// - to a time (Exclusive)
@@ -153,6 +296,81 @@ impl IntegratedDhtOpsBuf {
         ))
     }
 }
+
+/// Split an arc into the inclusive ranges of locations it covers.
+/// An arc which wraps past `u32::MAX` becomes two ranges.
+fn location_ranges(dht_arc: &DhtArc) -> Vec<(u32, u32)> {
+    if dht_arc.half_length == MAX_HALF_LENGTH {
+        return vec![(0, u32::MAX)];
+    }
+    let range = dht_arc.range();
+    if range.is_empty() {
+        return vec![];
+    }
+    match (range.start, range.end) {
+        (Bound::Included(start), Bound::Included(end)) if start <= end => vec![(start, end)],
+        (Bound::Included(start), Bound::Included(end)) => vec![(start, u32::MAX), (0, end)],
+        _ => unreachable!("Arc ranges are either empty or inclusive"),
+    }
+}
+
+/// Build the location index for any ops integrated before the index existed.
+/// This only does any work the first time it is called for an environment,
+/// after which a marker records that the backfill is complete,
+/// so it is cheap to call before every use of the index.
+pub fn backfill_location_index(env: &EnvironmentWrite) -> DatabaseResult<()> {
+    let marker: IntegratedDhtOpsLocationsBackfillStore =
+        KvStore::new(env.get_db(&*INTEGRATED_DHT_OPS_LOCATIONS_BACKFILL)?);
+    if fresh_reader!(env, |r| marker.get(&r, &UnitDbKey))?.is_some() {
+        return Ok(());
+    }
+    let ops: KvStore<DhtOpHash, IntegratedDhtOpsValue> =
+        KvStore::new(env.get_db(&*INTEGRATED_DHT_OPS)?);
+    let locations: KvStore<DhtOpLocationKey, Timestamp> =
+        KvStore::new(env.get_db(&*INTEGRATED_DHT_OPS_LOCATIONS)?);
+    env.guard().with_commit(|writer| {
+        // Another task may have finished the backfill while we waited to write
+        if marker.get(&*writer, &UnitDbKey)?.is_some() {
+            return Ok(());
+        }
+        let entries = ops
+            .iter(&*writer)?
+            .map(|(k, v)| {
+                let op_hash = DhtOpHash::from_raw_bytes(k.to_vec());
+                let key = DhtOpLocationKey::new(v.op.dht_basis().get_loc(), &op_hash);
+                Ok((key, v.when_integrated))
+            })
+            .collect::<Vec<_>>()?;
+        for (key, when_integrated) in entries {
+            locations.put(writer, &key, &when_integrated)?;
+        }
+        marker.put(writer, &UnitDbKey, &Timestamp::now())
+    })
+}
+
+/// Counts the rows of the location index read by range scans on this thread,
+/// so tests can check that queries don't iterate the whole index
+mod scan_counter {
+    #[cfg(test)]
+    thread_local! {
+        static ROWS_SCANNED: std::cell::Cell<usize> = std::cell::Cell::new(0);
+    }
+
+    #[cfg(test)]
+    pub(super) fn row_scanned() {
+        ROWS_SCANNED.with(|c| c.set(c.get() + 1));
+    }
+
+    #[cfg(not(test))]
+    pub(super) fn row_scanned() {}
+
+    /// Reset the count, returning the rows scanned since the last reset
+    #[cfg(test)]
+    pub(super) fn take() -> usize {
+        ROWS_SCANNED.with(|c| c.replace(0))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -286,4 +504,158 @@ mod tests {
             assert_eq!(r.len(), 3);
         }
     }
+
+    /// A basis hash with a chosen location
+    fn basis_at(loc: u32) -> AnyDhtHash {
+        let mut bytes = fixt!(ThirtyTwoBytes);
+        bytes.extend_from_slice(&loc.to_le_bytes());
+        AnyDhtHash::from_raw_bytes_and_type(bytes, hash_type::AnyDht::Entry)
+    }
+
+    fn value_at(loc: u32, when_integrated: Timestamp) -> IntegratedDhtOpsValue {
+        IntegratedDhtOpsValue {
+            validation_status: ValidationStatus::Valid,
+            op: DhtOpLight::RegisterAgentActivity(fixt!(HeaderHash), basis_at(loc)),
+            when_integrated,
+        }
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn ops_in_wrapping_arc_are_range_scanned() {
+        let test_env = test_cell_env();
+        let env = test_env.env();
+        let env_ref = env.guard();
+        let now = Utc::now();
+        let ages_ago: Timestamp = (now - Duration::weeks(5)).into();
+
+        // Centered on 0, this arc covers [u32::MAX - 9, u32::MAX] and [0, 10]
+        let arc = DhtArc::new(0, 11);
+        let inside = [u32::MAX - 9, u32::MAX - 1, u32::MAX, 0, 3, 10];
+        let outside = [u32::MAX - 10, 11, u32::MAX / 2];
+
+        let mut expected = Vec::new();
+        let old = fixt!(DhtOpHash);
+        {
+            let mut buf = IntegratedDhtOpsBuf::new(env.clone().into()).unwrap();
+            for &loc in inside.iter() {
+                let hash = fixt!(DhtOpHash);
+                buf.put(hash.clone(), value_at(loc, now.into())).unwrap();
+                expected.push(hash);
+            }
+            buf.put(old.clone(), value_at(1, ages_ago)).unwrap();
+            for &loc in outside.iter() {
+                buf.put(fixt!(DhtOpHash), value_at(loc, now.into()))
+                    .unwrap();
+            }
+            // Plenty of ops far from the arc
+            for i in 0..100 {
+                buf.put(fixt!(DhtOpHash), value_at(u32::MAX / 4 + i, now.into()))
+                    .unwrap();
+            }
+            env_ref
+                .with_commit(|writer| buf.flush_to_txn(writer))
+                .unwrap();
+        }
+
+        let reader = env_ref.reader().unwrap();
+        let buf = IntegratedDhtOpsBuf::new(env.clone().into()).unwrap();
+
+        scan_counter::take();
+        let mut r = buf
+            .ops_in_arc(&reader, arc, None, None)
+            .unwrap()
+            .collect::<Vec<_>>()
+            .unwrap();
+        let scanned = scan_counter::take();
+        r.sort();
+        let mut all = expected.clone();
+        all.push(old.clone());
+        all.sort();
+        assert_eq!(r, all);
+        // Each scan reads at most one row past the end of its range
+        assert!(scanned <= all.len() + 2, "scanned {} rows", scanned);
+
+        // The index agrees with a full scan
+        let mut full = buf
+            .query(&reader, None, None, Some(arc))
+            .unwrap()
+            .map(|(k, _)| Ok(k))
+            .collect::<Vec<_>>()
+            .unwrap();
+        full.sort();
+        assert_eq!(full, all);
+
+        // Timestamps are filtered
+        let mut r = buf
+            .ops_in_arc(&reader, arc, Some((now - Duration::hours(1)).into()), None)
+            .unwrap()
+            .collect::<Vec<_>>()
+            .unwrap();
+        r.sort();
+        expected.sort();
+        assert_eq!(r, expected);
+        let r = buf
+            .ops_in_arc(&reader, arc, None, Some((now - Duration::hours(1)).into()))
+            .unwrap()
+            .collect::<Vec<_>>()
+            .unwrap();
+        assert_eq!(r, vec![old]);
+
+        // Empty and full arcs
+        assert_eq!(location_ranges(&DhtArc::new(0, 0)), vec![]);
+        assert_eq!(
+            location_ranges(&DhtArc::new(12345, MAX_HALF_LENGTH)),
+            vec![(0, u32::MAX)]
+        );
+        assert_eq!(
+            buf.ops_in_arc(&reader, DhtArc::new(0, MAX_HALF_LENGTH), None, None)
+                .unwrap()
+                .count()
+                .unwrap(),
+            all.len() + outside.len() + 100
+        );
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn location_index_is_backfilled() {
+        let test_env = test_cell_env();
+        let env = test_env.env();
+        let env_ref = env.guard();
+        let now: Timestamp = Utc::now().into();
+
+        // Ops integrated before the index existed
+        let hash = fixt!(DhtOpHash);
+        {
+            let db = env.get_db(&*INTEGRATED_DHT_OPS).unwrap();
+            let mut store = IntegratedDhtOpsStore::new(env.clone().into(), db);
+            store.put(hash.clone(), value_at(42, now.clone())).unwrap();
+            store
+                .put(fixt!(DhtOpHash), value_at(u32::MAX / 2, now))
+                .unwrap();
+            env_ref
+                .with_commit(|writer| store.flush_to_txn(writer))
+                .unwrap();
+        }
+        let arc = DhtArc::new(42, 1);
+        let query = || {
+            let reader = env_ref.reader().unwrap();
+            let buf = IntegratedDhtOpsBuf::new(env.clone().into()).unwrap();
+            buf.ops_in_arc(&reader, arc, None, None)
+                .unwrap()
+                .collect::<Vec<_>>()
+                .unwrap()
+        };
+        assert_eq!(query(), vec![]);
+
+        backfill_location_index(&env).unwrap();
+        assert_eq!(query(), vec![hash.clone()]);
+        let marker: IntegratedDhtOpsLocationsBackfillStore =
+            KvStore::new(env.get_db(&*INTEGRATED_DHT_OPS_LOCATIONS_BACKFILL).unwrap());
+        let reader = env_ref.reader().unwrap();
+        assert!(marker.get(&reader, &UnitDbKey).unwrap().is_some());
+
+        // Running it again is harmless
+        backfill_location_index(&env).unwrap();
+        assert_eq!(query(), vec![hash]);
+    }
 }
@@ -12,12 +12,13 @@ use holo_hash::{EntryHash, HasHash, HeaderHash};
 use holochain_state::{
     buffer::CasBufFreshSync,
     db::{
-        GetDb, ELEMENT_CACHE_ENTRIES, ELEMENT_CACHE_HEADERS, ELEMENT_VAULT_HEADERS,
+        DbName, GetDb, ELEMENT_CACHE_ENTRIES, ELEMENT_CACHE_HEADERS, ELEMENT_VAULT_HEADERS,
         ELEMENT_VAULT_PRIVATE_ENTRIES, ELEMENT_VAULT_PUBLIC_ENTRIES,
     },
     error::{DatabaseError, DatabaseResult},
     exports::SingleStore,
     prelude::*,
+    shared_dht::SharedDhtWrites,
 };
 use holochain_types::{
     element::{Element, ElementGroup, SignedHeader, SignedHeaderHashed},
@@ -41,6 +42,18 @@ where
     public_entries: EntryCas<P>,
     private_entries: Option<EntryCas<P>>,
     headers: HeaderCas<P>,
+    /// Where the writes are journaled, if the headers and public entries
+    /// live in the DHT environment shared by the Cells of a DNA
+    shared: Option<SharedStores>,
+}
+
+/// The Cell environment whose writer journals the writes to the stores of
+/// a shared DHT environment, and the names of those stores
+#[derive(Clone)]
+struct SharedStores {
+    journal_env: EnvironmentRead,
+    public_entries: DbName,
+    headers: DbName,
 }
 
 impl ElementBuf<IntegratedPrefix> {
@@ -48,16 +61,45 @@ impl ElementBuf<IntegratedPrefix> {
     /// The `allow_private` argument allows you to specify whether private
     /// entries should be readable or writeable with this reference.
     /// The vault is constructed with the IntegratedPrefix.
+    ///
+    /// If the Cell shares a DHT environment with the other Cells of its DNA,
+    /// the headers and public entries are kept there, while private entries
+    /// stay in the Cell's own environment.
     pub fn vault(env: EnvironmentRead, allow_private: bool) -> DatabaseResult<Self> {
-        ElementBuf::new_vault(env, allow_private)
+        let mut buf = ElementBuf::new_vault(env.clone(), allow_private)?;
+        if let Some(shared_env) = env.dht_env() {
+            buf.public_entries = CasBufFreshSync::new(
+                shared_env.clone().into(),
+                shared_env.get_db(&*ELEMENT_VAULT_PUBLIC_ENTRIES)?,
+            );
+            buf.headers = CasBufFreshSync::new(
+                shared_env.clone().into(),
+                shared_env.get_db(&*ELEMENT_VAULT_HEADERS)?,
+            );
+            buf.shared = Some(SharedStores {
+                journal_env: env.clone(),
+                public_entries: DbName::ElementVaultPublicEntries,
+                headers: DbName::ElementVaultHeaders,
+            });
+        }
+        Ok(buf)
     }
 
     /// Create a ElementBuf using the Cache databases.
-    /// There is no cache for private entries, so private entries are disallowed
+    /// There is no cache for private entries, so private entries are disallowed.
+    /// The cache lives in the shared DHT environment, if the Cell has one.
     pub fn cache(env: EnvironmentRead) -> DatabaseResult<Self> {
+        let shared = env.dht_env().map(|_| SharedStores {
+            journal_env: env.clone(),
+            public_entries: DbName::ElementCacheEntries,
+            headers: DbName::ElementCacheHeaders,
+        });
+        let env = env.dht_env().cloned().map(Into::into).unwrap_or(env);
         let entries = env.get_db(&*ELEMENT_CACHE_ENTRIES)?;
         let headers = env.get_db(&*ELEMENT_CACHE_HEADERS)?;
-        ElementBuf::new(env, entries, None, headers)
+        let mut buf = ElementBuf::new(env, entries, None, headers)?;
+        buf.shared = shared;
+        Ok(buf)
     }
 }
 
@@ -85,6 +127,15 @@ impl ElementBuf<AuthoredPrefix> {
     }
 }
 
+impl ElementBuf<MigratedPrefix> {
+    /// Create a element buf for the elements authored on the chain of a Dna
+    /// the agent migrated from.
+    /// This reuses the database but is the data is completely separate.
+    pub fn migrated(env: EnvironmentRead, allow_private: bool) -> DatabaseResult<Self> {
+        ElementBuf::new_vault(env, allow_private)
+    }
+}
+
 impl<P> ElementBuf<P>
 where
     P: PrefixType,
@@ -104,6 +155,7 @@ where
             public_entries: CasBufFreshSync::new(env.clone(), public_entries_store),
             private_entries,
             headers: CasBufFreshSync::new(env, headers_store),
+            shared: None,
         })
     }
 
@@ -167,7 +219,7 @@ where
     /// - if it is a public entry, but the entry cannot be found, return error
     /// - if it is a private entry and cannot be found, return error
     /// - if it is a private entry but the private DB is disabled, return None
-    fn get_entry_from_header(&self, header: &Header) -> SourceChainResult<Option<Entry>> {
+    pub fn get_entry_from_header(&self, header: &Header) -> SourceChainResult<Option<Entry>> {
         Ok(match header.entry_data() {
             None => None,
             Some((entry_hash, entry_type)) => {
@@ -251,13 +303,18 @@ where
     pub fn delete(&mut self, header_hash: HeaderHash, entry_hash: Option<EntryHash>) {
         self.headers.delete(header_hash);
         if let Some(entry_hash) = entry_hash {
-            if let Some(db) = self.private_entries.as_mut() {
-                db.delete(entry_hash.clone())
-            }
-            self.public_entries.delete(entry_hash);
+            self.delete_entry(entry_hash);
         }
     }
 
+    /// Delete an entry, leaving any headers which refer to it in place
+    pub fn delete_entry(&mut self, entry_hash: EntryHash) {
+        if let Some(db) = self.private_entries.as_mut() {
+            db.delete(entry_hash.clone())
+        }
+        self.public_entries.delete(entry_hash);
+    }
+
     /// Removes a delete if there was one previously added
     pub fn cancel_delete(&mut self, header_hash: HeaderHash, entry_hash: Option<EntryHash>) {
         self.headers.cancel_delete(header_hash);
@@ -281,6 +338,17 @@ where
         self.private_entries.as_ref()
     }
 
+    /// Roughly how many bytes of headers and entries are waiting to be flushed
+    pub fn scratch_size(&self) -> usize {
+        self.headers.scratch_size()
+            + self.public_entries.scratch_size()
+            + self
+                .private_entries
+                .as_ref()
+                .map(|db| db.scratch_size())
+                .unwrap_or_default()
+    }
+
     #[cfg(test)]
     /// Clear all scratch and db, useful for tests
     pub fn clear_all(&mut self, writer: &mut Writer) -> DatabaseResult<()> {
@@ -309,12 +377,25 @@ impl<P: PrefixType> BufferedStore for ElementBuf<P> {
         if self.is_clean() {
             return Ok(());
         }
-        self.public_entries.flush_to_txn_ref(writer)?;
         if let Some(ref mut db) = self.private_entries {
             db.flush_to_txn_ref(writer)?
         };
-        self.headers.flush_to_txn_ref(writer)?;
-        Ok(())
+        // The writes to a shared env are journaled with the Cell's writer,
+        // so they commit or fail with the rest of the Cell's writes
+        match &self.shared {
+            Some(shared) => {
+                let mut writes = SharedDhtWrites::default();
+                self.public_entries
+                    .journal_to(shared.public_entries.clone(), &mut writes)?;
+                self.headers
+                    .journal_to(shared.headers.clone(), &mut writes)?;
+                writes.journal(&shared.journal_env, writer)
+            }
+            None => {
+                self.public_entries.flush_to_txn_ref(writer)?;
+                self.headers.flush_to_txn_ref(writer)
+            }
+        }
     }
 }
 
@@ -326,7 +407,11 @@ mod tests {
     use holo_hash::*;
     use holochain_keystore::test_keystore::spawn_test_keystore;
     use holochain_keystore::AgentPubKeyExt;
-    use holochain_state::{prelude::*, test_utils::test_cell_env};
+    use holochain_state::{
+        error::{DatabaseError, DatabaseResult},
+        prelude::*,
+        test_utils::{test_cell_env, test_dht_env},
+    };
     use holochain_zome_types::entry_def::EntryVisibility;
 
     #[tokio::test(threaded_scheduler)]
@@ -416,6 +501,118 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn shared_dht_env_shares_only_integrated_public_data() -> anyhow::Result<()> {
+        let keystore = spawn_test_keystore().await?;
+        let test_dht_env = test_dht_env();
+        let alice_test_env = test_cell_env();
+        let bob_test_env = test_cell_env();
+        let alice_env = alice_test_env.env().with_dht_env(test_dht_env.env());
+        let bob_env = bob_test_env.env().with_dht_env(test_dht_env.env());
+
+        let agent_key = AgentPubKey::new_from_pure_entropy(&keystore).await?;
+        let (header_pub, entry_pub) =
+            fake_unique_element(&keystore, agent_key.clone(), EntryVisibility::Public).await?;
+        let (header_priv, entry_priv) =
+            fake_unique_element(&keystore, agent_key.clone(), EntryVisibility::Private).await?;
+        let (header_authored, entry_authored) =
+            fake_unique_element(&keystore, agent_key.clone(), EntryVisibility::Public).await?;
+        let header_pub_hash = header_pub.header_address().clone();
+        let header_priv_hash = header_priv.header_address().clone();
+        let header_authored_hash = header_authored.header_address().clone();
+
+        // Alice integrates a public and a private element and authors another
+        alice_env.guard().with_commit(|txn| {
+            let mut vault = ElementBuf::vault(alice_env.clone().into(), true)?;
+            vault.put(header_pub, Some(entry_pub.clone()))?;
+            vault.put(header_priv, Some(entry_priv.clone()))?;
+            vault.flush_to_txn(txn)?;
+            let mut authored = ElementBuf::authored(alice_env.clone().into(), true)?;
+            authored.put(header_authored, Some(entry_authored))?;
+            authored.flush_to_txn(txn)
+        })?;
+
+        // Bob sees Alice's integrated headers and public entries
+        let bob_vault = ElementBuf::vault(bob_env.clone().into(), true)?;
+        assert!(bob_vault.get_header(&header_pub_hash)?.is_some());
+        assert!(bob_vault.get_header(&header_priv_hash)?.is_some());
+        assert_eq!(
+            bob_vault.get_entry(entry_pub.as_hash()),
+            Ok(Some(entry_pub))
+        );
+        // but not her private entries
+        assert_eq!(bob_vault.get_entry(entry_priv.as_hash()), Ok(None));
+        let alice_vault = ElementBuf::vault(alice_env.clone().into(), true)?;
+        assert_eq!(
+            alice_vault.get_entry(entry_priv.as_hash()),
+            Ok(Some(entry_priv))
+        );
+
+        // Authored data stays with its author
+        let bob_authored = ElementBuf::authored(bob_env.clone().into(), true)?;
+        assert!(bob_authored.get_header(&header_authored_hash)?.is_none());
+        let alice_authored = ElementBuf::authored(alice_env.clone().into(), true)?;
+        assert!(alice_authored.get_header(&header_authored_hash)?.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn shared_dht_env_writes_commit_with_the_cell() -> anyhow::Result<()> {
+        let keystore = spawn_test_keystore().await?;
+        let test_dht_env = test_dht_env();
+        let alice_test_env = test_cell_env();
+        let alice_env = alice_test_env.env().with_dht_env(test_dht_env.env());
+
+        let agent_key = AgentPubKey::new_from_pure_entropy(&keystore).await?;
+        let (header_pub, entry_pub) =
+            fake_unique_element(&keystore, agent_key.clone(), EntryVisibility::Public).await?;
+        let (header_priv, entry_priv) =
+            fake_unique_element(&keystore, agent_key.clone(), EntryVisibility::Private).await?;
+        let header_pub_hash = header_pub.header_address().clone();
+        let header_priv_hash = header_priv.header_address().clone();
+        let put = |vault: &mut ElementBuf| -> DatabaseResult<()> {
+            vault.put(header_pub.clone(), Some(entry_pub.clone()))?;
+            vault.put(header_priv.clone(), Some(entry_priv.clone()))
+        };
+
+        // A failed Cell commit writes nothing to the shared env either
+        let mut vault = ElementBuf::vault(alice_env.clone().into(), true)?;
+        put(&mut vault)?;
+        let lost: DatabaseResult<()> = alice_env.guard().with_commit(|txn| {
+            vault.flush_to_txn(txn)?;
+            Err(DatabaseError::InvalidValue)
+        });
+        assert!(lost.is_err());
+        let vault = ElementBuf::vault(alice_env.clone().into(), true)?;
+        assert!(vault.get_header(&header_pub_hash)?.is_none());
+        assert!(vault.get_header(&header_priv_hash)?.is_none());
+        assert_eq!(vault.get_entry(entry_priv.as_hash()), Ok(None));
+
+        // A Cell commit which stops before its journal is applied,
+        // as in a crash, leaves the shared env untouched
+        let mut vault = ElementBuf::vault(alice_env.clone().into(), true)?;
+        put(&mut vault)?;
+        {
+            let env_ref = alice_env.guard();
+            let mut txn = env_ref.writer_unmanaged()?;
+            vault.flush_to_txn(&mut txn)?;
+            txn.commit()?;
+        }
+        let vault = ElementBuf::vault(alice_env.clone().into(), true)?;
+        assert!(vault.get_header(&header_pub_hash)?.is_none());
+        assert_eq!(vault.get_entry(entry_priv.as_hash()), Ok(Some(entry_priv)));
+
+        // and the journal is replayed when the Cell starts again
+        alice_env.apply_shared_dht_journal()?;
+        alice_env.apply_shared_dht_journal()?;
+        let vault = ElementBuf::vault(alice_env.clone().into(), true)?;
+        assert!(vault.get_header(&header_pub_hash)?.is_some());
+        assert!(vault.get_header(&header_priv_hash)?.is_some());
+        assert_eq!(vault.get_entry(entry_pub.as_hash()), Ok(Some(entry_pub)));
+        Ok(())
+    }
 }
 
 /// Create an ElementBuf with a clone of the scratch
@@ -429,6 +626,7 @@ where
             public_entries: (&other.public_entries).into(),
             private_entries: other.private_entries.as_ref().map(|pe| pe.into()),
             headers: (&other.headers).into(),
+            shared: other.shared.clone(),
         }
     }
 }
@@ -0,0 +1,504 @@
+//! Auditing the integrity of a Cell's persisted Element data.
+//!
+//! The usual read paths treat a hash mismatch in a CAS database as fatal.
+//! The auditor instead reads the raw bytes of every record in the Element
+//! stores and checks that headers and entries still hash to their keys and
+//! that header signatures verify, so corruption can be found and dealt with
+//! before it takes the whole conductor down.
+//!
+//! Corrupt records in the cache are simply removed, since the data can be
+//! fetched from the network again. Corrupt records in the vault (authored,
+//! pending or integrated data) are never deleted, only reported.
+//!
+//! If the vault is intact, the source chain is also checked for forks.
+
+use super::source_chain::{ForkReport, SourceChainBuf, SourceChainResult};
+use holo_hash::EntryHash;
+use holochain_keystore::KeystoreError;
+use holochain_state::{
+    buffer::{KvStore, KvStoreT},
+    db::{
+        DbKey, GetDb, ELEMENT_CACHE_ENTRIES, ELEMENT_CACHE_HEADERS, ELEMENT_VAULT_HEADERS,
+        ELEMENT_VAULT_PRIVATE_ENTRIES, ELEMENT_VAULT_PUBLIC_ENTRIES, INTEGRITY_AUDIT,
+    },
+    env::{EnvironmentRead, EnvironmentWrite},
+    error::DatabaseResult,
+    exports::SingleStore,
+    fresh_reader,
+    prelude::*,
+};
+use holochain_types::{
+    element::{SignedHeader, SignedHeaderHashed, SignedHeaderHashedExt},
+    Timestamp,
+};
+use holochain_zome_types::Entry;
+use serde::{Deserialize, Serialize};
+use tracing::*;
+
+/// How many records are read from a database before yielding to other tasks
+const AUDIT_BATCH_SIZE: usize = 100;
+
+/// Size of a [PrefixHashKey]: prefix length 1 + hash length 36
+const PREFIX_HASH_KEY_SIZE: usize = 37;
+
+/// The database used to store the last [IntegrityAuditReport] of a Cell.
+/// It has only one key-value pair.
+pub type IntegrityAuditDb = KvStore<UnitDbKey, IntegrityAuditReport>;
+
+/// The Element stores which are audited
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AuditedStore {
+    /// Headers in the vault
+    VaultHeaders,
+    /// Public entries in the vault
+    VaultPublicEntries,
+    /// Private entries in the vault
+    VaultPrivateEntries,
+    /// Headers in the cache
+    CacheHeaders,
+    /// Entries in the cache
+    CacheEntries,
+}
+
+impl AuditedStore {
+    const ALL: [AuditedStore; 5] = [
+        AuditedStore::VaultHeaders,
+        AuditedStore::VaultPublicEntries,
+        AuditedStore::VaultPrivateEntries,
+        AuditedStore::CacheHeaders,
+        AuditedStore::CacheEntries,
+    ];
+
+    fn db_key(&self) -> &'static DbKey<SingleStore> {
+        match self {
+            AuditedStore::VaultHeaders => &*ELEMENT_VAULT_HEADERS,
+            AuditedStore::VaultPublicEntries => &*ELEMENT_VAULT_PUBLIC_ENTRIES,
+            AuditedStore::VaultPrivateEntries => &*ELEMENT_VAULT_PRIVATE_ENTRIES,
+            AuditedStore::CacheHeaders => &*ELEMENT_CACHE_HEADERS,
+            AuditedStore::CacheEntries => &*ELEMENT_CACHE_ENTRIES,
+        }
+    }
+
+    fn holds_headers(&self) -> bool {
+        match self {
+            AuditedStore::VaultHeaders | AuditedStore::CacheHeaders => true,
+            _ => false,
+        }
+    }
+
+    /// Whether corrupt records in this store can be deleted, because
+    /// the data is only a copy of what is held elsewhere on the network
+    pub fn is_cache(&self) -> bool {
+        match self {
+            AuditedStore::CacheHeaders | AuditedStore::CacheEntries => true,
+            _ => false,
+        }
+    }
+}
+
+/// The ways a record can fail an audit
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Corruption {
+    /// The key is not a prefixed hash
+    MalformedKey,
+    /// The value can't be deserialized
+    Undecodable,
+    /// The value doesn't hash to its key
+    HashMismatch,
+    /// The header's signature doesn't verify against its author
+    InvalidSignature,
+}
+
+/// A record which failed an audit
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorruptRecord {
+    /// The store the record was found in
+    pub store: AuditedStore,
+    /// The raw database key of the record, including its prefix
+    pub key: Vec<u8>,
+    /// What is wrong with the record
+    pub reason: Corruption,
+}
+
+/// The outcome of auditing all Element stores of a Cell
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IntegrityAuditReport {
+    /// When the audit began
+    pub timestamp: Timestamp,
+    /// How many records were checked
+    pub records_checked: u64,
+    /// Corrupt records found in the cache, which have been deleted
+    pub healed: Vec<CorruptRecord>,
+    /// Corrupt records found in the vault, which have been left in place
+    pub corrupt: Vec<CorruptRecord>,
+    /// The earliest fork found in the source chain
+    #[serde(default)]
+    pub fork: Option<ForkReport>,
+}
+
+impl IntegrityAuditReport {
+    /// The report for a Cell whose source chain was found to have forked
+    /// outside of an audit
+    pub fn forked(fork: ForkReport) -> Self {
+        Self {
+            timestamp: Timestamp::now(),
+            records_checked: 0,
+            healed: Vec::new(),
+            corrupt: Vec::new(),
+            fork: Some(fork),
+        }
+    }
+
+    /// True if no unhealed corruption or fork was found
+    pub fn is_intact(&self) -> bool {
+        self.corrupt.is_empty() && self.fork.is_none()
+    }
+}
+
+/// Audit every Element store of a Cell's environment, deleting any corrupt
+/// cache records, and persist the resulting report.
+///
+/// The stores are read in batches with a fresh reader each time, yielding
+/// between batches, so a large audit doesn't hold a read transaction open or
+/// starve other tasks.
+pub async fn audit_cell_env(env: &EnvironmentWrite) -> SourceChainResult<IntegrityAuditReport> {
+    let mut report = IntegrityAuditReport {
+        timestamp: Timestamp::now(),
+        records_checked: 0,
+        healed: Vec::new(),
+        corrupt: Vec::new(),
+        fork: None,
+    };
+    for store in AuditedStore::ALL.iter() {
+        audit_store(env, *store, &mut report).await?;
+    }
+    // Corrupt headers can't be read to follow the chain
+    if report.corrupt.is_empty() {
+        report.fork = SourceChainBuf::new(env.clone().into())?.detect_forks()?;
+        if let Some(fork) = &report.fork {
+            error!(msg = "Found a fork in the source chain", ?fork);
+        }
+    }
+
+    let db: IntegrityAuditDb = KvStore::new(env.get_db(&*INTEGRITY_AUDIT)?);
+    env.guard()
+        .with_commit(|writer| db.put(writer, &UnitDbKey, &report))?;
+    Ok(report)
+}
+
+/// Get the report of the last audit of a Cell's environment, if any
+pub fn last_audit_report(env: &EnvironmentRead) -> DatabaseResult<Option<IntegrityAuditReport>> {
+    let db: IntegrityAuditDb = KvStore::new(env.get_db(&*INTEGRITY_AUDIT)?);
+    fresh_reader!(env, |r| db.get(&r, &UnitDbKey))
+}
+
+async fn audit_store(
+    env: &EnvironmentWrite,
+    store: AuditedStore,
+    report: &mut IntegrityAuditReport,
+) -> SourceChainResult<()> {
+    // Only raw bytes are read from this store, so the value type is unused
+    let raw: KvStore<UnitDbKey, ()> = KvStore::new(env.get_db(store.db_key())?);
+    let mut last_key: Option<Vec<u8>> = None;
+    loop {
+        let batch: Vec<(Vec<u8>, Vec<u8>)> = fresh_reader!(env, |r| {
+            let batch = raw
+                .iter_raw_from(&r, last_key.as_deref())?
+                // Resuming from the last key would see it twice
+                .filter(|(k, _)| Ok(Some(*k) != last_key.as_deref()))
+                .take(AUDIT_BATCH_SIZE)
+                .map(|(k, v)| Ok((k.to_vec(), v.to_vec())))
+                .collect::<Vec<_>>();
+            batch
+        })?;
+
+        let mut to_heal = Vec::new();
+        for (key, value) in batch.iter() {
+            report.records_checked += 1;
+            if let Some(reason) = check_record(store, key, value).await? {
+                let record = CorruptRecord {
+                    store,
+                    key: key.clone(),
+                    reason,
+                };
+                if store.is_cache() {
+                    warn!(msg = "Deleting corrupt cache record", ?record);
+                    to_heal.push(key.clone());
+                    report.healed.push(record);
+                } else {
+                    error!(msg = "Found corrupt vault record", ?record);
+                    report.corrupt.push(record);
+                }
+            }
+        }
+
+        if !to_heal.is_empty() {
+            env.guard().with_commit(|writer| {
+                for key in to_heal.iter() {
+                    raw.db().delete(writer, key)?;
+                }
+                DatabaseResult::Ok(())
+            })?;
+        }
+
+        if batch.len() < AUDIT_BATCH_SIZE {
+            break;
+        }
+        last_key = batch.last().map(|(k, _)| k.clone());
+        tokio::task::yield_now().await;
+    }
+    Ok(())
+}
+
+async fn check_record(
+    store: AuditedStore,
+    key: &[u8],
+    value: &[u8],
+) -> SourceChainResult<Option<Corruption>> {
+    if key.len() != PREFIX_HASH_KEY_SIZE {
+        return Ok(Some(Corruption::MalformedKey));
+    }
+    let hash_bytes = &key[1..];
+    if store.holds_headers() {
+        let signed_header: SignedHeader = match holochain_serialized_bytes::decode(value) {
+            Ok(signed_header) => signed_header,
+            Err(_) => return Ok(Some(Corruption::Undecodable)),
+        };
+        let signed_header = SignedHeaderHashed::from_content_sync(signed_header);
+        if signed_header.header_address().as_ref() != hash_bytes {
+            return Ok(Some(Corruption::HashMismatch));
+        }
+        match signed_header.validate().await {
+            Ok(()) => Ok(None),
+            Err(KeystoreError::InvalidSignature(_, _)) => Ok(Some(Corruption::InvalidSignature)),
+            Err(e) => Err(e.into()),
+        }
+    } else {
+        let entry: Entry = match holochain_serialized_bytes::decode(value) {
+            Ok(entry) => entry,
+            Err(_) => return Ok(Some(Corruption::Undecodable)),
+        };
+        if EntryHash::with_data_sync(&entry).as_ref() != hash_bytes {
+            return Ok(Some(Corruption::HashMismatch));
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{conductor::dna_store::MockDnaStore, test_utils::setup_app};
+    use holochain_serialized_bytes::SerializedBytes;
+    use holochain_state::fresh_reader_test;
+    use holochain_types::{
+        app::InstalledCell,
+        cell::CellId,
+        dna::{DnaDef, DnaFile},
+        observability,
+        test_utils::{fake_agent_pubkey_1, fake_agent_pubkey_2, fake_entry_hash, fake_header_hash},
+    };
+    use holochain_wasm_test_utils::TestWasm;
+    use holochain_zome_types::header::{Header, InitZomesComplete};
+    use std::convert::TryFrom;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn audit_heals_cache_and_quarantines_vault_corruption() {
+        observability::test_run().ok();
+
+        let dna_file = DnaFile::new(
+            DnaDef {
+                name: "integrity_audit".to_string(),
+                uuid: "7d0f3c1e-5b8a-4e2f-9a61-3c4b2d1e0f9a".to_string(),
+                properties: SerializedBytes::try_from(()).unwrap(),
+                zomes: vec![TestWasm::Create.into()].into(),
+            },
+            vec![TestWasm::Create.into()],
+        )
+        .await
+        .unwrap();
+
+        let alice_cell_id = CellId::new(dna_file.dna_hash().to_owned(), fake_agent_pubkey_1());
+        let alice_installed_cell = InstalledCell::new(alice_cell_id.clone(), "alice_handle".into());
+
+        let mut dna_store = MockDnaStore::new();
+        dna_store.expect_get().return_const(Some(dna_file.clone()));
+        dna_store.expect_add_dnas::<Vec<_>>().return_const(());
+        dna_store.expect_add_entry_defs::<Vec<_>>().return_const(());
+        dna_store.expect_get_entry_def().return_const(None);
+
+        let (_tmpdir, _app_api, handle) = setup_app(
+            vec![("test_app", vec![(alice_installed_cell, None)])],
+            dna_store,
+        )
+        .await;
+
+        let env = handle.get_cell_env(&alice_cell_id).await.unwrap();
+
+        // An entry stored under the hash of a different entry
+        let cache_key = PrefixHashKey::<IntegratedPrefix>::new(&fake_entry_hash(1));
+        let cache: KvStore<PrefixHashKey<IntegratedPrefix>, Entry> =
+            KvStore::new(env.get_db(&*ELEMENT_CACHE_ENTRIES).unwrap());
+        // An entry stored where a header should be
+        let vault_key = PrefixHashKey::<IntegratedPrefix>::new(&fake_header_hash(1));
+        let vault: KvStore<PrefixHashKey<IntegratedPrefix>, Entry> =
+            KvStore::new(env.get_db(&*ELEMENT_VAULT_HEADERS).unwrap());
+        let entry = Entry::Agent(fake_agent_pubkey_2());
+        env.guard()
+            .with_commit(|writer| {
+                cache.put(writer, &cache_key, &entry)?;
+                vault.put(writer, &vault_key, &entry)
+            })
+            .unwrap();
+
+        let report = handle.audit_cell(&alice_cell_id).await.unwrap();
+
+        assert_eq!(
+            report.healed,
+            vec![CorruptRecord {
+                store: AuditedStore::CacheEntries,
+                key: cache_key.as_ref().to_vec(),
+                reason: Corruption::HashMismatch,
+            }]
+        );
+        assert_eq!(
+            report.corrupt,
+            vec![CorruptRecord {
+                store: AuditedStore::VaultHeaders,
+                key: vault_key.as_ref().to_vec(),
+                reason: Corruption::Undecodable,
+            }]
+        );
+
+        // The cache record was deleted but the vault record was not
+        fresh_reader_test!(env, |r| {
+            assert_eq!(cache.get(&r, &cache_key).unwrap(), None);
+            assert!(vault.get_bytes(&r, &vault_key).unwrap().is_some());
+        });
+
+        // The report was persisted
+        assert_eq!(
+            last_audit_report(&env.clone().into()).unwrap(),
+            Some(report.clone())
+        );
+
+        // The cell was quarantined
+        assert!(!handle
+            .list_cell_ids()
+            .await
+            .unwrap()
+            .contains(&alice_cell_id));
+        let state = handle.get_state_from_handle().await.unwrap();
+        assert_eq!(state.quarantined_cells.get(&alice_cell_id), Some(&report));
+
+        let shutdown = handle.take_shutdown_handle().await.unwrap();
+        handle.shutdown().await;
+        shutdown.await.unwrap();
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn audit_quarantines_forked_chain_until_resolved() {
+        observability::test_run().ok();
+
+        let dna_file = DnaFile::new(
+            DnaDef {
+                name: "integrity_audit_fork".to_string(),
+                uuid: "2b9e6f0a-8c3d-4f71-b5e2-6a0d9c4e1f38".to_string(),
+                properties: SerializedBytes::try_from(()).unwrap(),
+                zomes: vec![TestWasm::Create.into()].into(),
+            },
+            vec![TestWasm::Create.into()],
+        )
+        .await
+        .unwrap();
+
+        let alice_cell_id = CellId::new(dna_file.dna_hash().to_owned(), fake_agent_pubkey_1());
+        let alice_installed_cell = InstalledCell::new(alice_cell_id.clone(), "alice_handle".into());
+
+        let mut dna_store = MockDnaStore::new();
+        dna_store.expect_get().return_const(Some(dna_file.clone()));
+        dna_store.expect_add_dnas::<Vec<_>>().return_const(());
+        dna_store.expect_add_entry_defs::<Vec<_>>().return_const(());
+        dna_store.expect_get_entry_def().return_const(None);
+
+        let (_tmpdir, _app_api, handle) = setup_app(
+            vec![("test_app", vec![(alice_installed_cell, None)])],
+            dna_store,
+        )
+        .await;
+
+        let env = handle.get_cell_env(&alice_cell_id).await.unwrap();
+
+        // Write two headers which both follow the chain head
+        let (losing, keeping) = {
+            let mut source_chain = SourceChainBuf::new(env.clone().into()).unwrap();
+            let head = source_chain.chain_head().unwrap().clone();
+            let head_seq = source_chain
+                .get_header(&head)
+                .unwrap()
+                .unwrap()
+                .header()
+                .header_seq();
+            let now = Timestamp::now();
+            let fork_header = |secs| {
+                Header::InitZomesComplete(InitZomesComplete {
+                    author: fake_agent_pubkey_1(),
+                    timestamp: Timestamp(now.0 + secs, 0).into(),
+                    header_seq: head_seq + 1,
+                    prev_header: head.clone(),
+                })
+            };
+            let losing = source_chain.put_raw(fork_header(1), None).await.unwrap();
+            let keeping = source_chain.put_raw(fork_header(2), None).await.unwrap();
+            env.guard()
+                .with_commit(|writer| source_chain.flush_to_txn(writer))
+                .unwrap();
+            (losing, keeping)
+        };
+
+        let report = handle.audit_cell(&alice_cell_id).await.unwrap();
+        let fork = report.fork.clone().expect("The chain has forked");
+        assert_eq!(fork.competing, vec![losing, keeping.clone()]);
+        assert!(!report.is_intact());
+
+        // The cell was quarantined
+        assert!(!handle
+            .list_cell_ids()
+            .await
+            .unwrap()
+            .contains(&alice_cell_id));
+        let state = handle.get_state_from_handle().await.unwrap();
+        assert_eq!(state.quarantined_cells.get(&alice_cell_id), Some(&report));
+
+        // The fork is still visible in the state dump
+        let dump: serde_json::Value =
+            serde_json::from_str(&handle.dump_cell_state(&alice_cell_id).await.unwrap()).unwrap();
+        assert_eq!(dump["fork"], serde_json::to_value(&fork).unwrap());
+
+        assert_eq!(
+            handle
+                .clone()
+                .resolve_fork_keep(&alice_cell_id, &keeping)
+                .await
+                .unwrap(),
+            fork
+        );
+
+        // The resolved chain is intact, so the cell is running again
+        let state = handle.get_state_from_handle().await.unwrap();
+        assert!(state.quarantined_cells.is_empty());
+        assert!(handle
+            .list_cell_ids()
+            .await
+            .unwrap()
+            .contains(&alice_cell_id));
+        let source_chain = SourceChainBuf::new(env.clone().into()).unwrap();
+        assert_eq!(source_chain.detect_forks().unwrap(), None);
+        assert_eq!(source_chain.chain_head(), Some(&keeping));
+        assert!(handle.audit_cell(&alice_cell_id).await.unwrap().is_intact());
+
+        let shutdown = handle.take_shutdown_handle().await.unwrap();
+        handle.shutdown().await;
+        shutdown.await.unwrap();
+    }
+}
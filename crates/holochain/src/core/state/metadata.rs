@@ -11,19 +11,21 @@ use holochain_serialized_bytes::prelude::*;
 use holochain_state::{
     buffer::{KvBufUsed, KvvBufUsed},
     db::{
-        CACHE_LINKS_META, CACHE_STATUS_META, CACHE_SYSTEM_META, META_VAULT_LINKS, META_VAULT_MISC,
-        META_VAULT_SYS,
+        DbName, CACHE_LINKS_META, CACHE_STATUS_META, CACHE_SYSTEM_META, META_VAULT_LINKS,
+        META_VAULT_MISC, META_VAULT_SYS,
     },
     error::{DatabaseError, DatabaseResult},
     fresh_reader,
     prelude::*,
+    shared_dht::SharedDhtWrites,
 };
+use holochain_types::index::{IndexKey, IndexOp};
 use holochain_types::metadata::{EntryDhtStatus, TimedHeaderHash};
 use holochain_types::{header::NewEntryHeader, link::WireLinkMetaKey};
 use holochain_types::{HeaderHashed, Timestamp};
 use holochain_zome_types::header::{self, CreateLink, DeleteLink, ZomeId};
 use holochain_zome_types::{link::LinkTag, Header};
-use std::fmt::Debug;
+use std::{collections::BTreeSet, fmt::Debug};
 use tracing::*;
 
 pub use keys::*;
@@ -117,6 +119,20 @@ where
     /// Deregister a published [Header] on the authoring agent's public key
     fn deregister_activity(&mut self, header: &Header) -> DatabaseResult<()>;
 
+    /// Registers a change to an index we are an authority for.
+    /// A tombstone is kept apart from the row it removes,
+    /// so the two can arrive in either order.
+    fn register_index_op(&mut self, index_op: &IndexOp) -> DatabaseResult<()>;
+
+    /// Registers that the [Entry] a header created was indexed under a key
+    /// that its zome's index_hint callback gave it,
+    /// so the entry's authority can tombstone it when the header is deleted
+    fn register_indexed_header(
+        &mut self,
+        header_hash: &HeaderHash,
+        index_key: IndexKey,
+    ) -> DatabaseResult<()>;
+
     /// Registers a [Header::Update] on the referenced [Header] or [Entry]
     fn register_update(&mut self, update: header::Update) -> DatabaseResult<()>;
 
@@ -172,6 +188,22 @@ where
         entry_hash: EntryHash,
     ) -> DatabaseResult<Box<dyn FallibleIterator<Item = TimedHeaderHash, Error = DatabaseError> + '_>>;
 
+    /// Returns the hashes of the [Entry]s indexed under a key, in hash order.
+    /// An entry drops out of a key once every header creating it
+    /// has been tombstoned.
+    fn get_indexed<'r, R: Readable>(
+        &'r self,
+        r: &'r R,
+        index_key: &IndexKey,
+    ) -> DatabaseResult<Box<dyn FallibleIterator<Item = EntryHash, Error = DatabaseError> + 'r>>;
+
+    /// Returns the keys the [Entry] a header created was indexed under
+    fn get_indexed_header_keys<'r, R: Readable>(
+        &'r self,
+        r: &'r R,
+        header_hash: &HeaderHash,
+    ) -> DatabaseResult<Box<dyn FallibleIterator<Item = IndexKey, Error = DatabaseError> + 'r>>;
+
     /// Returns the current [EntryDhtStatus] of an [Entry]
     fn get_dht_status<'r, R: Readable>(
         &'r self,
@@ -218,22 +250,55 @@ where
     links_meta: KvBufUsed<PrefixBytesKey<P>, LinkMetaVal>,
     misc_meta: KvBufUsed<PrefixBytesKey<P>, MiscMetaValue>,
     env: EnvironmentRead,
+    /// Where the writes are journaled, if the metadata lives in
+    /// the DHT environment shared by the Cells of a DNA
+    shared: Option<SharedStores>,
+}
+
+/// The Cell environment whose writer journals the writes to the stores of
+/// a shared DHT environment, and the names of those stores
+#[derive(Clone)]
+struct SharedStores {
+    journal_env: EnvironmentRead,
+    system_meta: DbName,
+    links_meta: DbName,
+    misc_meta: DbName,
 }
 
 impl MetadataBuf<IntegratedPrefix> {
     /// Create a [MetadataBuf] with the vault databases using the IntegratedPrefix.
     /// The data in the type will be separate from the other prefixes even though the
     /// database is shared.
+    /// If the Cell shares a DHT environment with the other Cells of its DNA,
+    /// the metadata is kept there.
     pub fn vault(env: EnvironmentRead) -> DatabaseResult<Self> {
-        Self::new_vault(env)
-    }
-
-    /// Create a [MetadataBuf] with the cache databases
+        let shared = env.dht_env().map(|_| SharedStores {
+            journal_env: env.clone(),
+            system_meta: DbName::MetaVaultSys,
+            links_meta: DbName::MetaVaultLinks,
+            misc_meta: DbName::MetaVaultMisc,
+        });
+        let mut buf = Self::new_vault(env.dht_env().cloned().map(Into::into).unwrap_or(env))?;
+        buf.shared = shared;
+        Ok(buf)
+    }
+
+    /// Create a [MetadataBuf] with the cache databases.
+    /// The cache lives in the shared DHT environment, if the Cell has one.
     pub fn cache(env: EnvironmentRead) -> DatabaseResult<Self> {
+        let shared = env.dht_env().map(|_| SharedStores {
+            journal_env: env.clone(),
+            system_meta: DbName::MetaCacheSys,
+            links_meta: DbName::MetaCacheLinks,
+            misc_meta: DbName::MetaCacheStatus,
+        });
+        let env = env.dht_env().cloned().map(Into::into).unwrap_or(env);
         let system_meta = env.get_db(&*CACHE_SYSTEM_META)?;
         let links_meta = env.get_db(&*CACHE_LINKS_META)?;
         let misc_meta = env.get_db(&*CACHE_STATUS_META)?;
-        Self::new(env, system_meta, links_meta, misc_meta)
+        let mut buf = Self::new(env, system_meta, links_meta, misc_meta)?;
+        buf.shared = shared;
+        Ok(buf)
     }
 }
 
@@ -279,6 +344,7 @@ where
             links_meta: KvBufUsed::new(links_meta),
             misc_meta: KvBufUsed::new(misc_meta),
             env,
+            shared: None,
         })
     }
 
@@ -344,6 +410,13 @@ where
         )
     }
 
+    /// Roughly how many bytes of metadata are waiting to be flushed
+    pub fn scratch_size(&self) -> usize {
+        self.system_meta.scratch_size()
+            + self.links_meta.scratch_size()
+            + self.misc_meta.scratch_size()
+    }
+
     #[cfg(test)]
     pub fn clear_all(&mut self, writer: &mut Writer) -> DatabaseResult<()> {
         self.links_meta.clear_all(writer)?;
@@ -520,6 +593,40 @@ where
         self.misc_meta.delete(MiscMetaKey::chain_item(&key).into())
     }
 
+    fn register_index_op(&mut self, index_op: &IndexOp) -> DatabaseResult<()> {
+        match index_op {
+            IndexOp::Register {
+                index_key,
+                entry_hash,
+                header_hash,
+            } => {
+                let key = IndexItemKey::Full(index_key, entry_hash, header_hash);
+                let key = MiscMetaKey::index(&key).into();
+                self.misc_meta
+                    .put(key, MiscMetaValue::Index(entry_hash.clone()))
+            }
+            IndexOp::Tombstone {
+                index_key,
+                entry_hash,
+                header_hash,
+            } => {
+                let key = IndexItemKey::Full(index_key, entry_hash, header_hash);
+                let key = MiscMetaKey::index_tombstone(&key).into();
+                self.misc_meta.put(key, MiscMetaValue::IndexTombstone)
+            }
+        }
+    }
+
+    fn register_indexed_header(
+        &mut self,
+        header_hash: &HeaderHash,
+        index_key: IndexKey,
+    ) -> DatabaseResult<()> {
+        let key = MiscMetaKey::indexed_header(header_hash, Some(&index_key)).into();
+        self.misc_meta
+            .put(key, MiscMetaValue::IndexedHeader(index_key))
+    }
+
     fn get_headers<'r, R: Readable>(
         &'r self,
         r: &'r R,
@@ -618,6 +725,44 @@ where
         )))
     }
 
+    fn get_indexed<'r, R: Readable>(
+        &'r self,
+        r: &'r R,
+        index_key: &IndexKey,
+    ) -> DatabaseResult<Box<dyn FallibleIterator<Item = EntryHash, Error = DatabaseError> + 'r>>
+    {
+        let k = MiscMetaKey::index(&IndexItemKey::Key(index_key)).into();
+        // There is a row for each header creating an entry,
+        // and the entry is indexed while any of them isn't tombstoned
+        let entry_hashes = self
+            .misc_meta
+            .iter_all_key_matches(r, k)?
+            .filter(|(k, _)| {
+                let k: MiscMetaKey<IndexPrefix> =
+                    PrefixBytesKey::<P>::from_key_bytes_or_friendly_panic(k).into();
+                Ok(self.misc_meta.get(r, &k.tombstone().into())?.is_none())
+            })
+            .map(|(_, v)| Ok(MiscMetaValue::index(v)))
+            .collect::<BTreeSet<_>>()?;
+        Ok(Box::new(fallible_iterator::convert(
+            entry_hashes.into_iter().map(Ok),
+        )))
+    }
+
+    fn get_indexed_header_keys<'r, R: Readable>(
+        &'r self,
+        r: &'r R,
+        header_hash: &HeaderHash,
+    ) -> DatabaseResult<Box<dyn FallibleIterator<Item = IndexKey, Error = DatabaseError> + 'r>>
+    {
+        let k = MiscMetaKey::indexed_header(header_hash, None).into();
+        Ok(Box::new(
+            self.misc_meta
+                .iter_all_key_matches(r, k)?
+                .map(|(_, v)| Ok(MiscMetaValue::indexed_header(v))),
+        ))
+    }
+
     // TODO: For now this is only checking for deletes
     // Once the validation is finished this should check for that as well
     fn get_dht_status<'r, R: Readable>(
@@ -692,6 +837,18 @@ impl<P: PrefixType> BufferedStore for MetadataBuf<P> {
     type Error = DatabaseError;
 
     fn flush_to_txn_ref(&mut self, writer: &mut Writer) -> DatabaseResult<()> {
+        // The writes to a shared env are journaled with the Cell's writer,
+        // as for the ElementBuf
+        if let Some(shared) = &self.shared {
+            let mut writes = SharedDhtWrites::default();
+            self.system_meta
+                .journal_to(shared.system_meta.clone(), &mut writes)?;
+            self.links_meta
+                .journal_to(shared.links_meta.clone(), &mut writes)?;
+            self.misc_meta
+                .journal_to(shared.misc_meta.clone(), &mut writes)?;
+            return writes.journal(&shared.journal_env, writer);
+        }
         self.system_meta.flush_to_txn_ref(writer)?;
         self.links_meta.flush_to_txn_ref(writer)?;
         self.misc_meta.flush_to_txn_ref(writer)?;
@@ -710,6 +867,7 @@ where
             links_meta: (&other.links_meta).into(),
             misc_meta: (&other.misc_meta).into(),
             env: other.env.clone(),
+            shared: other.shared.clone(),
         }
     }
 }
@@ -94,6 +94,16 @@ pub enum ChainItemKey {
     Full(AgentPubKey, u32, HeaderHash),
 }
 
+/// To allow partial matching of all the entries
+/// indexed under a key, in the same way as the [ChainItemKey]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum IndexItemKey<'a> {
+    /// Match all the entries indexed under this key
+    Key(&'a IndexKey),
+    /// Match this entry, as created by this header, under this key
+    Full(&'a IndexKey, &'a EntryHash, &'a HeaderHash),
+}
+
 impl LinkMetaVal {
     /// Turn into a zome friendly type
     pub fn into_link(self) -> holochain_zome_types::link::Link {
@@ -335,6 +345,29 @@ impl From<&ChainItemKey> for BytesKey {
     }
 }
 
+impl From<&IndexItemKey<'_>> for BytesKey {
+    fn from(key: &IndexItemKey) -> Self {
+        use byteorder::{NativeEndian, WriteBytesExt};
+        let index_key = match key {
+            IndexItemKey::Key(k) | IndexItemKey::Full(k, _, _) => k,
+        };
+        let mut buf = vec![
+            u8::from(index_key.zome_id),
+            u8::from(index_key.entry_def_index),
+        ];
+        // The length comes first so that a key
+        // never matches the keys it is a prefix of
+        buf.write_u32::<NativeEndian>(index_key.key.len() as u32)
+            .unwrap();
+        buf.extend(&index_key.key);
+        if let IndexItemKey::Full(_, entry_hash, header_hash) = key {
+            buf.extend(entry_hash.as_ref());
+            buf.extend(header_hash.as_ref());
+        }
+        buf.into()
+    }
+}
+
 // TODO: This is way to fragile there must be a better way
 // get from the k bytes to the chain item key
 impl From<BytesKey> for ChainItemKey {
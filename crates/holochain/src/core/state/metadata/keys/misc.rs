@@ -30,6 +30,24 @@ impl PrefixType for ChainItemPrefix {
     const PREFIX: u8 = 0x2;
 }
 
+#[derive(PartialOrd, Clone, Ord, PartialEq, Eq, Debug)]
+pub struct IndexPrefix;
+impl PrefixType for IndexPrefix {
+    const PREFIX: u8 = 0x3;
+}
+
+#[derive(PartialOrd, Clone, Ord, PartialEq, Eq, Debug)]
+pub struct IndexTombstonePrefix;
+impl PrefixType for IndexTombstonePrefix {
+    const PREFIX: u8 = 0x4;
+}
+
+#[derive(PartialOrd, Clone, Ord, PartialEq, Eq, Debug)]
+pub struct IndexedHeaderPrefix;
+impl PrefixType for IndexedHeaderPrefix {
+    const PREFIX: u8 = 0x5;
+}
+
 impl<P: PrefixType> MiscMetaKey<P> {
     /// Create a new prefix bytes key
     pub fn new<I: IntoIterator<Item = u8>>(bytes: I) -> Self {
@@ -55,6 +73,12 @@ pub enum MiscMetaValue {
     /// There is a header at this key.
     /// We store the timestamp so headers can be ordered.
     ChainItem(Timestamp),
+    /// An entry is indexed under this key
+    Index(EntryHash),
+    /// The header which created the entry indexed at this key is deleted
+    IndexTombstone,
+    /// The entry this header created was indexed under this key
+    IndexedHeader(IndexKey),
 }
 
 impl MiscMetaKey<EntryStatusPrefix> {
@@ -83,6 +107,42 @@ impl MiscMetaKey<ChainItemPrefix> {
     }
 }
 
+impl MiscMetaKey<IndexPrefix> {
+    /// Create an index key
+    pub fn index(key: &IndexItemKey) -> MiscMetaKey<IndexPrefix> {
+        let bytes: BytesKey = key.into();
+        MiscMetaKey::new(bytes.0.into_iter())
+    }
+
+    /// The key of the tombstone for this index row
+    pub fn tombstone(&self) -> MiscMetaKey<IndexTombstonePrefix> {
+        MiscMetaKey::new(self.without_prefix().iter().copied())
+    }
+}
+
+impl MiscMetaKey<IndexTombstonePrefix> {
+    /// Create an index tombstone key
+    pub fn index_tombstone(key: &IndexItemKey) -> MiscMetaKey<IndexTombstonePrefix> {
+        MiscMetaKey::index(key).tombstone()
+    }
+}
+
+impl MiscMetaKey<IndexedHeaderPrefix> {
+    /// Create a key for a header whose entry is indexed,
+    /// or for all its keys if there is no index key
+    pub fn indexed_header(
+        hash: &HeaderHash,
+        index_key: Option<&IndexKey>,
+    ) -> MiscMetaKey<IndexedHeaderPrefix> {
+        let mut bytes = hash.as_ref().to_vec();
+        if let Some(index_key) = index_key {
+            let key: BytesKey = (&IndexItemKey::Key(index_key)).into();
+            bytes.extend(key.0);
+        }
+        MiscMetaKey::new(bytes.into_iter())
+    }
+}
+
 impl<PM, PB> From<MiscMetaKey<PM>> for PrefixBytesKey<PB>
 where
     PM: PrefixType,
@@ -121,6 +181,20 @@ impl MiscMetaValue {
         }
     }
 
+    pub fn index(self) -> EntryHash {
+        match self {
+            MiscMetaValue::Index(h) => h,
+            _ => unreachable!("Tried to go from {:?} to {:?}", self, "index"),
+        }
+    }
+
+    pub fn indexed_header(self) -> IndexKey {
+        match self {
+            MiscMetaValue::IndexedHeader(k) => k,
+            _ => unreachable!("Tried to go from {:?} to {:?}", self, "indexed_header"),
+        }
+    }
+
     pub fn new_store_element() -> Self {
         Self::StoreElement
     }
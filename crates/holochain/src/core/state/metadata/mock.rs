@@ -33,6 +33,16 @@ mock! {
         fn register_raw_on_header(&mut self, header_hash: HeaderHash, value: SysMetaVal);
         fn sync_deregister_add_link(&mut self, link_add: CreateLink) -> DatabaseResult<()>;
         fn sync_deregister_delete_link(&mut self, link_remove: DeleteLink) -> DatabaseResult<()>;
+        fn sync_register_index_op(&mut self, index_op: &IndexOp) -> DatabaseResult<()>;
+        fn sync_register_indexed_header(&mut self, header_hash: &HeaderHash, index_key: IndexKey) -> DatabaseResult<()>;
+        fn get_indexed(
+            &self,
+            index_key: &IndexKey,
+        ) -> DatabaseResult<Box<dyn FallibleIterator<Item = EntryHash, Error = DatabaseError>>>;
+        fn get_indexed_header_keys(
+            &self,
+            header_hash: &HeaderHash,
+        ) -> DatabaseResult<Box<dyn FallibleIterator<Item = IndexKey, Error = DatabaseError>>>;
         fn get_dht_status(&self, entry_hash: &EntryHash) -> DatabaseResult<EntryDhtStatus>;
         fn get_canonical_entry_hash(&self, entry_hash: EntryHash) -> DatabaseResult<EntryHash>;
         fn get_canonical_header_hash(&self, header_hash: HeaderHash) -> DatabaseResult<HeaderHash>;
@@ -99,6 +109,24 @@ impl MetadataBufT for MockMetadataBuf {
         MockMetadataBuf::get_dht_status(&self, entry_hash)
     }
 
+    fn get_indexed<'r, R: Readable>(
+        &'r self,
+        _r: &'r R,
+        index_key: &IndexKey,
+    ) -> DatabaseResult<Box<dyn FallibleIterator<Item = EntryHash, Error = DatabaseError> + 'r>>
+    {
+        MockMetadataBuf::get_indexed(&self, index_key)
+    }
+
+    fn get_indexed_header_keys<'r, R: Readable>(
+        &'r self,
+        _r: &'r R,
+        header_hash: &HeaderHash,
+    ) -> DatabaseResult<Box<dyn FallibleIterator<Item = IndexKey, Error = DatabaseError> + 'r>>
+    {
+        MockMetadataBuf::get_indexed_header_keys(&self, header_hash)
+    }
+
     fn get_canonical_header_hash(&self, header_hash: HeaderHash) -> DatabaseResult<HeaderHash> {
         self.get_canonical_header_hash(header_hash)
     }
@@ -195,6 +223,18 @@ impl MetadataBufT for MockMetadataBuf {
         self.sync_deregister_activity(header)
     }
 
+    fn register_index_op(&mut self, index_op: &IndexOp) -> DatabaseResult<()> {
+        self.sync_register_index_op(index_op)
+    }
+
+    fn register_indexed_header(
+        &mut self,
+        header_hash: &HeaderHash,
+        index_key: IndexKey,
+    ) -> DatabaseResult<()> {
+        self.sync_register_indexed_header(header_hash, index_key)
+    }
+
     fn deregister_update(&mut self, update: header::Update) -> DatabaseResult<()> {
         self.sync_deregister_update(update)
     }
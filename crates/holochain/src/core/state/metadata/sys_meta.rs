@@ -19,6 +19,7 @@ mod tests {
     use holochain_types::{
         fixt::{AppEntryTypeFixturator, HeaderBuilderCommonFixturator},
         header::NewEntryHeader,
+        index::{IndexKey, IndexOp},
         HeaderHashed,
     };
     use holochain_zome_types::header::{self, builder, EntryType, HeaderBuilder};
@@ -744,4 +745,80 @@ mod tests {
             .unwrap();
         assert_eq!(status, EntryDhtStatus::Dead);
     }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn index_drops_entries_once_every_header_is_tombstoned() {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+        let env = arc.guard();
+        let mut fx = TestFixtures::new();
+        let entry_hash = fx.entry_hash();
+        let (header_a, header_b) = (fx.header_hash(), fx.header_hash());
+        let app_entry_type = fixt!(AppEntryType);
+        let index_key = IndexKey::new(&app_entry_type, b"topic".to_vec());
+        let longer_key = IndexKey::new(&app_entry_type, b"topics".to_vec());
+        let reader = env.reader().unwrap();
+        let mut meta_buf = MetadataBuf::vault(arc.clone().into()).unwrap();
+        let get_indexed = |meta_buf: &MetadataBuf, index_key| {
+            meta_buf
+                .get_indexed(&reader, index_key)
+                .unwrap()
+                .collect::<Vec<_>>()
+                .unwrap()
+        };
+        let register = |header_hash: &HeaderHash| IndexOp::Register {
+            index_key: index_key.clone(),
+            entry_hash: entry_hash.clone(),
+            header_hash: header_hash.clone(),
+        };
+        let tombstone = |header_hash: &HeaderHash| IndexOp::Tombstone {
+            index_key: index_key.clone(),
+            entry_hash: entry_hash.clone(),
+            header_hash: header_hash.clone(),
+        };
+
+        // A tombstone which arrives first still removes its row
+        meta_buf.register_index_op(&tombstone(&header_b)).unwrap();
+        meta_buf.register_index_op(&register(&header_a)).unwrap();
+        meta_buf.register_index_op(&register(&header_b)).unwrap();
+        // The entry is returned once for both headers
+        assert_eq!(get_indexed(&meta_buf, &index_key), vec![entry_hash.clone()]);
+        // Keys must match exactly
+        assert!(get_indexed(&meta_buf, &longer_key).is_empty());
+
+        meta_buf.register_index_op(&tombstone(&header_a)).unwrap();
+        assert!(get_indexed(&meta_buf, &index_key).is_empty());
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn indexed_header_keys_are_found_by_header() {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+        let env = arc.guard();
+        let mut fx = TestFixtures::new();
+        let (header_a, header_b) = (fx.header_hash(), fx.header_hash());
+        let app_entry_type = fixt!(AppEntryType);
+        let topic = IndexKey::new(&app_entry_type, b"topic".to_vec());
+        let author = IndexKey::new(&app_entry_type, b"author".to_vec());
+        let reader = env.reader().unwrap();
+        let mut meta_buf = MetadataBuf::vault(arc.clone().into()).unwrap();
+
+        meta_buf
+            .register_indexed_header(&header_a, topic.clone())
+            .unwrap();
+        meta_buf
+            .register_indexed_header(&header_a, author.clone())
+            .unwrap();
+        meta_buf
+            .register_indexed_header(&header_b, topic.clone())
+            .unwrap();
+
+        let mut keys = meta_buf
+            .get_indexed_header_keys(&reader, &header_a)
+            .unwrap()
+            .collect::<Vec<_>>()
+            .unwrap();
+        keys.sort_by(|a, b| a.key.cmp(&b.key));
+        assert_eq!(keys, vec![author, topic]);
+    }
 }
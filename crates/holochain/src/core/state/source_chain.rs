@@ -9,7 +9,7 @@ use holo_hash::*;
 use holochain_state::{buffer::BufferedStore, error::DatabaseResult, fresh_reader, prelude::*};
 use holochain_types::{prelude::*, EntryHashed};
 use holochain_zome_types::{
-    capability::{CapAccess, CapGrant, CapSecret, GrantedFunction},
+    capability::{CapAccess, CapClaim, CapGrant, CapSecret, GrantedFunction},
     element::Element,
     entry::{CapClaimEntry, Entry},
     header::{builder, EntryType, Header, HeaderBuilder, HeaderBuilderCommon, HeaderInner},
@@ -49,11 +49,19 @@ impl SourceChain {
         Ok(SourceChainBuf::public_only(env)?.into())
     }
 
+    /// See [SourceChainBuf::read_only]
+    pub fn read_only(env: EnvironmentRead) -> DatabaseResult<Self> {
+        Ok(SourceChainBuf::read_only(env)?.into())
+    }
+
     pub fn into_inner(self) -> SourceChainBuf {
         self.0
     }
 
-    /// Add a Element to the source chain, using a HeaderBuilder
+    /// Add a Element to the source chain, using a HeaderBuilder.
+    /// The header is timestamped by the chain's clock, and is refused with
+    /// [SourceChainError::NonMonotonicTimestamp] if that isn't later than
+    /// the chain head's timestamp.
     pub async fn put<H: HeaderInner, B: HeaderBuilder<H>>(
         &mut self,
         header_builder: B,
@@ -61,7 +69,7 @@ impl SourceChain {
     ) -> SourceChainResult<HeaderHash> {
         let common = HeaderBuilderCommon {
             author: self.agent_pubkey()?,
-            timestamp: Timestamp::now().into(),
+            timestamp: self.clock().now().into(),
             header_seq: self.len() as u32,
             prev_header: self.chain_head()?.to_owned(),
         };
@@ -241,6 +249,74 @@ impl SourceChain {
         Ok(committed_valid_grant)
     }
 
+    /// Fetch the most recent CapClaim for calling a function on the given grantor.
+    ///
+    /// A claim is superseded by any newer claim with the same tag, so committing a new claim
+    /// under an old tag is how a rotated secret replaces the old one. Claims whose headers have
+    /// been updated or deleted are skipped too.
+    ///
+    /// NB: [B-01676] the entry must be persisted for this to work, as with `valid_cap_grant`.
+    pub fn latest_cap_claim(
+        &self,
+        grantor: &AgentPubKey,
+        check_function: &GrantedFunction,
+    ) -> SourceChainResult<Option<CapClaim>> {
+        let mut live_claims = fresh_reader!(self.env(), |r| {
+            let (references, claim_headers): (HashSet<HeaderHash>, Vec<_>) =
+                self.0.headers().iter_fail(&r)?.fold(
+                    (HashSet::new(), vec![]),
+                    |(mut references, mut claim_headers), header| {
+                        match header.as_content().header() {
+                            Header::Create(create) if create.entry_type == EntryType::CapClaim => {
+                                claim_headers.push((
+                                    header.as_hash().clone(),
+                                    create.header_seq,
+                                    create.entry_hash.clone(),
+                                ));
+                            }
+                            Header::Update(update) => {
+                                references.insert(update.original_header_address.clone());
+                                if update.entry_type == EntryType::CapClaim {
+                                    claim_headers.push((
+                                        header.as_hash().clone(),
+                                        update.header_seq,
+                                        update.entry_hash.clone(),
+                                    ));
+                                }
+                            }
+                            Header::Delete(delete) => {
+                                references.insert(delete.deletes_address.clone());
+                            }
+                            // no other headers are relevant
+                            _ => {}
+                        }
+                        Ok((references, claim_headers))
+                    },
+                )?;
+            SourceChainResult::Ok(
+                claim_headers
+                    .into_iter()
+                    .filter(|(header_hash, _, _)| !references.contains(header_hash))
+                    .map(|(_, header_seq, entry_hash)| (header_seq, entry_hash))
+                    .collect::<Vec<_>>(),
+            )
+        })?;
+
+        // newest first, so the first claim seen with a tag is the one in force for that tag
+        live_claims.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+        let mut seen_tags = HashSet::new();
+        for (_, entry_hash) in live_claims {
+            let claim = match self.0.get_entry(&entry_hash)?.map(|e| e.into_content()) {
+                Some(Entry::CapClaim(claim)) => claim,
+                _ => continue,
+            };
+            if seen_tags.insert(claim.tag().to_string()) && claim.is_for(grantor, check_function) {
+                return Ok(Some(claim));
+            }
+        }
+        Ok(None)
+    }
+
     // @todo bring all this back when we want to administer cap claims better
     //         /// Fetch a CapClaim from the private entries.
     //         ///
@@ -324,13 +400,190 @@ impl BufferedStore for SourceChain {
 pub mod tests {
 
     use super::*;
+    use crate::core::state::element_buf::ElementBuf;
     use crate::fixt::*;
     use ::fixt::prelude::*;
     use hdk3::prelude::*;
     use holochain_state::test_utils::test_cell_env;
-    use holochain_types::test_utils::fake_dna_hash;
+    use holochain_types::{
+        element::SignedHeaderHashed,
+        test_utils::{
+            fake_agent_pubkey_1, fake_dna_hash, fake_entry_hash, fake_header_hash, FakeClock,
+        },
+        HeaderHashed,
+    };
     use holochain_zome_types::capability::{CapAccess, ZomeCallCapGrant};
+    use matches::assert_matches;
     use std::collections::HashSet;
+    use std::sync::Arc;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn headers_are_timestamped_by_the_chain_clock() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let env = test_env.env();
+        let alice = fake_agent_pubkey_1();
+        let clock = Arc::new(FakeClock::new(Timestamp(100, 0)));
+
+        let mut store = SourceChainBuf::new(env.clone().into())?.with_clock(clock.clone());
+        store.genesis(fake_dna_hash(1), alice.clone(), None).await?;
+        let dna_header = store.get_dna_header_address()?.unwrap();
+        assert_eq!(
+            store.get_header(&dna_header)?.unwrap().header().timestamp(),
+            Timestamp(100, 0).into()
+        );
+
+        // Headers are timestamped by the clock as it moves on
+        clock.advance(50);
+        let mut chain = SourceChain::from(store);
+        let header_hash = chain
+            .put(
+                builder::Create {
+                    entry_type: EntryType::AgentPubKey,
+                    entry_hash: alice.clone().into(),
+                },
+                Some(Entry::Agent(alice.clone())),
+            )
+            .await?;
+        assert_eq!(
+            chain
+                .get_header(&header_hash)?
+                .unwrap()
+                .header()
+                .timestamp(),
+            Timestamp(150, 0).into()
+        );
+
+        // A second header in the same tick, or from a clock which has gone
+        // backwards, is refused instead of being written out of order
+        for step in &[0, -100] {
+            clock.advance(*step);
+            assert_matches!(
+                chain
+                    .put(
+                        builder::Create {
+                            entry_type: EntryType::AgentPubKey,
+                            entry_hash: alice.clone().into(),
+                        },
+                        Some(Entry::Agent(alice.clone())),
+                    )
+                    .await,
+                Err(SourceChainError::NonMonotonicTimestamp { prev, new })
+                    if prev == Timestamp(150, 0) && new == Timestamp(150 + step, 0)
+            );
+        }
+        assert_eq!(chain.len(), 4);
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn iter_back_typed_returns_only_one_header_type() -> SourceChainResult<()> {
+        use holochain_zome_types::{header, link::LinkTag};
+        use std::convert::TryFrom;
+
+        let test_env = test_cell_env();
+        let env = test_env.env();
+        let alice = fake_agent_pubkey_1();
+        let entry_hash: EntryHash = alice.clone().into();
+
+        let mut store = SourceChainBuf::new(env.clone().into())?;
+        store.genesis(fake_dna_hash(1), alice.clone(), None).await?;
+        let dna = store.get_dna_header_address()?.unwrap();
+        let mut chain = SourceChain::from(store);
+
+        let init = chain.put(builder::InitZomesComplete {}, None).await?;
+        let open = chain
+            .put(
+                builder::OpenChain {
+                    prev_dna_hash: fake_dna_hash(2),
+                },
+                None,
+            )
+            .await?;
+        let create = chain
+            .put(
+                builder::Create {
+                    entry_type: EntryType::AgentPubKey,
+                    entry_hash: entry_hash.clone(),
+                },
+                Some(Entry::Agent(alice.clone())),
+            )
+            .await?;
+        let update = chain
+            .put(
+                builder::Update {
+                    original_entry_address: entry_hash.clone(),
+                    original_header_address: create.clone(),
+                    entry_type: EntryType::AgentPubKey,
+                    entry_hash: entry_hash.clone(),
+                },
+                Some(Entry::Agent(alice.clone())),
+            )
+            .await?;
+        let create_link = chain
+            .put(
+                builder::CreateLink {
+                    base_address: entry_hash.clone(),
+                    target_address: entry_hash.clone(),
+                    zome_id: 0.into(),
+                    tag: LinkTag::new("tag"),
+                },
+                None,
+            )
+            .await?;
+        let delete_link = chain
+            .put(
+                builder::DeleteLink {
+                    link_add_address: create_link.clone(),
+                    base_address: entry_hash.clone(),
+                },
+                None,
+            )
+            .await?;
+        let delete = chain
+            .put(
+                builder::Delete {
+                    deletes_address: update.clone(),
+                    deletes_entry_address: entry_hash.clone(),
+                },
+                None,
+            )
+            .await?;
+        let close = chain
+            .put(
+                builder::CloseChain {
+                    new_dna_hash: fake_dna_hash(3),
+                },
+                None,
+            )
+            .await?;
+
+        fn hashes<H: TryFrom<Header>>(chain: &SourceChain) -> SourceChainResult<Vec<HeaderHash>> {
+            chain
+                .iter_back_typed::<H>()
+                .map(|(_, hash)| Ok(hash))
+                .collect()
+        }
+        assert_eq!(hashes::<header::Dna>(&chain)?, vec![dna]);
+        assert_eq!(hashes::<header::AgentValidationPkg>(&chain)?.len(), 1);
+        assert_eq!(hashes::<header::InitZomesComplete>(&chain)?, vec![init]);
+        assert_eq!(hashes::<header::OpenChain>(&chain)?, vec![open]);
+        assert_eq!(hashes::<header::Update>(&chain)?, vec![update.clone()]);
+        assert_eq!(hashes::<header::CreateLink>(&chain)?, vec![create_link]);
+        assert_eq!(hashes::<header::DeleteLink>(&chain)?, vec![delete_link]);
+        assert_eq!(hashes::<header::Delete>(&chain)?, vec![delete]);
+        assert_eq!(hashes::<header::CloseChain>(&chain)?, vec![close]);
+
+        // Latest first, so the agent key's Create from genesis comes last
+        let creates = hashes::<header::Create>(&chain)?;
+        assert_eq!(creates.len(), 2);
+        assert_eq!(creates[0], create);
+
+        // The headers come back as their own type
+        let (header, hash) = chain.iter_back_typed::<header::Update>().next()?.unwrap();
+        assert_eq!(hash, update);
+        assert_eq!(header.original_header_address, create);
+        Ok(())
+    }
 
     #[tokio::test(threaded_scheduler)]
     async fn test_get_cap_grant() -> SourceChainResult<()> {
@@ -489,6 +742,160 @@ pub mod tests {
         Ok(())
     }
 
+    #[tokio::test(threaded_scheduler)]
+    async fn migrated_and_integrated_grants_are_not_used() -> SourceChainResult<()> {
+        let old_env = test_cell_env();
+        let old_arc = old_env.env();
+        let new_env = test_cell_env();
+        let new_arc = new_env.env();
+        let secret = Some(CapSecretFixturator::new(Unpredictable).next().unwrap());
+        let function: GrantedFunction = ("foo".into(), "bar".into());
+        let mut functions: GrantedFunctions = HashSet::new();
+        functions.insert(function.clone());
+        let grant =
+            ZomeCallCapGrant::new("tag".into(), CapAccess::from(secret.unwrap()), functions);
+        let mut agents = AgentPubKeyFixturator::new(Predictable);
+        let alice = agents.next().unwrap();
+        let bob = agents.next().unwrap();
+
+        // Alice granted bob access on her old chain
+        {
+            let mut store = SourceChainBuf::new(old_arc.clone().into())?;
+            store.genesis(fake_dna_hash(1), alice.clone(), None).await?;
+            let mut chain = SourceChain::from(store);
+            let (entry, entry_hash) =
+                EntryHashed::from_content_sync(Entry::CapGrant(grant)).into_inner();
+            let header_builder = builder::Create {
+                entry_type: EntryType::CapGrant,
+                entry_hash,
+            };
+            chain.put(header_builder, Some(entry)).await?;
+            assert!(chain
+                .valid_cap_grant(&function, &bob, secret.as_ref())?
+                .is_some());
+            old_arc
+                .guard()
+                .with_commit(|writer| chain.flush_to_txn(writer))?;
+        }
+
+        // The old env also holds data integrated from other agents
+        let integrated_header = HeaderHashed::from_content_sync(Header::Create(header::Create {
+            author: bob.clone(),
+            timestamp: Timestamp(1, 0).into(),
+            header_seq: 3,
+            prev_header: fake_header_hash(1),
+            entry_type: EntryType::CapGrant,
+            entry_hash: fake_entry_hash(1),
+        }));
+        let integrated_hash = integrated_header.as_hash().clone();
+        {
+            let mut vault = ElementBuf::vault(old_arc.clone().into(), true)?;
+            vault.put(
+                SignedHeaderHashed::with_presigned(integrated_header, Signature(vec![0; 64])),
+                None,
+            )?;
+            old_arc
+                .guard()
+                .with_commit(|writer| vault.flush_to_txn(writer))?;
+        }
+
+        let mut store = SourceChainBuf::new(new_arc.clone().into())?;
+        store.genesis(fake_dna_hash(2), alice.clone(), None).await?;
+        // Only the 3 genesis elements and the grant that alice authored are copied
+        assert_eq!(
+            store.merge_authored_from_migration(old_arc.clone().into())?,
+            4
+        );
+        new_arc
+            .guard()
+            .with_commit(|writer| store.flush_to_txn(writer))?;
+
+        let chain = SourceChain::new(new_arc.clone().into())?;
+        assert_eq!(chain.get_element(&integrated_hash)?, None);
+        // The migrated grant is only a record, it doesn't grant anything on the new chain
+        assert_eq!(
+            chain.valid_cap_grant(&function, &bob, secret.as_ref())?,
+            None
+        );
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_latest_cap_claim() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let env = test_env.env();
+        let mut agents = AgentPubKeyFixturator::new(Predictable);
+        let alice = agents.next().unwrap();
+        let bob = agents.next().unwrap();
+        let function: GrantedFunction = ("foo".into(), "bar".into());
+        let mut functions: GrantedFunctions = HashSet::new();
+        functions.insert(function.clone());
+        {
+            let mut store = SourceChainBuf::new(env.clone().into())?;
+            store.genesis(fake_dna_hash(1), bob.clone(), None).await?;
+            env.guard()
+                .with_commit(|writer| store.flush_to_txn(writer))?;
+        }
+
+        let env_ref = &env;
+        let put_claim = |claim: CapClaim| async move {
+            let env = env_ref;
+            let mut chain = SourceChain::new(env.clone().into())?;
+            chain.put_cap_claim(claim).await?;
+            env.guard()
+                .with_commit(|writer| chain.flush_to_txn(writer))?;
+            SourceChainResult::Ok(())
+        };
+
+        let claim = CapClaim::new(
+            "alice".into(),
+            alice.clone(),
+            fixt!(CapSecret),
+            functions.clone(),
+        );
+        put_claim(claim.clone()).await?;
+        {
+            let chain = SourceChain::new(env.clone().into())?;
+            assert_eq!(chain.latest_cap_claim(&alice, &function)?, Some(claim));
+            // the claim is only for the function it was granted for
+            let other_function: GrantedFunction = ("foo".into(), "baz".into());
+            assert_eq!(chain.latest_cap_claim(&alice, &other_function)?, None);
+            // and only for the grantor
+            assert_eq!(chain.latest_cap_claim(&bob, &function)?, None);
+        }
+
+        // a newer claim with the same tag supersedes the original
+        let rotated_claim = CapClaim::new(
+            "alice".into(),
+            alice.clone(),
+            fixt!(CapSecret),
+            functions.clone(),
+        );
+        put_claim(rotated_claim.clone()).await?;
+        {
+            let chain = SourceChain::new(env.clone().into())?;
+            assert_eq!(
+                chain.latest_cap_claim(&alice, &function)?,
+                Some(rotated_claim)
+            );
+        }
+
+        // even if the newer claim is for something else
+        put_claim(CapClaim::new(
+            "alice".into(),
+            bob.clone(),
+            fixt!(CapSecret),
+            functions,
+        ))
+        .await?;
+        {
+            let chain = SourceChain::new(env.clone().into())?;
+            assert_eq!(chain.latest_cap_claim(&alice, &function)?, None);
+        }
+
+        Ok(())
+    }
+
     // @todo bring all this back when we want to administer cap claims better
     // #[tokio::test(threaded_scheduler)]
     // async fn test_get_cap_claim() -> SourceChainResult<()> {
@@ -497,7 +904,7 @@ pub mod tests {
     //     let env = env.guard().await;
     //     let secret = CapSecretFixturator::new(Unpredictable).next().unwrap();
     //     let agent_pubkey = fake_agent_pubkey_1().into();
-    //     let claim = CapClaim::new("tag".into(), agent_pubkey, secret.clone());
+    //     let claim = CapClaim::new("tag".into(), agent_pubkey, secret.clone(), HashSet::new());
     //     {
     //         let mut store = SourceChainBuf::new(env.clone().into(), &env).await?;
     //         store
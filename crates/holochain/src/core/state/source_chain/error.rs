@@ -3,7 +3,7 @@ use holo_hash::EntryHash;
 use holo_hash::HeaderHash;
 use holochain_serialized_bytes::prelude::*;
 use holochain_state::error::DatabaseError;
-use holochain_types::dht_op::error::DhtOpError;
+use holochain_types::{dht_op::error::DhtOpError, Timestamp};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -34,6 +34,9 @@ pub enum SourceChainError {
     #[error("SerdeJson Error: {0}")]
     SerdeJsonError(String),
 
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
     /// Element signature doesn't validate against the header
     #[error("Element signature is invalid")]
     InvalidSignature,
@@ -66,6 +69,55 @@ pub enum SourceChainError {
     /// Element signature doesn't validate against the header
     #[error("Element associated with header {0} was not found on the source chain")]
     ElementMissing(String),
+
+    /// The element a header refers to isn't on this source chain
+    #[error("No element with header {0} to delete was found on the source chain")]
+    EntryNotFound(HeaderHash),
+
+    /// The source chain was opened without a keystore, so it can't sign headers
+    #[error("The source chain is read-only")]
+    ReadOnly,
+
+    /// A header's timestamp must be later than the chain head's
+    #[error("Header timestamp {new} is not later than the chain head's timestamp {prev}")]
+    NonMonotonicTimestamp { prev: Timestamp, new: Timestamp },
+
+    /// The chain sequence holds more than one branch
+    #[error("The source chain has forked: {0}")]
+    ChainForked(ForkReport),
+
+    /// A fork can only be resolved by keeping one of its competing headers
+    #[error("Header {0} is not one of the competing headers of a source chain fork")]
+    NotAForkHead(HeaderHash),
+
+    /// A zome call wrote more to its workspace than it may hold before flushing
+    #[error("The zome call's workspace holds {used} bytes, more than its limit of {limit} bytes")]
+    ScratchSizeLimitExceeded { used: usize, limit: usize },
+
+    /// A section of the chain was asked for outside of the chain
+    #[error("The range {from_seq}..{to_seq} is not a section of a source chain of length {len}")]
+    InvalidRange {
+        from_seq: u32,
+        to_seq: u32,
+        len: usize,
+    },
+
+    /// Elements can only be tagged with a non-empty tag
+    #[error("A source chain tag must not be empty")]
+    EmptyTag,
+
+    /// Proof of work was asked for at a difficulty which could take
+    /// unreasonably long to find
+    #[error("A proof of work difficulty of {difficulty} bits is more than the limit of {max}")]
+    ProofOfWorkTooHard { difficulty: u8, max: u8 },
+
+    /// Only elements which haven't been flushed can be taken off the chain
+    #[error("Can't go back to a checkpoint at length {checkpoint} of a source chain of length {len} with {persisted_len} elements flushed")]
+    InvalidCheckpoint {
+        checkpoint: usize,
+        persisted_len: usize,
+        len: usize,
+    },
 }
 
 // serde_json::Error does not implement PartialEq - why is that a requirement??
@@ -88,6 +140,37 @@ pub enum ChainInvalidReason {
 
     #[error("Content was expected to definitely exist at this address, but didn't: {0}")]
     MissingData(EntryHash),
+
+    #[error("A header was expected to definitely exist at this address, but didn't: {0}")]
+    MissingHeader(HeaderHash),
+
+    #[error("Header sequence numbers must increase monotonically, but {1} followed {0}")]
+    NonMonotonicSequence(u32, u32),
+
+    #[error("Header {0} was not authored by the agent whose chain it is on")]
+    WrongAuthor(HeaderHash),
+}
+
+/// A header could not be put on the source chain because its position in
+/// the sequence is already taken, or the header is already on the chain.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Header {header_hash} already occupies position {header_seq} of the source chain")]
+pub struct SequenceConflict {
+    /// The header already on the chain
+    pub header_hash: HeaderHash,
+    /// The position of the conflict in the chain sequence
+    pub header_seq: u32,
+}
+
+/// Where a source chain has forked: two or more headers in the chain
+/// sequence follow the same previous header or claim the same position.
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[error("Headers {competing:?} compete for position {header_seq} of the source chain")]
+pub struct ForkReport {
+    /// The first position in the chain at which the branches diverge
+    pub header_seq: u32,
+    /// The competing headers, in the order they were sequenced
+    pub competing: Vec<HeaderHash>,
 }
 
 pub type SourceChainResult<T> = Result<T, SourceChainError>;
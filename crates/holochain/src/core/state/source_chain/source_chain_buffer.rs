@@ -1,48 +1,228 @@
 use super::ChainInvalidReason;
-use crate::core::state::{
-    chain_sequence::ChainSequenceBuf,
-    element_buf::{ElementBuf, HeaderCas},
-    source_chain::{SourceChainError, SourceChainResult},
+use crate::core::{
+    state::{
+        chain_sequence::ChainSequenceBuf,
+        dht_op_integration::AuthoredDhtOpsStore,
+        element_buf::{ElementBuf, HeaderCas},
+        source_chain::{ForkReport, SequenceConflict, SourceChainError, SourceChainResult},
+    },
+    sys_validate::MAX_ENTRY_SIZE,
 };
 use fallible_iterator::FallibleIterator;
-use holochain_state::{buffer::BufferedStore, error::DatabaseResult, fresh_reader, prelude::*};
+use futures::stream::{Stream, TryStreamExt};
+use holo_hash::encode::blake2b_256;
+use holochain_keystore::KeystoreError;
+use holochain_p2p::dht_arc::{DhtArc, MAX_HALF_LENGTH};
+use holochain_state::{
+    buffer::{BufferedStore, KvBufFresh, KvBufUsed, KvStore, KvvBufUsed},
+    db::{AUTHORED_DHT_OPS, CHAIN_ENTRY_TYPES, CHAIN_ENTRY_TYPES_BACKFILL, CHAIN_TAGS},
+    env::EnvironmentWrite,
+    error::DatabaseResult,
+    fresh_reader,
+    prelude::*,
+};
 use holochain_types::{
+    app::ProofOfWork,
     dht_op::{produce_ops_from_element, DhtOp},
-    element::{Element, SignedHeaderHashed, SignedHeaderHashedExt},
+    element::{Element, SignedHeader, SignedHeaderHashed, SignedHeaderHashedExt},
     entry::EntryHashed,
     prelude::*,
-    HeaderHashed,
+    Clock, HeaderHashed, SystemClock,
+};
+use holochain_zome_types::{
+    header::{self, EntryType},
+    Entry, Header,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    io::{Read, Write},
+    marker::PhantomData,
+    sync::Arc,
 };
-use holochain_zome_types::{header, Entry, Header};
 use tracing::*;
 
+/// How many headers genesis puts at the start of every chain:
+/// the Dna, the AgentValidationPkg and the agent's key
+const GENESIS_LEN: u32 = 3;
+
+/// The most leading zero bits [SourceChainBuf::calculate_proof_of_work]
+/// will look for. Each bit doubles the expected work, and this many take
+/// around 16 million hashes.
+pub const MAX_PROOF_OF_WORK_DIFFICULTY: u8 = 24;
+
+/// How many agents should hold each location on the DHT.
+/// An agent's arc covers this share of an estimated network.
+pub const DHT_REDUNDANCY_TARGET: u32 = 50;
+
+/// When [SourceChainBuf::prune_deleted_entries] removes the content of
+/// an entry whose headers have been deleted
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PrunePolicy {
+    /// As soon as the Delete is committed
+    Immediately,
+    /// Once the Delete is at least this many seconds old
+    AfterSeconds(u64),
+    /// Never, keeping deleted entries on the chain
+    Never,
+}
+
+/// One element of a chain written by [SourceChainBuf::export_binary]
+#[derive(Serialize, Deserialize, SerializedBytes)]
+struct BinaryElement {
+    signed_header: SignedHeader,
+    maybe_entry: Option<Entry>,
+}
+
+/// The longest frame of a binary chain export which will be read:
+/// the largest entry, plus room for its header
+const MAX_FRAME_LEN: u32 = MAX_ENTRY_SIZE as u32 + 64 * 1024;
+
+/// Read the length prefix of the next frame of a binary chain export,
+/// or None if the stream ends cleanly before it
+fn read_frame_len<R: Read>(r: &mut R) -> std::io::Result<Option<u32>> {
+    let mut len = [0; 4];
+    let mut read = 0;
+    while read < len.len() {
+        match r.read(&mut len[read..]) {
+            Ok(0) if read == 0 => return Ok(None),
+            Ok(0) => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => read += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(Some(u32::from_be_bytes(len)))
+}
+
+/// The key of the index of headers by [EntryType]: the serialized EntryType
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct EntryTypeKey(SerializedBytes);
+
+impl From<&EntryType> for EntryTypeKey {
+    fn from(entry_type: &EntryType) -> Self {
+        Self(
+            entry_type
+                .clone()
+                .try_into()
+                .expect("EntryTypeKey serialization cannot fail"),
+        )
+    }
+}
+
+impl AsRef<[u8]> for EntryTypeKey {
+    fn as_ref(&self) -> &[u8] {
+        self.0.bytes()
+    }
+}
+
+impl BufKey for EntryTypeKey {
+    fn from_key_bytes_or_friendly_panic(bytes: &[u8]) -> Self {
+        Self(UnsafeBytes::from(bytes.to_vec()).into())
+    }
+}
+
+/// The key of the index of headers by tag: the tag followed by the [HeaderHash],
+/// so that every header with a tag is in a contiguous range of keys
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct ChainTagKey(Vec<u8>);
+
+impl ChainTagKey {
+    fn new(tag: &[u8], header_hash: &HeaderHash) -> Self {
+        let mut bytes = Vec::with_capacity(tag.len() + header_hash.get_full_bytes().len());
+        bytes.extend_from_slice(tag);
+        bytes.extend_from_slice(header_hash.get_full_bytes());
+        Self(bytes)
+    }
+
+    /// The prefix shared by the keys of every header with this tag
+    fn prefix(tag: &[u8]) -> Self {
+        Self(tag.to_vec())
+    }
+}
+
+impl AsRef<[u8]> for ChainTagKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl BufKey for ChainTagKey {
+    fn from_key_bytes_or_friendly_panic(bytes: &[u8]) -> Self {
+        Self(bytes.to_vec())
+    }
+}
+
 pub struct SourceChainBuf {
     elements: ElementBuf<AuthoredPrefix>,
+    /// Elements authored on the chain of a Dna the agent migrated from,
+    /// see [SourceChainBuf::merge_authored_from_migration]
+    migrated: ElementBuf<MigratedPrefix>,
     sequence: ChainSequenceBuf,
-    keystore: KeystoreSender,
+    entry_types: KvvBufUsed<EntryTypeKey, HeaderHash>,
+    tags: KvBufUsed<ChainTagKey, HeaderHash>,
+    /// None if the buffer was opened read-only
+    keystore: Option<KeystoreSender>,
+    clock: Arc<dyn Clock>,
 
     env: EnvironmentRead,
 }
 
 impl SourceChainBuf {
     pub fn new(env: EnvironmentRead) -> DatabaseResult<Self> {
-        Ok(Self {
-            elements: ElementBuf::authored(env.clone(), true)?,
-            sequence: ChainSequenceBuf::new(env.clone())?,
-            keystore: env.keystore().clone(),
-            env,
-        })
+        let keystore = env.keystore().clone();
+        Self::open(env, true, Some(keystore))
     }
 
     pub fn public_only(env: EnvironmentRead) -> DatabaseResult<Self> {
+        let keystore = env.keystore().clone();
+        Self::open(env, false, Some(keystore))
+    }
+
+    /// Open the chain for reading only, without using the keystore,
+    /// e.g. to inspect a recovered environment.
+    /// Anything which would sign a header returns [SourceChainError::ReadOnly].
+    pub fn read_only(env: EnvironmentRead) -> DatabaseResult<Self> {
+        Self::open(env, true, None)
+    }
+
+    fn open(
+        env: EnvironmentRead,
+        allow_private: bool,
+        keystore: Option<KeystoreSender>,
+    ) -> DatabaseResult<Self> {
         Ok(Self {
-            elements: ElementBuf::authored(env.clone(), false)?,
+            elements: ElementBuf::authored(env.clone(), allow_private)?,
+            migrated: ElementBuf::migrated(env.clone(), allow_private)?,
             sequence: ChainSequenceBuf::new(env.clone())?,
-            keystore: env.keystore().clone(),
+            entry_types: KvvBufUsed::new(env.get_db(&*CHAIN_ENTRY_TYPES)?),
+            tags: KvBufUsed::new(env.get_db(&*CHAIN_TAGS)?),
+            keystore,
+            clock: Arc::new(SystemClock),
             env,
         })
     }
 
+    /// True if the chain was opened with [SourceChainBuf::read_only]
+    pub fn is_read_only(&self) -> bool {
+        self.keystore.is_none()
+    }
+
+    fn keystore(&self) -> SourceChainResult<&KeystoreSender> {
+        self.keystore.as_ref().ok_or(SourceChainError::ReadOnly)
+    }
+
+    /// Use the given clock instead of the system clock to timestamp new headers
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The clock used to timestamp new headers
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
     pub fn env(&self) -> &EnvironmentRead {
         &self.env
     }
@@ -74,9 +254,71 @@ impl SourceChainBuf {
         }
     }
 
+    /// The elements at sequence positions `[from_seq, to_seq)`, oldest first.
+    /// The range must be non-empty and end within the chain.
+    pub fn get_chain_section(&self, from_seq: u32, to_seq: u32) -> SourceChainResult<Vec<Element>> {
+        let len = self.len();
+        if from_seq >= to_seq || to_seq as usize > len {
+            return Err(SourceChainError::InvalidRange {
+                from_seq,
+                to_seq,
+                len,
+            });
+        }
+        (from_seq..to_seq)
+            .map(|i| {
+                self.get_at_index(i)?
+                    .ok_or_else(|| SourceChainError::ElementMissing(format!("at sequence {}", i)))
+            })
+            .collect()
+    }
+
+    /// The address of the [Dna] header, which genesis puts at index 0.
+    /// Returns None if genesis has not run.
+    pub fn get_dna_header_address(&self) -> SourceChainResult<Option<HeaderHash>> {
+        Ok(self.sequence.get(0)?)
+    }
+
+    /// The address of the [AgentValidationPkg] header, which genesis puts at index 1.
+    /// Returns None if genesis has not run.
+    pub fn get_agent_validation_pkg_address(&self) -> SourceChainResult<Option<HeaderHash>> {
+        Ok(self.sequence.get(1)?)
+    }
+
+    /// Get an element of this chain, or failing that one merged in from the
+    /// chain of a Dna the agent migrated from
     pub fn get_element(&self, k: &HeaderHash) -> SourceChainResult<Option<Element>> {
         debug!("GET {:?}", k);
-        self.elements.get_element(k)
+        match self.elements.get_element(k)? {
+            Some(element) => Ok(Some(element)),
+            None => self.migrated.get_element(k),
+        }
+    }
+
+    /// Copy every element authored on the chain in `old_env`, the environment
+    /// of the Dna the agent migrated from, into this buffer.
+    /// The elements don't become part of this chain, which can't be built on
+    /// them, but [SourceChainBuf::get_element] finds them.
+    /// Returns how many elements were copied.
+    pub fn merge_authored_from_migration(
+        &mut self,
+        old_env: EnvironmentRead,
+    ) -> DatabaseResult<usize> {
+        let old_elements = ElementBuf::authored(old_env.clone(), true)?;
+        let headers = fresh_reader!(old_env, |r| old_elements
+            .headers()
+            .iter_fail(&r)?
+            .map(|header| Ok(SignedHeaderHashed::from(header)))
+            .collect::<Vec<_>>())?;
+        let merged = headers.len();
+        for signed_header in headers {
+            let maybe_entry = match signed_header.header().entry_hash() {
+                Some(entry_hash) => old_elements.get_entry(entry_hash)?,
+                None => None,
+            };
+            self.migrated.put(signed_header, maybe_entry)?;
+        }
+        Ok(merged)
     }
 
     pub fn get_header(&self, k: &HeaderHash) -> DatabaseResult<Option<SignedHeaderHashed>> {
@@ -108,6 +350,15 @@ impl SourceChainBuf {
         Ok(ops)
     }
 
+    /// Count the elements whose DhtOps haven't been produced yet.
+    /// Unlike [get_incomplete_dht_ops], this doesn't load the elements,
+    /// so it is cheap enough to poll for monitoring.
+    pub fn count_incomplete_dht_ops(&self) -> SourceChainResult<usize> {
+        fresh_reader!(self.env(), |r| self
+            .sequence
+            .count_items_with_incomplete_dht_ops(&r))
+    }
+
     pub fn complete_dht_op(&mut self, i: u32) -> SourceChainResult<()> {
         self.sequence.complete_dht_op(i)
     }
@@ -120,15 +371,183 @@ impl SourceChainBuf {
         &self.sequence
     }
 
-    /// Add a Element to the source chain, using a fully-formed Header
+    /// Roughly how many bytes written to this chain are waiting to be flushed,
+    /// by the serialized size of the values in every scratch space.
+    /// Anything removed from the scratch space before it is flushed isn't counted.
+    pub fn scratch_size(&self) -> usize {
+        self.elements.scratch_size()
+            + self.sequence.scratch_size()
+            + self.entry_types.scratch_size()
+            + self.tags.scratch_size()
+    }
+
+    /// Find the earliest point at which the chain has forked, if any.
+    /// See [ChainSequenceBuf::detect_forks]
+    pub fn detect_forks(&self) -> SourceChainResult<Option<ForkReport>> {
+        self.sequence.detect_forks(&self.elements)
+    }
+
+    /// Resolve the earliest fork by keeping the branch beginning with `keep`.
+    /// See [ChainSequenceBuf::resolve_fork_keep]
+    pub fn resolve_fork_keep(&mut self, keep: &HeaderHash) -> SourceChainResult<ForkReport> {
+        self.sequence.resolve_fork_keep(&self.elements, keep)
+    }
+
+    /// Find the deepest header which both `a` and `b` descend from,
+    /// e.g. the last header shared by the two heads of a forked chain.
+    /// A header counts as its own ancestor.
+    /// Returns None if the ancestries don't meet before a header is missing.
+    pub fn common_ancestor(
+        &self,
+        a: &HeaderHash,
+        b: &HeaderHash,
+    ) -> SourceChainResult<Option<HeaderHash>> {
+        let mut a = self.get_header(a)?;
+        let mut b = self.get_header(b)?;
+        loop {
+            let (a_header, b_header) = match (&a, &b) {
+                (Some(a), Some(b)) => (a, b),
+                _ => return Ok(None),
+            };
+            if a_header.header_address() == b_header.header_address() {
+                return Ok(Some(a_header.header_address().clone()));
+            }
+            // Step back along the longer ancestry, or both if they're
+            // the same length, so the walks meet at the shared header
+            let a_seq = a_header.header().header_seq();
+            let b_seq = b_header.header().header_seq();
+            let a_prev = a_header.header().prev_header().cloned();
+            let b_prev = b_header.header().prev_header().cloned();
+            if a_seq >= b_seq {
+                a = match a_prev {
+                    Some(prev) => self.get_header(&prev)?,
+                    None => None,
+                };
+            }
+            if b_seq >= a_seq {
+                b = match b_prev {
+                    Some(prev) => self.get_header(&prev)?,
+                    None => None,
+                };
+            }
+        }
+    }
+
+    /// Add a Element to the source chain, using a fully-formed Header.
+    /// The header's timestamp must be strictly later than the chain head's,
+    /// so a clock which has gone backwards can't write a non-monotonic chain.
     pub async fn put_raw(
         &mut self,
         header: Header,
         maybe_entry: Option<Entry>,
     ) -> SourceChainResult<HeaderHash> {
+        let keystore = self.keystore()?.clone();
+        if let Some(head) = self.chain_head() {
+            if let Some(prev) = self.get_header(head)? {
+                let prev = prev.header().timestamp();
+                let new = header.timestamp();
+                if new <= prev {
+                    return Err(SourceChainError::NonMonotonicTimestamp {
+                        prev: prev.into(),
+                        new: new.into(),
+                    });
+                }
+            }
+        }
         let header = HeaderHashed::from_content_sync(header);
-        let header_address = header.as_hash().to_owned();
-        let signed_header = SignedHeaderHashed::new(&self.keystore, header).await?;
+        let signed_header = SignedHeaderHashed::new(&keystore, header).await?;
+        self.put_signed(signed_header, maybe_entry)
+    }
+
+    /// Put many elements on the chain at once, e.g. to replay a chain.
+    /// Each header must follow on from the one before it, the first from
+    /// the chain head: its `prev_header` must be the previous header's hash,
+    /// its `header_seq` one more than the previous header's, and its
+    /// timestamp later than the previous header's.
+    /// All the headers are signed concurrently before any are written,
+    /// so nothing is written if a header is out of place or can't be signed.
+    pub async fn put_raw_batch(
+        &mut self,
+        elements: Vec<(Header, Option<Entry>)>,
+    ) -> SourceChainResult<Vec<HeaderHash>> {
+        let keystore = self.keystore()?.clone();
+        let mut prev_header = self.chain_head().cloned();
+        let mut prev_timestamp = match &prev_header {
+            Some(head) => self.get_header(head)?.map(|h| h.header().timestamp()),
+            None => None,
+        };
+        let mut next_seq = self.len() as u32;
+        let mut headers = Vec::with_capacity(elements.len());
+        let mut entries = Vec::with_capacity(elements.len());
+        for (header, maybe_entry) in elements {
+            Self::check_follows(&header, prev_header.as_ref(), prev_timestamp, next_seq)?;
+            let timestamp = header.timestamp();
+            let header = HeaderHashed::from_content_sync(header);
+            prev_header = Some(header.as_hash().clone());
+            prev_timestamp = Some(timestamp);
+            next_seq += 1;
+            headers.push(header);
+            entries.push(maybe_entry);
+        }
+
+        let signed_headers = futures::future::try_join_all(
+            headers
+                .into_iter()
+                .map(|header| SignedHeaderHashed::new(&keystore, header)),
+        )
+        .await?;
+
+        signed_headers
+            .into_iter()
+            .zip(entries)
+            .map(|(signed_header, maybe_entry)| self.put_signed(signed_header, maybe_entry))
+            .collect()
+    }
+
+    /// Check that a header follows on from the previous header in a batch:
+    /// its `prev_header` must be the previous header's hash, its `header_seq`
+    /// must be `next_seq` and its timestamp must be later than the previous
+    /// header's.
+    fn check_follows(
+        header: &Header,
+        prev_header: Option<&HeaderHash>,
+        prev_timestamp: Option<holochain_zome_types::timestamp::Timestamp>,
+        next_seq: u32,
+    ) -> SourceChainResult<()> {
+        if header.prev_header() != prev_header {
+            return Err(SourceChainError::InvalidPreviousHeader(format!(
+                "Header at position {} has previous header {:?} but follows {:?}",
+                header.header_seq(),
+                header.prev_header(),
+                prev_header
+            )));
+        }
+        if header.header_seq() != next_seq {
+            return Err(SourceChainError::InvalidStructure(
+                ChainInvalidReason::NonMonotonicSequence(
+                    next_seq.wrapping_sub(1),
+                    header.header_seq(),
+                ),
+            ));
+        }
+        let timestamp = header.timestamp();
+        if let Some(prev) = prev_timestamp {
+            if timestamp <= prev {
+                return Err(SourceChainError::NonMonotonicTimestamp {
+                    prev: prev.into(),
+                    new: timestamp.into(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn put_signed(
+        &mut self,
+        signed_header: SignedHeaderHashed,
+        maybe_entry: Option<Entry>,
+    ) -> SourceChainResult<HeaderHash> {
+        let header_address = signed_header.header_address().to_owned();
         let maybe_entry = match maybe_entry {
             None => None,
             Some(entry) => Some(EntryHashed::from_content_sync(entry)),
@@ -142,10 +561,345 @@ impl SourceChainBuf {
         */
 
         self.sequence.put_header(header_address.clone())?;
+        if let Some(entry_type) = signed_header.header().entry_data().map(|(_, t)| t) {
+            self.entry_types
+                .insert(entry_type.into(), header_address.clone());
+        }
         self.elements.put(signed_header, maybe_entry)?;
         Ok(header_address)
     }
 
+    /// Take the elements put since this buffer was created back off the
+    /// chain, until it is `len` elements long, along with their entry types
+    /// and tags. Returns the elements taken off, newest first.
+    ///
+    /// Only elements which haven't been flushed can be taken off.
+    pub fn rewind_to(&mut self, len: usize) -> SourceChainResult<Vec<Element>> {
+        let persisted_len = self.sequence.persisted_len();
+        if len < persisted_len || len > self.len() {
+            return Err(SourceChainError::InvalidCheckpoint {
+                checkpoint: len,
+                persisted_len,
+                len: self.len(),
+            });
+        }
+        let rewound = (len..self.len())
+            .rev()
+            .map(|i| {
+                self.get_at_index(i as u32)?
+                    .ok_or_else(|| SourceChainError::ElementMissing(format!("at sequence {}", i)))
+            })
+            .collect::<SourceChainResult<Vec<_>>>()?;
+        // Entries can be shared with the elements which stay
+        let mut kept_entries = HashSet::new();
+        for i in persisted_len..len {
+            if let Some(element) = self.get_at_index(i as u32)? {
+                if let Some(entry_hash) = element.header().entry_data().map(|(h, _)| h) {
+                    kept_entries.insert(entry_hash.clone());
+                }
+            }
+        }
+        let persisted = ElementBuf::authored(self.env().clone(), true)?;
+        self.sequence.rewind_to(len)?;
+
+        let rewound_headers = rewound
+            .iter()
+            .map(|element| element.header_address().clone())
+            .collect::<HashSet<_>>();
+        for element in rewound.iter() {
+            let header_address = element.header_address().clone();
+            let entry_data = element.header().entry_data();
+            if let Some((_, entry_type)) = entry_data {
+                self.entry_types
+                    .delete(entry_type.into(), header_address.clone());
+            }
+            let entry_hash = match entry_data {
+                Some((entry_hash, _))
+                    if !kept_entries.contains(entry_hash)
+                        && persisted.get_entry(entry_hash)?.is_none() =>
+                {
+                    Some(entry_hash.clone())
+                }
+                _ => None,
+            };
+            self.elements.delete(header_address, entry_hash);
+        }
+        let tag_keys = fresh_reader!(self.env(), |r| {
+            self.tags
+                .iter(&r)?
+                .filter(|(_, header_hash)| Ok(rewound_headers.contains(header_hash)))
+                .map(|(k, _)| Ok(ChainTagKey::from_key_bytes_or_friendly_panic(k)))
+                .collect::<Vec<_>>()
+        })?;
+        for k in tag_keys {
+            self.tags.delete(k)?;
+        }
+        Ok(rewound)
+    }
+
+    /// Get every element on the chain whose entry is of the given type,
+    /// in chain order.
+    /// This looks the headers up in an index rather than scanning the chain.
+    pub fn get_elements_with_entry_type(
+        &self,
+        entry_type: &EntryType,
+    ) -> SourceChainResult<Vec<Element>> {
+        let header_hashes = fresh_reader!(self.env(), |r| {
+            self.entry_types
+                .get(&r, &entry_type.into())?
+                .collect::<DatabaseResult<Vec<_>>>()
+        })?;
+        let mut elements = header_hashes
+            .into_iter()
+            .map(|header_hash| {
+                self.get_element(&header_hash)?
+                    .ok_or_else(|| SourceChainError::ElementMissing(header_hash.to_string()))
+            })
+            .collect::<SourceChainResult<Vec<_>>>()?;
+        elements.sort_by_key(|e| e.header().header_seq());
+        Ok(elements)
+    }
+
+    /// Tag an element on the chain so that it can be found with [Self::get_by_tag].
+    /// An element may have many tags, and tagging it twice with the same tag
+    /// has no further effect.
+    pub fn tag_element(&mut self, header: &HeaderHash, tag: Vec<u8>) -> SourceChainResult<()> {
+        if tag.is_empty() {
+            return Err(SourceChainError::EmptyTag);
+        }
+        if self.get_header(header)?.is_none() {
+            return Err(SourceChainError::ElementMissing(header.to_string()));
+        }
+        self.tags
+            .put(ChainTagKey::new(&tag, header), header.clone())?;
+        Ok(())
+    }
+
+    /// Get every element on the chain with the given tag, in chain order.
+    /// This looks the headers up in an index rather than scanning the chain.
+    pub fn get_by_tag(&self, tag: &[u8]) -> SourceChainResult<Vec<Element>> {
+        if tag.is_empty() {
+            return Err(SourceChainError::EmptyTag);
+        }
+        let header_hashes = fresh_reader!(self.env(), |r| {
+            self.tags
+                .iter_all_key_matches(&r, ChainTagKey::prefix(tag))?
+                // A longer tag which starts with this one shares the prefix
+                .filter(|(k, header_hash)| {
+                    Ok(k.len() == tag.len() + header_hash.get_full_bytes().len())
+                })
+                .map(|(_, header_hash)| Ok(header_hash))
+                .collect::<Vec<_>>()
+        })?;
+        let mut elements = header_hashes
+            .into_iter()
+            .map(|header_hash| {
+                self.get_element(&header_hash)?
+                    .ok_or_else(|| SourceChainError::ElementMissing(header_hash.to_string()))
+            })
+            .collect::<SourceChainResult<Vec<_>>>()?;
+        elements.sort_by_key(|e| e.header().header_seq());
+        Ok(elements)
+    }
+
+    /// Get the targets of every link from `base` on the chain which hasn't
+    /// been deleted, in the order the links were committed.
+    /// This gives the result `get_links` would, for links authored here,
+    /// without going to the network.
+    pub fn get_link_targets(&self, base: &EntryHash) -> SourceChainResult<Vec<EntryHash>> {
+        // A DeleteLink always comes after its CreateLink,
+        // so walking back sees every delete before the link it deletes
+        let mut deleted = HashSet::new();
+        let mut targets = self
+            .iter_back()
+            .filter_map(|shh| {
+                let (header, header_hash) = shh.into_header_and_signature().0.into_inner();
+                Ok(match header {
+                    Header::DeleteLink(delete) if delete.base_address == *base => {
+                        deleted.insert(delete.link_add_address);
+                        None
+                    }
+                    Header::CreateLink(create)
+                        if create.base_address == *base && !deleted.contains(&header_hash) =>
+                    {
+                        Some(create.target_address)
+                    }
+                    _ => None,
+                })
+            })
+            .collect::<Vec<_>>()?;
+        targets.reverse();
+        Ok(targets)
+    }
+
+    /// Get the new entry and the `Update` header of every update on the chain
+    /// of `original_entry`, in the order the updates were committed.
+    /// Only direct updates are returned, not updates of the updates,
+    /// see [SourceChainBuf::get_update_chain].
+    pub fn get_all_update_targets(
+        &self,
+        original_entry: &EntryHash,
+    ) -> SourceChainResult<Vec<(EntryHash, HeaderHash)>> {
+        let mut targets = self
+            .iter_back_typed::<header::Update>()
+            .filter(|(update, _)| Ok(update.original_entry_address == *original_entry))
+            .map(|(update, header_hash)| Ok((update.entry_hash, header_hash)))
+            .collect::<Vec<_>>()?;
+        targets.reverse();
+        Ok(targets)
+    }
+
+    /// Follow the updates of `original` on the chain to its latest version.
+    /// The chain starts with `original`, and each entry after it is the latest
+    /// update of the one before which hasn't been deleted, so the last entry
+    /// is the live version. An entry which was never updated is its own chain.
+    pub fn get_update_chain(&self, original: &EntryHash) -> SourceChainResult<Vec<EntryHash>> {
+        // A Delete always comes after the Update it deletes, so walking back
+        // sees every delete before its update, and the first live update of
+        // each entry seen is the latest
+        let mut deleted = HashSet::new();
+        let mut latest_update = HashMap::new();
+        let mut iter = self.iter_back();
+        while let Some(shh) = iter.next()? {
+            let (header, header_hash) = shh.into_header_and_signature().0.into_inner();
+            match header {
+                Header::Delete(delete) => {
+                    deleted.insert(delete.deletes_address);
+                }
+                Header::Update(update) if !deleted.contains(&header_hash) => {
+                    latest_update
+                        .entry(update.original_entry_address)
+                        .or_insert(update.entry_hash);
+                }
+                _ => (),
+            }
+        }
+        let mut chain = vec![original.clone()];
+        let mut seen = HashSet::new();
+        seen.insert(original.clone());
+        // An entry can be updated back to an earlier version,
+        // which would otherwise never end
+        while let Some(next) = latest_update.remove(chain.last().expect("chain is not empty")) {
+            if !seen.insert(next.clone()) {
+                break;
+            }
+            chain.push(next);
+        }
+        Ok(chain)
+    }
+
+    /// Like [put_raw], but returns a [SequenceConflict] instead of writing
+    /// if the header is already on the chain or its `header_seq` is already
+    /// taken by another header, so inserts can be made idempotent.
+    pub async fn try_put_raw(
+        &mut self,
+        header: Header,
+        maybe_entry: Option<Entry>,
+    ) -> SourceChainResult<Result<HeaderHash, SequenceConflict>> {
+        let header_seq = header.header_seq();
+        let header_address = HeaderHash::with_data_sync(&header);
+        if self.elements.contains_header(&header_address)? {
+            return Ok(Err(SequenceConflict {
+                header_hash: header_address,
+                header_seq,
+            }));
+        }
+        if let Some(existing) = self.sequence.get(header_seq)? {
+            return Ok(Err(SequenceConflict {
+                header_hash: existing,
+                header_seq,
+            }));
+        }
+        Ok(Ok(self.put_raw(header, maybe_entry).await?))
+    }
+
+    /// Add a Delete header to the source chain.
+    /// The deleted element must be on this chain, and its entry must be the one the
+    /// Delete claims to delete.
+    pub async fn delete_entry(&mut self, delete: header::Delete) -> SourceChainResult<HeaderHash> {
+        let deleted = self
+            .get_header(&delete.deletes_address)?
+            .ok_or_else(|| SourceChainError::EntryNotFound(delete.deletes_address.clone()))?;
+        if deleted.header().entry_hash() != Some(&delete.deletes_entry_address) {
+            return Err(SourceChainError::InvalidCommit(format!(
+                "Delete of header {} names entry {}, but the header is for entry {:?}",
+                delete.deletes_address,
+                delete.deletes_entry_address,
+                deleted.header().entry_hash()
+            )));
+        }
+        self.put_raw(Header::Delete(delete), None).await
+    }
+
+    /// Remove the content of entries whose headers have all been deleted
+    /// by a Delete on this chain, once the policy allows.
+    /// The headers and the Deletes stay on the chain.
+    /// Entries created by genesis are never removed, nor are entries
+    /// until every op of their headers has been produced and published.
+    /// Returns how many entries were removed.
+    pub async fn prune_deleted_entries(&mut self, policy: PrunePolicy) -> SourceChainResult<usize> {
+        if policy == PrunePolicy::Never {
+            return Ok(0);
+        }
+        let now = self.clock.now();
+        let is_due = |deleted_at: i64| match policy {
+            PrunePolicy::Immediately => true,
+            PrunePolicy::AfterSeconds(secs) => now.0.saturating_sub(deleted_at) >= secs as i64,
+            PrunePolicy::Never => false,
+        };
+        let headers: Vec<SignedHeaderHashed> = self.iter_back().collect()?;
+
+        // When each header was deleted
+        let deleted: HashMap<&HeaderHash, i64> = headers
+            .iter()
+            .filter_map(|shh| match shh.header() {
+                Header::Delete(delete) => Some((&delete.deletes_address, delete.timestamp.0)),
+                _ => None,
+            })
+            .collect();
+        // Headers whose ops have all been sent to the DHT
+        let published = self.headers_with_published_ops()?;
+        // An entry created by several headers can only go once all are deleted
+        let mut due = HashMap::new();
+        for shh in headers.iter() {
+            if let Some(entry_hash) = shh.header().entry_hash() {
+                let header_due = shh.header().header_seq() >= GENESIS_LEN
+                    && published.contains(shh.header_address())
+                    && deleted
+                        .get(shh.header_address())
+                        .map_or(false, |deleted_at| is_due(*deleted_at));
+                *due.entry(entry_hash.clone()).or_insert(true) &= header_due;
+            }
+        }
+
+        let mut pruned = 0;
+        for (entry_hash, due) in due {
+            if due && self.elements.contains_entry(&entry_hash)? {
+                self.elements.delete_entry(entry_hash);
+                pruned += 1;
+            }
+        }
+        Ok(pruned)
+    }
+
+    /// The headers whose ops have all been produced and published.
+    /// A header with no authored ops hasn't had them produced yet.
+    fn headers_with_published_ops(&self) -> SourceChainResult<HashSet<HeaderHash>> {
+        let authored: AuthoredDhtOpsStore =
+            KvBufFresh::new(self.env().clone(), self.env().get_db(&*AUTHORED_DHT_OPS)?);
+        let mut published: HashMap<HeaderHash, bool> = HashMap::new();
+        fresh_reader!(self.env(), |r| authored.iter(&r)?.for_each(|(_, value)| {
+            *published
+                .entry(value.op.header_hash().clone())
+                .or_insert(true) &= value.last_publish_time.is_some();
+            Ok(())
+        }))?;
+        Ok(published
+            .into_iter()
+            .filter_map(|(header_hash, published)| if published { Some(header_hash) } else { None })
+            .collect())
+    }
+
     pub fn headers(&self) -> &HeaderCas<AuthoredPrefix> {
         &self.elements.headers()
     }
@@ -173,10 +927,342 @@ impl SourceChainBuf {
         }
     }
 
+    /// The arc of the DHT this chain's agent should hold, centered on the
+    /// location of its AgentPubKey. The arc covers enough of the ring for
+    /// [DHT_REDUNDANCY_TARGET] agents to hold each location in a network of
+    /// about `network_size_estimate` agents, or the whole ring in a network
+    /// smaller than that.
+    pub fn compute_dht_coverage_arc(
+        &self,
+        network_size_estimate: u32,
+    ) -> SourceChainResult<DhtArc> {
+        let agent_pubkey = self
+            .agent_pubkey()?
+            .ok_or(SourceChainError::InvalidStructure(
+                ChainInvalidReason::GenesisDataMissing,
+            ))?;
+        let half_length = if network_size_estimate <= DHT_REDUNDANCY_TARGET {
+            MAX_HALF_LENGTH
+        } else {
+            let covered = MAX_HALF_LENGTH as u64 * DHT_REDUNDANCY_TARGET as u64;
+            let size = network_size_estimate as u64;
+            ((covered + size - 1) / size) as u32
+        };
+        Ok(DhtArc::new(agent_pubkey.get_loc(), half_length))
+    }
+
+    /// Find a nonce for which the sha256 of `data` followed by the nonce
+    /// starts with `difficulty` zero bits. Each extra bit of difficulty
+    /// doubles the expected work, so it can be at most
+    /// [MAX_PROOF_OF_WORK_DIFFICULTY].
+    pub fn calculate_proof_of_work(data: &[u8], difficulty: u8) -> SourceChainResult<[u8; 32]> {
+        if difficulty > MAX_PROOF_OF_WORK_DIFFICULTY {
+            return Err(SourceChainError::ProofOfWorkTooHard {
+                difficulty,
+                max: MAX_PROOF_OF_WORK_DIFFICULTY,
+            });
+        }
+        let mut nonce = [0; 32];
+        while !Self::verify_proof_of_work(data, nonce, difficulty) {
+            // Count up, least significant byte first
+            for byte in nonce.iter_mut() {
+                *byte = byte.wrapping_add(1);
+                if *byte != 0 {
+                    break;
+                }
+            }
+        }
+        Ok(nonce)
+    }
+
+    /// Check that the sha256 of `data` followed by `nonce`
+    /// starts with `difficulty` zero bits
+    pub fn verify_proof_of_work(data: &[u8], nonce: [u8; 32], difficulty: u8) -> bool {
+        let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+        context.update(data);
+        context.update(&nonce);
+        let mut zero_bits = 0;
+        for byte in context.finish().as_ref() {
+            zero_bits += byte.leading_zeros();
+            if *byte != 0 {
+                break;
+            }
+        }
+        zero_bits >= difficulty as u32
+    }
+
+    /// The [ProofOfWork] this chain's agent presented as its membrane proof
+    /// at genesis. None if the membrane proof isn't a ProofOfWork, or if the
+    /// work doesn't hold for the bytes of the agent's public key.
+    pub fn agent_proof_of_work(&self) -> SourceChainResult<Option<ProofOfWork>> {
+        let agent_pubkey = match self.agent_pubkey()? {
+            Some(agent_pubkey) => agent_pubkey,
+            None => return Ok(None),
+        };
+        let agent_validation_pkg = match self.get_agent_validation_pkg_address()? {
+            Some(header_hash) => self.get_header(&header_hash)?,
+            None => None,
+        };
+        let membrane_proof = match agent_validation_pkg {
+            Some(signed_header) => match signed_header.header() {
+                Header::AgentValidationPkg(header::AgentValidationPkg {
+                    membrane_proof: Some(membrane_proof),
+                    ..
+                }) => membrane_proof.clone(),
+                _ => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+        Ok(ProofOfWork::try_from(membrane_proof).ok().filter(|pow| {
+            Self::verify_proof_of_work(agent_pubkey.get_full_bytes(), pow.nonce, pow.difficulty)
+        }))
+    }
+
     pub fn iter_back(&self) -> SourceChainBackwardIterator {
         SourceChainBackwardIterator::new(self)
     }
 
+    /// Iterate back from the chain head like [SourceChainBuf::iter_back],
+    /// along with the entry of each header. Each header is only read once,
+    /// unlike calling [SourceChainBuf::get_element] for each header of `iter_back`.
+    /// Private entries are None if the buffer was opened [SourceChainBuf::public_only].
+    pub fn iter_back_with_entries(&self) -> BackwardEntryIterator {
+        BackwardEntryIterator {
+            iter: self.iter_back(),
+        }
+    }
+
+    /// Iterate back from the chain head like [SourceChainBuf::iter_back],
+    /// returning only the headers of one type along with their hashes,
+    /// e.g. `iter_back_typed::<header::Create>()` returns only the Creates.
+    pub fn iter_back_typed<H: TryFrom<Header>>(&self) -> TypedBackwardIterator<'_, H> {
+        TypedBackwardIterator {
+            iter: self.iter_back(),
+            header_type: PhantomData,
+        }
+    }
+
+    /// Iterate back from the chain head, ending just before the first
+    /// header for which `stop` returns true. That header isn't returned,
+    /// so e.g. stopping at the last `InitZomesComplete` returns only the
+    /// elements committed after init.
+    pub fn iter_back_until<F: Fn(&SignedHeaderHashed) -> bool>(
+        &self,
+        stop: F,
+    ) -> BoundedBackwardIterator<F> {
+        BoundedBackwardIterator {
+            iter: self.iter_back(),
+            stop: Some(stop),
+        }
+    }
+
+    /// Stream every element on the chain, from genesis to head.
+    /// Elements are only fetched as the stream is polled.
+    /// The stream ends after the first error.
+    pub fn stream_elements(&self) -> impl Stream<Item = SourceChainResult<Element>> + '_ {
+        futures::stream::unfold(Some(0u32), move |i| async move {
+            let i = i?;
+            let header_hash = match self.sequence.get(i) {
+                Ok(Some(header_hash)) => header_hash,
+                Ok(None) => return None,
+                Err(e) => return Some((Err(e.into()), None)),
+            };
+            Some(match self.get_element(&header_hash) {
+                Ok(Some(element)) => (Ok(element), Some(i + 1)),
+                Ok(None) => (
+                    Err(SourceChainError::ElementMissing(header_hash.to_string())),
+                    None,
+                ),
+                Err(e) => (Err(e), None),
+            })
+        })
+    }
+
+    /// A digest of the whole chain, for quickly checking whether two copies
+    /// of a chain are the same.
+    ///
+    /// Each element's header hash, which covers its entry hash, is folded
+    /// into a running hash from genesis to head. Chains with the same
+    /// elements in the same order have the same digest.
+    pub async fn chain_digest(&self) -> SourceChainResult<HeaderHash> {
+        let digest = self
+            .stream_elements()
+            .try_fold(blake2b_256(&[]), |digest, element| async move {
+                let mut bytes = digest;
+                bytes.extend_from_slice(element.header_address().get_full_bytes());
+                Ok(blake2b_256(&bytes))
+            })
+            .await?;
+        Ok(HeaderHash::with_pre_hashed(digest))
+    }
+
+    /// Write the entire source chain, from the first element to the head,
+    /// in a compact binary format which [import_binary] can read back.
+    ///
+    /// Each element is encoded as [SerializedBytes] and prefixed with its
+    /// length as a big-endian u32. Headers are written with their original
+    /// signatures. A public-only chain is exported without private entries.
+    /// The environment is synced first, so the export never holds commits
+    /// which a crash could still lose.
+    pub fn export_binary<W: Write>(&self, mut w: W) -> SourceChainResult<()> {
+        self.env().guard().sync()?;
+        for i in 0..self.len() as u32 {
+            let header_hash = self
+                .sequence
+                .get(i)?
+                .ok_or_else(|| SourceChainError::ElementMissing(format!("at position {}", i)))?;
+            let (signed_header, entry) = self
+                .get_element(&header_hash)?
+                .ok_or_else(|| SourceChainError::ElementMissing(header_hash.to_string()))?
+                .into_inner();
+            let frame = SerializedBytes::try_from(BinaryElement {
+                signed_header: signed_header.into_inner().0,
+                maybe_entry: entry.into_option(),
+            })?;
+            let bytes = frame.bytes();
+            w.write_all(&(bytes.len() as u32).to_be_bytes())?;
+            w.write_all(bytes)?;
+        }
+        Ok(w.flush()?)
+    }
+
+    /// Read a chain written by [export_binary] onto the end of this chain,
+    /// keeping each header's original signature.
+    ///
+    /// Every element is read and checked before any is written: each header
+    /// must follow on from the one before it, as for [put_raw_batch], and its
+    /// signature must verify against its author.
+    /// A frame longer than an element can be is rejected before it is read.
+    pub async fn import_binary<R: Read>(&mut self, mut r: R) -> SourceChainResult<()> {
+        let mut prev_header = self.chain_head().cloned();
+        let mut prev_timestamp = match &prev_header {
+            Some(head) => self.get_header(head)?.map(|h| h.header().timestamp()),
+            None => None,
+        };
+        let mut next_seq = self.len() as u32;
+        let mut elements = Vec::new();
+        while let Some(len) = read_frame_len(&mut r)? {
+            if len > MAX_FRAME_LEN {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "Frame of {} bytes is longer than the limit of {}",
+                        len, MAX_FRAME_LEN
+                    ),
+                )
+                .into());
+            }
+            let mut bytes = vec![0; len as usize];
+            r.read_exact(&mut bytes)?;
+            let BinaryElement {
+                signed_header,
+                maybe_entry,
+            } = BinaryElement::try_from(SerializedBytes::from(UnsafeBytes::from(bytes)))?;
+            let signed_header = SignedHeaderHashed::from_content_sync(signed_header);
+            let header = signed_header.header();
+            Self::check_follows(header, prev_header.as_ref(), prev_timestamp, next_seq)?;
+            prev_header = Some(signed_header.header_address().clone());
+            prev_timestamp = Some(header.timestamp());
+            next_seq += 1;
+            elements.push((signed_header, maybe_entry));
+        }
+
+        futures::future::try_join_all(
+            elements
+                .iter()
+                .map(|(signed_header, _)| signed_header.validate()),
+        )
+        .await
+        .map_err(|e| match e {
+            KeystoreError::InvalidSignature(_, _) => SourceChainError::InvalidSignature,
+            e => e.into(),
+        })?;
+
+        for (signed_header, maybe_entry) in elements {
+            self.put_signed(signed_header, maybe_entry)?;
+        }
+        Ok(())
+    }
+
+    /// Write headers which were signed elsewhere onto the end of this chain,
+    /// keeping their signatures, e.g. to rebuild a chain from a peer.
+    /// Each header's entry, if it has one, is taken from `entries`;
+    /// entries which weren't sent, like private ones, are left out.
+    ///
+    /// Headers which are already on the chain are skipped, so replaying the
+    /// same headers twice has no further effect. Every other header is checked
+    /// before any is written: it must follow on from the one before it, as for
+    /// [put_raw_batch], it must be authored by `agent`, whose chain this is,
+    /// its signature must verify, and its entry must match its entry hash.
+    /// Returns how many elements were written.
+    pub async fn replay_signed_headers(
+        &mut self,
+        agent: &AgentPubKey,
+        headers: Vec<SignedHeaderHashed>,
+        entries: HashMap<EntryHash, Entry>,
+    ) -> SourceChainResult<usize> {
+        let mut prev_header = self.chain_head().cloned();
+        let mut prev_timestamp = None;
+        if let Some(head) = &prev_header {
+            if let Some(head_header) = self.get_header(head)? {
+                if head_header.header().author() != agent {
+                    return Err(SourceChainError::InvalidStructure(
+                        ChainInvalidReason::WrongAuthor(head.clone()),
+                    ));
+                }
+                prev_timestamp = Some(head_header.header().timestamp());
+            }
+        }
+        let mut next_seq = self.len() as u32;
+        let mut seen = HashSet::new();
+        let mut elements = Vec::new();
+        for signed_header in headers {
+            let header_address = signed_header.header_address().clone();
+            if self.get_header(&header_address)?.is_some() || !seen.insert(header_address) {
+                continue;
+            }
+            let header = signed_header.header();
+            if header.author() != agent {
+                return Err(SourceChainError::InvalidStructure(
+                    ChainInvalidReason::WrongAuthor(signed_header.header_address().clone()),
+                ));
+            }
+            Self::check_follows(header, prev_header.as_ref(), prev_timestamp, next_seq)?;
+            let maybe_entry = match header.entry_data() {
+                Some((entry_hash, _)) => match entries.get(entry_hash).cloned() {
+                    Some(entry) if EntryHash::with_data_sync(&entry) != *entry_hash => {
+                        return Err(SourceChainError::InvalidStructure(
+                            ChainInvalidReason::HeaderAndEntryMismatch(entry_hash.clone()),
+                        ))
+                    }
+                    maybe_entry => maybe_entry,
+                },
+                None => None,
+            };
+            prev_header = Some(signed_header.header_address().clone());
+            prev_timestamp = Some(header.timestamp());
+            next_seq += 1;
+            elements.push((signed_header, maybe_entry));
+        }
+
+        futures::future::try_join_all(
+            elements
+                .iter()
+                .map(|(signed_header, _)| signed_header.validate()),
+        )
+        .await
+        .map_err(|e| match e {
+            KeystoreError::InvalidSignature(_, _) => SourceChainError::InvalidSignature,
+            e => e.into(),
+        })?;
+
+        let count = elements.len();
+        for (signed_header, maybe_entry) in elements {
+            self.put_signed(signed_header, maybe_entry)?;
+        }
+        Ok(count)
+    }
+
     /// dump the entire source chain as a pretty-printed json string
     pub async fn dump_as_json(&self) -> Result<String, SourceChainError> {
         #[derive(Serialize, Deserialize)]
@@ -228,10 +1314,16 @@ impl SourceChainBuf {
         agent_pubkey: AgentPubKey,
         membrane_proof: Option<SerializedBytes>,
     ) -> SourceChainResult<()> {
+        // Each genesis header is timestamped a nanosecond after the last,
+        // so the chain is monotonic even if the clock doesn't move
+        let dna_timestamp = self.clock.now();
+        let avh_timestamp = next_timestamp(dna_timestamp);
+        let agent_timestamp = next_timestamp(avh_timestamp);
+
         // create a DNA chain element and add it directly to the store
         let dna_header = Header::Dna(header::Dna {
             author: agent_pubkey.clone(),
-            timestamp: Timestamp::now().into(),
+            timestamp: dna_timestamp.into(),
             hash: dna_hash,
         });
         let dna_header_address = self.put_raw(dna_header, None).await?;
@@ -239,7 +1331,7 @@ impl SourceChainBuf {
         // create the agent validation entry and add it directly to the store
         let agent_validation_header = Header::AgentValidationPkg(header::AgentValidationPkg {
             author: agent_pubkey.clone(),
-            timestamp: Timestamp::now().into(),
+            timestamp: avh_timestamp.into(),
             header_seq: 1,
             prev_header: dna_header_address,
             membrane_proof,
@@ -249,7 +1341,7 @@ impl SourceChainBuf {
         // create a agent chain element and add it directly to the store
         let agent_header = Header::Create(header::Create {
             author: agent_pubkey.clone(),
-            timestamp: Timestamp::now().into(),
+            timestamp: agent_timestamp.into(),
             header_seq: 2,
             prev_header: avh_addr,
             entry_type: header::EntryType::AgentPubKey,
@@ -260,31 +1352,264 @@ impl SourceChainBuf {
 
         Ok(())
     }
-}
-
-impl BufferedStore for SourceChainBuf {
-    type Error = SourceChainError;
 
-    fn flush_to_txn_ref(&mut self, writer: &mut Writer) -> Result<(), Self::Error> {
-        self.elements.flush_to_txn_ref(writer)?;
-        self.sequence.flush_to_txn_ref(writer)?;
-        Ok(())
+    /// True if the chain head is a [header::CloseChain],
+    /// after which nothing more should be written
+    pub fn is_closed(&self) -> SourceChainResult<bool> {
+        Ok(match self.chain_head() {
+            Some(head) => matches!(
+                self.get_header(head)?.as_ref().map(|h| h.header()),
+                Some(Header::CloseChain(_))
+            ),
+            None => false,
+        })
     }
-}
 
-/// FallibleIterator returning SignedHeaderHashed instances from chain
-/// starting with the head, moving back to the origin (Dna) header.
-pub struct SourceChainBackwardIterator<'a> {
-    store: &'a SourceChainBuf,
-    current: Option<HeaderHash>,
-}
+    /// Roughly how many bytes this chain takes on disk, for enforcing
+    /// storage budgets: the pages in use in the LMDB environment times
+    /// the page size. rkv doesn't give the stat of a single database, so
+    /// this counts every database in the Cell's environment alongside the
+    /// element and sequence databases, and pages LMDB has freed but not yet
+    /// reused, making it an upper bound. Uncommitted writes aren't counted.
+    pub fn estimated_disk_size(&self) -> DatabaseResult<u64> {
+        let env = self.env.guard();
+        let info = env.rkv().info()?;
+        let stat = env.rkv().stat()?;
+        Ok((info.last_pgno() as u64 + 1) * stat.page_size() as u64)
+    }
 
-impl<'a> SourceChainBackwardIterator<'a> {
-    pub fn new(store: &'a SourceChainBuf) -> Self {
-        Self {
-            store,
-            current: store.chain_head().cloned(),
-        }
+    /// Give up writing to this chain, e.g. once it is closed.
+    /// Anything written but not yet flushed can still be read,
+    /// but will never be flushed.
+    pub fn freeze(self) -> FrozenSourceChain {
+        FrozenSourceChain(self)
+    }
+}
+
+/// The reads of a source chain, shared by a [SourceChainBuf]
+/// and the [FrozenSourceChain] it becomes when frozen
+pub trait SourceChainRead {
+    /// The chain being read
+    fn source_chain_buf(&self) -> &SourceChainBuf;
+
+    /// See [SourceChainBuf::env]
+    fn env(&self) -> &EnvironmentRead {
+        self.source_chain_buf().env()
+    }
+
+    /// See [SourceChainBuf::chain_head]
+    fn chain_head(&self) -> Option<&HeaderHash> {
+        self.source_chain_buf().chain_head()
+    }
+
+    /// See [SourceChainBuf::len]
+    fn len(&self) -> usize {
+        self.source_chain_buf().len()
+    }
+
+    /// See [SourceChainBuf::is_empty]
+    fn is_empty(&self) -> bool {
+        self.source_chain_buf().is_empty()
+    }
+
+    /// See [SourceChainBuf::has_genesis]
+    fn has_genesis(&self) -> bool {
+        self.source_chain_buf().has_genesis()
+    }
+
+    /// See [SourceChainBuf::is_closed]
+    fn is_closed(&self) -> SourceChainResult<bool> {
+        self.source_chain_buf().is_closed()
+    }
+
+    /// See [SourceChainBuf::estimated_disk_size]
+    fn estimated_disk_size(&self) -> DatabaseResult<u64> {
+        self.source_chain_buf().estimated_disk_size()
+    }
+
+    /// See [SourceChainBuf::get_at_index]
+    fn get_at_index(&self, i: u32) -> SourceChainResult<Option<Element>> {
+        self.source_chain_buf().get_at_index(i)
+    }
+
+    /// See [SourceChainBuf::get_chain_section]
+    fn get_chain_section(&self, from_seq: u32, to_seq: u32) -> SourceChainResult<Vec<Element>> {
+        self.source_chain_buf().get_chain_section(from_seq, to_seq)
+    }
+
+    /// See [SourceChainBuf::get_element]
+    fn get_element(&self, k: &HeaderHash) -> SourceChainResult<Option<Element>> {
+        self.source_chain_buf().get_element(k)
+    }
+
+    /// See [SourceChainBuf::get_header]
+    fn get_header(&self, k: &HeaderHash) -> DatabaseResult<Option<SignedHeaderHashed>> {
+        self.source_chain_buf().get_header(k)
+    }
+
+    /// See [SourceChainBuf::get_entry]
+    fn get_entry(&self, k: &EntryHash) -> DatabaseResult<Option<EntryHashed>> {
+        self.source_chain_buf().get_entry(k)
+    }
+
+    /// See [SourceChainBuf::agent_pubkey]
+    fn agent_pubkey(&self) -> SourceChainResult<Option<AgentPubKey>> {
+        self.source_chain_buf().agent_pubkey()
+    }
+
+    /// See [SourceChainBuf::detect_forks]
+    fn detect_forks(&self) -> SourceChainResult<Option<ForkReport>> {
+        self.source_chain_buf().detect_forks()
+    }
+
+    /// See [SourceChainBuf::common_ancestor]
+    fn common_ancestor(
+        &self,
+        a: &HeaderHash,
+        b: &HeaderHash,
+    ) -> SourceChainResult<Option<HeaderHash>> {
+        self.source_chain_buf().common_ancestor(a, b)
+    }
+
+    /// See [SourceChainBuf::get_elements_with_entry_type]
+    fn get_elements_with_entry_type(
+        &self,
+        entry_type: &EntryType,
+    ) -> SourceChainResult<Vec<Element>> {
+        self.source_chain_buf()
+            .get_elements_with_entry_type(entry_type)
+    }
+
+    /// See [SourceChainBuf::get_by_tag]
+    fn get_by_tag(&self, tag: &[u8]) -> SourceChainResult<Vec<Element>> {
+        self.source_chain_buf().get_by_tag(tag)
+    }
+
+    /// See [SourceChainBuf::get_link_targets]
+    fn get_link_targets(&self, base: &EntryHash) -> SourceChainResult<Vec<EntryHash>> {
+        self.source_chain_buf().get_link_targets(base)
+    }
+
+    /// See [SourceChainBuf::get_all_update_targets]
+    fn get_all_update_targets(
+        &self,
+        original_entry: &EntryHash,
+    ) -> SourceChainResult<Vec<(EntryHash, HeaderHash)>> {
+        self.source_chain_buf()
+            .get_all_update_targets(original_entry)
+    }
+
+    /// See [SourceChainBuf::get_update_chain]
+    fn get_update_chain(&self, original: &EntryHash) -> SourceChainResult<Vec<EntryHash>> {
+        self.source_chain_buf().get_update_chain(original)
+    }
+
+    /// See [SourceChainBuf::iter_back]
+    fn iter_back(&self) -> SourceChainBackwardIterator {
+        self.source_chain_buf().iter_back()
+    }
+
+    /// See [SourceChainBuf::export_binary]
+    fn export_binary<W: Write>(&self, w: W) -> SourceChainResult<()> {
+        self.source_chain_buf().export_binary(w)
+    }
+}
+
+impl SourceChainRead for SourceChainBuf {
+    fn source_chain_buf(&self) -> &SourceChainBuf {
+        self
+    }
+}
+
+/// A source chain which can't be written to, made by [SourceChainBuf::freeze].
+/// It only has the methods of [SourceChainRead] and a few async reads,
+/// so a write to it doesn't compile.
+pub struct FrozenSourceChain(SourceChainBuf);
+
+impl FrozenSourceChain {
+    /// See [SourceChainBuf::dump_as_json]
+    pub async fn dump_as_json(&self) -> SourceChainResult<String> {
+        self.0.dump_as_json().await
+    }
+
+    /// See [SourceChainBuf::chain_digest]
+    pub async fn chain_digest(&self) -> SourceChainResult<HeaderHash> {
+        self.0.chain_digest().await
+    }
+
+    /// See [SourceChainBuf::stream_elements]
+    pub fn stream_elements(&self) -> impl Stream<Item = SourceChainResult<Element>> + '_ {
+        self.0.stream_elements()
+    }
+}
+
+impl SourceChainRead for FrozenSourceChain {
+    fn source_chain_buf(&self) -> &SourceChainBuf {
+        &self.0
+    }
+}
+
+/// The earliest timestamp after the given one
+fn next_timestamp(timestamp: Timestamp) -> Timestamp {
+    (chrono::DateTime::<chrono::Utc>::from(timestamp) + chrono::Duration::nanoseconds(1)).into()
+}
+
+impl BufferedStore for SourceChainBuf {
+    type Error = SourceChainError;
+
+    fn flush_to_txn_ref(&mut self, writer: &mut Writer) -> Result<(), Self::Error> {
+        self.elements.flush_to_txn_ref(writer)?;
+        self.migrated.flush_to_txn_ref(writer)?;
+        self.sequence.flush_to_txn_ref(writer)?;
+        self.entry_types.flush_to_txn_ref(writer)?;
+        self.tags.flush_to_txn_ref(writer)?;
+        Ok(())
+    }
+}
+
+/// Build the entry type index for any headers put before the index existed.
+/// This only does any work the first time it is called for an environment,
+/// after which a marker records that the backfill is complete.
+pub fn backfill_entry_type_index(env: &EnvironmentWrite) -> SourceChainResult<()> {
+    let marker: KvStore<UnitDbKey, Timestamp> =
+        KvStore::new(env.get_db(&*CHAIN_ENTRY_TYPES_BACKFILL)?);
+    if fresh_reader!(env, |r| marker.get(&r, &UnitDbKey))?.is_some() {
+        return Ok(());
+    }
+    let mut entry_types: KvvBufUsed<EntryTypeKey, HeaderHash> =
+        KvvBufUsed::new(env.get_db(&*CHAIN_ENTRY_TYPES)?);
+    SourceChainBuf::read_only(env.clone().into())?
+        .iter_back()
+        .for_each(|header| {
+            if let Some((_, entry_type)) = header.header().entry_data() {
+                entry_types.insert(entry_type.into(), header.header_address().clone());
+            }
+            Ok(())
+        })?;
+    env.guard().with_commit(|writer| {
+        // Another task may have finished the backfill while we read the chain
+        if marker.get(&*writer, &UnitDbKey)?.is_some() {
+            return Ok(());
+        }
+        entry_types.flush_to_txn(writer)?;
+        marker.put(writer, &UnitDbKey, &Timestamp::now())?;
+        SourceChainResult::Ok(())
+    })
+}
+
+/// FallibleIterator returning SignedHeaderHashed instances from chain
+/// starting with the head, moving back to the origin (Dna) header.
+pub struct SourceChainBackwardIterator<'a> {
+    store: &'a SourceChainBuf,
+    current: Option<HeaderHash>,
+}
+
+impl<'a> SourceChainBackwardIterator<'a> {
+    pub fn new(store: &'a SourceChainBuf) -> Self {
+        Self {
+            store,
+            current: store.chain_head().cloned(),
+        }
     }
 }
 
@@ -308,19 +1633,115 @@ impl<'a> FallibleIterator for SourceChainBackwardIterator<'a> {
     }
 }
 
+/// A [SourceChainBackwardIterator] which also returns the entry of each header,
+/// see [SourceChainBuf::iter_back_with_entries]
+pub struct BackwardEntryIterator<'a> {
+    iter: SourceChainBackwardIterator<'a>,
+}
+
+impl<'a> FallibleIterator for BackwardEntryIterator<'a> {
+    type Item = (SignedHeaderHashed, Option<Entry>);
+    type Error = SourceChainError;
+
+    fn next(&mut self) -> Result<Option<Self::Item>, Self::Error> {
+        match self.iter.next()? {
+            Some(header) => {
+                let entry = self
+                    .iter
+                    .store
+                    .elements
+                    .get_entry_from_header(header.header())?;
+                Ok(Some((header, entry)))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// A [SourceChainBackwardIterator] which skips the headers that aren't an `H`,
+/// see [SourceChainBuf::iter_back_typed]
+pub struct TypedBackwardIterator<'a, H> {
+    iter: SourceChainBackwardIterator<'a>,
+    header_type: PhantomData<H>,
+}
+
+impl<'a, H: TryFrom<Header>> FallibleIterator for TypedBackwardIterator<'a, H> {
+    type Item = (H, HeaderHash);
+    type Error = SourceChainError;
+
+    fn next(&mut self) -> Result<Option<Self::Item>, Self::Error> {
+        while let Some(header) = self.iter.next()? {
+            let (header, hash) = header.into_header_and_signature().0.into_inner();
+            if let Ok(header) = H::try_from(header) {
+                return Ok(Some((header, hash)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A [SourceChainBackwardIterator] which ends early,
+/// see [SourceChainBuf::iter_back_until]
+pub struct BoundedBackwardIterator<'a, F> {
+    iter: SourceChainBackwardIterator<'a>,
+    /// None once the iterator has stopped
+    stop: Option<F>,
+}
+
+impl<'a, F: Fn(&SignedHeaderHashed) -> bool> FallibleIterator for BoundedBackwardIterator<'a, F> {
+    type Item = SignedHeaderHashed;
+    type Error = SourceChainError;
+
+    fn next(&mut self) -> Result<Option<Self::Item>, Self::Error> {
+        let stop = match &self.stop {
+            Some(stop) => stop,
+            None => return Ok(None),
+        };
+        match self.iter.next()? {
+            Some(header) if stop(&header) => {
+                self.stop = None;
+                Ok(None)
+            }
+            header => Ok(header),
+        }
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
 
-    use super::SourceChainBuf;
-    use crate::core::state::source_chain::SourceChainResult;
+    use super::{
+        backfill_entry_type_index, PrunePolicy, SourceChainBuf, SourceChainRead,
+        DHT_REDUNDANCY_TARGET, MAX_PROOF_OF_WORK_DIFFICULTY,
+    };
+    use crate::core::state::{
+        dht_op_integration::{AuthoredDhtOpsStore, AuthoredDhtOpsValue},
+        source_chain::{ChainInvalidReason, SequenceConflict, SourceChainError, SourceChainResult},
+    };
     use fallible_iterator::FallibleIterator;
-    use holochain_state::{prelude::*, test_utils::test_cell_env};
+    use futures::StreamExt;
+    use holochain_p2p::dht_arc::{DhtArc, MAX_HALF_LENGTH};
+    use holochain_state::{
+        buffer::{KvBufFresh, KvvBufUsed},
+        db::{AUTHORED_DHT_OPS, CHAIN_ENTRY_TYPES},
+        env::EnvironmentWrite,
+        prelude::*,
+        test_utils::test_cell_env,
+    };
     use holochain_types::{
+        app::ProofOfWork,
+        dht_op::{produce_ops_from_element, DhtOpHashed},
+        element::{Element, SignedHeaderHashed, SignedHeaderHashedExt},
         prelude::*,
-        test_utils::{fake_agent_pubkey_1, fake_dna_file},
+        test_utils::{
+            fake_agent_pubkey_1, fake_agent_pubkey_2, fake_dna_file, fake_entry_hash,
+            fake_header_hash, FakeClock,
+        },
         HeaderHashed,
     };
-    use holochain_zome_types::{header, Entry, Header};
+    use holochain_zome_types::{entry_def::EntryVisibility, header, link::LinkTag, Entry, Header};
+    use matches::assert_matches;
+    use std::{collections::HashMap, sync::Arc};
 
     fn fixtures() -> (
         AgentPubKey,
@@ -436,42 +1857,1632 @@ pub mod tests {
     }
 
     #[tokio::test(threaded_scheduler)]
-    async fn source_chain_buffer_dump_entries_json() -> SourceChainResult<()> {
+    async fn iter_back_until_stops_early() -> SourceChainResult<()> {
         let test_env = test_cell_env();
         let arc = test_env.env();
 
-        let (_agent_pubkey, dna_header, dna_entry, agent_header, agent_entry) = fixtures();
+        let (agent_pubkey, dna_header, dna_entry, agent_header, agent_entry) = fixtures();
+        let init_header = Header::InitZomesComplete(header::InitZomesComplete {
+            author: agent_pubkey.clone(),
+            timestamp: Timestamp(2, 0).into(),
+            header_seq: 2,
+            prev_header: agent_header.as_hash().clone(),
+        });
+        let mut elements = vec![
+            (dna_header.into_content(), dna_entry),
+            (agent_header.into_content(), agent_entry.clone()),
+            (init_header, None),
+        ];
+        for secs in 3..5 {
+            let prev_header = HeaderHash::with_data_sync(&elements.last().unwrap().0);
+            let header = Header::Create(header::Create {
+                author: agent_pubkey.clone(),
+                timestamp: Timestamp(secs, 0).into(),
+                header_seq: secs as u32,
+                prev_header,
+                entry_type: header::EntryType::AgentPubKey,
+                entry_hash: agent_pubkey.clone().into(),
+            });
+            elements.push((header, agent_entry.clone()));
+        }
+        let mut store = SourceChainBuf::new(arc.clone().into()).unwrap();
+        let hashes = store.put_raw_batch(elements).await?;
+
+        fn header_hashes(
+            iter: impl FallibleIterator<Item = SignedHeaderHashed, Error = SourceChainError>,
+        ) -> Vec<HeaderHash> {
+            iter.map(|h| Ok(h.header_address().clone()))
+                .collect()
+                .unwrap()
+        }
+
+        // Only the elements committed after init
+        let since_init =
+            store.iter_back_until(|h| matches!(h.header(), Header::InitZomesComplete(_)));
+        assert_eq!(
+            header_hashes(since_init),
+            vec![hashes[4].clone(), hashes[3].clone()]
+        );
+
+        // Only the elements newer than 3s
+        let since = Timestamp(3, 0);
+        let newer = store.iter_back_until(|h| Timestamp::from(h.header().timestamp()) <= since);
+        assert_eq!(header_hashes(newer), vec![hashes[4].clone()]);
+
+        // The whole chain if the predicate never matches
+        let all = store.iter_back_until(|_| false);
+        let mut expected = hashes;
+        expected.reverse();
+        assert_eq!(header_hashes(all), expected);
+
+        // Nothing if the head matches, and nothing after stopping
+        let mut none = store.iter_back_until(|_| true);
+        assert_eq!(none.next()?, None);
+        assert_eq!(none.next()?, None);
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn iter_back_with_entries_matches_elements() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
 
+        let (agent_pubkey, dna_header, dna_entry, agent_header, agent_entry) = fixtures();
+        let private_entry = Entry::app(SerializedBytes::try_from(()).unwrap()).unwrap();
+        let private_header = Header::Create(header::Create {
+            author: agent_pubkey.clone(),
+            timestamp: Timestamp(2, 0).into(),
+            header_seq: 2,
+            prev_header: agent_header.as_hash().clone(),
+            entry_type: header::EntryType::App(header::AppEntryType::new(
+                0.into(),
+                0.into(),
+                EntryVisibility::Private,
+            )),
+            entry_hash: EntryHash::with_data_sync(&private_entry),
+        });
+        let elements = vec![
+            (dna_header.into_content(), dna_entry),
+            (agent_header.into_content(), agent_entry),
+            (private_header, Some(private_entry.clone())),
+        ];
         {
             let mut store = SourceChainBuf::new(arc.clone().into()).unwrap();
+            store.put_raw_batch(elements).await?;
+            arc.guard()
+                .with_commit(|writer| store.flush_to_txn(writer))?;
+        }
+
+        for store in vec![
+            SourceChainBuf::new(arc.clone().into()).unwrap(),
+            SourceChainBuf::public_only(arc.clone().into()).unwrap(),
+        ] {
+            let expected: Vec<(SignedHeaderHashed, Option<Entry>)> = store
+                .iter_back()
+                .map(|h| {
+                    let (header, entry) =
+                        store.get_element(h.header_address())?.unwrap().into_inner();
+                    Ok((header, entry.into_option()))
+                })
+                .collect()?;
+            let with_entries: Vec<_> = store.iter_back_with_entries().collect()?;
+            assert_eq!(with_entries.len(), 3);
+            assert_eq!(with_entries, expected);
+        }
+
+        // The private entry is only returned when private entries are readable
+        let mut all = SourceChainBuf::new(arc.clone().into())
+            .unwrap()
+            .iter_back_with_entries()
+            .collect::<Vec<_>>()?;
+        assert_eq!(all.remove(0).1, Some(private_entry));
+        let mut public = SourceChainBuf::public_only(arc.clone().into())
+            .unwrap()
+            .iter_back_with_entries()
+            .collect::<Vec<_>>()?;
+        assert_eq!(public.remove(0).1, None);
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn chain_fork_is_detected_and_resolved() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+
+        let (agent_pubkey, dna_header, dna_entry, agent_header, agent_entry) = fixtures();
+        let init_header = |prev_header: HeaderHash, header_seq, secs| {
+            Header::InitZomesComplete(header::InitZomesComplete {
+                author: agent_pubkey.clone(),
+                timestamp: Timestamp(secs, 0).into(),
+                header_seq,
+                prev_header,
+            })
+        };
+
+        // Write two headers which both follow the agent header,
+        // and build on the second
+        let (losing, keeping, tip) = {
+            let mut store = SourceChainBuf::new(arc.clone().into())?;
             store
                 .put_raw(dna_header.as_content().clone(), dna_entry)
                 .await?;
             store
                 .put_raw(agent_header.as_content().clone(), agent_entry)
                 .await?;
-
+            let losing = store
+                .put_raw(init_header(agent_header.as_hash().clone(), 2, 2), None)
+                .await?;
+            let keeping = store
+                .put_raw(init_header(agent_header.as_hash().clone(), 2, 3), None)
+                .await?;
+            let tip = store
+                .put_raw(init_header(keeping.clone(), 3, 4), None)
+                .await?;
             arc.guard()
                 .with_commit(|writer| store.flush_to_txn(writer))?;
+            (losing, keeping, tip)
+        };
+
+        let mut store = SourceChainBuf::new(arc.clone().into())?;
+        let fork = store.detect_forks()?.expect("The chain has forked");
+        assert_eq!(fork.header_seq, 2);
+        assert_eq!(fork.competing, vec![losing.clone(), keeping.clone()]);
+
+        assert_matches!(
+            store.resolve_fork_keep(&fake_header_hash(1)),
+            Err(SourceChainError::NotAForkHead(_))
+        );
+        assert_eq!(store.resolve_fork_keep(&keeping)?, fork);
+        assert_eq!(store.len(), 4);
+        assert_eq!(store.chain_head(), Some(&tip));
+        arc.guard()
+            .with_commit(|writer| store.flush_to_txn(writer))?;
+
+        let store = SourceChainBuf::new(arc.clone().into())?;
+        assert_eq!(store.detect_forks()?, None);
+        assert_eq!(
+            store
+                .iter_back()
+                .map(|h| Ok(h.header_address().clone()))
+                .collect::<Vec<_>>()?,
+            vec![
+                tip,
+                keeping,
+                agent_header.as_hash().clone(),
+                dna_header.as_hash().clone()
+            ]
+        );
+        // The losing header is no longer sequenced, but is still stored
+        assert!(store.get_header(&losing)?.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn common_ancestor_of_forked_heads() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+
+        let (agent_pubkey, dna_header, dna_entry, agent_header, agent_entry) = fixtures();
+        let init_header = |prev_header: HeaderHash, header_seq, secs| {
+            Header::InitZomesComplete(header::InitZomesComplete {
+                author: agent_pubkey.clone(),
+                timestamp: Timestamp(secs, 0).into(),
+                header_seq,
+                prev_header,
+            })
+        };
+
+        // Fork after the agent header, with one branch longer than the other
+        let mut store = SourceChainBuf::new(arc.clone().into())?;
+        store
+            .put_raw(dna_header.as_content().clone(), dna_entry)
+            .await?;
+        store
+            .put_raw(agent_header.as_content().clone(), agent_entry)
+            .await?;
+        let agent_hash = agent_header.as_hash().clone();
+        let short = store
+            .put_raw(init_header(agent_hash.clone(), 2, 2), None)
+            .await?;
+        let long = store
+            .put_raw(init_header(agent_hash.clone(), 2, 3), None)
+            .await?;
+        let long = store.put_raw(init_header(long, 3, 4), None).await?;
+
+        assert_eq!(
+            store.common_ancestor(&short, &long)?,
+            Some(agent_hash.clone())
+        );
+        assert_eq!(
+            store.common_ancestor(&long, &short)?,
+            Some(agent_hash.clone())
+        );
+        assert_eq!(store.common_ancestor(&long, &agent_hash)?, Some(agent_hash));
+        assert_eq!(store.common_ancestor(&long, &long)?, Some(long.clone()));
+        assert_eq!(store.common_ancestor(&long, &fake_header_hash(1))?, None);
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn link_targets_exclude_deleted_links() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+
+        let (agent_pubkey, dna_header, dna_entry, agent_header, agent_entry) = fixtures();
+        let mut store = SourceChainBuf::new(arc.clone().into())?;
+        store
+            .put_raw(dna_header.as_content().clone(), dna_entry)
+            .await?;
+        store
+            .put_raw(agent_header.as_content().clone(), agent_entry)
+            .await?;
+
+        let base = fake_entry_hash(1);
+        let mut prev_header = agent_header.as_hash().clone();
+        let mut header_seq = 2;
+        let mut create_link = |base_address: EntryHash, target_address: EntryHash| {
+            let header = Header::CreateLink(header::CreateLink {
+                author: agent_pubkey.clone(),
+                timestamp: Timestamp::now().into(),
+                header_seq,
+                prev_header: prev_header.clone(),
+                base_address,
+                target_address,
+                zome_id: 0.into(),
+                tag: LinkTag::new(""),
+            });
+            header_seq += 1;
+            prev_header = HeaderHashed::from_content_sync(header.clone()).into_hash();
+            header
+        };
+        let first = create_link(base.clone(), fake_entry_hash(2));
+        let second = create_link(base.clone(), fake_entry_hash(3));
+        let other_base = create_link(fake_entry_hash(4), fake_entry_hash(5));
+        let third = create_link(base.clone(), fake_entry_hash(6));
+        let mut link_add_addresses = vec![];
+        for header in vec![first, second, other_base, third] {
+            link_add_addresses.push(store.put_raw(header, None).await?);
+        }
+        assert_eq!(
+            store.get_link_targets(&base)?,
+            vec![fake_entry_hash(2), fake_entry_hash(3), fake_entry_hash(6)]
+        );
+
+        store
+            .put_raw(
+                Header::DeleteLink(header::DeleteLink {
+                    author: agent_pubkey.clone(),
+                    timestamp: Timestamp::now().into(),
+                    header_seq: 6,
+                    prev_header: link_add_addresses[3].clone(),
+                    base_address: base.clone(),
+                    link_add_address: link_add_addresses[1].clone(),
+                }),
+                None,
+            )
+            .await?;
+        assert_eq!(
+            store.get_link_targets(&base)?,
+            vec![fake_entry_hash(2), fake_entry_hash(6)]
+        );
+        assert_eq!(
+            store.get_link_targets(&fake_entry_hash(4))?,
+            vec![fake_entry_hash(5)]
+        );
+        assert!(store.get_link_targets(&fake_entry_hash(7))?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn update_chain_follows_live_updates() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+
+        let (agent_pubkey, dna_header, dna_entry, agent_header, agent_entry) = fixtures();
+        let mut store = SourceChainBuf::new(arc.clone().into())?;
+        store
+            .put_raw(dna_header.as_content().clone(), dna_entry)
+            .await?;
+        store
+            .put_raw(agent_header.as_content().clone(), agent_entry)
+            .await?;
+
+        let mut prev_header = agent_header.as_hash().clone();
+        let mut header_seq = 2;
+        let mut update = |original: u8, new: u8| {
+            let header = Header::Update(header::Update {
+                author: agent_pubkey.clone(),
+                timestamp: Timestamp::now().into(),
+                header_seq,
+                prev_header: prev_header.clone(),
+                original_header_address: fake_header_hash(original),
+                original_entry_address: fake_entry_hash(original),
+                entry_type: header::EntryType::AgentPubKey,
+                entry_hash: fake_entry_hash(new),
+            });
+            header_seq += 1;
+            prev_header = HeaderHashed::from_content_sync(header.clone()).into_hash();
+            header
+        };
+        // 1 is updated to 2 and then 3, and 3 is updated to 4
+        let mut update_addresses = vec![];
+        for header in vec![update(1, 2), update(5, 6), update(1, 3), update(3, 4)] {
+            update_addresses.push(store.put_raw(header, None).await?);
         }
+        assert_eq!(
+            store.get_all_update_targets(&fake_entry_hash(1))?,
+            vec![
+                (fake_entry_hash(2), update_addresses[0].clone()),
+                (fake_entry_hash(3), update_addresses[2].clone()),
+            ]
+        );
+        assert!(store
+            .get_all_update_targets(&fake_entry_hash(4))?
+            .is_empty());
+        assert_eq!(
+            store.get_update_chain(&fake_entry_hash(1))?,
+            vec![fake_entry_hash(1), fake_entry_hash(3), fake_entry_hash(4)]
+        );
+        assert_eq!(
+            store.get_update_chain(&fake_entry_hash(7))?,
+            vec![fake_entry_hash(7)]
+        );
+
+        // Deleting the update to 3 leaves 2 as the live version
+        store
+            .put_raw(
+                Header::Delete(header::Delete {
+                    author: agent_pubkey.clone(),
+                    timestamp: Timestamp::now().into(),
+                    header_seq: 6,
+                    prev_header: update_addresses[3].clone(),
+                    deletes_address: update_addresses[2].clone(),
+                    deletes_entry_address: fake_entry_hash(3),
+                }),
+                None,
+            )
+            .await?;
+        assert_eq!(store.get_all_update_targets(&fake_entry_hash(1))?.len(), 2);
+        assert_eq!(
+            store.get_update_chain(&fake_entry_hash(1))?,
+            vec![fake_entry_hash(1), fake_entry_hash(2)]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn migrated_elements_are_found_but_not_on_the_chain() -> SourceChainResult<()> {
+        let old_env = test_cell_env();
+        let old_arc = old_env.env();
+        let new_env = test_cell_env();
+        let new_arc = new_env.env();
 
+        let (_, dna_header, dna_entry, agent_header, agent_entry) = fixtures();
         {
-            let store = SourceChainBuf::new(arc.clone().into()).unwrap();
-            let json = store.dump_as_json().await?;
-            let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+            let mut old = SourceChainBuf::new(old_arc.clone().into())?;
+            old.put_raw(dna_header.as_content().clone(), dna_entry)
+                .await?;
+            old.put_raw(agent_header.as_content().clone(), agent_entry.clone())
+                .await?;
+            old_arc
+                .guard()
+                .with_commit(|writer| old.flush_to_txn(writer))?;
+        }
 
-            assert_eq!(parsed[0]["element"]["header"]["type"], "Create");
-            assert_eq!(parsed[0]["element"]["header"]["entry_type"], "AgentPubKey");
-            assert_eq!(parsed[0]["element"]["entry"]["entry_type"], "Agent");
-            assert_ne!(
-                parsed[0]["element"]["entry"]["entry"],
-                serde_json::Value::Null
-            );
+        let mut store = SourceChainBuf::new(new_arc.clone().into())?;
+        assert_eq!(store.get_element(agent_header.as_hash())?, None);
+        assert_eq!(
+            store.merge_authored_from_migration(old_arc.clone().into())?,
+            2
+        );
+        new_arc
+            .guard()
+            .with_commit(|writer| store.flush_to_txn(writer))?;
+
+        let store = SourceChainBuf::new(new_arc.clone().into())?;
+        let element = store.get_element(agent_header.as_hash())?.unwrap();
+        assert_eq!(element.header(), agent_header.as_content());
+        assert_eq!(element.entry().as_option(), agent_entry.as_ref());
+        assert!(store.get_element(dna_header.as_hash())?.is_some());
+
+        // The new chain is still empty
+        assert!(store.is_empty());
+        assert_eq!(store.chain_head(), None);
+        assert_eq!(store.get_header(agent_header.as_hash())?, None);
+        Ok(())
+    }
 
-            assert_eq!(parsed[1]["element"]["header"]["type"], "Dna");
-            assert_eq!(parsed[1]["element"]["entry"], serde_json::Value::Null);
+    #[tokio::test(threaded_scheduler)]
+    async fn binary_export_round_trips() -> SourceChainResult<()> {
+        let source_env = test_cell_env();
+        let source_arc = source_env.env();
+        let dest_env = test_cell_env();
+        let dest_arc = dest_env.env();
+
+        let (_, dna_header, dna_entry, agent_header, agent_entry) = fixtures();
+        let mut source = SourceChainBuf::new(source_arc.clone().into())?;
+        source
+            .put_raw(dna_header.as_content().clone(), dna_entry)
+            .await?;
+        source
+            .put_raw(agent_header.as_content().clone(), agent_entry)
+            .await?;
+        let mut export = Vec::new();
+        source.export_binary(&mut export)?;
+
+        let mut dest = SourceChainBuf::new(dest_arc.clone().into())?;
+        dest.import_binary(&export[..]).await?;
+        dest_arc
+            .guard()
+            .with_commit(|writer| dest.flush_to_txn(writer))?;
+
+        // The same elements, with the same signatures
+        let mut dest = SourceChainBuf::new(dest_arc.clone().into())?;
+        assert_eq!(dest.len(), 2);
+        for i in 0..2 {
+            let element = dest.get_at_index(i)?.unwrap();
+            element.signed_header().validate().await?;
+            assert_eq!(Some(element), source.get_at_index(i)?);
+        }
+
+        // The export can't be imported on top of itself
+        assert_matches!(
+            dest.import_binary(&export[..]).await,
+            Err(SourceChainError::InvalidPreviousHeader(_))
+        );
+
+        // A truncated export is rejected without writing anything
+        let truncated_env = test_cell_env();
+        let mut truncated = SourceChainBuf::new(truncated_env.env().into())?;
+        assert_matches!(
+            truncated.import_binary(&export[..export.len() - 1]).await,
+            Err(SourceChainError::IoError(_))
+        );
+        assert_eq!(truncated.len(), 0);
+
+        // So is a frame claiming to be longer than any element can be
+        let mut oversized = export.clone();
+        oversized.extend_from_slice(&u32::MAX.to_be_bytes());
+        assert_matches!(
+            truncated.import_binary(&oversized[..]).await,
+            Err(SourceChainError::IoError(e)) if e.kind() == std::io::ErrorKind::InvalidData
+        );
+        assert_eq!(truncated.len(), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn replay_signed_headers() -> SourceChainResult<()> {
+        let source_env = test_cell_env();
+        let dest_env = test_cell_env();
+        let dest_arc = dest_env.env();
+
+        let agent = fake_agent_pubkey_1();
+        let mut source = SourceChainBuf::new(source_env.env().into())?;
+        source
+            .genesis(fake_dna_file("a").dna_hash().clone(), agent.clone(), None)
+            .await?;
+        put_app_entries(&mut source, 2).await?;
+        let source_elements = source.get_chain_section(0, 5)?;
+        let mut headers = Vec::new();
+        let mut entries = HashMap::new();
+        for element in source_elements.clone() {
+            let (signed_header, entry) = element.into_inner();
+            if let Some(entry) = entry.into_option() {
+                entries.insert(EntryHash::with_data_sync(&entry), entry);
+            }
+            headers.push(signed_header);
         }
 
+        let mut dest = SourceChainBuf::new(dest_arc.clone().into())?;
+        assert_eq!(
+            dest.replay_signed_headers(&agent, headers[..3].to_vec(), entries.clone())
+                .await?,
+            3
+        );
+        // Headers already on the chain are skipped
+        assert_eq!(
+            dest.replay_signed_headers(&agent, headers.clone(), entries.clone())
+                .await?,
+            2
+        );
+        dest_arc
+            .guard()
+            .with_commit(|writer| dest.flush_to_txn(writer))?;
+
+        // The same elements, with the same signatures
+        let mut dest = SourceChainBuf::new(dest_arc.clone().into())?;
+        assert_eq!(dest.get_chain_section(0, 5)?, source_elements);
+        assert_eq!(
+            dest.replay_signed_headers(&agent, headers.clone(), entries.clone())
+                .await?,
+            0
+        );
+        assert_eq!(dest.len(), 5);
+
+        // A bad signature is rejected without writing anything
+        let (header, _) = headers[2].clone().into_header_and_signature();
+        let mut forged = headers.clone();
+        forged[2] = SignedHeaderHashed::with_presigned(header, Signature(vec![1; 64]));
+        let forged_env = test_cell_env();
+        let mut forged_dest = SourceChainBuf::new(forged_env.env().into())?;
+        assert_matches!(
+            forged_dest
+                .replay_signed_headers(&agent, forged, entries.clone())
+                .await,
+            Err(SourceChainError::InvalidSignature)
+        );
+        assert_eq!(forged_dest.len(), 0);
+
+        // So is an entry which doesn't match its header
+        let agent_entry_hash = headers[2].header().entry_data().unwrap().0.clone();
+        let mut wrong_entries = entries;
+        wrong_entries.insert(
+            agent_entry_hash.clone(),
+            Entry::Agent(fake_agent_pubkey_2()),
+        );
+        let wrong_env = test_cell_env();
+        let mut wrong_dest = SourceChainBuf::new(wrong_env.env().into())?;
+        assert_matches!(
+            wrong_dest.replay_signed_headers(&agent, headers, wrong_entries).await,
+            Err(SourceChainError::InvalidStructure(
+                ChainInvalidReason::HeaderAndEntryMismatch(h)
+            )) if h == agent_entry_hash
+        );
+        assert_eq!(wrong_dest.len(), 0);
+
+        // And another agent's chain, even onto an empty chain
+        let other_env = test_cell_env();
+        let mut other_source = SourceChainBuf::new(other_env.env().into())?;
+        other_source
+            .genesis(
+                fake_dna_file("a").dna_hash().clone(),
+                fake_agent_pubkey_2(),
+                None,
+            )
+            .await?;
+        let other_headers: Vec<_> = other_source
+            .get_chain_section(0, 3)?
+            .into_iter()
+            .map(|element| element.into_inner().0)
+            .collect();
+        let other_dest_env = test_cell_env();
+        let mut other_dest = SourceChainBuf::new(other_dest_env.env().into())?;
+        assert_matches!(
+            other_dest
+                .replay_signed_headers(&agent, other_headers.clone(), HashMap::new())
+                .await,
+            Err(SourceChainError::InvalidStructure(
+                ChainInvalidReason::WrongAuthor(h)
+            )) if h == *other_headers[0].header_address()
+        );
+        assert_eq!(other_dest.len(), 0);
+
+        // Nor can another agent replay onto this agent's chain
+        assert_matches!(
+            dest.replay_signed_headers(&fake_agent_pubkey_2(), other_headers, HashMap::new())
+                .await,
+            Err(SourceChainError::InvalidStructure(
+                ChainInvalidReason::WrongAuthor(_)
+            ))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn chain_digest_changes_with_elements() -> SourceChainResult<()> {
+        let (env_a, env_b) = (test_cell_env(), test_cell_env());
+        let (_, dna_header, dna_entry, agent_header, agent_entry) = fixtures();
+        let mut a = SourceChainBuf::new(env_a.env().into())?;
+        let mut b = SourceChainBuf::new(env_b.env().into())?;
+        let empty_digest = a.chain_digest().await?;
+        assert_eq!(empty_digest, b.chain_digest().await?);
+
+        for chain in vec![&mut a, &mut b] {
+            chain
+                .put_raw(dna_header.as_content().clone(), dna_entry.clone())
+                .await?;
+        }
+        let digest = a.chain_digest().await?;
+        assert_ne!(digest, empty_digest);
+        assert_eq!(digest, b.chain_digest().await?);
+
+        // Diverging copies have different digests
+        a.put_raw(agent_header.as_content().clone(), agent_entry)
+            .await?;
+        assert_ne!(a.chain_digest().await?, digest);
+        assert_eq!(b.chain_digest().await?, digest);
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn dht_coverage_arc_shrinks_with_network_size() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let agent_pubkey = fake_agent_pubkey_1();
+        let mut store = SourceChainBuf::new(test_env.env().into())?;
+        assert_matches!(
+            store.compute_dht_coverage_arc(10),
+            Err(SourceChainError::InvalidStructure(
+                ChainInvalidReason::GenesisDataMissing
+            ))
+        );
+        store
+            .genesis(
+                fake_dna_file("a").dna_hash().clone(),
+                agent_pubkey.clone(),
+                None,
+            )
+            .await?;
+
+        // A small network holds everything
+        let arc = store.compute_dht_coverage_arc(DHT_REDUNDANCY_TARGET)?;
+        assert_eq!(arc, DhtArc::new(agent_pubkey.get_loc(), MAX_HALF_LENGTH));
+
+        let half_network = store.compute_dht_coverage_arc(DHT_REDUNDANCY_TARGET * 2)?;
+        let big_network = store.compute_dht_coverage_arc(DHT_REDUNDANCY_TARGET * 1000)?;
+        assert_eq!(half_network.center_loc, arc.center_loc);
+        // Rounded up, so the ring is never left uncovered
+        assert_eq!(half_network.half_length, (MAX_HALF_LENGTH + 1) / 2);
+        assert!(big_network.half_length < half_network.half_length);
+        assert!(big_network.half_length > 0);
+        assert!(big_network.contains(agent_pubkey.get_loc()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn proof_of_work_has_leading_zero_bits() {
+        let nonce = SourceChainBuf::calculate_proof_of_work(b"data", 12).unwrap();
+        assert!(SourceChainBuf::verify_proof_of_work(b"data", nonce, 12));
+        assert!(SourceChainBuf::verify_proof_of_work(b"data", nonce, 0));
+        // Any nonce does for no work at all
+        assert_eq!(
+            SourceChainBuf::calculate_proof_of_work(b"data", 0).unwrap(),
+            [0; 32]
+        );
+        // The work is tied to the data
+        let nonce_1 = SourceChainBuf::calculate_proof_of_work(b"data_1", 12).unwrap();
+        assert!(!SourceChainBuf::verify_proof_of_work(b"data", nonce_1, 12));
+        assert!(!SourceChainBuf::verify_proof_of_work(b"data", nonce_1, 255));
+        // Work which could take too long isn't attempted
+        assert_matches!(
+            SourceChainBuf::calculate_proof_of_work(b"data", MAX_PROOF_OF_WORK_DIFFICULTY + 1),
+            Err(SourceChainError::ProofOfWorkTooHard { difficulty, max })
+                if difficulty == MAX_PROOF_OF_WORK_DIFFICULTY + 1
+                    && max == MAX_PROOF_OF_WORK_DIFFICULTY
+        );
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn genesis_with_proof_of_work() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let agent_pubkey = fake_agent_pubkey_1();
+        let nonce = SourceChainBuf::calculate_proof_of_work(agent_pubkey.get_full_bytes(), 8)?;
+        let pow = ProofOfWork {
+            nonce,
+            difficulty: 8,
+        };
+        let mut store = SourceChainBuf::new(test_env.env().into())?;
+        assert_eq!(store.agent_proof_of_work()?, None);
+        store
+            .genesis(
+                fake_dna_file("a").dna_hash().clone(),
+                agent_pubkey.clone(),
+                Some(pow.clone().try_into().unwrap()),
+            )
+            .await?;
+        assert_eq!(store.agent_proof_of_work()?, Some(pow));
+
+        // Work done for another agent doesn't count
+        let test_env = test_cell_env();
+        let nonce = SourceChainBuf::calculate_proof_of_work(b"someone else", 8)?;
+        let mut store = SourceChainBuf::new(test_env.env().into())?;
+        store
+            .genesis(
+                fake_dna_file("a").dna_hash().clone(),
+                agent_pubkey,
+                Some(
+                    ProofOfWork {
+                        nonce,
+                        difficulty: 8,
+                    }
+                    .try_into()
+                    .unwrap(),
+                ),
+            )
+            .await?;
+        assert_eq!(store.agent_proof_of_work()?, None);
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn closed_chain_can_be_frozen_and_read() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let agent_pubkey = fake_agent_pubkey_1();
+        let clock = Arc::new(FakeClock::new(Timestamp(100, 0)));
+        let mut store = SourceChainBuf::new(test_env.env().into())?.with_clock(clock);
+        store
+            .genesis(
+                fake_dna_file("a").dna_hash().clone(),
+                agent_pubkey.clone(),
+                None,
+            )
+            .await?;
+        assert!(!store.is_closed()?);
+
+        let close_chain = Header::CloseChain(header::CloseChain {
+            author: agent_pubkey.clone(),
+            timestamp: Timestamp(101, 0).into(),
+            header_seq: 3,
+            prev_header: store.chain_head().unwrap().clone(),
+            new_dna_hash: fake_dna_file("b").dna_hash().clone(),
+        });
+        let close_hash = store.put_raw(close_chain, None).await?;
+        assert!(store.is_closed()?);
+
+        let frozen = store.freeze();
+        assert!(frozen.is_closed()?);
+        assert_eq!(frozen.len(), 4);
+        assert_eq!(frozen.chain_head(), Some(&close_hash));
+        assert_eq!(frozen.agent_pubkey()?, Some(agent_pubkey));
+        assert!(frozen.get_element(&close_hash)?.is_some());
+        assert_eq!(frozen.iter_back().count()?, 4);
+        let json: serde_json::Value = serde_json::from_str(&frozen.dump_as_json().await?)?;
+        assert_eq!(json.as_array().unwrap().len(), 4);
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn estimated_disk_size_grows_with_a_large_entry() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+        let author = fake_agent_pubkey_1();
+        let mut store = SourceChainBuf::new(arc.clone().into())?;
+        store
+            .genesis(fake_dna_file("a").dna_hash().clone(), author.clone(), None)
+            .await?;
+        arc.guard()
+            .with_commit(|writer| store.flush_to_txn(writer))?;
+        let mut store = SourceChainBuf::new(arc.clone().into())?;
+        let before = store.estimated_disk_size()?;
+        assert!(before > 0);
+
+        let bytes: Vec<u8> = (0..512 * 1024).map(|i| i as u8).collect();
+        let entry = Entry::app(UnsafeBytes::from(bytes).into()).unwrap();
+        let header = Header::Create(header::Create {
+            author,
+            timestamp: Timestamp::now().into(),
+            header_seq: 3,
+            prev_header: store.chain_head().unwrap().clone(),
+            entry_type: header::EntryType::App(header::AppEntryType::new(
+                0.into(),
+                0.into(),
+                EntryVisibility::Public,
+            )),
+            entry_hash: EntryHash::with_data_sync(&entry),
+        });
+        store.put_raw(header, Some(entry)).await?;
+        // Writes in the scratch space aren't on disk yet
+        assert_eq!(store.estimated_disk_size()?, before);
+        arc.guard()
+            .with_commit(|writer| store.flush_to_txn(writer))?;
+
+        let store = SourceChainBuf::new(arc.clone().into())?;
+        let after = store.estimated_disk_size()?;
+        assert!(after >= before + 512 * 1024, "{} -> {}", before, after);
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn read_only_chain_can_be_read_but_not_written() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+
+        let (agent_pubkey, dna_header, dna_entry, agent_header, agent_entry) = fixtures();
+        {
+            let mut store = SourceChainBuf::new(arc.clone().into())?;
+            store
+                .put_raw(dna_header.as_content().clone(), dna_entry)
+                .await?;
+            arc.guard()
+                .with_commit(|writer| store.flush_to_txn(writer))?;
+        }
+
+        let mut store = SourceChainBuf::read_only(arc.clone().into())?;
+        assert!(store.is_read_only());
+        assert_eq!(
+            store
+                .iter_back()
+                .map(|h| Ok(h.header_address().clone()))
+                .collect::<Vec<_>>()?,
+            vec![dna_header.as_hash().clone()]
+        );
+        assert!(store.dump_as_json().await.is_ok());
+
+        assert_matches!(
+            store
+                .put_raw(agent_header.as_content().clone(), agent_entry.clone())
+                .await,
+            Err(SourceChainError::ReadOnly)
+        );
+        assert_matches!(
+            store
+                .put_raw_batch(vec![(agent_header.into_content(), agent_entry)])
+                .await,
+            Err(SourceChainError::ReadOnly)
+        );
+        assert_matches!(
+            store
+                .genesis(fake_dna_file("a").dna_hash().clone(), agent_pubkey, None)
+                .await,
+            Err(SourceChainError::ReadOnly)
+        );
+        assert_eq!(store.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn source_chain_buffer_stream_elements() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+
+        let (_agent_pubkey, dna_header, dna_entry, agent_header, agent_entry) = fixtures();
+
+        {
+            let mut store = SourceChainBuf::new(arc.clone().into()).unwrap();
+            store
+                .put_raw(dna_header.as_content().clone(), dna_entry.clone())
+                .await?;
+            arc.guard()
+                .with_commit(|writer| store.flush_to_txn(writer))?;
+        }
+
+        {
+            let mut store = SourceChainBuf::new(arc.clone().into()).unwrap();
+            // Leave this one in the scratch space
+            store
+                .put_raw(agent_header.as_content().clone(), agent_entry.clone())
+                .await?;
+
+            let elements: Vec<_> = store
+                .stream_elements()
+                .collect::<Vec<_>>()
+                .await
+                .into_iter()
+                .collect::<SourceChainResult<_>>()?;
+            let headers: Vec<_> = elements.iter().map(|e| e.header().clone()).collect();
+            assert_eq!(
+                vec![
+                    dna_header.as_content().clone(),
+                    agent_header.as_content().clone(),
+                ],
+                headers
+            );
+            assert_eq!(agent_entry.as_ref(), elements[1].entry().as_option());
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn source_chain_buffer_try_put_raw_conflicts() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+
+        let (agent_pubkey, dna_header, dna_entry, agent_header, agent_entry) = fixtures();
+
+        let mut store = SourceChainBuf::new(arc.clone().into()).unwrap();
+        let dna_hash = store
+            .try_put_raw(dna_header.as_content().clone(), dna_entry.clone())
+            .await?
+            .unwrap();
+        assert_eq!(&dna_hash, dna_header.as_hash());
+
+        // Putting the same header again conflicts with itself
+        assert_eq!(
+            store
+                .try_put_raw(dna_header.as_content().clone(), dna_entry.clone())
+                .await?,
+            Err(SequenceConflict {
+                header_hash: dna_hash.clone(),
+                header_seq: 0,
+            })
+        );
+
+        // A different header at the same position conflicts with the first
+        let other_dna_header = Header::Dna(header::Dna {
+            author: agent_pubkey,
+            timestamp: Timestamp(2, 0).into(),
+            hash: fake_dna_file("b").dna_hash().clone(),
+        });
+        assert_eq!(
+            store.try_put_raw(other_dna_header, None).await?,
+            Err(SequenceConflict {
+                header_hash: dna_hash,
+                header_seq: 0,
+            })
+        );
+        assert_eq!(store.len(), 1);
+
+        store
+            .try_put_raw(agent_header.as_content().clone(), agent_entry)
+            .await?
+            .unwrap();
+        assert_eq!(store.len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn source_chain_buffer_delete_entry() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+
+        let (agent_pubkey, dna_header, dna_entry, agent_header, agent_entry) = fixtures();
+
+        let mut store = SourceChainBuf::new(arc.clone().into()).unwrap();
+        store
+            .put_raw(dna_header.as_content().clone(), dna_entry)
+            .await?;
+        store
+            .put_raw(agent_header.as_content().clone(), agent_entry)
+            .await?;
+
+        let delete = header::Delete {
+            author: agent_pubkey.clone(),
+            timestamp: Timestamp(2, 0).into(),
+            header_seq: 2,
+            prev_header: agent_header.as_hash().clone(),
+            deletes_address: agent_header.as_hash().clone(),
+            deletes_entry_address: agent_pubkey.clone().into(),
+        };
+
+        // The deleted header must exist
+        let missing = header::Delete {
+            deletes_address: fake_header_hash(1),
+            ..delete.clone()
+        };
+        assert_matches!(
+            store.delete_entry(missing).await,
+            Err(SourceChainError::EntryNotFound(_))
+        );
+
+        // The deleted entry must match the header
+        let mismatched = header::Delete {
+            deletes_entry_address: fake_entry_hash(1),
+            ..delete.clone()
+        };
+        assert_matches!(
+            store.delete_entry(mismatched).await,
+            Err(SourceChainError::InvalidCommit(_))
+        );
+        assert_eq!(store.len(), 2);
+
+        let delete_hash = store.delete_entry(delete.clone()).await?;
+        assert_eq!(store.chain_head(), Some(&delete_hash));
+        assert_eq!(
+            store.get_header(&delete_hash)?.unwrap().header(),
+            &Header::Delete(delete)
+        );
+
+        Ok(())
+    }
+
+    /// Record the ops of an element as authored, and maybe published
+    async fn author_ops(
+        env: &EnvironmentWrite,
+        element: &Element,
+        published: bool,
+    ) -> SourceChainResult<()> {
+        let mut authored: AuthoredDhtOpsStore =
+            KvBufFresh::new(env.clone().into(), env.get_db(&*AUTHORED_DHT_OPS)?);
+        for op in produce_ops_from_element(element).await? {
+            let (op, hash) = DhtOpHashed::from_content_sync(op).into_inner();
+            let mut value = AuthoredDhtOpsValue::from_light(op.to_light().await);
+            if published {
+                value.last_publish_time = Some(Timestamp::now());
+            }
+            authored.put(hash, value)?;
+        }
+        env.guard()
+            .with_commit(|writer| authored.flush_to_txn(writer))?;
+        Ok(())
+    }
+
+    /// Put genesis and an app entry on the chain, then delete the app entry
+    /// at 10s, and the agent entry at 11s if asked.
+    /// Returns the app entry and the address of its header.
+    async fn chain_with_deleted_app_entry(
+        env: &EnvironmentWrite,
+        delete_agent: bool,
+    ) -> SourceChainResult<(EntryHash, HeaderHash)> {
+        let (agent_pubkey, dna_header, dna_entry, agent_header, agent_entry) = fixtures();
+        let mut store = SourceChainBuf::new(env.clone().into())?;
+        store
+            .put_raw(dna_header.as_content().clone(), dna_entry)
+            .await?;
+        store
+            .put_raw(agent_header.as_content().clone(), agent_entry)
+            .await?;
+        let init_hash = store
+            .put_raw(
+                Header::InitZomesComplete(header::InitZomesComplete {
+                    author: agent_pubkey.clone(),
+                    timestamp: Timestamp(2, 0).into(),
+                    header_seq: 2,
+                    prev_header: agent_header.as_hash().clone(),
+                }),
+                None,
+            )
+            .await?;
+        let app_entry = Entry::app(SerializedBytes::try_from(()).unwrap()).unwrap();
+        let entry_hash = EntryHash::with_data_sync(&app_entry);
+        let app_hash = store
+            .put_raw(
+                Header::Create(header::Create {
+                    author: agent_pubkey.clone(),
+                    timestamp: Timestamp(3, 0).into(),
+                    header_seq: 3,
+                    prev_header: init_hash,
+                    entry_type: header::EntryType::App(header::AppEntryType::new(
+                        0.into(),
+                        0.into(),
+                        EntryVisibility::Public,
+                    )),
+                    entry_hash: entry_hash.clone(),
+                }),
+                Some(app_entry),
+            )
+            .await?;
+        let mut head = store
+            .delete_entry(header::Delete {
+                author: agent_pubkey.clone(),
+                timestamp: Timestamp(10, 0).into(),
+                header_seq: 4,
+                prev_header: app_hash.clone(),
+                deletes_address: app_hash.clone(),
+                deletes_entry_address: entry_hash.clone(),
+            })
+            .await?;
+        if delete_agent {
+            head = store
+                .delete_entry(header::Delete {
+                    author: agent_pubkey.clone(),
+                    timestamp: Timestamp(11, 0).into(),
+                    header_seq: 5,
+                    prev_header: head,
+                    deletes_address: agent_header.as_hash().clone(),
+                    deletes_entry_address: agent_pubkey.clone().into(),
+                })
+                .await?;
+        }
+        assert_eq!(store.chain_head(), Some(&head));
+        env.guard()
+            .with_commit(|writer| store.flush_to_txn(writer))?;
+        Ok((entry_hash, app_hash))
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn prune_deleted_entries_keeps_headers() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+
+        let (entry_hash, app_hash) = chain_with_deleted_app_entry(&arc, false).await?;
+        let clock = Arc::new(FakeClock::new(Timestamp(15, 0)));
+        let mut store = SourceChainBuf::new(arc.clone().into())?.with_clock(clock.clone());
+        author_ops(&arc, &store.get_element(&app_hash)?.unwrap(), true).await?;
+
+        assert_eq!(store.prune_deleted_entries(PrunePolicy::Never).await?, 0);
+        assert_eq!(
+            store
+                .prune_deleted_entries(PrunePolicy::AfterSeconds(10))
+                .await?,
+            0
+        );
+        assert!(store.get_entry(&entry_hash)?.is_some());
+
+        clock.advance(5);
+        assert_eq!(
+            store
+                .prune_deleted_entries(PrunePolicy::AfterSeconds(10))
+                .await?,
+            1
+        );
+        assert_eq!(
+            store
+                .prune_deleted_entries(PrunePolicy::Immediately)
+                .await?,
+            0
+        );
+        let delete_hash = store.chain_head().unwrap().clone();
+        arc.guard()
+            .with_commit(|writer| store.flush_to_txn(writer))?;
+
+        let store = SourceChainBuf::new(arc.clone().into())?;
+        assert_eq!(store.get_entry(&entry_hash)?, None);
+        assert!(store.get_header(&app_hash)?.is_some());
+        assert!(store.get_header(&delete_hash)?.is_some());
+        assert_eq!(store.len(), 5);
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn prune_deleted_entries_spares_genesis_and_unpublished() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+
+        let (agent_pubkey, _, _, agent_header, _) = fixtures();
+        let agent_entry_hash: EntryHash = agent_pubkey.into();
+        let (entry_hash, app_hash) = chain_with_deleted_app_entry(&arc, true).await?;
+        let mut store = SourceChainBuf::new(arc.clone().into())?;
+        let app_element = store.get_element(&app_hash)?.unwrap();
+
+        // No ops have been produced yet
+        assert_eq!(
+            store
+                .prune_deleted_entries(PrunePolicy::Immediately)
+                .await?,
+            0
+        );
+
+        // The ops are produced but not published
+        author_ops(&arc, &app_element, false).await?;
+        assert_eq!(
+            store
+                .prune_deleted_entries(PrunePolicy::Immediately)
+                .await?,
+            0
+        );
+
+        // The genesis agent entry stays even once published
+        author_ops(
+            &arc,
+            &store.get_element(agent_header.as_hash())?.unwrap(),
+            true,
+        )
+        .await?;
+        author_ops(&arc, &app_element, true).await?;
+        assert_eq!(
+            store
+                .prune_deleted_entries(PrunePolicy::Immediately)
+                .await?,
+            1
+        );
+        assert_eq!(store.get_entry(&entry_hash)?, None);
+        assert!(store.get_entry(&agent_entry_hash)?.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn put_raw_rejects_non_monotonic_timestamps() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+
+        let (agent_pubkey, dna_header, dna_entry, agent_header, agent_entry) = fixtures();
+
+        let mut store = SourceChainBuf::new(arc.clone().into()).unwrap();
+        store
+            .put_raw(dna_header.as_content().clone(), dna_entry)
+            .await?;
+        store
+            .put_raw(agent_header.as_content().clone(), agent_entry)
+            .await?;
+
+        // The head was timestamped at 1s
+        for secs in &[0, 1] {
+            let header = Header::InitZomesComplete(header::InitZomesComplete {
+                author: agent_pubkey.clone(),
+                timestamp: Timestamp(*secs, 0).into(),
+                header_seq: 2,
+                prev_header: agent_header.as_hash().clone(),
+            });
+            assert_matches!(
+                store.put_raw(header, None).await,
+                Err(SourceChainError::NonMonotonicTimestamp { prev, new })
+                    if prev == Timestamp(1, 0) && new == Timestamp(*secs, 0)
+            );
+        }
+        assert_eq!(store.len(), 2);
+
+        let header = Header::InitZomesComplete(header::InitZomesComplete {
+            author: agent_pubkey,
+            timestamp: Timestamp(1, 1).into(),
+            header_seq: 2,
+            prev_header: agent_header.as_hash().clone(),
+        });
+        store.put_raw(header, None).await?;
+        assert_eq!(store.len(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn put_raw_batch_keeps_chain_continuity() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+
+        let (agent_pubkey, dna_header, dna_entry, agent_header, agent_entry) = fixtures();
+        let init_header = |prev_header: HeaderHash, header_seq| {
+            Header::InitZomesComplete(header::InitZomesComplete {
+                author: agent_pubkey.clone(),
+                timestamp: Timestamp(2, 0).into(),
+                header_seq,
+                prev_header,
+            })
+        };
+
+        let mut store = SourceChainBuf::new(arc.clone().into()).unwrap();
+        let hashes = store
+            .put_raw_batch(vec![
+                (dna_header.as_content().clone(), dna_entry),
+                (agent_header.as_content().clone(), agent_entry),
+            ])
+            .await?;
+        assert_eq!(
+            hashes,
+            vec![dna_header.as_hash().clone(), agent_header.as_hash().clone()]
+        );
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.chain_head(), Some(agent_header.as_hash()));
+
+        // Nothing is written if any header in the batch is out of place
+        let good = init_header(agent_header.as_hash().clone(), 2);
+        let good_hash = HeaderHash::with_data_sync(&good);
+        let wrong_prev = init_header(dna_header.as_hash().clone(), 3);
+        assert_matches!(
+            store
+                .put_raw_batch(vec![(good.clone(), None), (wrong_prev, None)])
+                .await,
+            Err(SourceChainError::InvalidPreviousHeader(_))
+        );
+        let wrong_seq = init_header(agent_header.as_hash().clone(), 3);
+        assert_matches!(
+            store.put_raw_batch(vec![(wrong_seq, None)]).await,
+            Err(SourceChainError::InvalidStructure(
+                ChainInvalidReason::NonMonotonicSequence(1, 3)
+            ))
+        );
+        assert_eq!(store.len(), 2);
+
+        assert_eq!(
+            store.put_raw_batch(vec![(good, None)]).await?,
+            vec![good_hash.clone()]
+        );
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.chain_head(), Some(&good_hash));
+        assert_eq!(store.get_at_index(2)?.unwrap().header_address(), &good_hash);
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn chain_section_is_a_forward_sub_range() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+        let dna = fake_dna_file("a");
+
+        let mut store = SourceChainBuf::new(arc.clone().into())?;
+        store
+            .genesis(dna.dna_hash().clone(), fake_agent_pubkey_1(), None)
+            .await?;
+        assert_eq!(store.len(), 3);
+
+        let section = store.get_chain_section(1, 3)?;
+        let seqs: Vec<_> = section.iter().map(|el| el.header().header_seq()).collect();
+        assert_eq!(seqs, vec![1, 2]);
+        assert_eq!(section[0], store.get_at_index(1)?.unwrap());
+
+        assert_matches!(
+            store.get_chain_section(2, 2),
+            Err(SourceChainError::InvalidRange { .. })
+        );
+        assert_matches!(
+            store.get_chain_section(0, 4),
+            Err(SourceChainError::InvalidRange { len: 3, .. })
+        );
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn source_chain_buffer_genesis_addresses() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+        let agent_pubkey = fake_agent_pubkey_1();
+        let dna = fake_dna_file("a");
+
+        let mut store = SourceChainBuf::new(arc.clone().into())?;
+        assert_eq!(store.get_dna_header_address()?, None);
+        assert_eq!(store.get_agent_validation_pkg_address()?, None);
+
+        store
+            .genesis(dna.dna_hash().clone(), agent_pubkey, None)
+            .await?;
+
+        let dna_header = store.get_dna_header_address()?.unwrap();
+        let avp_header = store.get_agent_validation_pkg_address()?.unwrap();
+        match store.get_header(&dna_header)?.unwrap().header() {
+            Header::Dna(_) => (),
+            h => panic!("expected a Dna header, got {:?}", h),
+        }
+        match store.get_header(&avp_header)?.unwrap().header() {
+            Header::AgentValidationPkg(_) => (),
+            h => panic!("expected an AgentValidationPkg header, got {:?}", h),
+        }
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn source_chain_buffer_dump_entries_json() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+
+        let (_agent_pubkey, dna_header, dna_entry, agent_header, agent_entry) = fixtures();
+
+        {
+            let mut store = SourceChainBuf::new(arc.clone().into()).unwrap();
+            store
+                .put_raw(dna_header.as_content().clone(), dna_entry)
+                .await?;
+            store
+                .put_raw(agent_header.as_content().clone(), agent_entry)
+                .await?;
+
+            arc.guard()
+                .with_commit(|writer| store.flush_to_txn(writer))?;
+        }
+
+        {
+            let store = SourceChainBuf::new(arc.clone().into()).unwrap();
+            let json = store.dump_as_json().await?;
+            let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+            assert_eq!(parsed[0]["element"]["header"]["type"], "Create");
+            assert_eq!(parsed[0]["element"]["header"]["entry_type"], "AgentPubKey");
+            assert_eq!(parsed[0]["element"]["entry"]["entry_type"], "Agent");
+            assert_ne!(
+                parsed[0]["element"]["entry"]["entry"],
+                serde_json::Value::Null
+            );
+
+            assert_eq!(parsed[1]["element"]["header"]["type"], "Dna");
+            assert_eq!(parsed[1]["element"]["entry"], serde_json::Value::Null);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn get_elements_with_entry_type() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+        let author = fake_agent_pubkey_1();
+        let app_type = |id: u8| {
+            header::EntryType::App(header::AppEntryType::new(
+                id.into(),
+                0.into(),
+                EntryVisibility::Public,
+            ))
+        };
+        let entry = Entry::app(SerializedBytes::try_from(()).unwrap()).unwrap();
+
+        let mut store = SourceChainBuf::new(arc.clone().into())?;
+        store
+            .genesis(fake_dna_file("a").dna_hash().clone(), author.clone(), None)
+            .await?;
+        let mut expected = Vec::new();
+        for i in 0..6 {
+            let header = Header::Create(header::Create {
+                author: author.clone(),
+                timestamp: Timestamp::now().into(),
+                header_seq: 3 + i,
+                prev_header: store.chain_head().unwrap().clone(),
+                entry_type: app_type((i % 2) as u8),
+                entry_hash: EntryHash::with_data_sync(&entry),
+            });
+            let hash = store.put_raw(header, Some(entry.clone())).await?;
+            if i % 2 == 1 {
+                expected.push(hash);
+            }
+        }
+
+        let header_hashes = |elements: Vec<Element>| {
+            elements
+                .into_iter()
+                .map(|e| e.header_address().clone())
+                .collect::<Vec<_>>()
+        };
+
+        // The index includes the scratch space
+        assert_eq!(
+            header_hashes(store.get_elements_with_entry_type(&app_type(1))?),
+            expected
+        );
+        arc.guard()
+            .with_commit(|writer| store.flush_to_txn(writer))?;
+
+        let store = SourceChainBuf::new(arc.clone().into())?;
+        assert_eq!(
+            header_hashes(store.get_elements_with_entry_type(&app_type(1))?),
+            expected
+        );
+        assert_eq!(
+            store
+                .get_elements_with_entry_type(&header::EntryType::AgentPubKey)?
+                .len(),
+            1
+        );
+        assert!(store.get_elements_with_entry_type(&app_type(2))?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn backfill_indexes_entry_types_put_before_the_index() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+        let mut store = SourceChainBuf::new(arc.clone().into())?;
+        store
+            .genesis(
+                fake_dna_file("a").dna_hash().clone(),
+                fake_agent_pubkey_1(),
+                None,
+            )
+            .await?;
+        arc.guard()
+            .with_commit(|writer| store.flush_to_txn(writer))?;
+
+        // A chain written before the index existed
+        let mut entry_types: KvvBufUsed<super::EntryTypeKey, HeaderHash> =
+            KvvBufUsed::new(arc.get_db(&*CHAIN_ENTRY_TYPES)?);
+        arc.guard()
+            .with_commit(|writer| entry_types.clear_all(writer))?;
+        let agent_type = header::EntryType::AgentPubKey;
+        let store = SourceChainBuf::new(arc.clone().into())?;
+        assert!(store.get_elements_with_entry_type(&agent_type)?.is_empty());
+
+        backfill_entry_type_index(&arc)?;
+        let store = SourceChainBuf::new(arc.clone().into())?;
+        let agents = store.get_elements_with_entry_type(&agent_type)?;
+        assert_eq!(agents.len(), 1);
+        assert_eq!(agents[0].header().header_seq(), 2);
+
+        // Once backfilled the index isn't rebuilt
+        arc.guard()
+            .with_commit(|writer| entry_types.clear_all(writer))?;
+        backfill_entry_type_index(&arc)?;
+        let store = SourceChainBuf::new(arc.clone().into())?;
+        assert!(store.get_elements_with_entry_type(&agent_type)?.is_empty());
+        Ok(())
+    }
+
+    /// Put `n` app entries on a chain which has had genesis
+    async fn put_app_entries(
+        store: &mut SourceChainBuf,
+        n: u32,
+    ) -> SourceChainResult<Vec<HeaderHash>> {
+        let author = fake_agent_pubkey_1();
+        let entry = Entry::app(SerializedBytes::try_from(()).unwrap()).unwrap();
+        let mut hashes = Vec::new();
+        for _ in 0..n {
+            let header = Header::Create(header::Create {
+                author: author.clone(),
+                timestamp: Timestamp::now().into(),
+                header_seq: store.len() as u32,
+                prev_header: store.chain_head().unwrap().clone(),
+                entry_type: header::EntryType::App(header::AppEntryType::new(
+                    0.into(),
+                    0.into(),
+                    EntryVisibility::Public,
+                )),
+                entry_hash: EntryHash::with_data_sync(&entry),
+            });
+            hashes.push(store.put_raw(header, Some(entry.clone())).await?);
+        }
+        Ok(hashes)
+    }
+
+    fn header_hashes(elements: Vec<Element>) -> Vec<HeaderHash> {
+        elements
+            .into_iter()
+            .map(|e| e.header_address().clone())
+            .collect()
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn get_by_tag() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+
+        let mut store = SourceChainBuf::new(arc.clone().into())?;
+        store
+            .genesis(
+                fake_dna_file("a").dna_hash().clone(),
+                fake_agent_pubkey_1(),
+                None,
+            )
+            .await?;
+        let hashes = put_app_entries(&mut store, 4).await?;
+        store.tag_element(&hashes[3], b"red".to_vec())?;
+        store.tag_element(&hashes[1], b"red".to_vec())?;
+        // Tagging twice has no further effect
+        store.tag_element(&hashes[1], b"red".to_vec())?;
+        // A tag which starts with another tag is a different tag
+        store.tag_element(&hashes[2], b"redder".to_vec())?;
+
+        // The index includes the scratch space, and is in chain order
+        let red = vec![hashes[1].clone(), hashes[3].clone()];
+        assert_eq!(header_hashes(store.get_by_tag(b"red")?), red);
+        arc.guard()
+            .with_commit(|writer| store.flush_to_txn(writer))?;
+
+        let store = SourceChainBuf::new(arc.clone().into())?;
+        assert_eq!(header_hashes(store.get_by_tag(b"red")?), red);
+        assert_eq!(
+            header_hashes(store.get_by_tag(b"redder")?),
+            vec![hashes[2].clone()]
+        );
+        assert!(store.get_by_tag(b"re")?.is_empty());
+        assert!(store.get_by_tag(b"blue")?.is_empty());
+        assert_matches!(store.get_by_tag(b""), Err(SourceChainError::EmptyTag));
+        Ok(())
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn get_by_tag_with_many_tags() -> SourceChainResult<()> {
+        let test_env = test_cell_env();
+        let arc = test_env.env();
+
+        let mut store = SourceChainBuf::new(arc.clone().into())?;
+        store
+            .genesis(
+                fake_dna_file("a").dna_hash().clone(),
+                fake_agent_pubkey_1(),
+                None,
+            )
+            .await?;
+        let hashes = put_app_entries(&mut store, 3).await?;
+        for tag in &[b"a".to_vec(), b"b".to_vec(), b"c".to_vec()] {
+            store.tag_element(&hashes[0], tag.clone())?;
+        }
+        store.tag_element(&hashes[2], b"b".to_vec())?;
+        arc.guard()
+            .with_commit(|writer| store.flush_to_txn(writer))?;
+
+        let mut store = SourceChainBuf::new(arc.clone().into())?;
+        assert_eq!(
+            header_hashes(store.get_by_tag(b"a")?),
+            vec![hashes[0].clone()]
+        );
+        assert_eq!(
+            header_hashes(store.get_by_tag(b"b")?),
+            vec![hashes[0].clone(), hashes[2].clone()]
+        );
+        assert_eq!(
+            header_hashes(store.get_by_tag(b"c")?),
+            vec![hashes[0].clone()]
+        );
+
+        // Only elements on the chain can be tagged
+        let missing = fake_header_hash(1);
+        assert_matches!(
+            store.tag_element(&missing, b"a".to_vec()),
+            Err(SourceChainError::ElementMissing(_))
+        );
+        assert_matches!(
+            store.tag_element(&hashes[1], Vec::new()),
+            Err(SourceChainError::EmptyTag)
+        );
         Ok(())
     }
 
@@ -1,5 +1,6 @@
 //! # Validation Database Types
 
+use super::validation_receipts_db::ValidationAnnotation;
 use holo_hash::{AnyDhtHash, DhtOpHash};
 use holochain_serialized_bytes::prelude::*;
 use holochain_state::{
@@ -8,8 +9,12 @@ use holochain_state::{
     error::DatabaseResult,
     prelude::{EnvironmentRead, GetDb},
 };
-use holochain_types::{dht_op::DhtOpLight, Timestamp};
+use holochain_types::{
+    dht_op::{DhtOpLight, OpRequirements},
+    Timestamp,
+};
 use shrinkwraprs::Shrinkwrap;
+use std::time::Duration;
 
 #[derive(Shrinkwrap)]
 #[shrinkwrap(mutable)]
@@ -34,6 +39,14 @@ pub struct ValidationLimboValue {
     pub last_try: Option<Timestamp>,
     /// Number of times we have tried to validate the op
     pub num_tries: u32,
+    /// What validation has noticed about the op so far,
+    /// to pass on to its author in our receipt
+    #[serde(default)]
+    pub annotations: Vec<ValidationAnnotation>,
+    /// The requirements the op's author claimed for it when publishing,
+    /// checked against our own entry defs by sys validation
+    #[serde(default)]
+    pub requirements: Option<OpRequirements>,
 }
 
 /// The status of a [DhtOp] in limbo
@@ -43,6 +56,14 @@ pub enum ValidationLimboStatus {
     Pending,
     /// Is waiting for dependencies so the op can proceed to system validation
     AwaitingSysDeps(AnyDhtHash),
+    /// Is held back because its header's timestamp is too far ahead of our
+    /// clock, and will be system validated again once it no longer is
+    Delayed {
+        /// When the header's timestamp will be within tolerance
+        until: Timestamp,
+        /// How far ahead of our clock the header's timestamp was
+        observed_skew: Duration,
+    },
     /// Is awaiting to be app validated
     SysValidated,
     /// Is waiting for dependencies so the op can proceed to app validation
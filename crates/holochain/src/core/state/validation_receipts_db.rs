@@ -5,12 +5,13 @@ use holo_hash::{AgentPubKey, DhtOpHash};
 use holochain_keystore::{AgentPubKeyExt, KeystoreSender};
 use holochain_serialized_bytes::prelude::*;
 use holochain_state::{
-    buffer::{BufferedStore, KvvBufUsed},
+    buffer::{BufferedStore, KvBufFresh, KvvBufUsed},
     db::GetDb,
     error::{DatabaseError, DatabaseResult},
     prelude::{Readable, Writer},
 };
 use holochain_zome_types::signature::Signature;
+use std::time::Duration;
 
 /// The result of a DhtOp Validation.
 #[derive(
@@ -25,6 +26,22 @@ pub enum ValidationResult {
     // Warrant { .. },
 }
 
+/// Something a validator noticed about an op which its author may want to
+/// know about, whatever the result of validating it.
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(tag = "type")]
+pub enum ValidationAnnotation {
+    /// The timestamp of the op's header was too far ahead of the validator's
+    /// clock, so validation was delayed until the clock caught up.
+    /// The author's clock is probably running fast.
+    ClockSkewSuspected {
+        /// How far ahead of the validator's clock the timestamp was
+        observed_skew: Duration,
+    },
+}
+
 /// Validation receipt content - to be signed.
 #[derive(
     Debug,
@@ -47,6 +64,10 @@ pub struct ValidationReceipt {
 
     /// the remote validator which is signing this receipt.
     pub validator: AgentPubKey,
+
+    /// anything the validator noticed about the op along the way.
+    #[serde(default)]
+    pub annotations: Vec<ValidationAnnotation>,
 }
 
 impl ValidationReceipt {
@@ -81,6 +102,45 @@ pub struct SignedValidationReceipt {
     pub validator_signature: Signature,
 }
 
+/// Several signed validation receipts sent to their author in one message.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    SerializedBytes,
+    derive_more::From,
+    derive_more::Into,
+)]
+pub struct ValidationReceiptBundle(pub Vec<SignedValidationReceipt>);
+
+/// Decode the receipts sent in a validation receipt message, which holds
+/// either a [ValidationReceiptBundle] or a single [SignedValidationReceipt].
+pub fn decode_validation_receipts(
+    receipts: SerializedBytes,
+) -> Result<Vec<SignedValidationReceipt>, SerializedBytesError> {
+    match ValidationReceiptBundle::try_from(receipts.clone()) {
+        Ok(bundle) => Ok(bundle.0),
+        Err(_) => Ok(vec![SignedValidationReceipt::try_from(receipts)?]),
+    }
+}
+
+/// A validation receipt an op's publisher is waiting for
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PendingValidationReceipt {
+    /// The agent to send the receipt to
+    pub publisher: AgentPubKey,
+    /// What validation noticed about the op so far, to be sent with the receipt
+    #[serde(default)]
+    pub annotations: Vec<ValidationAnnotation>,
+}
+
+/// The ops whose publisher asked for a validation receipt, keyed by
+/// [DhtOpHash], along with the agent to send the receipt to.
+pub type PendingValidationReceiptsStore = KvBufFresh<DhtOpHash, PendingValidationReceipt>;
+
 /// The database/buffer for aggregating validation_receipts sent by remote
 /// nodes in charge of storage thereof.
 pub struct ValidationReceiptsBuf(KvvBufUsed<DhtOpHash, SignedValidationReceipt>);
@@ -166,6 +226,7 @@ mod tests {
             dht_op_hash: dht_op_hash.clone(),
             validation_result: ValidationResult::Valid,
             validator: agent,
+            annotations: Vec::new(),
         };
         receipt.sign(keystore).await.unwrap()
     }
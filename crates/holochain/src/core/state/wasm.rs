@@ -2,11 +2,12 @@ use holo_hash::WasmHash;
 use holochain_state::error::{DatabaseError, DatabaseResult};
 use holochain_state::exports::SingleStore;
 use holochain_state::{
-    buffer::CasBufFreshAsync,
+    buffer::{CasBufFreshAsync, KvStore},
     prelude::{BufferedStore, EnvironmentRead},
     transaction::Writer,
 };
 use holochain_types::dna::wasm::{DnaWasm, DnaWasmHashed};
+use serde::{Deserialize, Serialize};
 
 /// This is where wasm lives
 pub struct WasmBuf(CasBufFreshAsync<DnaWasm>);
@@ -34,6 +35,31 @@ impl BufferedStore for WasmBuf {
     }
 }
 
+/// The database of [WasmArtifact]s, keyed by the hash of the wasm they were compiled from
+pub type WasmArtifactStore = KvStore<WasmHash, WasmArtifact>;
+
+/// A wasm module compiled to machine code and serialized,
+/// so that it can be loaded without compiling it again
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WasmArtifact {
+    /// The engine version and target the module was compiled for.
+    /// An artifact is only loaded by the same engine on the same target.
+    pub engine: String,
+    /// The blake2b-256 hash of `bytes`, to catch corruption before loading
+    pub checksum: Vec<u8>,
+    /// The serialized module
+    pub bytes: Vec<u8>,
+}
+
+impl std::fmt::Debug for WasmArtifact {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmArtifact")
+            .field("engine", &self.engine)
+            .field("len", &self.bytes.len())
+            .finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,186 @@
+//! A bounded journal of the errors returned by a Cell's queue consumer
+//! workflows, kept in the Cell's environment for post-mortem debugging.
+//!
+//! A workflow run which fails with the same error as the last record for
+//! that workflow bumps the record's count instead of adding a new record,
+//! so a workflow stuck retrying a bad item doesn't flood the journal.
+
+use crate::core::workflow::error::WorkflowError;
+use holochain_state::{
+    buffer::{KvStore, KvStoreT},
+    db::{GetDb, WORKFLOW_ERRORS},
+    env::{EnvironmentRead, EnvironmentWrite},
+    error::DatabaseResult,
+    fresh_reader,
+    prelude::*,
+};
+use holochain_types::Timestamp;
+use serde::{Deserialize, Serialize};
+
+/// The most records kept in a journal. The oldest records are dropped first.
+pub const MAX_WORKFLOW_ERROR_RECORDS: usize = 100;
+
+/// The database used to store the journal of a Cell's workflow errors.
+/// It has only one key-value pair.
+pub type WorkflowErrorDb = KvStore<UnitDbKey, Vec<WorkflowErrorRecord>>;
+
+/// The queue consumer workflows which report into the journal
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum WorkflowName {
+    /// The SysValidation workflow
+    SysValidation,
+    /// The AppValidation workflow
+    AppValidation,
+    /// The DhtOpIntegration workflow
+    IntegrateDhtOps,
+    /// The ProduceDhtOps workflow
+    ProduceDhtOps,
+    /// The Publish workflow
+    PublishDhtOps,
+    /// The ValidationReceipt workflow
+    ValidationReceipt,
+}
+
+/// Why a workflow was run
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TriggerReason {
+    /// Another workflow or the Cell asked for the run
+    Triggered,
+    /// The workflow triggered itself after an incomplete or failed run
+    Retry,
+}
+
+/// A run (or run of identical failures) of a workflow which returned an error
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkflowErrorRecord {
+    /// The workflow which failed
+    pub workflow: WorkflowName,
+    /// When this error was first seen
+    pub first_seen: Timestamp,
+    /// When this error was last seen
+    pub last_seen: Timestamp,
+    /// The error, as displayed
+    pub error: String,
+    /// Why the last failed run was triggered
+    pub trigger: TriggerReason,
+    /// How many items were waiting in the workflow's source queue when the
+    /// last failed run started, if the queue could be counted
+    pub items_attempted: Option<usize>,
+    /// How many times in a row this error was seen
+    pub count: u32,
+}
+
+/// Append a workflow error to the journal of a Cell's environment,
+/// coalescing it with the last record for the workflow if the error is the same.
+pub fn record_workflow_error(
+    env: &EnvironmentWrite,
+    workflow: WorkflowName,
+    trigger: TriggerReason,
+    items_attempted: Option<usize>,
+    error: &WorkflowError,
+) -> DatabaseResult<()> {
+    let db: WorkflowErrorDb = KvStore::new(env.get_db(&*WORKFLOW_ERRORS)?);
+    let error = error.to_string();
+    let now = Timestamp::now();
+    env.guard().with_commit(|writer| {
+        let mut records = db.get(&*writer, &UnitDbKey)?.unwrap_or_default();
+        let last = records.iter().rposition(|r| r.workflow == workflow);
+        match last {
+            Some(i) if records[i].error == error => {
+                let last = &mut records[i];
+                last.last_seen = now;
+                last.trigger = trigger;
+                last.items_attempted = items_attempted;
+                last.count = last.count.saturating_add(1);
+            }
+            _ => {
+                records.push(WorkflowErrorRecord {
+                    workflow,
+                    first_seen: now,
+                    last_seen: now,
+                    error,
+                    trigger,
+                    items_attempted,
+                    count: 1,
+                });
+                if records.len() > MAX_WORKFLOW_ERROR_RECORDS {
+                    let excess = records.len() - MAX_WORKFLOW_ERROR_RECORDS;
+                    records.drain(..excess);
+                }
+            }
+        }
+        db.put(writer, &UnitDbKey, &records)
+    })
+}
+
+/// Get the journal of a Cell's workflow errors, oldest first
+pub fn get_workflow_errors(env: &EnvironmentRead) -> DatabaseResult<Vec<WorkflowErrorRecord>> {
+    let db: WorkflowErrorDb = KvStore::new(env.get_db(&*WORKFLOW_ERRORS)?);
+    fresh_reader!(env, |r| Ok(db.get(&r, &UnitDbKey)?.unwrap_or_default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        queue_consumer::QueueTriggerClosedError, state::source_chain::SourceChainError,
+    };
+    use holochain_state::test_utils::test_cell_env;
+
+    #[tokio::test(threaded_scheduler)]
+    async fn identical_errors_are_coalesced() {
+        let test_env = test_cell_env();
+        let env = test_env.env();
+        let error = WorkflowError::from(QueueTriggerClosedError);
+        for _ in 0..3 {
+            record_workflow_error(
+                &env,
+                WorkflowName::IntegrateDhtOps,
+                TriggerReason::Retry,
+                Some(1),
+                &error,
+            )
+            .unwrap();
+        }
+        record_workflow_error(
+            &env,
+            WorkflowName::PublishDhtOps,
+            TriggerReason::Triggered,
+            None,
+            &error,
+        )
+        .unwrap();
+
+        let records = get_workflow_errors(&env.clone().into()).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].workflow, WorkflowName::IntegrateDhtOps);
+        assert_eq!(records[0].count, 3);
+        assert_eq!(records[0].error, error.to_string());
+        assert_eq!(records[1].workflow, WorkflowName::PublishDhtOps);
+        assert_eq!(records[1].count, 1);
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn journal_is_bounded() {
+        let test_env = test_cell_env();
+        let env = test_env.env();
+        for i in 0..MAX_WORKFLOW_ERROR_RECORDS + 5 {
+            let error = WorkflowError::from(SourceChainError::ElementMissing(i.to_string()));
+            record_workflow_error(
+                &env,
+                WorkflowName::SysValidation,
+                TriggerReason::Triggered,
+                None,
+                &error,
+            )
+            .unwrap();
+        }
+        let records = get_workflow_errors(&env.clone().into()).unwrap();
+        assert_eq!(records.len(), MAX_WORKFLOW_ERROR_RECORDS);
+        // The oldest records were dropped
+        assert_eq!(
+            records[0].error,
+            WorkflowError::from(SourceChainError::ElementMissing(5.to_string())).to_string()
+        );
+    }
+}
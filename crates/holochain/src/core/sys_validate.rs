@@ -11,8 +11,12 @@ use crate::conductor::{api::CellConductorApiT, entry_def_store::get_entry_def};
 use fallible_iterator::FallibleIterator;
 use holochain_keystore::AgentPubKeyExt;
 use holochain_p2p::HolochainP2pCell;
-use holochain_state::{env::EnvironmentWrite, error::DatabaseResult, fresh_reader};
-use holochain_types::{dht_op::DhtOp, header::NewEntryHeaderRef, Entry};
+use holochain_state::{env::EnvironmentWrite, fresh_reader};
+use holochain_types::{
+    dht_op::{DhtOp, OpRequirements},
+    header::NewEntryHeaderRef,
+    Entry,
+};
 use holochain_zome_types::{element::ElementEntry, signature::Signature};
 use holochain_zome_types::{
     entry_def::{EntryDef, EntryVisibility},
@@ -20,7 +24,7 @@ use holochain_zome_types::{
     link::LinkTag,
     Header,
 };
-use std::convert::TryInto;
+use std::{convert::TryInto, time::Duration};
 
 pub use crate::core::state::source_chain::{SourceChainError, SourceChainResult};
 pub(super) use error::*;
@@ -31,6 +35,7 @@ pub use holochain_types::{
     HeaderHashed, Timestamp,
 };
 
+pub mod clock_skew;
 #[allow(missing_docs)]
 mod error;
 #[cfg(test)]
@@ -111,22 +116,24 @@ pub async fn check_chain_rollback(
 ) -> SysValidationResult<()> {
     let header_hash = HeaderHash::with_data_sync(header);
     let k = ChainItemKey::AgentSequence(header.author().clone(), header.header_seq());
-    let env = workspace.meta_vault.env();
     // Check there are no conflicting chain items
     // at any valid or potentially valid stores.
-    let count = fresh_reader!(env, |r| {
-        let vault_count = workspace
+    // The vault may be in a shared DHT env, so each store gets its own reader.
+    let vault_count = fresh_reader!(workspace.meta_vault.env(), |r| {
+        workspace
             .meta_vault
             .get_activity(&r, k.clone())?
             .filter(|thh| Ok(thh.header_hash != header_hash))
-            .count()?;
-        let pending_count = workspace
+            .count()
+    })?;
+    let pending_count = fresh_reader!(workspace.meta_pending.env(), |r| {
+        workspace
             .meta_pending
             .get_activity(&r, k.clone())?
             .filter(|thh| Ok(thh.header_hash != header_hash))
-            .count()?;
-        DatabaseResult::Ok(vault_count + pending_count)
+            .count()
     })?;
+    let count = vault_count + pending_count;
 
     // Ok or log warning
     if count == 0 {
@@ -162,6 +169,26 @@ pub fn check_prev_timestamp(header: &Header, prev_header: &Header) -> SysValidat
     }
 }
 
+/// Check the header's timestamp is no further ahead of `now` than the tolerance.
+/// A header which is too far ahead becomes acceptable once the clock catches up.
+pub fn check_timestamp_drift(
+    header: &Header,
+    now: Timestamp,
+    tolerance: Duration,
+) -> SysValidationResult<()> {
+    let timestamp: Timestamp = header.timestamp().into();
+    let skew_ms = clock_skew::millis_between(now, timestamp);
+    if skew_ms <= tolerance.as_millis() as i64 {
+        Ok(())
+    } else {
+        Err(ValidationOutcome::TimestampAhead {
+            observed_skew: Duration::from_millis(skew_ms as u64),
+            acceptable_at: clock_skew::timestamp_before(timestamp, tolerance),
+        }
+        .into())
+    }
+}
+
 /// Check the previous header is one less then the current
 pub fn check_prev_seq(header: &Header, prev_header: &Header) -> SysValidationResult<()> {
     let header_seq = header.header_seq();
@@ -222,6 +249,32 @@ pub async fn check_app_entry_type(
     }
 }
 
+/// Check the requirements the author published an op with are the ones
+/// its entry def sets, so an author can't lower them.
+/// Ops without a claim aren't checked here. Only app validation falls
+/// back to the local entry def for them.
+pub async fn check_op_requirements(
+    op: &DhtOp,
+    claimed: Option<&OpRequirements>,
+    conductor_api: &impl CellConductorApiT,
+) -> SysValidationResult<()> {
+    let claimed = match claimed {
+        Some(claimed) => claimed,
+        None => return Ok(()),
+    };
+    let expected = match op.requirements_entry_type() {
+        Some(entry_type) => Some(OpRequirements::from(
+            &check_app_entry_type(&entry_type, conductor_api).await?,
+        )),
+        None => None,
+    };
+    if expected.as_ref() == Some(claimed) {
+        Ok(())
+    } else {
+        Err(ValidationOutcome::OpRequirements(*claimed, expected).into())
+    }
+}
+
 /// Check the app entry type isn't private for store entry
 pub fn check_not_private(entry_def: &EntryDef) -> SysValidationResult<()> {
     match entry_def.visibility {
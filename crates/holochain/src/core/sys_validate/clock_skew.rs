@@ -0,0 +1,156 @@
+//! Tolerating, and estimating, the skew between peers' clocks and our own.
+//!
+//! Sys validation holds back ops whose header timestamps are too far ahead
+//! of this conductor's clock, so a peer can't author data "in the future".
+//! Consumer devices are often a few minutes off, so rather than rejecting
+//! such ops they are delayed until their timestamps fall within tolerance.
+//! Every op received also contributes a sample to a running estimate of how
+//! far the clocks of each space's peers are from ours.
+
+use crate::conductor::config::ClockSkewConfig;
+use holo_hash::{DhtOpHash, DnaHash};
+use holochain_types::Timestamp;
+use parking_lot::Mutex;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
+
+/// How many of the most recent samples each space's estimate is taken from
+pub const CLOCK_SKEW_SAMPLES: usize = 101;
+
+/// The milliseconds from one timestamp to another, negative if `to` is earlier
+pub fn millis_between(from: Timestamp, to: Timestamp) -> i64 {
+    (to.0 - from.0) * 1000 + (to.1 as i64 - from.1 as i64) / 1_000_000
+}
+
+/// A timestamp moved back by a duration
+pub fn timestamp_before(t: Timestamp, duration: Duration) -> Timestamp {
+    let mut secs = t.0 - duration.as_secs() as i64;
+    let mut nanos = t.1 as i64 - duration.subsec_nanos() as i64;
+    if nanos < 0 {
+        secs -= 1;
+        nanos += 1_000_000_000;
+    }
+    Timestamp(secs, nanos as u32)
+}
+
+/// A conductor's clock skew tolerances, along with its running estimate of
+/// the skew of each space's peers. Clones share the same estimates.
+#[derive(Clone, Debug, Default)]
+pub struct ClockSkew {
+    config: ClockSkewConfig,
+    samples: Arc<Mutex<HashMap<DnaHash, VecDeque<(DhtOpHash, i64)>>>>,
+}
+
+impl ClockSkew {
+    /// Track skew with the given tolerances, with no samples yet
+    pub fn new(config: ClockSkewConfig) -> Self {
+        Self {
+            config,
+            samples: Default::default(),
+        }
+    }
+
+    /// How far ahead of our clock an authored header may be
+    pub fn authored_tolerance(&self) -> Duration {
+        Duration::from_secs(self.config.authored_tolerance_s)
+    }
+
+    /// How far ahead of our clock a received header may be
+    pub fn received_tolerance(&self) -> Duration {
+        Duration::from_secs(self.config.received_tolerance_s)
+    }
+
+    /// Record that the op with a header stamped `header_time` was received
+    /// in a space at `received_at`, by our clock.
+    /// An op which is still among the recent samples isn't counted again,
+    /// e.g. when it is received by several cells or from several publishers.
+    pub fn observe(
+        &self,
+        dna_hash: &DnaHash,
+        op_hash: &DhtOpHash,
+        header_time: Timestamp,
+        received_at: Timestamp,
+    ) {
+        let mut samples = self.samples.lock();
+        let samples = samples.entry(dna_hash.clone()).or_default();
+        if samples.iter().any(|(hash, _)| hash == op_hash) {
+            return;
+        }
+        if samples.len() >= CLOCK_SKEW_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back((op_hash.clone(), millis_between(received_at, header_time)));
+    }
+
+    /// The median of a space's recent samples, in milliseconds, positive when
+    /// peers' clocks are ahead of ours. None if nothing has been received.
+    /// The gap between authoring and receipt is included, so this is only
+    /// a rough measure of skew.
+    pub fn estimate_ms(&self, dna_hash: &DnaHash) -> Option<i64> {
+        let samples = self.samples.lock();
+        let mut sorted: Vec<_> = samples
+            .get(dna_hash)?
+            .iter()
+            .map(|(_, sample)| *sample)
+            .collect();
+        sorted.sort_unstable();
+        Some(sorted[sorted.len() / 2])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::fixt::prelude::*;
+    use holo_hash::fixt::DhtOpHashFixturator;
+    use holochain_types::test_utils::fake_dna_hash;
+
+    #[test]
+    fn estimate_is_the_median_of_recent_samples() {
+        let skew = ClockSkew::default();
+        let dna_hash = fake_dna_hash(1);
+        assert_eq!(skew.estimate_ms(&dna_hash), None);
+
+        let received_at = Timestamp(1000, 0);
+        let accurate_op = fixt!(DhtOpHash);
+        for offset in &[180, 175, 185, 181] {
+            skew.observe(
+                &dna_hash,
+                &fixt!(DhtOpHash),
+                Timestamp(1000 + offset, 0),
+                received_at,
+            );
+        }
+        skew.observe(&dna_hash, &accurate_op, Timestamp(1003, 0), received_at);
+        // One peer with an accurate clock doesn't sway the estimate
+        assert_eq!(skew.estimate_ms(&dna_hash), Some(180_000));
+        assert_eq!(skew.estimate_ms(&fake_dna_hash(2)), None);
+
+        // Receiving the same op again doesn't count it twice
+        for _ in 0..3 {
+            skew.observe(&dna_hash, &accurate_op, Timestamp(1003, 0), received_at);
+        }
+        assert_eq!(skew.estimate_ms(&dna_hash), Some(180_000));
+
+        // Old samples give way to new ones
+        for _ in 0..CLOCK_SKEW_SAMPLES {
+            skew.observe(
+                &dna_hash,
+                &fixt!(DhtOpHash),
+                Timestamp(999, 500_000_000),
+                received_at,
+            );
+        }
+        assert_eq!(skew.estimate_ms(&dna_hash), Some(-500));
+    }
+
+    #[test]
+    fn timestamps_move_back_across_seconds() {
+        let t = timestamp_before(Timestamp(10, 100), Duration::from_millis(1500));
+        assert_eq!(t, Timestamp(8, 500_000_100));
+        assert_eq!(millis_between(t, Timestamp(10, 100)), 1500);
+    }
+}
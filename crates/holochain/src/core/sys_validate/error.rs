@@ -10,11 +10,14 @@ use holo_hash::{AnyDhtHash, HeaderHash};
 use holochain_keystore::KeystoreError;
 use holochain_state::error::DatabaseError;
 use holochain_types::cell::CellId;
+use holochain_types::dht_op::OpRequirements;
+use holochain_types::Timestamp;
 use holochain_zome_types::signature::Signature;
 use holochain_zome_types::{
     header::{AppEntryType, EntryType},
     Header,
 };
+use std::time::Duration;
 use thiserror::Error;
 
 /// Validation can result in either
@@ -108,10 +111,17 @@ pub enum ValidationOutcome {
     NotNewEntry(Header),
     #[error("The dependency {0:?} is not held")]
     NotHoldingDep(AnyDhtHash),
+    #[error("The op was published with requirements {0:?} but its entry def requires {1:?}")]
+    OpRequirements(OpRequirements, Option<OpRequirements>),
     #[error(transparent)]
     PrevHeaderError(#[from] PrevHeaderError),
     #[error("StoreEntry should not be gossiped for private entries")]
     PrivateEntry,
+    #[error("The header's timestamp is {observed_skew:?} ahead of our clock, so it can't be accepted until {acceptable_at}")]
+    TimestampAhead {
+        observed_skew: Duration,
+        acceptable_at: Timestamp,
+    },
     #[error("Update original EntryType: {0:?} doesn't match new EntryType {1:?}")]
     UpdateTypeMismatch(EntryType, EntryType),
     #[error("Signature {0:?} failed to verify for Header {1:?}")]
@@ -7,14 +7,16 @@ use holochain_keystore::AgentPubKeyExt;
 use holochain_serialized_bytes::SerializedBytes;
 use holochain_state::{env::EnvironmentRead, test_utils::test_cell_env};
 use holochain_types::{
+    dht_op::{DhtOp, OpRequirements},
     dna::{DnaDef, DnaFile},
     fixt::*,
+    header::NewEntryHeader,
     observability,
     test_utils::fake_agent_pubkey_1,
     Timestamp,
 };
 use holochain_wasm_test_utils::TestWasm;
-use holochain_zome_types::Header;
+use holochain_zome_types::{validate::RequiredValidationType, Header};
 use matches::assert_matches;
 use std::convert::TryFrom;
 
@@ -341,6 +343,81 @@ async fn check_app_entry_type_test() {
     assert_matches!(check_app_entry_type(&aet, &conductor_api).await, Ok(_));
 }
 
+#[tokio::test(threaded_scheduler)]
+async fn check_op_requirements_test() {
+    observability::test_run().ok();
+    let dna_file = DnaFile::new(
+        DnaDef {
+            name: "op_requirements_test".to_string(),
+            uuid: "4a3f1c2e-8b7d-4e5a-9f60-1d2c3b4a5e6f".to_string(),
+            properties: SerializedBytes::try_from(()).unwrap(),
+            zomes: vec![TestWasm::EntryDefs.into()].into(),
+        },
+        vec![TestWasm::EntryDefs.into()],
+    )
+    .await
+    .unwrap();
+    let mut entry_def = fixt!(EntryDef);
+    entry_def.visibility = EntryVisibility::Public;
+    entry_def.required_validations = 3.into();
+    entry_def.required_validation_type = RequiredValidationType::SubChain;
+    let expected = OpRequirements::from(&entry_def);
+
+    let mut conductor_api = MockCellConductorApi::new();
+    conductor_api.expect_cell_id().return_const(fixt!(CellId));
+    conductor_api
+        .expect_sync_get_this_dna()
+        .return_const(Some(dna_file));
+    conductor_api
+        .expect_sync_get_entry_def()
+        .return_const(Some(entry_def));
+
+    let mut create = fixt!(Create);
+    create.entry_type = EntryType::App(AppEntryType::new(
+        0.into(),
+        0.into(),
+        EntryVisibility::Public,
+    ));
+    let op = DhtOp::StoreEntry(
+        fixt!(Signature),
+        NewEntryHeader::Create(create),
+        Box::new(fixt!(Entry)),
+    );
+
+    // Ops published without requirements have no claim to check
+    assert_matches!(
+        check_op_requirements(&op, None, &conductor_api).await,
+        Ok(())
+    );
+
+    // The claim matches the entry def
+    assert_matches!(
+        check_op_requirements(&op, Some(&expected), &conductor_api).await,
+        Ok(())
+    );
+
+    // The author claims fewer receipts than the entry def requires
+    let claimed = OpRequirements {
+        required_validations: 1.into(),
+        ..expected
+    };
+    assert_matches!(
+        check_op_requirements(&op, Some(&claimed), &conductor_api).await,
+        Err(SysValidationError::ValidationOutcome(
+            ValidationOutcome::OpRequirements(c, Some(e))
+        )) if c == claimed && e == expected
+    );
+
+    // Ops which aren't of app entries have no requirements to claim
+    let op = DhtOp::RegisterAddLink(fixt!(Signature), fixt!(CreateLink));
+    assert_matches!(
+        check_op_requirements(&op, Some(&expected), &conductor_api).await,
+        Err(SysValidationError::ValidationOutcome(
+            ValidationOutcome::OpRequirements(_, None)
+        ))
+    );
+}
+
 #[tokio::test(threaded_scheduler)]
 async fn check_entry_not_private_test() {
     let mut ed = fixt!(EntryDef);
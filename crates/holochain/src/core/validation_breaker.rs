@@ -0,0 +1,367 @@
+//! A circuit breaker which pauses a Cell's app validation while its
+//! validation callbacks keep failing with errors.
+//!
+//! A Dna whose validate callback errors on every op would otherwise have
+//! every authority retry the same ops over and over. The app validation
+//! workflow records each validation in the Cell's [ValidationBreaker], and
+//! once the share of errors in the window passes the configured rate the
+//! breaker opens. An open breaker lets a small batch of ops through after
+//! each probe interval, and closes again once a batch validates without
+//! an error. Rejected ops are not errors and never open the breaker.
+
+use crate::conductor::config::ValidationCircuitBreakerConfig;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::Notify;
+
+/// Whether a Cell's app validation is running
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BreakerState {
+    /// Ops are validated as they arrive
+    Closed,
+    /// Ops are left in the validation limbo until the next probe
+    Open,
+    /// A probe batch is being validated
+    HalfOpen,
+}
+
+/// The state of a Cell's [ValidationBreaker] and what it has counted
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationBreakerStatus {
+    /// Whether app validation is running
+    pub state: BreakerState,
+    /// Whether the breaker was forced open by an admin,
+    /// in which case it doesn't probe until it is forced closed
+    pub forced: bool,
+    /// How many validations ran in the current window
+    pub window_executions: u64,
+    /// How many of the validations in the current window failed with an error
+    pub window_errors: u64,
+    /// How many validations have run since the Cell was started
+    pub total_executions: u64,
+    /// How many validations have failed with an error since the Cell was started
+    pub total_errors: u64,
+    /// How many times the breaker has opened
+    pub times_opened: u64,
+    /// The last error a validation failed with
+    pub last_error: Option<String>,
+}
+
+/// What the app validation workflow may do on its next run
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Admission {
+    /// Validate every op
+    All,
+    /// Validate at most this many ops, to test whether the errors have stopped
+    Probe(usize),
+    /// Leave the ops alone until this much time has passed
+    Wait(Duration),
+}
+
+#[derive(Debug)]
+struct Breaker {
+    config: ValidationCircuitBreakerConfig,
+    state: BreakerState,
+    forced: bool,
+    /// When each validation in the window ran and whether it errored
+    window: VecDeque<(Instant, bool)>,
+    next_probe: Option<Instant>,
+    probe_failed: bool,
+    /// The error which opened the breaker, until it has been taken
+    newly_opened: Option<String>,
+    total_executions: u64,
+    total_errors: u64,
+    times_opened: u64,
+    last_error: Option<String>,
+}
+
+/// Where the app validation workflow of a Cell records its validations.
+/// Clones share the same breaker.
+#[derive(Clone, Debug)]
+pub struct ValidationBreaker {
+    breaker: Arc<Mutex<Breaker>>,
+    changed: Arc<Notify>,
+}
+
+impl ValidationBreaker {
+    /// Create a closed breaker
+    pub fn new(config: ValidationCircuitBreakerConfig) -> Self {
+        Self {
+            breaker: Arc::new(Mutex::new(Breaker {
+                config,
+                state: BreakerState::Closed,
+                forced: false,
+                window: VecDeque::new(),
+                next_probe: None,
+                probe_failed: false,
+                newly_opened: None,
+                total_executions: 0,
+                total_errors: 0,
+                times_opened: 0,
+                last_error: None,
+            })),
+            changed: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Record a validation which finished, whatever its outcome
+    pub fn record_success(&self, now: Instant) {
+        self.breaker.lock().record(now, None);
+    }
+
+    /// Record a validation whose callback failed with an error
+    pub fn record_error(&self, now: Instant, error: String) {
+        self.breaker.lock().record(now, Some(error));
+    }
+
+    /// Whether the breaker has opened, so the rest of the ops
+    /// should be left for a probe
+    pub fn is_open(&self) -> bool {
+        self.breaker.lock().state == BreakerState::Open
+    }
+
+    /// Decide how many ops the next run may validate.
+    /// An open breaker whose probe is due becomes half open.
+    pub fn admit(&self, now: Instant) -> Admission {
+        let mut breaker = self.breaker.lock();
+        let probe_interval = Duration::from_secs(breaker.config.probe_interval_s);
+        match breaker.state {
+            BreakerState::Closed => Admission::All,
+            BreakerState::Open if breaker.forced => Admission::Wait(probe_interval),
+            BreakerState::Open => match breaker.next_probe {
+                Some(next_probe) if next_probe > now => Admission::Wait(next_probe - now),
+                _ => {
+                    breaker.state = BreakerState::HalfOpen;
+                    breaker.probe_failed = false;
+                    Admission::Probe(breaker.config.probe_batch)
+                }
+            },
+            BreakerState::HalfOpen => Admission::Probe(breaker.config.probe_batch),
+        }
+    }
+
+    /// Finish a run. After a probe the breaker closes if none of the
+    /// probe's validations errored, otherwise it waits for the next probe.
+    pub fn end_run(&self, now: Instant) {
+        let mut breaker = self.breaker.lock();
+        if breaker.state != BreakerState::HalfOpen {
+            return;
+        }
+        if breaker.probe_failed {
+            breaker.state = BreakerState::Open;
+            breaker.next_probe = Some(now + Duration::from_secs(breaker.config.probe_interval_s));
+        } else {
+            breaker.close();
+        }
+    }
+
+    /// Take the error which opened the breaker, if it has opened
+    /// since this was last called
+    pub fn take_opened(&self) -> Option<String> {
+        self.breaker.lock().newly_opened.take()
+    }
+
+    /// Open the breaker until it is forced closed
+    pub fn force_open(&self) {
+        {
+            let mut breaker = self.breaker.lock();
+            if breaker.state != BreakerState::Open {
+                breaker.times_opened += 1;
+            }
+            breaker.state = BreakerState::Open;
+            breaker.forced = true;
+        }
+        self.changed.notify();
+    }
+
+    /// Close the breaker, forgetting the validations in the window
+    pub fn force_close(&self) {
+        self.breaker.lock().close();
+        self.changed.notify();
+    }
+
+    /// Wait until the breaker is forced open or closed
+    pub async fn changed(&self) {
+        self.changed.notified().await
+    }
+
+    /// The state of the breaker and what it has counted
+    pub fn snapshot(&self, now: Instant) -> ValidationBreakerStatus {
+        let mut breaker = self.breaker.lock();
+        breaker.prune(now);
+        ValidationBreakerStatus {
+            state: breaker.state,
+            forced: breaker.forced,
+            window_executions: breaker.window.len() as u64,
+            window_errors: breaker.window.iter().filter(|(_, e)| *e).count() as u64,
+            total_executions: breaker.total_executions,
+            total_errors: breaker.total_errors,
+            times_opened: breaker.times_opened,
+            last_error: breaker.last_error.clone(),
+        }
+    }
+}
+
+impl Breaker {
+    fn record(&mut self, now: Instant, error: Option<String>) {
+        let errored = error.is_some();
+        self.total_executions += 1;
+        if let Some(error) = error {
+            self.total_errors += 1;
+            self.last_error = Some(error);
+        }
+        match self.state {
+            BreakerState::Closed => {
+                self.window.push_back((now, errored));
+                self.prune(now);
+                if self.should_trip() {
+                    self.state = BreakerState::Open;
+                    self.next_probe = Some(now + Duration::from_secs(self.config.probe_interval_s));
+                    self.times_opened += 1;
+                    self.newly_opened = self.last_error.clone();
+                }
+            }
+            BreakerState::HalfOpen => self.probe_failed |= errored,
+            BreakerState::Open => (),
+        }
+    }
+
+    fn should_trip(&self) -> bool {
+        let executions = self.window.len();
+        let errors = self.window.iter().filter(|(_, e)| *e).count();
+        executions > 0
+            && executions >= self.config.min_executions as usize
+            && errors as f64 >= executions as f64 * self.config.error_rate
+    }
+
+    /// Drop the validations which have left the window
+    fn prune(&mut self, now: Instant) {
+        let window = Duration::from_secs(self.config.window_s);
+        while let Some((at, _)) = self.window.front() {
+            if now.saturating_duration_since(*at) > window {
+                self.window.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        self.state = BreakerState::Closed;
+        self.forced = false;
+        self.window.clear();
+        self.next_probe = None;
+        self.probe_failed = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker() -> ValidationBreaker {
+        ValidationBreaker::new(ValidationCircuitBreakerConfig {
+            window_s: 10,
+            error_rate: 0.5,
+            min_executions: 3,
+            probe_interval_s: 60,
+            probe_batch: 2,
+        })
+    }
+
+    #[test]
+    fn errors_within_the_window_open_the_breaker() {
+        let breaker = breaker();
+        let start = Instant::now();
+
+        // Errors spread beyond the window don't add up
+        breaker.record_error(start, "boom".into());
+        breaker.record_error(start + Duration::from_secs(20), "boom".into());
+        breaker.record_success(start + Duration::from_secs(21));
+        assert_eq!(
+            breaker.admit(start + Duration::from_secs(21)),
+            Admission::All
+        );
+        assert!(breaker.take_opened().is_none());
+
+        // Half the validations in the window error
+        let now = start + Duration::from_secs(22);
+        breaker.record_error(now, "boom again".into());
+        assert!(breaker.is_open());
+        assert_eq!(breaker.take_opened(), Some("boom again".to_string()));
+        assert_eq!(breaker.take_opened(), None);
+        assert_eq!(breaker.admit(now), Admission::Wait(Duration::from_secs(60)));
+
+        let status = breaker.snapshot(now);
+        assert_eq!(status.state, BreakerState::Open);
+        assert_eq!(status.window_executions, 3);
+        assert_eq!(status.window_errors, 2);
+        assert_eq!(status.total_executions, 4);
+        assert_eq!(status.total_errors, 3);
+        assert_eq!(status.times_opened, 1);
+        assert_eq!(status.last_error, Some("boom again".to_string()));
+    }
+
+    #[test]
+    fn failed_probes_stay_open_until_one_succeeds() {
+        let breaker = breaker();
+        let start = Instant::now();
+        for _ in 0..3 {
+            breaker.record_error(start, "boom".into());
+        }
+        assert!(breaker.is_open());
+        assert_eq!(breaker.take_opened(), Some("boom".to_string()));
+
+        // The probe keeps failing
+        let probe = start + Duration::from_secs(60);
+        assert_eq!(breaker.admit(probe), Admission::Probe(2));
+        breaker.record_success(probe);
+        breaker.record_error(probe, "boom".into());
+        breaker.end_run(probe);
+        assert!(breaker.is_open());
+        assert_eq!(
+            breaker.admit(probe + Duration::from_secs(1)),
+            Admission::Wait(Duration::from_secs(59))
+        );
+        // Reopening after a probe isn't a new opening
+        assert_eq!(breaker.take_opened(), None);
+
+        // The next probe succeeds
+        let probe = probe + Duration::from_secs(60);
+        assert_eq!(breaker.admit(probe), Admission::Probe(2));
+        breaker.record_success(probe);
+        breaker.end_run(probe);
+        assert_eq!(breaker.admit(probe), Admission::All);
+        let status = breaker.snapshot(probe);
+        assert_eq!(status.state, BreakerState::Closed);
+        assert_eq!(status.window_executions, 0);
+        assert_eq!(status.times_opened, 1);
+    }
+
+    #[test]
+    fn forced_open_waits_for_forced_close() {
+        let breaker = breaker();
+        let now = Instant::now();
+        breaker.force_open();
+        assert!(breaker.is_open());
+        assert!(breaker.snapshot(now).forced);
+
+        // No probe is let through
+        let later = now + Duration::from_secs(600);
+        assert_eq!(
+            breaker.admit(later),
+            Admission::Wait(Duration::from_secs(60))
+        );
+
+        breaker.force_close();
+        assert_eq!(breaker.admit(later), Admission::All);
+        let status = breaker.snapshot(later);
+        assert!(!status.forced);
+        assert_eq!(status.times_opened, 1);
+    }
+}
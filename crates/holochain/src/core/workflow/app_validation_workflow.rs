@@ -1,6 +1,6 @@
 //! The workflow and queue consumer for sys validation
 
-use std::{collections::BinaryHeap, convert::TryInto, sync::Arc};
+use std::{collections::BinaryHeap, convert::TryInto, sync::Arc, time::Instant};
 
 use super::{
     error::WorkflowError, error::WorkflowResult,
@@ -9,11 +9,13 @@ use super::{
 use crate::{
     conductor::api::CellConductorApiT,
     conductor::entry_def_store::get_entry_def,
+    core::ribosome::guest_callback::index_hint::{IndexHintInvocation, IndexHintResult},
     core::ribosome::guest_callback::validate_link::ValidateCreateLinkInvocation,
     core::ribosome::guest_callback::validate_link::ValidateDeleteLinkInvocation,
     core::ribosome::guest_callback::validate_link::ValidateLinkHostAccess,
     core::ribosome::guest_callback::validate_link::ValidateLinkInvocation,
     core::ribosome::guest_callback::validate_link::ValidateLinkResult,
+    core::ribosome::validation_pool::{ExecutionContext, ValidationPoolError},
     core::ribosome::wasm_ribosome::WasmRibosome,
     core::ribosome::Invocation,
     core::ribosome::ZomesToInvoke,
@@ -25,24 +27,27 @@ use crate::{
         ribosome::guest_callback::validate::ValidateResult,
         ribosome::RibosomeT,
         state::{
+            cascade::error::CascadeError,
             cascade::DbPair,
             cascade::DbPairMut,
             dht_op_integration::{
                 IntegratedDhtOpsStore, IntegrationLimboStore, IntegrationLimboValue,
             },
             element_buf::ElementBuf,
-            metadata::MetadataBuf,
+            metadata::{MetadataBuf, MetadataBufT},
             validation_db::{ValidationLimboStatus, ValidationLimboStore, ValidationLimboValue},
             workspace::{Workspace, WorkspaceResult},
         },
         validation::DhtOpOrder,
         validation::OrderedOp,
+        validation::OutcomeOrError,
+        validation_breaker::ValidationBreaker,
     },
 };
 use error::AppValidationResult;
 pub use error::*;
 use fallible_iterator::FallibleIterator;
-use holo_hash::DhtOpHash;
+use holo_hash::{DhtOpHash, EntryHash, HeaderHash};
 use holochain_p2p::{HolochainP2pCell, HolochainP2pCellT};
 use holochain_state::{
     buffer::{BufferedStore, KvBufFresh},
@@ -51,8 +56,13 @@ use holochain_state::{
     prelude::*,
 };
 use holochain_types::{
-    dht_op::DhtOp, dna::zome::Zome, dna::DnaFile, test_utils::which_agent,
-    validate::ValidationStatus, Entry, HeaderHashed, Timestamp,
+    dht_op::{DhtOp, OpRequirements},
+    dna::zome::Zome,
+    dna::DnaFile,
+    index::{IndexKey, IndexOp},
+    test_utils::which_agent,
+    validate::ValidationStatus,
+    Entry, HeaderHashed, Timestamp,
 };
 use holochain_zome_types::{
     element::Element,
@@ -70,23 +80,50 @@ use holochain_zome_types::{
 use tracing::*;
 pub use types::Outcome;
 
+#[cfg(test)]
+mod circuit_breaker_test;
 #[cfg(test)]
 mod network_call_tests;
 #[cfg(test)]
+mod queue_full_test;
+#[cfg(test)]
 mod tests;
 
 mod error;
 mod types;
 
-#[instrument(skip(workspace, writer, trigger_integration, conductor_api, network))]
+/// Validate the ops waiting for app validation, or at most `limit` of them.
+/// Ops whose validation callbacks fail with an error are recorded in the
+/// breaker and left in the limbo, and the run fails with the first error
+/// once the rest of the ops have been validated.
+#[instrument(skip(
+    workspace,
+    writer,
+    trigger_integration,
+    conductor_api,
+    network,
+    context,
+    breaker
+))]
 pub async fn app_validation_workflow(
     mut workspace: AppValidationWorkspace,
     writer: OneshotWriter,
     trigger_integration: &mut TriggerSender,
     conductor_api: impl CellConductorApiT,
     network: HolochainP2pCell,
+    context: &ExecutionContext,
+    breaker: &ValidationBreaker,
+    limit: Option<usize>,
 ) -> WorkflowResult<WorkComplete> {
-    let complete = app_validation_workflow_inner(&mut workspace, conductor_api, &network).await?;
+    let (complete, callback_error) = app_validation_workflow_inner(
+        &mut workspace,
+        conductor_api,
+        &network,
+        context,
+        breaker,
+        limit,
+    )
+    .await?;
     // --- END OF WORKFLOW, BEGIN FINISHER BOILERPLATE ---
 
     // commit the workspace
@@ -95,13 +132,21 @@ pub async fn app_validation_workflow(
     // trigger other workflows
     trigger_integration.trigger();
 
+    // The ops whose callbacks failed are back in the limbo,
+    // but the run still fails so it is journaled and backed off
+    if let Some(e) = callback_error {
+        return Err(e.into());
+    }
     Ok(complete)
 }
 async fn app_validation_workflow_inner(
     workspace: &mut AppValidationWorkspace,
     conductor_api: impl CellConductorApiT,
     network: &HolochainP2pCell,
-) -> WorkflowResult<WorkComplete> {
+    context: &ExecutionContext,
+    breaker: &ValidationBreaker,
+    limit: Option<usize>,
+) -> WorkflowResult<(WorkComplete, Option<AppValidationError>)> {
     let env = workspace.validation_limbo.env().clone();
 
     // Drain the ops into a sorted binary heap
@@ -117,7 +162,8 @@ async fn app_validation_workflow_inner(
                         ValidationLimboStatus::SysValidated
                         | ValidationLimboStatus::AwaitingAppDeps(_) => Ok(true),
                         ValidationLimboStatus::Pending
-                        | ValidationLimboStatus::AwaitingSysDeps(_) => Ok(false),
+                        | ValidationLimboStatus::AwaitingSysDeps(_)
+                        | ValidationLimboStatus::Delayed { .. } => Ok(false),
                     }
                 })?
                 .map_err(WorkflowError::from)
@@ -142,6 +188,9 @@ async fn app_validation_workflow_inner(
     })?;
 
     // Validate all the ops
+    let mut ops_left = false;
+    let mut validated = 0;
+    let mut callback_error = None;
     for so in sorted_ops.into_sorted_vec() {
         let OrderedOp {
             hash,
@@ -150,13 +199,49 @@ async fn app_validation_workflow_inner(
             ..
         } = so;
 
+        // The breaker has opened or the limit has been reached so the
+        // rest of the ops are left in the limbo for the next run
+        if breaker.is_open() || limit.map_or(false, |limit| validated >= limit) {
+            ops_left = true;
+            workspace.return_val_limbo(hash, vlv)?;
+            continue;
+        }
+        validated += 1;
+
         match &vlv.status {
             ValidationLimboStatus::AwaitingAppDeps(_) | ValidationLimboStatus::SysValidated => {
-                // Validate this op
-                let outcome = validate_op(op.clone(), &conductor_api, workspace, &network)
+                // Validate this op, waiting for the validation pool
+                // to catch up whenever it is full
+                let result = loop {
+                    match validate_op(
+                        op.clone(),
+                        vlv.requirements,
+                        &conductor_api,
+                        workspace,
+                        &network,
+                        context,
+                    )
                     .await
+                    {
+                        Err(OutcomeOrError::Err(AppValidationError::ValidationPool(
+                            ValidationPoolError::QueueFull,
+                        ))) => context.capacity_freed().await,
+                        result => break result,
+                    }
+                };
+                let outcome = match result {
+                    // A callback failed so the op is tried again later
+                    Err(OutcomeOrError::Err(e)) if is_callback_error(&e) => {
+                        warn!(?hash, ?e, "App validation callback failed");
+                        breaker.record_error(Instant::now(), e.to_string());
+                        workspace.put_val_limbo(hash, vlv)?;
+                        callback_error.get_or_insert(e);
+                        continue;
+                    }
                     // Get the outcome or return the error
-                    .or_else(|outcome_or_err| outcome_or_err.try_into())?;
+                    outcome => outcome.or_else(|outcome_or_err| outcome_or_err.try_into())?,
+                };
+                breaker.record_success(Instant::now());
 
                 match outcome {
                     Outcome::Accepted => {
@@ -182,7 +267,22 @@ async fn app_validation_workflow_inner(
             _ => unreachable!("Should not contain any other status"),
         }
     }
-    Ok(WorkComplete::Complete)
+    let complete = if ops_left {
+        WorkComplete::Incomplete
+    } else {
+        WorkComplete::Complete
+    };
+    Ok((complete, callback_error))
+}
+
+/// Whether the error came from running a validation callback,
+/// rather than from gathering what the callback needs
+fn is_callback_error(e: &AppValidationError) -> bool {
+    matches!(
+        e,
+        AppValidationError::RibosomeError(_)
+            | AppValidationError::ValidationPool(ValidationPoolError::Dropped)
+    )
 }
 
 fn to_zome_name(zomes_to_invoke: ZomesToInvoke) -> AppValidationResult<ZomeName> {
@@ -194,13 +294,27 @@ fn to_zome_name(zomes_to_invoke: ZomesToInvoke) -> AppValidationResult<ZomeName>
 
 async fn validate_op(
     op: DhtOp,
+    requirements: Option<OpRequirements>,
     conductor_api: &impl CellConductorApiT,
     workspace: &mut AppValidationWorkspace,
     network: &HolochainP2pCell,
+    context: &ExecutionContext,
 ) -> AppValidationOutcome<Outcome> {
     // Get the workspace for the validation calls
     let workspace_lock = workspace.validation_workspace();
 
+    // Only the authorities holding an entry index it
+    let is_store_entry = matches!(op, DhtOp::StoreEntry(..));
+
+    // The entry authorities tombstone the keys of a deleted header
+    let deleted_entry_header = match &op {
+        DhtOp::RegisterDeletedEntryHeader(_, delete) => Some((
+            delete.deletes_address.clone(),
+            delete.deletes_entry_address.clone(),
+        )),
+        _ => None,
+    };
+
     // Create the element
     let element = get_element(op)?;
 
@@ -219,7 +333,8 @@ async fn validate_op(
     };
 
     // Get the validation package
-    let validation_package = get_validation_package(&element, &entry_def, network.clone()).await?;
+    let validation_package =
+        op_validation_package(&element, requirements.as_ref(), &entry_def, network.clone()).await?;
 
     // Get the EntryDefId associated with this Element if there is one
     let entry_def_id = entry_def.map(|ed| ed.id);
@@ -227,20 +342,55 @@ async fn validate_op(
     // Get the zome names
     let zomes_to_invoke = get_zomes_to_invoke(&element, &dna_file, workspace, network).await?;
 
+    // Get what is needed to index this entry if it is an app entry
+    let to_index = match (
+        is_store_entry,
+        element.header().entry_data(),
+        element.entry().as_option(),
+        &entry_def_id,
+        &zomes_to_invoke,
+    ) {
+        (
+            true,
+            Some((entry_hash, EntryType::App(aet))),
+            Some(entry),
+            Some(EntryDefId::App(entry_def_id)),
+            ZomesToInvoke::One(zome_name),
+        ) => Some((
+            IndexHintInvocation {
+                zome_name: zome_name.clone(),
+                entry_def_id: entry_def_id.clone(),
+                entry: Arc::new(entry.clone()),
+            },
+            aet.clone(),
+            entry_hash.clone(),
+            element.header_address().clone(),
+        )),
+        _ => None,
+    };
+
     // Create the ribosome
-    let ribosome = WasmRibosome::new(dna_file);
+    let ribosome =
+        WasmRibosome::new(dna_file).with_module_cache(conductor_api.wasm_module_cache().clone());
 
     let outcome = match element.header() {
         Header::DeleteLink(delete_link) => {
             let zome_name = to_zome_name(zomes_to_invoke)?;
+            let delete_link = delete_link.clone();
+            let (ribosome, workspace_lock, network) =
+                (ribosome.clone(), workspace_lock.clone(), network.clone());
             // Run the link validation
-            run_delete_link_validation_callback(
-                zome_name,
-                delete_link.clone(),
-                &ribosome,
-                workspace_lock.clone(),
-                network.clone(),
-            )?
+            context
+                .run(move || {
+                    run_delete_link_validation_callback(
+                        zome_name,
+                        delete_link,
+                        &ribosome,
+                        workspace_lock,
+                        network,
+                    )
+                })
+                .await??
         }
         Header::CreateLink(link_add) => {
             // Get the base and target for this link
@@ -261,17 +411,23 @@ async fn validate_op(
             let target = Arc::new(target);
 
             let zome_name = to_zome_name(zomes_to_invoke)?;
+            let (ribosome, workspace_lock, network) =
+                (ribosome.clone(), workspace_lock.clone(), network.clone());
 
             // Run the link validation
-            run_create_link_validation_callback(
-                zome_name,
-                link_add,
-                base,
-                target,
-                &ribosome,
-                workspace_lock.clone(),
-                network.clone(),
-            )?
+            context
+                .run(move || {
+                    run_create_link_validation_callback(
+                        zome_name,
+                        link_add,
+                        base,
+                        target,
+                        &ribosome,
+                        workspace_lock,
+                        network,
+                    )
+                })
+                .await??
         }
         _ => {
             // Element
@@ -279,18 +435,44 @@ async fn validate_op(
             // Call the callback
             let element = Arc::new(element);
             let validation_package = validation_package.map(Arc::new);
+            let (ribosome, workspace_lock, network) =
+                (ribosome.clone(), workspace_lock.clone(), network.clone());
             // Call the element validation
-            run_validation_callback_inner(
-                zomes_to_invoke,
-                element,
-                validation_package,
-                entry_def_id,
+            context
+                .run(move || {
+                    run_validation_callback_inner(
+                        zomes_to_invoke,
+                        element,
+                        validation_package,
+                        entry_def_id,
+                        &ribosome,
+                        workspace_lock,
+                        network,
+                    )
+                })
+                .await??
+        }
+    };
+    let outcome = match (outcome, to_index) {
+        (Outcome::Accepted, Some((invocation, aet, entry_hash, header_hash))) => {
+            index_entry(
+                invocation,
+                &aet,
+                entry_hash,
+                header_hash,
                 &ribosome,
-                workspace_lock.clone(),
+                workspace,
+                workspace_lock,
                 network.clone(),
-            )?
+                context,
+            )
+            .await?
         }
+        (outcome, _) => outcome,
     };
+    if let (Outcome::Accepted, Some((header_hash, entry_hash))) = (&outcome, deleted_entry_header) {
+        tombstone_entry(header_hash, entry_hash, workspace, network.clone()).await?;
+    }
     if let Outcome::AwaitingDeps(_) | Outcome::Rejected(_) = &outcome {
         warn!(
             agent = %which_agent(conductor_api.cell_id().agent_pubkey()),
@@ -505,16 +687,48 @@ fn extract_app_type(element: &Element) -> Option<AppEntryType> {
         })
 }
 
+/// The validation package an op needs. Ops published with requirements
+/// use the ones sys validation checked against the entry def,
+/// other ops use their associated entry def's if they have one.
+pub(crate) fn required_validation_type(
+    requirements: Option<&OpRequirements>,
+    entry_def: &Option<EntryDef>,
+) -> Option<RequiredValidationType> {
+    requirements
+        .map(|requirements| requirements.required_validation_type)
+        .or_else(|| {
+            entry_def
+                .as_ref()
+                .map(|entry_def| entry_def.required_validation_type)
+        })
+}
+
+/// Get the validation package an op needs from its author,
+/// based on the requirements it was published with
+pub(crate) async fn op_validation_package(
+    element: &Element,
+    requirements: Option<&OpRequirements>,
+    entry_def: &Option<EntryDef>,
+    network: HolochainP2pCell,
+) -> AppValidationResult<Option<ValidationPackage>> {
+    get_validation_package(
+        element,
+        required_validation_type(requirements, entry_def),
+        network,
+    )
+    .await
+}
+
 /// Get the validation package based on
 /// the requirements set by the AppEntryType
 async fn get_validation_package(
     element: &Element,
-    entry_def: &Option<EntryDef>,
+    required_validation_type: Option<RequiredValidationType>,
     mut network: HolochainP2pCell,
 ) -> AppValidationResult<Option<ValidationPackage>> {
-    match entry_def {
-        Some(entry_def) => {
-            Ok(match entry_def.required_validation_type {
+    match required_validation_type {
+        Some(required_validation_type) => {
+            Ok(match required_validation_type {
                 // Only needs the element
                 RequiredValidationType::Element => None,
                 RequiredValidationType::SubChain | RequiredValidationType::Full => {
@@ -562,9 +776,13 @@ pub async fn run_validation_callback_direct(
         Err(outcome) => return outcome.try_into(),
     };
 
-    let validation_package = get_validation_package(&element, &entry_def, network.clone())
-        .await?
-        .map(Arc::new);
+    let validation_package = get_validation_package(
+        &element,
+        required_validation_type(None, &entry_def),
+        network.clone(),
+    )
+    .await?
+    .map(Arc::new);
     let entry_def_id = entry_def.map(|ed| ed.id);
 
     let element = Arc::new(element);
@@ -605,6 +823,111 @@ fn run_validation_callback_inner(
     }
 }
 
+/// Run the index_hint callback of an accepted entry and publish the
+/// entry to the authorities for every key it gives.
+/// The callback must be deterministic so it is run twice, and the
+/// op is rejected if the runs disagree or either fails.
+async fn index_entry(
+    invocation: IndexHintInvocation,
+    app_entry_type: &AppEntryType,
+    entry_hash: EntryHash,
+    header_hash: HeaderHash,
+    ribosome: &(impl RibosomeT + Clone + Send + 'static),
+    workspace: &mut AppValidationWorkspace,
+    workspace_lock: CallZomeWorkspaceLock,
+    mut network: HolochainP2pCell,
+    context: &ExecutionContext,
+) -> AppValidationResult<Outcome> {
+    let runs = {
+        let ribosome = ribosome.clone();
+        let invocation = invocation.clone();
+        let network = network.clone();
+        context
+            .run(move || {
+                let run = || {
+                    ribosome.run_index_hint(
+                        ValidateHostAccess::new(workspace_lock.clone(), network.clone()),
+                        invocation.clone(),
+                    )
+                };
+                (run(), run())
+            })
+            .await?
+    };
+    let keys = match runs {
+        (Ok(IndexHintResult::Keys(keys)), Ok(IndexHintResult::Keys(again))) => {
+            if keys != again {
+                return Ok(Outcome::Rejected(format!(
+                    "The index_hint callback for {} gave different keys for the same entry",
+                    invocation.entry_def_id
+                )));
+            }
+            keys
+        }
+        (Ok(IndexHintResult::Fail(reason)), _) | (_, Ok(IndexHintResult::Fail(reason))) => {
+            return Ok(Outcome::Rejected(format!(
+                "The index_hint callback for {} failed: {}",
+                invocation.entry_def_id, reason
+            )));
+        }
+        (Err(e), _) | (_, Err(e)) => {
+            return Ok(Outcome::Rejected(format!(
+                "The index_hint callback for {} could not be run: {}",
+                invocation.entry_def_id, e
+            )));
+        }
+    };
+    for key in keys {
+        let index_key = IndexKey::new(app_entry_type, key);
+        // Remembered so the key can be tombstoned if the header is deleted
+        workspace
+            .meta_vault
+            .register_indexed_header(&header_hash, index_key.clone())?;
+        network
+            .publish_index(
+                IndexOp::Register {
+                    index_key,
+                    entry_hash: entry_hash.clone(),
+                    header_hash: header_hash.clone(),
+                },
+                None,
+            )
+            .await?;
+    }
+    Ok(Outcome::Accepted)
+}
+
+/// Publish a tombstone to the authorities for every key
+/// the entry a deleted header created was indexed under
+async fn tombstone_entry(
+    header_hash: HeaderHash,
+    entry_hash: EntryHash,
+    workspace: &AppValidationWorkspace,
+    mut network: HolochainP2pCell,
+) -> AppValidationResult<()> {
+    let env = workspace.meta_vault.env().clone();
+    let index_keys: Vec<_> = fresh_reader!(env, |r| {
+        workspace
+            .meta_vault
+            .get_indexed_header_keys(&r, &header_hash)?
+            .collect()
+    })
+    .map_err(CascadeError::from)?;
+    for index_key in index_keys {
+        network
+            .publish_index(
+                IndexOp::Tombstone {
+                    index_key,
+                    entry_hash: entry_hash.clone(),
+                    header_hash: header_hash.clone(),
+                },
+                None,
+            )
+            .await?;
+    }
+    Ok(())
+}
+
 pub fn run_create_link_validation_callback(
     zome_name: ZomeName,
     link_add: Arc<CreateLink>,
@@ -741,6 +1064,17 @@ impl AppValidationWorkspace {
         Ok(())
     }
 
+    /// Put an op back in the limbo without counting a try,
+    /// because it wasn't validated
+    fn return_val_limbo(
+        &mut self,
+        hash: DhtOpHash,
+        vlv: ValidationLimboValue,
+    ) -> WorkflowResult<()> {
+        self.validation_limbo.put(hash, vlv)?;
+        Ok(())
+    }
+
     #[tracing::instrument(skip(self, hash))]
     fn put_int_limbo(
         &mut self,
@@ -793,6 +1127,8 @@ impl Workspace for AppValidationWorkspace {
         self.integration_limbo.flush_to_txn_ref(writer)?;
         self.element_pending.flush_to_txn_ref(writer)?;
         self.meta_pending.flush_to_txn_ref(writer)?;
+        // The keys of indexed headers are the only integrated data written here
+        self.meta_vault.flush_to_txn_ref(writer)?;
 
         // Flush for cascade
         self.element_cache.flush_to_txn_ref(writer)?;
@@ -0,0 +1,192 @@
+use super::*;
+use crate::{
+    conductor::{api::MockCellConductorApi, config::ValidationCircuitBreakerConfig},
+    core::{
+        ribosome::module_cache::WasmModuleCache,
+        validation_breaker::{Admission, BreakerState},
+        workflow::incoming_dht_ops_workflow::incoming_dht_ops_workflow,
+    },
+    fixt::CreateFixturator,
+    test_utils::test_network,
+};
+use ::fixt::prelude::*;
+use holo_hash::AgentPubKey;
+use holochain_keystore::KeystoreSender;
+use holochain_serialized_bytes::SerializedBytes;
+use holochain_state::{
+    env::EnvironmentWrite,
+    test_utils::{test_cell_env, test_wasm_env},
+};
+use holochain_types::{
+    cell::CellId,
+    dna::DnaDef,
+    element::{SignedHeaderHashed, SignedHeaderHashedExt},
+    test_utils::fake_agent_pubkey_1,
+};
+use holochain_wasm_test_utils::TestWasm;
+use matches::assert_matches;
+use std::convert::TryFrom;
+
+async fn dna_file(wasm: TestWasm) -> DnaFile {
+    DnaFile::new(
+        DnaDef {
+            name: "circuit_breaker_test".to_string(),
+            uuid: "0b6e5b2b-1d8e-4a3c-9c7e-2f4d0a6c8e31".to_string(),
+            properties: SerializedBytes::try_from(()).unwrap(),
+            zomes: vec![wasm.into()].into(),
+        },
+        vec![wasm.into()],
+    )
+    .await
+    .unwrap()
+}
+
+fn conductor_api(
+    cell_id: &CellId,
+    dna_file: &DnaFile,
+    wasm_module_cache: &WasmModuleCache,
+) -> MockCellConductorApi {
+    let mut conductor_api = MockCellConductorApi::new();
+    conductor_api.expect_cell_id().return_const(cell_id.clone());
+    conductor_api
+        .expect_sync_get_this_dna()
+        .return_const(Some(dna_file.clone()));
+    conductor_api
+        .expect_mock_wasm_module_cache()
+        .return_const(wasm_module_cache.clone());
+    conductor_api
+}
+
+/// Put ops which are validated by every zome straight into app validation
+async fn sys_validated_ops(
+    env: &EnvironmentWrite,
+    keystore: &KeystoreSender,
+    author: &AgentPubKey,
+    count: usize,
+) -> Vec<DhtOpHash> {
+    let mut ops = Vec::new();
+    for _ in 0..count {
+        let mut create = fixt!(Create);
+        create.author = author.clone();
+        create.entry_type = EntryType::AgentPubKey;
+        let signed = SignedHeaderHashed::new(
+            keystore,
+            HeaderHashed::from_content_sync(Header::Create(create)),
+        )
+        .await
+        .unwrap();
+        let op = DhtOp::StoreElement(signed.signature().clone(), signed.header().clone(), None);
+        ops.push((DhtOpHash::with_data_sync(&op), op));
+    }
+    let hashes = ops.iter().map(|(hash, _)| hash.clone()).collect::<Vec<_>>();
+
+    let (trigger, _rx) = TriggerSender::new();
+    incoming_dht_ops_workflow(env, trigger, ops).await.unwrap();
+    let mut workspace = AppValidationWorkspace::new(env.clone().into()).unwrap();
+    for hash in &hashes {
+        let mut vlv = workspace.validation_limbo.get(hash).unwrap().unwrap();
+        vlv.status = ValidationLimboStatus::SysValidated;
+        workspace.validation_limbo.put(hash.clone(), vlv).unwrap();
+    }
+    env.guard()
+        .with_commit(|writer| workspace.flush_to_txn_ref(writer))
+        .unwrap();
+    hashes
+}
+
+fn in_limbo(env: &EnvironmentWrite, hashes: &[DhtOpHash]) -> usize {
+    let workspace = AppValidationWorkspace::new(env.clone().into()).unwrap();
+    hashes
+        .iter()
+        .filter(|hash| workspace.validation_limbo.get(hash).unwrap().is_some())
+        .count()
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn erroring_callbacks_open_the_breaker_until_a_probe_succeeds() {
+    observability::test_run().ok();
+    let test_env = test_cell_env();
+    let env = test_env.env();
+    let test_wasm_env = test_wasm_env();
+    let keystore = env.keystore().clone();
+    let wasm_module_cache = WasmModuleCache::new(test_wasm_env.env());
+
+    let author = fake_agent_pubkey_1();
+    let panicking = dna_file(TestWasm::ValidatePanic).await;
+    let fixed = dna_file(TestWasm::ValidateValid).await;
+    let cell_id = CellId::new(panicking.dna_hash().clone(), author.clone());
+    let (_network, _recv, cell_network) =
+        test_network(Some(cell_id.dna_hash().clone()), Some(author.clone())).await;
+    let hashes = sys_validated_ops(&env, &keystore, &author, 4).await;
+
+    // Probes are due as soon as the breaker opens
+    let breaker = ValidationBreaker::new(ValidationCircuitBreakerConfig {
+        window_s: 60,
+        error_rate: 0.5,
+        min_executions: 3,
+        probe_interval_s: 0,
+        probe_batch: 1,
+    });
+    let run = |dna_file: &DnaFile, limit| {
+        let workspace = AppValidationWorkspace::new(env.clone().into()).unwrap();
+        let writer = OneshotWriter::from(env.clone());
+        let conductor_api = conductor_api(&cell_id, dna_file, &wasm_module_cache);
+        let network = cell_network.clone();
+        let breaker = &breaker;
+        async move {
+            let (mut trigger_integration, _rx) = TriggerSender::new();
+            app_validation_workflow(
+                workspace,
+                writer,
+                &mut trigger_integration,
+                conductor_api,
+                network,
+                &ExecutionContext::Caller,
+                breaker,
+                limit,
+            )
+            .await
+        }
+    };
+
+    // The panics open the breaker, leaving the last op unvalidated
+    assert_eq!(breaker.admit(Instant::now()), Admission::All);
+    let result = run(&panicking, None).await;
+    assert_matches!(
+        result,
+        Err(WorkflowError::AppValidationError(
+            AppValidationError::RibosomeError(_)
+        ))
+    );
+    breaker.end_run(Instant::now());
+    assert!(breaker.take_opened().is_some());
+    let status = breaker.snapshot(Instant::now());
+    assert_eq!(status.state, BreakerState::Open);
+    assert_eq!(status.total_errors, 3);
+    assert_eq!(in_limbo(&env, &hashes), 4);
+
+    // The probe keeps failing so the breaker stays open
+    assert_eq!(breaker.admit(Instant::now()), Admission::Probe(1));
+    assert!(run(&panicking, Some(1)).await.is_err());
+    breaker.end_run(Instant::now());
+    let status = breaker.snapshot(Instant::now());
+    assert_eq!(status.state, BreakerState::Open);
+    assert_eq!(status.total_executions, 4);
+    assert_eq!(status.times_opened, 1);
+    assert_eq!(in_limbo(&env, &hashes), 4);
+
+    // Once the Dna is fixed the probe succeeds and the breaker closes
+    assert_eq!(breaker.admit(Instant::now()), Admission::Probe(1));
+    assert_eq!(
+        run(&fixed, Some(1)).await.unwrap(),
+        WorkComplete::Incomplete
+    );
+    breaker.end_run(Instant::now());
+    assert_eq!(in_limbo(&env, &hashes), 3);
+
+    // So the rest of the ops are validated
+    assert_eq!(breaker.admit(Instant::now()), Admission::All);
+    assert_eq!(run(&fixed, None).await.unwrap(), WorkComplete::Complete);
+    assert_eq!(in_limbo(&env, &hashes), 0);
+    assert_eq!(breaker.snapshot(Instant::now()).total_errors, 4);
+}
@@ -5,6 +5,7 @@ use thiserror::Error;
 
 use crate::{
     conductor::entry_def_store::error::EntryDefStoreError, core::ribosome::error::RibosomeError,
+    core::ribosome::validation_pool::ValidationPoolError,
     core::state::cascade::error::CascadeError, core::validation::OutcomeOrError, from_sub_error,
 };
 
@@ -24,6 +25,8 @@ pub enum AppValidationError {
     LinkMultipleZomes,
     #[error(transparent)]
     RibosomeError(#[from] RibosomeError),
+    #[error(transparent)]
+    ValidationPool(#[from] ValidationPoolError),
     #[error("The app entry type {0:?} zome id was out of range")]
     ZomeId(ZomeId),
 }
@@ -43,3 +46,4 @@ impl<T> From<AppValidationError> for OutcomeOrError<T, AppValidationError> {
 from_sub_error!(AppValidationError, RibosomeError);
 from_sub_error!(AppValidationError, CascadeError);
 from_sub_error!(AppValidationError, EntryDefStoreError);
+from_sub_error!(AppValidationError, ValidationPoolError);
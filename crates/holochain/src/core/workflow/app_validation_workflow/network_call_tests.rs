@@ -1,41 +1,39 @@
-use std::convert::TryInto;
-
 use fallible_iterator::FallibleIterator;
-use hdk3::prelude::ValidationPackage;
+use hdk3::prelude::{RequiredValidationType, ValidationPackage};
 use holo_hash::HeaderHash;
 use holochain_p2p::HolochainP2pCellT;
+use holochain_types::{
+    test_utils::{fake_agent_pubkey_1, fake_agent_pubkey_2},
+    validate::ValidationPackageReport,
+};
 use holochain_wasm_test_utils::TestWasm;
+use holochain_zome_types::{element::ElementEntry, entry_def::EntryVisibility};
 
-use crate::test_utils::{conductor_setup::ConductorCallData, host_fn_api::*, new_invocation};
-use crate::{
-    core::state::source_chain::SourceChain, test_utils::conductor_setup::ConductorTestData,
-};
+use crate::core::state::source_chain::SourceChain;
+use crate::test_utils::test_conductor::{test_dna_file, TestCell, TestConductor};
 
 #[tokio::test(threaded_scheduler)]
 async fn get_validation_package_test() {
     observability::test_run().ok();
 
-    let zomes = vec![TestWasm::Create];
-    let conductor_test = ConductorTestData::new(zomes, false).await;
-    let ConductorTestData {
-        __tmpdir,
-        handle,
-        mut alice_call_data,
-        ..
-    } = conductor_test;
-    let alice_cell_id = &alice_call_data.cell_id;
-    let alice_agent_id = alice_cell_id.agent_pubkey();
-
-    let header_hash = commit_some_data("create_entry", &alice_call_data).await;
-
-    let validation_package = alice_call_data
-        .network
+    let conductor = TestConductor::new().await;
+    let dna = test_dna_file(vec![TestWasm::Create]).await;
+    let alice = conductor
+        .setup_app("alice", fake_agent_pubkey_1(), &[dna])
+        .await;
+    let alice = &alice.cells()[0];
+    let alice_agent_id = alice.cell_id().agent_pubkey();
+    let mut network = alice.network().clone();
+
+    let header_hash = commit_some_data("create_entry", alice).await;
+
+    let validation_package = network
         .get_validation_package(alice_agent_id.clone(), header_hash.clone())
         .await
         .unwrap();
 
     // Expecting every header from the latest to the beginning
-    let alice_source_chain = SourceChain::public_only(alice_call_data.env.clone().into()).unwrap();
+    let alice_source_chain = SourceChain::public_only(alice.env().clone().into()).unwrap();
     let alice_authored = alice_source_chain.elements();
     let expected_package = alice_source_chain
         .iter_back()
@@ -46,21 +44,19 @@ async fn get_validation_package_test() {
     assert_eq!(validation_package, expected_package);
 
     // What happens if we commit a private entry?
-    let header_hash_priv = commit_some_data("create_priv_msg", &alice_call_data).await;
+    let header_hash_priv = commit_some_data("create_priv_msg", alice).await;
 
     // Check we still get the last package with new commits
-    let validation_package = alice_call_data
-        .network
+    let validation_package = network
         .get_validation_package(alice_agent_id.clone(), header_hash)
         .await
         .unwrap();
     assert_eq!(validation_package, expected_package);
 
     // Get the package for the private entry, this is still full chain
-    let alice_source_chain = SourceChain::public_only(alice_call_data.env.clone().into()).unwrap();
+    let alice_source_chain = SourceChain::public_only(alice.env().clone().into()).unwrap();
     let alice_authored = alice_source_chain.elements();
-    let validation_package = alice_call_data
-        .network
+    let validation_package = network
         .get_validation_package(alice_agent_id.clone(), header_hash_priv)
         .await
         .unwrap();
@@ -77,7 +73,7 @@ async fn get_validation_package_test() {
     // Test sub chain package
 
     // Commit some entries with sub chain requirements
-    let header_hash = commit_some_data("create_msg", &alice_call_data).await;
+    let header_hash = commit_some_data("create_msg", alice).await;
 
     // Get the entry type
     let entry_type = alice_source_chain
@@ -90,14 +86,13 @@ async fn get_validation_package_test() {
         .1
         .clone();
 
-    let validation_package = alice_call_data
-        .network
+    let validation_package = network
         .get_validation_package(alice_agent_id.clone(), header_hash)
         .await
         .unwrap();
 
     // Expecting all the elements that match this entry type from the latest to the start
-    let alice_source_chain = SourceChain::public_only(alice_call_data.env.clone().into()).unwrap();
+    let alice_source_chain = SourceChain::public_only(alice.env().clone().into()).unwrap();
     let alice_authored = alice_source_chain.elements();
     let expected_package = alice_source_chain
         .iter_back()
@@ -117,25 +112,96 @@ async fn get_validation_package_test() {
     let expected_package = Some(ValidationPackage::new(expected_package)).into();
     assert_eq!(validation_package, expected_package);
 
-    ConductorTestData::shutdown_conductor(handle).await;
+    conductor.shutdown().await;
 }
 
-async fn commit_some_data(call: &'static str, alice_call_data: &ConductorCallData) -> HeaderHash {
+/// The package a cell builds for its own header is the same one it sends
+/// over the network, except that only the author sees its private entries
+#[tokio::test(threaded_scheduler)]
+async fn build_and_fetch_validation_package_agree() {
+    observability::test_run().ok();
+
+    let conductor = TestConductor::new().await;
+    let dna = test_dna_file(vec![TestWasm::Create]).await;
+    let alice = conductor
+        .setup_app("alice", fake_agent_pubkey_1(), &[dna.clone()])
+        .await;
+    let bob = conductor
+        .setup_app("bob", fake_agent_pubkey_2(), &[dna])
+        .await;
+    let handle = conductor.handle();
+    let alice = &alice.cells()[0];
+    let bob = &bob.cells()[0];
+
+    commit_some_data("create_priv_msg", alice).await;
+    let header_hash = commit_some_data("create_entry", alice).await;
+    // Bob needs to hold the header to ask for its package
+    conductor.consistency().await;
+
+    let built = handle
+        .build_validation_package(alice.cell_id(), header_hash.clone())
+        .await
+        .unwrap();
+    let fetched_by_alice = handle
+        .fetch_validation_package_from_author(alice.cell_id(), header_hash.clone())
+        .await
+        .unwrap();
+    let fetched_by_bob = handle
+        .fetch_validation_package_from_author(bob.cell_id(), header_hash)
+        .await
+        .unwrap();
+
+    let header_hashes = |report: &ValidationPackageReport| {
+        report
+            .package
+            .0
+            .iter()
+            .map(|el| el.header_address().clone())
+            .collect::<Vec<_>>()
+    };
+    let private_entries = |report: &ValidationPackageReport| {
+        report
+            .package
+            .0
+            .iter()
+            .filter(|el| {
+                matches!(el.entry(), ElementEntry::Present(_))
+                    && el.header().entry_data().map(|(_, et)| *et.visibility())
+                        == Some(EntryVisibility::Private)
+            })
+            .count()
+    };
+    assert!(built.summary.package_present);
+    assert_eq!(
+        built.summary.required_validation_type,
+        Some(RequiredValidationType::Full)
+    );
+    assert!(built.summary.element_count > 10);
+    assert_eq!(header_hashes(&built), header_hashes(&fetched_by_alice));
+    assert_eq!(header_hashes(&built), header_hashes(&fetched_by_bob));
+
+    // Only the package built by the author holds the private entries
+    let private_count = private_entries(&built);
+    assert!(private_count >= 5);
+    assert_eq!(built.summary.redacted_entries, 0);
+    for fetched in &[fetched_by_alice, fetched_by_bob] {
+        assert_eq!(private_entries(fetched), 0);
+        assert_eq!(fetched.summary.redacted_entries, private_count);
+        assert_eq!(
+            fetched.summary.required_validation_type,
+            built.summary.required_validation_type
+        );
+        assert_eq!(fetched.summary.element_count, built.summary.element_count);
+    }
+
+    conductor.shutdown().await;
+}
+
+async fn commit_some_data(call: &'static str, alice: &TestCell) -> HeaderHash {
     let mut header_hash = None;
     // Commit 5 entries
     for _ in 0..5 {
-        let invocation =
-            new_invocation(&alice_call_data.cell_id, call, (), TestWasm::Create).unwrap();
-        header_hash = Some(
-            call_zome_direct(
-                &alice_call_data.env,
-                alice_call_data.call_data(TestWasm::Create),
-                invocation,
-            )
-            .await
-            .try_into()
-            .unwrap(),
-        );
+        header_hash = Some(alice.call(TestWasm::Create, call, ()).await);
     }
     header_hash.unwrap()
 }
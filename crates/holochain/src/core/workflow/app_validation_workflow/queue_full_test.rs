@@ -0,0 +1,154 @@
+use super::*;
+use crate::{
+    conductor::api::MockCellConductorApi,
+    core::{
+        ribosome::{module_cache::WasmModuleCache, validation_pool::ValidationPool},
+        workflow::incoming_dht_ops_workflow::incoming_dht_ops_workflow,
+    },
+    fixt::{CreateFixturator, DnaFileFixturator},
+    test_utils::test_network,
+};
+use ::fixt::prelude::*;
+use holochain_state::{
+    env::EnvironmentWrite,
+    test_utils::{test_cell_env, test_wasm_env},
+};
+use holochain_types::{
+    cell::CellId,
+    element::{SignedHeaderHashed, SignedHeaderHashedExt},
+    test_utils::fake_agent_pubkey_1,
+};
+use std::sync::Barrier;
+
+fn conductor_api(
+    cell_id: &CellId,
+    dna_file: &DnaFile,
+    wasm_module_cache: &WasmModuleCache,
+) -> MockCellConductorApi {
+    let mut conductor_api = MockCellConductorApi::new();
+    conductor_api.expect_cell_id().return_const(cell_id.clone());
+    conductor_api
+        .expect_sync_get_this_dna()
+        .return_const(Some(dna_file.clone()));
+    conductor_api
+        .expect_mock_wasm_module_cache()
+        .return_const(wasm_module_cache.clone());
+    conductor_api
+}
+
+fn limbo_value(env: &EnvironmentWrite, op_hash: &DhtOpHash) -> Option<ValidationLimboValue> {
+    let workspace = AppValidationWorkspace::new(env.clone().into()).unwrap();
+    workspace.validation_limbo.get(op_hash).unwrap()
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn ops_wait_for_room_in_a_full_validation_queue() {
+    observability::test_run().ok();
+    let test_env = test_cell_env();
+    let env = test_env.env();
+    let test_wasm_env = test_wasm_env();
+    let keystore = env.keystore().clone();
+
+    // An op which is validated by every zome
+    let author = fake_agent_pubkey_1();
+    let dna_file = DnaFileFixturator::new(Empty).next().unwrap();
+    let cell_id = CellId::new(dna_file.dna_hash().clone(), author.clone());
+    let mut create = fixt!(Create);
+    create.author = author.clone();
+    create.entry_type = EntryType::AgentPubKey;
+    let signed = SignedHeaderHashed::new(
+        &keystore,
+        HeaderHashed::from_content_sync(Header::Create(create)),
+    )
+    .await
+    .unwrap();
+    let op = DhtOp::StoreElement(signed.signature().clone(), signed.header().clone(), None);
+    let op_hash = DhtOpHash::with_data_sync(&op);
+
+    // Skip straight to app validation
+    let (trigger, _rx) = TriggerSender::new();
+    incoming_dht_ops_workflow(&env, trigger, vec![(op_hash.clone(), op)])
+        .await
+        .unwrap();
+    {
+        let mut workspace = AppValidationWorkspace::new(env.clone().into()).unwrap();
+        let mut vlv = workspace.validation_limbo.get(&op_hash).unwrap().unwrap();
+        vlv.status = ValidationLimboStatus::SysValidated;
+        workspace
+            .validation_limbo
+            .put(op_hash.clone(), vlv)
+            .unwrap();
+        env.guard()
+            .with_commit(|writer| workspace.flush_to_txn_ref(writer))
+            .unwrap();
+    }
+
+    let wasm_module_cache = WasmModuleCache::new(test_wasm_env.env());
+    let (_network, _recv, cell_network) =
+        test_network(Some(cell_id.dna_hash().clone()), Some(author)).await;
+    let breaker = ValidationBreaker::new(Default::default());
+    let (mut trigger_integration, _rx) = TriggerSender::new();
+
+    // An op skipped because of the limit isn't counted as a try
+    let workspace = AppValidationWorkspace::new(env.clone().into()).unwrap();
+    let complete = app_validation_workflow(
+        workspace,
+        env.clone().into(),
+        &mut trigger_integration,
+        conductor_api(&cell_id, &dna_file, &wasm_module_cache),
+        cell_network.clone(),
+        &ExecutionContext::Caller,
+        &breaker,
+        Some(0),
+    )
+    .await
+    .unwrap();
+    assert_eq!(complete, WorkComplete::Incomplete);
+    let vlv = limbo_value(&env, &op_hash).unwrap();
+    assert_eq!(vlv.status, ValidationLimboStatus::SysValidated);
+    assert_eq!(vlv.num_tries, 0);
+
+    // Hold the pool's only thread, with no room to queue more work
+    let pool = ValidationPool::new(1, 0);
+    let barrier = Arc::new(Barrier::new(2));
+    let (started, is_started) = tokio::sync::oneshot::channel();
+    let busy = tokio::spawn({
+        let pool = pool.clone();
+        let barrier = barrier.clone();
+        async move {
+            pool.run(move || {
+                started.send(()).unwrap();
+                barrier.wait();
+            })
+            .await
+        }
+    });
+    is_started.await.unwrap();
+
+    // The workflow waits for the pool rather than giving up on the op
+    let context = ExecutionContext::Validation(pool);
+    let workspace = AppValidationWorkspace::new(env.clone().into()).unwrap();
+    let run = app_validation_workflow(
+        workspace,
+        env.clone().into(),
+        &mut trigger_integration,
+        conductor_api(&cell_id, &dna_file, &wasm_module_cache),
+        cell_network,
+        &context,
+        &breaker,
+        None,
+    );
+    tokio::pin!(run);
+    assert!(
+        tokio::time::timeout(std::time::Duration::from_millis(200), &mut run)
+            .await
+            .is_err()
+    );
+
+    // Once the pool is free the op is validated and leaves the limbo
+    barrier.wait();
+    busy.await.unwrap().unwrap();
+    let complete = run.await.unwrap();
+    assert_eq!(complete, WorkComplete::Complete);
+    assert_eq!(limbo_value(&env, &op_hash), None);
+}
@@ -1,13 +1,16 @@
 use super::{
-    app_validation_workflow, error::WorkflowResult, sys_validation_workflow::sys_validate_element,
+    app_validation_workflow, error::WorkflowResult,
+    integrate_dht_ops_workflow::disintegrate_from_authored,
+    sys_validation_workflow::sys_validate_element,
 };
 use crate::conductor::api::CellConductorApiT;
 use crate::conductor::interface::SignalBroadcaster;
 use crate::core::ribosome::error::RibosomeError;
+use crate::core::ribosome::metering::ZomeCallResources;
 use crate::core::ribosome::ZomeCallInvocation;
 use crate::core::ribosome::{error::RibosomeResult, RibosomeT, ZomeCallHostAccess};
-use crate::core::state::metadata::MetadataBufT;
-use crate::core::state::source_chain::SourceChainError;
+use crate::core::state::metadata::{ChainItemKey, MetadataBufT};
+use crate::core::state::source_chain::{ChainInvalidReason, SourceChainError, SourceChainResult};
 use crate::core::state::workspace::Workspace;
 use crate::core::{
     queue_consumer::{OneshotWriter, TriggerSender},
@@ -18,10 +21,13 @@ use crate::core::{
 };
 pub use call_zome_workspace_lock::CallZomeWorkspaceLock;
 use either::Either;
+use fallible_iterator::FallibleIterator;
+use holo_hash::EntryHash;
 use holochain_keystore::KeystoreSender;
 use holochain_p2p::HolochainP2pCell;
-use holochain_state::prelude::*;
+use holochain_state::{fresh_reader, prelude::*};
 use holochain_types::element::Element;
+use holochain_types::metadata::TimedHeaderHash;
 use holochain_zome_types::entry::GetOptions;
 use holochain_zome_types::header::Header;
 use holochain_zome_types::ZomeCallResponse;
@@ -30,6 +36,25 @@ use tracing::instrument;
 
 pub mod call_zome_workspace_lock;
 
+#[cfg(test)]
+mod benchmark_test;
+
+#[cfg(test)]
+mod dry_run_test;
+
+#[cfg(test)]
+#[cfg(feature = "slow_tests")]
+mod durability_test;
+
+#[cfg(test)]
+#[cfg(feature = "slow_tests")]
+#[cfg(target_arch = "x86_64")]
+mod metering_test;
+
+#[cfg(test)]
+#[cfg(feature = "slow_tests")]
+mod scratch_size_test;
+
 #[cfg(test)]
 mod validation_test;
 
@@ -37,6 +62,19 @@ mod validation_test;
 /// TODO: do we want this to be the same as ZomeCallInvocationRESPONSE?
 pub type ZomeCallInvocationResult = RibosomeResult<ZomeCallResponse>;
 
+/// The result of a zome invocation along with the resources the guest used,
+/// for the conductor. Clients are only sent the result.
+#[derive(Debug)]
+pub struct MeteredZomeCallInvocationResult {
+    /// What the call returned
+    pub result: ZomeCallInvocationResult,
+    /// The resources the call used, if it reached the guest
+    pub resources: Option<ZomeCallResources>,
+}
+
+/// How many bytes a zome call may write to its workspace if no limit is configured
+pub const DEFAULT_SCRATCH_SIZE_LIMIT: usize = 64 * 1024 * 1024;
+
 #[derive(Debug)]
 pub struct CallZomeWorkflowArgs<Ribosome: RibosomeT, C: CellConductorApiT> {
     pub ribosome: Ribosome,
@@ -45,6 +83,9 @@ pub struct CallZomeWorkflowArgs<Ribosome: RibosomeT, C: CellConductorApiT> {
     pub conductor_api: C,
 }
 
+/// Run a zome call and commit what it wrote.
+/// Returns the result along with the peak size of the workspace's scratch
+/// space during the call, see [CallZomeWorkspace::peak_scratch_size].
 #[instrument(skip(workspace, network, keystore, writer, args, trigger_produce_dht_ops))]
 pub async fn call_zome_workflow<'env, Ribosome: RibosomeT, C: CellConductorApiT>(
     workspace: CallZomeWorkspace,
@@ -53,22 +94,55 @@ pub async fn call_zome_workflow<'env, Ribosome: RibosomeT, C: CellConductorApiT>
     writer: OneshotWriter,
     args: CallZomeWorkflowArgs<Ribosome, C>,
     mut trigger_produce_dht_ops: TriggerSender,
-) -> WorkflowResult<ZomeCallInvocationResult> {
+) -> WorkflowResult<(ZomeCallInvocationResult, usize)> {
+    let durability = args.invocation.durability;
     let workspace_lock = CallZomeWorkspaceLock::new(workspace);
     let result = call_zome_workflow_inner(workspace_lock.clone(), network, keystore, args).await?;
 
     // --- END OF WORKFLOW, BEGIN FINISHER BOILERPLATE ---
 
     // commit the workspace
-    {
+    let peak_scratch_size = {
         let mut guard = workspace_lock.write().await;
         let workspace = &mut guard;
-        writer.with_writer(|writer| Ok(workspace.flush_to_txn_ref(writer)?))?;
-    }
+        writer
+            .with_durability(durability)
+            .with_writer(|writer| Ok(workspace.flush_to_txn_ref(writer)?))?;
+        workspace.peak_scratch_size()
+    };
 
     trigger_produce_dht_ops.trigger();
 
-    Ok(result)
+    Ok((result, peak_scratch_size))
+}
+
+/// Run a zome call against the workspace without committing anything.
+///
+/// The call is run and validated exactly as in [call_zome_workflow], but the
+/// finisher is skipped: nothing is flushed and no DhtOps are produced.
+/// Returns the result along with the headers which would have been written.
+#[instrument(skip(workspace, network, keystore, args))]
+pub async fn call_zome_workflow_dry_run<'env, Ribosome: RibosomeT, C: CellConductorApiT>(
+    workspace: CallZomeWorkspace,
+    network: HolochainP2pCell,
+    keystore: KeystoreSender,
+    args: CallZomeWorkflowArgs<Ribosome, C>,
+) -> WorkflowResult<(ZomeCallInvocationResult, Vec<Header>)> {
+    let chain_head_start_len = workspace.source_chain.len();
+    let workspace_lock = CallZomeWorkspaceLock::new(workspace);
+    let result = call_zome_workflow_inner(workspace_lock.clone(), network, keystore, args).await?;
+
+    // Collect the headers that would have been committed.
+    // The workspace is dropped afterwards without being flushed.
+    let workspace = workspace_lock.read().await;
+    let mut pending_headers = Vec::new();
+    let mut i = chain_head_start_len;
+    while let Some(element) = workspace.source_chain.get_at_index(i as u32)? {
+        pending_headers.push(element.header().clone());
+        i += 1;
+    }
+
+    Ok((result, pending_headers))
 }
 
 async fn call_zome_workflow_inner<'env, Ribosome: RibosomeT, C: CellConductorApiT>(
@@ -103,6 +177,16 @@ async fn call_zome_workflow_inner<'env, Ribosome: RibosomeT, C: CellConductorApi
     };
     tracing::trace!(line = line!());
 
+    // A call which ran out of fuel is aborted, so nothing it wrote is flushed
+    let result = match result {
+        Err(e @ RibosomeError::FuelExhausted { .. }) => return Err(e.into()),
+        result => result,
+    };
+
+    // A commit which went over the scratch limit fails the call
+    // even if the zome carried on, so nothing is flushed
+    workspace_lock.write().await.check_scratch_size()?;
+
     let to_app_validate = {
         let mut workspace = workspace_lock.write().await;
         // Get the new head
@@ -233,6 +317,14 @@ async fn call_zome_workflow_inner<'env, Ribosome: RibosomeT, C: CellConductorApi
     Ok(result)
 }
 
+/// A point in a zome call which its workspace can go back to,
+/// see [CallZomeWorkspace::apply_checkpoint]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WorkspaceCheckpoint {
+    /// How long the source chain was
+    chain_len: usize,
+}
+
 pub struct CallZomeWorkspace {
     pub source_chain: SourceChain,
     pub meta_authored: MetadataBuf<AuthoredPrefix>,
@@ -240,6 +332,10 @@ pub struct CallZomeWorkspace {
     pub meta_integrated: MetadataBuf<IntegratedPrefix>,
     pub element_cache: ElementBuf,
     pub meta_cache: MetadataBuf,
+    /// The most bytes the scratch spaces may hold, if limited
+    scratch_size_limit: Option<usize>,
+    /// The most bytes the scratch spaces have held when checked
+    peak_scratch_size: usize,
 }
 
 impl<'a> CallZomeWorkspace {
@@ -258,12 +354,69 @@ impl<'a> CallZomeWorkspace {
             meta_integrated,
             element_cache,
             meta_cache,
+            scratch_size_limit: None,
+            peak_scratch_size: 0,
         })
     }
 
+    /// Limit how many bytes the scratch spaces may hold,
+    /// see [CallZomeWorkspace::check_scratch_size]
+    pub fn with_scratch_size_limit(mut self, limit: usize) -> Self {
+        self.scratch_size_limit = Some(limit);
+        self
+    }
+
+    /// Roughly how many bytes the call has written to the scratch spaces.
+    /// The caches only hold what gets fetched from the network,
+    /// so they aren't counted against the call.
+    pub fn scratch_size(&self) -> usize {
+        self.source_chain.scratch_size()
+            + self.meta_authored.scratch_size()
+            + self.element_integrated.scratch_size()
+            + self.meta_integrated.scratch_size()
+    }
+
+    /// The largest the scratch spaces have been when checked
+    pub fn peak_scratch_size(&self) -> usize {
+        self.peak_scratch_size
+    }
+
+    /// Check the scratch spaces hold no more than the limit, if there is one.
+    /// The commit host functions check after each commit so that a call
+    /// which writes too much fails before it can use up the conductor's memory.
+    pub fn check_scratch_size(&mut self) -> SourceChainResult<()> {
+        let used = self.scratch_size();
+        self.peak_scratch_size = self.peak_scratch_size.max(used);
+        match self.scratch_size_limit {
+            Some(limit) if used > limit => {
+                Err(SourceChainError::ScratchSizeLimitExceeded { used, limit })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Mark the current state of the source chain and authored metadata,
+    /// so that a call can undo the commits it makes after this point with
+    /// [CallZomeWorkspace::restore_checkpoint].
+    pub fn apply_checkpoint(&mut self) -> WorkspaceCheckpoint {
+        WorkspaceCheckpoint {
+            chain_len: self.source_chain.len(),
+        }
+    }
+
+    /// Take every element committed since the checkpoint back off the
+    /// source chain, along with the metadata they added to the authored store.
+    /// A checkpoint from before the workspace was last flushed can't be restored.
+    pub fn restore_checkpoint(&mut self, checkpoint: WorkspaceCheckpoint) -> WorkspaceResult<()> {
+        let rewound = self.source_chain.rewind_to(checkpoint.chain_len)?;
+        for element in rewound.iter() {
+            disintegrate_from_authored(element, &mut self.meta_authored)?;
+        }
+        Ok(())
+    }
+
     pub fn cascade(&'a mut self, network: HolochainP2pCell) -> Cascade<'a> {
         Cascade::new(
-            self.source_chain.env().clone(),
             &self.source_chain.elements(),
             &self.meta_authored,
             &self.element_integrated,
@@ -277,10 +430,93 @@ impl<'a> CallZomeWorkspace {
     pub fn env(&self) -> &EnvironmentRead {
         self.meta_authored.env()
     }
+
+    /// Check that the authored data in this workspace is internally consistent:
+    /// - every authored element's entry hashes to the entry hash in its header
+    /// - every authored metadata item references an element on the source chain
+    /// - header sequence numbers increase monotonically
+    ///
+    /// It reads back the whole chain, so it only runs before every flush
+    /// in debug builds.
+    pub fn validate_workspace_consistency(&self) -> WorkspaceResult<()> {
+        let mut prev_seq: Option<u32> = None;
+        let mut entry_hashes = Vec::new();
+        let mut author = None;
+        for i in 0..self.source_chain.len() as u32 {
+            let header_hash = match self.source_chain.sequence().get(i)? {
+                Some(h) => h,
+                None => continue,
+            };
+            let element = self
+                .source_chain
+                .get_element(&header_hash)?
+                .ok_or_else(|| {
+                    SourceChainError::InvalidStructure(ChainInvalidReason::MissingHeader(
+                        header_hash.clone(),
+                    ))
+                })?;
+            let header = element.header();
+
+            let seq = header.header_seq();
+            if let Some(prev_seq) = prev_seq {
+                if seq <= prev_seq {
+                    return Err(SourceChainError::InvalidStructure(
+                        ChainInvalidReason::NonMonotonicSequence(prev_seq, seq),
+                    )
+                    .into());
+                }
+            }
+            prev_seq = Some(seq);
+
+            if let (Some((entry_hash, _)), Some(entry)) =
+                (header.entry_data(), element.entry().as_option())
+            {
+                if EntryHash::with_data_sync(entry) != *entry_hash {
+                    return Err(SourceChainError::InvalidStructure(
+                        ChainInvalidReason::HeaderAndEntryMismatch(entry_hash.clone()),
+                    )
+                    .into());
+                }
+                entry_hashes.push(entry_hash.clone());
+            }
+            if author.is_none() {
+                author = Some(header.author().clone());
+            }
+        }
+
+        let author = match author {
+            Some(author) => author,
+            None => return Ok(()),
+        };
+        let check_exists =
+            |timed: TimedHeaderHash| {
+                if self.source_chain.get_header(&timed.header_hash)?.is_none() {
+                    return Err(SourceChainError::InvalidStructure(
+                        ChainInvalidReason::MissingHeader(timed.header_hash),
+                    )
+                    .into());
+                }
+                WorkspaceResult::Ok(())
+            };
+        fresh_reader!(self.env(), |r| {
+            let activity: Vec<_> = self
+                .meta_authored
+                .get_activity(&r, ChainItemKey::Agent(author))?
+                .collect()?;
+            activity.into_iter().try_for_each(&check_exists)?;
+            for entry_hash in entry_hashes {
+                let headers: Vec<_> = self.meta_authored.get_headers(&r, entry_hash)?.collect()?;
+                headers.into_iter().try_for_each(&check_exists)?;
+            }
+            WorkspaceResult::Ok(())
+        })
+    }
 }
 
 impl Workspace for CallZomeWorkspace {
     fn flush_to_txn_ref(&mut self, writer: &mut Writer) -> WorkspaceResult<()> {
+        #[cfg(debug_assertions)]
+        self.validate_workspace_consistency()?;
         self.source_chain.flush_to_txn_ref(writer)?;
         self.meta_authored.flush_to_txn_ref(writer)?;
         self.element_cache.flush_to_txn_ref(writer)?;
@@ -293,19 +529,32 @@ impl Workspace for CallZomeWorkspace {
 pub mod tests {
     use super::*;
     use crate::conductor::{api::CellConductorApi, handle::MockConductorHandleT};
+    use crate::core::state::metadata::SysMetaVal;
+    use crate::core::state::workspace::WorkspaceError;
     use crate::core::{
-        ribosome::MockRibosomeT,
-        workflow::{error::WorkflowError, genesis_workflow::tests::fake_genesis},
+        ribosome::{MockRibosomeT, RecordingRibosome},
+        workflow::{
+            error::WorkflowError, genesis_workflow::tests::fake_genesis,
+            integrate_dht_ops_workflow::integrate_to_authored,
+        },
     };
     use crate::fixt::KeystoreSenderFixturator;
     use ::fixt::prelude::*;
     use holo_hash::fixt::*;
     use holochain_p2p::HolochainP2pCellFixturator;
     use holochain_serialized_bytes::prelude::*;
-    use holochain_state::{env::ReadManager, test_utils::test_cell_env};
-    use holochain_types::{cell::CellId, observability, test_utils::fake_agent_pubkey_1};
+    use holochain_state::{env::WriteManager, fresh_reader_test, test_utils::test_cell_env};
+    use holochain_types::{
+        cell::CellId,
+        fixt::CapSecretFixturator,
+        metadata::TimedHeaderHash,
+        observability,
+        test_utils::{fake_agent_pubkey_1, fake_agent_pubkey_2},
+        Timestamp,
+    };
     use holochain_wasm_test_utils::TestWasm;
     use holochain_zome_types::entry::Entry;
+    use holochain_zome_types::header;
     use holochain_zome_types::ExternInput;
     use holochain_zome_types::ExternOutput;
     use matches::assert_matches;
@@ -339,13 +588,10 @@ pub mod tests {
     // we stop the process and return an error. MVT
     // TODO: B-01553: Finish this test when capabilities land
     #[ignore]
-    #[allow(unused_variables, unreachable_code)]
     #[tokio::test]
     async fn private_zome_call() {
         let test_env = test_cell_env();
         let env = test_env.env();
-        let env_ref = env.guard();
-        let reader = env_ref.reader().unwrap();
         let workspace = CallZomeWorkspace::new(env.clone().into()).unwrap();
         let ribosome = MockRibosomeT::new();
         // FIXME: CAP: Set this function to private
@@ -359,9 +605,10 @@ pub mod tests {
                 ExternInput::new(Payload { a: 1 }.try_into().unwrap()),
             ),
         )
+        .with_cap(Some(fixt!(CapSecret)))
+        .with_provenance(fixt!(AgentPubKey))
         .next()
         .unwrap();
-        invocation.cap = todo!("Make secret cap token");
         let error = run_call_zome(workspace, ribosome, invocation)
             .await
             .unwrap_err();
@@ -412,16 +659,16 @@ pub mod tests {
         // Genesis
         fake_genesis(&mut workspace.source_chain).await.unwrap();
 
+        let chain_head = workspace.source_chain.chain_head().unwrap().clone();
+
         let agent_pubkey = fake_agent_pubkey_1();
         let _agent_entry = Entry::Agent(agent_pubkey.clone().into());
         let mut ribosome = MockRibosomeT::new();
         // Call zome mock that it writes to source chain
-        ribosome
-            .expect_call_zome_function()
-            .returning(move |_workspace, _invocation| {
-                let x = SerializedBytes::try_from(Payload { a: 3 }).unwrap();
-                Ok(ZomeCallResponse::Ok(ExternOutput::new(x)))
-            });
+        let recording = RecordingRibosome::new(&mut ribosome, move |_workspace, _invocation| {
+            let x = SerializedBytes::try_from(Payload { a: 3 }).unwrap();
+            Ok(ZomeCallResponse::Ok(ExternOutput::new(x)))
+        });
 
         let invocation = crate::core::ribosome::ZomeCallInvocationFixturator::new(
             crate::core::ribosome::NamedInvocation(
@@ -435,6 +682,7 @@ pub mod tests {
         )
         .next()
         .unwrap();
+        let expected = invocation.clone();
         // IDEA: Mock the system validation and check it's called
         /* This is one way to test the correctness of the calls to sys val
         let mut sys_val = MockSystemValidation::new();
@@ -447,6 +695,17 @@ pub mod tests {
         let _result = run_call_zome(workspace, ribosome, invocation)
             .await
             .unwrap();
+
+        // The ribosome was called once, with the invocation and the genesis workspace
+        let calls = recording.calls();
+        assert_eq!(calls.len(), 1);
+        let (invocation, access) = &calls[0];
+        assert_eq!(invocation.cell_id, expected.cell_id);
+        assert_eq!(invocation.zome_name, expected.zome_name);
+        assert_eq!(invocation.fn_name, expected.fn_name);
+        assert_eq!(invocation.payload, expected.payload);
+        let workspace = access.workspace.read().await;
+        assert_eq!(workspace.source_chain.chain_head().unwrap(), &chain_head);
     }
 
     // 4.2. Call app validation of list of entries and headers: (MVI)
@@ -508,4 +767,175 @@ pub mod tests {
             .unwrap();
         // TODO: Check the workspace has changes
     }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn workspace_consistency_detects_non_monotonic_sequence() {
+        observability::test_run().ok();
+        let test_env = test_cell_env();
+        let env = test_env.env();
+        let mut workspace = CallZomeWorkspace::new(env.clone().into()).unwrap();
+
+        fake_genesis(&mut workspace.source_chain).await.unwrap();
+        workspace.validate_workspace_consistency().unwrap();
+
+        // Reuse a sequence number from genesis
+        let header = Header::InitZomesComplete(header::InitZomesComplete {
+            author: fake_agent_pubkey_1(),
+            timestamp: Timestamp::now().into(),
+            header_seq: 1,
+            prev_header: workspace.source_chain.chain_head().unwrap().clone(),
+        });
+        workspace.source_chain.put_raw(header, None).await.unwrap();
+
+        let err = workspace.validate_workspace_consistency().unwrap_err();
+        assert_matches!(
+            err,
+            WorkspaceError::SourceChainError(SourceChainError::InvalidStructure(
+                ChainInvalidReason::NonMonotonicSequence(2, 1)
+            ))
+        );
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn only_the_calls_own_writes_count_against_the_scratch_size_limit() {
+        observability::test_run().ok();
+        let test_env = test_cell_env();
+        let env = test_env.env();
+        let mut workspace = CallZomeWorkspace::new(env.clone().into())
+            .unwrap()
+            .with_scratch_size_limit(0);
+
+        // What is fetched from the network is cached, and doesn't count
+        workspace
+            .meta_cache
+            .register_raw_on_entry(
+                fixt!(EntryHash),
+                SysMetaVal::NewEntry(TimedHeaderHash {
+                    timestamp: Timestamp::now(),
+                    header_hash: fixt!(HeaderHash),
+                }),
+            )
+            .unwrap();
+        workspace.check_scratch_size().unwrap();
+
+        fake_genesis(&mut workspace.source_chain).await.unwrap();
+        assert_matches!(
+            workspace.check_scratch_size(),
+            Err(SourceChainError::ScratchSizeLimitExceeded { limit: 0, .. })
+        );
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn restoring_a_checkpoint_undoes_later_commits() {
+        observability::test_run().ok();
+        let test_env = test_cell_env();
+        let env = test_env.env();
+        {
+            let mut workspace = CallZomeWorkspace::new(env.clone().into()).unwrap();
+            fake_genesis(&mut workspace.source_chain).await.unwrap();
+            env.guard()
+                .with_commit(|writer| workspace.flush_to_txn(writer))
+                .unwrap();
+        }
+        let mut workspace = CallZomeWorkspace::new(env.clone().into()).unwrap();
+        let head = workspace.source_chain.chain_head().unwrap().clone();
+        let checkpoint = workspace.apply_checkpoint();
+
+        let entry = Entry::Agent(fake_agent_pubkey_2().into());
+        let entry_hash = EntryHash::with_data_sync(&entry);
+        let header_hash = workspace
+            .source_chain
+            .put(
+                header::builder::Create {
+                    entry_type: header::EntryType::AgentPubKey,
+                    entry_hash: entry_hash.clone(),
+                },
+                Some(entry),
+            )
+            .await
+            .unwrap();
+        let element = workspace
+            .source_chain
+            .get_element(&header_hash)
+            .unwrap()
+            .unwrap();
+        integrate_to_authored(
+            &element,
+            workspace.source_chain.elements(),
+            &mut workspace.meta_authored,
+        )
+        .await
+        .unwrap();
+
+        workspace.restore_checkpoint(checkpoint).unwrap();
+        assert_eq!(workspace.source_chain.len(), 3);
+        assert_eq!(workspace.source_chain.chain_head().unwrap(), &head);
+        assert_eq!(
+            workspace.source_chain.get_element(&header_hash).unwrap(),
+            None
+        );
+        assert_eq!(workspace.source_chain.get_entry(&entry_hash).unwrap(), None);
+        let headers = fresh_reader_test!(env, |r| workspace
+            .meta_authored
+            .get_headers(&r, entry_hash.clone())
+            .unwrap()
+            .count()
+            .unwrap());
+        assert_eq!(headers, 0);
+
+        // The chain can be built on again, but not rewound past the flush
+        workspace.validate_workspace_consistency().unwrap();
+        assert_matches!(
+            workspace.restore_checkpoint(WorkspaceCheckpoint { chain_len: 0 }),
+            Err(WorkspaceError::SourceChainError(
+                SourceChainError::InvalidCheckpoint { .. }
+            ))
+        );
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn checkpoint_cannot_be_restored_after_flushing_in_place() {
+        observability::test_run().ok();
+        let test_env = test_cell_env();
+        let env = test_env.env();
+        let mut workspace = CallZomeWorkspace::new(env.clone().into()).unwrap();
+        let checkpoint = workspace.apply_checkpoint();
+        fake_genesis(&mut workspace.source_chain).await.unwrap();
+        env.guard()
+            .with_commit(|writer| workspace.flush_to_txn_ref(writer))
+            .unwrap();
+
+        assert_matches!(
+            workspace.restore_checkpoint(checkpoint),
+            Err(WorkspaceError::SourceChainError(
+                SourceChainError::InvalidCheckpoint {
+                    checkpoint: 0,
+                    persisted_len: 3,
+                    len: 3,
+                }
+            ))
+        );
+        assert_eq!(workspace.source_chain.len(), 3);
+
+        // Commits after the flush can still be undone, and flushing again
+        // doesn't mistake the earlier flush for another writer moving the head
+        let checkpoint = workspace.apply_checkpoint();
+        let entry = Entry::Agent(fake_agent_pubkey_2().into());
+        workspace
+            .source_chain
+            .put(
+                header::builder::Create {
+                    entry_type: header::EntryType::AgentPubKey,
+                    entry_hash: EntryHash::with_data_sync(&entry),
+                },
+                Some(entry),
+            )
+            .await
+            .unwrap();
+        workspace.restore_checkpoint(checkpoint).unwrap();
+        assert_eq!(workspace.source_chain.len(), 3);
+        env.guard()
+            .with_commit(|writer| workspace.flush_to_txn_ref(writer))
+            .unwrap();
+    }
 }
@@ -0,0 +1,69 @@
+use crate::{
+    conductor::{api::error::ConductorApiError, dna_store::MockDnaStore, ConductorHandle},
+    test_utils::{new_invocation, setup_app},
+};
+use holochain_serialized_bytes::SerializedBytes;
+use holochain_types::{
+    app::InstalledCell, cell::CellId, dna::DnaDef, dna::DnaFile, test_utils::fake_agent_pubkey_1,
+};
+use holochain_wasm_test_utils::TestWasm;
+use matches::assert_matches;
+use std::convert::TryFrom;
+
+#[tokio::test(threaded_scheduler)]
+async fn benchmark_times_all_but_the_first_call() {
+    observability::test_run().ok();
+
+    let dna_file = DnaFile::new(
+        DnaDef {
+            name: "benchmark_times_all_but_the_first_call".to_string(),
+            uuid: "5d0b4f4e-6a53-4f0e-9a0c-2f4c3e8a1b7d".to_string(),
+            properties: SerializedBytes::try_from(()).unwrap(),
+            zomes: vec![TestWasm::Create.into()].into(),
+        },
+        vec![TestWasm::Create.into()],
+    )
+    .await
+    .unwrap();
+
+    let alice_agent_id = fake_agent_pubkey_1();
+    let alice_cell_id = CellId::new(dna_file.dna_hash().to_owned(), alice_agent_id.clone());
+    let alice_installed_cell = InstalledCell::new(alice_cell_id.clone(), "alice_handle".into());
+
+    let mut dna_store = MockDnaStore::new();
+
+    dna_store.expect_get().return_const(Some(dna_file.clone()));
+    dna_store.expect_add_dnas::<Vec<_>>().return_const(());
+    dna_store.expect_add_entry_defs::<Vec<_>>().return_const(());
+    dna_store.expect_get_entry_def().return_const(None);
+
+    let (_tmpdir, _app_api, handle) = setup_app(
+        vec![("test_app", vec![(alice_installed_cell, None)])],
+        dna_store,
+    )
+    .await;
+
+    run_test(alice_cell_id, handle.clone()).await;
+
+    let shutdown = handle.take_shutdown_handle().await.unwrap();
+    handle.shutdown().await;
+    shutdown.await.unwrap();
+}
+
+async fn run_test(alice_cell_id: CellId, handle: ConductorHandle) {
+    let invocation = new_invocation(&alice_cell_id, "get_entry", (), TestWasm::Create).unwrap();
+    let result = handle
+        .benchmark_zome_call(invocation.clone(), 5)
+        .await
+        .unwrap();
+    assert_eq!(result.runs, 4);
+    assert!(result.p50_ms <= result.p95_ms);
+    assert!(result.p95_ms <= result.p99_ms);
+    assert!(result.p99_ms <= result.max_ms);
+    assert!(result.mean_ms <= result.max_ms);
+
+    assert_matches!(
+        handle.benchmark_zome_call(invocation, 1).await,
+        Err(ConductorApiError::BenchmarkTooFewIterations(1))
+    );
+}
@@ -0,0 +1,74 @@
+use crate::{
+    conductor::{dna_store::MockDnaStore, ConductorHandle},
+    core::state::source_chain::SourceChainBuf,
+    test_utils::{new_invocation, setup_app},
+};
+use holochain_serialized_bytes::SerializedBytes;
+use holochain_types::{
+    app::InstalledCell, cell::CellId, dna::DnaDef, dna::DnaFile, test_utils::fake_agent_pubkey_1,
+};
+use holochain_wasm_test_utils::TestWasm;
+use holochain_zome_types::header::Header;
+use matches::assert_matches;
+use std::convert::TryFrom;
+
+#[tokio::test(threaded_scheduler)]
+async fn dry_run_does_not_commit() {
+    observability::test_run().ok();
+
+    let dna_file = DnaFile::new(
+        DnaDef {
+            name: "dry_run_does_not_commit".to_string(),
+            uuid: "0c2ea1b0-44e4-4a1e-8d0b-6f1b2a9f4d3c".to_string(),
+            properties: SerializedBytes::try_from(()).unwrap(),
+            zomes: vec![TestWasm::Create.into()].into(),
+        },
+        vec![TestWasm::Create.into()],
+    )
+    .await
+    .unwrap();
+
+    let alice_agent_id = fake_agent_pubkey_1();
+    let alice_cell_id = CellId::new(dna_file.dna_hash().to_owned(), alice_agent_id.clone());
+    let alice_installed_cell = InstalledCell::new(alice_cell_id.clone(), "alice_handle".into());
+
+    let mut dna_store = MockDnaStore::new();
+
+    dna_store.expect_get().return_const(Some(dna_file.clone()));
+    dna_store.expect_add_dnas::<Vec<_>>().return_const(());
+    dna_store.expect_add_entry_defs::<Vec<_>>().return_const(());
+    dna_store.expect_get_entry_def().return_const(None);
+
+    let (_tmpdir, _app_api, handle) = setup_app(
+        vec![("test_app", vec![(alice_installed_cell, None)])],
+        dna_store,
+    )
+    .await;
+
+    run_test(alice_cell_id, handle.clone()).await;
+
+    let shutdown = handle.take_shutdown_handle().await.unwrap();
+    handle.shutdown().await;
+    shutdown.await.unwrap();
+}
+
+/// - A dry run returns the headers it would have written
+/// - The source chain is left untouched
+async fn run_test(alice_cell_id: CellId, handle: ConductorHandle) {
+    // Make sure init has run so it doesn't count towards the chain length
+    let invocation = new_invocation(&alice_cell_id, "create_entry", (), TestWasm::Create).unwrap();
+    handle.call_zome(invocation).await.unwrap().unwrap();
+
+    let env = handle.get_cell_env(&alice_cell_id).await.unwrap();
+    let len_before = SourceChainBuf::new(env.clone().into()).unwrap().len();
+
+    let invocation = new_invocation(&alice_cell_id, "create_entry", (), TestWasm::Create).unwrap();
+    let (result, headers) = handle.call_zome_dry_run(invocation).await.unwrap();
+    result.unwrap();
+
+    assert_eq!(headers.len(), 1);
+    assert_matches!(headers[0], Header::Create(_));
+
+    let len_after = SourceChainBuf::new(env.into()).unwrap().len();
+    assert_eq!(len_before, len_after);
+}
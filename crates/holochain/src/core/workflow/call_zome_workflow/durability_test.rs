@@ -0,0 +1,101 @@
+use crate::{
+    conductor::{config::ConductorConfig, ConductorHandle},
+    core::{ribosome::ZomeCallInvocation, state::source_chain::SourceChainBuf},
+    test_utils::{
+        install_app, new_invocation, setup_conductor_with_dna, shutdown_conductor,
+        single_zome_dna_file,
+    },
+};
+use holochain_state::env::Durability;
+use holochain_types::{
+    app::InstalledCell,
+    cell::CellId,
+    test_utils::{fake_agent_pubkey_1, fake_cell_id},
+};
+use holochain_wasm_test_utils::TestWasm;
+use test_wasm_common::TestInt;
+
+fn create_entries(cell_id: &CellId, n: u32, durability: Durability) -> ZomeCallInvocation {
+    let mut invocation = new_invocation(
+        cell_id,
+        "create_entry_multiple",
+        TestInt(n),
+        TestWasm::MultipleCalls,
+    )
+    .unwrap();
+    invocation.durability = durability;
+    invocation
+}
+
+async fn chain_len(handle: &ConductorHandle, cell_id: &CellId) -> usize {
+    let env = handle.get_cell_env(cell_id).await.unwrap();
+    SourceChainBuf::new(env.into()).unwrap().len()
+}
+
+/// A call commits what it wrote whether or not it asks for the commit to be synced
+#[tokio::test(threaded_scheduler)]
+async fn calls_commit_with_either_durability() {
+    observability::test_run().ok();
+    let dna_file = single_zome_dna_file(
+        "durability",
+        "0c6f3a52-9e1d-4b8a-a7f4-2d5e8b1c6a93",
+        TestWasm::MultipleCalls,
+    )
+    .await;
+    let cell_id = CellId::new(dna_file.dna_hash().to_owned(), fake_agent_pubkey_1());
+    let installed_cell = InstalledCell::new(cell_id.clone(), "handle".into());
+    let (_tmpdirs, _, handle) =
+        setup_conductor_with_dna(&dna_file, ConductorConfig::default()).await;
+    install_app("app", vec![(installed_cell, None)], handle.clone()).await;
+
+    // Run init first so its commits aren't counted
+    handle
+        .call_zome(create_entries(&cell_id, 0, Durability::Sync))
+        .await
+        .unwrap()
+        .unwrap();
+    let len_before = chain_len(&handle, &cell_id).await;
+
+    handle
+        .call_zome(create_entries(&cell_id, 2, Durability::Sync))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(chain_len(&handle, &cell_id).await, len_before + 2);
+
+    handle
+        .call_zome(create_entries(&cell_id, 3, Durability::NoSync))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(chain_len(&handle, &cell_id).await, len_before + 5);
+
+    shutdown_conductor(handle).await;
+}
+
+/// Invocations from clients which don't know about durability are synced
+#[test]
+fn invocations_without_a_durability_are_synced() {
+    #[derive(serde::Serialize, Debug)]
+    struct OldZomeCallInvocation {
+        cell_id: CellId,
+        zome_name: holochain_zome_types::zome::ZomeName,
+        cap: Option<holochain_zome_types::capability::CapSecret>,
+        fn_name: holochain_zome_types::zome::FunctionName,
+        payload: holochain_zome_types::ExternInput,
+        provenance: holo_hash::AgentPubKey,
+    }
+
+    let invocation = create_entries(&fake_cell_id(1), 1, Durability::NoSync);
+    let old = OldZomeCallInvocation {
+        cell_id: invocation.cell_id,
+        zome_name: invocation.zome_name,
+        cap: invocation.cap,
+        fn_name: invocation.fn_name,
+        payload: invocation.payload,
+        provenance: invocation.provenance,
+    };
+    let bytes = holochain_serialized_bytes::encode(&old).unwrap();
+    let decoded: ZomeCallInvocation = holochain_serialized_bytes::decode(&bytes).unwrap();
+    assert_eq!(decoded.durability, Durability::Sync);
+}
@@ -0,0 +1,172 @@
+use crate::{
+    conductor::{
+        api::error::ConductorApiError,
+        config::{ConductorConfig, WasmMeteringConfig},
+        CellError, ConductorHandle,
+    },
+    core::{
+        ribosome::{error::RibosomeError, metering::ZomeFnMetrics},
+        state::source_chain::SourceChainBuf,
+        workflow::error::WorkflowError,
+    },
+    test_utils::{
+        install_app, new_invocation, setup_conductor_with_dna, shutdown_conductor,
+        single_zome_dna_file,
+    },
+};
+use holochain_types::{
+    app::InstalledCell, cell::CellId, dna::DnaFile, test_utils::fake_agent_pubkey_1,
+};
+use holochain_wasm_test_utils::TestWasm;
+use holochain_zome_types::zome::FunctionName;
+use matches::assert_matches;
+use std::sync::Arc;
+use tempdir::TempDir;
+use test_wasm_common::TestInt;
+
+async fn dna_file() -> DnaFile {
+    single_zome_dna_file(
+        "wasm_metering",
+        "0c6f3b8e-2d9a-4e51-a7c4-91b5e3f0d288",
+        TestWasm::Metering,
+    )
+    .await
+}
+
+/// A conductor with one cell running the Metering zome, compiled with metering
+async fn setup(
+    dna_file: &DnaFile,
+    fuel_limit: Option<u64>,
+) -> (Vec<Arc<TempDir>>, CellId, ConductorHandle) {
+    let cell_id = CellId::new(dna_file.dna_hash().to_owned(), fake_agent_pubkey_1());
+    let installed_cell = InstalledCell::new(cell_id.clone(), "handle".into());
+
+    let (tmpdirs, _, handle) = setup_conductor_with_dna(
+        dna_file,
+        ConductorConfig {
+            wasm_metering: WasmMeteringConfig {
+                enabled: true,
+                fuel_limit,
+            },
+            ..Default::default()
+        },
+    )
+    .await;
+    install_app("app", vec![(installed_cell, None)], handle.clone()).await;
+
+    (tmpdirs, cell_id, handle)
+}
+
+async fn call(
+    handle: &ConductorHandle,
+    cell_id: &CellId,
+    func: &str,
+    n: u32,
+) -> Result<(), ConductorApiError> {
+    let invocation = new_invocation(cell_id, func, TestInt(n), TestWasm::Metering).unwrap();
+    handle.call_zome(invocation).await?.unwrap();
+    Ok(())
+}
+
+async fn trivial(handle: &ConductorHandle, cell_id: &CellId) {
+    let invocation = new_invocation(cell_id, "trivial", (), TestWasm::Metering).unwrap();
+    handle.call_zome(invocation).await.unwrap().unwrap();
+}
+
+async fn fn_metrics(handle: &ConductorHandle, fn_name: &str) -> ZomeFnMetrics {
+    let fn_name = FunctionName::from(fn_name);
+    handle
+        .metrics()
+        .await
+        .unwrap()
+        .zome_fns
+        .into_iter()
+        .find(|m| m.fn_name == fn_name)
+        .unwrap()
+}
+
+async fn last_call_memory_bytes(handle: &ConductorHandle, cell_id: &CellId) -> u64 {
+    handle
+        .get_cell_status(cell_id)
+        .await
+        .unwrap()
+        .last_call_resources
+        .unwrap()
+        .memory_bytes
+}
+
+async fn chain_len(handle: &ConductorHandle, cell_id: &CellId) -> usize {
+    let env = handle.get_cell_env(cell_id).await.unwrap();
+    SourceChainBuf::new(env.into()).unwrap().len()
+}
+
+/// - A loop burns more fuel than a trivial call, and the calls are added up
+/// - A large allocation raises the memory high-water mark
+#[tokio::test(threaded_scheduler)]
+async fn zome_calls_are_metered() {
+    observability::test_run().ok();
+    let dna_file = dna_file().await;
+    let (_tmpdirs, cell_id, handle) = setup(&dna_file, None).await;
+
+    trivial(&handle, &cell_id).await;
+    trivial(&handle, &cell_id).await;
+    call(&handle, &cell_id, "spin", 100_000).await.unwrap();
+
+    let trivial_metrics = fn_metrics(&handle, "trivial").await;
+    let spin_metrics = fn_metrics(&handle, "spin").await;
+    assert_eq!(trivial_metrics.calls, 2);
+    assert_eq!(spin_metrics.calls, 1);
+    assert!(trivial_metrics.total_fuel > 0);
+    assert!(spin_metrics.total_fuel > trivial_metrics.total_fuel * 100);
+    assert_eq!(spin_metrics.dna_hash, *dna_file.dna_hash());
+
+    let small = last_call_memory_bytes(&handle, &cell_id).await;
+    call(&handle, &cell_id, "allocate", 16).await.unwrap();
+    let large = last_call_memory_bytes(&handle, &cell_id).await;
+    assert!(large >= small + 16 * 1024 * 1024);
+    assert_eq!(
+        fn_metrics(&handle, "allocate").await.peak_memory_bytes,
+        large
+    );
+
+    shutdown_conductor(handle).await;
+}
+
+/// - A call which burns more than the fuel limit fails typed
+///   and persists none of its commits
+/// - A call under the limit is unaffected
+#[tokio::test(threaded_scheduler)]
+async fn fuel_limit_aborts_call() {
+    observability::test_run().ok();
+    let dna_file = dna_file().await;
+
+    // Measure a short call without a limit
+    let (_tmpdirs, cell_id, handle) = setup(&dna_file, None).await;
+    call(&handle, &cell_id, "create_then_spin", 10)
+        .await
+        .unwrap();
+    let short = fn_metrics(&handle, "create_then_spin").await.total_fuel;
+    shutdown_conductor(handle).await;
+
+    let limit = short * 10;
+    let (_tmpdirs, cell_id, handle) = setup(&dna_file, Some(limit)).await;
+    // Make sure init has run so its commits don't count
+    trivial(&handle, &cell_id).await;
+    let len_before = chain_len(&handle, &cell_id).await;
+
+    match call(&handle, &cell_id, "create_then_spin", 10_000_000).await {
+        Err(ConductorApiError::CellError(CellError::WorkflowError(e))) => assert_matches!(
+            *e,
+            WorkflowError::RibosomeError(RibosomeError::FuelExhausted { limit: l, .. })
+                if l == limit
+        ),
+        r => panic!("Expected the call to exhaust its fuel but got {:?}", r),
+    }
+    assert_eq!(chain_len(&handle, &cell_id).await, len_before);
+
+    call(&handle, &cell_id, "create_then_spin", 10)
+        .await
+        .unwrap();
+    assert_eq!(chain_len(&handle, &cell_id).await, len_before + 1);
+    shutdown_conductor(handle).await;
+}
@@ -0,0 +1,130 @@
+use crate::{
+    conductor::{
+        api::error::ConductorApiError, config::ConductorConfig, CellError, ConductorHandle,
+    },
+    core::{
+        state::source_chain::{SourceChainBuf, SourceChainError},
+        workflow::error::WorkflowError,
+    },
+    test_utils::{
+        install_app, new_invocation, setup_conductor_with_dna, shutdown_conductor,
+        single_zome_dna_file,
+    },
+};
+use holochain_types::{
+    app::InstalledCell, cell::CellId, dna::DnaFile, test_utils::fake_agent_pubkey_1,
+};
+use holochain_wasm_test_utils::TestWasm;
+use matches::assert_matches;
+use std::sync::Arc;
+use tempdir::TempDir;
+use test_wasm_common::TestInt;
+
+async fn dna_file() -> DnaFile {
+    single_zome_dna_file(
+        "scratch_size_limit",
+        "5b1f0d3e-7c7a-4f0e-9d62-3a8e2c1b9f40",
+        TestWasm::MultipleCalls,
+    )
+    .await
+}
+
+/// A conductor with one cell running the MultipleCalls zome
+async fn setup(
+    dna_file: &DnaFile,
+    scratch_size_limit: Option<usize>,
+) -> (Vec<Arc<TempDir>>, CellId, ConductorHandle) {
+    let cell_id = CellId::new(dna_file.dna_hash().to_owned(), fake_agent_pubkey_1());
+    let installed_cell = InstalledCell::new(cell_id.clone(), "handle".into());
+
+    let (tmpdirs, _, handle) = setup_conductor_with_dna(
+        dna_file,
+        ConductorConfig {
+            scratch_size_limit,
+            ..Default::default()
+        },
+    )
+    .await;
+    install_app("app", vec![(installed_cell, None)], handle.clone()).await;
+
+    // Make sure init has run so its commits don't count towards any call's scratch
+    create_entries(&handle, &cell_id, 0).await.unwrap();
+
+    (tmpdirs, cell_id, handle)
+}
+
+async fn create_entries(
+    handle: &ConductorHandle,
+    cell_id: &CellId,
+    n: u32,
+) -> Result<(), ConductorApiError> {
+    let invocation = new_invocation(
+        cell_id,
+        "create_entry_multiple",
+        TestInt(n),
+        TestWasm::MultipleCalls,
+    )
+    .unwrap();
+    handle.call_zome(invocation).await?.unwrap();
+    Ok(())
+}
+
+async fn peak_scratch_size(handle: &ConductorHandle, cell_id: &CellId) -> usize {
+    handle
+        .get_cell_status(cell_id)
+        .await
+        .unwrap()
+        .last_call_peak_scratch_size
+}
+
+async fn chain_len(handle: &ConductorHandle, cell_id: &CellId) -> usize {
+    let env = handle.get_cell_env(cell_id).await.unwrap();
+    SourceChainBuf::new(env.into()).unwrap().len()
+}
+
+/// - A call under the default limit is unaffected and reports its peak
+/// - A call which commits past the limit fails at the expected count
+///   and persists none of its commits
+#[tokio::test(threaded_scheduler)]
+async fn scratch_size_limit() {
+    observability::test_run().ok();
+    let dna_file = dna_file().await;
+
+    // Measure the scratch each commit adds without a limit to speak of
+    let (_tmpdirs, cell_id, handle) = setup(&dna_file, None).await;
+    let len_before = chain_len(&handle, &cell_id).await;
+    create_entries(&handle, &cell_id, 1).await.unwrap();
+    let one = peak_scratch_size(&handle, &cell_id).await;
+    create_entries(&handle, &cell_id, 2).await.unwrap();
+    let two = peak_scratch_size(&handle, &cell_id).await;
+    assert!(one > 0);
+    assert!(two > one);
+    assert_eq!(chain_len(&handle, &cell_id).await, len_before + 3);
+    shutdown_conductor(handle).await;
+
+    // Allow five commits and half of a sixth
+    let per_commit = two - one;
+    let limit = one + 4 * per_commit + per_commit / 2;
+    let (_tmpdirs, cell_id, handle) = setup(&dna_file, Some(limit)).await;
+    let len_before = chain_len(&handle, &cell_id).await;
+
+    match create_entries(&handle, &cell_id, 6).await {
+        Err(ConductorApiError::CellError(CellError::WorkflowError(e))) => assert_matches!(
+            *e,
+            WorkflowError::SourceChainError(SourceChainError::ScratchSizeLimitExceeded {
+                used,
+                limit: l,
+            }) if used > limit && l == limit
+        ),
+        r => panic!(
+            "Expected the call to exceed the scratch size limit but got {:?}",
+            r
+        ),
+    }
+    assert_eq!(chain_len(&handle, &cell_id).await, len_before);
+
+    create_entries(&handle, &cell_id, 5).await.unwrap();
+    assert!(peak_scratch_size(&handle, &cell_id).await <= limit);
+    assert_eq!(chain_len(&handle, &cell_id).await, len_before + 5);
+    shutdown_conductor(handle).await;
+}
@@ -6,7 +6,9 @@ use super::{
     produce_dht_ops_workflow::dht_op_light::error::DhtOpConvertError,
 };
 use crate::{
-    conductor::{api::error::ConductorApiError, CellError},
+    conductor::{
+        api::error::ConductorApiError, entry_def_store::error::EntryDefStoreError, CellError,
+    },
     core::{
         queue_consumer::QueueTriggerClosedError,
         ribosome::error::RibosomeError,
@@ -16,9 +18,10 @@ use crate::{
         SysValidationError,
     },
 };
+use holochain_keystore::KeystoreError;
 use holochain_p2p::HolochainP2pError;
 use holochain_state::error::DatabaseError;
-use holochain_types::{dht_op::error::DhtOpError, prelude::*};
+use holochain_types::{cell::CellId, dht_op::error::DhtOpError, prelude::*};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -29,6 +32,9 @@ pub enum WorkflowError {
     #[error("Agent is invalid: {0:?}")]
     AgentInvalid(AgentPubKey),
 
+    #[error("The genesis elements failed their self-check: {reason}")]
+    GenesisSelfCheckFailed { reason: String },
+
     #[error("Conductor API error: {0}")]
     ConductorApi(#[from] Box<ConductorApiError>),
 
@@ -68,9 +74,79 @@ pub enum WorkflowError {
     #[error(transparent)]
     DhtOpError(#[from] DhtOpError),
 
+    #[error("Dna is missing for this cell {0:?}. Cannot produce ops without dna.")]
+    DnaMissing(CellId),
+
+    #[error(transparent)]
+    EntryDefStoreError(#[from] EntryDefStoreError),
+
     #[error(transparent)]
     SysValidationError(#[from] SysValidationError),
 }
 
+impl WorkflowError {
+    /// Whether the error is likely to clear up by itself, so the workflow is
+    /// worth running again after a backoff: the network or the keystore
+    /// being unavailable, or an LMDB map filling up while it grows.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            WorkflowError::HolochainP2pError(e) => network_is_transient(e),
+            WorkflowError::CascadeError(e) => cascade_is_transient(e),
+            WorkflowError::DatabaseError(e) => e.is_map_full(),
+            WorkflowError::WorkspaceError(e) => workspace_is_transient(e),
+            WorkflowError::SourceChainError(e) => source_chain_is_transient(e),
+            WorkflowError::SysValidationError(e) => match e {
+                SysValidationError::CascadeError(e) => cascade_is_transient(e),
+                SysValidationError::DatabaseError(e) => e.is_map_full(),
+                SysValidationError::KeystoreError(e) => keystore_is_unavailable(e),
+                SysValidationError::SourceChainError(e) => source_chain_is_transient(e),
+                SysValidationError::WorkflowError(e) => e.is_transient(),
+                SysValidationError::WorkspaceError(e) => workspace_is_transient(e),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+/// A peer sending us data we can't understand won't change on a retry
+fn network_is_transient(e: &HolochainP2pError) -> bool {
+    !matches!(
+        e,
+        HolochainP2pError::CorruptPayload { .. }
+            | HolochainP2pError::InvalidP2pMessage(_)
+            | HolochainP2pError::SerializedBytesError(_)
+    )
+}
+
+fn cascade_is_transient(e: &CascadeError) -> bool {
+    match e {
+        CascadeError::NetworkError(e) => network_is_transient(e),
+        CascadeError::DatabaseError(e) => e.is_map_full(),
+        CascadeError::SourceChainError(e) => source_chain_is_transient(e),
+        _ => false,
+    }
+}
+
+fn workspace_is_transient(e: &WorkspaceError) -> bool {
+    match e {
+        WorkspaceError::DatabaseError(e) => e.is_map_full(),
+        WorkspaceError::SourceChainError(e) => source_chain_is_transient(e),
+    }
+}
+
+fn source_chain_is_transient(e: &SourceChainError) -> bool {
+    match e {
+        SourceChainError::DatabaseError(e) => e.is_map_full(),
+        SourceChainError::KeystoreError(e) => keystore_is_unavailable(e),
+        _ => false,
+    }
+}
+
+/// The keystore actor couldn't be reached
+fn keystore_is_unavailable(e: &KeystoreError) -> bool {
+    matches!(e, KeystoreError::GhostError(_))
+}
+
 /// Internal type to handle running workflows
 pub type WorkflowResult<T> = Result<T, WorkflowError>;
@@ -3,31 +3,76 @@
 //! - AgentValidationPkg
 //! - AgentId
 //!
+//! Unless it is skipped, a self-check first builds the genesis elements in a
+//! scratch workspace which is never flushed, and runs sys validation and the
+//! Dna's validate callbacks on them. Genesis is only committed if they pass,
+//! so a rejected agent leaves the Cell's environment empty.
+//!
 
 // FIXME: understand the details of actually getting the DNA
 // FIXME: creating entries in the config db
 
-use super::error::{WorkflowError, WorkflowResult};
+use super::{
+    error::{WorkflowError, WorkflowResult},
+    sys_validation_workflow::sys_validate_element,
+    CallZomeWorkspace, CallZomeWorkspaceLock,
+};
 use crate::conductor::api::CellConductorApiT;
 use crate::core::{
     queue_consumer::OneshotWriter,
+    ribosome::{
+        guest_callback::validate::{ValidateHostAccess, ValidateInvocation, ValidateResult},
+        wasm_ribosome::WasmRibosome,
+        RibosomeT, ZomesToInvoke,
+    },
     state::{
         source_chain::SourceChainBuf,
         workspace::{Workspace, WorkspaceResult},
     },
+    sys_validate::ValidationOutcome,
 };
-use derive_more::Constructor;
+use holochain_p2p::HolochainP2pCell;
 use holochain_state::prelude::*;
 use holochain_types::dna::DnaFile;
 use holochain_types::prelude::*;
+use std::{convert::TryFrom, sync::Arc};
 use tracing::*;
 
+#[cfg(test)]
+#[cfg(feature = "slow_tests")]
+mod self_check_test;
+
 /// The struct which implements the genesis Workflow
-#[derive(Constructor, Debug)]
+#[derive(Debug)]
 pub struct GenesisWorkflowArgs {
     dna_file: DnaFile,
     agent_pubkey: AgentPubKey,
     membrane_proof: Option<SerializedBytes>,
+    /// The network to check the genesis elements with before committing them,
+    /// or None to skip the self-check
+    self_check: Option<HolochainP2pCell>,
+}
+
+impl GenesisWorkflowArgs {
+    /// Constructor, without the self-check
+    pub fn new(
+        dna_file: DnaFile,
+        agent_pubkey: AgentPubKey,
+        membrane_proof: Option<SerializedBytes>,
+    ) -> Self {
+        Self {
+            dna_file,
+            agent_pubkey,
+            membrane_proof,
+            self_check: None,
+        }
+    }
+
+    /// Validate the genesis elements before committing them
+    pub fn with_self_check(mut self, network: HolochainP2pCell) -> Self {
+        self.self_check = Some(network);
+        self
+    }
 }
 
 #[instrument(skip(workspace, writer, api))]
@@ -37,6 +82,11 @@ pub async fn genesis_workflow<'env, Api: CellConductorApiT>(
     api: Api,
     args: GenesisWorkflowArgs,
 ) -> WorkflowResult<()> {
+    if let Some(network) = args.self_check.clone() {
+        let env = workspace.source_chain.env().clone();
+        self_check(&args, env, network, &api).await?;
+    }
+
     genesis_workflow_inner(&mut workspace, args, api).await?;
 
     // --- END OF WORKFLOW, BEGIN FINISHER BOILERPLATE ---
@@ -56,6 +106,7 @@ async fn genesis_workflow_inner<Api: CellConductorApiT>(
         dna_file,
         agent_pubkey,
         membrane_proof,
+        self_check: _,
     } = args;
 
     // TODO: this is a placeholder for a real DPKI request to show intent
@@ -81,6 +132,64 @@ async fn genesis_workflow_inner<Api: CellConductorApiT>(
     Ok(())
 }
 
+/// Build the genesis elements in a scratch workspace which is never flushed,
+/// and check that sys validation and every zome's validate callbacks accept them
+async fn self_check<Api: CellConductorApiT>(
+    args: &GenesisWorkflowArgs,
+    env: EnvironmentRead,
+    network: HolochainP2pCell,
+    api: &Api,
+) -> WorkflowResult<()> {
+    let mut workspace = CallZomeWorkspace::new(env)?;
+    workspace
+        .source_chain
+        .genesis(
+            args.dna_file.dna_hash().clone(),
+            args.agent_pubkey.clone(),
+            args.membrane_proof.clone(),
+        )
+        .await?;
+    let len = workspace.source_chain.len() as u32;
+    let elements = workspace.source_chain.get_chain_section(0, len)?;
+
+    for element in &elements {
+        if let Err(outcome_or_err) =
+            sys_validate_element(element, &mut workspace, network.clone(), api).await
+        {
+            return Err(WorkflowError::GenesisSelfCheckFailed {
+                reason: ValidationOutcome::try_from(outcome_or_err)?.to_string(),
+            });
+        }
+    }
+
+    let ribosome =
+        WasmRibosome::new(args.dna_file.clone()).with_module_cache(api.wasm_module_cache().clone());
+    let workspace_lock = CallZomeWorkspaceLock::new(workspace);
+    for element in elements {
+        let result = ribosome.run_validate(
+            ValidateHostAccess::new(workspace_lock.clone(), network.clone()),
+            ValidateInvocation {
+                zomes_to_invoke: ZomesToInvoke::All,
+                element: Arc::new(element),
+                validation_package: None,
+                entry_def_id: None,
+            },
+        )?;
+        match result {
+            ValidateResult::Valid => (),
+            ValidateResult::Invalid(reason) => {
+                return Err(WorkflowError::GenesisSelfCheckFailed { reason })
+            }
+            ValidateResult::UnresolvedDependencies(deps) => {
+                return Err(WorkflowError::GenesisSelfCheckFailed {
+                    reason: format!("Genesis depends on data which can't be found: {:?}", deps),
+                })
+            }
+        }
+    }
+    Ok(())
+}
+
 /// The workspace for Genesis
 pub struct GenesisWorkspace {
     source_chain: SourceChainBuf,
@@ -145,6 +254,7 @@ pub mod tests {
                 dna_file: dna.clone(),
                 agent_pubkey: agent_pubkey.clone(),
                 membrane_proof: None,
+                self_check: None,
             };
             let _: () = genesis_workflow(workspace, arc.clone().into(), api, args).await?;
         }
@@ -165,7 +275,11 @@ pub mod tests {
 
             assert_matches!(
                 headers.as_slice(),
-                [Header::Create(_), Header::AgentValidationPkg(_), Header::Dna(_)]
+                [
+                    Header::Create(_),
+                    Header::AgentValidationPkg(_),
+                    Header::Dna(_)
+                ]
             );
         }
 
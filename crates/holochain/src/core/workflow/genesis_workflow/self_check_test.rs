@@ -0,0 +1,115 @@
+use crate::{
+    conductor::{config::ConductorConfig, error::ConductorError, ConductorHandle},
+    core::state::source_chain::SourceChainBuf,
+    test_utils::{install_app, setup_conductor_with_dna, shutdown_conductor, single_zome_dna_file},
+};
+use holochain_serialized_bytes::SerializedBytes;
+use holochain_state::env::{EnvironmentKind, EnvironmentWrite};
+use holochain_types::{
+    app::InstalledCell, cell::CellId, dna::DnaFile, test_utils::fake_agent_pubkey_1,
+};
+use holochain_wasm_test_utils::TestWasm;
+use matches::assert_matches;
+use std::{
+    convert::TryFrom,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tempdir::TempDir;
+use test_wasm_common::TestString;
+
+async fn dna_file() -> DnaFile {
+    single_zome_dna_file(
+        "genesis_self_check",
+        "0c6f3e52-1d4b-4a8e-b2f7-9e5a7d3c8b14",
+        TestWasm::GenesisSelfCheck,
+    )
+    .await
+}
+
+fn proof(password: &str) -> Option<SerializedBytes> {
+    Some(SerializedBytes::try_from(TestString(password.to_string())).unwrap())
+}
+
+/// A conductor with the GenesisSelfCheck dna in its store
+/// and nothing installed yet
+async fn setup(
+    dna_file: &DnaFile,
+    skip_genesis_self_check: bool,
+) -> (Vec<Arc<TempDir>>, PathBuf, ConductorHandle) {
+    setup_conductor_with_dna(
+        dna_file,
+        ConductorConfig {
+            skip_genesis_self_check,
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+/// The length of a cell's chain, read straight from its environment
+/// so that it works for cells which aren't running
+fn chain_len(root_env_dir: &Path, handle: &ConductorHandle, cell_id: &CellId) -> usize {
+    let env = EnvironmentWrite::new(
+        root_env_dir,
+        EnvironmentKind::Cell(cell_id.clone()),
+        handle.keystore().clone(),
+    )
+    .unwrap();
+    SourceChainBuf::new(env.into()).unwrap().len()
+}
+
+/// - A membrane proof the dna rejects fails the install with a typed error
+///   and leaves the cell's chain empty
+/// - Retrying the same cell with an accepted proof succeeds
+#[tokio::test(threaded_scheduler)]
+async fn rejected_genesis_commits_nothing() {
+    observability::test_run().ok();
+    let dna_file = dna_file().await;
+    let cell_id = CellId::new(dna_file.dna_hash().to_owned(), fake_agent_pubkey_1());
+    let installed_cell = InstalledCell::new(cell_id.clone(), "handle".into());
+    let (_tmpdirs, root_env_dir, handle) = setup(&dna_file, false).await;
+
+    let result = handle
+        .clone()
+        .install_app(
+            "bad proof".to_string(),
+            vec![(installed_cell.clone(), proof("let me out"))],
+            false,
+        )
+        .await;
+    assert_matches!(
+        result,
+        Err(ConductorError::GenesisSelfCheckFailed { cell_id: id, reason })
+            if id == cell_id && reason == "wrong membrane proof"
+    );
+    assert_eq!(chain_len(&root_env_dir, &handle, &cell_id), 0);
+
+    install_app(
+        "good proof",
+        vec![(installed_cell, proof("let me in"))],
+        handle.clone(),
+    )
+    .await;
+    assert_eq!(chain_len(&root_env_dir, &handle, &cell_id), 3);
+    shutdown_conductor(handle).await;
+}
+
+/// Skipping the self-check commits genesis without running the callback
+#[tokio::test(threaded_scheduler)]
+async fn skip_genesis_self_check() {
+    observability::test_run().ok();
+    let dna_file = dna_file().await;
+    let cell_id = CellId::new(dna_file.dna_hash().to_owned(), fake_agent_pubkey_1());
+    let installed_cell = InstalledCell::new(cell_id.clone(), "handle".into());
+    let (_tmpdirs, root_env_dir, handle) = setup(&dna_file, true).await;
+
+    install_app(
+        "bad proof",
+        vec![(installed_cell, proof("let me out"))],
+        handle.clone(),
+    )
+    .await;
+    assert_eq!(chain_len(&root_env_dir, &handle, &cell_id), 3);
+    shutdown_conductor(handle).await;
+}
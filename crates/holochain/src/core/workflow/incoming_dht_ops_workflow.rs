@@ -25,7 +25,11 @@ use holochain_state::{
     error::DatabaseResult,
     prelude::{EnvironmentRead, GetDb, PendingPrefix, Writer},
 };
-use holochain_types::{dht_op::DhtOp, Timestamp};
+use holochain_types::{
+    dht_op::{DhtOp, OpRequirements},
+    Timestamp,
+};
+use std::collections::HashMap;
 use tracing::instrument;
 
 #[cfg(test)]
@@ -33,19 +37,34 @@ mod test;
 
 #[instrument(skip(state_env, sys_validation_trigger, ops))]
 pub async fn incoming_dht_ops_workflow(
+    state_env: &EnvironmentWrite,
+    sys_validation_trigger: TriggerSender,
+    ops: Vec<(holo_hash::DhtOpHash, holochain_types::dht_op::DhtOp)>,
+) -> WorkflowResult<()> {
+    incoming_dht_ops_workflow_with_requirements(state_env, sys_validation_trigger, ops, Vec::new())
+        .await
+}
+
+/// Add incoming ops to the validation limbo along with the requirements
+/// their author claimed for them, which sys validation checks
+#[instrument(skip(state_env, sys_validation_trigger, ops, requirements))]
+pub async fn incoming_dht_ops_workflow_with_requirements(
     state_env: &EnvironmentWrite,
     mut sys_validation_trigger: TriggerSender,
     ops: Vec<(holo_hash::DhtOpHash, holochain_types::dht_op::DhtOp)>,
+    requirements: Vec<(holo_hash::DhtOpHash, OpRequirements)>,
 ) -> WorkflowResult<()> {
     // set up our workspace
     let mut workspace = IncomingDhtOpsWorkspace::new(state_env.clone().into())?;
+    let mut requirements = requirements.into_iter().collect::<HashMap<_, _>>();
 
     // add incoming ops to the validation limbo
     for (hash, op) in ops {
         if !workspace.op_exists(&hash)? {
             tracing::debug!(?hash, ?op);
             if should_keep(&op).await? {
-                workspace.add_to_pending(hash, op).await?;
+                let requirements = requirements.remove(&hash);
+                workspace.add_to_pending(hash, op, requirements).await?;
             } else {
                 tracing::warn!(
                     msg = "Dropping op because it failed counterfeit checks",
@@ -113,7 +132,12 @@ impl IncomingDhtOpsWorkspace {
         })
     }
 
-    async fn add_to_pending(&mut self, hash: DhtOpHash, op: DhtOp) -> DhtOpConvertResult<()> {
+    async fn add_to_pending(
+        &mut self,
+        hash: DhtOpHash,
+        op: DhtOp,
+        requirements: Option<OpRequirements>,
+    ) -> DhtOpConvertResult<()> {
         let basis = op.dht_basis().await;
         let op_light = op.to_light().await;
         tracing::debug!(?op_light);
@@ -131,6 +155,8 @@ impl IncomingDhtOpsWorkspace {
             time_added: Timestamp::now(),
             last_try: None,
             num_tries: 0,
+            annotations: Vec::new(),
+            requirements,
         };
         self.validation_limbo.put(hash, vlv)?;
         Ok(())
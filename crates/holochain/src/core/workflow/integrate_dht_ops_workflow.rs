@@ -8,7 +8,7 @@ use crate::core::{
         cascade::Cascade,
         cascade::DbPair,
         dht_op_integration::{
-            IntegratedDhtOpsStore, IntegratedDhtOpsValue, IntegrationLimboStore,
+            IntegratedDhtOpsBuf, IntegratedDhtOpsValue, IntegrationLimboStore,
             IntegrationLimboValue,
         },
         element_buf::ElementBuf,
@@ -23,12 +23,8 @@ use error::WorkflowResult;
 use fallible_iterator::FallibleIterator;
 use holo_hash::{DhtOpHash, EntryHash, HeaderHash};
 use holochain_state::{
-    buffer::BufferedStore,
-    buffer::KvBufFresh,
-    db::{INTEGRATED_DHT_OPS, INTEGRATION_LIMBO},
-    error::DatabaseResult,
-    fresh_reader,
-    prelude::*,
+    buffer::BufferedStore, buffer::KvBufFresh, db::INTEGRATION_LIMBO, error::DatabaseResult,
+    fresh_reader, prelude::*,
 };
 use holochain_types::{
     dht_op::{produce_op_lights_from_elements, DhtOp, DhtOpLight, UniqueForm},
@@ -36,7 +32,9 @@ use holochain_types::{
     validate::ValidationStatus,
     Entry, EntryHashed, Timestamp,
 };
-use holochain_zome_types::{element::ElementEntry, signature::Signature};
+use holochain_zome_types::{
+    element::ElementEntry, entry_def::EntryVisibility, signature::Signature,
+};
 use holochain_zome_types::{element::SignedHeader, Header};
 use produce_dht_ops_workflow::dht_op_light::{
     error::{DhtOpConvertError, DhtOpConvertResult},
@@ -56,8 +54,9 @@ pub async fn integrate_dht_ops_workflow(
     writer: OneshotWriter,
     trigger_sys: &mut TriggerSender,
 ) -> WorkflowResult<WorkComplete> {
-    // one of many possible ways to access the env
-    let env = workspace.elements.headers().env().clone();
+    // the limbo is always in the Cell's own env, even if the
+    // integrated data is in a shared DHT env
+    let env = workspace.integration_limbo.env().clone();
     // Pull ops out of queue
     // TODO: PERF: Combine this collect with the sort when ElementBuf gets
     // aren't async
@@ -428,6 +427,17 @@ fn put_data<P: PrefixType>(
     maybe_entry: Option<Entry>,
     element_store: &mut ElementBuf<P>,
 ) -> DhtOpConvertResult<()> {
+    // Private entries are never published, so one arriving here means
+    // it has leaked out of its author's source chain
+    debug_assert!(
+        maybe_entry.is_none()
+            || !matches!(
+                header.entry_data().map(|(_, et)| et.visibility()),
+                Some(EntryVisibility::Private)
+            ),
+        "A private entry is about to be integrated: {:?}",
+        header
+    );
     let signed_header = SignedHeaderHashed::from_content_sync(SignedHeader(header, signature));
     let maybe_entry_hashed = match maybe_entry {
         Some(entry) => Some(EntryHashed::from_content_sync(entry)),
@@ -476,7 +486,7 @@ pub struct IntegrateDhtOpsWorkspace {
     /// integration queue
     pub integration_limbo: IntegrationLimboStore,
     /// integrated ops
-    pub integrated_dht_ops: IntegratedDhtOpsStore,
+    pub integrated_dht_ops: IntegratedDhtOpsBuf,
     /// Cas for storing
     pub elements: ElementBuf,
     /// metadata store
@@ -516,8 +526,7 @@ impl Workspace for IntegrateDhtOpsWorkspace {
 impl IntegrateDhtOpsWorkspace {
     /// Constructor
     pub fn new(env: EnvironmentRead) -> WorkspaceResult<Self> {
-        let db = env.get_db(&*INTEGRATED_DHT_OPS)?;
-        let integrated_dht_ops = KvBufFresh::new(env.clone(), db);
+        let integrated_dht_ops = IntegratedDhtOpsBuf::new(env.clone())?;
 
         let db = env.get_db(&*INTEGRATION_LIMBO)?;
         let integration_limbo = KvBufFresh::new(env.clone(), db);
@@ -1,5 +1,6 @@
 use super::*;
 use holo_hash::EntryHash;
+use holochain_types::header::NewEntryHeader;
 
 pub fn disintegrate_single_metadata<C, P>(
     op: DhtOpLight,
@@ -45,6 +46,33 @@ where
     Ok(())
 }
 
+/// Undo [integrate_to_authored] for an element which is taken off the
+/// chain before it was flushed
+pub fn disintegrate_from_authored<C: MetadataBufT<AuthoredPrefix>>(
+    element: &Element,
+    meta_store: &mut C,
+) -> DatabaseResult<()> {
+    let header = element.header();
+    meta_store.deregister_element_header(element.header_address().clone())?;
+    meta_store.deregister_activity(header)?;
+    match header.clone() {
+        Header::Create(create) => meta_store.deregister_header(NewEntryHeader::Create(create))?,
+        Header::Update(update) => {
+            meta_store.deregister_header(NewEntryHeader::Update(update.clone()))?;
+            meta_store.deregister_update(update)?;
+        }
+        Header::Delete(delete) => meta_store.deregister_delete(delete)?,
+        Header::CreateLink(link_add) => meta_store.deregister_add_link(link_add)?,
+        Header::DeleteLink(link_remove) => meta_store.deregister_delete_link(link_remove)?,
+        Header::Dna(_)
+        | Header::AgentValidationPkg(_)
+        | Header::InitZomesComplete(_)
+        | Header::OpenChain(_)
+        | Header::CloseChain(_) => (),
+    }
+    Ok(())
+}
+
 #[tracing::instrument(skip(op, element_store))]
 /// Store a DhtOp's data in an element buf without dependency checks
 pub fn disintegrate_single_data<P: PrefixType>(op: DhtOpLight, element_store: &mut ElementBuf<P>) {
@@ -40,7 +40,9 @@ use holochain_zome_types::{
     zome::ZomeName,
     CreateInput, CreateLinkInput, GetInput, GetLinksInput, Header,
 };
-use produce_dht_ops_workflow::{produce_dht_ops_workflow, ProduceDhtOpsWorkspace};
+use produce_dht_ops_workflow::{
+    conductor_api_without_zomes, produce_dht_ops_workflow, ProduceDhtOpsWorkspace,
+};
 use std::{
     collections::BTreeMap,
     convert::{TryFrom, TryInto},
@@ -842,9 +844,14 @@ async fn test_ops_state() {
 async fn produce_dht_ops<'env>(env: EnvironmentWrite) {
     let (mut qt, _rx) = TriggerSender::new();
     let workspace = ProduceDhtOpsWorkspace::new(env.clone().into()).unwrap();
-    produce_dht_ops_workflow(workspace, env.clone().into(), &mut qt)
-        .await
-        .unwrap();
+    produce_dht_ops_workflow(
+        workspace,
+        env.clone().into(),
+        &mut qt,
+        &conductor_api_without_zomes(),
+    )
+    .await
+    .unwrap();
 }
 
 /// Run genesis on the source chain
@@ -915,7 +922,7 @@ async fn commit_entry<'env>(
         .next()
         .unwrap();
 
-    let input = CreateInput::new((entry_def_id.clone(), entry.clone()));
+    let input = CreateInput::new((entry_def_id.clone(), entry.clone(), None));
 
     let output = {
         let mut host_access = fixt!(ZomeCallHostAccess);
@@ -936,7 +943,7 @@ async fn commit_entry<'env>(
 
     let entry_hash = holochain_types::entry::EntryHashed::from_content_sync(entry).into_hash();
 
-    (entry_hash, output.into_inner().try_into().unwrap())
+    (entry_hash, output.into_inner().unwrap())
 }
 
 async fn get_entry(env: EnvironmentWrite, entry_hash: EntryHash) -> Option<Entry> {
@@ -992,7 +999,7 @@ async fn create_link(
     call_context.zome_name = zome_name.clone();
 
     // Call create_link
-    let input = CreateLinkInput::new((base_address.into(), target_address.into(), link_tag));
+    let input = CreateLinkInput::new((base_address.into(), target_address.into(), link_tag, None));
 
     let output = {
         let mut host_access = fixt!(ZomeCallHostAccess);
@@ -1013,7 +1020,7 @@ async fn create_link(
     }
 
     // Get the CreateLink HeaderHash back
-    output.into_inner()
+    output.into_inner().unwrap()
 }
 
 async fn get_links(
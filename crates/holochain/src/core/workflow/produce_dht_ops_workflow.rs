@@ -1,4 +1,5 @@
-use super::error::WorkflowResult;
+use super::error::{WorkflowError, WorkflowResult};
+use crate::conductor::{api::CellConductorApiT, entry_def_store::get_entry_def_from_ids};
 use crate::core::queue_consumer::{OneshotWriter, TriggerSender, WorkComplete};
 use crate::core::state::{
     dht_op_integration::{AuthoredDhtOpsStore, AuthoredDhtOpsValue},
@@ -10,18 +11,19 @@ use holochain_state::{
     db::AUTHORED_DHT_OPS,
     prelude::{BufferedStore, EnvironmentRead, GetDb, Writer},
 };
-use holochain_types::dht_op::DhtOpHashed;
+use holochain_types::dht_op::{DhtOp, DhtOpHashed, OpRequirements};
 use tracing::*;
 
 pub mod dht_op_light;
 
-#[instrument(skip(workspace, writer, trigger_publish))]
+#[instrument(skip(workspace, writer, trigger_publish, conductor_api))]
 pub async fn produce_dht_ops_workflow(
     mut workspace: ProduceDhtOpsWorkspace,
     writer: OneshotWriter,
     trigger_publish: &mut TriggerSender,
+    conductor_api: &impl CellConductorApiT,
 ) -> WorkflowResult<WorkComplete> {
-    let complete = produce_dht_ops_workflow_inner(&mut workspace).await?;
+    let complete = produce_dht_ops_workflow_inner(&mut workspace, conductor_api).await?;
 
     // --- END OF WORKFLOW, BEGIN FINISHER BOILERPLATE ---
 
@@ -36,18 +38,21 @@ pub async fn produce_dht_ops_workflow(
 
 async fn produce_dht_ops_workflow_inner(
     workspace: &mut ProduceDhtOpsWorkspace,
+    conductor_api: &impl CellConductorApiT,
 ) -> WorkflowResult<WorkComplete> {
     debug!("Starting dht op workflow");
     let all_ops = workspace.source_chain.get_incomplete_dht_ops().await?;
 
     for (index, ops) in all_ops {
         for op in ops {
+            let requirements = op_requirements(&op, conductor_api).await?;
             let (op, hash) = DhtOpHashed::from_content_sync(op).into_inner();
-            debug!(?hash, ?op);
+            debug!(?hash, ?op, ?requirements);
             let value = AuthoredDhtOpsValue {
                 op: op.to_light().await,
                 receipt_count: 0,
                 last_publish_time: None,
+                requirements,
             };
             workspace.authored_dht_ops.put(hash, value)?;
         }
@@ -58,6 +63,41 @@ async fn produce_dht_ops_workflow_inner(
     Ok(WorkComplete::Complete)
 }
 
+/// The requirements of the entry def of the op's app entry,
+/// if the op is one they apply to
+async fn op_requirements(
+    op: &DhtOp,
+    conductor_api: &impl CellConductorApiT,
+) -> WorkflowResult<Option<OpRequirements>> {
+    let app_entry_type = match op.requirements_entry_type() {
+        Some(app_entry_type) => app_entry_type,
+        None => return Ok(None),
+    };
+    let dna_file = { conductor_api.get_this_dna().await };
+    let dna_file =
+        dna_file.ok_or_else(|| WorkflowError::DnaMissing(conductor_api.cell_id().clone()))?;
+    let entry_def = get_entry_def_from_ids(
+        app_entry_type.zome_id(),
+        app_entry_type.id(),
+        &dna_file,
+        conductor_api,
+    )
+    .await?;
+    Ok(entry_def.as_ref().map(OpRequirements::from))
+}
+
+/// A conductor api whose Dna has no zomes,
+/// so none of the ops produced have entry def requirements
+#[cfg(test)]
+pub fn conductor_api_without_zomes() -> crate::conductor::api::MockCellConductorApi {
+    let dna_file = holochain_types::test_utils::fake_dna_zomes("without zomes", vec![]);
+    let mut conductor_api = crate::conductor::api::MockCellConductorApi::new();
+    conductor_api
+        .expect_sync_get_this_dna()
+        .return_const(Some(dna_file));
+    conductor_api
+}
+
 pub struct ProduceDhtOpsWorkspace {
     pub source_chain: SourceChain,
     pub authored_dht_ops: AuthoredDhtOpsStore,
@@ -210,9 +250,10 @@ mod tests {
         // Run the workflow and commit it
         {
             let mut workspace = ProduceDhtOpsWorkspace::new(env.clone().into()).unwrap();
-            let complete = produce_dht_ops_workflow_inner(&mut workspace)
-                .await
-                .unwrap();
+            let complete =
+                produce_dht_ops_workflow_inner(&mut workspace, &conductor_api_without_zomes())
+                    .await
+                    .unwrap();
             assert_matches!(complete, WorkComplete::Complete);
             env_ref
                 .with_commit(|writer| workspace.flush_to_txn(writer))
@@ -254,9 +295,10 @@ mod tests {
         // because no new ops should hav been added
         {
             let mut workspace = ProduceDhtOpsWorkspace::new(env.clone().into()).unwrap();
-            let complete = produce_dht_ops_workflow_inner(&mut workspace)
-                .await
-                .unwrap();
+            let complete =
+                produce_dht_ops_workflow_inner(&mut workspace, &conductor_api_without_zomes())
+                    .await
+                    .unwrap();
             assert_matches!(complete, WorkComplete::Complete);
             env_ref
                 .with_commit(|writer| workspace.flush_to_txn(writer))
@@ -15,9 +15,10 @@ use super::{
     produce_dht_ops_workflow::dht_op_light::{error::DhtOpConvertError, light_to_op},
 };
 use crate::core::{
+    gossip_metrics::GossipMeter,
     queue_consumer::{OneshotWriter, WorkComplete},
     state::{
-        dht_op_integration::AuthoredDhtOpsStore,
+        dht_op_integration::{AuthoredDhtOpsStore, AuthoredDhtOpsValue},
         element_buf::ElementBuf,
         workspace::{Workspace, WorkspaceResult},
     },
@@ -33,15 +34,17 @@ use holochain_state::{
     prelude::*,
     transaction::Writer,
 };
-use holochain_types::{dht_op::DhtOp, Timestamp};
+use holochain_types::{
+    dht_op::{DhtOp, OpRequirements},
+    Timestamp,
+};
 use std::collections::HashMap;
 use std::time;
 use tracing::*;
 
-/// Default redundancy factor for validation receipts
-// TODO: Pull this from the wasm entry def and only use this if it's missing
+/// Default redundancy factor for validation receipts,
+/// for ops which have no entry def requirements
 // TODO: Put a default in the DnaBundle
-// TODO: build zome_types/entry_def map to get the (AppEntryType map to entry def)
 pub const DEFAULT_RECEIPT_BUNDLE_SIZE: u32 = 5;
 
 /// Don't publish a DhtOp more than once during this interval.
@@ -57,17 +60,31 @@ pub struct PublishDhtOpsWorkspace {
     elements: ElementBuf<AuthoredPrefix>,
 }
 
-#[instrument(skip(workspace, writer, network))]
+#[instrument(skip(workspace, writer, network, gossip_meter))]
 pub async fn publish_dht_ops_workflow(
     mut workspace: PublishDhtOpsWorkspace,
     writer: OneshotWriter,
     network: &mut HolochainP2pCell,
+    gossip_meter: &GossipMeter,
 ) -> WorkflowResult<WorkComplete> {
     let to_publish = publish_dht_ops_workflow_inner(&mut workspace).await?;
 
     // Commit to the network
     for (basis, ops) in to_publish {
-        network.publish(true, basis, ops, None).await?;
+        let requirements = ops
+            .iter()
+            .filter_map(|(op_hash, _, requirements)| {
+                requirements.map(|requirements| (op_hash.clone(), requirements))
+            })
+            .collect();
+        let ops = ops
+            .into_iter()
+            .map(|(op_hash, op, _)| (op_hash, op))
+            .collect::<Vec<_>>();
+        network
+            .publish(true, basis, ops.clone(), requirements, None)
+            .await?;
+        gossip_meter.record_sent(ops.iter().map(|(_, op)| op));
     }
     // --- END OF WORKFLOW, BEGIN FINISHER BOILERPLATE ---
 
@@ -77,10 +94,23 @@ pub async fn publish_dht_ops_workflow(
     Ok(WorkComplete::Complete)
 }
 
-/// Read the authored for ops with receipt count < R
+/// How many validation receipts an op is published until it has:
+/// the count its entry def requires, or the default if it has no requirements.
+/// Every op is published at least once, even if its def requires no receipts.
+pub fn required_receipt_count(value: &AuthoredDhtOpsValue) -> u32 {
+    value
+        .requirements
+        .map(|requirements| u8::from(requirements.required_validations) as u32)
+        .unwrap_or(DEFAULT_RECEIPT_BUNDLE_SIZE)
+        .max(1)
+}
+
+/// Read the authored for ops with receipt count < R,
+/// along with the requirements their author claims for them
+#[allow(clippy::type_complexity)]
 pub async fn publish_dht_ops_workflow_inner(
     workspace: &mut PublishDhtOpsWorkspace,
-) -> WorkflowResult<HashMap<AnyDhtHash, Vec<(DhtOpHash, DhtOp)>>> {
+) -> WorkflowResult<HashMap<AnyDhtHash, Vec<(DhtOpHash, DhtOp, Option<OpRequirements>)>>> {
     // TODO: PERF: We need to check all ops every time this runs
     // instead we could have a queue of ops where count < R and a kv for count > R.
     // Then if the count for an ops reduces below R move it to the queue.
@@ -97,7 +127,7 @@ pub async fn publish_dht_ops_workflow_inner(
         .authored()
         .iter(&r)?
         .filter_map(|(k, mut r)| {
-            Ok(if r.receipt_count < DEFAULT_RECEIPT_BUNDLE_SIZE {
+            Ok(if r.receipt_count < required_receipt_count(&r) {
                 let needs_publish = r
                     .last_publish_time
                     .map(|last| {
@@ -123,6 +153,7 @@ pub async fn publish_dht_ops_workflow_inner(
     for (op_hash, value) in values {
         // Insert updated values into database for items about to be published
         let op = value.op.clone();
+        let requirements = value.requirements;
         workspace.authored().put(op_hash.clone(), value)?;
 
         let op = match light_to_op(op, workspace.elements()) {
@@ -135,7 +166,7 @@ pub async fn publish_dht_ops_workflow_inner(
         to_publish
             .entry(op.dht_basis().await)
             .or_insert_with(Vec::new)
-            .push((op_hash, op));
+            .push((op_hash, op, requirements));
     }
 
     Ok(to_publish)
@@ -173,16 +204,20 @@ impl PublishDhtOpsWorkspace {
 mod tests {
     use super::*;
     use crate::{
+        conductor::api::MockCellConductorApi,
         core::{
             queue_consumer::TriggerSender,
             state::{dht_op_integration::AuthoredDhtOpsValue, source_chain::SourceChain},
             workflow::{
+                app_validation_workflow::op_validation_package,
                 fake_genesis,
-                produce_dht_ops_workflow::{produce_dht_ops_workflow, ProduceDhtOpsWorkspace},
+                produce_dht_ops_workflow::{
+                    conductor_api_without_zomes, produce_dht_ops_workflow, ProduceDhtOpsWorkspace,
+                },
             },
             SourceChainError,
         },
-        fixt::{CreateLinkFixturator, EntryFixturator},
+        fixt::{CreateLinkFixturator, EntryDefFixturator, EntryFixturator},
     };
     use ::fixt::prelude::*;
     use futures::future::FutureExt;
@@ -201,12 +236,17 @@ mod tests {
     use holochain_types::{
         dht_op::{DhtOp, DhtOpHashed, DhtOpLight},
         fixt::{AppEntryTypeFixturator, SignatureFixturator},
-        observability, HeaderHashed,
+        observability,
+        test_utils::{fake_agent_pubkey_1, fake_dna_zomes},
+        validate::ValidationPackageResponse,
+        HeaderHashed,
     };
+    use holochain_wasm_test_utils::TestWasm;
     use holochain_zome_types::entry_def::EntryVisibility;
     use holochain_zome_types::{
         element::SignedHeaderHashed,
-        header::{builder, EntryType, Update},
+        header::{builder, AppEntryType, EntryType, Update},
+        validate::RequiredValidationType,
     };
     use matches::assert_matches;
     use std::{
@@ -290,7 +330,7 @@ mod tests {
             .collect::<Vec<_>>();
 
         // Create the network
-        let (network, mut recv) = spawn_holochain_p2p().await.unwrap();
+        let (network, mut recv) = spawn_holochain_p2p(Default::default()).await.unwrap();
         let (tx_complete, rx_complete) = tokio::sync::oneshot::channel();
         let cell_network = network.to_cell(dna.clone(), agents[0].clone());
         let mut recv_count: u32 = 0;
@@ -335,9 +375,14 @@ mod tests {
     /// Call the workflow
     async fn call_workflow(env: EnvironmentWrite, mut cell_network: HolochainP2pCell) {
         let workspace = PublishDhtOpsWorkspace::new(env.clone().into()).unwrap();
-        publish_dht_ops_workflow(workspace, env.clone().into(), &mut cell_network)
-            .await
-            .unwrap();
+        publish_dht_ops_workflow(
+            workspace,
+            env.clone().into(),
+            &mut cell_network,
+            &GossipMeter::default(),
+        )
+        .await
+        .unwrap();
     }
 
     /// There is a test that shows that network messages would be sent to all agents via broadcast.
@@ -393,6 +438,258 @@ mod tests {
         });
     }
 
+    /// Ops which share a basis are published together in one message,
+    /// instead of one message per op
+    #[tokio::test(threaded_scheduler)]
+    async fn ops_on_the_same_basis_are_published_together() {
+        let test_env = test_cell_env();
+        let env = test_env.env();
+
+        // Three links from the same base
+        let base_address = fixt!(EntryHash);
+        let mut link_add_fixt = CreateLinkFixturator::new(Unpredictable);
+        let mut workspace = PublishDhtOpsWorkspace::new(env.clone().into()).unwrap();
+        for _ in 0..3 {
+            let sig = fixt!(Signature);
+            let mut link_add = link_add_fixt.next().unwrap();
+            link_add.base_address = base_address.clone();
+            let op = DhtOp::RegisterAddLink(sig.clone(), link_add.clone());
+            let op_hash = DhtOpHashed::from_content_sync(op).into_hash();
+            let header_hash = HeaderHashed::from_content_sync(link_add.into());
+            let op_light = DhtOpLight::RegisterAddLink(
+                header_hash.as_hash().clone(),
+                base_address.clone().into(),
+            );
+            workspace
+                .authored_dht_ops
+                .put(op_hash, AuthoredDhtOpsValue::from_light(op_light))
+                .unwrap();
+            let signed_header = SignedHeaderHashed::with_presigned(header_hash, sig);
+            workspace.elements.put(signed_header, None).unwrap();
+        }
+        env.guard()
+            .with_commit::<DatabaseError, _, _>(|writer| {
+                workspace.authored_dht_ops.flush_to_txn_ref(writer)?;
+                workspace.elements.flush_to_txn_ref(writer)?;
+                Ok(())
+            })
+            .unwrap();
+
+        let mut workspace = PublishDhtOpsWorkspace::new(env.clone().into()).unwrap();
+        let to_publish = publish_dht_ops_workflow_inner(&mut workspace)
+            .await
+            .unwrap();
+        let basis: AnyDhtHash = base_address.into();
+        assert_eq!(to_publish.len(), 1);
+        assert_eq!(to_publish[&basis].len(), 3);
+    }
+
+    /// The entry def of an entry sets how many receipts its ops are
+    /// published until, and which validation package authorities request
+    #[tokio::test(threaded_scheduler)]
+    async fn entry_def_requirements_apply_per_def() {
+        observability::test_run().ok();
+        let test_env = test_cell_env();
+        let env = test_env.env();
+        let env_ref = env.guard();
+
+        // The first def requires sub chain packages and 3 receipts,
+        // the second has the defaults
+        let mut sub_chain_def = fixt!(EntryDef);
+        sub_chain_def.visibility = EntryVisibility::Public;
+        sub_chain_def.required_validations = 3.into();
+        sub_chain_def.required_validation_type = RequiredValidationType::SubChain;
+        let mut default_def = fixt!(EntryDef);
+        default_def.visibility = EntryVisibility::Public;
+        default_def.required_validations = Default::default();
+        default_def.required_validation_type = Default::default();
+        let sub_chain_requirements = OpRequirements::from(&sub_chain_def);
+        let default_requirements = OpRequirements::from(&default_def);
+
+        let dna_file = fake_dna_zomes(
+            "entry_def_requirements_apply_per_def",
+            vec![(TestWasm::EntryDefs.into(), TestWasm::EntryDefs.into())],
+        );
+        let mut conductor_api = MockCellConductorApi::new();
+        conductor_api
+            .expect_sync_get_this_dna()
+            .return_const(Some(dna_file));
+        conductor_api.expect_sync_get_entry_def().returning({
+            let entry_defs = vec![sub_chain_def, default_def];
+            move |key| entry_defs.get(key.entry_def_position().index()).cloned()
+        });
+
+        // Commit an entry of each def
+        let headers = {
+            let mut source_chain = SourceChain::new(env.clone().into()).unwrap();
+            fake_genesis(&mut source_chain).await.unwrap();
+            let mut headers = Vec::new();
+            for entry_def_index in 0..2u8 {
+                let entry = fixt!(Entry);
+                let entry_type =
+                    AppEntryType::new(entry_def_index.into(), 0.into(), EntryVisibility::Public);
+                let header_hash = source_chain
+                    .put(
+                        builder::Create {
+                            entry_type: EntryType::App(entry_type),
+                            entry_hash: EntryHash::with_data_sync(&entry),
+                        },
+                        Some(entry),
+                    )
+                    .await
+                    .unwrap();
+                headers.push(header_hash);
+            }
+            env_ref
+                .with_commit::<SourceChainError, _, _>(|writer| {
+                    source_chain.flush_to_txn(writer)?;
+                    Ok(())
+                })
+                .unwrap();
+            headers
+        };
+        let (sub_chain_header, default_header) = (headers[0].clone(), headers[1].clone());
+
+        {
+            let workspace = ProduceDhtOpsWorkspace::new(env.clone().into()).unwrap();
+            let (mut qt, _rx) = TriggerSender::new();
+            let complete =
+                produce_dht_ops_workflow(workspace, env.clone().into(), &mut qt, &conductor_api)
+                    .await
+                    .unwrap();
+            assert_matches!(complete, WorkComplete::Complete);
+        }
+
+        // Every op has 3 receipts
+        let mut requirements = HashMap::new();
+        {
+            let reader = env_ref.reader().unwrap();
+            let mut workspace = PublishDhtOpsWorkspace::new(env.clone().into()).unwrap();
+            let values = workspace
+                .authored_dht_ops
+                .iter(&reader)
+                .unwrap()
+                .map(|(k, mut v)| {
+                    if let DhtOpLight::StoreEntry(header_hash, _, _) = &v.op {
+                        requirements.insert(header_hash.clone(), v.requirements);
+                    }
+                    v.receipt_count = 3;
+                    Ok((DhtOpHash::with_pre_hashed(k.to_vec()), v))
+                })
+                .collect::<Vec<_>>()
+                .unwrap();
+            for (hash, v) in values.into_iter() {
+                workspace.authored_dht_ops.put(hash, v).unwrap();
+            }
+            env_ref
+                .with_commit::<DatabaseError, _, _>(|writer| {
+                    workspace.authored_dht_ops.flush_to_txn(writer)?;
+                    Ok(())
+                })
+                .unwrap();
+        }
+
+        // The ops are produced with the requirements of their def
+        assert_eq!(
+            requirements[&sub_chain_header],
+            Some(sub_chain_requirements)
+        );
+        assert_eq!(requirements[&default_header], Some(default_requirements));
+
+        // So authorities request a sub chain package from the author
+        // for the first def only
+        let (network, mut recv) = spawn_holochain_p2p(Default::default()).await.unwrap();
+        let requested = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let recv_task = tokio::task::spawn({
+            let requested = requested.clone();
+            async move {
+                use tokio::stream::StreamExt;
+                while let Some(evt) = recv.next().await {
+                    use holochain_p2p::event::HolochainP2pEvent::*;
+                    match evt {
+                        GetValidationPackage {
+                            respond,
+                            header_hash,
+                            ..
+                        } => {
+                            requested.lock().push(header_hash);
+                            respond.respond(Ok(async move { Ok(ValidationPackageResponse(None)) }
+                                .boxed()
+                                .into()));
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        });
+        let dna = fixt!(DnaHash);
+        let authority = fixt!(AgentPubKey);
+        for agent in vec![fake_agent_pubkey_1(), authority.clone()] {
+            HolochainP2pRef::join(&network, dna.clone(), agent)
+                .await
+                .unwrap();
+        }
+        let cell_network = network.to_cell(dna, authority);
+        let source_chain = SourceChain::new(env.clone().into()).unwrap();
+        for header_hash in vec![sub_chain_header.clone(), default_header.clone()] {
+            let element = source_chain.get_element(&header_hash).unwrap().unwrap();
+            op_validation_package(
+                &element,
+                requirements[&header_hash].as_ref(),
+                &None,
+                cell_network.clone(),
+            )
+            .await
+            .unwrap();
+        }
+        tokio::time::timeout(Duration::from_secs(10), network.ghost_actor_shutdown())
+            .await
+            .ok();
+        tokio::time::timeout(Duration::from_secs(10), recv_task)
+            .await
+            .ok();
+        assert_eq!(*requested.lock(), vec![sub_chain_header]);
+
+        // And only the second def's ops need more receipts
+        let mut workspace = PublishDhtOpsWorkspace::new(env.clone().into()).unwrap();
+        let republished = publish_dht_ops_workflow_inner(&mut workspace)
+            .await
+            .unwrap()
+            .into_iter()
+            .flat_map(|(_, ops)| ops)
+            .filter(|(_, op, _)| op.requirements_entry_type().is_some())
+            .map(|(_, op, requirements)| {
+                (
+                    HeaderHashed::from_content_sync(op.header()).into_hash(),
+                    requirements,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            republished,
+            vec![
+                (default_header.clone(), Some(default_requirements)),
+                (default_header, Some(default_requirements)),
+            ]
+        );
+    }
+
+    /// Ops whose entry def requires no receipts are still published once
+    #[test]
+    fn ops_are_published_until_they_have_a_receipt() {
+        let mut value = AuthoredDhtOpsValue::from_light(DhtOpLight::RegisterAddLink(
+            fixt!(HeaderHash),
+            fixt!(EntryHash).into(),
+        ));
+        value.requirements = Some(OpRequirements {
+            required_validations: 0.into(),
+            required_validation_type: Default::default(),
+        });
+        assert_eq!(required_receipt_count(&value), 1);
+        value.requirements = None;
+        assert_eq!(required_receipt_count(&value), DEFAULT_RECEIPT_BUNDLE_SIZE);
+    }
+
     /// There is a test that shows that if the validation_receipt_count > R
     /// for a DHTOp we don't re-publish it
     #[test_case(1, 1)]
@@ -522,9 +819,14 @@ mod tests {
                 {
                     let workspace = ProduceDhtOpsWorkspace::new(env.clone().into()).unwrap();
                     let (mut qt, _rx) = TriggerSender::new();
-                    let complete = produce_dht_ops_workflow(workspace, env.clone().into(), &mut qt)
-                        .await
-                        .unwrap();
+                    let complete = produce_dht_ops_workflow(
+                        workspace,
+                        env.clone().into(),
+                        &mut qt,
+                        &conductor_api_without_zomes(),
+                    )
+                    .await
+                    .unwrap();
                     assert_matches!(complete, WorkComplete::Complete);
                 }
                 {
@@ -646,9 +948,14 @@ mod tests {
                 {
                     let workspace = ProduceDhtOpsWorkspace::new(env.clone().into()).unwrap();
                     let (mut qt, _rx) = TriggerSender::new();
-                    let complete = produce_dht_ops_workflow(workspace, env.clone().into(), &mut qt)
-                        .await
-                        .unwrap();
+                    let complete = produce_dht_ops_workflow(
+                        workspace,
+                        env.clone().into(),
+                        &mut qt,
+                        &conductor_api_without_zomes(),
+                    )
+                    .await
+                    .unwrap();
                     assert_matches!(complete, WorkComplete::Complete);
                 }
 
@@ -659,7 +966,7 @@ mod tests {
                     .collect::<Vec<_>>();
 
                 // Create the network
-                let (network, mut recv) = spawn_holochain_p2p().await.unwrap();
+                let (network, mut recv) = spawn_holochain_p2p(Default::default()).await.unwrap();
                 let cell_network = network.to_cell(dna.clone(), agents[0].clone());
                 let (tx_complete, rx_complete) = tokio::sync::oneshot::channel();
                 // We are expecting five ops per agent
@@ -14,6 +14,7 @@ use crate::{
             element_buf::ElementBuf,
             metadata::MetadataBuf,
             validation_db::{ValidationLimboStatus, ValidationLimboStore, ValidationLimboValue},
+            validation_receipts_db::{PendingValidationReceiptsStore, ValidationAnnotation},
             workspace::{Workspace, WorkspaceError, WorkspaceResult},
         },
         sys_validate::*,
@@ -26,20 +27,23 @@ use holo_hash::DhtOpHash;
 use holochain_p2p::{HolochainP2pCell, HolochainP2pCellT};
 use holochain_state::{
     buffer::{BufferedStore, KvBufFresh},
-    db::INTEGRATION_LIMBO,
+    db::{INTEGRATION_LIMBO, PENDING_VALIDATION_RECEIPTS},
     fresh_reader,
     prelude::*,
 };
 use holochain_types::{
-    dht_op::DhtOp, header::NewEntryHeaderRef, test_utils::which_agent, validate::ValidationStatus,
-    Entry, Timestamp,
+    dht_op::{DhtOp, OpRequirements},
+    header::NewEntryHeaderRef,
+    test_utils::which_agent,
+    validate::ValidationStatus,
+    Clock, Entry, SystemClock, Timestamp,
 };
 use holochain_zome_types::signature::Signature;
 use holochain_zome_types::{
     header::{CreateLink, Delete, DeleteLink, EntryType, Update},
     Header,
 };
-use std::{collections::BinaryHeap, convert::TryFrom, convert::TryInto};
+use std::{collections::BinaryHeap, convert::TryFrom, convert::TryInto, sync::Arc, time::Duration};
 use tracing::*;
 
 use produce_dht_ops_workflow::dht_op_light::light_to_op;
@@ -50,6 +54,10 @@ pub mod types;
 #[cfg(test)]
 mod chain_test;
 #[cfg(test)]
+mod clock_skew_test;
+#[cfg(test)]
+mod requirements_test;
+#[cfg(test)]
 mod tests;
 
 #[instrument(skip(
@@ -94,18 +102,29 @@ async fn sys_validation_workflow_inner(
     sys_validation_trigger: TriggerSender,
 ) -> WorkflowResult<WorkComplete> {
     let env = workspace.validation_limbo.env().clone();
+    let now = workspace.clock.now();
+    // The earliest time a delayed op will be ready to validate again
+    let mut next_wake: Option<Timestamp> = None;
     // Drain all the ops
     let sorted_ops: BinaryHeap<OrderedOp<ValidationLimboValue>> = fresh_reader!(env, |r| {
         let validation_limbo = &mut workspace.validation_limbo;
         let element_pending = &workspace.element_pending;
+        let next_wake = &mut next_wake;
 
         let sorted_ops: Result<BinaryHeap<OrderedOp<ValidationLimboValue>>, WorkflowError> =
             validation_limbo
-                .drain_iter_filter(&r, |(_, vlv)| {
+                .drain_iter_filter(&r, move |(_, vlv)| {
                     match vlv.status {
                         // We only want pending or awaiting sys dependency ops
                         ValidationLimboStatus::Pending
                         | ValidationLimboStatus::AwaitingSysDeps(_) => Ok(true),
+                        // and delayed ops which are now ready
+                        ValidationLimboStatus::Delayed { until, .. } => {
+                            if until > now {
+                                wake_at(next_wake, until);
+                            }
+                            Ok(until <= now)
+                        }
                         ValidationLimboStatus::SysValidated
                         | ValidationLimboStatus::AwaitingAppDeps(_) => Ok(false),
                     }
@@ -145,8 +164,19 @@ async fn sys_validation_workflow_inner(
         let incoming_dht_ops_sender =
             IncomingDhtOpSender::new(workspace.env.clone().into(), sys_validation_trigger.clone());
 
+        // Every newly received op contributes to the estimate of its authors' clock skew
+        if vlv.num_tries == 0 {
+            conductor_api.clock_skew().observe(
+                conductor_api.cell_id().dna_hash(),
+                &op_hash,
+                op.header().timestamp().into(),
+                vlv.time_added,
+            );
+        }
+
         let outcome = validate_op(
             &op,
+            vlv.requirements.as_ref(),
             workspace,
             network.clone(),
             &conductor_api,
@@ -183,6 +213,27 @@ async fn sys_validation_workflow_inner(
                 vlv.status = ValidationLimboStatus::Pending;
                 workspace.put_val_limbo(op_hash, vlv)?;
             }
+            Outcome::Delayed {
+                until,
+                observed_skew,
+            } => {
+                wake_at(&mut next_wake, until);
+                // The first delay saw the op at its furthest ahead
+                if !vlv
+                    .annotations
+                    .iter()
+                    .any(|a| matches!(a, ValidationAnnotation::ClockSkewSuspected { .. }))
+                {
+                    let annotation = ValidationAnnotation::ClockSkewSuspected { observed_skew };
+                    workspace.annotate_pending_receipt(&op_hash, annotation.clone())?;
+                    vlv.annotations.push(annotation);
+                }
+                vlv.status = ValidationLimboStatus::Delayed {
+                    until,
+                    observed_skew,
+                };
+                workspace.put_val_limbo(op_hash, vlv)?;
+            }
             Outcome::Rejected => {
                 let iv = IntegrationLimboValue {
                     op: vlv.op,
@@ -192,25 +243,48 @@ async fn sys_validation_workflow_inner(
             }
         }
     }
+
+    // Come back for the delayed ops once they are ready
+    if let Some(until) = next_wake {
+        let delay_ms = clock_skew::millis_between(workspace.clock.now(), until).max(0) as u64;
+        sys_validation_trigger.trigger_after(Duration::from_millis(delay_ms));
+    }
     Ok(WorkComplete::Complete)
 }
 
+/// Bring forward the time to wake up for delayed ops
+fn wake_at(next_wake: &mut Option<Timestamp>, until: Timestamp) {
+    if next_wake.map_or(true, |wake| until < wake) {
+        *next_wake = Some(until);
+    }
+}
+
 async fn validate_op(
     op: &DhtOp,
+    requirements: Option<&OpRequirements>,
     workspace: &mut SysValidationWorkspace,
     network: HolochainP2pCell,
     conductor_api: &impl CellConductorApiT,
     incoming_dht_ops_sender: Option<IncomingDhtOpSender>,
 ) -> WorkflowResult<Outcome> {
-    match validate_op_inner(
+    let result = validate_op_inner(
         op,
+        requirements,
         workspace,
         network,
         conductor_api,
         incoming_dht_ops_sender,
     )
     .await
-    {
+    // Ops which are otherwise valid are only held back by clock skew
+    .and_then(|_| {
+        check_timestamp_drift(
+            &op.header(),
+            workspace.clock.now(),
+            conductor_api.clock_skew().received_tolerance(),
+        )
+    });
+    match result {
         Ok(_) => match op {
             // TODO: Check strict mode where store element
             // is also run through app validation
@@ -252,11 +326,19 @@ fn handle_failed(error: ValidationOutcome) -> Outcome {
         ValidationOutcome::NotCreateLink(_) => Rejected,
         ValidationOutcome::NotNewEntry(_) => Rejected,
         ValidationOutcome::NotHoldingDep(dep) => AwaitingOpDep(dep),
+        ValidationOutcome::OpRequirements(_, _) => Rejected,
         ValidationOutcome::PrevHeaderError(PrevHeaderError::MissingMeta(dep)) => {
             AwaitingOpDep(dep.into())
         }
         ValidationOutcome::PrevHeaderError(_) => Rejected,
         ValidationOutcome::PrivateEntry => Rejected,
+        ValidationOutcome::TimestampAhead {
+            observed_skew,
+            acceptable_at,
+        } => Delayed {
+            until: acceptable_at,
+            observed_skew,
+        },
         ValidationOutcome::UpdateTypeMismatch(_, _) => Rejected,
         ValidationOutcome::VerifySignature(_, _) => Rejected,
         ValidationOutcome::ZomeId(_) => Rejected,
@@ -265,11 +347,13 @@ fn handle_failed(error: ValidationOutcome) -> Outcome {
 
 async fn validate_op_inner(
     op: &DhtOp,
+    requirements: Option<&OpRequirements>,
     workspace: &mut SysValidationWorkspace,
     network: HolochainP2pCell,
     conductor_api: &impl CellConductorApiT,
     incoming_dht_ops_sender: Option<IncomingDhtOpSender>,
 ) -> SysValidationResult<()> {
+    check_op_requirements(op, requirements, conductor_api).await?;
     match op {
         DhtOp::StoreElement(_, header, entry) => {
             store_element(header, workspace, network.clone()).await?;
@@ -421,6 +505,11 @@ async fn sys_validate_element_inner(
         }
         _ => (),
     }
+    check_timestamp_drift(
+        header,
+        workspace.clock.now(),
+        conductor_api.clock_skew().authored_tolerance(),
+    )?;
     Ok(())
 }
 
@@ -666,7 +755,11 @@ pub struct SysValidationWorkspace {
     /// Cached data
     pub element_cache: ElementBuf,
     pub meta_cache: MetadataBuf,
+    /// Receipts waiting for the ops to be validated, to be annotated
+    pub pending_receipts: PendingValidationReceiptsStore,
     pub env: EnvironmentRead,
+    /// The clock header timestamps are checked against
+    pub clock: Arc<dyn Clock>,
 }
 
 impl<'a> SysValidationWorkspace {
@@ -675,7 +768,6 @@ impl<'a> SysValidationWorkspace {
         network: Network,
     ) -> Cascade<'a, Network> {
         Cascade::new(
-            self.validation_limbo.env().clone(),
             &self.element_authored,
             &self.meta_authored,
             &self.element_vault,
@@ -708,6 +800,9 @@ impl SysValidationWorkspace {
         let element_rejected = ElementBuf::rejected(env.clone())?;
         let meta_rejected = MetadataBuf::rejected(env.clone())?;
 
+        let db = env.get_db(&*PENDING_VALIDATION_RECEIPTS)?;
+        let pending_receipts = KvBufFresh::new(env.clone(), db);
+
         Ok(Self {
             integration_limbo,
             validation_limbo,
@@ -721,10 +816,18 @@ impl SysValidationWorkspace {
             meta_authored,
             element_cache,
             meta_cache,
+            pending_receipts,
             env,
+            clock: Arc::new(SystemClock),
         })
     }
 
+    /// Check header timestamps against a different clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
     fn put_val_limbo(
         &mut self,
         hash: DhtOpHash,
@@ -736,6 +839,19 @@ impl SysValidationWorkspace {
         Ok(())
     }
 
+    /// Add to the receipt the op's publisher is waiting for, if there is one
+    fn annotate_pending_receipt(
+        &mut self,
+        hash: &DhtOpHash,
+        annotation: ValidationAnnotation,
+    ) -> WorkflowResult<()> {
+        if let Some(mut pending) = self.pending_receipts.get(hash)? {
+            pending.annotations.push(annotation);
+            self.pending_receipts.put(hash.clone(), pending)?;
+        }
+        Ok(())
+    }
+
     #[tracing::instrument(skip(self, hash))]
     fn put_int_limbo(&mut self, hash: DhtOpHash, iv: IntegrationLimboValue) -> WorkflowResult<()> {
         self.integration_limbo.put(hash, iv)?;
@@ -804,6 +920,7 @@ impl Workspace for SysValidationWorkspace {
 
         self.element_pending.flush_to_txn_ref(writer)?;
         self.meta_pending.flush_to_txn_ref(writer)?;
+        self.pending_receipts.flush_to_txn_ref(writer)?;
         Ok(())
     }
 }
@@ -820,6 +937,7 @@ impl TryFrom<&CallZomeWorkspace> for SysValidationWorkspace {
             meta_integrated,
             element_cache,
             meta_cache,
+            ..
         } = call_zome;
         let mut sys_val = Self::new(call_zome.env().clone())?;
         sys_val.element_authored = source_chain.elements().into();
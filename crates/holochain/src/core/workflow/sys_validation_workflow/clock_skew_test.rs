@@ -0,0 +1,157 @@
+use super::*;
+use crate::{
+    conductor::api::MockCellConductorApi,
+    core::{
+        clock_skew::ClockSkew,
+        state::dht_op_integration::IntegratedDhtOpsBuf,
+        workflow::{
+            incoming_dht_ops_workflow::incoming_dht_ops_workflow,
+            integrate_dht_ops_workflow::{integrate_dht_ops_workflow, IntegrateDhtOpsWorkspace},
+        },
+    },
+    test_utils::test_network,
+};
+use holochain_state::{env::EnvironmentWrite, test_utils::test_cell_env};
+use holochain_types::{
+    cell::CellId,
+    element::{SignedHeaderHashed, SignedHeaderHashedExt},
+    test_utils::{fake_agent_pubkey_1, fake_dna_hash, FakeClock},
+    HeaderHashed,
+};
+use holochain_zome_types::header::Dna;
+use matches::assert_matches;
+
+pub(super) fn conductor_api(cell_id: &CellId, clock_skew: &ClockSkew) -> MockCellConductorApi {
+    let mut conductor_api = MockCellConductorApi::new();
+    conductor_api.expect_cell_id().return_const(cell_id.clone());
+    conductor_api
+        .expect_mock_clock_skew()
+        .return_const(clock_skew.clone());
+    conductor_api
+}
+
+pub(super) async fn run_sys_validation(
+    env: &EnvironmentWrite,
+    clock: &Arc<FakeClock>,
+    conductor_api: MockCellConductorApi,
+    network: HolochainP2pCell,
+) {
+    let mut workspace = SysValidationWorkspace::new(env.clone().into())
+        .unwrap()
+        .with_clock(clock.clone());
+    let (trigger, _rx) = TriggerSender::new();
+    sys_validation_workflow_inner(&mut workspace, network, conductor_api, trigger)
+        .await
+        .unwrap();
+    let writer: OneshotWriter = env.clone().into();
+    writer
+        .with_writer(|writer| Ok(workspace.flush_to_txn_ref(writer)?))
+        .unwrap();
+}
+
+pub(super) fn limbo_value(
+    env: &EnvironmentWrite,
+    op_hash: &DhtOpHash,
+) -> Option<ValidationLimboValue> {
+    let workspace = SysValidationWorkspace::new(env.clone().into()).unwrap();
+    workspace.validation_limbo.get(op_hash).unwrap()
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn ops_from_a_fast_clock_are_delayed_then_integrated() {
+    observability::test_run().ok();
+    let test_env = test_cell_env();
+    let env = test_env.env();
+    let keystore = env.keystore().clone();
+    let clock = Arc::new(FakeClock::new(Timestamp::now()));
+    // Received headers may be up to 60s ahead
+    let clock_skew = ClockSkew::default();
+
+    // The author's clock is three minutes fast
+    let author = fake_agent_pubkey_1();
+    let dna_hash = fake_dna_hash(1);
+    let cell_id = CellId::new(dna_hash.clone(), author.clone());
+    let mut ahead = clock.now();
+    ahead.0 += 180;
+    let header = Header::Dna(Dna {
+        author: author.clone(),
+        timestamp: ahead.into(),
+        hash: dna_hash.clone(),
+    });
+    let signed = SignedHeaderHashed::new(&keystore, HeaderHashed::from_content_sync(header))
+        .await
+        .unwrap();
+    let op = DhtOp::RegisterAgentActivity(signed.signature().clone(), signed.header().clone());
+    let op_hash = DhtOpHash::with_data_sync(&op);
+
+    let (trigger, _rx) = TriggerSender::new();
+    incoming_dht_ops_workflow(&env, trigger, vec![(op_hash.clone(), op)])
+        .await
+        .unwrap();
+    let (_network, _recv, cell_network) =
+        test_network(Some(dna_hash.clone()), Some(author.clone())).await;
+
+    run_sys_validation(
+        &env,
+        &clock,
+        conductor_api(&cell_id, &clock_skew),
+        cell_network.clone(),
+    )
+    .await;
+    let vlv = limbo_value(&env, &op_hash).unwrap();
+    let until = Timestamp(ahead.0 - 60, ahead.1);
+    assert_eq!(
+        vlv.status,
+        ValidationLimboStatus::Delayed {
+            until,
+            observed_skew: Duration::from_secs(180),
+        }
+    );
+    assert_eq!(
+        vlv.annotations,
+        vec![ValidationAnnotation::ClockSkewSuspected {
+            observed_skew: Duration::from_secs(180),
+        }]
+    );
+    // The estimate is taken against when the op was received
+    let estimate = clock_skew.estimate_ms(&dna_hash).unwrap();
+    assert!((179_000..=180_000).contains(&estimate), "{}", estimate);
+
+    // Still too far ahead
+    clock.advance(60);
+    run_sys_validation(
+        &env,
+        &clock,
+        conductor_api(&cell_id, &clock_skew),
+        cell_network.clone(),
+    )
+    .await;
+    assert_matches!(
+        limbo_value(&env, &op_hash).unwrap().status,
+        ValidationLimboStatus::Delayed { .. }
+    );
+
+    // Now within tolerance, so the op is validated and integrated
+    clock.advance(60);
+    run_sys_validation(
+        &env,
+        &clock,
+        conductor_api(&cell_id, &clock_skew),
+        cell_network,
+    )
+    .await;
+    assert_eq!(limbo_value(&env, &op_hash), None);
+    let workspace = IntegrateDhtOpsWorkspace::new(env.clone().into()).unwrap();
+    let (mut trigger, _rx) = TriggerSender::new();
+    integrate_dht_ops_workflow(workspace, env.clone().into(), &mut trigger)
+        .await
+        .unwrap();
+    let integrated = IntegratedDhtOpsBuf::new(env.clone().into())
+        .unwrap()
+        .get(&op_hash)
+        .unwrap()
+        .unwrap();
+    assert_eq!(integrated.validation_status, ValidationStatus::Valid);
+    // A retried op isn't counted again
+    assert_eq!(clock_skew.estimate_ms(&dna_hash), Some(estimate));
+}
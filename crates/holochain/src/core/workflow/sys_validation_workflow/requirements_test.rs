@@ -0,0 +1,154 @@
+use super::clock_skew_test::{conductor_api, limbo_value, run_sys_validation};
+use super::*;
+use crate::{
+    core::{
+        clock_skew::ClockSkew,
+        state::dht_op_integration::IntegratedDhtOpsBuf,
+        workflow::{
+            incoming_dht_ops_workflow::{
+                incoming_dht_ops_workflow, incoming_dht_ops_workflow_with_requirements,
+            },
+            integrate_dht_ops_workflow::{integrate_dht_ops_workflow, IntegrateDhtOpsWorkspace},
+        },
+    },
+    test_utils::{single_zome_dna_file, test_network},
+};
+use ::fixt::prelude::*;
+use holo_hash::EntryHash;
+use holochain_serialized_bytes::SerializedBytes;
+use holochain_state::test_utils::test_cell_env;
+use holochain_types::{
+    cell::CellId,
+    element::{SignedHeaderHashed, SignedHeaderHashedExt},
+    fixt::EntryDefFixturator,
+    header::NewEntryHeader,
+    test_utils::{fake_agent_pubkey_1, FakeClock},
+    HeaderHashed,
+};
+use holochain_wasm_test_utils::TestWasm;
+use holochain_zome_types::{
+    entry_def::EntryVisibility,
+    header::{AppEntryType, Create, Dna, EntryType},
+    validate::RequiredValidationType,
+};
+use test_wasm_common::TestString;
+
+/// - An entry op published without a claim is sys validated
+///   without its requirements being checked
+/// - The same kind of op claiming less than the entry def requires
+///   is rejected
+#[tokio::test(threaded_scheduler)]
+async fn ops_without_a_claim_pass_sys_validation() {
+    observability::test_run().ok();
+    let test_env = test_cell_env();
+    let env = test_env.env();
+    let keystore = env.keystore().clone();
+    let clock = Arc::new(FakeClock::new(Timestamp::now()));
+    let clock_skew = ClockSkew::default();
+
+    let dna_file = single_zome_dna_file(
+        "op_requirements",
+        "8e2c7a41-5b3d-4f96-a0e1-7c4d2b9f6a35",
+        TestWasm::EntryDefs,
+    )
+    .await;
+    let mut entry_def = fixt!(EntryDef);
+    entry_def.visibility = EntryVisibility::Public;
+    entry_def.required_validations = 3.into();
+    entry_def.required_validation_type = RequiredValidationType::SubChain;
+    let expected = OpRequirements::from(&entry_def);
+
+    let author = fake_agent_pubkey_1();
+    let dna_hash = dna_file.dna_hash().clone();
+    let cell_id = CellId::new(dna_hash.clone(), author.clone());
+    let (_network, _recv, cell_network) =
+        test_network(Some(dna_hash.clone()), Some(author.clone())).await;
+    let mut conductor_api = conductor_api(&cell_id, &clock_skew);
+    conductor_api
+        .expect_sync_get_this_dna()
+        .return_const(Some(dna_file.clone()));
+    conductor_api
+        .expect_sync_get_entry_def()
+        .return_const(Some(entry_def));
+
+    // The author's chain starts with a Dna header which is already held,
+    // so the ops after it have their previous header
+    let dna = Header::Dna(Dna {
+        author: author.clone(),
+        timestamp: clock.now().into(),
+        hash: dna_hash.clone(),
+    });
+    let dna = SignedHeaderHashed::new(&keystore, HeaderHashed::from_content_sync(dna))
+        .await
+        .unwrap();
+    let prev_header = dna.as_hash().clone();
+    let op = DhtOp::StoreElement(dna.signature().clone(), dna.header().clone(), None);
+    let (trigger, _rx) = TriggerSender::new();
+    incoming_dht_ops_workflow(&env, trigger, vec![(DhtOpHash::with_data_sync(&op), op)])
+        .await
+        .unwrap();
+
+    // Two entries are created after it
+    let store_entry = |content: &str| {
+        let entry = Entry::app(SerializedBytes::try_from(TestString(content.to_string())).unwrap())
+            .unwrap();
+        let mut timestamp = clock.now();
+        timestamp.0 += 1;
+        let create = Create {
+            author: author.clone(),
+            timestamp: timestamp.into(),
+            header_seq: 1,
+            prev_header: prev_header.clone(),
+            entry_type: EntryType::App(AppEntryType::new(
+                0.into(),
+                0.into(),
+                EntryVisibility::Public,
+            )),
+            entry_hash: EntryHash::with_data_sync(&entry),
+        };
+        (create, entry)
+    };
+    let mut ops = Vec::new();
+    for (create, entry) in vec![store_entry("unclaimed"), store_entry("lowered")] {
+        let signed = SignedHeaderHashed::new(
+            &keystore,
+            HeaderHashed::from_content_sync(Header::Create(create.clone())),
+        )
+        .await
+        .unwrap();
+        let op = DhtOp::StoreEntry(
+            signed.signature().clone(),
+            NewEntryHeader::Create(create),
+            Box::new(entry),
+        );
+        ops.push((DhtOpHash::with_data_sync(&op), op));
+    }
+    let unclaimed = ops[0].0.clone();
+    let lowered = ops[1].0.clone();
+    let claim = OpRequirements {
+        required_validations: 1.into(),
+        ..expected
+    };
+    let (trigger, _rx) = TriggerSender::new();
+    incoming_dht_ops_workflow_with_requirements(&env, trigger, ops, vec![(lowered.clone(), claim)])
+        .await
+        .unwrap();
+
+    run_sys_validation(&env, &clock, conductor_api, cell_network).await;
+    let vlv = limbo_value(&env, &unclaimed).unwrap();
+    assert_eq!(vlv.status, ValidationLimboStatus::SysValidated);
+    assert_eq!(vlv.requirements, None);
+
+    assert_eq!(limbo_value(&env, &lowered), None);
+    let workspace = IntegrateDhtOpsWorkspace::new(env.clone().into()).unwrap();
+    let (mut trigger, _rx) = TriggerSender::new();
+    integrate_dht_ops_workflow(workspace, env.clone().into(), &mut trigger)
+        .await
+        .unwrap();
+    let integrated = IntegratedDhtOpsBuf::new(env.clone().into())
+        .unwrap()
+        .get(&lowered)
+        .unwrap()
+        .unwrap();
+    assert_eq!(integrated.validation_status, ValidationStatus::Rejected);
+}
@@ -14,6 +14,12 @@ pub(super) enum Outcome {
     /// be found currently on the DHT.
     /// Note this is not proof it doesn't exist.
     MissingDhtDep,
+    /// Stays in limbo until the header's timestamp
+    /// is no longer too far ahead of our clock
+    Delayed {
+        until: Timestamp,
+        observed_skew: Duration,
+    },
     /// Moves to integration with status rejected
     Rejected,
 }
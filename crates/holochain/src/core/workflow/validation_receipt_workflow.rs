@@ -0,0 +1,265 @@
+//! # Validation Receipt Workflow
+//!
+//! Sends validation receipts back to the agents which published ops to this
+//! authority and asked for them.
+//! Receipts are only sent once the ops have been integrated, and all the
+//! receipts owed to one publisher are sent together in a single
+//! [ValidationReceiptBundle], instead of one message per op.
+//! Receipts which couldn't be sent stay pending, and the run fails so that
+//! its consumer backs off and tries them again.
+
+use super::error::WorkflowResult;
+use crate::core::{
+    queue_consumer::{OneshotWriter, WorkComplete},
+    state::{
+        dht_op_integration::{AuthoredDhtOpsStore, IntegratedDhtOpsStore, IntegrationLimboStore},
+        validation_db::ValidationLimboStore,
+        validation_receipts_db::{
+            decode_validation_receipts, PendingValidationReceipt, PendingValidationReceiptsStore,
+            ValidationAnnotation, ValidationReceipt, ValidationReceiptBundle,
+            ValidationReceiptsBuf, ValidationResult,
+        },
+        workspace::{Workspace, WorkspaceResult},
+    },
+};
+use fallible_iterator::FallibleIterator;
+use holo_hash::{AgentPubKey, DhtOpHash};
+use holochain_keystore::{AgentPubKeyExt, KeystoreSender};
+use holochain_p2p::HolochainP2pCellT;
+use holochain_serialized_bytes::prelude::*;
+use holochain_state::{
+    buffer::{BufferedStore, KvBufFresh},
+    db::{AUTHORED_DHT_OPS, INTEGRATED_DHT_OPS, INTEGRATION_LIMBO, PENDING_VALIDATION_RECEIPTS},
+    env::EnvironmentWrite,
+    error::{DatabaseError, DatabaseResult},
+    fresh_reader,
+    prelude::*,
+};
+use holochain_types::validate::ValidationStatus;
+use std::collections::{BTreeSet, HashMap};
+use std::time;
+use tracing::*;
+
+#[cfg(test)]
+mod tests;
+
+/// How long the authority keeps a batch of receipts open after the first
+/// integration pass which owes one, so that receipts for ops integrated
+/// over several passes can be sent together.
+pub const DEFAULT_RECEIPT_FLUSH_INTERVAL: time::Duration = time::Duration::from_millis(500);
+
+/// Database buffers required for sending validation receipts
+pub struct ValidationReceiptWorkspace {
+    /// The ops whose publisher asked for a receipt
+    pending_receipts: PendingValidationReceiptsStore,
+    /// Where ops end up once they have been validated
+    integrated_dht_ops: IntegratedDhtOpsStore,
+    /// Ops which are validated but not yet integrated
+    integration_limbo: IntegrationLimboStore,
+    /// Ops which are still being validated
+    validation_limbo: ValidationLimboStore,
+}
+
+#[instrument(skip(workspace, writer, network, keystore))]
+pub async fn validation_receipt_workflow(
+    mut workspace: ValidationReceiptWorkspace,
+    writer: OneshotWriter,
+    network: &mut impl HolochainP2pCellT,
+    keystore: KeystoreSender,
+) -> WorkflowResult<WorkComplete> {
+    let validator = network.from_agent();
+    let to_send = validation_receipt_workflow_inner(&mut workspace)?;
+
+    // One message per publisher, holding all the receipts owed to it
+    let mut send_error = None;
+    for (publisher, ops) in to_send {
+        let mut receipts = Vec::with_capacity(ops.len());
+        for (dht_op_hash, annotations) in ops.iter().cloned() {
+            let receipt = ValidationReceipt {
+                dht_op_hash,
+                validation_result: ValidationResult::Valid,
+                validator: validator.clone(),
+                annotations,
+            };
+            receipts.push(receipt.sign(&keystore).await?);
+        }
+        let bundle = SerializedBytes::try_from(ValidationReceiptBundle(receipts))?;
+        match network
+            .send_validation_receipt(publisher.clone(), bundle)
+            .await
+        {
+            Ok(()) => {
+                for (op_hash, _) in ops {
+                    workspace.pending_receipts.delete(op_hash)?;
+                }
+            }
+            // The receipts stay pending until the retry
+            Err(err) => {
+                warn!(?publisher, ?err, "Failed to send validation receipts");
+                send_error.get_or_insert(err);
+            }
+        }
+    }
+
+    // --- END OF WORKFLOW, BEGIN FINISHER BOILERPLATE ---
+
+    // commit the workspace
+    writer.with_writer(|writer| Ok(workspace.flush_to_txn(writer)?))?;
+
+    // Fail the run so the unsent receipts are retried after a backoff
+    if let Some(err) = send_error {
+        return Err(err.into());
+    }
+    Ok(WorkComplete::Complete)
+}
+
+/// Find the pending ops which have been integrated as valid, along with
+/// their annotations, grouped by the agent which asked for their receipts.
+/// Pending ops which will never be integrated as valid are forgotten.
+pub fn validation_receipt_workflow_inner(
+    workspace: &mut ValidationReceiptWorkspace,
+) -> WorkflowResult<HashMap<AgentPubKey, Vec<(DhtOpHash, Vec<ValidationAnnotation>)>>> {
+    let env = workspace.pending_receipts.env().clone();
+    let pending = fresh_reader!(env, |r| workspace
+        .pending_receipts
+        .iter(&r)?
+        .map(|(k, pending)| Ok((DhtOpHash::with_pre_hashed(k.to_vec()), pending)))
+        .collect::<Vec<_>>())?;
+
+    let mut to_send: HashMap<AgentPubKey, Vec<_>> = HashMap::new();
+    for (op_hash, pending) in pending {
+        match workspace.integrated_dht_ops.get(&op_hash)? {
+            Some(value) if value.validation_status == ValidationStatus::Valid => {
+                to_send
+                    .entry(pending.publisher)
+                    .or_default()
+                    .push((op_hash, pending.annotations));
+            }
+            // There is no receipt for an op which isn't valid
+            Some(_) => workspace.pending_receipts.delete(op_hash)?,
+            None => {
+                let still_validating = workspace.integration_limbo.contains(&op_hash)?
+                    || workspace.validation_limbo.contains(&op_hash)?;
+                // The op was dropped before it was validated, e.g. as a counterfeit
+                if !still_validating {
+                    workspace.pending_receipts.delete(op_hash)?;
+                }
+            }
+        }
+    }
+    Ok(to_send)
+}
+
+/// Remember that `publisher` asked for receipts for these ops,
+/// so they are sent once the ops have been integrated.
+/// Call this before the ops are validated, so that anything
+/// validation notices can be added to the receipts.
+pub fn request_validation_receipts(
+    env: &EnvironmentWrite,
+    publisher: AgentPubKey,
+    op_hashes: impl IntoIterator<Item = DhtOpHash>,
+) -> WorkflowResult<()> {
+    let mut workspace = ValidationReceiptWorkspace::new(env.clone().into())?;
+    for op_hash in op_hashes {
+        // Keep what has been noticed about an op which is published again
+        let annotations = workspace
+            .pending_receipts
+            .get(&op_hash)?
+            .map(|pending| pending.annotations)
+            .unwrap_or_default();
+        let pending = PendingValidationReceipt {
+            publisher: publisher.clone(),
+            annotations,
+        };
+        workspace.pending_receipts.put(op_hash, pending)?;
+    }
+    let writer: OneshotWriter = env.clone().into();
+    writer.with_writer(|writer| Ok(workspace.flush_to_txn(writer)?))?;
+    Ok(())
+}
+
+/// Record the validation receipts an authority sent for ops this agent
+/// authored, and update each op's receipt count so that it is no longer
+/// published once it has enough receipts.
+/// Accepts either a single [SignedValidationReceipt](crate::core::state::validation_receipts_db::SignedValidationReceipt)
+/// or a [ValidationReceiptBundle].
+#[instrument(skip(env, receipts))]
+pub async fn receive_validation_receipts(
+    env: &EnvironmentWrite,
+    receipts: SerializedBytes,
+) -> WorkflowResult<()> {
+    let receipts = decode_validation_receipts(receipts)?;
+
+    let mut receipts_buf = ValidationReceiptsBuf::new(env)?;
+    let mut authored_dht_ops: AuthoredDhtOpsStore =
+        KvBufFresh::new(env.clone().into(), env.get_db(&*AUTHORED_DHT_OPS)?);
+
+    let mut op_hashes = BTreeSet::new();
+    for receipt in receipts {
+        // Only ops this agent published are counted
+        if !authored_dht_ops.contains(&receipt.receipt.dht_op_hash)? {
+            continue;
+        }
+        let valid_signature = receipt
+            .receipt
+            .validator
+            .verify_signature(&receipt.validator_signature, receipt.receipt.clone())
+            .await
+            .map_err(DatabaseError::from)?;
+        if !valid_signature {
+            warn!(validator = ?receipt.receipt.validator, "Dropping receipt with an invalid signature");
+            continue;
+        }
+        op_hashes.insert(receipt.receipt.dht_op_hash.clone());
+        receipts_buf.add_if_unique(receipt)?;
+    }
+
+    env.guard()
+        .with_commit(|writer| receipts_buf.flush_to_txn(writer))?;
+
+    // Count the receipts now that duplicates have been dropped
+    let receipts_buf = ValidationReceiptsBuf::new(env)?;
+    let counts = fresh_reader!(env, |r| op_hashes
+        .into_iter()
+        .map(|op_hash| Ok((receipts_buf.count_valid(&r, &op_hash)?, op_hash)))
+        .collect::<DatabaseResult<Vec<_>>>())?;
+    for (count, op_hash) in counts {
+        if let Some(mut value) = authored_dht_ops.get(&op_hash)? {
+            value.receipt_count = count as u32;
+            authored_dht_ops.put(op_hash, value)?;
+        }
+    }
+    env.guard()
+        .with_commit(|writer| authored_dht_ops.flush_to_txn(writer))?;
+
+    Ok(())
+}
+
+impl Workspace for ValidationReceiptWorkspace {
+    fn flush_to_txn_ref(&mut self, writer: &mut Writer) -> WorkspaceResult<()> {
+        self.pending_receipts.flush_to_txn_ref(writer)?;
+        Ok(())
+    }
+}
+
+impl ValidationReceiptWorkspace {
+    pub fn new(env: EnvironmentRead) -> WorkspaceResult<Self> {
+        let db = env.get_db(&*PENDING_VALIDATION_RECEIPTS)?;
+        let pending_receipts = KvBufFresh::new(env.clone(), db);
+
+        let db = env.get_db(&*INTEGRATED_DHT_OPS)?;
+        let integrated_dht_ops = KvBufFresh::new(env.clone(), db);
+
+        let db = env.get_db(&*INTEGRATION_LIMBO)?;
+        let integration_limbo = KvBufFresh::new(env.clone(), db);
+
+        let validation_limbo = ValidationLimboStore::new(env)?;
+
+        Ok(Self {
+            pending_receipts,
+            integrated_dht_ops,
+            integration_limbo,
+            validation_limbo,
+        })
+    }
+}
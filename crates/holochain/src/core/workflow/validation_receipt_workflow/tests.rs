@@ -0,0 +1,320 @@
+use super::*;
+use crate::core::state::{
+    dht_op_integration::{AuthoredDhtOpsValue, IntegratedDhtOpsValue, IntegrationLimboValue},
+    validation_receipts_db::SignedValidationReceipt,
+};
+use ::fixt::prelude::*;
+use futures::future::FutureExt;
+use ghost_actor::GhostControlSender;
+use holo_hash::fixt::{
+    AgentPubKeyFixturator, AnyDhtHashFixturator, DhtOpHashFixturator, DnaHashFixturator,
+    HeaderHashFixturator,
+};
+use holochain_keystore::KeystoreSenderExt;
+use holochain_p2p::{
+    actor::HolochainP2pRefToCell, spawn_holochain_p2p, HolochainP2pError, HolochainP2pRef,
+    MockHolochainP2pCellT,
+};
+use holochain_state::{fresh_reader_test, test_utils::test_cell_env};
+use holochain_types::{dht_op::DhtOpLight, Timestamp};
+use parking_lot::Mutex;
+use std::{sync::Arc, time::Duration};
+
+fn fake_op_light() -> DhtOpLight {
+    DhtOpLight::RegisterAgentActivity(fixt!(HeaderHash), fixt!(AnyDhtHash))
+}
+
+async fn fake_receipt(
+    dht_op_hash: &DhtOpHash,
+    validator: &AgentPubKey,
+    keystore: &KeystoreSender,
+) -> SignedValidationReceipt {
+    ValidationReceipt {
+        dht_op_hash: dht_op_hash.clone(),
+        validation_result: ValidationResult::Valid,
+        validator: validator.clone(),
+        annotations: Vec::new(),
+    }
+    .sign(keystore)
+    .await
+    .unwrap()
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn receipts_for_integrated_ops_are_sent_in_one_bundle() {
+    let test_env = test_cell_env();
+    let env = test_env.env();
+    let keystore = env.keystore().clone();
+    let validator = keystore
+        .generate_sign_keypair_from_pure_entropy()
+        .await
+        .unwrap();
+    let publisher = keystore
+        .generate_sign_keypair_from_pure_entropy()
+        .await
+        .unwrap();
+
+    // Three ops are integrated, one is still being validated
+    // and one was dropped before it was validated
+    let integrated = DhtOpHashFixturator::new(Unpredictable)
+        .take(3)
+        .collect::<Vec<_>>();
+    let validating = fixt!(DhtOpHash);
+    let dropped = fixt!(DhtOpHash);
+    {
+        let mut workspace = ValidationReceiptWorkspace::new(env.clone().into()).unwrap();
+        for op_hash in integrated.iter().cloned() {
+            let value = IntegratedDhtOpsValue {
+                validation_status: ValidationStatus::Valid,
+                op: fake_op_light(),
+                when_integrated: Timestamp::now(),
+            };
+            workspace.integrated_dht_ops.put(op_hash, value).unwrap();
+        }
+        let value = IntegrationLimboValue {
+            validation_status: ValidationStatus::Valid,
+            op: fake_op_light(),
+        };
+        workspace
+            .integration_limbo
+            .put(validating.clone(), value)
+            .unwrap();
+        env.guard()
+            .with_commit::<DatabaseError, _, _>(|writer| {
+                workspace.integrated_dht_ops.flush_to_txn_ref(writer)?;
+                workspace.integration_limbo.flush_to_txn_ref(writer)?;
+                Ok(())
+            })
+            .unwrap();
+    }
+    let requested = integrated
+        .iter()
+        .cloned()
+        .chain(vec![validating.clone(), dropped]);
+    request_validation_receipts(&env, publisher.clone(), requested).unwrap();
+
+    // Validation noticed something about the first op
+    let annotation = ValidationAnnotation::ClockSkewSuspected {
+        observed_skew: Duration::from_secs(30),
+    };
+    {
+        let mut workspace = ValidationReceiptWorkspace::new(env.clone().into()).unwrap();
+        let pending = PendingValidationReceipt {
+            publisher: publisher.clone(),
+            annotations: vec![annotation.clone()],
+        };
+        workspace
+            .pending_receipts
+            .put(integrated[0].clone(), pending)
+            .unwrap();
+        env.guard()
+            .with_commit(|writer| workspace.flush_to_txn(writer))
+            .unwrap();
+    }
+
+    // Both agents join a real network, which records the messages it delivers
+    let dna = fixt!(DnaHash);
+    let (network, mut recv) = spawn_holochain_p2p(Default::default()).await.unwrap();
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let recv_task = tokio::task::spawn({
+        let sent = sent.clone();
+        async move {
+            use tokio::stream::StreamExt;
+            while let Some(evt) = recv.next().await {
+                use holochain_p2p::event::HolochainP2pEvent::*;
+                match evt {
+                    ValidationReceiptReceived {
+                        respond,
+                        to_agent,
+                        receipt,
+                        ..
+                    } => {
+                        sent.lock().push((to_agent, receipt));
+                        respond.respond(Ok(async move { Ok(()) }.boxed().into()));
+                    }
+                    _ => (),
+                }
+            }
+        }
+    });
+    for agent in vec![validator.clone(), publisher.clone()] {
+        HolochainP2pRef::join(&network, dna.clone(), agent)
+            .await
+            .unwrap();
+    }
+    let mut cell_network = network.to_cell(dna, validator.clone());
+
+    let workspace = ValidationReceiptWorkspace::new(env.clone().into()).unwrap();
+    validation_receipt_workflow(workspace, env.clone().into(), &mut cell_network, keystore)
+        .await
+        .unwrap();
+
+    tokio::time::timeout(Duration::from_secs(10), network.ghost_actor_shutdown())
+        .await
+        .ok();
+    tokio::time::timeout(Duration::from_secs(10), recv_task)
+        .await
+        .ok();
+
+    // One message holds the receipts for all the integrated ops
+    let mut sent = sent.lock();
+    assert_eq!(sent.len(), 1);
+    let (to_agent, receipts) = sent.pop().unwrap();
+    assert_eq!(to_agent, publisher);
+    let mut receipt_op_hashes = decode_validation_receipts(receipts)
+        .unwrap()
+        .into_iter()
+        .map(|r| {
+            assert_eq!(r.receipt.validator, validator);
+            // The annotation reaches the author in the op's receipt
+            if r.receipt.dht_op_hash == integrated[0] {
+                assert_eq!(r.receipt.annotations, vec![annotation.clone()]);
+            } else {
+                assert!(r.receipt.annotations.is_empty());
+            }
+            r.receipt.dht_op_hash
+        })
+        .collect::<Vec<_>>();
+    receipt_op_hashes.sort();
+    let mut expected = integrated;
+    expected.sort();
+    assert_eq!(receipt_op_hashes, expected);
+
+    // Only the op which is still being validated is waiting for its receipt
+    let workspace = ValidationReceiptWorkspace::new(env.clone().into()).unwrap();
+    let pending = fresh_reader_test!(env, |r| workspace
+        .pending_receipts
+        .iter(&r)
+        .unwrap()
+        .map(|(k, _)| Ok(DhtOpHash::with_pre_hashed(k.to_vec())))
+        .collect::<Vec<_>>()
+        .unwrap());
+    assert_eq!(pending, vec![validating]);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn receipts_which_fail_to_send_stay_pending() {
+    let test_env = test_cell_env();
+    let env = test_env.env();
+    let keystore = env.keystore().clone();
+    let validator = keystore
+        .generate_sign_keypair_from_pure_entropy()
+        .await
+        .unwrap();
+    let op_hash = fixt!(DhtOpHash);
+    {
+        let mut workspace = ValidationReceiptWorkspace::new(env.clone().into()).unwrap();
+        let value = IntegratedDhtOpsValue {
+            validation_status: ValidationStatus::Valid,
+            op: fake_op_light(),
+            when_integrated: Timestamp::now(),
+        };
+        workspace
+            .integrated_dht_ops
+            .put(op_hash.clone(), value)
+            .unwrap();
+        env.guard()
+            .with_commit::<DatabaseError, _, _>(|writer| {
+                workspace.integrated_dht_ops.flush_to_txn_ref(writer)?;
+                Ok(())
+            })
+            .unwrap();
+    }
+    request_validation_receipts(&env, fixt!(AgentPubKey), vec![op_hash.clone()]).unwrap();
+
+    let mut network = MockHolochainP2pCellT::new();
+    network.expect_from_agent().return_const(validator);
+    network
+        .expect_send_validation_receipt()
+        .times(1)
+        .returning(|_, _| Err(HolochainP2pError::other("publisher is offline")));
+
+    // The run fails so its consumer retries after a backoff
+    let workspace = ValidationReceiptWorkspace::new(env.clone().into()).unwrap();
+    let result =
+        validation_receipt_workflow(workspace, env.clone().into(), &mut network, keystore).await;
+    assert!(result.is_err());
+
+    let workspace = ValidationReceiptWorkspace::new(env.clone().into()).unwrap();
+    let pending = fresh_reader_test!(env, |r| workspace
+        .pending_receipts
+        .iter(&r)
+        .unwrap()
+        .map(|(k, _)| Ok(DhtOpHash::with_pre_hashed(k.to_vec())))
+        .collect::<Vec<_>>()
+        .unwrap());
+    assert_eq!(pending, vec![op_hash]);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn receipts_are_recorded_from_single_and_bundled_messages() {
+    let test_env = test_cell_env();
+    let env = test_env.env();
+    let keystore = env.keystore().clone();
+    let alice = keystore
+        .generate_sign_keypair_from_pure_entropy()
+        .await
+        .unwrap();
+    let bob = keystore
+        .generate_sign_keypair_from_pure_entropy()
+        .await
+        .unwrap();
+
+    // This agent authored three ops
+    let ops = DhtOpHashFixturator::new(Unpredictable)
+        .take(3)
+        .collect::<Vec<_>>();
+    {
+        let mut authored_dht_ops: AuthoredDhtOpsStore =
+            KvBufFresh::new(env.clone().into(), env.get_db(&*AUTHORED_DHT_OPS).unwrap());
+        for op_hash in ops.iter().cloned() {
+            authored_dht_ops
+                .put(op_hash, AuthoredDhtOpsValue::from_light(fake_op_light()))
+                .unwrap();
+        }
+        env.guard()
+            .with_commit(|writer| authored_dht_ops.flush_to_txn(writer))
+            .unwrap();
+    }
+
+    // Alice sends a bundle with receipts for all the ops
+    let mut bundle = Vec::new();
+    for op_hash in ops.iter() {
+        bundle.push(fake_receipt(op_hash, &alice, &keystore).await);
+    }
+    let bundle = SerializedBytes::try_from(ValidationReceiptBundle(bundle)).unwrap();
+    receive_validation_receipts(&env, bundle).await.unwrap();
+
+    // Bob sends a single receipt for the first op, twice
+    let single = fake_receipt(&ops[0], &bob, &keystore).await;
+    for _ in 0..2 {
+        let single = SerializedBytes::try_from(single.clone()).unwrap();
+        receive_validation_receipts(&env, single).await.unwrap();
+    }
+
+    // Receipts with a bad signature or for ops this agent didn't author are dropped
+    let forged = SignedValidationReceipt {
+        receipt: ValidationReceipt {
+            dht_op_hash: ops[1].clone(),
+            ..single.receipt.clone()
+        },
+        validator_signature: single.validator_signature.clone(),
+    };
+    let unknown = fake_receipt(&fixt!(DhtOpHash), &bob, &keystore).await;
+    let bundle = SerializedBytes::try_from(ValidationReceiptBundle(vec![forged, unknown])).unwrap();
+    receive_validation_receipts(&env, bundle).await.unwrap();
+
+    let authored_dht_ops: AuthoredDhtOpsStore =
+        KvBufFresh::new(env.clone().into(), env.get_db(&*AUTHORED_DHT_OPS).unwrap());
+    let counts = ops
+        .iter()
+        .map(|op_hash| {
+            authored_dht_ops
+                .get(op_hash)
+                .unwrap()
+                .unwrap()
+                .receipt_count
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(counts, vec![2, 1, 1]);
+}
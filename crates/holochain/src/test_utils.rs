@@ -21,13 +21,14 @@ use holochain_p2p::{
 };
 use holochain_serialized_bytes::{SerializedBytes, SerializedBytesError, UnsafeBytes};
 use holochain_state::{
-    env::EnvironmentWrite,
+    env::{Durability, EnvironmentWrite},
     fresh_reader_test,
     test_utils::{test_conductor_env, test_p2p_env, test_wasm_env, TestEnvironment},
 };
 use holochain_types::{
     app::InstalledCell,
     cell::CellId,
+    dna::{DnaDef, DnaFile},
     element::{SignedHeaderHashed, SignedHeaderHashedExt},
     fixt::CapSecretFixturator,
     test_utils::fake_header_hash,
@@ -39,7 +40,12 @@ use holochain_zome_types::{
     header::{Create, EntryType, Header},
     ExternInput,
 };
-use std::{convert::TryInto, sync::Arc, time::Duration};
+use std::{
+    convert::{TryFrom, TryInto},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
 use tempdir::TempDir;
 
 #[cfg(test)]
@@ -48,6 +54,9 @@ pub mod host_fn_api;
 #[cfg(test)]
 pub mod conductor_setup;
 
+#[cfg(test)]
+pub mod test_conductor;
+
 #[macro_export]
 macro_rules! here {
     ($test: expr) => {
@@ -141,7 +150,7 @@ pub async fn test_network(
     dna_hash: Option<DnaHash>,
     agent_key: Option<AgentPubKey>,
 ) -> (HolochainP2pRef, HolochainP2pEventReceiver, HolochainP2pCell) {
-    let (network, recv) = spawn_holochain_p2p().await.unwrap();
+    let (network, recv) = spawn_holochain_p2p(Default::default()).await.unwrap();
     let dna = dna_hash.unwrap_or_else(|| fixt!(DnaHash));
     let mut key_fixt = AgentPubKeyFixturator::new(Predictable);
     let agent_key = agent_key.unwrap_or_else(|| key_fixt.next().unwrap());
@@ -158,7 +167,7 @@ pub async fn install_app(
 ) {
     conductor_handle
         .clone()
-        .install_app(name.to_string(), cell_data)
+        .install_app(name.to_string(), cell_data, true)
         .await
         .unwrap();
 
@@ -216,6 +225,59 @@ pub async fn setup_app(
     )
 }
 
+/// A DnaFile made of just the one test zome
+pub async fn single_zome_dna_file(name: &str, uuid: &str, zome: TestWasm) -> DnaFile {
+    DnaFile::new(
+        DnaDef {
+            name: name.to_string(),
+            uuid: uuid.to_string(),
+            properties: SerializedBytes::try_from(()).unwrap(),
+            zomes: vec![zome.into()].into(),
+        },
+        vec![zome.into()],
+    )
+    .await
+    .unwrap()
+}
+
+/// A conductor built with `config` whose mock DnaStore always hands out `dna_file`,
+/// with nothing installed yet.
+/// Returns the tmpdirs to keep alive, the root directory of the conductor's
+/// environments and the conductor's handle.
+pub async fn setup_conductor_with_dna(
+    dna_file: &DnaFile,
+    config: ConductorConfig,
+) -> (Vec<Arc<TempDir>>, PathBuf, ConductorHandle) {
+    let mut dna_store = MockDnaStore::new();
+    dna_store.expect_get().return_const(Some(dna_file.clone()));
+    dna_store.expect_add_dnas::<Vec<_>>().return_const(());
+    dna_store.expect_add_entry_defs::<Vec<_>>().return_const(());
+    dna_store.expect_get_entry_def().return_const(None);
+
+    let test_env = test_conductor_env();
+    let root_env_dir = test_env.tmpdir.path().to_path_buf();
+    let wasm_env = test_wasm_env();
+    let p2p_env = test_p2p_env();
+    let handle = ConductorBuilder::with_mock_dna_store(dna_store)
+        .config(config)
+        .test(test_env, wasm_env.env(), p2p_env.env())
+        .await
+        .unwrap();
+
+    (
+        vec![wasm_env.tmpdir(), p2p_env.tmpdir()],
+        root_env_dir,
+        handle,
+    )
+}
+
+/// Shut the conductor down and wait for it to finish
+pub async fn shutdown_conductor(handle: ConductorHandle) {
+    let shutdown = handle.take_shutdown_handle().await.unwrap();
+    handle.shutdown().await;
+    shutdown.await.unwrap();
+}
+
 /// If HC_WASM_CACHE_PATH is set warm the cache
 pub fn warm_wasm_tests() {
     if let Some(_path) = std::env::var_os("HC_WASM_CACHE_PATH") {
@@ -293,5 +355,6 @@ where
         fn_name: func.into(),
         payload: ExternInput::new(payload.try_into()?),
         provenance: cell_id.agent_pubkey().clone(),
+        durability: Durability::Sync,
     })
 }
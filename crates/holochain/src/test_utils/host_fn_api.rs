@@ -154,7 +154,7 @@ pub async fn commit_entry<'env, E: Into<entry_def::EntryDefId>>(
     let workspace = CallZomeWorkspace::new(env.clone().into()).unwrap();
     let workspace_lock = CallZomeWorkspaceLock::new(workspace);
 
-    let input = CreateInput::new((entry_def_id.into(), entry));
+    let input = CreateInput::new((entry_def_id.into(), entry, None));
 
     let output = {
         let host_access = ZomeCallHostAccess::new(
@@ -177,7 +177,7 @@ pub async fn commit_entry<'env, E: Into<entry_def::EntryDefId>>(
         .with_commit(|writer| workspace.flush_to_txn_ref(writer))
         .unwrap();
 
-    output.into_inner()
+    output.into_inner().unwrap()
 }
 
 pub async fn delete_entry<'env>(
@@ -197,7 +197,7 @@ pub async fn delete_entry<'env>(
     let workspace = CallZomeWorkspace::new(env.clone().into()).unwrap();
     let workspace_lock = CallZomeWorkspaceLock::new(workspace);
 
-    let input = DeleteInput::new(hash);
+    let input = DeleteInput::new((hash, None));
 
     let output = {
         let host_access = ZomeCallHostAccess::new(
@@ -225,7 +225,7 @@ pub async fn delete_entry<'env>(
         .with_commit(|writer| workspace.flush_to_txn_ref(writer))
         .unwrap();
 
-    output.into_inner()
+    output.into_inner().unwrap()
 }
 
 pub async fn update_entry<'env, E: Into<entry_def::EntryDefId>>(
@@ -247,7 +247,7 @@ pub async fn update_entry<'env, E: Into<entry_def::EntryDefId>>(
     let workspace = CallZomeWorkspace::new(env.clone().into()).unwrap();
     let workspace_lock = CallZomeWorkspaceLock::new(workspace);
 
-    let input = UpdateInput::new((entry_def_id.into(), entry, original_header_hash));
+    let input = UpdateInput::new((entry_def_id.into(), entry, original_header_hash, None));
 
     let output = {
         let host_access = ZomeCallHostAccess::new(
@@ -270,7 +270,7 @@ pub async fn update_entry<'env, E: Into<entry_def::EntryDefId>>(
         .with_commit(|writer| workspace.flush_to_txn_ref(writer))
         .unwrap();
 
-    output.into_inner()
+    output.into_inner().unwrap()
 }
 
 pub async fn get(
@@ -370,7 +370,7 @@ pub async fn create_link<'env>(
     let workspace = CallZomeWorkspace::new(env.clone().into()).unwrap();
     let workspace_lock = CallZomeWorkspaceLock::new(workspace);
 
-    let input = CreateLinkInput::new((base.clone(), target.clone(), link_tag));
+    let input = CreateLinkInput::new((base.clone(), target.clone(), link_tag, None));
 
     let output = {
         let host_access = ZomeCallHostAccess::new(
@@ -393,7 +393,7 @@ pub async fn create_link<'env>(
         .with_commit(|writer| workspace.flush_to_txn_ref(writer))
         .unwrap();
 
-    output.into_inner()
+    output.into_inner().unwrap()
 }
 
 pub async fn delete_link<'env>(
@@ -413,7 +413,7 @@ pub async fn delete_link<'env>(
     let workspace = CallZomeWorkspace::new(env.clone().into()).unwrap();
     let workspace_lock = CallZomeWorkspaceLock::new(workspace);
 
-    let input = DeleteLinkInput::new(link_add_hash);
+    let input = DeleteLinkInput::new((link_add_hash, None));
 
     let output = {
         let host_access = ZomeCallHostAccess::new(
@@ -436,7 +436,7 @@ pub async fn delete_link<'env>(
         .with_commit(|writer| workspace.flush_to_txn_ref(writer))
         .unwrap();
 
-    output.into_inner()
+    output.into_inner().unwrap()
 }
 
 pub async fn get_links<'env>(
@@ -0,0 +1,407 @@
+//! A high level harness for tests which run apps on one or more conductors.
+//!
+//! A lone conductor spawns its own network with no transport to others.
+//! The conductors of a [TestConductorBatch] share an in-process network,
+//! so cells on different conductors of one batch see each other's data.
+//!
+//! ```ignore
+//! let conductor = TestConductor::new().await;
+//! let dna = test_dna_file(vec![TestWasm::Create]).await;
+//! let alice = conductor
+//!     .setup_app("alice", fake_agent_pubkey_1(), &[dna])
+//!     .await;
+//! let header_hash: HeaderHash = alice.cells()[0]
+//!     .call(TestWasm::Create, "create_entry", ())
+//!     .await;
+//! conductor.consistency().await;
+//! conductor.shutdown().await;
+//! ```
+
+use super::{conductor_setup::ConductorCallData, host_fn_api::CallData, new_invocation};
+use crate::{
+    conductor::{
+        config::{AdminInterfaceConfig, ConductorConfig, InterfaceDriver},
+        p2p_store::AgentKv,
+        ConductorBuilder, ConductorHandle,
+    },
+    core::{
+        queue_consumer::InitialQueueTriggers,
+        state::{chain_sequence::ChainSequenceBuf, dht_op_integration::AuthoredDhtOpsStore},
+        workflow::incoming_dht_ops_workflow::IncomingDhtOpsWorkspace,
+    },
+};
+use fallible_iterator::FallibleIterator;
+use futures::future::select_all;
+use holo_hash::AgentPubKey;
+use holochain_p2p::{kitsune_p2p::config::KitsuneP2pConfig, HolochainP2pCell};
+use holochain_serialized_bytes::{SerializedBytes, SerializedBytesError};
+use holochain_state::{
+    buffer::{KvBufFresh, KvStoreT},
+    db::{GetDb, AUTHORED_DHT_OPS},
+    env::{EnvironmentWrite, ReadManager, WriteManager},
+    error::DatabaseError,
+    fresh_reader_test,
+    test_utils::{test_conductor_env, test_p2p_env, test_wasm_env, TestEnvironment},
+};
+use holochain_types::{
+    app::InstalledCell,
+    cell::CellId,
+    dna::{DnaDef, DnaFile},
+};
+use holochain_wasm_test_utils::TestWasm;
+use holochain_zome_types::{zome::ZomeName, ZomeCallResponse};
+use std::{
+    convert::{TryFrom, TryInto},
+    fmt::Debug,
+    ops::Deref,
+    sync::Arc,
+    time::Duration,
+};
+use tempdir::TempDir;
+
+/// How long [TestConductor::consistency] waits before giving up
+const CONSISTENCY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Make a DnaFile from some test wasms
+pub async fn test_dna_file(zomes: Vec<TestWasm>) -> DnaFile {
+    DnaFile::new(
+        DnaDef {
+            name: "test_conductor".to_string(),
+            uuid: "ba1d046d-ce29-4778-914b-47e6010d2faf".to_string(),
+            properties: SerializedBytes::try_from(()).unwrap(),
+            zomes: zomes.clone().into_iter().map(Into::into).collect(),
+        },
+        zomes.into_iter().map(Into::into),
+    )
+    .await
+    .unwrap()
+}
+
+/// A batch of conductors running side by side.
+/// The conductors share an in-process network of their own,
+/// so cells on any of them reach each other as if on one conductor,
+/// but never reach the conductors of another batch.
+pub struct TestConductorBatch(Vec<TestConductor>);
+
+impl TestConductorBatch {
+    /// Start `n` conductors on a new in-process network
+    pub async fn new(n: usize) -> Self {
+        let network = format!("test_conductor_batch_{}", nanoid::nanoid!());
+        let mut conductors = Vec::with_capacity(n);
+        for _ in 0..n {
+            conductors.push(TestConductor::on_network(Some(network.clone()), false).await);
+        }
+        Self(conductors)
+    }
+
+    /// Copy every conductor's agent info into every other conductor's
+    /// peer store, so each can look up the others' agents
+    pub async fn exchange_peer_info(&self) {
+        let mut all_info = Vec::new();
+        for conductor in self.0.iter() {
+            let store = AgentKv::new(conductor.p2p_env.clone().into()).unwrap();
+            let env = conductor.p2p_env.guard();
+            let reader = env.reader().unwrap();
+            let info: Vec<_> = store
+                .as_store_ref()
+                .iter(&reader)
+                .unwrap()
+                .map(|(_, info)| Ok(info))
+                .collect()
+                .unwrap();
+            all_info.extend(info);
+        }
+        for conductor in self.0.iter() {
+            let store = AgentKv::new(conductor.p2p_env.clone().into()).unwrap();
+            conductor
+                .p2p_env
+                .guard()
+                .with_commit(|writer| {
+                    for info in all_info.iter() {
+                        store.as_store_ref().put(writer, &info.into(), info)?;
+                    }
+                    Ok::<_, DatabaseError>(())
+                })
+                .unwrap();
+        }
+    }
+
+    /// Wait until every op authored by any cell has been integrated by
+    /// every cell on every conductor.
+    /// Panics if this doesn't happen within a reasonable time.
+    pub async fn consistency(&self) {
+        let mut cells = Vec::new();
+        for conductor in self.0.iter() {
+            cells.extend(conductor.cells().await);
+        }
+        wait_for_consistency(cells).await;
+    }
+
+    /// Shut down every conductor
+    pub async fn shutdown(self) {
+        for conductor in self.0 {
+            conductor.shutdown().await;
+        }
+    }
+}
+
+impl Deref for TestConductorBatch {
+    type Target = [TestConductor];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// A conductor with test environments, which are removed when it's dropped
+pub struct TestConductor {
+    handle: ConductorHandle,
+    p2p_env: EnvironmentWrite,
+    _tmpdirs: Vec<Arc<TempDir>>,
+}
+
+impl TestConductor {
+    /// Start a conductor with an admin interface on an arbitrary port
+    pub async fn new() -> Self {
+        Self::on_network(None, false).await
+    }
+
+    /// Start a conductor whose cells of the same Dna share one DHT environment
+    pub async fn new_shared_dht() -> Self {
+        Self::on_network(None, true).await
+    }
+
+    /// Start a conductor which joins its spaces on the named
+    /// in-process network, if any
+    async fn on_network(in_process_network: Option<String>, shared_dht_spaces: bool) -> Self {
+        let test_env = test_conductor_env();
+        let TestEnvironment {
+            env: wasm_env,
+            tmpdir: wasm_tmpdir,
+        } = test_wasm_env();
+        let TestEnvironment {
+            env: p2p_env,
+            tmpdir: p2p_tmpdir,
+        } = test_p2p_env();
+        let tmpdir = test_env.tmpdir.clone();
+
+        let handle = ConductorBuilder::new()
+            .config(ConductorConfig {
+                admin_interfaces: Some(vec![AdminInterfaceConfig {
+                    driver: InterfaceDriver::Websocket { port: 0 },
+                }]),
+                kitsune_p2p: KitsuneP2pConfig {
+                    in_process_network,
+                    ..Default::default()
+                },
+                shared_dht_spaces,
+                ..Default::default()
+            })
+            .test(test_env, wasm_env, p2p_env.clone())
+            .await
+            .unwrap();
+
+        Self {
+            handle,
+            p2p_env,
+            _tmpdirs: vec![tmpdir, wasm_tmpdir, p2p_tmpdir],
+        }
+    }
+
+    /// Install the Dnas, then install and activate an app with one cell
+    /// per Dna for the agent
+    pub async fn setup_app(&self, app_id: &str, agent: AgentPubKey, dnas: &[DnaFile]) -> TestApp {
+        let mut installed = Vec::with_capacity(dnas.len());
+        for (i, dna) in dnas.iter().enumerate() {
+            self.handle.install_dna(dna.clone()).await.unwrap();
+            let cell_id = CellId::new(dna.dna_hash().clone(), agent.clone());
+            let cell_handle = format!("{}-{}", app_id, i);
+            installed.push((InstalledCell::new(cell_id, cell_handle), None));
+        }
+        super::install_app(app_id, installed.clone(), self.handle.clone()).await;
+
+        let mut cells = Vec::with_capacity(dnas.len());
+        for ((installed_cell, _), dna) in installed.iter().zip(dnas) {
+            let cell_id = installed_cell.as_id();
+            cells.push(TestCell {
+                call_data: ConductorCallData::new(cell_id, &self.handle, dna).await,
+                handle: self.handle.clone(),
+            });
+        }
+        TestApp { cells }
+    }
+
+    /// The conductor's handle
+    pub fn handle(&self) -> &ConductorHandle {
+        &self.handle
+    }
+
+    /// Wait until every op authored by any cell on this conductor has been
+    /// integrated by every cell on it.
+    /// Panics if this doesn't happen within a reasonable time.
+    pub async fn consistency(&self) {
+        wait_for_consistency(self.cells().await).await;
+    }
+
+    async fn cells(&self) -> Vec<(CellId, EnvironmentWrite, InitialQueueTriggers)> {
+        let mut cells = Vec::new();
+        for cell_id in self.handle.list_cell_ids().await.unwrap() {
+            let env = self.handle.get_cell_env(&cell_id).await.unwrap();
+            let triggers = self.handle.get_cell_triggers(&cell_id).await.unwrap();
+            cells.push((cell_id, env, triggers));
+        }
+        cells
+    }
+
+    /// Shut down the conductor and wait for its tasks to end
+    pub async fn shutdown(self) {
+        let shutdown = self.handle.take_shutdown_handle().await.unwrap();
+        self.handle.shutdown().await;
+        shutdown.await.unwrap();
+    }
+}
+
+/// The cells of an installed app, in the order of its Dnas
+pub struct TestApp {
+    cells: Vec<TestCell>,
+}
+
+impl TestApp {
+    /// The app's cells
+    pub fn cells(&self) -> &[TestCell] {
+        &self.cells
+    }
+}
+
+/// A cell of an installed app
+pub struct TestCell {
+    call_data: ConductorCallData,
+    handle: ConductorHandle,
+}
+
+impl TestCell {
+    /// The cell's id
+    pub fn cell_id(&self) -> &CellId {
+        &self.call_data.cell_id
+    }
+
+    /// The cell's environment
+    pub fn env(&self) -> &EnvironmentWrite {
+        &self.call_data.env
+    }
+
+    /// The cell's network
+    pub fn network(&self) -> &HolochainP2pCell {
+        &self.call_data.network
+    }
+
+    /// Everything needed to call the cell's host functions directly
+    pub fn call_data<Z: Into<ZomeName>>(&self, zome_name: Z) -> CallData {
+        self.call_data.call_data(zome_name)
+    }
+
+    /// Call a zome function through the conductor as the cell's agent.
+    /// Panics with the function name if the call fails or its output
+    /// doesn't decode.
+    pub async fn call<Z, I, O>(&self, zome_name: Z, fn_name: &str, payload: I) -> O
+    where
+        Z: Into<ZomeName>,
+        I: TryInto<SerializedBytes, Error = SerializedBytesError>,
+        O: TryFrom<SerializedBytes, Error = SerializedBytesError> + Debug,
+    {
+        let invocation = new_invocation(self.cell_id(), fn_name, payload, zome_name)
+            .unwrap_or_else(|e| panic!("Couldn't serialize the input to {}: {:?}", fn_name, e));
+        let output = match self.handle.call_zome(invocation).await {
+            Ok(Ok(ZomeCallResponse::Ok(output))) => output.into_inner(),
+            Ok(Ok(ZomeCallResponse::Unauthorized)) => {
+                panic!("The call to {} was unauthorized", fn_name)
+            }
+            Ok(Err(e)) => panic!("The call to {} failed: {:?}", fn_name, e),
+            Err(e) => panic!("The conductor couldn't call {}: {:?}", fn_name, e),
+        };
+        O::try_from(output)
+            .unwrap_or_else(|e| panic!("Couldn't deserialize the output of {}: {:?}", fn_name, e))
+    }
+}
+
+/// Wait until every cell has integrated every op authored by any of them.
+/// Each check is made when a cell's integration workflow has run,
+/// rather than by polling.
+async fn wait_for_consistency(mut cells: Vec<(CellId, EnvironmentWrite, InitialQueueTriggers)>) {
+    let mut notifications: Vec<_> = cells
+        .iter_mut()
+        .map(|(_, _, triggers)| {
+            // Make sure everything committed so far has been turned into ops
+            triggers.produce_dht_ops.trigger();
+            triggers.subscribe_to_integration()
+        })
+        .collect();
+
+    let wait = async {
+        loop {
+            let all_authored = cells.iter().map(|(_, env, _)| authored_count(env)).sum();
+            if cells
+                .iter()
+                .all(|(_, env, _)| is_consistent(env, all_authored))
+            {
+                return;
+            }
+            // Wait for any cell to integrate something
+            select_all(notifications.iter_mut().map(|n| Box::pin(n.recv()))).await;
+        }
+    };
+    if tokio::time::timeout(CONSISTENCY_TIMEOUT, wait)
+        .await
+        .is_err()
+    {
+        let counts: Vec<_> = cells
+            .iter()
+            .map(|(cell_id, env, _)| (cell_id.clone(), integrated_count(env)))
+            .collect();
+        panic!(
+            "Cells did not reach consistency within {:?}. Integrated op counts: {:?}",
+            CONSISTENCY_TIMEOUT, counts
+        );
+    }
+}
+
+fn authored_count(env: &EnvironmentWrite) -> usize {
+    let authored: AuthoredDhtOpsStore =
+        KvBufFresh::new(env.clone().into(), env.get_db(&*AUTHORED_DHT_OPS).unwrap());
+    fresh_reader_test!(env, |r| authored.iter(&r).unwrap().count().unwrap())
+}
+
+/// How many ops the cell has integrated
+pub fn integrated_count(env: &EnvironmentWrite) -> usize {
+    let workspace = IncomingDhtOpsWorkspace::new(env.clone().into()).unwrap();
+    fresh_reader_test!(env, |r| workspace
+        .integrated_dht_ops
+        .iter(&r)
+        .unwrap()
+        .count()
+        .unwrap())
+}
+
+/// Has the cell turned all its commits into ops, and integrated
+/// every authored op and nothing else
+fn is_consistent(env: &EnvironmentWrite, all_authored: usize) -> bool {
+    let sequence = ChainSequenceBuf::new(env.clone().into()).unwrap();
+    let workspace = IncomingDhtOpsWorkspace::new(env.clone().into()).unwrap();
+    fresh_reader_test!(env, |r| {
+        sequence.count_items_with_incomplete_dht_ops(&r).unwrap() == 0
+            && workspace
+                .validation_limbo
+                .iter(&r)
+                .unwrap()
+                .count()
+                .unwrap()
+                == 0
+            && workspace
+                .integration_limbo
+                .iter(&r)
+                .unwrap()
+                .count()
+                .unwrap()
+                == 0
+    }) && integrated_count(env) == all_authored
+}
@@ -94,7 +94,6 @@ async fn get_links() -> SourceChainResult<()> {
 
     // Pass in stores as references
     let mut cascade = Cascade::new(
-        env.clone().into(),
         &source_chain.elements(),
         &meta_authored,
         &element_vault,
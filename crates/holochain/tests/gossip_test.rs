@@ -9,6 +9,7 @@ use holochain::{
     fixt::*,
     test_utils::{install_app, setup_app},
 };
+use holochain_state::env::Durability;
 use holochain_types::app::InstalledCell;
 use holochain_types::cell::CellId;
 use holochain_types::dna::DnaDef;
@@ -160,5 +161,6 @@ where
         fn_name: func.into(),
         payload: ExternInput::new(payload.try_into()?),
         provenance: cell_id.agent_pubkey().clone(),
+        durability: Durability::Sync,
     })
 }
@@ -8,6 +8,7 @@ use holochain::conductor::{
 };
 use holochain::core::ribosome::ZomeCallInvocation;
 use holochain::fixt::*;
+use holochain_state::env::Durability;
 use holochain_state::test_utils::test_p2p_env;
 use holochain_state::test_utils::{test_conductor_env, test_wasm_env, TestEnvironment};
 use holochain_types::app::InstalledCell;
@@ -130,6 +131,7 @@ async fn ser_regression_test() {
         fn_name: "create_channel".into(),
         payload: ExternInput::new(channel.try_into().unwrap()),
         provenance: alice_agent_id.clone(),
+        durability: Durability::Sync,
     };
 
     let request = Box::new(invocation.clone());
@@ -167,6 +169,7 @@ async fn ser_regression_test() {
         fn_name: "create_message".into(),
         payload: ExternInput::new(message.try_into().unwrap()),
         provenance: alice_agent_id.clone(),
+        durability: Durability::Sync,
     };
 
     let request = Box::new(invocation.clone());
@@ -220,7 +223,7 @@ pub async fn setup_app(
 
     conductor_handle
         .clone()
-        .install_app("test app".to_string(), cell_data)
+        .install_app("test app".to_string(), cell_data, true)
         .await
         .unwrap();
 
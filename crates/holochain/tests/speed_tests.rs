@@ -25,6 +25,7 @@ use holochain::conductor::{
 };
 use holochain::fixt::*;
 use holochain::{core::ribosome::ZomeCallInvocation, test_utils::warm_wasm_tests};
+use holochain_state::env::Durability;
 use holochain_state::test_utils::test_p2p_env;
 use holochain_state::test_utils::{test_conductor_env, test_wasm_env, TestEnvironment};
 use holochain_types::app::InstalledCell;
@@ -229,6 +230,7 @@ async fn speed_test(n: Option<usize>) -> TestEnvironment {
             fn_name: func.into(),
             payload: ExternInput::new(payload.try_into()?),
             provenance: cell_id.agent_pubkey().clone(),
+            durability: Durability::Sync,
         })
     }
 
@@ -351,7 +353,7 @@ pub async fn setup_app(
 
     conductor_handle
         .clone()
-        .install_app("test app".to_string(), cell_data)
+        .install_app("test app".to_string(), cell_data, true)
         .await
         .unwrap();
 
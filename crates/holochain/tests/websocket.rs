@@ -7,6 +7,10 @@ use holochain::{
         api::{AdminRequest, AdminResponse, AppRequest, AppResponse},
         config::*,
         error::ConductorError,
+        interface::{
+            signal_queue::{SignalQueueStats, DEFAULT_SIGNAL_QUEUE_DEPTH},
+            InterfaceInfo, InterfaceType,
+        },
         Conductor,
     },
     core::signal::Signal,
@@ -64,27 +68,24 @@ pub async fn check_started(holochain: &mut Child) {
 }
 
 fn create_config(port: u16, environment_path: PathBuf) -> ConductorConfig {
+    // Every other field keeps its default, so new config fields
+    // don't need to be added here
     ConductorConfig {
         admin_interfaces: Some(vec![AdminInterfaceConfig {
             driver: InterfaceDriver::Websocket { port },
         }]),
         environment_path: environment_path.into(),
-        network: None,
-        signing_service_uri: None,
-        encryption_service_uri: None,
-        decryption_service_uri: None,
-        dpki: None,
         passphrase_service: Some(PassphraseServiceConfig::FromConfig {
             passphrase: "password".into(),
         }),
-        keystore_path: None,
         use_dangerous_test_keystore: true,
+        ..Default::default()
     }
 }
 
 pub fn write_config(mut path: PathBuf, config: &ConductorConfig) -> PathBuf {
     path.push("conductor_config.toml");
-    std::fs::write(path.clone(), toml::to_string(&config).unwrap()).unwrap();
+    std::fs::write(path.clone(), config.to_toml().unwrap()).unwrap();
     path
 }
 
@@ -163,6 +164,7 @@ async fn call_admin() {
         dnas: vec![dna_payload],
         app_id: "test".to_string(),
         agent_key,
+        allow_multiple_agents: false,
     };
     let request = AdminRequest::InstallApp(Box::new(payload));
     let response = client.request(request);
@@ -315,6 +317,7 @@ async fn call_zome() {
         dnas: vec![dna_payload],
         app_id: "test".to_string(),
         agent_key,
+        allow_multiple_agents: false,
     };
     let request = AdminRequest::InstallApp(Box::new(payload));
     let response = client.request(request);
@@ -410,6 +413,7 @@ async fn emit_signals() {
         dnas: vec![dna_payload],
         app_id: "test".to_string(),
         agent_key: agent_key.clone(),
+        allow_multiple_agents: false,
     };
     let request = AdminRequest::InstallApp(Box::new(payload));
     let response = admin_tx.request(request);
@@ -492,6 +496,7 @@ async fn conductor_admin_interface_runs_from_config() -> Result<()> {
         dnas: vec![dna_payload],
         app_id: "test".to_string(),
         agent_key,
+        allow_multiple_agents: false,
     };
     let request = AdminRequest::InstallApp(Box::new(payload));
     let response = client.request(request).await;
@@ -549,6 +554,7 @@ async fn conductor_admin_interface_ends_with_shutdown() -> Result<()> {
         dnas: vec![dna_payload],
         app_id: "test".to_string(),
         agent_key,
+        allow_multiple_agents: false,
     };
     let request = AdminRequest::InstallApp(Box::new(payload));
 
@@ -562,3 +568,65 @@ async fn conductor_admin_interface_ends_with_shutdown() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(threaded_scheduler)]
+async fn conductor_lists_interfaces_and_their_clients() -> Result<()> {
+    observability::test_run().ok();
+    let tmp_dir = TempDir::new("conductor_cfg").unwrap();
+    let environment_path = tmp_dir.path().to_path_buf();
+    let config = create_config(0, environment_path);
+    let conductor_handle = Conductor::builder().config(config).build().await?;
+    let admin_port = admin_port(&conductor_handle).await;
+    let app_port = conductor_handle.clone().add_app_interface(0).await?;
+
+    let expected = |admin_clients, app_clients| {
+        vec![
+            InterfaceInfo {
+                port: admin_port,
+                interface_type: InterfaceType::Admin,
+                connected_client_count: admin_clients,
+                signal_queue: None,
+            },
+            InterfaceInfo {
+                port: app_port,
+                interface_type: InterfaceType::App,
+                connected_client_count: app_clients,
+                signal_queue: Some(SignalQueueStats {
+                    interface_id: "interface-0".into(),
+                    depth: DEFAULT_SIGNAL_QUEUE_DEPTH,
+                    queued: 0,
+                    forwarded: 0,
+                    dropped: 0,
+                }),
+            },
+        ]
+    };
+    assert_eq!(
+        conductor_handle.list_app_interfaces().await?,
+        expected(0, 0)
+    );
+
+    let _admin_client = websocket_client(&conductor_handle).await?;
+    let _app_clients = (
+        websocket_client_by_port(app_port).await?,
+        websocket_client_by_port(app_port).await?,
+    );
+    // Connections are counted once the interface has accepted them
+    let mut interfaces = Vec::new();
+    for _ in 0..50 {
+        interfaces = conductor_handle.list_app_interfaces().await?;
+        if interfaces == expected(1, 2) {
+            break;
+        }
+        tokio::time::delay_for(Duration::from_millis(100)).await;
+    }
+    assert_eq!(interfaces, expected(1, 2));
+
+    conductor_handle.shutdown().await;
+    assert_matches!(
+        conductor_handle.list_app_interfaces().await,
+        Err(ConductorError::ShuttingDown)
+    );
+
+    Ok(())
+}
@@ -4,7 +4,7 @@
 use holo_hash::*;
 use holochain_serialized_bytes::prelude::*;
 use holochain_zome_types::zome::FunctionName;
-use holochain_zome_types::{capability::CapSecret, zome::ZomeName};
+use holochain_zome_types::{capability::CapSecret, signature::Signature, zome::ZomeName};
 use std::sync::Arc;
 
 mod types;
@@ -15,6 +15,7 @@ mod spawn;
 use ghost_actor::dependencies::{tracing, tracing_futures::Instrument};
 use holochain_types::{element::GetElementResponse, validate::ValidationPackageResponse};
 use holochain_types::{
+    index::{GetIndexedResponse, IndexKey, IndexOp},
     link::{GetLinksResponse, WireLinkMetaKey},
     metadata::MetadataSet,
 };
@@ -37,6 +38,9 @@ pub trait HolochainP2pCellT {
     /// The p2p module must be informed at runtime which dna/agent pairs it should be tracking.
     async fn join(&mut self) -> actor::HolochainP2pResult<()>;
 
+    /// Track this cell when its dht data is shared with the other cells of its dna.
+    async fn join_shared(&mut self) -> actor::HolochainP2pResult<()>;
+
     /// If a cell is deactivated, we'll need to \"leave\" the network module as well.
     async fn leave(&mut self) -> actor::HolochainP2pResult<()>;
 
@@ -57,6 +61,17 @@ pub trait HolochainP2pCellT {
         request_validation_receipt: bool,
         dht_hash: holo_hash::AnyDhtHash,
         ops: Vec<(holo_hash::DhtOpHash, holochain_types::dht_op::DhtOp)>,
+        requirements: Vec<(
+            holo_hash::DhtOpHash,
+            holochain_types::dht_op::OpRequirements,
+        )>,
+        timeout_ms: Option<u64>,
+    ) -> actor::HolochainP2pResult<()>;
+
+    /// Publish a change to an index to the authorities for its key.
+    async fn publish_index(
+        &mut self,
+        index_op: IndexOp,
         timeout_ms: Option<u64>,
     ) -> actor::HolochainP2pResult<()>;
 
@@ -74,6 +89,13 @@ pub trait HolochainP2pCellT {
         options: actor::GetOptions,
     ) -> actor::HolochainP2pResult<Vec<GetElementResponse>>;
 
+    /// Get the element a header hash refers to from the header's authorities.
+    async fn get_element_by_header(
+        &mut self,
+        header_hash: HeaderHash,
+        options: actor::GetOptions,
+    ) -> actor::HolochainP2pResult<Vec<GetElementResponse>>;
+
     /// Get metadata from the DHT.
     async fn get_meta(
         &mut self,
@@ -88,12 +110,46 @@ pub trait HolochainP2pCellT {
         options: actor::GetLinksOptions,
     ) -> actor::HolochainP2pResult<Vec<GetLinksResponse>>;
 
-    /// Send a validation receipt to a remote node.
+    /// Get the entries indexed under a key from the DHT.
+    async fn get_indexed(
+        &mut self,
+        index_key: IndexKey,
+        options: actor::GetIndexedOptions,
+    ) -> actor::HolochainP2pResult<Vec<GetIndexedResponse>>;
+
+    /// Send a validation receipt, or a bundle of receipts, to a remote node.
     async fn send_validation_receipt(
         &mut self,
         to_agent: AgentPubKey,
         receipt: SerializedBytes,
     ) -> actor::HolochainP2pResult<()>;
+
+    /// Start a countersigning session with a remote agent by sending it
+    /// the preflight both agents will sign.
+    async fn countersigning_request(
+        &mut self,
+        to_agent: AgentPubKey,
+        preflight: SerializedBytes,
+    ) -> actor::HolochainP2pResult<SerializedBytes>;
+
+    /// Return our partial signature of a preflight to the agent which sent it.
+    async fn countersigning_response(
+        &mut self,
+        to_agent: AgentPubKey,
+        partial_signature: Signature,
+    ) -> actor::HolochainP2pResult<()>;
+
+    /// Send our full arc claim to a remote node, which may respond
+    /// with its own signed agent info.
+    async fn request_arc_sync(
+        &mut self,
+        to_agent: AgentPubKey,
+        from_arc: dht_arc::DhtArc,
+        from_info: kitsune_p2p::agent_store::AgentInfoSigned,
+    ) -> actor::HolochainP2pResult<Option<kitsune_p2p::agent_store::AgentInfoSigned>>;
+
+    /// Report the gossip progress of this cell.
+    async fn network_info(&mut self) -> actor::HolochainP2pResult<actor::NetworkInfo>;
 }
 
 /// A wrapper around HolochainP2pSender that partially applies the dna_hash / agent_pub_key.
@@ -124,6 +180,13 @@ impl HolochainP2pCellT for HolochainP2pCell {
             .await
     }
 
+    /// Track this cell when its dht data is shared with the other cells of its dna.
+    async fn join_shared(&mut self) -> actor::HolochainP2pResult<()> {
+        self.sender
+            .join_shared((*self.dna_hash).clone(), (*self.from_agent).clone())
+            .await
+    }
+
     /// If a cell is deactivated, we'll need to \"leave\" the network module as well.
     async fn leave(&mut self) -> actor::HolochainP2pResult<()> {
         self.sender
@@ -159,6 +222,10 @@ impl HolochainP2pCellT for HolochainP2pCell {
         request_validation_receipt: bool,
         dht_hash: holo_hash::AnyDhtHash,
         ops: Vec<(holo_hash::DhtOpHash, holochain_types::dht_op::DhtOp)>,
+        requirements: Vec<(
+            holo_hash::DhtOpHash,
+            holochain_types::dht_op::OpRequirements,
+        )>,
         timeout_ms: Option<u64>,
     ) -> actor::HolochainP2pResult<()> {
         self.sender
@@ -168,6 +235,23 @@ impl HolochainP2pCellT for HolochainP2pCell {
                 request_validation_receipt,
                 dht_hash,
                 ops,
+                requirements,
+                timeout_ms,
+            )
+            .await
+    }
+
+    /// Publish a change to an index to the authorities for its key.
+    async fn publish_index(
+        &mut self,
+        index_op: IndexOp,
+        timeout_ms: Option<u64>,
+    ) -> actor::HolochainP2pResult<()> {
+        self.sender
+            .publish_index(
+                (*self.dna_hash).clone(),
+                (*self.from_agent).clone(),
+                index_op,
                 timeout_ms,
             )
             .await
@@ -206,6 +290,23 @@ impl HolochainP2pCellT for HolochainP2pCell {
             .await
     }
 
+    /// Get the element a header hash refers to from the header's authorities.
+    async fn get_element_by_header(
+        &mut self,
+        header_hash: HeaderHash,
+        options: actor::GetOptions,
+    ) -> actor::HolochainP2pResult<Vec<GetElementResponse>> {
+        self.sender
+            .get_element_by_header(
+                (*self.dna_hash).clone(),
+                (*self.from_agent).clone(),
+                header_hash,
+                options,
+            )
+            .instrument(tracing::debug_span!("HolochainP2p::get_element_by_header"))
+            .await
+    }
+
     /// Get metadata from the DHT.
     async fn get_meta(
         &mut self,
@@ -238,7 +339,23 @@ impl HolochainP2pCellT for HolochainP2pCell {
             .await
     }
 
-    /// Send a validation receipt to a remote node.
+    /// Get the entries indexed under a key from the DHT.
+    async fn get_indexed(
+        &mut self,
+        index_key: IndexKey,
+        options: actor::GetIndexedOptions,
+    ) -> actor::HolochainP2pResult<Vec<GetIndexedResponse>> {
+        self.sender
+            .get_indexed(
+                (*self.dna_hash).clone(),
+                (*self.from_agent).clone(),
+                index_key,
+                options,
+            )
+            .await
+    }
+
+    /// Send a validation receipt, or a bundle of receipts, to a remote node.
     async fn send_validation_receipt(
         &mut self,
         to_agent: AgentPubKey,
@@ -253,6 +370,65 @@ impl HolochainP2pCellT for HolochainP2pCell {
             )
             .await
     }
+
+    /// Start a countersigning session with a remote agent by sending it
+    /// the preflight both agents will sign.
+    async fn countersigning_request(
+        &mut self,
+        to_agent: AgentPubKey,
+        preflight: SerializedBytes,
+    ) -> actor::HolochainP2pResult<SerializedBytes> {
+        self.sender
+            .countersigning_request(
+                (*self.dna_hash).clone(),
+                (*self.from_agent).clone(),
+                to_agent,
+                preflight,
+            )
+            .await
+    }
+
+    /// Return our partial signature of a preflight to the agent which sent it.
+    async fn countersigning_response(
+        &mut self,
+        to_agent: AgentPubKey,
+        partial_signature: Signature,
+    ) -> actor::HolochainP2pResult<()> {
+        self.sender
+            .countersigning_response(
+                (*self.dna_hash).clone(),
+                (*self.from_agent).clone(),
+                to_agent,
+                partial_signature,
+            )
+            .await
+    }
+
+    /// Send our full arc claim to a remote node, which may respond
+    /// with its own signed agent info.
+    async fn request_arc_sync(
+        &mut self,
+        to_agent: AgentPubKey,
+        from_arc: dht_arc::DhtArc,
+        from_info: kitsune_p2p::agent_store::AgentInfoSigned,
+    ) -> actor::HolochainP2pResult<Option<kitsune_p2p::agent_store::AgentInfoSigned>> {
+        self.sender
+            .request_arc_sync(
+                (*self.dna_hash).clone(),
+                (*self.from_agent).clone(),
+                to_agent,
+                from_arc,
+                from_info,
+            )
+            .await
+    }
+
+    /// Report the gossip progress of this cell.
+    async fn network_info(&mut self) -> actor::HolochainP2pResult<actor::NetworkInfo> {
+        self.sender
+            .network_info((*self.dna_hash).clone(), (*self.from_agent).clone())
+            .await
+    }
 }
 
 pub use kitsune_p2p::dht_arc;
@@ -1,11 +1,30 @@
 use crate::actor::*;
+use crate::compression::WireCompressionConfig;
 use crate::event::*;
+use kitsune_p2p::config::KitsuneP2pConfig;
 
 mod actor;
 use actor::*;
 
+/// Spawn a new HolochainP2p actor which compresses large payloads
+/// with the default [WireCompressionConfig].
+/// The config is passed on to the underlying KitsuneP2p actor.
+pub async fn spawn_holochain_p2p(
+    config: KitsuneP2pConfig,
+) -> HolochainP2pResult<(
+    ghost_actor::GhostSender<HolochainP2p>,
+    HolochainP2pEventReceiver,
+)> {
+    spawn_holochain_p2p_with_compression(config, WireCompressionConfig::default()).await
+}
+
 /// Spawn a new HolochainP2p actor.  Conductor will call this on initialization.
-pub async fn spawn_holochain_p2p() -> HolochainP2pResult<(
+/// The config is passed on to the underlying KitsuneP2p actor,
+/// and the compression config sets which payloads are compressed on the wire.
+pub async fn spawn_holochain_p2p_with_compression(
+    config: KitsuneP2pConfig,
+    compression: WireCompressionConfig,
+) -> HolochainP2pResult<(
     ghost_actor::GhostSender<HolochainP2p>,
     HolochainP2pEventReceiver,
 )> {
@@ -17,7 +36,10 @@ pub async fn spawn_holochain_p2p() -> HolochainP2pResult<(
 
     let sender = channel_factory.create_channel::<HolochainP2p>().await?;
 
-    tokio::task::spawn(builder.spawn(HolochainP2pActor::new(channel_factory, evt_send).await?));
+    tokio::task::spawn(
+        builder
+            .spawn(HolochainP2pActor::new(channel_factory, evt_send, config, compression).await?),
+    );
 
     Ok((sender, evt_recv))
 }
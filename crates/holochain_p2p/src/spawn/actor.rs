@@ -1,6 +1,6 @@
-use crate::{actor::*, event::*, *};
+use crate::{actor::*, compression::*, event::*, *};
 
-use futures::future::FutureExt;
+use futures::future::{Future, FutureExt};
 
 use crate::types::AgentPubKeyExt;
 
@@ -8,13 +8,14 @@ use ghost_actor::dependencies::{tracing, tracing_futures::Instrument};
 use holochain_types::{
     element::GetElementResponse, validate::ValidationPackageResponse, Timestamp,
 };
-use holochain_zome_types::zome::FunctionName;
+use holochain_zome_types::{signature::Signature, zome::FunctionName};
 use kitsune_p2p::actor::KitsuneP2pSender;
 use kitsune_p2p::agent_store::AgentInfoSigned;
 
 pub(crate) struct HolochainP2pActor {
     evt_sender: futures::channel::mpsc::Sender<HolochainP2pEvent>,
     kitsune_p2p: ghost_actor::GhostSender<kitsune_p2p::actor::KitsuneP2p>,
+    compression: WireCompression,
 }
 
 impl ghost_actor::GhostControlHandler for HolochainP2pActor {}
@@ -24,17 +25,147 @@ impl HolochainP2pActor {
     pub async fn new(
         channel_factory: ghost_actor::actor_builder::GhostActorChannelFactory<Self>,
         evt_sender: futures::channel::mpsc::Sender<HolochainP2pEvent>,
+        config: kitsune_p2p::config::KitsuneP2pConfig,
+        compression: WireCompressionConfig,
     ) -> HolochainP2pResult<Self> {
-        let (kitsune_p2p, kitsune_p2p_events) = kitsune_p2p::spawn_kitsune_p2p().await?;
+        let (kitsune_p2p, kitsune_p2p_events) = kitsune_p2p::spawn_kitsune_p2p(config).await?;
 
         channel_factory.attach_receiver(kitsune_p2p_events).await?;
 
         Ok(Self {
             evt_sender,
             kitsune_p2p,
+            compression: WireCompression::new(compression),
         })
     }
 
+    /// Send a request to one remote node, compressed if what we have learned
+    /// about it allows, see [WireCompression::request_single]
+    fn request_single(
+        &self,
+        class: &'static str,
+        dna_hash: DnaHash,
+        to_agent: AgentPubKey,
+        from_agent: AgentPubKey,
+        body: Vec<u8>,
+    ) -> impl Future<Output = HolochainP2pResult<Vec<u8>>> + Send + 'static {
+        let compression = self.compression.clone();
+        let kitsune_p2p = self.kitsune_p2p.clone();
+        let evt_sender = self.evt_sender.clone();
+        async move {
+            let peer = peer_wire(&evt_sender, &dna_hash, &from_agent, &to_agent).await;
+            let space = dna_hash.to_kitsune();
+            let (to, from) = (to_agent.to_kitsune(), from_agent.to_kitsune());
+            let (response, learned) = compression
+                .request_single(class, peer, body, |req| {
+                    kitsune_p2p.rpc_single(space.clone(), to.clone(), from.clone(), req)
+                })
+                .await?;
+            if let Some(learned) = learned {
+                learn_peer_wire(&evt_sender, dna_hash, from_agent, to_agent, learned).await;
+            }
+            Ok(response)
+        }
+    }
+
+    /// Send a request to the remote nodes kitsune picks for a basis,
+    /// compressed if every peer of the space is known to read our codec.
+    /// A node which rejects the compressed request is sent it again
+    /// uncompressed, on its own.
+    /// Returns the body of each response.
+    fn request_multi(
+        &self,
+        class: &'static str,
+        dna_hash: DnaHash,
+        from_agent: AgentPubKey,
+        mut input: kitsune_p2p::actor::RpcMulti,
+        body: Vec<u8>,
+    ) -> impl Future<Output = HolochainP2pResult<Vec<Vec<u8>>>> + Send + 'static {
+        let compression = self.compression.clone();
+        let kitsune_p2p = self.kitsune_p2p.clone();
+        let evt_sender = self.evt_sender.clone();
+        async move {
+            let peers = space_peer_wires(&evt_sender, &dna_hash, &from_agent).await;
+            let (payload, compressed) =
+                compression.seal_request(class, &body, compression.may_compress_all(&peers));
+            input.payload = payload;
+            let result = kitsune_p2p
+                .rpc_multi(input)
+                .instrument(tracing::debug_span!("rpc_multi"))
+                .await?;
+
+            let mut out = Vec::new();
+            for item in result {
+                let kitsune_p2p::actor::RpcMultiResponse { agent, response } = item;
+                let response = match compression.open(response) {
+                    Err(HolochainP2pError::UnsupportedWireCodec(_)) if compressed => {
+                        let to_agent = AgentPubKey::try_from_kitsune(&agent)?;
+                        let (payload, _) = compression.seal_request(class, &body, false);
+                        let response = kitsune_p2p
+                            .rpc_single(
+                                dna_hash.to_kitsune(),
+                                agent,
+                                from_agent.to_kitsune(),
+                                payload,
+                            )
+                            .await?;
+                        let rejected_by = compression.rejected_by();
+                        learn_peer_wire(
+                            &evt_sender,
+                            dna_hash.clone(),
+                            from_agent.clone(),
+                            to_agent,
+                            rejected_by,
+                        )
+                        .await;
+                        compression.open(response)?.body
+                    }
+                    response => response?.body,
+                };
+                out.push(response);
+            }
+            Ok(out)
+        }
+    }
+
+    /// Send a notification to the remote nodes kitsune picks for a basis,
+    /// compressed if every peer of the space is known to read our codec
+    fn notify_multi(
+        &self,
+        class: &'static str,
+        dna_hash: DnaHash,
+        from_agent: AgentPubKey,
+        mut input: kitsune_p2p::actor::NotifyMulti,
+        body: Vec<u8>,
+    ) -> impl Future<Output = HolochainP2pResult<()>> + Send + 'static {
+        let compression = self.compression.clone();
+        let kitsune_p2p = self.kitsune_p2p.clone();
+        let evt_sender = self.evt_sender.clone();
+        async move {
+            let peers = space_peer_wires(&evt_sender, &dna_hash, &from_agent).await;
+            let (payload, _) =
+                compression.seal_request(class, &body, compression.may_compress_all(&peers));
+            input.payload = payload;
+            kitsune_p2p.notify_multi(input).await?;
+            Ok(())
+        }
+    }
+
+    /// Keep what a remote node reads, from the envelope of a payload it sent
+    fn learn_from_payload(
+        &self,
+        dna_hash: DnaHash,
+        to_agent: AgentPubKey,
+        from_agent: AgentPubKey,
+        accepts: Option<u8>,
+    ) {
+        let wire = self.compression.learn(accepts);
+        let evt_sender = self.evt_sender.clone();
+        tokio::task::spawn(async move {
+            learn_peer_wire(&evt_sender, dna_hash, to_agent, from_agent, wire).await
+        });
+    }
+
     /// receiving an incoming request from a remote node
     #[allow(clippy::too_many_arguments)]
     fn handle_incoming_call_remote(
@@ -83,6 +214,28 @@ impl HolochainP2pActor {
         .into())
     }
 
+    /// receiving an incoming get_element_by_header request from a remote node
+    fn handle_incoming_get_element_by_header(
+        &mut self,
+        dna_hash: DnaHash,
+        to_agent: AgentPubKey,
+        header_hash: HeaderHash,
+        options: event::GetOptions,
+    ) -> kitsune_p2p::actor::KitsuneP2pHandlerResult<Vec<u8>> {
+        let evt_sender = self.evt_sender.clone();
+        Ok(async move {
+            let res = evt_sender
+                .get_element_by_header(dna_hash, to_agent, header_hash, options)
+                .await;
+            res.and_then(|r| Ok(SerializedBytes::try_from(r)?))
+                .map_err(kitsune_p2p::KitsuneP2pError::from)
+                .map(|res| UnsafeBytes::from(res).into())
+        }
+        .instrument(tracing::debug_span!("incoming_get_element_by_header_task"))
+        .boxed()
+        .into())
+    }
+
     /// receiving an incoming get_meta request from a remote node
     fn handle_incoming_get_meta(
         &mut self,
@@ -125,6 +278,27 @@ impl HolochainP2pActor {
         .into())
     }
 
+    /// receiving an incoming get_indexed request from a remote node
+    fn handle_incoming_get_indexed(
+        &mut self,
+        dna_hash: DnaHash,
+        to_agent: AgentPubKey,
+        index_key: IndexKey,
+        options: event::GetIndexedOptions,
+    ) -> kitsune_p2p::actor::KitsuneP2pHandlerResult<Vec<u8>> {
+        let evt_sender = self.evt_sender.clone();
+        Ok(async move {
+            let res = evt_sender
+                .get_indexed(dna_hash, to_agent, index_key, options)
+                .await;
+            res.and_then(|r| Ok(SerializedBytes::try_from(r)?))
+                .map_err(kitsune_p2p::KitsuneP2pError::from)
+                .map(|res| UnsafeBytes::from(res).into())
+        }
+        .boxed()
+        .into())
+    }
+
     /// receiving an incoming publish from a remote node
     fn handle_incoming_publish(
         &mut self,
@@ -134,6 +308,10 @@ impl HolochainP2pActor {
         request_validation_receipt: bool,
         dht_hash: holo_hash::AnyDhtHash,
         ops: Vec<(holo_hash::DhtOpHash, holochain_types::dht_op::DhtOp)>,
+        requirements: Vec<(
+            holo_hash::DhtOpHash,
+            holochain_types::dht_op::OpRequirements,
+        )>,
     ) -> kitsune_p2p::actor::KitsuneP2pHandlerResult<()> {
         let evt_sender = self.evt_sender.clone();
         Ok(async move {
@@ -145,6 +323,7 @@ impl HolochainP2pActor {
                     request_validation_receipt,
                     dht_hash,
                     ops,
+                    requirements,
                 )
                 .await?;
             Ok(())
@@ -153,6 +332,25 @@ impl HolochainP2pActor {
         .into())
     }
 
+    /// receiving an incoming change to an index from a remote node
+    fn handle_incoming_publish_index(
+        &mut self,
+        dna_hash: DnaHash,
+        to_agent: AgentPubKey,
+        from_agent: AgentPubKey,
+        index_op: IndexOp,
+    ) -> kitsune_p2p::actor::KitsuneP2pHandlerResult<()> {
+        let evt_sender = self.evt_sender.clone();
+        Ok(async move {
+            evt_sender
+                .publish_index(dna_hash, to_agent, from_agent, index_op)
+                .await?;
+            Ok(())
+        }
+        .boxed()
+        .into())
+    }
+
     /// receiving an incoming validation receipt from a remote node
     fn handle_incoming_validation_receipt(
         &mut self,
@@ -175,6 +373,69 @@ impl HolochainP2pActor {
         .into())
     }
 
+    /// receiving an incoming countersigning preflight from a remote node
+    fn handle_incoming_countersigning_request(
+        &mut self,
+        dna_hash: DnaHash,
+        to_agent: AgentPubKey,
+        from_agent: AgentPubKey,
+        preflight: Vec<u8>,
+    ) -> kitsune_p2p::actor::KitsuneP2pHandlerResult<Vec<u8>> {
+        let preflight: SerializedBytes = UnsafeBytes::from(preflight).into();
+        let evt_sender = self.evt_sender.clone();
+        Ok(async move {
+            let res = evt_sender
+                .countersigning_request(dna_hash, to_agent, from_agent, preflight)
+                .await;
+            res.map_err(kitsune_p2p::KitsuneP2pError::from)
+                .map(|res| UnsafeBytes::from(res).into())
+        }
+        .boxed()
+        .into())
+    }
+
+    /// receiving an incoming countersigning partial signature from a remote node
+    fn handle_incoming_countersigning_response(
+        &mut self,
+        dna_hash: DnaHash,
+        to_agent: AgentPubKey,
+        from_agent: AgentPubKey,
+        partial_signature: Signature,
+    ) -> kitsune_p2p::actor::KitsuneP2pHandlerResult<Vec<u8>> {
+        let evt_sender = self.evt_sender.clone();
+        Ok(async move {
+            evt_sender
+                .countersigning_response(dna_hash, to_agent, from_agent, partial_signature)
+                .await?;
+
+            // the signature is only acknowledged
+            Ok(Vec::with_capacity(0))
+        }
+        .boxed()
+        .into())
+    }
+
+    /// receiving an incoming arc sync request from a remote node
+    fn handle_incoming_request_arc_sync(
+        &mut self,
+        dna_hash: DnaHash,
+        to_agent: AgentPubKey,
+        from_agent: AgentPubKey,
+        from_arc: kitsune_p2p::dht_arc::DhtArc,
+        from_info: AgentInfoSigned,
+    ) -> kitsune_p2p::actor::KitsuneP2pHandlerResult<Vec<u8>> {
+        let evt_sender = self.evt_sender.clone();
+        Ok(async move {
+            let res = evt_sender
+                .request_arc_sync(dna_hash, to_agent, from_agent, from_arc, from_info)
+                .await;
+            res.and_then(|agent_info| Ok(crate::wire::WireArcSyncResponse { agent_info }.encode()?))
+                .map_err(kitsune_p2p::KitsuneP2pError::from)
+        }
+        .boxed()
+        .into())
+    }
+
     /// Receiving an incoming validation package request
     fn handle_incoming_get_validation_package(
         &mut self,
@@ -210,8 +471,8 @@ impl kitsune_p2p::event::KitsuneP2pEventHandler for HolochainP2pActor {
             agent,
             agent_info_signed,
         } = input;
-        let space = DnaHash::from_kitsune(&space);
-        let agent = AgentPubKey::from_kitsune(&agent);
+        let space = DnaHash::try_from_kitsune(&space)?;
+        let agent = AgentPubKey::try_from_kitsune(&agent)?;
         let evt_sender = self.evt_sender.clone();
         Ok(async move {
             Ok(evt_sender
@@ -228,8 +489,8 @@ impl kitsune_p2p::event::KitsuneP2pEventHandler for HolochainP2pActor {
         input: kitsune_p2p::event::GetAgentInfoSignedEvt,
     ) -> kitsune_p2p::event::KitsuneP2pEventHandlerResult<Option<AgentInfoSigned>> {
         let kitsune_p2p::event::GetAgentInfoSignedEvt { space, agent } = input;
-        let h_space = DnaHash::from_kitsune(&space);
-        let h_agent = AgentPubKey::from_kitsune(&agent);
+        let h_space = DnaHash::try_from_kitsune(&space)?;
+        let h_agent = AgentPubKey::try_from_kitsune(&agent)?;
         let evt_sender = self.evt_sender.clone();
         Ok(async move {
             Ok(evt_sender
@@ -248,13 +509,31 @@ impl kitsune_p2p::event::KitsuneP2pEventHandler for HolochainP2pActor {
         from_agent: Arc<kitsune_p2p::KitsuneAgent>,
         payload: Vec<u8>,
     ) -> kitsune_p2p::event::KitsuneP2pEventHandlerResult<Vec<u8>> {
-        let space = DnaHash::from_kitsune(&space);
-        let to_agent = AgentPubKey::from_kitsune(&to_agent);
-        let from_agent = AgentPubKey::from_kitsune(&from_agent);
-
-        let request = crate::wire::WireMessage::decode(payload).map_err(HolochainP2pError::from)?;
-
-        match request {
+        let request = match self.compression.open(payload) {
+            Ok(request) => request,
+            // The sender will try again uncompressed
+            Err(HolochainP2pError::UnsupportedWireCodec(tag)) => {
+                let rejection = WireCompression::reject(tag);
+                return Ok(async move { Ok(rejection) }.boxed().into());
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let space = DnaHash::try_from_kitsune(&space)?;
+        let to_agent = AgentPubKey::try_from_kitsune(&to_agent)?;
+        let from_agent = AgentPubKey::try_from_kitsune(&from_agent)?;
+        self.learn_from_payload(
+            space.clone(),
+            to_agent.clone(),
+            from_agent.clone(),
+            request.accepts,
+        );
+
+        let accepts = request.accepts;
+        let request = crate::wire::WireMessage::decode(request.body)?;
+        let class = request.class();
+
+        let response = match request {
             crate::wire::WireMessage::CallRemote {
                 zome_name,
                 fn_name,
@@ -266,15 +545,23 @@ impl kitsune_p2p::event::KitsuneP2pEventHandler for HolochainP2pActor {
             crate::wire::WireMessage::Get { dht_hash, options } => {
                 self.handle_incoming_get(space, to_agent, dht_hash, options)
             }
+            crate::wire::WireMessage::GetElementByHeader {
+                header_hash,
+                options,
+            } => self.handle_incoming_get_element_by_header(space, to_agent, header_hash, options),
             crate::wire::WireMessage::GetMeta { dht_hash, options } => {
                 self.handle_incoming_get_meta(space, to_agent, dht_hash, options)
             }
             crate::wire::WireMessage::GetLinks { link_key, options } => {
                 self.handle_incoming_get_links(space, to_agent, link_key, options)
             }
+            crate::wire::WireMessage::GetIndexed { index_key, options } => {
+                self.handle_incoming_get_indexed(space, to_agent, index_key, options)
+            }
             // holochain_p2p never publishes via request
             // these only occur on broadcasts
-            crate::wire::WireMessage::Publish { .. } => {
+            crate::wire::WireMessage::Publish { .. }
+            | crate::wire::WireMessage::PublishIndex { .. } => {
                 Err(HolochainP2pError::invalid_p2p_message(
                     "invalid: publish is a broadcast type, not a request".to_string(),
                 )
@@ -286,7 +573,35 @@ impl kitsune_p2p::event::KitsuneP2pEventHandler for HolochainP2pActor {
             crate::wire::WireMessage::GetValidationPackage { header_hash } => {
                 self.handle_incoming_get_validation_package(space, to_agent, header_hash)
             }
+            crate::wire::WireMessage::CountersigningRequest { preflight } => {
+                self.handle_incoming_countersigning_request(space, to_agent, from_agent, preflight)
+            }
+            crate::wire::WireMessage::CountersigningResponse { partial_signature } => self
+                .handle_incoming_countersigning_response(
+                    space,
+                    to_agent,
+                    from_agent,
+                    partial_signature,
+                ),
+            crate::wire::WireMessage::RequestArcSync {
+                from_arc,
+                from_info,
+            } => self.handle_incoming_request_arc_sync(
+                space,
+                to_agent,
+                from_agent,
+                from_arc.into(),
+                from_info,
+            ),
+        }?;
+
+        let compression = self.compression.clone();
+        Ok(async move {
+            let response = response.await?;
+            Ok(compression.seal_response(class, &response, accepts))
         }
+        .boxed()
+        .into())
     }
 
     fn handle_notify(
@@ -296,19 +611,31 @@ impl kitsune_p2p::event::KitsuneP2pEventHandler for HolochainP2pActor {
         from_agent: Arc<kitsune_p2p::KitsuneAgent>,
         payload: Vec<u8>,
     ) -> kitsune_p2p::event::KitsuneP2pEventHandlerResult<()> {
-        let space = DnaHash::from_kitsune(&space);
-        let to_agent = AgentPubKey::from_kitsune(&to_agent);
-        let from_agent = AgentPubKey::from_kitsune(&from_agent);
-
-        let request = crate::wire::WireMessage::decode(payload).map_err(HolochainP2pError::from)?;
+        let space = DnaHash::try_from_kitsune(&space)?;
+        let to_agent = AgentPubKey::try_from_kitsune(&to_agent)?;
+        let from_agent = AgentPubKey::try_from_kitsune(&from_agent)?;
+
+        let request = self.compression.open(payload)?;
+        self.learn_from_payload(
+            space.clone(),
+            to_agent.clone(),
+            from_agent.clone(),
+            request.accepts,
+        );
+        let request = crate::wire::WireMessage::decode(request.body)?;
 
         match request {
             // error on these call type messages
             crate::wire::WireMessage::CallRemote { .. }
             | crate::wire::WireMessage::Get { .. }
+            | crate::wire::WireMessage::GetElementByHeader { .. }
             | crate::wire::WireMessage::GetMeta { .. }
             | crate::wire::WireMessage::GetLinks { .. }
+            | crate::wire::WireMessage::GetIndexed { .. }
             | crate::wire::WireMessage::GetValidationPackage { .. }
+            | crate::wire::WireMessage::RequestArcSync { .. }
+            | crate::wire::WireMessage::CountersigningRequest { .. }
+            | crate::wire::WireMessage::CountersigningResponse { .. }
             | crate::wire::WireMessage::ValidationReceipt { .. } => {
                 Err(HolochainP2pError::invalid_p2p_message(
                     "invalid call type message in a notify".to_string(),
@@ -319,6 +646,7 @@ impl kitsune_p2p::event::KitsuneP2pEventHandler for HolochainP2pActor {
                 request_validation_receipt,
                 dht_hash,
                 ops,
+                requirements,
             } => self.handle_incoming_publish(
                 space,
                 to_agent,
@@ -326,7 +654,11 @@ impl kitsune_p2p::event::KitsuneP2pEventHandler for HolochainP2pActor {
                 request_validation_receipt,
                 dht_hash,
                 ops,
+                requirements,
             ),
+            crate::wire::WireMessage::PublishIndex { index_op } => {
+                self.handle_incoming_publish_index(space, to_agent, from_agent, index_op)
+            }
         }
     }
 
@@ -338,12 +670,11 @@ impl kitsune_p2p::event::KitsuneP2pEventHandler for HolochainP2pActor {
         op_hash: Arc<kitsune_p2p::KitsuneOpHash>,
         op_data: Vec<u8>,
     ) -> kitsune_p2p::event::KitsuneP2pEventHandlerResult<()> {
-        let space = DnaHash::from_kitsune(&space);
-        let to_agent = AgentPubKey::from_kitsune(&to_agent);
-        let _from_agent = AgentPubKey::from_kitsune(&from_agent);
-        let op_hash = DhtOpHash::from_kitsune(&op_hash);
-        let op_data =
-            crate::wire::WireDhtOpData::decode(op_data).map_err(HolochainP2pError::from)?;
+        let space = DnaHash::try_from_kitsune(&space)?;
+        let to_agent = AgentPubKey::try_from_kitsune(&to_agent)?;
+        let _from_agent = AgentPubKey::try_from_kitsune(&from_agent)?;
+        let op_hash = DhtOpHash::try_from_kitsune(&op_hash)?;
+        let op_data = crate::wire::WireDhtOpData::decode(op_data)?;
         self.handle_incoming_publish(
             space,
             to_agent,
@@ -351,14 +682,17 @@ impl kitsune_p2p::event::KitsuneP2pEventHandler for HolochainP2pActor {
             false,
             op_data.dht_hash,
             vec![(op_hash, op_data.op_data)],
+            // Gossiped ops come without their author's claimed requirements
+            Vec::new(),
         )
     }
 
     fn handle_fetch_op_hashes_for_constraints(
         &mut self,
         input: kitsune_p2p::event::FetchOpHashesForConstraintsEvt,
-    ) -> kitsune_p2p::event::KitsuneP2pEventHandlerResult<Vec<Arc<kitsune_p2p::KitsuneOpHash>>>
-    {
+    ) -> kitsune_p2p::event::KitsuneP2pEventHandlerResult<
+        kitsune_p2p::event::ArcCoverage<Vec<Arc<kitsune_p2p::KitsuneOpHash>>>,
+    > {
         let kitsune_p2p::event::FetchOpHashesForConstraintsEvt {
             space,
             agent,
@@ -366,8 +700,8 @@ impl kitsune_p2p::event::KitsuneP2pEventHandler for HolochainP2pActor {
             since_utc_epoch_s,
             until_utc_epoch_s,
         } = input;
-        let space = DnaHash::from_kitsune(&space);
-        let agent = AgentPubKey::from_kitsune(&agent);
+        let space = DnaHash::try_from_kitsune(&space)?;
+        let agent = AgentPubKey::try_from_kitsune(&agent)?;
         let since = Timestamp(since_utc_epoch_s, 0);
         let until = Timestamp(until_utc_epoch_s, 0);
 
@@ -376,9 +710,7 @@ impl kitsune_p2p::event::KitsuneP2pEventHandler for HolochainP2pActor {
             Ok(evt_sender
                 .fetch_op_hashes_for_constraints(space, agent, dht_arc, since, until)
                 .await?
-                .into_iter()
-                .map(|h| h.into_kitsune())
-                .collect())
+                .map(|hashes| hashes.into_iter().map(|h| h.into_kitsune()).collect()))
         }
         .boxed()
         .into())
@@ -395,12 +727,12 @@ impl kitsune_p2p::event::KitsuneP2pEventHandler for HolochainP2pActor {
             agent,
             op_hashes,
         } = input;
-        let space = DnaHash::from_kitsune(&space);
-        let agent = AgentPubKey::from_kitsune(&agent);
+        let space = DnaHash::try_from_kitsune(&space)?;
+        let agent = AgentPubKey::try_from_kitsune(&agent)?;
         let op_hashes = op_hashes
-            .into_iter()
-            .map(|h| DhtOpHash::from_kitsune(&h))
-            .collect::<Vec<_>>();
+            .iter()
+            .map(DhtOpHash::try_from_kitsune)
+            .collect::<HolochainP2pResult<Vec<_>>>()?;
 
         let evt_sender = self.evt_sender.clone();
         Ok(async move {
@@ -432,6 +764,36 @@ impl kitsune_p2p::event::KitsuneP2pEventHandler for HolochainP2pActor {
     ) -> kitsune_p2p::event::KitsuneP2pEventHandlerResult<kitsune_p2p::KitsuneSignature> {
         unimplemented!()
     }
+
+    fn handle_peer_connected(
+        &mut self,
+        input: kitsune_p2p::event::PeerConnectedEvt,
+    ) -> kitsune_p2p::event::KitsuneP2pEventHandlerResult<()> {
+        let kitsune_p2p::event::PeerConnectedEvt { space, agent, urls } = input;
+        let space = DnaHash::try_from_kitsune(&space)?;
+        let agent = AgentPubKey::try_from_kitsune(&agent)?;
+        let evt_sender = self.evt_sender.clone();
+        Ok(
+            async move { Ok(evt_sender.peer_connected(space, agent, urls).await?) }
+                .boxed()
+                .into(),
+        )
+    }
+
+    fn handle_peer_disconnected(
+        &mut self,
+        input: kitsune_p2p::event::PeerDisconnectedEvt,
+    ) -> kitsune_p2p::event::KitsuneP2pEventHandlerResult<()> {
+        let kitsune_p2p::event::PeerDisconnectedEvt { space, agent } = input;
+        let space = DnaHash::try_from_kitsune(&space)?;
+        let agent = AgentPubKey::try_from_kitsune(&agent)?;
+        let evt_sender = self.evt_sender.clone();
+        Ok(
+            async move { Ok(evt_sender.peer_disconnected(space, agent).await?) }
+                .boxed()
+                .into(),
+        )
+    }
 }
 
 impl ghost_actor::GhostHandler<HolochainP2p> for HolochainP2pActor {}
@@ -442,13 +804,55 @@ impl HolochainP2pHandler for HolochainP2pActor {
         dna_hash: DnaHash,
         agent_pub_key: AgentPubKey,
     ) -> HolochainP2pHandlerResult<()> {
-        let space = dna_hash.into_kitsune();
-        let agent = agent_pub_key.into_kitsune();
+        let space = dna_hash.to_kitsune();
+        let agent = agent_pub_key.to_kitsune();
+        let own_wire = self.compression.own_wire();
 
         let kitsune_p2p = self.kitsune_p2p.clone();
-        Ok(async move { Ok(kitsune_p2p.join(space, agent).await?) }
-            .boxed()
-            .into())
+        let evt_sender = self.evt_sender.clone();
+        Ok(async move {
+            kitsune_p2p.join(space, agent).await?;
+            // Our own agents are peers of the others in the space
+            learn_peer_wire(
+                &evt_sender,
+                dna_hash,
+                agent_pub_key.clone(),
+                agent_pub_key,
+                own_wire,
+            )
+            .await;
+            Ok(())
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_join_shared(
+        &mut self,
+        dna_hash: DnaHash,
+        agent_pub_key: AgentPubKey,
+    ) -> HolochainP2pHandlerResult<()> {
+        let space = dna_hash.to_kitsune();
+        let agent = agent_pub_key.to_kitsune();
+        let own_wire = self.compression.own_wire();
+
+        let kitsune_p2p = self.kitsune_p2p.clone();
+        let evt_sender = self.evt_sender.clone();
+        Ok(async move {
+            kitsune_p2p.join_shared(space, agent).await?;
+            // Our own agents are peers of the others in the space
+            learn_peer_wire(
+                &evt_sender,
+                dna_hash,
+                agent_pub_key.clone(),
+                agent_pub_key,
+                own_wire,
+            )
+            .await;
+            Ok(())
+        }
+        .boxed()
+        .into())
     }
 
     fn handle_leave(
@@ -475,18 +879,12 @@ impl HolochainP2pHandler for HolochainP2pActor {
         cap: Option<CapSecret>,
         request: SerializedBytes,
     ) -> HolochainP2pHandlerResult<SerializedBytes> {
-        let space = dna_hash.into_kitsune();
-        let to_agent = to_agent.into_kitsune();
-        let from_agent = from_agent.into_kitsune();
-
         let req =
             crate::wire::WireMessage::call_remote(zome_name, fn_name, cap, request).encode()?;
 
-        let kitsune_p2p = self.kitsune_p2p.clone();
+        let response = self.request_single("CallRemote", dna_hash, to_agent, from_agent, req);
         Ok(async move {
-            let result = kitsune_p2p
-                .rpc_single(space, to_agent, from_agent, req)
-                .await?;
+            let result = response.await?;
             let result = UnsafeBytes::from(result).into();
             Ok(result)
         }
@@ -501,49 +899,75 @@ impl HolochainP2pHandler for HolochainP2pActor {
         request_validation_receipt: bool,
         dht_hash: holo_hash::AnyDhtHash,
         ops: Vec<(holo_hash::DhtOpHash, holochain_types::dht_op::DhtOp)>,
+        requirements: Vec<(
+            holo_hash::DhtOpHash,
+            holochain_types::dht_op::OpRequirements,
+        )>,
         timeout_ms: Option<u64>,
     ) -> HolochainP2pHandlerResult<()> {
-        let space = dna_hash.into_kitsune();
-        let from_agent = from_agent.into_kitsune();
+        let space = dna_hash.to_kitsune();
         let basis = dht_hash.to_kitsune();
 
-        let payload = crate::wire::WireMessage::publish(request_validation_receipt, dht_hash, ops)
-            .encode()?;
+        let payload = crate::wire::WireMessage::publish(
+            request_validation_receipt,
+            dht_hash,
+            ops,
+            requirements,
+        )
+        .encode()?;
 
-        let kitsune_p2p = self.kitsune_p2p.clone();
-        Ok(async move {
-            kitsune_p2p
-                .notify_multi(kitsune_p2p::actor::NotifyMulti {
-                    space,
-                    from_agent,
-                    basis,
-                    remote_agent_count: None, // default best-effort
-                    timeout_ms,
-                    payload,
-                })
-                .await?;
-            Ok(())
-        }
-        .boxed()
-        .into())
+        let input = kitsune_p2p::actor::NotifyMulti {
+            space,
+            from_agent: from_agent.to_kitsune(),
+            basis,
+            remote_agent_count: None, // default best-effort
+            timeout_ms,
+            payload: Vec::new(),
+        };
+        let sent = self.notify_multi("Publish", dna_hash, from_agent, input, payload);
+        Ok(async move { sent.await }.boxed().into())
+    }
+
+    fn handle_publish_index(
+        &mut self,
+        dna_hash: DnaHash,
+        from_agent: AgentPubKey,
+        index_op: IndexOp,
+        timeout_ms: Option<u64>,
+    ) -> HolochainP2pHandlerResult<()> {
+        let space = dna_hash.to_kitsune();
+        let basis = index_op.index_key().basis().to_kitsune();
+
+        let payload = crate::wire::WireMessage::publish_index(index_op).encode()?;
+
+        let input = kitsune_p2p::actor::NotifyMulti {
+            space,
+            from_agent: from_agent.to_kitsune(),
+            basis,
+            remote_agent_count: None, // default best-effort
+            timeout_ms,
+            payload: Vec::new(),
+        };
+        let sent = self.notify_multi("PublishIndex", dna_hash, from_agent, input, payload);
+        Ok(async move { sent.await }.boxed().into())
     }
 
     fn handle_get_validation_package(
         &mut self,
         input: actor::GetValidationPackage,
     ) -> HolochainP2pHandlerResult<ValidationPackageResponse> {
-        let space = input.dna_hash.into_kitsune();
-        let to_agent = input.request_from.into_kitsune();
-        let from_agent = input.agent_pub_key.into_kitsune();
-
         let req = crate::wire::WireMessage::get_validation_package(input.header_hash).encode()?;
 
-        let kitsune_p2p = self.kitsune_p2p.clone();
+        let response = self.request_single(
+            "GetValidationPackage",
+            input.dna_hash,
+            input.request_from,
+            input.agent_pub_key,
+            req,
+        );
         Ok(async move {
-            let response = kitsune_p2p
-                .rpc_single(space, to_agent, from_agent, req)
-                .await?;
-            let response = SerializedBytes::from(UnsafeBytes::from(response)).try_into()?;
+            let response = response.await?;
+            let response = crate::wire::decode_remote("ValidationPackageResponse", response)?;
             Ok(response)
         }
         .boxed()
@@ -558,35 +982,65 @@ impl HolochainP2pHandler for HolochainP2pActor {
         dht_hash: holo_hash::AnyDhtHash,
         options: actor::GetOptions,
     ) -> HolochainP2pHandlerResult<Vec<GetElementResponse>> {
-        let space = dna_hash.into_kitsune();
-        let from_agent = from_agent.into_kitsune();
+        let space = dna_hash.to_kitsune();
         let basis = dht_hash.to_kitsune();
         let r_options: event::GetOptions = (&options).into();
 
         let payload = crate::wire::WireMessage::get(dht_hash, r_options).encode()?;
 
-        let kitsune_p2p = self.kitsune_p2p.clone();
+        let input = kitsune_p2p::actor::RpcMulti {
+            space,
+            from_agent: from_agent.to_kitsune(),
+            basis,
+            remote_agent_count: options.remote_agent_count,
+            timeout_ms: options.timeout_ms,
+            as_race: options.as_race,
+            race_timeout_ms: options.race_timeout_ms,
+            payload: Vec::new(),
+        };
+        let responses = self.request_multi("Get", dna_hash, from_agent, input, payload);
         Ok(async move {
-            let result = kitsune_p2p
-                .rpc_multi(kitsune_p2p::actor::RpcMulti {
-                    space,
-                    from_agent,
-                    basis,
-                    remote_agent_count: options.remote_agent_count,
-                    timeout_ms: options.timeout_ms,
-                    as_race: options.as_race,
-                    race_timeout_ms: options.race_timeout_ms,
-                    payload,
-                })
-                .instrument(tracing::debug_span!("rpc_multi"))
-                .await?;
-
             let mut out = Vec::new();
-            for item in result {
-                let kitsune_p2p::actor::RpcMultiResponse { response, .. } = item;
-                out.push(SerializedBytes::from(UnsafeBytes::from(response)).try_into()?);
+            for response in responses.await? {
+                out.push(crate::wire::decode_remote("GetElementResponse", response)?);
             }
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
 
+    fn handle_get_element_by_header(
+        &mut self,
+        dna_hash: DnaHash,
+        from_agent: AgentPubKey,
+        header_hash: HeaderHash,
+        options: actor::GetOptions,
+    ) -> HolochainP2pHandlerResult<Vec<GetElementResponse>> {
+        let space = dna_hash.to_kitsune();
+        let basis = holo_hash::AnyDhtHash::from(header_hash.clone()).to_kitsune();
+        let r_options: event::GetOptions = (&options).into();
+
+        let payload =
+            crate::wire::WireMessage::get_element_by_header(header_hash, r_options).encode()?;
+
+        let input = kitsune_p2p::actor::RpcMulti {
+            space,
+            from_agent: from_agent.to_kitsune(),
+            basis,
+            remote_agent_count: options.remote_agent_count,
+            timeout_ms: options.timeout_ms,
+            as_race: options.as_race,
+            race_timeout_ms: options.race_timeout_ms,
+            payload: Vec::new(),
+        };
+        let responses =
+            self.request_multi("GetElementByHeader", dna_hash, from_agent, input, payload);
+        Ok(async move {
+            let mut out = Vec::new();
+            for response in responses.await? {
+                out.push(crate::wire::decode_remote("GetElementResponse", response)?);
+            }
             Ok(out)
         }
         .boxed()
@@ -600,34 +1054,28 @@ impl HolochainP2pHandler for HolochainP2pActor {
         dht_hash: holo_hash::AnyDhtHash,
         options: actor::GetMetaOptions,
     ) -> HolochainP2pHandlerResult<Vec<MetadataSet>> {
-        let space = dna_hash.into_kitsune();
-        let from_agent = from_agent.into_kitsune();
+        let space = dna_hash.to_kitsune();
         let basis = dht_hash.to_kitsune();
         let r_options: event::GetMetaOptions = (&options).into();
 
         let payload = crate::wire::WireMessage::get_meta(dht_hash, r_options).encode()?;
 
-        let kitsune_p2p = self.kitsune_p2p.clone();
+        let input = kitsune_p2p::actor::RpcMulti {
+            space,
+            from_agent: from_agent.to_kitsune(),
+            basis,
+            remote_agent_count: options.remote_agent_count,
+            timeout_ms: options.timeout_ms,
+            as_race: options.as_race,
+            race_timeout_ms: options.race_timeout_ms,
+            payload: Vec::new(),
+        };
+        let responses = self.request_multi("GetMeta", dna_hash, from_agent, input, payload);
         Ok(async move {
-            let result = kitsune_p2p
-                .rpc_multi(kitsune_p2p::actor::RpcMulti {
-                    space,
-                    from_agent,
-                    basis,
-                    remote_agent_count: options.remote_agent_count,
-                    timeout_ms: options.timeout_ms,
-                    as_race: options.as_race,
-                    race_timeout_ms: options.race_timeout_ms,
-                    payload,
-                })
-                .await?;
-
             let mut out = Vec::new();
-            for item in result {
-                let kitsune_p2p::actor::RpcMultiResponse { response, .. } = item;
-                out.push(SerializedBytes::from(UnsafeBytes::from(response)).try_into()?);
+            for response in responses.await? {
+                out.push(crate::wire::decode_remote("MetadataSet", response)?);
             }
-
             Ok(out)
         }
         .boxed()
@@ -641,37 +1089,67 @@ impl HolochainP2pHandler for HolochainP2pActor {
         link_key: WireLinkMetaKey,
         options: actor::GetLinksOptions,
     ) -> HolochainP2pHandlerResult<Vec<GetLinksResponse>> {
-        let space = dna_hash.into_kitsune();
-        let from_agent = from_agent.into_kitsune();
+        let space = dna_hash.to_kitsune();
         let basis = link_key.basis().to_kitsune();
         let r_options: event::GetLinksOptions = (&options).into();
 
         let payload = crate::wire::WireMessage::get_links(link_key, r_options).encode()?;
 
-        let kitsune_p2p = self.kitsune_p2p.clone();
+        // TODO - We're just targeting a single remote node for now
+        //        without doing any pagination / etc...
+        //        Setting up RpcMulti to act like RpcSingle
+        let input = kitsune_p2p::actor::RpcMulti {
+            space,
+            from_agent: from_agent.to_kitsune(),
+            basis,
+            remote_agent_count: Some(1),
+            timeout_ms: options.timeout_ms,
+            as_race: false,
+            race_timeout_ms: options.timeout_ms,
+            payload: Vec::new(),
+        };
+        let responses = self.request_multi("GetLinks", dna_hash, from_agent, input, payload);
         Ok(async move {
-            // TODO - We're just targeting a single remote node for now
-            //        without doing any pagination / etc...
-            //        Setting up RpcMulti to act like RpcSingle
-            let result = kitsune_p2p
-                .rpc_multi(kitsune_p2p::actor::RpcMulti {
-                    space,
-                    from_agent,
-                    basis,
-                    remote_agent_count: Some(1),
-                    timeout_ms: options.timeout_ms,
-                    as_race: false,
-                    race_timeout_ms: options.timeout_ms,
-                    payload,
-                })
-                .await?;
-
             let mut out = Vec::new();
-            for item in result {
-                let kitsune_p2p::actor::RpcMultiResponse { response, .. } = item;
-                out.push(SerializedBytes::from(UnsafeBytes::from(response)).try_into()?);
+            for response in responses.await? {
+                out.push(crate::wire::decode_remote("GetLinksResponse", response)?);
             }
+            Ok(out)
+        }
+        .boxed()
+        .into())
+    }
 
+    fn handle_get_indexed(
+        &mut self,
+        dna_hash: DnaHash,
+        from_agent: AgentPubKey,
+        index_key: IndexKey,
+        options: actor::GetIndexedOptions,
+    ) -> HolochainP2pHandlerResult<Vec<GetIndexedResponse>> {
+        let space = dna_hash.to_kitsune();
+        let basis = index_key.basis().to_kitsune();
+        let r_options: event::GetIndexedOptions = (&options).into();
+
+        let payload = crate::wire::WireMessage::get_indexed(index_key, r_options).encode()?;
+
+        // Like get_links, this only asks a single remote node for now
+        let input = kitsune_p2p::actor::RpcMulti {
+            space,
+            from_agent: from_agent.to_kitsune(),
+            basis,
+            remote_agent_count: Some(1),
+            timeout_ms: options.timeout_ms,
+            as_race: false,
+            race_timeout_ms: options.timeout_ms,
+            payload: Vec::new(),
+        };
+        let responses = self.request_multi("GetIndexed", dna_hash, from_agent, input, payload);
+        Ok(async move {
+            let mut out = Vec::new();
+            for response in responses.await? {
+                out.push(crate::wire::decode_remote("GetIndexedResponse", response)?);
+            }
             Ok(out)
         }
         .boxed()
@@ -685,20 +1163,161 @@ impl HolochainP2pHandler for HolochainP2pActor {
         from_agent: AgentPubKey,
         receipt: SerializedBytes,
     ) -> HolochainP2pHandlerResult<()> {
-        let space = dna_hash.into_kitsune();
-        let to_agent = to_agent.into_kitsune();
-        let from_agent = from_agent.into_kitsune();
-
         let req = crate::wire::WireMessage::validation_receipt(receipt).encode()?;
 
-        let kitsune_p2p = self.kitsune_p2p.clone();
+        let response =
+            self.request_single("ValidationReceipt", dna_hash, to_agent, from_agent, req);
         Ok(async move {
-            kitsune_p2p
-                .rpc_single(space, to_agent, from_agent, req)
-                .await?;
+            response.await?;
+            Ok(())
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_countersigning_request(
+        &mut self,
+        dna_hash: DnaHash,
+        from_agent: AgentPubKey,
+        to_agent: AgentPubKey,
+        preflight: SerializedBytes,
+    ) -> HolochainP2pHandlerResult<SerializedBytes> {
+        let req = crate::wire::WireMessage::countersigning_request(preflight).encode()?;
+
+        let response =
+            self.request_single("CountersigningRequest", dna_hash, to_agent, from_agent, req);
+        Ok(async move {
+            let result = response.await?;
+            Ok(UnsafeBytes::from(result).into())
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_countersigning_response(
+        &mut self,
+        dna_hash: DnaHash,
+        from_agent: AgentPubKey,
+        to_agent: AgentPubKey,
+        partial_signature: Signature,
+    ) -> HolochainP2pHandlerResult<()> {
+        let req = crate::wire::WireMessage::countersigning_response(partial_signature).encode()?;
+
+        let response = self.request_single(
+            "CountersigningResponse",
+            dna_hash,
+            to_agent,
+            from_agent,
+            req,
+        );
+        Ok(async move {
+            response.await?;
             Ok(())
         }
         .boxed()
         .into())
     }
+
+    fn handle_request_arc_sync(
+        &mut self,
+        dna_hash: DnaHash,
+        from_agent: AgentPubKey,
+        to_agent: AgentPubKey,
+        from_arc: kitsune_p2p::dht_arc::DhtArc,
+        from_info: AgentInfoSigned,
+    ) -> HolochainP2pHandlerResult<Option<AgentInfoSigned>> {
+        let req = crate::wire::WireMessage::request_arc_sync(from_arc, from_info).encode()?;
+
+        let response = self.request_single("RequestArcSync", dna_hash, to_agent, from_agent, req);
+        Ok(async move {
+            let response = response.await?;
+            Ok(crate::wire::WireArcSyncResponse::decode(response)?.agent_info)
+        }
+        .boxed()
+        .into())
+    }
+
+    fn handle_network_info(
+        &mut self,
+        dna_hash: DnaHash,
+        agent_pub_key: AgentPubKey,
+    ) -> HolochainP2pHandlerResult<NetworkInfo> {
+        let space = dna_hash.into_kitsune();
+        let agent = agent_pub_key.into_kitsune();
+
+        let kitsune_p2p = self.kitsune_p2p.clone();
+        Ok(
+            async move { Ok(kitsune_p2p.network_info(space, agent).await?) }
+                .boxed()
+                .into(),
+        )
+    }
+
+    fn handle_wire_compression_metrics(
+        &mut self,
+    ) -> HolochainP2pHandlerResult<WireCompressionMetrics> {
+        let metrics = self.compression.metrics();
+        Ok(async move { Ok(metrics) }.boxed().into())
+    }
+}
+
+type EvtSender = futures::channel::mpsc::Sender<HolochainP2pEvent>;
+
+/// What we have learned about a peer's wire, if anything.
+/// Peer metadata only saves us a rejected request,
+/// so a failure to read it is logged and treated as knowing nothing.
+async fn peer_wire(
+    evt_sender: &EvtSender,
+    dna_hash: &DnaHash,
+    to_agent: &AgentPubKey,
+    peer: &AgentPubKey,
+) -> Option<PeerWire> {
+    match evt_sender
+        .get_peer_meta(dna_hash.clone(), to_agent.clone(), peer.clone())
+        .await
+    {
+        Ok(peer_meta) => peer_meta.and_then(|peer_meta| peer_meta.wire),
+        Err(e) => {
+            tracing::warn!(msg = "peer metadata could not be read", ?e);
+            None
+        }
+    }
+}
+
+/// What we have learned about the wire of every peer of a space
+async fn space_peer_wires(
+    evt_sender: &EvtSender,
+    dna_hash: &DnaHash,
+    to_agent: &AgentPubKey,
+) -> Vec<Option<PeerWire>> {
+    match evt_sender
+        .get_space_peer_meta(dna_hash.clone(), to_agent.clone())
+        .await
+    {
+        Ok(peers) => peers
+            .into_iter()
+            .map(|(_, peer_meta)| peer_meta.and_then(|peer_meta| peer_meta.wire))
+            .collect(),
+        Err(e) => {
+            tracing::warn!(msg = "peer metadata could not be read", ?e);
+            Vec::new()
+        }
+    }
+}
+
+/// Keep what we have learned about a peer's wire in its peer metadata
+async fn learn_peer_wire(
+    evt_sender: &EvtSender,
+    dna_hash: DnaHash,
+    to_agent: AgentPubKey,
+    peer: AgentPubKey,
+    wire: PeerWire,
+) {
+    let peer_meta = PeerMeta { wire: Some(wire) };
+    if let Err(e) = evt_sender
+        .put_peer_meta(dna_hash, to_agent, peer, peer_meta)
+        .await
+    {
+        tracing::warn!(msg = "peer metadata could not be written", ?e);
+    }
 }
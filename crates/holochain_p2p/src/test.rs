@@ -8,7 +8,7 @@ fixturator!(
     curve Empty {
         // TODO: Make this empty
         tokio_safe_block_on::tokio_safe_block_forever_on(async {
-            let (holochain_p2p, _p2p_evt) = crate::spawn_holochain_p2p().await.unwrap();
+            let (holochain_p2p, _p2p_evt) = crate::spawn_holochain_p2p(Default::default()).await.unwrap();
             holochain_p2p.to_cell(
                 DnaHashFixturator::new(Empty).next().unwrap(),
                 AgentPubKeyFixturator::new(Empty).next().unwrap(),
@@ -18,7 +18,7 @@ fixturator!(
     curve Unpredictable {
         // TODO: Make this unpredictable
         tokio_safe_block_on::tokio_safe_block_forever_on(async {
-            let (holochain_p2p, _p2p_evt) = crate::spawn_holochain_p2p().await.unwrap();
+            let (holochain_p2p, _p2p_evt) = crate::spawn_holochain_p2p(Default::default()).await.unwrap();
             holochain_p2p.to_cell(
                 DnaHashFixturator::new(Unpredictable).next().unwrap(),
                 AgentPubKeyFixturator::new(Unpredictable).next().unwrap(),
@@ -27,7 +27,7 @@ fixturator!(
     };
     curve Predictable {
         tokio_safe_block_on::tokio_safe_block_forever_on(async {
-            let (holochain_p2p, _p2p_evt) = crate::spawn_holochain_p2p().await.unwrap();
+            let (holochain_p2p, _p2p_evt) = crate::spawn_holochain_p2p(Default::default()).await.unwrap();
             holochain_p2p.to_cell(
                 DnaHashFixturator::new(Predictable).next().unwrap(),
                 AgentPubKeyFixturator::new(Predictable).next().unwrap(),
@@ -41,8 +41,13 @@ mod tests {
     use ::fixt::prelude::*;
     use futures::future::FutureExt;
     use ghost_actor::GhostControlSender;
+    use holo_hash::fixt::DhtOpHashFixturator;
+    use holochain_types::dht_op::DhtOp;
     use holochain_types::element::{Element, SignedHeaderHashed, WireElement};
+    use holochain_types::header::NewEntryHeader;
+    use holochain_types::Entry;
     use holochain_types::{fixt::*, HeaderHashed};
+    use std::convert::TryInto;
 
     macro_rules! newhash {
         ($p:ident, $c:expr) => {
@@ -68,7 +73,7 @@ mod tests {
     async fn test_call_remote_workflow() {
         let (dna, a1, a2, _) = test_setup();
 
-        let (p2p, mut evt) = spawn_holochain_p2p().await.unwrap();
+        let (p2p, mut evt) = spawn_holochain_p2p(Default::default()).await.unwrap();
 
         let r_task = tokio::task::spawn(async move {
             use tokio::stream::StreamExt;
@@ -110,11 +115,69 @@ mod tests {
         r_task.await.unwrap();
     }
 
+    #[tokio::test(threaded_scheduler)]
+    async fn test_call_remote_compresses_large_payloads() {
+        let (dna, a1, a2, _) = test_setup();
+
+        let (p2p, mut evt) = spawn_holochain_p2p(Default::default()).await.unwrap();
+        let large = b"holochain".repeat(64 * 1024);
+
+        let response = large.clone();
+        let r_task = tokio::task::spawn(async move {
+            use tokio::stream::StreamExt;
+            while let Some(evt) = evt.next().await {
+                use crate::types::event::HolochainP2pEvent::*;
+                match evt {
+                    CallRemote { respond, .. } => {
+                        let response = response.clone();
+                        respond.r(Ok(async move { Ok(UnsafeBytes::from(response).into()) }
+                            .boxed()
+                            .into()));
+                    }
+                    _ => (),
+                }
+            }
+        });
+
+        p2p.join(dna.clone(), a1.clone()).await.unwrap();
+        p2p.join(dna.clone(), a2.clone()).await.unwrap();
+
+        for payload in vec![b"small".to_vec(), large.clone()] {
+            let res = p2p
+                .call_remote(
+                    dna.clone(),
+                    a1.clone(),
+                    a2.clone(),
+                    "".into(),
+                    "".into(),
+                    None,
+                    UnsafeBytes::from(payload).into(),
+                )
+                .await
+                .unwrap();
+            let res: Vec<u8> = UnsafeBytes::from(res).into();
+            assert_eq!(large, res);
+        }
+
+        // Only the large request was compressed, but both responses were
+        let metrics = p2p.wire_compression_metrics().await.unwrap();
+        let requests = metrics["CallRemote"];
+        assert_eq!(requests.payloads, 2);
+        assert_eq!(requests.compressed_payloads, 1);
+        assert!(requests.bytes_after < requests.bytes_before);
+        let responses = metrics["CallRemote response"];
+        assert_eq!(responses.payloads, 2);
+        assert_eq!(responses.compressed_payloads, 2);
+
+        p2p.ghost_actor_shutdown().await.unwrap();
+        r_task.await.unwrap();
+    }
+
     #[tokio::test(threaded_scheduler)]
     async fn test_send_validation_receipt_workflow() {
         let (dna, a1, a2, _) = test_setup();
 
-        let (p2p, mut evt) = spawn_holochain_p2p().await.unwrap();
+        let (p2p, mut evt) = spawn_holochain_p2p(Default::default()).await.unwrap();
 
         let r_task = tokio::task::spawn(async move {
             use tokio::stream::StreamExt;
@@ -149,6 +212,64 @@ mod tests {
         r_task.await.unwrap();
     }
 
+    #[tokio::test(threaded_scheduler)]
+    async fn test_countersigning_workflow() {
+        let (dna, a1, a2, _) = test_setup();
+
+        let (p2p, mut evt) = spawn_holochain_p2p(Default::default()).await.unwrap();
+
+        let r_task = tokio::task::spawn(async move {
+            use tokio::stream::StreamExt;
+            while let Some(evt) = evt.next().await {
+                use crate::types::event::HolochainP2pEvent::*;
+                match evt {
+                    CountersigningRequest {
+                        respond, preflight, ..
+                    } => {
+                        let preflight: Vec<u8> = UnsafeBytes::from(preflight).into();
+                        assert_eq!(b"preflight".to_vec(), preflight);
+                        respond.r(Ok(
+                            async move { Ok(UnsafeBytes::from(b"ack".to_vec()).into()) }
+                                .boxed()
+                                .into(),
+                        ));
+                    }
+                    CountersigningResponse {
+                        respond,
+                        partial_signature,
+                        ..
+                    } => {
+                        assert_eq!(Signature(vec![7; 64]), partial_signature);
+                        respond.r(Ok(async move { Ok(()) }.boxed().into()));
+                    }
+                    _ => (),
+                }
+            }
+        });
+
+        p2p.join(dna.clone(), a1.clone()).await.unwrap();
+        p2p.join(dna.clone(), a2.clone()).await.unwrap();
+
+        let res = p2p
+            .countersigning_request(
+                dna.clone(),
+                a1.clone(),
+                a2.clone(),
+                UnsafeBytes::from(b"preflight".to_vec()).into(),
+            )
+            .await
+            .unwrap();
+        let res: Vec<u8> = UnsafeBytes::from(res).into();
+        assert_eq!(b"ack".to_vec(), res);
+
+        p2p.countersigning_response(dna, a2, a1, Signature(vec![7; 64]))
+            .await
+            .unwrap();
+
+        p2p.ghost_actor_shutdown().await.unwrap();
+        r_task.await.unwrap();
+    }
+
     #[tokio::test(threaded_scheduler)]
     // @TODO flakey test
     // ---- test::tests::test_publish_workflow stdout ----
@@ -158,7 +279,7 @@ mod tests {
     async fn test_publish_workflow() {
         let (dna, a1, a2, a3) = test_setup();
 
-        let (p2p, mut evt) = spawn_holochain_p2p().await.unwrap();
+        let (p2p, mut evt) = spawn_holochain_p2p(Default::default()).await.unwrap();
 
         let recv_count = Arc::new(std::sync::atomic::AtomicU8::new(0));
 
@@ -186,7 +307,7 @@ mod tests {
             holo_hash::hash_type::AnyDht::Header,
         );
 
-        p2p.publish(dna, a1, true, header_hash, vec![], Some(20))
+        p2p.publish(dna, a1, true, header_hash, vec![], vec![], Some(20))
             .await
             .unwrap();
 
@@ -196,11 +317,96 @@ mod tests {
         r_task.await.unwrap();
     }
 
+    #[tokio::test(threaded_scheduler)]
+    async fn test_publish_compresses_large_payloads_when_every_peer_accepts() {
+        let (dna, a1, a2, a3) = test_setup();
+
+        let (p2p, mut evt) = spawn_holochain_p2p(Default::default()).await.unwrap();
+
+        let entry = Entry::App(
+            SerializedBytes::from(UnsafeBytes::from(b"holochain".repeat(64 * 1024)))
+                .try_into()
+                .unwrap(),
+        );
+        let op = DhtOp::StoreEntry(
+            fixt!(Signature),
+            NewEntryHeader::Create(fixt!(Create)),
+            Box::new(entry),
+        );
+        let ops = vec![(fixt!(DhtOpHash), op)];
+
+        let recv_count = Arc::new(std::sync::atomic::AtomicU8::new(0));
+
+        let recv_count_clone = recv_count.clone();
+        let sent_ops = ops.clone();
+        let r_task = tokio::task::spawn(async move {
+            use tokio::stream::StreamExt;
+            let mut peer_metas = std::collections::HashMap::new();
+            while let Some(evt) = evt.next().await {
+                use crate::types::event::HolochainP2pEvent::*;
+                match evt {
+                    PutPeerMeta {
+                        respond,
+                        peer,
+                        peer_meta,
+                        ..
+                    } => {
+                        peer_metas.insert(peer, peer_meta);
+                        respond.r(Ok(async move { Ok(()) }.boxed().into()));
+                    }
+                    GetPeerMeta { respond, peer, .. } => {
+                        let peer_meta = peer_metas.get(&peer).cloned();
+                        respond.r(Ok(async move { Ok(peer_meta) }.boxed().into()));
+                    }
+                    GetSpacePeerMeta { respond, .. } => {
+                        let peers = peer_metas
+                            .iter()
+                            .map(|(peer, peer_meta)| (peer.clone(), Some(peer_meta.clone())))
+                            .collect();
+                        respond.r(Ok(async move { Ok(peers) }.boxed().into()));
+                    }
+                    Publish { respond, ops, .. } => {
+                        assert_eq!(sent_ops, ops);
+                        respond.r(Ok(async move { Ok(()) }.boxed().into()));
+                        recv_count_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    }
+                    _ => (),
+                }
+            }
+        });
+
+        p2p.join(dna.clone(), a1.clone()).await.unwrap();
+        p2p.join(dna.clone(), a2.clone()).await.unwrap();
+        p2p.join(dna.clone(), a3.clone()).await.unwrap();
+
+        let header_hash = holo_hash::AnyDhtHash::from_raw_bytes_and_type(
+            b"eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee".to_vec(),
+            holo_hash::hash_type::AnyDht::Header,
+        );
+
+        p2p.publish(dna, a1, true, header_hash, ops, vec![], Some(20))
+            .await
+            .unwrap();
+
+        assert_eq!(3, recv_count.load(std::sync::atomic::Ordering::SeqCst));
+
+        // Every agent of the space accepts the codec, so the one payload
+        // sent to all of them was compressed
+        let metrics = p2p.wire_compression_metrics().await.unwrap();
+        let publishes = metrics["Publish"];
+        assert_eq!(publishes.payloads, 1);
+        assert_eq!(publishes.compressed_payloads, 1);
+        assert!(publishes.bytes_after < publishes.bytes_before);
+
+        p2p.ghost_actor_shutdown().await.unwrap();
+        r_task.await.unwrap();
+    }
+
     #[tokio::test(threaded_scheduler)]
     async fn test_get_workflow() {
         let (dna, a1, a2, a3) = test_setup();
 
-        let (p2p, mut evt) = spawn_holochain_p2p().await.unwrap();
+        let (p2p, mut evt) = spawn_holochain_p2p(Default::default()).await.unwrap();
 
         let test_1 = GetElementResponse::GetHeader(Some(Box::new(WireElement::from_element(
             Element::new(
@@ -266,11 +472,64 @@ mod tests {
         r_task.await.unwrap();
     }
 
+    #[tokio::test(threaded_scheduler)]
+    async fn test_get_element_by_header_workflow() {
+        let (dna, a1, a2, _) = test_setup();
+
+        let (p2p, mut evt) = spawn_holochain_p2p(Default::default()).await.unwrap();
+
+        let header = HeaderHashed::from_content_sync(fixt!(Header));
+        let header_hash = header.as_hash().clone();
+        let test_1 = GetElementResponse::GetHeader(Some(Box::new(WireElement::from_element(
+            Element::new(
+                SignedHeaderHashed::with_presigned(header, fixt!(Signature)),
+                None,
+            ),
+            None,
+        ))));
+
+        let test_1_clone = test_1.clone();
+        let expected_hash = header_hash.clone();
+        let r_task = tokio::task::spawn(async move {
+            use tokio::stream::StreamExt;
+            while let Some(evt) = evt.next().await {
+                let test_1_clone = test_1_clone.clone();
+                use crate::types::event::HolochainP2pEvent::*;
+                match evt {
+                    GetElementByHeader {
+                        respond,
+                        header_hash,
+                        ..
+                    } => {
+                        assert_eq!(header_hash, expected_hash);
+                        respond.r(Ok(async move { Ok(test_1_clone) }.boxed().into()));
+                    }
+                    Get { .. } => panic!("the element should be fetched by its header"),
+                    _ => (),
+                }
+            }
+        });
+
+        p2p.join(dna.clone(), a1.clone()).await.unwrap();
+        p2p.join(dna.clone(), a2.clone()).await.unwrap();
+
+        let res = p2p
+            .get_element_by_header(dna, a1, header_hash, actor::GetOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(1, res.len());
+        assert_eq!(res[0], test_1);
+
+        p2p.ghost_actor_shutdown().await.unwrap();
+        r_task.await.unwrap();
+    }
+
     #[tokio::test(threaded_scheduler)]
     async fn test_get_links_workflow() {
         let (dna, a1, a2, _) = test_setup();
 
-        let (p2p, mut evt) = spawn_holochain_p2p().await.unwrap();
+        let (p2p, mut evt) = spawn_holochain_p2p(Default::default()).await.unwrap();
 
         let test_1 = GetLinksResponse {
             link_adds: vec![(fixt!(CreateLink), fixt!(Signature))],
@@ -26,6 +26,23 @@ pub enum HolochainP2pError {
     #[error("InvalidP2pMessage: {0}")]
     InvalidP2pMessage(String),
 
+    /// A remote peer sent bytes which could not be decoded
+    #[error("CorruptPayload: {context}")]
+    CorruptPayload {
+        /// What was being decoded, and why it failed
+        context: String,
+    },
+
+    /// A payload was compressed with a codec the receiving peer doesn't
+    /// support, see [compression]
+    #[error("UnsupportedWireCodec: {0:#04x}")]
+    UnsupportedWireCodec(u8),
+
+    /// A payload came in a version of the envelope this node doesn't know,
+    /// see [compression]
+    #[error("UnsupportedWireVersion: {0}")]
+    UnsupportedWireVersion(u8),
+
     /// Other
     #[error("Other: {0}")]
     Other(Box<dyn std::error::Error + Send + Sync>),
@@ -41,6 +58,11 @@ impl HolochainP2pError {
     pub fn invalid_p2p_message(s: String) -> Self {
         Self::InvalidP2pMessage(s)
     }
+
+    /// construct a corrupt payload error variant
+    pub fn corrupt_payload(context: String) -> Self {
+        Self::CorruptPayload { context }
+    }
 }
 
 // do some manual type translation so we get better error displays
@@ -92,6 +114,7 @@ impl From<&str> for HolochainP2pError {
 }
 
 pub mod actor;
+pub mod compression;
 pub mod event;
 
 pub(crate) mod wire;
@@ -106,6 +129,9 @@ macro_rules! to_and_from_kitsune {
                     self.clone().into_kitsune()
                 }
                 fn from_kitsune(k: &::std::sync::Arc<$k>) -> Self;
+                fn try_from_kitsune(
+                    k: &::std::sync::Arc<$k>,
+                ) -> actor::HolochainP2pResult<Self>;
             }
 
             impl $i for $h {
@@ -120,6 +146,20 @@ macro_rules! to_and_from_kitsune {
                 fn from_kitsune(k: &::std::sync::Arc<$k>) -> Self {
                     <$h>::from_raw_bytes((**k).clone().into()).into()
                 }
+
+                fn try_from_kitsune(
+                    k: &::std::sync::Arc<$k>,
+                ) -> actor::HolochainP2pResult<Self> {
+                    let bytes: Vec<u8> = (**k).clone().into();
+                    if bytes.len() != holo_hash::HOLO_HASH_SERIALIZED_LEN {
+                        return Err(HolochainP2pError::corrupt_payload(format!(
+                            "{} of {} bytes",
+                            stringify!($k),
+                            bytes.len(),
+                        )));
+                    }
+                    Ok(<$h>::from_raw_bytes(bytes).into())
+                }
             }
         )*
     };
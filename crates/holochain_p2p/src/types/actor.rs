@@ -2,8 +2,10 @@
 #![allow(clippy::too_many_arguments)]
 
 use crate::*;
-use holochain_zome_types::request::MetadataRequest;
+use holochain_zome_types::signature::Signature;
 use holochain_zome_types::zome::FunctionName;
+pub use kitsune_p2p::actor::NetworkInfo;
+use kitsune_p2p::agent_store::AgentInfoSigned;
 
 /// Request a validation package.
 pub struct GetValidationPackage {
@@ -107,8 +109,48 @@ pub struct GetMetaOptions {
     pub race_timeout_ms: Option<u64>,
 
     /// [Remote]
-    /// Tells the remote-end which metadata to return
-    pub metadata_request: MetadataRequest,
+    /// Return the headers that created or updated the entry.
+    pub include_headers: bool,
+
+    /// [Remote]
+    /// Return the deletes on the basis.
+    pub include_deletes: bool,
+
+    /// [Remote]
+    /// Return the updates on the basis.
+    pub include_updates: bool,
+
+    /// [Remote]
+    /// Return the link creates and link deletes on the basis.
+    pub include_links: bool,
+
+    /// [Remote]
+    /// Return the agent activity if the basis is an agent.
+    pub include_activity: bool,
+}
+
+impl GetMetaOptions {
+    /// Only request the metadata needed to resolve CRUD status
+    /// (deletes and updates).
+    pub fn crud() -> Self {
+        Self {
+            include_headers: false,
+            include_links: false,
+            include_activity: false,
+            ..Default::default()
+        }
+    }
+
+    /// Only request the link creates and deletes on the basis.
+    pub fn links() -> Self {
+        Self {
+            include_headers: false,
+            include_deletes: false,
+            include_updates: false,
+            include_activity: false,
+            ..Default::default()
+        }
+    }
 }
 
 impl Default for GetMetaOptions {
@@ -118,7 +160,11 @@ impl Default for GetMetaOptions {
             timeout_ms: None,
             as_race: true,
             race_timeout_ms: None,
-            metadata_request: MetadataRequest::default(),
+            include_headers: true,
+            include_deletes: true,
+            include_updates: true,
+            include_links: true,
+            include_activity: true,
         }
     }
 }
@@ -128,6 +174,8 @@ impl Default for GetMetaOptions {
 /// Fields tagged with `[Network]` are network-level controls.
 /// Fields tagged with `[Remote]` are controls that will be forwarded to the
 /// remote agent processing this `GetLinks` request.
+/// Links come back in the order of [holochain_types::link::link_order],
+/// so any future paging of the results should count positions in that order.
 pub struct GetLinksOptions {
     /// [Network]
     /// Timeout to await responses for aggregation.
@@ -143,6 +191,24 @@ impl Default for GetLinksOptions {
     }
 }
 
+#[derive(Debug, Clone)]
+/// Get the entries indexed under a key from the DHT.
+/// Fields tagged with `[Network]` are network-level controls.
+pub struct GetIndexedOptions {
+    /// [Network]
+    /// Timeout to await responses for aggregation.
+    /// Set to `None` for a default "best-effort".
+    /// Note - if all requests time-out you will receive an empty result,
+    /// not a timeout error.
+    pub timeout_ms: Option<u64>,
+}
+
+impl Default for GetIndexedOptions {
+    fn default() -> Self {
+        Self { timeout_ms: None }
+    }
+}
+
 ghost_actor::ghost_chan! {
     /// The HolochainP2pSender struct allows controlling the HolochainP2p
     /// actor instance.
@@ -150,6 +216,10 @@ ghost_actor::ghost_chan! {
         /// The p2p module must be informed at runtime which dna/agent pairs it should be tracking.
         fn join(dna_hash: DnaHash, agent_pub_key: AgentPubKey) -> ();
 
+        /// Track a dna/agent pair whose dht data lives in an environment it shares
+        /// with the other cells of its dna, so the space is only joined once.
+        fn join_shared(dna_hash: DnaHash, agent_pub_key: AgentPubKey) -> ();
+
         /// If a cell is deactivated, we'll need to \"leave\" the network module as well.
         fn leave(dna_hash: DnaHash, agent_pub_key: AgentPubKey) -> ();
 
@@ -171,6 +241,15 @@ ghost_actor::ghost_chan! {
             request_validation_receipt: bool,
             dht_hash: holo_hash::AnyDhtHash,
             ops: Vec<(holo_hash::DhtOpHash, holochain_types::dht_op::DhtOp)>,
+            requirements: Vec<(holo_hash::DhtOpHash, holochain_types::dht_op::OpRequirements)>,
+            timeout_ms: Option<u64>,
+        ) -> ();
+
+        /// Publish a change to an index to the authorities for its key.
+        fn publish_index(
+            dna_hash: DnaHash,
+            from_agent: AgentPubKey,
+            index_op: IndexOp,
             timeout_ms: Option<u64>,
         ) -> ();
 
@@ -185,6 +264,14 @@ ghost_actor::ghost_chan! {
             options: GetOptions,
         ) -> Vec<GetElementResponse>;
 
+        /// Get the element a header hash refers to from the header's authorities.
+        fn get_element_by_header(
+            dna_hash: DnaHash,
+            from_agent: AgentPubKey,
+            header_hash: HeaderHash,
+            options: GetOptions,
+        ) -> Vec<GetElementResponse>;
+
         /// Get metadata from the DHT.
         fn get_meta(
             dna_hash: DnaHash,
@@ -201,8 +288,50 @@ ghost_actor::ghost_chan! {
             options: GetLinksOptions,
         ) -> Vec<GetLinksResponse>;
 
-        /// Send a validation receipt to a remote node.
+        /// Get the entries indexed under a key from the DHT.
+        fn get_indexed(
+            dna_hash: DnaHash,
+            from_agent: AgentPubKey,
+            index_key: IndexKey,
+            options: GetIndexedOptions,
+        ) -> Vec<GetIndexedResponse>;
+
+        /// Send a validation receipt, or a bundle of receipts, to a remote node.
         fn send_validation_receipt(dna_hash: DnaHash, to_agent: AgentPubKey, from_agent: AgentPubKey, receipt: SerializedBytes) -> ();
+
+        /// Start a countersigning session with a remote agent by sending it
+        /// the preflight both agents will sign.
+        fn countersigning_request(
+            dna_hash: DnaHash,
+            from_agent: AgentPubKey,
+            to_agent: AgentPubKey,
+            preflight: SerializedBytes,
+        ) -> SerializedBytes;
+
+        /// Return our partial signature of a preflight to the agent which sent it.
+        fn countersigning_response(
+            dna_hash: DnaHash,
+            from_agent: AgentPubKey,
+            to_agent: AgentPubKey,
+            partial_signature: Signature,
+        ) -> ();
+
+        /// Send our full arc claim to a remote node, which may respond
+        /// with its own signed agent info.
+        fn request_arc_sync(
+            dna_hash: DnaHash,
+            from_agent: AgentPubKey,
+            to_agent: AgentPubKey,
+            from_arc: kitsune_p2p::dht_arc::DhtArc,
+            from_info: AgentInfoSigned,
+        ) -> Option<AgentInfoSigned>;
+
+        /// Report the gossip progress of a dna/agent pair on this network.
+        fn network_info(dna_hash: DnaHash, agent_pub_key: AgentPubKey) -> NetworkInfo;
+
+        /// Report how many bytes each class of payload has been sent in,
+        /// before and after compression.
+        fn wire_compression_metrics() -> crate::compression::WireCompressionMetrics;
     }
 }
 
@@ -0,0 +1,611 @@
+//! Compression of large payloads on the wire.
+//!
+//! Every payload holochain_p2p hands to kitsune starts with a four byte
+//! envelope: a marker byte, the envelope version, the tag of the codec its
+//! body is encoded with, and the tag of the codec its sender accepts a
+//! response in. Bodies larger than the configured threshold are compressed.
+//!
+//! A receiver which can't decode a body's codec, e.g. because it has
+//! compression disabled, responds with a rejection naming the codec instead.
+//! The sender then sends the request again uncompressed, and remembers not to
+//! compress requests to that peer.
+//!
+//! What each peer reads is learned from the payloads it sends us and kept in
+//! its [PeerMeta], which the conductor stores alongside the peer's agent info.
+//! A request to a single peer is compressed unless the peer is known not to
+//! read our codec. Kitsune picks the peers a notification or a request to
+//! many peers goes to, so those are only compressed when every peer of the
+//! space is known to read our codec. A peer which rejects a compressed request
+//! to many peers is sent it again uncompressed, on its own. A notification has
+//! no response to reject it with, so a peer which can't read one drops it,
+//! and is sent the ops by gossip instead.
+//!
+//! Peers built before the envelope send bare msgpack bodies. The marker byte
+//! is one msgpack never uses, so a payload which doesn't start with it comes
+//! from such a peer: it is read as a bare body, the response to it is bare,
+//! and later requests to that peer are bare too. This is a wire break for
+//! every other payload: a peer built before the envelope can't decode
+//! notifications, requests to many peers, or a request to a single peer
+//! which hasn't sent us anything yet.
+
+use crate::*;
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    io::{Read, Write},
+    sync::Mutex,
+};
+
+/// The first byte of every envelope. Msgpack never uses this byte,
+/// so no bare body from a peer built before the envelope starts with it.
+pub(crate) const ENVELOPE_MARKER: u8 = 0xc1;
+
+/// The version of the envelope this node sends and reads
+pub(crate) const ENVELOPE_VERSION: u8 = 1;
+
+/// The length of the envelope before the body
+const ENVELOPE_LEN: usize = 4;
+
+/// The tag of a body which is not compressed
+pub(crate) const CODEC_NONE: u8 = 0x00;
+
+/// The tag of a body compressed with deflate
+pub(crate) const CODEC_DEFLATE: u8 = 0x01;
+
+/// The codec byte of a response rejecting a request's codec.
+/// The byte after it is the tag of the codec which was rejected.
+pub(crate) const ENVELOPE_REJECTED: u8 = 0xff;
+
+/// How many bytes a body may decompress to, so that a small payload
+/// can't make the receiver allocate without bound
+const MAX_DECOMPRESSED_LEN: u64 = 256 * 1024 * 1024;
+
+/// When large payloads are compressed before they are sent
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct WireCompressionConfig {
+    /// Whether large payloads are compressed, and compressed payloads accepted.
+    /// A node with compression disabled rejects compressed requests,
+    /// and its peers fall back to sending it uncompressed requests.
+    pub enabled: bool,
+    /// Payloads larger than this many bytes are compressed
+    pub threshold_bytes: usize,
+}
+
+impl Default for WireCompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold_bytes: 16 * 1024,
+        }
+    }
+}
+
+/// How many payloads of one class were sent,
+/// and how many bytes their bodies took before and after compression
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PayloadClassMetrics {
+    /// How many payloads were sent
+    pub payloads: u64,
+    /// How many of the payloads were compressed
+    pub compressed_payloads: u64,
+    /// The bytes of the payloads' bodies before compression
+    pub bytes_before: u64,
+    /// The bytes of the payloads' bodies as they were sent
+    pub bytes_after: u64,
+}
+
+/// The payloads sent, by class, e.g. "CallRemote" for requests
+/// and "CallRemote response" for their responses
+pub type WireCompressionMetrics = BTreeMap<String, PayloadClassMetrics>;
+
+/// A way of compressing the body of a payload
+pub(crate) trait WireCodec: Send + Sync {
+    /// The tag which marks a body encoded with this codec
+    fn tag(&self) -> u8;
+
+    /// Compress a body
+    fn compress(&self, data: &[u8]) -> Vec<u8>;
+
+    /// Decompress a body, which may have come from anywhere
+    fn decompress(&self, data: &[u8]) -> std::io::Result<Vec<u8>>;
+}
+
+/// Compresses bodies with deflate
+pub(crate) struct Deflate;
+
+impl WireCodec for Deflate {
+    fn tag(&self) -> u8 {
+        CODEC_DEFLATE
+    }
+
+    fn compress(&self, data: &[u8]) -> Vec<u8> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(data)
+            .expect("writing to a Vec can't fail");
+        encoder.finish().expect("writing to a Vec can't fail")
+    }
+
+    fn decompress(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        DeflateDecoder::new(data)
+            .take(MAX_DECOMPRESSED_LEN + 1)
+            .read_to_end(&mut out)?;
+        if out.len() as u64 > MAX_DECOMPRESSED_LEN {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "decompressed body is too large",
+            ));
+        }
+        Ok(out)
+    }
+}
+
+/// The body of a payload which has been taken out of its envelope
+#[derive(Debug)]
+pub(crate) struct Opened {
+    pub body: Vec<u8>,
+    /// The codec the sender accepts a response in,
+    /// or None if it was sent by a peer built before the envelope
+    pub accepts: Option<u8>,
+}
+
+/// What a peer reads on the wire
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum PeerWire {
+    /// The peer reads envelopes, and bodies in the codec with this tag
+    Enveloped {
+        /// The tag of the codec the peer accepts
+        accepts: u8,
+    },
+    /// The peer was built before the envelope and only reads bare bodies
+    Bare,
+}
+
+/// What this node has learned about a remote peer
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PeerMeta {
+    /// What the peer reads on the wire, once it is known
+    pub wire: Option<PeerWire>,
+}
+
+/// Puts payloads in envelopes and takes them out again.
+/// Clones share the same metrics.
+#[derive(Clone)]
+pub(crate) struct WireCompression {
+    config: WireCompressionConfig,
+    codec: Arc<dyn WireCodec>,
+    metrics: Arc<Mutex<WireCompressionMetrics>>,
+}
+
+impl WireCompression {
+    pub fn new(config: WireCompressionConfig) -> Self {
+        Self {
+            config,
+            codec: Arc::new(Deflate),
+            metrics: Default::default(),
+        }
+    }
+
+    /// The codec this node accepts responses in
+    pub fn accept_tag(&self) -> u8 {
+        if self.config.enabled {
+            self.codec.tag()
+        } else {
+            CODEC_NONE
+        }
+    }
+
+    /// Put a body in an envelope, compressing it if `compress` is set,
+    /// compression is enabled and the body is over the threshold.
+    /// A body which doesn't get smaller is sent uncompressed.
+    /// Returns the payload and whether it was compressed.
+    pub fn seal(&self, class: &str, body: &[u8], compress: bool, accept: u8) -> (Vec<u8>, bool) {
+        let compressed =
+            if compress && self.config.enabled && body.len() > self.config.threshold_bytes {
+                Some(self.codec.compress(body)).filter(|c| c.len() < body.len())
+            } else {
+                None
+            };
+        let (tag, sent) = match &compressed {
+            Some(compressed) => (self.codec.tag(), &compressed[..]),
+            None => (CODEC_NONE, body),
+        };
+
+        self.record(class, body.len(), sent.len(), compressed.is_some());
+
+        let mut payload = Vec::with_capacity(sent.len() + ENVELOPE_LEN);
+        payload.extend_from_slice(&[ENVELOPE_MARKER, ENVELOPE_VERSION, tag, accept]);
+        payload.extend_from_slice(sent);
+        (payload, compressed.is_some())
+    }
+
+    /// Send a body without an envelope, to a peer built before it
+    pub fn seal_bare(&self, class: &str, body: Vec<u8>) -> Vec<u8> {
+        self.record(class, body.len(), body.len(), false);
+        body
+    }
+
+    fn record(&self, class: &str, before: usize, after: usize, compressed: bool) {
+        let mut metrics = self.metrics.lock().expect("metrics lock poisoned");
+        let class = metrics.entry(class.to_string()).or_default();
+        class.payloads += 1;
+        class.compressed_payloads += compressed as u64;
+        class.bytes_before += before as u64;
+        class.bytes_after += after as u64;
+    }
+
+    /// Put a request in an envelope, accepting a response in our codec
+    pub fn seal_request(&self, class: &str, body: &[u8], compress: bool) -> (Vec<u8>, bool) {
+        self.seal(class, body, compress, self.accept_tag())
+    }
+
+    /// Put a response in an envelope, compressed if the requester accepts our
+    /// codec, or send it bare if the request came without an envelope
+    pub fn seal_response(&self, class: &str, body: &[u8], accepts: Option<u8>) -> Vec<u8> {
+        let class = format!("{} response", class);
+        match accepts {
+            Some(accepts) => {
+                let compress = accepts == self.codec.tag();
+                self.seal(&class, body, compress, CODEC_NONE).0
+            }
+            None => self.seal_bare(&class, body.to_vec()),
+        }
+    }
+
+    /// The response rejecting a request whose codec we can't decode
+    pub fn reject(tag: u8) -> Vec<u8> {
+        vec![ENVELOPE_MARKER, ENVELOPE_VERSION, ENVELOPE_REJECTED, tag]
+    }
+
+    /// Take a body out of its envelope, decompressing it if needed.
+    /// A payload which doesn't start with the envelope marker is a bare body
+    /// from a peer built before the envelope.
+    /// A codec we can't decode, or a rejection of our own codec,
+    /// is an UnsupportedWireCodec error.
+    pub fn open(&self, payload: Vec<u8>) -> actor::HolochainP2pResult<Opened> {
+        match payload.first() {
+            Some(&ENVELOPE_MARKER) => (),
+            Some(_) => {
+                return Ok(Opened {
+                    body: payload,
+                    accepts: None,
+                })
+            }
+            None => {
+                return Err(HolochainP2pError::corrupt_payload(
+                    "envelope: empty payload".to_string(),
+                ))
+            }
+        }
+        let (version, tag, accepts) = match payload.get(1..ENVELOPE_LEN) {
+            Some(&[version, tag, accepts]) => (version, tag, accepts),
+            _ => {
+                return Err(HolochainP2pError::corrupt_payload(
+                    "envelope: too short".to_string(),
+                ))
+            }
+        };
+        if version != ENVELOPE_VERSION {
+            return Err(HolochainP2pError::UnsupportedWireVersion(version));
+        }
+        let body = match tag {
+            CODEC_NONE => {
+                let mut payload = payload;
+                payload.drain(..ENVELOPE_LEN);
+                payload
+            }
+            tag if self.config.enabled && tag == self.codec.tag() => self
+                .codec
+                .decompress(&payload[ENVELOPE_LEN..])
+                .map_err(|e| HolochainP2pError::corrupt_payload(format!("envelope: {}", e)))?,
+            ENVELOPE_REJECTED => return Err(HolochainP2pError::UnsupportedWireCodec(accepts)),
+            tag => return Err(HolochainP2pError::UnsupportedWireCodec(tag)),
+        };
+        Ok(Opened {
+            body,
+            accepts: Some(accepts),
+        })
+    }
+
+    /// Whether requests to a peer may be compressed.
+    /// A peer we know nothing about is tried with compression.
+    pub fn may_compress(&self, peer: Option<PeerWire>) -> bool {
+        self.config.enabled
+            && match peer {
+                Some(PeerWire::Enveloped { accepts }) => accepts == self.codec.tag(),
+                Some(PeerWire::Bare) => false,
+                None => true,
+            }
+    }
+
+    /// Whether a payload to whichever peers of a space kitsune picks may be
+    /// compressed, given what we know of every peer of the space
+    pub fn may_compress_all(&self, peers: &[Option<PeerWire>]) -> bool {
+        self.config.enabled
+            && !peers.is_empty()
+            && peers.iter().all(|peer| match peer {
+                Some(PeerWire::Enveloped { accepts }) => *accepts == self.codec.tag(),
+                _ => false,
+            })
+    }
+
+    /// What a peer reads, from the codec it accepts in a payload it sent us
+    pub fn learn(&self, accepts: Option<u8>) -> PeerWire {
+        match accepts {
+            Some(accepts) => PeerWire::Enveloped { accepts },
+            None => PeerWire::Bare,
+        }
+    }
+
+    /// What this node reads
+    pub fn own_wire(&self) -> PeerWire {
+        PeerWire::Enveloped {
+            accepts: self.accept_tag(),
+        }
+    }
+
+    /// Send a request to one peer, compressed if it's large and the peer may
+    /// accept it. If the peer rejects the compressed request it is sent again
+    /// uncompressed, once.
+    /// A peer built before the envelope is sent the bare body.
+    /// Returns the body of the response, and what the peer was learned to
+    /// read if it rejected the compressed request.
+    pub async fn request_single<F, Fut, E>(
+        &self,
+        class: &str,
+        peer: Option<PeerWire>,
+        body: Vec<u8>,
+        send: F,
+    ) -> actor::HolochainP2pResult<(Vec<u8>, Option<PeerWire>)>
+    where
+        F: Fn(Vec<u8>) -> Fut,
+        Fut: Future<Output = Result<Vec<u8>, E>>,
+        HolochainP2pError: From<E>,
+    {
+        if peer == Some(PeerWire::Bare) {
+            let payload = self.seal_bare(class, body);
+            return Ok((self.open(send(payload).await?)?.body, None));
+        }
+        let (payload, compressed) = self.seal_request(class, &body, self.may_compress(peer));
+        match self.open(send(payload).await?) {
+            Err(HolochainP2pError::UnsupportedWireCodec(_)) if compressed => {
+                let (payload, _) = self.seal_request(class, &body, false);
+                let response = self.open(send(payload).await?)?.body;
+                Ok((response, Some(self.rejected_by())))
+            }
+            response => Ok((response?.body, None)),
+        }
+    }
+
+    /// What we learn of a peer which rejected a compressed request
+    pub fn rejected_by(&self) -> PeerWire {
+        PeerWire::Enveloped {
+            accepts: CODEC_NONE,
+        }
+    }
+
+    /// The payloads sent so far, by class
+    pub fn metrics(&self) -> WireCompressionMetrics {
+        self.metrics.lock().expect("metrics lock poisoned").clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn compression(enabled: bool) -> WireCompression {
+        WireCompression::new(WireCompressionConfig {
+            enabled,
+            threshold_bytes: 1024,
+        })
+    }
+
+    /// A body which compresses well
+    fn large_body() -> Vec<u8> {
+        b"holochain"
+            .iter()
+            .cycle()
+            .take(64 * 1024)
+            .cloned()
+            .collect()
+    }
+
+    /// Answer a request the way handle_call does, echoing the body back
+    fn respond(receiver: &WireCompression, class: &str, payload: Vec<u8>) -> Vec<u8> {
+        match receiver.open(payload) {
+            Ok(Opened { body, accepts }) => receiver.seal_response(class, &body, accepts),
+            Err(HolochainP2pError::UnsupportedWireCodec(tag)) => WireCompression::reject(tag),
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn only_payloads_over_the_threshold_are_compressed() {
+        let sender = compression(true);
+        let small = vec![0xdb; 1024];
+        let (payload, compressed) = sender.seal("Small", &small, true, CODEC_NONE);
+        assert!(!compressed);
+        assert_eq!(
+            payload[..3],
+            [ENVELOPE_MARKER, ENVELOPE_VERSION, CODEC_NONE]
+        );
+
+        let (payload, compressed) = sender.seal("Large", &large_body(), true, CODEC_NONE);
+        assert!(compressed);
+        assert_eq!(payload[2], CODEC_DEFLATE);
+
+        // A disabled sender never compresses
+        let (_, compressed) = compression(false).seal("Large", &large_body(), true, CODEC_NONE);
+        assert!(!compressed);
+
+        let metrics = sender.metrics();
+        assert_eq!(
+            metrics["Small"],
+            PayloadClassMetrics {
+                payloads: 1,
+                compressed_payloads: 0,
+                bytes_before: 1024,
+                bytes_after: 1024,
+            }
+        );
+        assert_eq!(metrics["Large"].compressed_payloads, 1);
+        assert_eq!(metrics["Large"].bytes_before, 64 * 1024);
+        assert!(metrics["Large"].bytes_after < 1024);
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn mixed_pair_falls_back_to_uncompressed() {
+        let sender = compression(true);
+        let receiver = compression(false);
+        let sends = AtomicUsize::new(0);
+        let send = |payload| {
+            sends.fetch_add(1, Ordering::SeqCst);
+            let response = respond(&receiver, "CallRemote", payload);
+            async move { actor::HolochainP2pResult::Ok(response) }
+        };
+
+        // The compressed request is rejected and sent again uncompressed
+        let body = large_body();
+        let (response, learned) = sender
+            .request_single("CallRemote", None, body.clone(), &send)
+            .await
+            .unwrap();
+        assert_eq!(response, body);
+        assert_eq!(sends.load(Ordering::SeqCst), 2);
+        assert_eq!(learned, Some(sender.rejected_by()));
+        assert!(!sender.may_compress(learned));
+
+        // After which requests to the peer go uncompressed the first time
+        let (response, learned) = sender
+            .request_single("CallRemote", learned, body.clone(), &send)
+            .await
+            .unwrap();
+        assert_eq!(response, body);
+        assert_eq!(sends.load(Ordering::SeqCst), 3);
+        assert_eq!(learned, None);
+
+        let metrics = sender.metrics()["CallRemote"];
+        assert_eq!(metrics.payloads, 3);
+        assert_eq!(metrics.compressed_payloads, 1);
+        // The receiver doesn't accept compressed responses either
+        let metrics = receiver.metrics()["CallRemote response"];
+        assert_eq!(metrics.compressed_payloads, 0);
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn responses_are_compressed_for_requesters_which_accept_them() {
+        let sender = compression(true);
+        let receiver = compression(true);
+        let send = |payload| {
+            let response = respond(&receiver, "GetValidationPackage", payload);
+            async move { actor::HolochainP2pResult::Ok(response) }
+        };
+
+        // A small request gets a large response
+        let body = large_body();
+        let (payload, compressed) =
+            sender.seal("GetValidationPackage", &[0xdb; 8], true, CODEC_DEFLATE);
+        assert!(!compressed);
+        let response = receiver.open(payload).unwrap();
+        assert_eq!(response.accepts, Some(CODEC_DEFLATE));
+        let response = receiver.seal_response("GetValidationPackage", &body, response.accepts);
+        assert_eq!(response[2], CODEC_DEFLATE);
+        assert_eq!(sender.open(response).unwrap().body, body);
+
+        sender
+            .request_single("GetValidationPackage", None, body, &send)
+            .await
+            .unwrap();
+        let metrics = receiver.metrics()["GetValidationPackage response"];
+        assert_eq!(metrics.payloads, 2);
+        assert_eq!(metrics.compressed_payloads, 2);
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn peers_built_before_the_envelope_are_read_and_answered_bare() {
+        let node = compression(true);
+        // What a peer built before the envelope sends: a bare msgpack body
+        let bare = vec![0x82, 0xa4, 0xdb];
+
+        let request = node.open(bare.clone()).unwrap();
+        assert_eq!(request.body, bare);
+        assert_eq!(request.accepts, None);
+        assert_eq!(
+            node.seal_response("CallRemote", &large_body(), request.accepts),
+            large_body()
+        );
+
+        // Once the peer is known to be bare, requests to it are bare too
+        let peer = Some(node.learn(request.accepts));
+        assert_eq!(peer, Some(PeerWire::Bare));
+        assert!(!node.may_compress(peer));
+        let sent = Mutex::new(Vec::new());
+        let send = |payload: Vec<u8>| {
+            sent.lock().unwrap().push(payload.clone());
+            async move { actor::HolochainP2pResult::Ok(payload) }
+        };
+        let (response, _) = node
+            .request_single("CallRemote", peer, large_body(), &send)
+            .await
+            .unwrap();
+        assert_eq!(response, large_body());
+        assert_eq!(*sent.lock().unwrap(), vec![large_body()]);
+    }
+
+    #[test]
+    fn payloads_to_many_peers_are_compressed_only_if_all_accept() {
+        let node = compression(true);
+        let deflate = Some(node.learn(Some(CODEC_DEFLATE)));
+        let none = Some(node.learn(Some(CODEC_NONE)));
+        assert!(node.may_compress_all(&[deflate, deflate]));
+        assert!(!node.may_compress_all(&[deflate, none]));
+        assert!(!node.may_compress_all(&[deflate, Some(PeerWire::Bare)]));
+        // A peer we know nothing about may not read our codec,
+        // and with no peers there is nothing to go by
+        assert!(!node.may_compress_all(&[deflate, None]));
+        assert!(!node.may_compress_all(&[]));
+        // A disabled node never compresses
+        assert!(!compression(false).may_compress_all(&[deflate]));
+    }
+
+    #[test]
+    fn mangled_envelopes_are_corrupt_or_unsupported() {
+        let receiver = compression(true);
+        assert!(matches!(
+            receiver.open(vec![]),
+            Err(HolochainP2pError::CorruptPayload { .. })
+        ));
+        assert!(matches!(
+            receiver.open(vec![ENVELOPE_MARKER, ENVELOPE_VERSION]),
+            Err(HolochainP2pError::CorruptPayload { .. })
+        ));
+        assert!(matches!(
+            receiver.open(vec![
+                ENVELOPE_MARKER,
+                ENVELOPE_VERSION,
+                CODEC_DEFLATE,
+                CODEC_NONE,
+                0xdb,
+                0xdb
+            ]),
+            Err(HolochainP2pError::CorruptPayload { .. })
+        ));
+        assert!(matches!(
+            receiver.open(vec![ENVELOPE_MARKER, ENVELOPE_VERSION, 0x7f, CODEC_NONE]),
+            Err(HolochainP2pError::UnsupportedWireCodec(0x7f))
+        ));
+        assert!(matches!(
+            receiver.open(vec![ENVELOPE_MARKER, 0x7f, CODEC_NONE, CODEC_NONE]),
+            Err(HolochainP2pError::UnsupportedWireVersion(0x7f))
+        ));
+        assert!(matches!(
+            receiver.open(WireCompression::reject(CODEC_DEFLATE)),
+            Err(HolochainP2pError::UnsupportedWireCodec(CODEC_DEFLATE))
+        ));
+    }
+}
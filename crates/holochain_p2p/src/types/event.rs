@@ -26,12 +26,52 @@ impl From<&actor::GetOptions> for GetOptions {
 }
 
 /// GetMeta options help control how the get is processed at various levels.
+/// Each flag selects a class of metadata for the remote-end to return.
+/// Missing flags default to `true` so older requests still get everything.
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
-pub struct GetMetaOptions {}
+pub struct GetMetaOptions {
+    /// Return the headers that created or updated the entry.
+    #[serde(default = "default_true")]
+    pub include_headers: bool,
+    /// Return the deletes on the basis.
+    #[serde(default = "default_true")]
+    pub include_deletes: bool,
+    /// Return the updates on the basis.
+    #[serde(default = "default_true")]
+    pub include_updates: bool,
+    /// Return the link creates and link deletes on the basis.
+    #[serde(default = "default_true")]
+    pub include_links: bool,
+    /// Return the agent activity if the basis is an agent.
+    #[serde(default = "default_true")]
+    pub include_activity: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for GetMetaOptions {
+    fn default() -> Self {
+        Self {
+            include_headers: true,
+            include_deletes: true,
+            include_updates: true,
+            include_links: true,
+            include_activity: true,
+        }
+    }
+}
 
 impl From<&actor::GetMetaOptions> for GetMetaOptions {
-    fn from(_a: &actor::GetMetaOptions) -> Self {
-        Self {}
+    fn from(a: &actor::GetMetaOptions) -> Self {
+        Self {
+            include_headers: a.include_headers,
+            include_deletes: a.include_deletes,
+            include_updates: a.include_updates,
+            include_links: a.include_links,
+            include_activity: a.include_activity,
+        }
     }
 }
 
@@ -45,6 +85,16 @@ impl From<&actor::GetLinksOptions> for GetLinksOptions {
     }
 }
 
+/// GetIndexed options help control how the get is processed at various levels.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct GetIndexedOptions {}
+
+impl From<&actor::GetIndexedOptions> for GetIndexedOptions {
+    fn from(_a: &actor::GetIndexedOptions) -> Self {
+        Self {}
+    }
+}
+
 ghost_actor::ghost_chan! {
     /// The HolochainP2pEvent stream allows handling events generated from
     /// the HolochainP2p actor.
@@ -55,6 +105,27 @@ ghost_actor::ghost_chan! {
         /// We need to get previously stored agent info.
         fn get_agent_info_signed(dna_hash: DnaHash, to_agent: AgentPubKey, kitsune_space: Arc<kitsune_p2p::KitsuneSpace>, kitsune_agent: Arc<kitsune_p2p::KitsuneAgent>) -> Option<AgentInfoSigned>;
 
+        /// We learned something about a peer, to keep in its peer metadata.
+        fn put_peer_meta(dna_hash: DnaHash, to_agent: AgentPubKey, peer: AgentPubKey, peer_meta: compression::PeerMeta) -> ();
+
+        /// We need what we have learned about a peer, if anything.
+        fn get_peer_meta(dna_hash: DnaHash, to_agent: AgentPubKey, peer: AgentPubKey) -> Option<compression::PeerMeta>;
+
+        /// We need what we have learned about every peer we hold agent info for
+        /// in this dna's space, with `None` for a peer we know nothing about.
+        fn get_space_peer_meta(dna_hash: DnaHash, to_agent: AgentPubKey) -> Vec<(AgentPubKey, Option<compression::PeerMeta>)>;
+
+        /// A remote node is sending us its full arc claim, so we can route
+        /// gossip to it. We respond with our own signed agent info to
+        /// reciprocate, or `None` to decline.
+        fn request_arc_sync(
+            dna_hash: DnaHash,
+            to_agent: AgentPubKey,
+            from_agent: AgentPubKey,
+            from_arc: kitsune_p2p::dht_arc::DhtArc,
+            from_info: AgentInfoSigned,
+        ) -> Option<AgentInfoSigned>;
+
         /// A remote node is attempting to make a remote call on us.
         fn call_remote(
             dna_hash: DnaHash,
@@ -74,6 +145,16 @@ ghost_actor::ghost_chan! {
             request_validation_receipt: bool,
             dht_hash: holo_hash::AnyDhtHash,
             ops: Vec<(holo_hash::DhtOpHash, holochain_types::dht_op::DhtOp)>,
+            requirements: Vec<(holo_hash::DhtOpHash, holochain_types::dht_op::OpRequirements)>,
+        ) -> ();
+
+        /// A remote node is publishing a change to an index
+        /// for a key we claim to be holding.
+        fn publish_index(
+            dna_hash: DnaHash,
+            to_agent: AgentPubKey,
+            from_agent: AgentPubKey,
+            index_op: IndexOp,
         ) -> ();
 
         /// A remote node is requesting a validation package.
@@ -93,6 +174,14 @@ ghost_actor::ghost_chan! {
             options: GetOptions,
         ) -> GetElementResponse;
 
+        /// A remote node is requesting the element a header hash refers to.
+        fn get_element_by_header(
+            dna_hash: DnaHash,
+            to_agent: AgentPubKey,
+            header_hash: HeaderHash,
+            options: GetOptions,
+        ) -> GetElementResponse;
+
         /// A remote node is requesting metadata from us.
         fn get_meta(
             dna_hash: DnaHash,
@@ -109,7 +198,35 @@ ghost_actor::ghost_chan! {
             options: GetLinksOptions,
         ) -> GetLinksResponse;
 
-        /// A remote node has sent us a validation receipt.
+        /// A remote node is requesting our slice of an index.
+        fn get_indexed(
+            dna_hash: DnaHash,
+            to_agent: AgentPubKey,
+            index_key: IndexKey,
+            options: GetIndexedOptions,
+        ) -> GetIndexedResponse;
+
+        /// A remote agent is starting a countersigning session with us
+        /// by sending the preflight both agents will sign.
+        /// We respond once the session is recorded.
+        fn countersigning_request(
+            dna_hash: DnaHash,
+            to_agent: AgentPubKey,
+            from_agent: AgentPubKey,
+            preflight: SerializedBytes,
+        ) -> SerializedBytes;
+
+        /// A remote agent we sent a preflight to has signed it,
+        /// and is returning its partial signature.
+        fn countersigning_response(
+            dna_hash: DnaHash,
+            to_agent: AgentPubKey,
+            from_agent: AgentPubKey,
+            partial_signature: Signature,
+        ) -> ();
+
+        /// A remote node has sent us a validation receipt,
+        /// or a bundle of receipts for several ops.
         fn validation_receipt_received(
             dna_hash: DnaHash,
             to_agent: AgentPubKey,
@@ -117,13 +234,14 @@ ghost_actor::ghost_chan! {
         ) -> ();
 
         /// The p2p module wishes to query our DhtOpHash store.
+        /// The answer says whether we cover the arc at all.
         fn fetch_op_hashes_for_constraints(
             dna_hash: DnaHash,
             to_agent: AgentPubKey,
             dht_arc: kitsune_p2p::dht_arc::DhtArc,
             since: holochain_types::Timestamp,
             until: holochain_types::Timestamp,
-        ) -> Vec<holo_hash::DhtOpHash>;
+        ) -> kitsune_p2p::event::ArcCoverage<Vec<holo_hash::DhtOpHash>>;
 
         /// The p2p module needs access to the content for a given set of DhtOpHashes.
         fn fetch_op_hash_data(
@@ -141,6 +259,22 @@ ghost_actor::ghost_chan! {
             // The data to sign.
             data: Vec<u8>,
         ) -> Signature;
+
+        /// An agent has connected to the network of a dna.
+        fn peer_connected(
+            dna_hash: DnaHash,
+            // The agent which connected.
+            to_agent: AgentPubKey,
+            // The urls it can be reached at, if known.
+            urls: kitsune_p2p::agent_store::Urls,
+        ) -> ();
+
+        /// An agent has disconnected from the network of a dna.
+        fn peer_disconnected(
+            dna_hash: DnaHash,
+            // The agent which disconnected.
+            to_agent: AgentPubKey,
+        ) -> ();
     }
 }
 
@@ -150,16 +284,27 @@ macro_rules! match_p2p_evt {
         match $h {
             HolochainP2pEvent::CallRemote { $i, .. } => { $($t)* }
             HolochainP2pEvent::Publish { $i, .. } => { $($t)* }
+            HolochainP2pEvent::PublishIndex { $i, .. } => { $($t)* }
             HolochainP2pEvent::GetValidationPackage { $i, .. } => { $($t)* }
             HolochainP2pEvent::Get { $i, .. } => { $($t)* }
+            HolochainP2pEvent::GetElementByHeader { $i, .. } => { $($t)* }
             HolochainP2pEvent::GetMeta { $i, .. } => { $($t)* }
             HolochainP2pEvent::GetLinks { $i, .. } => { $($t)* }
+            HolochainP2pEvent::GetIndexed { $i, .. } => { $($t)* }
+            HolochainP2pEvent::CountersigningRequest { $i, .. } => { $($t)* }
+            HolochainP2pEvent::CountersigningResponse { $i, .. } => { $($t)* }
             HolochainP2pEvent::ValidationReceiptReceived { $i, .. } => { $($t)* }
             HolochainP2pEvent::FetchOpHashesForConstraints { $i, .. } => { $($t)* }
             HolochainP2pEvent::FetchOpHashData { $i, .. } => { $($t)* }
             HolochainP2pEvent::SignNetworkData { $i, .. } => { $($t)* }
             HolochainP2pEvent::PutAgentInfoSigned { $i, .. } => { $($t)* }
             HolochainP2pEvent::GetAgentInfoSigned { $i, .. } => { $($t)* }
+            HolochainP2pEvent::PutPeerMeta { $i, .. } => { $($t)* }
+            HolochainP2pEvent::GetPeerMeta { $i, .. } => { $($t)* }
+            HolochainP2pEvent::GetSpacePeerMeta { $i, .. } => { $($t)* }
+            HolochainP2pEvent::RequestArcSync { $i, .. } => { $($t)* }
+            HolochainP2pEvent::PeerConnected { $i, .. } => { $($t)* }
+            HolochainP2pEvent::PeerDisconnected { $i, .. } => { $($t)* }
         }
     };
 }
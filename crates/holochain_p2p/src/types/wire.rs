@@ -1,5 +1,17 @@
 use crate::*;
-use holochain_zome_types::zome::FunctionName;
+use holochain_zome_types::{signature::Signature, zome::FunctionName};
+use kitsune_p2p::{agent_store::AgentInfoSigned, dht_arc::DhtArc};
+
+/// Decode bytes which came from a remote peer.
+/// Any failure is a CorruptPayload error naming what was being decoded.
+pub(crate) fn decode_remote<T>(context: &str, data: Vec<u8>) -> actor::HolochainP2pResult<T>
+where
+    T: TryFrom<SerializedBytes, Error = SerializedBytesError>,
+{
+    let data: SerializedBytes = UnsafeBytes::from(data).into();
+    data.try_into()
+        .map_err(|e| HolochainP2pError::corrupt_payload(format!("{}: {}", context, e)))
+}
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, SerializedBytes)]
 pub(crate) struct WireDhtOpData {
@@ -13,9 +25,46 @@ impl WireDhtOpData {
         Ok(UnsafeBytes::from(SerializedBytes::try_from(self)?).into())
     }
 
-    pub fn decode(data: Vec<u8>) -> Result<Self, SerializedBytesError> {
-        let request: SerializedBytes = UnsafeBytes::from(data).into();
-        Ok(request.try_into()?)
+    pub fn decode(data: Vec<u8>) -> actor::HolochainP2pResult<Self> {
+        decode_remote("WireDhtOpData", data)
+    }
+}
+
+/// A [DhtArc] as it is sent over the wire
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct WireDhtArc {
+    pub center_loc: u32,
+    pub half_length: u32,
+}
+
+impl From<DhtArc> for WireDhtArc {
+    fn from(arc: DhtArc) -> Self {
+        Self {
+            center_loc: arc.center_loc.into(),
+            half_length: arc.half_length,
+        }
+    }
+}
+
+impl From<WireDhtArc> for DhtArc {
+    fn from(arc: WireDhtArc) -> Self {
+        DhtArc::new(arc.center_loc, arc.half_length)
+    }
+}
+
+/// The response to a [WireMessage::RequestArcSync]
+#[derive(Debug, serde::Serialize, serde::Deserialize, SerializedBytes)]
+pub(crate) struct WireArcSyncResponse {
+    pub agent_info: Option<AgentInfoSigned>,
+}
+
+impl WireArcSyncResponse {
+    pub fn encode(self) -> Result<Vec<u8>, SerializedBytesError> {
+        Ok(UnsafeBytes::from(SerializedBytes::try_from(self)?).into())
+    }
+
+    pub fn decode(data: Vec<u8>) -> actor::HolochainP2pResult<Self> {
+        decode_remote("WireArcSyncResponse", data)
     }
 }
 
@@ -33,15 +82,36 @@ pub(crate) enum WireMessage {
         request_validation_receipt: bool,
         dht_hash: holo_hash::AnyDhtHash,
         ops: Vec<(holo_hash::DhtOpHash, holochain_types::dht_op::DhtOp)>,
+        /// The requirements the author claims for the StoreElement
+        /// and StoreEntry ops of app entries
+        #[serde(default)]
+        requirements: Vec<(
+            holo_hash::DhtOpHash,
+            holochain_types::dht_op::OpRequirements,
+        )>,
+    },
+    PublishIndex {
+        index_op: IndexOp,
     },
     ValidationReceipt {
         #[serde(with = "serde_bytes")]
         receipt: Vec<u8>,
     },
+    CountersigningRequest {
+        #[serde(with = "serde_bytes")]
+        preflight: Vec<u8>,
+    },
+    CountersigningResponse {
+        partial_signature: Signature,
+    },
     Get {
         dht_hash: holo_hash::AnyDhtHash,
         options: event::GetOptions,
     },
+    GetElementByHeader {
+        header_hash: HeaderHash,
+        options: event::GetOptions,
+    },
     GetMeta {
         dht_hash: holo_hash::AnyDhtHash,
         options: event::GetMetaOptions,
@@ -50,9 +120,17 @@ pub(crate) enum WireMessage {
         link_key: WireLinkMetaKey,
         options: event::GetLinksOptions,
     },
+    GetIndexed {
+        index_key: IndexKey,
+        options: event::GetIndexedOptions,
+    },
     GetValidationPackage {
         header_hash: HeaderHash,
     },
+    RequestArcSync {
+        from_arc: WireDhtArc,
+        from_info: AgentInfoSigned,
+    },
 }
 
 impl WireMessage {
@@ -60,9 +138,28 @@ impl WireMessage {
         Ok(UnsafeBytes::from(SerializedBytes::try_from(self)?).into())
     }
 
-    pub fn decode(data: Vec<u8>) -> Result<Self, SerializedBytesError> {
-        let request: SerializedBytes = UnsafeBytes::from(data).into();
-        Ok(request.try_into()?)
+    pub fn decode(data: Vec<u8>) -> actor::HolochainP2pResult<Self> {
+        decode_remote("WireMessage", data)
+    }
+
+    /// The class of payload this message is counted under in the
+    /// [compression](crate::compression) metrics
+    pub fn class(&self) -> &'static str {
+        match self {
+            Self::CallRemote { .. } => "CallRemote",
+            Self::Publish { .. } => "Publish",
+            Self::PublishIndex { .. } => "PublishIndex",
+            Self::ValidationReceipt { .. } => "ValidationReceipt",
+            Self::CountersigningRequest { .. } => "CountersigningRequest",
+            Self::CountersigningResponse { .. } => "CountersigningResponse",
+            Self::Get { .. } => "Get",
+            Self::GetElementByHeader { .. } => "GetElementByHeader",
+            Self::GetMeta { .. } => "GetMeta",
+            Self::GetLinks { .. } => "GetLinks",
+            Self::GetIndexed { .. } => "GetIndexed",
+            Self::GetValidationPackage { .. } => "GetValidationPackage",
+            Self::RequestArcSync { .. } => "RequestArcSync",
+        }
     }
 
     pub fn call_remote(
@@ -83,24 +180,53 @@ impl WireMessage {
         request_validation_receipt: bool,
         dht_hash: holo_hash::AnyDhtHash,
         ops: Vec<(holo_hash::DhtOpHash, holochain_types::dht_op::DhtOp)>,
+        requirements: Vec<(
+            holo_hash::DhtOpHash,
+            holochain_types::dht_op::OpRequirements,
+        )>,
     ) -> WireMessage {
         Self::Publish {
             request_validation_receipt,
             dht_hash,
             ops,
+            requirements,
         }
     }
 
+    pub fn publish_index(index_op: IndexOp) -> WireMessage {
+        Self::PublishIndex { index_op }
+    }
+
     pub fn validation_receipt(receipt: SerializedBytes) -> WireMessage {
         Self::ValidationReceipt {
             receipt: UnsafeBytes::from(receipt).into(),
         }
     }
 
+    pub fn countersigning_request(preflight: SerializedBytes) -> WireMessage {
+        Self::CountersigningRequest {
+            preflight: UnsafeBytes::from(preflight).into(),
+        }
+    }
+
+    pub fn countersigning_response(partial_signature: Signature) -> WireMessage {
+        Self::CountersigningResponse { partial_signature }
+    }
+
     pub fn get(dht_hash: holo_hash::AnyDhtHash, options: event::GetOptions) -> WireMessage {
         Self::Get { dht_hash, options }
     }
 
+    pub fn get_element_by_header(
+        header_hash: HeaderHash,
+        options: event::GetOptions,
+    ) -> WireMessage {
+        Self::GetElementByHeader {
+            header_hash,
+            options,
+        }
+    }
+
     pub fn get_meta(
         dht_hash: holo_hash::AnyDhtHash,
         options: event::GetMetaOptions,
@@ -111,7 +237,21 @@ impl WireMessage {
     pub fn get_links(link_key: WireLinkMetaKey, options: event::GetLinksOptions) -> WireMessage {
         Self::GetLinks { link_key, options }
     }
+
+    pub fn get_indexed(index_key: IndexKey, options: event::GetIndexedOptions) -> WireMessage {
+        Self::GetIndexed { index_key, options }
+    }
     pub fn get_validation_package(header_hash: HeaderHash) -> WireMessage {
         Self::GetValidationPackage { header_hash }
     }
+
+    pub fn request_arc_sync(from_arc: DhtArc, from_info: AgentInfoSigned) -> WireMessage {
+        Self::RequestArcSync {
+            from_arc: from_arc.into(),
+            from_info,
+        }
+    }
 }
+
+#[cfg(test)]
+mod fuzz_tests;
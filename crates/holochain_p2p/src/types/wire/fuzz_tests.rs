@@ -0,0 +1,363 @@
+//! Feed arbitrary and mangled bytes to the decoders on the inbound path.
+//! Decoding must never panic, must only fail with a CorruptPayload error,
+//! and anything which does decode must be safe to use.
+
+use super::*;
+use crate::compression::*;
+use crate::types::AgentPubKeyExt;
+use holochain_types::{
+    dht_op::{DhtOp, OpRequirements},
+    element::GetElementResponse,
+    index::{GetIndexedResponse, IndexKey, IndexOp},
+    link::{GetLinksResponse, WireLinkMetaKey},
+    metadata::MetadataSet,
+    validate::ValidationPackageResponse,
+};
+use holochain_zome_types::{
+    header::{Dna, Header},
+    signature::Signature,
+    timestamp::Timestamp,
+    validate::RequiredValidationType,
+};
+use proptest::prelude::*;
+
+/// How many cases each property runs, to keep the suite fast in CI.
+const CASES: u32 = 256;
+
+/// The msgpack bin8 marker and length of a full hash
+const HASH_PREFIX: [u8; 2] = [0xc4, HOLO_HASH_SERIALIZED_LEN as u8];
+
+fn hash_bytes() -> Vec<u8> {
+    vec![0xdb; HOLO_HASH_SERIALIZED_LEN]
+}
+
+fn fixture_op() -> DhtOp {
+    DhtOp::RegisterAgentActivity(
+        Signature(vec![0xdb; 64]),
+        Header::Dna(Dna {
+            author: AgentPubKey::from_raw_bytes(hash_bytes()),
+            timestamp: Timestamp(0, 0),
+            hash: DnaHash::from_raw_bytes(hash_bytes()),
+        }),
+    )
+}
+
+/// Encoded wire messages which each contain at least one hash
+fn hash_fixtures() -> Vec<Vec<u8>> {
+    let entry_hash = EntryHash::from_raw_bytes(hash_bytes());
+    let dht_hash: AnyDhtHash = entry_hash.clone().into();
+    vec![
+        WireMessage::get(
+            dht_hash.clone(),
+            event::GetOptions {
+                follow_redirects: false,
+                all_live_headers_with_metadata: true,
+            },
+        )
+        .encode()
+        .unwrap(),
+        WireMessage::get_meta(
+            dht_hash.clone(),
+            event::GetMetaOptions {
+                include_headers: true,
+                include_deletes: true,
+                include_updates: true,
+                include_links: true,
+                include_activity: true,
+            },
+        )
+        .encode()
+        .unwrap(),
+        WireMessage::get_links(
+            WireLinkMetaKey::Base(entry_hash.clone()),
+            event::GetLinksOptions {},
+        )
+        .encode()
+        .unwrap(),
+        WireMessage::get_validation_package(HeaderHash::from_raw_bytes(hash_bytes()))
+            .encode()
+            .unwrap(),
+        WireMessage::publish(
+            false,
+            dht_hash.clone(),
+            vec![(DhtOpHash::from_raw_bytes(hash_bytes()), fixture_op())],
+            vec![(
+                DhtOpHash::from_raw_bytes(hash_bytes()),
+                OpRequirements {
+                    required_validations: 3.into(),
+                    required_validation_type: RequiredValidationType::SubChain,
+                },
+            )],
+        )
+        .encode()
+        .unwrap(),
+        WireMessage::publish_index(IndexOp::Register {
+            index_key: IndexKey {
+                zome_id: 0.into(),
+                entry_def_index: 0.into(),
+                key: b"key".to_vec(),
+            },
+            entry_hash: entry_hash.clone(),
+            header_hash: HeaderHash::from_raw_bytes(hash_bytes()),
+        })
+        .encode()
+        .unwrap(),
+        WireDhtOpData {
+            from_agent: AgentPubKey::from_raw_bytes(hash_bytes()),
+            dht_hash,
+            op_data: fixture_op(),
+        }
+        .encode()
+        .unwrap(),
+    ]
+}
+
+fn fixture_agent_info() -> AgentInfoSigned {
+    AgentInfoSigned::try_new(
+        kitsune_p2p::KitsuneSignature(vec![0xdb; 64]),
+        kitsune_p2p::agent_store::AgentInfo::new(
+            kitsune_p2p::KitsuneSpace::from(hash_bytes()),
+            kitsune_p2p::KitsuneAgent::from(hash_bytes()),
+            vec![],
+            0,
+        ),
+    )
+    .unwrap()
+}
+
+/// Encoded wire messages of every kind
+fn fixtures() -> Vec<Vec<u8>> {
+    let mut fixtures = hash_fixtures();
+    fixtures.push(
+        WireMessage::call_remote(
+            "zome".into(),
+            "fn".into(),
+            None,
+            UnsafeBytes::from(vec![0xdb; 8]).into(),
+        )
+        .encode()
+        .unwrap(),
+    );
+    fixtures.push(
+        WireMessage::validation_receipt(UnsafeBytes::from(vec![0xdb; 8]).into())
+            .encode()
+            .unwrap(),
+    );
+    fixtures.push(
+        WireMessage::countersigning_request(UnsafeBytes::from(vec![0xdb; 8]).into())
+            .encode()
+            .unwrap(),
+    );
+    fixtures.push(
+        WireMessage::countersigning_response(Signature(vec![0xdb; 64]))
+            .encode()
+            .unwrap(),
+    );
+    fixtures.push(
+        WireMessage::request_arc_sync(DhtArc::new(7, 100), fixture_agent_info())
+            .encode()
+            .unwrap(),
+    );
+    fixtures.push(
+        WireArcSyncResponse {
+            agent_info: Some(fixture_agent_info()),
+        }
+        .encode()
+        .unwrap(),
+    );
+    fixtures
+}
+
+/// Replace every full hash in an encoding with an empty one
+fn shrink_hashes(data: &[u8]) -> Vec<u8> {
+    let mut full_hash = HASH_PREFIX.to_vec();
+    full_hash.extend(hash_bytes());
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        if data[i..].starts_with(&full_hash) {
+            out.extend(&[0xc4, 0x00]);
+            i += full_hash.len();
+        } else {
+            out.push(data[i]);
+            i += 1;
+        }
+    }
+    out
+}
+
+fn assert_corrupt<T: std::fmt::Debug>(result: actor::HolochainP2pResult<T>) {
+    match result {
+        Err(HolochainP2pError::CorruptPayload { .. }) => (),
+        other => panic!("expected a CorruptPayload error, got: {:?}", other),
+    }
+}
+
+fn check_decode<T: std::fmt::Debug>(result: actor::HolochainP2pResult<T>) -> Option<T> {
+    match result {
+        Ok(t) => Some(t),
+        Err(HolochainP2pError::CorruptPayload { .. }) => None,
+        Err(e) => panic!("expected a CorruptPayload error, got: {:?}", e),
+    }
+}
+
+/// Run every inbound decoder over the data, using any hashes which decode
+fn check_all(data: Vec<u8>) {
+    match check_decode(WireMessage::decode(data.clone())) {
+        Some(WireMessage::Get { dht_hash, .. }) | Some(WireMessage::GetMeta { dht_hash, .. }) => {
+            dht_hash.get_loc();
+        }
+        Some(WireMessage::GetLinks { link_key, .. }) => {
+            link_key.basis().get_loc();
+        }
+        Some(WireMessage::GetIndexed { index_key, .. }) => {
+            index_key.basis().get_loc();
+        }
+        Some(WireMessage::GetElementByHeader { header_hash, .. })
+        | Some(WireMessage::GetValidationPackage { header_hash }) => {
+            header_hash.get_loc();
+        }
+        Some(WireMessage::Publish {
+            dht_hash,
+            ops,
+            requirements,
+            ..
+        }) => {
+            dht_hash.get_loc();
+            for (op_hash, _) in ops {
+                op_hash.get_loc();
+            }
+            for (op_hash, _) in requirements {
+                op_hash.get_loc();
+            }
+        }
+        Some(WireMessage::PublishIndex { index_op }) => match index_op {
+            IndexOp::Register {
+                index_key,
+                entry_hash,
+                header_hash,
+            }
+            | IndexOp::Tombstone {
+                index_key,
+                entry_hash,
+                header_hash,
+            } => {
+                index_key.basis().get_loc();
+                entry_hash.get_loc();
+                header_hash.get_loc();
+            }
+        },
+        Some(WireMessage::RequestArcSync { from_arc, .. }) => {
+            DhtArc::from(from_arc).contains(0u32);
+        }
+        Some(WireMessage::CallRemote { .. })
+        | Some(WireMessage::ValidationReceipt { .. })
+        | Some(WireMessage::CountersigningRequest { .. })
+        | Some(WireMessage::CountersigningResponse { .. })
+        | None => (),
+    }
+    if let Some(op_data) = check_decode(WireDhtOpData::decode(data.clone())) {
+        op_data.from_agent.get_loc();
+        op_data.dht_hash.get_loc();
+    }
+    check_decode(WireArcSyncResponse::decode(data.clone()));
+    // The option structs are only ever decoded as part of a WireMessage
+    let _: Result<event::GetOptions, _> = holochain_serialized_bytes::decode(&data);
+    let _: Result<event::GetMetaOptions, _> = holochain_serialized_bytes::decode(&data);
+    let _: Result<event::GetLinksOptions, _> = holochain_serialized_bytes::decode(&data);
+    let _: Result<event::GetIndexedOptions, _> = holochain_serialized_bytes::decode(&data);
+    check_decode::<WireLinkMetaKey>(decode_remote("test", data.clone()));
+    check_decode::<GetElementResponse>(decode_remote("test", data.clone()));
+    check_decode::<MetadataSet>(decode_remote("test", data.clone()));
+    check_decode::<GetLinksResponse>(decode_remote("test", data.clone()));
+    check_decode::<GetIndexedResponse>(decode_remote("test", data.clone()));
+    check_decode::<ValidationPackageResponse>(decode_remote("test", data.clone()));
+    match WireCompression::new(Default::default()).open(data) {
+        Ok(_)
+        | Err(HolochainP2pError::CorruptPayload { .. })
+        | Err(HolochainP2pError::UnsupportedWireCodec(_))
+        | Err(HolochainP2pError::UnsupportedWireVersion(_)) => (),
+        Err(e) => panic!("expected a CorruptPayload error, got: {:?}", e),
+    }
+}
+
+#[test]
+fn fixtures_roundtrip() {
+    for data in fixtures() {
+        assert!(
+            WireMessage::decode(data.clone()).is_ok()
+                || WireDhtOpData::decode(data.clone()).is_ok()
+                || WireArcSyncResponse::decode(data).is_ok()
+        );
+    }
+}
+
+#[test]
+fn fixtures_roundtrip_through_compression() {
+    let compression = WireCompression::new(WireCompressionConfig {
+        enabled: true,
+        threshold_bytes: 0,
+    });
+    for data in fixtures() {
+        assert_eq!(Deflate.decompress(&Deflate.compress(&data)).unwrap(), data);
+        let (payload, _) = compression.seal_request("test", &data, true);
+        let opened = compression.open(payload).unwrap();
+        assert_eq!(opened.body, data);
+        assert_eq!(opened.accepts, CODEC_DEFLATE);
+    }
+    assert!(compression.metrics()["test"].compressed_payloads > 0);
+}
+
+#[test]
+fn short_hashes_are_corrupt_payloads() {
+    let fixtures = hash_fixtures();
+    let (op_data, messages) = fixtures.split_last().unwrap();
+    for data in messages {
+        let short = shrink_hashes(data);
+        assert_ne!(&short, data);
+        assert_corrupt(WireMessage::decode(short));
+    }
+    let short = shrink_hashes(op_data);
+    assert_ne!(&short, op_data);
+    assert_corrupt(WireDhtOpData::decode(short));
+}
+
+#[test]
+fn short_kitsune_hashes_are_corrupt_payloads() {
+    for len in &[
+        0,
+        4,
+        HOLO_HASH_SERIALIZED_LEN - 1,
+        HOLO_HASH_SERIALIZED_LEN + 1,
+    ] {
+        let agent = Arc::new(kitsune_p2p::KitsuneAgent::from(vec![0xdb; *len]));
+        assert_corrupt(AgentPubKey::try_from_kitsune(&agent));
+    }
+    let agent = Arc::new(kitsune_p2p::KitsuneAgent::from(hash_bytes()));
+    assert!(AgentPubKey::try_from_kitsune(&agent).is_ok());
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(CASES))]
+
+    #[test]
+    fn arbitrary_bytes_never_panic(data in prop::collection::vec(any::<u8>(), 0..512)) {
+        check_all(data);
+    }
+
+    #[test]
+    fn mangled_fixtures_never_panic(
+        fixture in any::<prop::sample::Index>(),
+        flips in prop::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 0..8),
+        truncate in any::<prop::sample::Index>(),
+    ) {
+        let fixtures = fixtures();
+        let mut data = fixtures[fixture.index(fixtures.len())].clone();
+        for (i, byte) in flips {
+            let i = i.index(data.len());
+            data[i] = byte;
+        }
+        data.truncate(truncate.index(data.len() + 1));
+        check_all(data);
+    }
+}
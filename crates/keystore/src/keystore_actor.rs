@@ -23,6 +23,10 @@ pub trait KeystoreSenderExt {
 
     /// Generate a signature for a given blob of binary data.
     fn sign(&self, input: SignInput) -> KeystoreApiFuture<Signature>;
+
+    /// List the public keys of every signing keypair in the keystore,
+    /// in the order they were added.
+    fn list_sign_pub_keys(&self) -> KeystoreApiFuture<Vec<holo_hash::AgentPubKey>>;
 }
 
 impl KeystoreSenderExt for KeystoreSender {
@@ -50,4 +54,25 @@ impl KeystoreSenderExt for KeystoreSender {
         .boxed()
         .into()
     }
+
+    fn list_sign_pub_keys(&self) -> KeystoreApiFuture<Vec<holo_hash::AgentPubKey>> {
+        use lair_keystore_api::actor::{LairClientApiSender, LairEntryType};
+        let keystore = self.clone();
+        async move {
+            let last: u32 = keystore.lair_get_last_entry_index().await?.into();
+            let mut keys = Vec::new();
+            // Entries are indexed from 1; TLS certs share the same index space
+            for index in 1..=last {
+                if let LairEntryType::SignEd25519 =
+                    keystore.lair_get_entry_type(index.into()).await?
+                {
+                    let pk = keystore.sign_ed25519_get(index.into()).await?;
+                    keys.push(holo_hash::AgentPubKey::with_pre_hashed(pk.to_vec()));
+                }
+            }
+            Ok(keys)
+        }
+        .boxed()
+        .into()
+    }
 }
@@ -111,4 +111,25 @@ mod tests {
         .await
         .unwrap();
     }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_list_sign_pub_keys() {
+        tokio::task::spawn(async move {
+            let keystore = spawn_test_keystore().await.unwrap();
+            assert!(keystore.list_sign_pub_keys().await.unwrap().is_empty());
+
+            let agent_pubkey1 = holo_hash::AgentPubKey::new_from_pure_entropy(&keystore)
+                .await
+                .unwrap();
+            let agent_pubkey2 = holo_hash::AgentPubKey::new_from_pure_entropy(&keystore)
+                .await
+                .unwrap();
+            assert_eq!(
+                keystore.list_sign_pub_keys().await.unwrap(),
+                vec![agent_pubkey1, agent_pubkey2]
+            );
+        })
+        .await
+        .unwrap();
+    }
 }
@@ -1,11 +1,14 @@
 use crate::actor::*;
+use crate::config::KitsuneP2pConfig;
 use crate::event::*;
 
 mod actor;
 use actor::*;
 
 /// Spawn a new KitsuneP2p actor.
-pub async fn spawn_kitsune_p2p() -> KitsuneP2pResult<(
+pub async fn spawn_kitsune_p2p(
+    config: KitsuneP2pConfig,
+) -> KitsuneP2pResult<(
     ghost_actor::GhostSender<KitsuneP2p>,
     KitsuneP2pEventReceiver,
 )> {
@@ -22,6 +25,7 @@ pub async fn spawn_kitsune_p2p() -> KitsuneP2pResult<(
         channel_factory,
         internal_sender,
         evt_send,
+        config,
     )?));
 
     Ok((sender, evt_recv))
@@ -1,6 +1,6 @@
 // this is largely a passthrough that routes to a specific space handler
 
-use crate::{actor, actor::*, event::*, types::*};
+use crate::{actor, actor::*, config::KitsuneP2pConfig, event::*, types::*};
 use futures::future::FutureExt;
 use kitsune_p2p_types::async_lazy::AsyncLazy;
 use std::{
@@ -9,6 +9,7 @@ use std::{
 };
 
 mod gossip;
+mod in_process;
 mod space;
 use ghost_actor::dependencies::tracing;
 use space::*;
@@ -27,6 +28,7 @@ pub(crate) struct KitsuneP2pActor {
     #[allow(dead_code)]
     evt_sender: futures::channel::mpsc::Sender<KitsuneP2pEvent>,
     spaces: HashMap<Arc<KitsuneSpace>, AsyncLazy<ghost_actor::GhostSender<KitsuneP2p>>>,
+    config: KitsuneP2pConfig,
 }
 
 impl KitsuneP2pActor {
@@ -34,16 +36,43 @@ impl KitsuneP2pActor {
         channel_factory: ghost_actor::actor_builder::GhostActorChannelFactory<Self>,
         internal_sender: ghost_actor::GhostSender<Internal>,
         evt_sender: futures::channel::mpsc::Sender<KitsuneP2pEvent>,
+        config: KitsuneP2pConfig,
     ) -> KitsuneP2pResult<Self> {
         Ok(Self {
             channel_factory,
             internal_sender,
             evt_sender,
             spaces: HashMap::new(),
+            config,
         })
     }
 }
 
+impl KitsuneP2pActor {
+    /// The sender of a space's actor, spawning the space the first time
+    fn space_sender(
+        &mut self,
+        space: Arc<KitsuneSpace>,
+    ) -> impl std::future::Future<Output = ghost_actor::GhostSender<KitsuneP2p>> + 'static {
+        let internal_sender = self.internal_sender.clone();
+        let config = self.config.clone();
+        let space_sender = match self.spaces.entry(space.clone()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(AsyncLazy::new(async move {
+                let (send, evt_recv) = spawn_space(space, config)
+                    .await
+                    .expect("cannot fail to create space");
+                internal_sender
+                    .register_space_event_handler(evt_recv)
+                    .await
+                    .expect("FAIL");
+                send
+            })),
+        };
+        space_sender.get()
+    }
+}
+
 impl ghost_actor::GhostControlHandler for KitsuneP2pActor {}
 
 impl ghost_actor::GhostHandler<Internal> for KitsuneP2pActor {}
@@ -116,7 +145,7 @@ impl KitsuneP2pEventHandler for KitsuneP2pActor {
     fn handle_fetch_op_hashes_for_constraints(
         &mut self,
         input: FetchOpHashesForConstraintsEvt,
-    ) -> KitsuneP2pEventHandlerResult<Vec<Arc<KitsuneOpHash>>> {
+    ) -> KitsuneP2pEventHandlerResult<ArcCoverage<Vec<Arc<KitsuneOpHash>>>> {
         Ok(self.evt_sender.fetch_op_hashes_for_constraints(input))
     }
 
@@ -133,6 +162,20 @@ impl KitsuneP2pEventHandler for KitsuneP2pActor {
     ) -> KitsuneP2pEventHandlerResult<KitsuneSignature> {
         Ok(self.evt_sender.sign_network_data(input))
     }
+
+    fn handle_peer_connected(
+        &mut self,
+        input: PeerConnectedEvt,
+    ) -> KitsuneP2pEventHandlerResult<()> {
+        Ok(self.evt_sender.peer_connected(input))
+    }
+
+    fn handle_peer_disconnected(
+        &mut self,
+        input: PeerDisconnectedEvt,
+    ) -> KitsuneP2pEventHandlerResult<()> {
+        Ok(self.evt_sender.peer_disconnected(input))
+    }
 }
 
 impl ghost_actor::GhostHandler<KitsuneP2p> for KitsuneP2pActor {}
@@ -143,27 +186,25 @@ impl KitsuneP2pHandler for KitsuneP2pActor {
         space: Arc<KitsuneSpace>,
         agent: Arc<KitsuneAgent>,
     ) -> KitsuneP2pHandlerResult<()> {
-        let internal_sender = self.internal_sender.clone();
-        let space2 = space.clone();
-        let space_sender = match self.spaces.entry(space.clone()) {
-            Entry::Occupied(entry) => entry.into_mut(),
-            Entry::Vacant(entry) => entry.insert(AsyncLazy::new(async move {
-                let (send, evt_recv) = spawn_space(space2)
-                    .await
-                    .expect("cannot fail to create space");
-                internal_sender
-                    .register_space_event_handler(evt_recv)
-                    .await
-                    .expect("FAIL");
-                send
-            })),
-        };
-        let space_sender = space_sender.get();
+        let space_sender = self.space_sender(space.clone());
         Ok(async move { space_sender.await.join(space, agent).await }
             .boxed()
             .into())
     }
 
+    fn handle_join_shared(
+        &mut self,
+        space: Arc<KitsuneSpace>,
+        agent: Arc<KitsuneAgent>,
+    ) -> KitsuneP2pHandlerResult<()> {
+        let space_sender = self.space_sender(space.clone());
+        Ok(
+            async move { space_sender.await.join_shared(space, agent).await }
+                .boxed()
+                .into(),
+        )
+    }
+
     fn handle_leave(
         &mut self,
         space: Arc<KitsuneSpace>,
@@ -225,4 +266,20 @@ impl KitsuneP2pHandler for KitsuneP2pActor {
             .boxed()
             .into())
     }
+
+    fn handle_network_info(
+        &mut self,
+        space: Arc<KitsuneSpace>,
+        agent: Arc<KitsuneAgent>,
+    ) -> KitsuneP2pHandlerResult<actor::NetworkInfo> {
+        let space_sender = match self.spaces.get_mut(&space) {
+            None => return Err(KitsuneP2pError::RoutingSpaceError(space)),
+            Some(space) => space.get(),
+        };
+        Ok(
+            async move { space_sender.await.network_info(space, agent).await }
+                .boxed()
+                .into(),
+        )
+    }
 }
@@ -1,7 +1,7 @@
 //! This is a temporary quick-hack gossip module for use with the
 //! in-memory / full-sync / non-sharded networking module
 
-use crate::{types::actor::KitsuneP2pResult, *};
+use crate::{event::ArcCoverage, types::actor::KitsuneP2pResult, *};
 use ghost_actor::dependencies::{tracing, tracing_futures};
 use kitsune_p2p_types::dht_arc::DhtArc;
 use std::{collections::HashSet, iter::FromIterator, sync::Arc};
@@ -12,14 +12,15 @@ ghost_actor::ghost_chan! {
         /// get a list of agents we know about
         fn list_neighbor_agents() -> Vec<Arc<KitsuneAgent>>;
 
-        /// fetch op list from/to with constraints
+        /// fetch op list from/to with constraints,
+        /// if to_agent covers the arc at all
         fn req_op_hashes(
             from_agent: Arc<KitsuneAgent>,
             to_agent: Arc<KitsuneAgent>,
             dht_arc: DhtArc,
             since_utc_epoch_s: i64,
             until_utc_epoch_s: i64,
-        ) -> Vec<Arc<KitsuneOpHash>>;
+        ) -> ArcCoverage<Vec<Arc<KitsuneOpHash>>>;
 
         /// fetch op data for op hash list
         fn req_op_data(
@@ -34,16 +35,31 @@ ghost_actor::ghost_chan! {
             to_agent: Arc<KitsuneAgent>,
             ops: Vec<(Arc<KitsuneOpHash>, Vec<u8>)>,
         ) -> ();
+
+        /// a gossip round between two agents completed,
+        /// with the op counts each advertised within the round's arc
+        fn gossip_round_complete(
+            from_agent: Arc<KitsuneAgent>,
+            from_op_count: u64,
+            to_agent: Arc<KitsuneAgent>,
+            to_op_count: u64,
+        ) -> ();
     }
 }
 
 pub type GossipEventReceiver = futures::channel::mpsc::Receiver<GossipEvent>;
 
-/// spawn a gossip module to control gossip for a space
-pub fn spawn_gossip_module() -> GossipEventReceiver {
+/// The arc every agent stores and gossips while in full-sync mode
+pub fn full_sync_arc() -> DhtArc {
+    DhtArc::new(0, u32::MAX)
+}
+
+/// spawn a gossip module to control gossip for a space,
+/// fetching the data of at most `fetch_batch_size` ops per request
+pub fn spawn_gossip_module(fetch_batch_size: usize) -> GossipEventReceiver {
     let (evt_send, evt_recv) = futures::channel::mpsc::channel(10);
 
-    tokio::task::spawn(gossip_loop(evt_send));
+    tokio::task::spawn(gossip_loop(evt_send, fetch_batch_size));
 
     evt_recv
 }
@@ -53,8 +69,9 @@ pub fn spawn_gossip_module() -> GossipEventReceiver {
 /// awaiting requests - not process requests in parallel.
 async fn gossip_loop(
     evt_send: futures::channel::mpsc::Sender<GossipEvent>,
+    fetch_batch_size: usize,
 ) -> KitsuneP2pResult<()> {
-    let mut gossip_data = GossipData::new(evt_send);
+    let mut gossip_data = GossipData::new(evt_send, fetch_batch_size);
     loop {
         gossip_data.take_action().await?;
 
@@ -65,13 +82,18 @@ async fn gossip_loop(
 struct GossipData {
     evt_send: futures::channel::mpsc::Sender<GossipEvent>,
     pending_gossip_list: Vec<(Arc<KitsuneAgent>, Arc<KitsuneAgent>)>,
+    fetch_batch_size: usize,
 }
 
 impl GossipData {
-    pub fn new(evt_send: futures::channel::mpsc::Sender<GossipEvent>) -> Self {
+    pub fn new(
+        evt_send: futures::channel::mpsc::Sender<GossipEvent>,
+        fetch_batch_size: usize,
+    ) -> Self {
         Self {
             evt_send,
             pending_gossip_list: Vec::new(),
+            fetch_batch_size: fetch_batch_size.max(1),
         }
     }
 
@@ -79,7 +101,13 @@ impl GossipData {
         if self.pending_gossip_list.is_empty() {
             self.fetch_pending_gossip_list().await?;
         } else {
-            self.process_next_gossip().await?;
+            match self.process_next_gossip().await {
+                // an agent on another node left mid-round, just drop the pair
+                Err(KitsuneP2pError::RoutingAgentError(agent)) => {
+                    tracing::debug!(?agent, "skipping gossip, the agent is no longer reachable");
+                }
+                res => res?,
+            }
         }
         Ok(())
     }
@@ -107,30 +135,44 @@ impl GossipData {
         type S = HashSet<Arc<KitsuneOpHash>>;
 
         // we'll just fetch all with no constraints for now
-        let op_hashes_from: S = HashSet::from_iter(
-            self.evt_send
-                .req_op_hashes(
-                    from_agent.clone(), // from not to because we're initiating
-                    from_agent.clone(),
-                    DhtArc::new(0, u32::MAX),
-                    i64::MIN,
-                    i64::MAX,
-                )
-                .await?,
-        );
+        let op_hashes_from = self
+            .evt_send
+            .req_op_hashes(
+                from_agent.clone(), // from not to because we're initiating
+                from_agent.clone(),
+                full_sync_arc(),
+                i64::MIN,
+                i64::MAX,
+            )
+            .await?;
 
         // we'll just fetch all with no constraints for now
-        let op_hashes_to: S = HashSet::from_iter(
-            self.evt_send
-                .req_op_hashes(
-                    from_agent.clone(),
-                    to_agent.clone(),
-                    DhtArc::new(0, u32::MAX),
-                    i64::MIN,
-                    i64::MAX,
-                )
-                .await?,
-        );
+        let op_hashes_to = self
+            .evt_send
+            .req_op_hashes(
+                from_agent.clone(),
+                to_agent.clone(),
+                full_sync_arc(),
+                i64::MIN,
+                i64::MAX,
+            )
+            .await?;
+
+        // only sync with agents that actually cover the arc, an empty
+        // list from one that doesn't would look like it needs everything
+        let (op_hashes_from, op_hashes_to): (S, S) = match (op_hashes_from, op_hashes_to) {
+            (ArcCoverage::Covered(from), ArcCoverage::Covered(to)) => {
+                (HashSet::from_iter(from), HashSet::from_iter(to))
+            }
+            _ => {
+                tracing::debug!(
+                    ?from_agent,
+                    ?to_agent,
+                    "skipping gossip, the arc isn't covered by both agents"
+                );
+                return Ok(());
+            }
+        };
 
         // values that to_agent has, and from_agent needs
         let from_needs = op_hashes_to
@@ -145,43 +187,56 @@ impl GossipData {
             .collect::<Vec<_>>();
 
         // fetch values that to_agent needs from from_agent
-        if !to_needs.is_empty() {
-            if let Ok(result) = self
-                .evt_send
-                .req_op_data(
-                    from_agent.clone(), // from not to because we're initiating
-                    from_agent.clone(),
-                    to_needs,
-                )
-                .await
-            {
-                if !result.is_empty() {
-                    if let Err(e) = self
-                        .evt_send
-                        .gossip_ops(from_agent.clone(), to_agent.clone(), result)
-                        .await
-                    {
-                        tracing::error!(?e);
-                    }
-                }
-            }
-        }
+        self.fetch_and_gossip(
+            from_agent.clone(), // from not to because we're initiating
+            from_agent.clone(),
+            to_agent.clone(),
+            to_needs,
+        )
+        .await;
 
         // fetch values that from_agent needs from to_agent
-        if !from_needs.is_empty() {
+        self.fetch_and_gossip(
+            from_agent.clone(),
+            to_agent.clone(), // we fetched from to
+            from_agent.clone(),
+            from_needs,
+        )
+        .await;
+
+        self.evt_send
+            .gossip_round_complete(
+                from_agent,
+                op_hashes_from.len() as u64,
+                to_agent,
+                op_hashes_to.len() as u64,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Fetch the data of some ops from one agent, in batches of at most
+    /// `fetch_batch_size`, and gossip each batch on to another agent.
+    /// A batch which fails is skipped, the ops will come up again in a
+    /// later round.
+    async fn fetch_and_gossip(
+        &mut self,
+        requester: Arc<KitsuneAgent>,
+        holder: Arc<KitsuneAgent>,
+        receiver: Arc<KitsuneAgent>,
+        op_hashes: Vec<Arc<KitsuneOpHash>>,
+    ) {
+        for batch in op_hashes.chunks(self.fetch_batch_size) {
             if let Ok(result) = self
                 .evt_send
-                .req_op_data(from_agent.clone(), to_agent.clone(), from_needs)
+                .req_op_data(requester.clone(), holder.clone(), batch.to_vec())
                 .await
             {
                 if !result.is_empty() {
                     if let Err(e) = self
                         .evt_send
-                        .gossip_ops(
-                            to_agent.clone(), // we fetched from to
-                            from_agent.clone(),
-                            result,
-                        )
+                        .gossip_ops(holder.clone(), receiver.clone(), result)
                         .await
                     {
                         tracing::error!(?e);
@@ -189,7 +244,5 @@ impl GossipData {
                 }
             }
         }
-
-        Ok(())
     }
 }
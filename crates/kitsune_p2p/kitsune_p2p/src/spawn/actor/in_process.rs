@@ -0,0 +1,92 @@
+//! A network shared by the spaces of several KitsuneP2p actors in one process.
+//! Until we have real networking, this lets tests run several nodes which
+//! reach each other's agents through the same short-circuit the agents
+//! joined to one actor use.
+
+use crate::{event::KitsuneP2pEvent, types::*};
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+type EvtSender = futures::channel::mpsc::Sender<KitsuneP2pEvent>;
+
+/// The agents joined to each named network and space,
+/// along with the event sender of the node each one is joined to
+/// and whether the agent is listed among the space's agents
+static NETWORKS: Lazy<
+    Mutex<HashMap<(String, Arc<KitsuneSpace>), HashMap<Arc<KitsuneAgent>, (EvtSender, bool)>>>,
+> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// One space on a named in-process network
+#[derive(Clone)]
+pub(crate) struct InProcessNetwork {
+    key: (String, Arc<KitsuneSpace>),
+}
+
+impl InProcessNetwork {
+    /// Join a space on the network with this name
+    pub fn new(name: String, space: Arc<KitsuneSpace>) -> Self {
+        Self { key: (name, space) }
+    }
+
+    /// Make an agent reachable through the event sender of its node
+    pub fn join(&self, agent: Arc<KitsuneAgent>, evt_sender: EvtSender) {
+        self.with_agents(|agents| {
+            agents.insert(agent, (evt_sender, true));
+        })
+    }
+
+    /// Make an agent reachable through the event sender of its node
+    /// without listing it among the space's agents
+    pub fn join_unlisted(&self, agent: Arc<KitsuneAgent>, evt_sender: EvtSender) {
+        self.with_agents(|agents| {
+            agents.insert(agent, (evt_sender, false));
+        })
+    }
+
+    /// Stop an agent being reachable
+    pub fn leave(&self, agent: &Arc<KitsuneAgent>) {
+        self.with_agents(|agents| {
+            agents.remove(agent);
+        })
+    }
+
+    /// The event sender of the node an agent is joined to
+    pub fn evt_sender(&self, agent: &Arc<KitsuneAgent>) -> Option<EvtSender> {
+        self.with_agents(|agents| agents.get(agent).map(|(evt_sender, _)| evt_sender.clone()))
+    }
+
+    /// Every listed agent joined to this space on the network
+    pub fn agents(&self) -> Vec<Arc<KitsuneAgent>> {
+        self.with_agents(|agents| {
+            agents
+                .iter()
+                .filter(|(_, (_, listed))| *listed)
+                .map(|(agent, _)| agent.clone())
+                .collect()
+        })
+    }
+
+    /// How many listed agents are joined to this space on the network
+    pub fn agent_count(&self) -> usize {
+        self.with_agents(|agents| agents.values().filter(|(_, listed)| *listed).count())
+    }
+
+    /// Run a function over this space's agents, first forgetting those
+    /// whose node has shut down without leaving
+    fn with_agents<R>(
+        &self,
+        f: impl FnOnce(&mut HashMap<Arc<KitsuneAgent>, (EvtSender, bool)>) -> R,
+    ) -> R {
+        let mut networks = NETWORKS.lock().expect("in-process network lock poisoned");
+        let agents = networks.entry(self.key.clone()).or_default();
+        agents.retain(|_, (evt_sender, _)| !evt_sender.is_closed());
+        let res = f(agents);
+        if agents.is_empty() {
+            networks.remove(&self.key);
+        }
+        res
+    }
+}
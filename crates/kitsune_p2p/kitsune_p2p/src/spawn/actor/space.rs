@@ -1,6 +1,6 @@
-use super::*;
+use super::{in_process::InProcessNetwork, *};
 use ghost_actor::dependencies::{tracing, tracing_futures::Instrument};
-use std::collections::HashSet;
+use std::collections::{btree_map, BTreeMap, HashSet};
 
 /// if the user specifies None or zero (0) for remote_agent_count
 const DEFAULT_NOTIFY_REMOTE_AGENT_COUNT: u8 = 5;
@@ -39,6 +39,7 @@ ghost_actor::ghost_chan! {
 
 pub(crate) async fn spawn_space(
     space: Arc<KitsuneSpace>,
+    config: KitsuneP2pConfig,
 ) -> KitsuneP2pResult<(
     ghost_actor::GhostSender<KitsuneP2p>,
     KitsuneP2pEventReceiver,
@@ -48,7 +49,7 @@ pub(crate) async fn spawn_space(
     let builder = ghost_actor::actor_builder::GhostActorBuilder::new();
 
     // initialize gossip module
-    let gossip_recv = gossip::spawn_gossip_module();
+    let gossip_recv = gossip::spawn_gossip_module(config.gossip_fetch_batch_size);
     builder
         .channel_factory()
         .attach_receiver(gossip_recv)
@@ -64,7 +65,11 @@ pub(crate) async fn spawn_space(
         .create_channel::<KitsuneP2p>()
         .await?;
 
-    tokio::task::spawn(builder.spawn(Space::new(space, internal_sender, evt_send)));
+    let network = config
+        .in_process_network
+        .map(|name| InProcessNetwork::new(name, space.clone()));
+
+    tokio::task::spawn(builder.spawn(Space::new(space, internal_sender, evt_send, network)));
 
     Ok((sender, evt_recv))
 }
@@ -76,7 +81,7 @@ impl gossip::GossipEventHandler for Space {
         &mut self,
     ) -> gossip::GossipEventHandlerResult<Vec<Arc<KitsuneAgent>>> {
         // while full-sync this is just a clone of list_by_basis
-        let res = self.agents.keys().cloned().collect();
+        let res = self.all_agents();
         Ok(async move { Ok(res) }.boxed().into())
     }
 
@@ -87,11 +92,11 @@ impl gossip::GossipEventHandler for Space {
         dht_arc: kitsune_p2p_types::dht_arc::DhtArc,
         since_utc_epoch_s: i64,
         until_utc_epoch_s: i64,
-    ) -> gossip::GossipEventHandlerResult<Vec<Arc<KitsuneOpHash>>> {
-        // while full-sync just redirecting to self...
+    ) -> gossip::GossipEventHandlerResult<ArcCoverage<Vec<Arc<KitsuneOpHash>>>> {
+        // while full-sync just redirecting to whichever node to_agent is on...
         // but eventually some of these will be outgoing remote requests
         let fut = self
-            .evt_sender
+            .evt_sender_for(&to_agent)?
             .fetch_op_hashes_for_constraints(FetchOpHashesForConstraintsEvt {
                 space: self.space.clone(),
                 agent: to_agent,
@@ -108,13 +113,15 @@ impl gossip::GossipEventHandler for Space {
         to_agent: Arc<KitsuneAgent>,
         op_hashes: Vec<Arc<KitsuneOpHash>>,
     ) -> gossip::GossipEventHandlerResult<Vec<(Arc<KitsuneOpHash>, Vec<u8>)>> {
-        // while full-sync just redirecting to self...
+        // while full-sync just redirecting to whichever node to_agent is on...
         // but eventually some of these will be outgoing remote requests
-        let fut = self.evt_sender.fetch_op_hash_data(FetchOpHashDataEvt {
-            space: self.space.clone(),
-            agent: to_agent,
-            op_hashes,
-        });
+        let fut = self
+            .evt_sender_for(&to_agent)?
+            .fetch_op_hash_data(FetchOpHashDataEvt {
+                space: self.space.clone(),
+                agent: to_agent,
+                op_hashes,
+            });
         Ok(async move { fut.await }.boxed().into())
     }
 
@@ -124,10 +131,14 @@ impl gossip::GossipEventHandler for Space {
         to_agent: Arc<KitsuneAgent>,
         ops: Vec<(Arc<KitsuneOpHash>, Vec<u8>)>,
     ) -> gossip::GossipEventHandlerResult<()> {
+        let evt_sender = self.evt_sender_for(&to_agent)?;
+        if let Some(info) = self.agents.get_mut(&to_agent) {
+            info.bytes_received += ops.iter().map(|(_, data)| data.len() as u64).sum::<u64>();
+        }
         let all = ops
             .into_iter()
             .map(|(op_hash, op_data)| {
-                self.evt_sender.gossip(
+                evt_sender.gossip(
                     self.space.clone(),
                     to_agent.clone(),
                     from_agent.clone(),
@@ -150,6 +161,26 @@ impl gossip::GossipEventHandler for Space {
         .boxed()
         .into())
     }
+
+    fn handle_gossip_round_complete(
+        &mut self,
+        from_agent: Arc<KitsuneAgent>,
+        from_op_count: u64,
+        to_agent: Arc<KitsuneAgent>,
+        to_op_count: u64,
+    ) -> gossip::GossipEventHandlerResult<()> {
+        let now = now_ms();
+        for (agent, op_count) in vec![(from_agent, from_op_count), (to_agent, to_op_count)] {
+            if let Some(info) = self.agents.get_mut(&agent) {
+                if let Some(old) = info.advertised_op_count.replace(op_count) {
+                    forget_op_count(&mut self.advertised_op_counts, old);
+                }
+                *self.advertised_op_counts.entry(op_count).or_insert(0) += 1;
+                info.last_gossip_ms = Some(now);
+            }
+        }
+        Ok(async move { Ok(()) }.boxed().into())
+    }
 }
 
 impl ghost_actor::GhostHandler<SpaceInternal> for Space {}
@@ -163,19 +194,15 @@ impl SpaceInternalHandler for Space {
         data: Arc<Vec<u8>>,
     ) -> SpaceInternalHandlerResult<Vec<u8>> {
         // Right now we are only implementing the "short-circuit"
-        // that routes messages to other agents joined on this same system.
+        // that routes messages to other agents joined on this same system,
+        // or on the same in-process network.
         // I.e. we don't bother with peer discovery because we know the
         // remote is local.
-        if !self.agents.contains_key(&to_agent) {
-            return Err(KitsuneP2pError::RoutingAgentError(to_agent));
-        }
+        // If to_agent *is* joined, this is the event sender to forward to.
+        let evt_sender = self.evt_sender_for(&to_agent)?;
 
-        // to_agent *is* joined - let's forward the request
         let space = self.space.clone();
 
-        // clone the event sender
-        let evt_sender = self.evt_sender.clone();
-
         // As this is a short-circuit - we need to decode the data inline - here.
         // In the future, we will probably need to branch here, so the real
         // networking can forward the encoded data. Or, split immediate_request
@@ -212,7 +239,7 @@ impl SpaceInternalHandler for Space {
         // we're ignoring the basis_hash and just returning everyone.
         _basis: Arc<KitsuneBasis>,
     ) -> SpaceInternalHandlerResult<Vec<Arc<KitsuneAgent>>> {
-        let res = self.agents.keys().cloned().collect();
+        let res = self.all_agents();
         Ok(async move { Ok(res) }.boxed().into())
     }
 }
@@ -230,7 +257,27 @@ impl KitsuneP2pHandler for Space {
         match self.agents.entry(agent.clone()) {
             Entry::Occupied(_) => (),
             Entry::Vacant(entry) => {
-                entry.insert(AgentInfo { agent });
+                entry.insert(AgentInfo::new(agent.clone()));
+                if let Some(network) = &self.network {
+                    network.join(agent.clone(), self.evt_sender.clone());
+                }
+                self.spawn_peer_connected(agent);
+            }
+        }
+        Ok(async move { Ok(()) }.boxed().into())
+    }
+
+    fn handle_join_shared(
+        &mut self,
+        space: Arc<KitsuneSpace>,
+        agent: Arc<KitsuneAgent>,
+    ) -> KitsuneP2pHandlerResult<()> {
+        if self.agents.is_empty() || self.agents.contains_key(&agent) {
+            return self.handle_join(space, agent);
+        }
+        if self.aliases.insert(agent.clone()) {
+            if let Some(network) = &self.network {
+                network.join_unlisted(agent, self.evt_sender.clone());
             }
         }
         Ok(async move { Ok(()) }.boxed().into())
@@ -241,7 +288,32 @@ impl KitsuneP2pHandler for Space {
         _space: Arc<KitsuneSpace>,
         agent: Arc<KitsuneAgent>,
     ) -> KitsuneP2pHandlerResult<()> {
-        self.agents.remove(&agent);
+        if self.aliases.remove(&agent) {
+            if let Some(network) = &self.network {
+                network.leave(&agent);
+            }
+        } else if let Some(info) = self.agents.remove(&agent) {
+            if let Some(op_count) = info.advertised_op_count {
+                forget_op_count(&mut self.advertised_op_counts, op_count);
+            }
+            if let Some(network) = &self.network {
+                network.leave(&agent);
+            }
+            self.spawn_peer_disconnected(agent);
+            // The data of the agents sharing it is still here,
+            // so one of them takes the place of the last to leave
+            if self.agents.is_empty() {
+                if let Some(alias) = self.aliases.iter().next().cloned() {
+                    self.aliases.remove(&alias);
+                    self.agents
+                        .insert(alias.clone(), AgentInfo::new(alias.clone()));
+                    if let Some(network) = &self.network {
+                        network.join(alias.clone(), self.evt_sender.clone());
+                    }
+                    self.spawn_peer_connected(alias);
+                }
+            }
+        }
         Ok(async move { Ok(()) }.boxed().into())
     }
 
@@ -365,12 +437,87 @@ impl KitsuneP2pHandler for Space {
             Ok(inner_fut)
         }
     }
+
+    fn handle_network_info(
+        &mut self,
+        _space: Arc<KitsuneSpace>,
+        agent: Arc<KitsuneAgent>,
+    ) -> KitsuneP2pHandlerResult<actor::NetworkInfo> {
+        // An agent sharing its data with the agents joined here
+        // has the network info of whichever of them takes part
+        let info = match self.agents.get(&agent) {
+            Some(info) => info,
+            None if self.aliases.contains(&agent) => match self.agents.values().next() {
+                Some(info) => info,
+                None => return Err(KitsuneP2pError::RoutingAgentError(agent)),
+            },
+            None => return Err(KitsuneP2pError::RoutingAgentError(agent)),
+        };
+        // While full-sync, every peer holds the whole dht,
+        // so any peer's count is an estimate for this agent's arc.
+        // The largest count is this agent's own at most once,
+        // so at most two counts are looked at.
+        let own_op_count = info.advertised_op_count;
+        let expected_op_count = self
+            .advertised_op_counts
+            .iter()
+            .rev()
+            .find(|(op_count, agents)| Some(**op_count) != own_op_count || **agents > 1)
+            .map(|(op_count, _)| *op_count);
+        let agent_count = match &self.network {
+            Some(network) => network.agent_count().max(self.agents.len()),
+            None => self.agents.len(),
+        };
+        let res = actor::NetworkInfo {
+            peer_count: (agent_count - 1) as u32,
+            storage_arc: gossip::full_sync_arc(),
+            expected_op_count,
+            last_gossip_ms: info.last_gossip_ms,
+            bytes_received: info.bytes_received,
+        };
+        Ok(async move { Ok(res) }.boxed().into())
+    }
 }
 
 /// Local helper struct for associating info with a connected agent.
 struct AgentInfo {
     #[allow(dead_code)]
     agent: Arc<KitsuneAgent>,
+    /// The op count this agent advertised in its last gossip round
+    advertised_op_count: Option<u64>,
+    /// When this agent's last gossip round completed, in unix ms
+    last_gossip_ms: Option<u64>,
+    /// Bytes of op data this agent has received by gossip since joining
+    bytes_received: u64,
+}
+
+impl AgentInfo {
+    fn new(agent: Arc<KitsuneAgent>) -> Self {
+        Self {
+            agent,
+            advertised_op_count: None,
+            last_gossip_ms: None,
+            bytes_received: 0,
+        }
+    }
+}
+
+/// Remove one agent's advertised op count from the counts of all agents
+fn forget_op_count(advertised_op_counts: &mut BTreeMap<u64, usize>, op_count: u64) {
+    if let btree_map::Entry::Occupied(mut entry) = advertised_op_counts.entry(op_count) {
+        *entry.get_mut() -= 1;
+        if *entry.get() == 0 {
+            entry.remove();
+        }
+    }
+}
+
+/// The current time in unix ms
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("system clock is before the unix epoch")
+        .as_millis() as u64
 }
 
 /// A Kitsune P2p Node can track multiple "spaces" -- Non-interacting namespaced
@@ -380,6 +527,24 @@ pub(crate) struct Space {
     internal_sender: ghost_actor::GhostSender<SpaceInternal>,
     evt_sender: futures::channel::mpsc::Sender<KitsuneP2pEvent>,
     agents: HashMap<Arc<KitsuneAgent>, AgentInfo>,
+    /// Agents joined with `join_shared` which share their data with the
+    /// agents joined here. They are routed to but take no part in gossip.
+    aliases: HashSet<Arc<KitsuneAgent>>,
+    /// How many agents last advertised each op count,
+    /// so network info doesn't have to scan every agent
+    advertised_op_counts: BTreeMap<u64, usize>,
+    /// The in-process network our agents are also reachable on, if any
+    network: Option<InProcessNetwork>,
+}
+
+impl Drop for Space {
+    fn drop(&mut self) {
+        if let Some(network) = &self.network {
+            for agent in self.agents.keys().chain(self.aliases.iter()) {
+                network.leave(agent);
+            }
+        }
+    }
 }
 
 impl Space {
@@ -388,15 +553,86 @@ impl Space {
         space: Arc<KitsuneSpace>,
         internal_sender: ghost_actor::GhostSender<SpaceInternal>,
         evt_sender: futures::channel::mpsc::Sender<KitsuneP2pEvent>,
+        network: Option<InProcessNetwork>,
     ) -> Self {
         Self {
             space,
             internal_sender,
             evt_sender,
             agents: HashMap::new(),
+            aliases: HashSet::new(),
+            advertised_op_counts: BTreeMap::new(),
+            network,
         }
     }
 
+    /// Every agent we can route to, whether joined here or on our network
+    fn all_agents(&self) -> Vec<Arc<KitsuneAgent>> {
+        let mut agents: Vec<_> = self.agents.keys().cloned().collect();
+        if let Some(network) = &self.network {
+            for agent in network.agents() {
+                if !self.agents.contains_key(&agent) {
+                    agents.push(agent);
+                }
+            }
+        }
+        agents
+    }
+
+    /// The event sender of the node an agent is joined to
+    fn evt_sender_for(
+        &self,
+        agent: &Arc<KitsuneAgent>,
+    ) -> KitsuneP2pResult<futures::channel::mpsc::Sender<KitsuneP2pEvent>> {
+        if self.agents.contains_key(agent) || self.aliases.contains(agent) {
+            return Ok(self.evt_sender.clone());
+        }
+        self.network
+            .as_ref()
+            .and_then(|network| network.evt_sender(agent))
+            .ok_or_else(|| KitsuneP2pError::RoutingAgentError(agent.clone()))
+    }
+
+    /// Tell our implementor an agent has connected, along with the urls
+    /// from its agent info if we hold it.
+    /// This is spawned so a slow handler can't block this actor.
+    fn spawn_peer_connected(&self, agent: Arc<KitsuneAgent>) {
+        let space = self.space.clone();
+        let evt_sender = self.evt_sender.clone();
+        tokio::task::spawn(async move {
+            let urls = match evt_sender
+                .get_agent_info_signed(GetAgentInfoSignedEvt {
+                    space: space.clone(),
+                    agent: agent.clone(),
+                })
+                .await
+            {
+                Ok(Some(info)) => info.as_agent_info_ref().as_urls_ref().to_vec(),
+                _ => Vec::new(),
+            };
+            if let Err(e) = evt_sender
+                .peer_connected(PeerConnectedEvt { space, agent, urls })
+                .await
+            {
+                tracing::warn!(msg = "peer connected event was not handled", ?e);
+            }
+        });
+    }
+
+    /// Tell our implementor an agent has disconnected.
+    /// This is spawned so a slow handler can't block this actor.
+    fn spawn_peer_disconnected(&self, agent: Arc<KitsuneAgent>) {
+        let fut = self.evt_sender.peer_disconnected(PeerDisconnectedEvt {
+            space: self.space.clone(),
+            agent,
+        });
+        tokio::task::spawn(async move {
+            if let Err(e) = fut.await {
+                tracing::warn!(msg = "peer disconnected event was not handled", ?e);
+            }
+        });
+    }
+
     /// actual logic for handle_rpc_multi ...
     /// the top-level handler may or may not spawn a task for this
     #[allow(unused_variables, unused_assignments, unused_mut)]
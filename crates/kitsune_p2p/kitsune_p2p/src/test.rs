@@ -3,7 +3,7 @@ mod tests {
     use crate::{
         event::*,
         spawn::*,
-        types::{actor::KitsuneP2pSender, *},
+        types::{actor, actor::KitsuneP2pSender, config::KitsuneP2pConfig, *},
     };
     use futures::future::FutureExt;
     use ghost_actor::GhostControlSender;
@@ -18,7 +18,9 @@ mod tests {
         let a2: Arc<KitsuneAgent> =
             Arc::new(b"222222222222222222222222222222222222".to_vec().into());
 
-        let (p2p, mut evt) = spawn_kitsune_p2p().await.unwrap();
+        let (p2p, mut evt) = spawn_kitsune_p2p(KitsuneP2pConfig::default())
+            .await
+            .unwrap();
 
         let space1_clone = space1.clone();
         let a2_clone = a2.clone();
@@ -76,7 +78,9 @@ mod tests {
         let a3: Arc<KitsuneAgent> =
             Arc::new(b"333333333333333333333333333333333333".to_vec().into());
 
-        let (p2p, mut evt) = spawn_kitsune_p2p().await.unwrap();
+        let (p2p, mut evt) = spawn_kitsune_p2p(KitsuneP2pConfig::default())
+            .await
+            .unwrap();
 
         let recv_count = Arc::new(std::sync::atomic::AtomicU8::new(0));
 
@@ -142,7 +146,9 @@ mod tests {
         let a3: Arc<KitsuneAgent> =
             Arc::new(b"333333333333333333333333333333333333".to_vec().into());
 
-        let (p2p, mut evt) = spawn_kitsune_p2p().await.unwrap();
+        let (p2p, mut evt) = spawn_kitsune_p2p(KitsuneP2pConfig::default())
+            .await
+            .unwrap();
 
         let space1_clone = space1.clone();
         let r_task = tokio::task::spawn(async move {
@@ -207,7 +213,9 @@ mod tests {
         let a1: Arc<KitsuneAgent> =
             Arc::new(b"111111111111111111111111111111111111".to_vec().into());
 
-        let (p2p, mut evt) = spawn_kitsune_p2p().await.unwrap();
+        let (p2p, mut evt) = spawn_kitsune_p2p(KitsuneP2pConfig::default())
+            .await
+            .unwrap();
 
         let space1_clone = space1.clone();
         let r_task = tokio::task::spawn(async move {
@@ -230,6 +238,13 @@ mod tests {
                             .boxed()
                             .into()));
                     }
+                    // joining tells us the agent has connected
+                    GetAgentInfoSigned { respond, .. } => {
+                        respond.r(Ok(async move { Ok(None) }.boxed().into()));
+                    }
+                    PeerConnected { respond, .. } => {
+                        respond.r(Ok(async move { Ok(()) }.boxed().into()));
+                    }
                     _ => panic!("unexpected event"),
                 }
             }
@@ -277,7 +292,9 @@ mod tests {
         let oh2: Arc<KitsuneOpHash> =
             Arc::new(b"hhhhhhhhhhhhhhhhhhhhhhhhhhhhhhhhhhhh".to_vec().into());
 
-        let (p2p, mut evt) = spawn_kitsune_p2p().await.unwrap();
+        let (p2p, mut evt) = spawn_kitsune_p2p(KitsuneP2pConfig::default())
+            .await
+            .unwrap();
 
         let result = Arc::new(std::sync::RwLock::new((false, false)));
 
@@ -297,7 +314,9 @@ mod tests {
                         } else {
                             oh2.clone()
                         };
-                        respond.r(Ok(async move { Ok(vec![oh]) }.boxed().into()));
+                        respond.r(Ok(async move { Ok(ArcCoverage::Covered(vec![oh])) }
+                            .boxed()
+                            .into()));
                     }
                     FetchOpHashData { respond, input, .. } => {
                         //println!("FETCH HASH DATA REQ: {:#?}", input);
@@ -348,4 +367,559 @@ mod tests {
             panic!("failed to gossip both dht op hashes");
         }
     }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_network_info_workflow() {
+        use std::collections::{HashMap, HashSet};
+
+        let space1: Arc<KitsuneSpace> =
+            Arc::new(b"ssssssssssssssssssssssssssssssssssss".to_vec().into());
+        let a1: Arc<KitsuneAgent> =
+            Arc::new(b"111111111111111111111111111111111111".to_vec().into());
+        let a2: Arc<KitsuneAgent> =
+            Arc::new(b"222222222222222222222222222222222222".to_vec().into());
+
+        // a1 is seeded with 50 ops of 10 bytes each, a2 holds nothing
+        let mut held: HashMap<Arc<KitsuneAgent>, HashSet<Arc<KitsuneOpHash>>> = HashMap::new();
+        held.insert(
+            a1.clone(),
+            (0..50u8).map(|i| Arc::new(vec![i; 36].into())).collect(),
+        );
+        held.insert(a2.clone(), HashSet::new());
+        let held = Arc::new(std::sync::Mutex::new(held));
+
+        let (p2p, mut evt) = spawn_kitsune_p2p(KitsuneP2pConfig::default())
+            .await
+            .unwrap();
+
+        let held_clone = held.clone();
+        let r_task = tokio::task::spawn(async move {
+            use tokio::stream::StreamExt;
+            while let Some(evt) = evt.next().await {
+                use KitsuneP2pEvent::*;
+                match evt {
+                    FetchOpHashesForConstraints { respond, input, .. } => {
+                        let out = held_clone.lock().unwrap()[&input.agent]
+                            .iter()
+                            .cloned()
+                            .collect::<Vec<_>>();
+                        respond.r(Ok(async move { Ok(ArcCoverage::Covered(out)) }
+                            .boxed()
+                            .into()));
+                    }
+                    FetchOpHashData { respond, input, .. } => {
+                        let out = input
+                            .op_hashes
+                            .into_iter()
+                            .map(|op_hash| (op_hash, vec![0; 10]))
+                            .collect::<Vec<_>>();
+                        respond.r(Ok(async move { Ok(out) }.boxed().into()));
+                    }
+                    Gossip {
+                        respond,
+                        to_agent,
+                        op_hash,
+                        ..
+                    } => {
+                        held_clone
+                            .lock()
+                            .unwrap()
+                            .get_mut(&to_agent)
+                            .unwrap()
+                            .insert(op_hash);
+                        respond.r(Ok(async move { Ok(()) }.boxed().into()));
+                    }
+                    _ => (),
+                }
+            }
+        });
+
+        p2p.join(space1.clone(), a1.clone()).await.unwrap();
+
+        // Alone in the space there is nothing to estimate from
+        let info = p2p.network_info(space1.clone(), a1.clone()).await.unwrap();
+        assert_eq!(info.peer_count, 0);
+        assert_eq!(info.expected_op_count, None);
+        assert_eq!(info.last_gossip_ms, None);
+        assert_eq!(info.bytes_received, 0);
+
+        p2p.join(space1.clone(), a2.clone()).await.unwrap();
+
+        let held_count = || held.lock().unwrap()[&a2].len();
+        let mut last_gossip_ms = None;
+        for _ in 0..100 {
+            let info = p2p.network_info(space1.clone(), a2.clone()).await.unwrap();
+            assert_eq!(info.peer_count, 1);
+            if info.expected_op_count == Some(50) && held_count() == 50 {
+                assert_eq!(info.bytes_received, 500);
+                last_gossip_ms = info.last_gossip_ms;
+                break;
+            }
+            tokio::time::delay_for(std::time::Duration::from_millis(10)).await;
+        }
+        let last_gossip_ms = last_gossip_ms.expect("a2 never caught up with a1");
+
+        // Gossip rounds keep completing after a2 has caught up
+        let mut advanced = false;
+        for _ in 0..100 {
+            tokio::time::delay_for(std::time::Duration::from_millis(10)).await;
+            let info = p2p.network_info(space1.clone(), a2.clone()).await.unwrap();
+            if info.last_gossip_ms.unwrap() > last_gossip_ms {
+                advanced = true;
+                break;
+            }
+        }
+
+        p2p.ghost_actor_shutdown().await.unwrap();
+        r_task.await.unwrap();
+
+        assert!(advanced, "last gossip timestamp never advanced");
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_gossip_skips_agents_not_covering_the_arc() {
+        use std::collections::{HashMap, HashSet};
+
+        let space1: Arc<KitsuneSpace> =
+            Arc::new(b"ssssssssssssssssssssssssssssssssssss".to_vec().into());
+        let a1: Arc<KitsuneAgent> =
+            Arc::new(b"111111111111111111111111111111111111".to_vec().into());
+        let a2: Arc<KitsuneAgent> =
+            Arc::new(b"222222222222222222222222222222222222".to_vec().into());
+        let a3: Arc<KitsuneAgent> =
+            Arc::new(b"333333333333333333333333333333333333".to_vec().into());
+        let oh1: Arc<KitsuneOpHash> =
+            Arc::new(b"oooooooooooooooooooooooooooooooooooo".to_vec().into());
+
+        // a1 holds an op, a2 covers the arc but holds nothing,
+        // and a3 doesn't cover the arc at all
+        let mut held: HashMap<Arc<KitsuneAgent>, HashSet<Arc<KitsuneOpHash>>> = HashMap::new();
+        held.insert(a1.clone(), vec![oh1.clone()].into_iter().collect());
+        held.insert(a2.clone(), HashSet::new());
+        held.insert(a3.clone(), HashSet::new());
+        let held = Arc::new(std::sync::Mutex::new(held));
+
+        let (p2p, mut evt) = spawn_kitsune_p2p(KitsuneP2pConfig::default())
+            .await
+            .unwrap();
+
+        let held_clone = held.clone();
+        let a3_clone = a3.clone();
+        let r_task = tokio::task::spawn(async move {
+            use tokio::stream::StreamExt;
+            while let Some(evt) = evt.next().await {
+                use KitsuneP2pEvent::*;
+                match evt {
+                    FetchOpHashesForConstraints { respond, input, .. } => {
+                        let out = if input.agent == a3_clone {
+                            ArcCoverage::NotCovered
+                        } else {
+                            ArcCoverage::Covered(
+                                held_clone.lock().unwrap()[&input.agent]
+                                    .iter()
+                                    .cloned()
+                                    .collect::<Vec<_>>(),
+                            )
+                        };
+                        respond.r(Ok(async move { Ok(out) }.boxed().into()));
+                    }
+                    FetchOpHashData { respond, input, .. } => {
+                        let out = input
+                            .op_hashes
+                            .into_iter()
+                            .map(|op_hash| (op_hash, vec![]))
+                            .collect::<Vec<_>>();
+                        respond.r(Ok(async move { Ok(out) }.boxed().into()));
+                    }
+                    Gossip {
+                        respond,
+                        to_agent,
+                        op_hash,
+                        ..
+                    } => {
+                        held_clone
+                            .lock()
+                            .unwrap()
+                            .get_mut(&to_agent)
+                            .unwrap()
+                            .insert(op_hash);
+                        respond.r(Ok(async move { Ok(()) }.boxed().into()));
+                    }
+                    _ => (),
+                }
+            }
+        });
+
+        p2p.join(space1.clone(), a1.clone()).await.unwrap();
+        p2p.join(space1.clone(), a2.clone()).await.unwrap();
+        p2p.join(space1.clone(), a3.clone()).await.unwrap();
+
+        let held_by = |agent: &Arc<KitsuneAgent>| held.lock().unwrap()[agent].len();
+        for _ in 0..100 {
+            if held_by(&a2) == 1 {
+                break;
+            }
+            tokio::time::delay_for(std::time::Duration::from_millis(10)).await;
+        }
+        // Give a few more rounds the chance to reach a3
+        tokio::time::delay_for(std::time::Duration::from_millis(100)).await;
+
+        p2p.ghost_actor_shutdown().await.unwrap();
+        r_task.await.unwrap();
+
+        assert_eq!(held_by(&a2), 1, "a2 never received the op from a1");
+        assert_eq!(
+            held_by(&a3),
+            0,
+            "gossip reached an agent not covering the arc"
+        );
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_gossip_fetches_op_data_in_batches() {
+        use std::collections::{HashMap, HashSet};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let space1: Arc<KitsuneSpace> =
+            Arc::new(b"ssssssssssssssssssssssssssssssssssss".to_vec().into());
+        let a1: Arc<KitsuneAgent> =
+            Arc::new(b"111111111111111111111111111111111111".to_vec().into());
+        let a2: Arc<KitsuneAgent> =
+            Arc::new(b"222222222222222222222222222222222222".to_vec().into());
+        let ops: Vec<Arc<KitsuneOpHash>> = (b'a'..=b'e')
+            .map(|c| Arc::new(vec![c; 36].into()))
+            .collect();
+
+        // a1 holds five ops and a2 holds nothing
+        let mut held: HashMap<Arc<KitsuneAgent>, HashSet<Arc<KitsuneOpHash>>> = HashMap::new();
+        held.insert(a1.clone(), ops.iter().cloned().collect());
+        held.insert(a2.clone(), HashSet::new());
+        let held = Arc::new(std::sync::Mutex::new(held));
+        let largest_fetch = Arc::new(AtomicUsize::new(0));
+
+        let config = KitsuneP2pConfig {
+            gossip_fetch_batch_size: 2,
+            ..Default::default()
+        };
+        let (p2p, mut evt) = spawn_kitsune_p2p(config).await.unwrap();
+
+        let held_clone = held.clone();
+        let largest_fetch_clone = largest_fetch.clone();
+        let r_task = tokio::task::spawn(async move {
+            use tokio::stream::StreamExt;
+            while let Some(evt) = evt.next().await {
+                use KitsuneP2pEvent::*;
+                match evt {
+                    FetchOpHashesForConstraints { respond, input, .. } => {
+                        let out = ArcCoverage::Covered(
+                            held_clone.lock().unwrap()[&input.agent]
+                                .iter()
+                                .cloned()
+                                .collect::<Vec<_>>(),
+                        );
+                        respond.r(Ok(async move { Ok(out) }.boxed().into()));
+                    }
+                    FetchOpHashData { respond, input, .. } => {
+                        largest_fetch_clone.fetch_max(input.op_hashes.len(), Ordering::SeqCst);
+                        let out = input
+                            .op_hashes
+                            .into_iter()
+                            .map(|op_hash| (op_hash, vec![]))
+                            .collect::<Vec<_>>();
+                        respond.r(Ok(async move { Ok(out) }.boxed().into()));
+                    }
+                    Gossip {
+                        respond,
+                        to_agent,
+                        op_hash,
+                        ..
+                    } => {
+                        held_clone
+                            .lock()
+                            .unwrap()
+                            .get_mut(&to_agent)
+                            .unwrap()
+                            .insert(op_hash);
+                        respond.r(Ok(async move { Ok(()) }.boxed().into()));
+                    }
+                    _ => (),
+                }
+            }
+        });
+
+        p2p.join(space1.clone(), a1.clone()).await.unwrap();
+        p2p.join(space1.clone(), a2.clone()).await.unwrap();
+
+        let held_by = |agent: &Arc<KitsuneAgent>| held.lock().unwrap()[agent].len();
+        for _ in 0..100 {
+            if held_by(&a2) == ops.len() {
+                break;
+            }
+            tokio::time::delay_for(std::time::Duration::from_millis(10)).await;
+        }
+
+        p2p.ghost_actor_shutdown().await.unwrap();
+        r_task.await.unwrap();
+
+        assert_eq!(held_by(&a2), ops.len(), "a2 never received all the ops");
+        assert_eq!(
+            largest_fetch.load(Ordering::SeqCst),
+            2,
+            "op data wasn't fetched in batches of the configured size"
+        );
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_peer_connection_events() {
+        let space1: Arc<KitsuneSpace> =
+            Arc::new(b"ssssssssssssssssssssssssssssssssssss".to_vec().into());
+        let a1: Arc<KitsuneAgent> =
+            Arc::new(b"111111111111111111111111111111111111".to_vec().into());
+        let a2: Arc<KitsuneAgent> =
+            Arc::new(b"222222222222222222222222222222222222".to_vec().into());
+
+        let (p2p, mut evt) = spawn_kitsune_p2p(KitsuneP2pConfig::default())
+            .await
+            .unwrap();
+
+        let (peer_send, mut peer_recv) = tokio::sync::mpsc::unbounded_channel();
+        let (release_send, release_recv) = tokio::sync::oneshot::channel::<()>();
+        let a2_clone = a2.clone();
+        let r_task = tokio::task::spawn(async move {
+            use tokio::stream::StreamExt;
+            let mut release_recv = Some(release_recv);
+            while let Some(evt) = evt.next().await {
+                use KitsuneP2pEvent::*;
+                match evt {
+                    GetAgentInfoSigned { respond, input, .. } => {
+                        // only a2 has published agent info
+                        let info = if input.agent == a2_clone {
+                            let agent_info = agent_store::AgentInfo::new(
+                                (*input.space).clone(),
+                                (*input.agent).clone(),
+                                vec![url2::url2!("kitsune-mem://a2")],
+                                0,
+                            );
+                            Some(
+                                agent_store::AgentInfoSigned::try_new(
+                                    vec![0; 64].into(),
+                                    agent_info,
+                                )
+                                .unwrap(),
+                            )
+                        } else {
+                            None
+                        };
+                        respond.r(Ok(async move { Ok(info) }.boxed().into()));
+                    }
+                    PeerConnected { respond, input, .. } => {
+                        // Hold up the first answer, which mustn't stop a2 joining
+                        if let Some(release_recv) = release_recv.take() {
+                            release_recv.await.unwrap();
+                        }
+                        peer_send.send((true, input.agent, input.urls)).unwrap();
+                        respond.r(Ok(async move { Ok(()) }.boxed().into()));
+                    }
+                    PeerDisconnected { respond, input, .. } => {
+                        peer_send.send((false, input.agent, vec![])).unwrap();
+                        respond.r(Ok(async move { Ok(()) }.boxed().into()));
+                    }
+                    _ => (),
+                }
+            }
+        });
+
+        p2p.join(space1.clone(), a1.clone()).await.unwrap();
+        p2p.join(space1.clone(), a2.clone()).await.unwrap();
+        // Joining again is not another connection
+        p2p.join(space1.clone(), a2.clone()).await.unwrap();
+        release_send.send(()).unwrap();
+
+        let mut connected = vec![
+            peer_recv.recv().await.unwrap(),
+            peer_recv.recv().await.unwrap(),
+        ];
+        connected.sort_by(|a, b| a.1.cmp(&b.1));
+        assert_eq!(
+            connected,
+            vec![
+                (true, a1.clone(), vec![]),
+                (true, a2.clone(), vec![url2::url2!("kitsune-mem://a2")]),
+            ]
+        );
+
+        p2p.leave(space1.clone(), a1.clone()).await.unwrap();
+        // Leaving again is not another disconnection
+        p2p.leave(space1.clone(), a1.clone()).await.unwrap();
+        assert_eq!(peer_recv.recv().await.unwrap(), (false, a1, vec![]));
+
+        p2p.ghost_actor_shutdown().await.unwrap();
+        r_task.await.unwrap();
+        assert!(peer_recv.recv().await.is_none());
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_in_process_network_workflow() {
+        let space1: Arc<KitsuneSpace> =
+            Arc::new(b"ssssssssssssssssssssssssssssssssssss".to_vec().into());
+        let a1: Arc<KitsuneAgent> =
+            Arc::new(b"111111111111111111111111111111111111".to_vec().into());
+        let a2: Arc<KitsuneAgent> =
+            Arc::new(b"222222222222222222222222222222222222".to_vec().into());
+
+        // Two nodes on one network, and a third which isn't on it
+        let config = KitsuneP2pConfig {
+            in_process_network: Some("test_in_process_network_workflow".to_string()),
+            ..Default::default()
+        };
+        let (p2p1, evt1) = spawn_kitsune_p2p(config.clone()).await.unwrap();
+        let (p2p2, mut evt2) = spawn_kitsune_p2p(config).await.unwrap();
+        let (p2p3, evt3) = spawn_kitsune_p2p(KitsuneP2pConfig::default())
+            .await
+            .unwrap();
+
+        // Nothing is asked of the other nodes' implementors
+        for mut evt in vec![evt1, evt3] {
+            tokio::task::spawn(async move {
+                use tokio::stream::StreamExt;
+                while evt.next().await.is_some() {}
+            });
+        }
+
+        let a2_clone = a2.clone();
+        let r_task = tokio::task::spawn(async move {
+            use tokio::stream::StreamExt;
+            while let Some(evt) = evt2.next().await {
+                use KitsuneP2pEvent::*;
+                match evt {
+                    Call {
+                        respond,
+                        to_agent,
+                        payload,
+                        ..
+                    } => {
+                        if to_agent != a2_clone {
+                            panic!("unexpected agent");
+                        }
+                        if &*payload != b"hello" {
+                            panic!("unexpected request");
+                        }
+                        respond.r(Ok(async move { Ok(b"echo: hello".to_vec()) }
+                            .boxed()
+                            .into()));
+                    }
+                    _ => (),
+                }
+            }
+        });
+
+        p2p1.join(space1.clone(), a1.clone()).await.unwrap();
+        p2p2.join(space1.clone(), a2.clone()).await.unwrap();
+        p2p3.join(space1.clone(), a1.clone()).await.unwrap();
+
+        // a1 reaches a2 on the other node
+        let res = p2p1
+            .rpc_single(space1.clone(), a2.clone(), a1.clone(), b"hello".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(b"echo: hello".to_vec(), res);
+
+        let peer_count = |p2p: ghost_actor::GhostSender<actor::KitsuneP2p>| {
+            let space1 = space1.clone();
+            let a1 = a1.clone();
+            async move { p2p.network_info(space1, a1).await.unwrap().peer_count }
+        };
+        assert_eq!(1, peer_count(p2p1.clone()).await);
+        assert_eq!(0, peer_count(p2p3.clone()).await);
+
+        // Once a2 leaves it is no longer a peer
+        p2p2.leave(space1.clone(), a2.clone()).await.unwrap();
+        assert_eq!(0, peer_count(p2p1.clone()).await);
+
+        p2p1.ghost_actor_shutdown().await.unwrap();
+        p2p2.ghost_actor_shutdown().await.unwrap();
+        p2p3.ghost_actor_shutdown().await.unwrap();
+        r_task.await.unwrap();
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn test_join_shared_workflow() {
+        let space1: Arc<KitsuneSpace> =
+            Arc::new(b"ssssssssssssssssssssssssssssssssssss".to_vec().into());
+        let a1: Arc<KitsuneAgent> =
+            Arc::new(b"111111111111111111111111111111111111".to_vec().into());
+        let a2: Arc<KitsuneAgent> =
+            Arc::new(b"222222222222222222222222222222222222".to_vec().into());
+        let a3: Arc<KitsuneAgent> =
+            Arc::new(b"333333333333333333333333333333333333".to_vec().into());
+
+        let config = KitsuneP2pConfig {
+            in_process_network: Some("test_join_shared_workflow".to_string()),
+            ..Default::default()
+        };
+        let (p2p1, mut evt1) = spawn_kitsune_p2p(config.clone()).await.unwrap();
+        let (p2p2, mut evt2) = spawn_kitsune_p2p(config).await.unwrap();
+
+        tokio::task::spawn(async move {
+            use tokio::stream::StreamExt;
+            while evt2.next().await.is_some() {}
+        });
+
+        let a2_clone = a2.clone();
+        let r_task = tokio::task::spawn(async move {
+            use tokio::stream::StreamExt;
+            while let Some(evt) = evt1.next().await {
+                use KitsuneP2pEvent::*;
+                match evt {
+                    Call {
+                        respond,
+                        to_agent,
+                        payload,
+                        ..
+                    } => {
+                        if to_agent != a2_clone {
+                            panic!("unexpected agent");
+                        }
+                        if &*payload != b"hello" {
+                            panic!("unexpected request");
+                        }
+                        respond.r(Ok(async move { Ok(b"echo: hello".to_vec()) }
+                            .boxed()
+                            .into()));
+                    }
+                    _ => (),
+                }
+            }
+        });
+
+        // a2 shares its data with a1 on the first node
+        p2p1.join(space1.clone(), a1.clone()).await.unwrap();
+        p2p1.join_shared(space1.clone(), a2.clone()).await.unwrap();
+        p2p2.join(space1.clone(), a3.clone()).await.unwrap();
+
+        // a3 still reaches a2
+        let res = p2p2
+            .rpc_single(space1.clone(), a2.clone(), a3.clone(), b"hello".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(b"echo: hello".to_vec(), res);
+
+        // but only a1 is a peer of a3, and a2 has a1's network info
+        let peer_count = |p2p: ghost_actor::GhostSender<actor::KitsuneP2p>, agent| {
+            let space1 = space1.clone();
+            async move { p2p.network_info(space1, agent).await.unwrap().peer_count }
+        };
+        assert_eq!(1, peer_count(p2p2.clone(), a3.clone()).await);
+        assert_eq!(1, peer_count(p2p1.clone(), a2.clone()).await);
+
+        // Once a1 leaves, a2 takes its place
+        p2p1.leave(space1.clone(), a1.clone()).await.unwrap();
+        assert_eq!(1, peer_count(p2p2.clone(), a3.clone()).await);
+        assert_eq!(1, peer_count(p2p1.clone(), a2.clone()).await);
+        assert!(p2p1.network_info(space1.clone(), a1.clone()).await.is_err());
+
+        p2p1.ghost_actor_shutdown().await.unwrap();
+        p2p2.ghost_actor_shutdown().await.unwrap();
+        r_task.await.unwrap();
+    }
 }
@@ -178,6 +178,7 @@ impl std::fmt::Debug for KitsuneSignature {
 
 pub mod actor;
 pub mod agent_store;
+pub mod config;
 pub mod event;
 pub(crate) mod wire;
 
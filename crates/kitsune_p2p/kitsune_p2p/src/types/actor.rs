@@ -62,12 +62,35 @@ pub struct NotifyMulti {
     pub payload: Vec<u8>,
 }
 
+/// Gossip progress of an agent joined to a space, as seen by this node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NetworkInfo {
+    /// How many other agents in the space this node knows of.
+    pub peer_count: u32,
+    /// The arc of the dht this agent is storing.
+    pub storage_arc: kitsune_p2p_types::dht_arc::DhtArc,
+    /// An estimate of how many ops are held within the storage arc,
+    /// from the op counts peers advertise during gossip.
+    /// `None` until a peer has advertised a count.
+    pub expected_op_count: Option<u64>,
+    /// When the last gossip round with this agent completed, in unix ms.
+    /// `None` until a round has completed.
+    pub last_gossip_ms: Option<u64>,
+    /// How many bytes of op data this agent has received by gossip since it joined.
+    pub bytes_received: u64,
+}
+
 ghost_actor::ghost_chan! {
     /// The KitsuneP2pSender allows async remote-control of the KitsuneP2p actor.
     pub chan KitsuneP2p<super::KitsuneP2pError> {
         /// Announce a space/agent pair on this network.
         fn join(space: Arc<super::KitsuneSpace>, agent: Arc<super::KitsuneAgent>) -> ();
 
+        /// Announce a space/agent pair which shares its data with the agents
+        /// already joined to the space on this node. Only the first agent joined
+        /// takes part in gossip, but requests addressed to any of them reach it.
+        fn join_shared(space: Arc<super::KitsuneSpace>, agent: Arc<super::KitsuneAgent>) -> ();
+
         /// Withdraw this space/agent pair from this network.
         fn leave(space: Arc<super::KitsuneSpace>, agent: Arc<super::KitsuneAgent>) -> ();
 
@@ -83,5 +106,8 @@ ghost_actor::ghost_chan! {
         /// Returns an approximate number of nodes reached.
         /// The remote sides will see these messages as "Notify" events.
         fn notify_multi(input: NotifyMulti) -> u8;
+
+        /// Report the gossip progress of a space/agent pair joined on this network.
+        fn network_info(space: Arc<super::KitsuneSpace>, agent: Arc<super::KitsuneAgent>) -> NetworkInfo;
     }
 }
@@ -0,0 +1,32 @@
+//! Configuration of a KitsuneP2p actor.
+
+/// The default for [KitsuneP2pConfig::gossip_fetch_batch_size]
+pub const DEFAULT_GOSSIP_FETCH_BATCH_SIZE: usize = 100;
+
+/// Configures a KitsuneP2p actor, see [spawn_kitsune_p2p](crate::spawn_kitsune_p2p)
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct KitsuneP2pConfig {
+    /// How many ops gossip asks an agent for the data of in one request.
+    /// Each request is a round trip, so on a high-latency link larger
+    /// batches sync faster. But the data of every op in a batch is held in
+    /// memory at once, so a memory-constrained node should use smaller ones.
+    /// Zero is treated as one.
+    pub gossip_fetch_batch_size: usize,
+
+    /// Join spaces on a network shared with every other KitsuneP2p actor
+    /// in this process configured with the same name, so that their agents
+    /// reach each other as if they were joined to one actor.
+    /// This is only for tests which run several nodes in one process.
+    /// With None, only the agents joined to this actor reach each other.
+    pub in_process_network: Option<String>,
+}
+
+impl Default for KitsuneP2pConfig {
+    fn default() -> Self {
+        Self {
+            gossip_fetch_batch_size: DEFAULT_GOSSIP_FETCH_BATCH_SIZE,
+            in_process_network: None,
+        }
+    }
+}
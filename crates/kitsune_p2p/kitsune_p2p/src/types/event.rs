@@ -1,6 +1,6 @@
 //! Definitions for events emited from the KitsuneP2p actor.
 
-use crate::types::agent_store::AgentInfoSigned;
+use crate::types::agent_store::{AgentInfoSigned, Urls};
 use std::sync::Arc;
 
 /// Gather a list of op-hashes from our implementor that meet criteria.
@@ -18,6 +18,37 @@ pub struct FetchOpHashesForConstraintsEvt {
     pub until_utc_epoch_s: i64,
 }
 
+/// Our implementor's answer to a [FetchOpHashesForConstraintsEvt].
+/// An agent that doesn't store any of the requested arc can't say what is
+/// in it, so an empty list would be misleading. Gossip only syncs with
+/// agents whose answer is `Covered`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArcCoverage<T> {
+    /// The agent doesn't store any part of the requested arc.
+    NotCovered,
+    /// The agent stores the requested arc, and these are the results,
+    /// which may be empty.
+    Covered(T),
+}
+
+impl<T> ArcCoverage<T> {
+    /// Convert the results, if the arc is covered.
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> ArcCoverage<U> {
+        match self {
+            ArcCoverage::NotCovered => ArcCoverage::NotCovered,
+            ArcCoverage::Covered(t) => ArcCoverage::Covered(f(t)),
+        }
+    }
+
+    /// The results, if the arc is covered.
+    pub fn covered(self) -> Option<T> {
+        match self {
+            ArcCoverage::NotCovered => None,
+            ArcCoverage::Covered(t) => Some(t),
+        }
+    }
+}
+
 /// Gather all op-hash data for a list of op-hashes from our implementor.
 #[derive(Debug)]
 pub struct FetchOpHashDataEvt {
@@ -60,6 +91,28 @@ pub struct GetAgentInfoSignedEvt {
     pub agent: Arc<super::KitsuneAgent>,
 }
 
+#[derive(Debug)]
+/// An agent can now be reached in a space.
+pub struct PeerConnectedEvt {
+    /// The "space" context.
+    pub space: Arc<super::KitsuneSpace>,
+    /// The agent which connected.
+    pub agent: Arc<super::KitsuneAgent>,
+    /// The urls the agent can be reached at, from its signed agent info.
+    /// Empty if we hold no agent info for it, as while agents are only
+    /// short-circuited within this node.
+    pub urls: Urls,
+}
+
+#[derive(Debug)]
+/// An agent can no longer be reached in a space.
+pub struct PeerDisconnectedEvt {
+    /// The "space" context.
+    pub space: Arc<super::KitsuneSpace>,
+    /// The agent which disconnected.
+    pub agent: Arc<super::KitsuneAgent>,
+}
+
 ghost_actor::ghost_chan! {
     /// The KitsuneP2pEvent stream allows handling events generated from the
     /// KitsuneP2p actor.
@@ -86,13 +139,21 @@ ghost_actor::ghost_chan! {
         ) -> ();
 
         /// Gather a list of op-hashes from our implementor that meet criteria.
-        fn fetch_op_hashes_for_constraints(input: FetchOpHashesForConstraintsEvt) -> Vec<Arc<super::KitsuneOpHash>>;
+        fn fetch_op_hashes_for_constraints(input: FetchOpHashesForConstraintsEvt) -> ArcCoverage<Vec<Arc<super::KitsuneOpHash>>>;
 
         /// Gather all op-hash data for a list of op-hashes from our implementor.
         fn fetch_op_hash_data(input: FetchOpHashDataEvt) -> Vec<(Arc<super::KitsuneOpHash>, Vec<u8>)>;
 
         /// Request that our implementor sign some data on behalf of an agent.
         fn sign_network_data(input: SignNetworkDataEvt) -> super::KitsuneSignature;
+
+        /// An agent has connected to a space. Sent without waiting on the
+        /// answer, so handling it can't hold up the network.
+        fn peer_connected(input: PeerConnectedEvt) -> ();
+
+        /// An agent has disconnected from a space. Sent without waiting on
+        /// the answer, so handling it can't hold up the network.
+        fn peer_disconnected(input: PeerDisconnectedEvt) -> ();
     }
 }
 
@@ -69,6 +69,35 @@ impl DhtArc {
         do_hold_something && (only_hold_self || within_range)
     }
 
+    /// Check if this arc and another have any location in common.
+    /// Two arcs overlap exactly when one contains the start of the other.
+    pub fn overlaps(&self, other: &DhtArc) -> bool {
+        match (self.range().start, other.range().start) {
+            (Bound::Included(a), Bound::Included(b)) => self.contains(b) || other.contains(a),
+            _ => false,
+        }
+    }
+
+    /// A human readable summary of the arc, for logs, e.g.
+    /// `center=0x40000000 half_len=25.00% covers [0x00000001..=0x7fffffff]`.
+    /// The half length is given as a share of the whole ring.
+    pub fn coverage_summary(&self) -> String {
+        let half_len = self.half_length as f64 / (u32::MAX as f64 + 1.0) * 100.0;
+        let covers = match self.range() {
+            ArcRange {
+                start: Bound::Included(lo),
+                end: Bound::Included(hi),
+            } => format!("[0x{:08x}..=0x{:08x}]", lo, hi),
+            _ => "nothing".to_string(),
+        };
+        format!(
+            "center=0x{:08x} half_len={:.2}% covers {}",
+            u32::from(self.center_loc),
+            half_len,
+            covers
+        )
+    }
+
     /// Get the range of the arc
     pub fn range(&self) -> ArcRange {
         if self.half_length == 0 {
@@ -103,6 +132,12 @@ impl DhtArc {
     }
 }
 
+impl std::fmt::Display for DhtArc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.coverage_summary())
+    }
+}
+
 impl From<u32> for DhtLocation {
     fn from(a: u32) -> Self {
         Self(Wrapping(a))
@@ -318,4 +353,55 @@ mod tests {
         );
         check_bounds_full(0, MAX_HALF_LENGTH, half, half - 1);
     }
+
+    #[test]
+    fn test_arc_overlaps() {
+        let quarter = (u32::MAX as f64 / 4.0).round() as u32;
+        let half = (u32::MAX as f64 / 2.0).round() as u32;
+        let full = DhtArc::new(0, MAX_HALF_LENGTH);
+
+        // Arcs on either side of the circle
+        let left = DhtArc::new(quarter, quarter / 2);
+        let right = DhtArc::new(quarter * 3, quarter / 2);
+        assert!(!left.overlaps(&right));
+        assert!(!right.overlaps(&left));
+        assert!(left.overlaps(&full));
+        assert!(full.overlaps(&right));
+
+        // Overlapping at one end, and one within the other
+        let middle = DhtArc::new(half, quarter + 1);
+        assert!(middle.overlaps(&left));
+        assert!(right.overlaps(&middle));
+        assert!(middle.overlaps(&DhtArc::new(half, 10)));
+        assert!(DhtArc::new(half, 10).overlaps(&middle));
+
+        // Overlapping across zero
+        assert!(DhtArc::new(0, 2).overlaps(&DhtArc::new(u32::MAX, 2)));
+        assert!(!DhtArc::new(1, 1).overlaps(&DhtArc::new(u32::MAX, 2)));
+
+        // An empty arc overlaps nothing
+        assert!(!DhtArc::new(0, 0).overlaps(&full));
+        assert!(!full.overlaps(&DhtArc::new(0, 0)));
+    }
+
+    #[test]
+    fn test_arc_coverage_summary() {
+        let quarter = (u32::MAX as f64 / 4.0).round() as u32;
+        assert_eq!(
+            DhtArc::new(quarter, quarter / 2).coverage_summary(),
+            "center=0x40000000 half_len=12.50% covers [0x20000001..=0x5fffffff]"
+        );
+        assert_eq!(
+            DhtArc::new(0, 2).to_string(),
+            "center=0x00000000 half_len=0.00% covers [0xffffffff..=0x00000001]"
+        );
+        assert_eq!(
+            DhtArc::new(0, MAX_HALF_LENGTH).coverage_summary(),
+            "center=0x00000000 half_len=50.00% covers [0x80000000..=0x7fffffff]"
+        );
+        assert_eq!(
+            DhtArc::new(7, 0).coverage_summary(),
+            "center=0x00000007 half_len=0.00% covers nothing"
+        );
+    }
 }
@@ -87,16 +87,23 @@ where
         self.0.contains(r, &k)
     }
 
-    /// Iterate over the underlying persisted data taking the scratch space into consideration
+    /// Iterate over the underlying persisted data taking the scratch space into consideration.
+    /// Only the keys under this buffer's prefix are visited, as the database
+    /// is shared with the other prefixes.
     pub fn iter_fail<'r, R: Readable>(
         &'r self,
         r: &'r R,
     ) -> DatabaseResult<impl FallibleIterator<Item = HoloHashed<C>, Error = DatabaseError> + 'r>
     {
-        Ok(Box::new(self.0.iter(r)?.map(|(h, c)| {
-            let k: PrefixHashKey<P> = PrefixHashKey::from_key_bytes_or_friendly_panic(h);
-            Ok(Self::deserialize_and_hash_blocking(k.as_hash_bytes(), c))
-        })))
+        Ok(Box::new(
+            self.0
+                .iter_from(r, PrefixHashKey::first())?
+                .take_while(|(h, _)| Ok(h.first() == Some(&P::PREFIX)))
+                .map(|(h, c)| {
+                    let k: PrefixHashKey<P> = PrefixHashKey::from_key_bytes_or_friendly_panic(h);
+                    Ok(Self::deserialize_and_hash_blocking(k.as_hash_bytes(), c))
+                }),
+        ))
     }
 
     fn deserialize_and_hash_blocking(hash: &[u8], content: C) -> HoloHashed<C> {
@@ -3,10 +3,12 @@
 
 use crate::{
     buffer::{BufferedStore, KvBufUsed},
+    db::DbName,
     env::EnvironmentRead,
     error::{DatabaseError, DatabaseResult},
     fatal_db_hash_integrity_check, fresh_reader,
     prelude::*,
+    shared_dht::SharedDhtWrites,
     transaction::Readable,
 };
 use fallible_iterator::FallibleIterator;
@@ -53,6 +55,11 @@ where
         self.0.delete(k).expect("Hash key is empty");
     }
 
+    /// Roughly how many bytes are waiting in the underlying [KvBufUsed] scratch space
+    pub fn scratch_size(&self) -> usize {
+        self.0.scratch_size()
+    }
+
     /// Remove a delete from the underlying [KvBufUsed] scratch space
     pub fn cancel_delete(&mut self, k: HoloHashOf<C>) {
         let k = PrefixHashKey::new(k.as_hash());
@@ -80,16 +87,23 @@ where
         self.0.contains(r, &k)
     }
 
-    /// Iterate over the underlying persisted data taking the scratch space into consideration
+    /// Iterate over the underlying persisted data taking the scratch space into consideration.
+    /// Only the keys under this buffer's prefix are visited, as the database
+    /// is shared with the other prefixes.
     pub fn iter_fail<'r, R: Readable>(
         &'r self,
         r: &'r R,
     ) -> DatabaseResult<impl FallibleIterator<Item = HoloHashed<C>, Error = DatabaseError> + 'r>
     {
-        Ok(Box::new(self.0.iter(r)?.map(|(h, c)| {
-            let k: PrefixHashKey<P> = PrefixHashKey::from_key_bytes_or_friendly_panic(h);
-            Ok(Self::deserialize_and_hash(k.as_hash_bytes(), c))
-        })))
+        Ok(Box::new(
+            self.0
+                .iter_from(r, PrefixHashKey::first())?
+                .take_while(|(h, _)| Ok(h.first() == Some(&P::PREFIX)))
+                .map(|(h, c)| {
+                    let k: PrefixHashKey<P> = PrefixHashKey::from_key_bytes_or_friendly_panic(h);
+                    Ok(Self::deserialize_and_hash(k.as_hash_bytes(), c))
+                }),
+        ))
     }
 
     fn deserialize_and_hash(hash_bytes: &[u8], content: C) -> HoloHashed<C> {
@@ -108,6 +122,12 @@ where
     pub fn clear_all(&mut self, writer: &mut Writer) -> DatabaseResult<()> {
         self.0.clear_all(writer)
     }
+
+    /// Record the writes a flush would make, for a store of the shared
+    /// DHT environment, instead of making them
+    pub fn journal_to(&self, db: DbName, writes: &mut SharedDhtWrites) -> DatabaseResult<()> {
+        self.0.journal_to(db, writes)
+    }
 }
 
 #[derive(shrinkwraprs::Shrinkwrap)]
@@ -7,10 +7,12 @@ use crate::buffer::{
     BufferedStore,
 };
 use crate::{
+    db::DbName,
     env::EnvironmentRead,
     error::{DatabaseError, DatabaseResult},
     fresh_reader,
     prelude::*,
+    shared_dht::{SharedDhtWrites, StoreWrite},
 };
 use fallible_iterator::FallibleIterator;
 use rkv::{IntegerStore, SingleStore};
@@ -37,6 +39,19 @@ pub enum KvOp<V> {
     Delete,
 }
 
+impl<V: BufVal> KvOp<V> {
+    /// Roughly how many bytes this op holds in the scratch space:
+    /// the serialized size of a put value
+    fn scratch_size(&self) -> usize {
+        match self {
+            KvOp::Put(v) => holochain_serialized_bytes::encode(v)
+                .map(|buf| buf.len())
+                .unwrap_or_default(),
+            KvOp::Delete => 0,
+        }
+    }
+}
+
 pub struct Used<K, V, Store>
 where
     K: BufKey,
@@ -90,6 +105,19 @@ where
         self.scratch.clear();
         Ok(self.store.delete_all(writer)?)
     }
+
+    /// Record the writes a flush would make, for a store of the shared
+    /// DHT environment, instead of making them
+    pub fn journal_to(&self, db: DbName, writes: &mut SharedDhtWrites) -> DatabaseResult<()> {
+        for (k, op) in self.scratch.iter() {
+            let write = match op {
+                KvOp::Put(v) => StoreWrite::Put(k.clone(), holochain_serialized_bytes::encode(v)?),
+                KvOp::Delete => StoreWrite::Delete(k.clone()),
+            };
+            writes.push(db.clone(), write);
+        }
+        Ok(())
+    }
 }
 
 impl<'env, K, V, Store> Used<K, V, Store>
@@ -156,6 +184,13 @@ where
         self.scratch.is_empty()
     }
 
+    /// Roughly how many bytes of values are waiting in the scratch space
+    /// to be flushed, by their serialized size.
+    /// Every value is encoded to work this out, so only ask when it's needed.
+    pub fn scratch_size(&self) -> usize {
+        self.scratch.values().map(KvOp::scratch_size).sum()
+    }
+
     #[cfg(test)]
     pub(crate) fn scratch(&self) -> &Scratch<V> {
         &self.scratch
@@ -170,6 +170,28 @@ async fn kv_indicate_value_overwritten() -> DatabaseResult<()> {
     })
 }
 
+#[tokio::test(threaded_scheduler)]
+async fn kv_scratch_size() -> DatabaseResult<()> {
+    let test_env = test_cell_env();
+    let arc = test_env.env();
+    let env = arc.guard();
+    let db = env.inner().open_single("kv", StoreOptions::create())?;
+    let mut buf = Store::new(db);
+    let size = holochain_serialized_bytes::encode(&V(1))?.len();
+    assert_eq!(buf.scratch_size(), 0);
+
+    buf.put("a".into(), V(1)).unwrap();
+    buf.put("b".into(), V(2)).unwrap();
+    assert_eq!(buf.scratch_size(), 2 * size);
+    // Overwriting a value doesn't count it twice
+    buf.put("a".into(), V(3)).unwrap();
+    assert_eq!(buf.scratch_size(), 2 * size);
+    // Deleting a value in the scratch space frees it
+    buf.delete("a".into()).unwrap();
+    assert_eq!(buf.scratch_size(), size);
+    Ok(())
+}
+
 #[tokio::test(threaded_scheduler)]
 async fn kv_deleted_persisted() -> DatabaseResult<()> {
     use tracing::*;
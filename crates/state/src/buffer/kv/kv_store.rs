@@ -106,6 +106,27 @@ where
         self.db
     }
 
+    /// Iterate over the raw persisted key and value bytes, optionally
+    /// starting from a key. Values are not deserialized, so this can be used
+    /// to inspect data which may be corrupt.
+    pub fn iter_raw_from<'env, R: Readable>(
+        &self,
+        reader: &'env R,
+        from: Option<&[u8]>,
+    ) -> DatabaseResult<
+        impl FallibleIterator<Item = (&'env [u8], &'env [u8]), Error = DatabaseError> + 'env,
+    > {
+        let iter = match from {
+            Some(k) => self.db.iter_from(reader, k)?,
+            None => self.db.iter_start(reader)?,
+        };
+        Ok(fallible_iterator::convert(iter.map(|item| match item {
+            Ok((k, Some(rkv::Value::Blob(buf)))) => Ok((k, buf)),
+            Ok(_) => Err(DatabaseError::InvalidValue),
+            Err(e) => Err(DatabaseError::from(e)),
+        })))
+    }
+
     // TODO: This should be cfg test but can't because it's in a different crate
     /// Clear db, useful for tests
     pub fn delete_all(&mut self, writer: &mut Writer) -> DatabaseResult<()> {
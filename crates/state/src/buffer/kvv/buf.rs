@@ -1,7 +1,9 @@
 use crate::{
     buffer::BufferedStore,
+    db::DbName,
     error::{DatabaseError, DatabaseResult},
     prelude::*,
+    shared_dht::{SharedDhtWrites, StoreWrite},
 };
 use either::Either;
 use rkv::MultiStore;
@@ -35,6 +37,24 @@ impl<V: Ord + Eq> ValuesDelta<V> {
     }
 }
 
+impl<V: BufMultiVal> ValuesDelta<V> {
+    /// Roughly how many bytes the inserted values take, serialized
+    fn scratch_size(&self) -> usize {
+        self.deltas
+            .iter()
+            .filter(|(_, op)| **op == KvvOp::Insert)
+            .map(|(v, _)| value_size(v))
+            .sum()
+    }
+}
+
+/// The serialized size of a value
+fn value_size<V: BufMultiVal>(v: &V) -> usize {
+    holochain_serialized_bytes::encode(v)
+        .map(|buf| buf.len())
+        .unwrap_or_default()
+}
+
 // This would be equivalent to the derived impl, except that this
 // doesn't require `V: Default`
 impl<V: Ord + Eq> Default for ValuesDelta<V> {
@@ -152,6 +172,13 @@ where
         self.scratch.insert(k, ValuesDelta::all_deleted());
     }
 
+    /// Roughly how many bytes of values are waiting in the scratch space
+    /// to be flushed, by their serialized size.
+    /// Every value is encoded to work this out, so only ask when it's needed.
+    pub fn scratch_size(&self) -> usize {
+        self.scratch.values().map(ValuesDelta::scratch_size).sum()
+    }
+
     /// Fetch data from DB, deserialize into V type
     #[instrument(skip(self, r))]
     fn get_persisted<'r, R: Readable>(
@@ -205,6 +232,31 @@ where
         self.scratch.clear();
         Ok(self.db.clear(writer)?)
     }
+
+    /// Record the writes a flush would make, for a store of the shared
+    /// DHT environment, instead of making them
+    pub fn journal_to(&self, db: DbName, writes: &mut SharedDhtWrites) -> DatabaseResult<()> {
+        for (k, ValuesDelta { delete_all, deltas }) in self.scratch.iter() {
+            let key = k.as_ref().to_vec();
+            if *delete_all {
+                writes.push(db.clone(), StoreWrite::DeleteAll(key.clone()));
+            }
+            for (v, op) in deltas {
+                let value = holochain_serialized_bytes::encode(&v)?;
+                match op {
+                    KvvOp::Insert => {
+                        writes.push(db.clone(), StoreWrite::Insert(key.clone(), value))
+                    }
+                    // Everything persisted is already deleted
+                    KvvOp::Delete if *delete_all => {}
+                    KvvOp::Delete => {
+                        writes.push(db.clone(), StoreWrite::DeleteValue(key.clone(), value))
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 impl<K, V> BufferedStore for KvvBufUsed<K, V>
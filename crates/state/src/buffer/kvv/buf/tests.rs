@@ -327,6 +327,32 @@ async fn idempotent_inserts() {
     .unwrap();
 }
 
+#[tokio::test(threaded_scheduler)]
+async fn kvv_scratch_size() -> DatabaseResult<()> {
+    let test_env = test_cell_env();
+    let arc = test_env.env();
+    let env = arc.guard();
+    let db = env.inner().open_multi("kvv", StoreOptions::create())?;
+    let mut buf = Store::new(db);
+    let size = holochain_serialized_bytes::encode(&V(1))?.len();
+    assert_eq!(buf.scratch_size(), 0);
+
+    buf.insert("a".into(), V(1));
+    buf.insert("a".into(), V(2));
+    buf.insert("b".into(), V(1));
+    assert_eq!(buf.scratch_size(), 3 * size);
+    // Inserting a value again doesn't count it twice
+    buf.insert("a".into(), V(1));
+    assert_eq!(buf.scratch_size(), 3 * size);
+    // Deleting an inserted value frees it, deleting anything else costs nothing
+    buf.delete("a".into(), V(1));
+    buf.delete("a".into(), V(3));
+    assert_eq!(buf.scratch_size(), 2 * size);
+    buf.delete_all("a".into());
+    assert_eq!(buf.scratch_size(), size);
+    Ok(())
+}
+
 #[tokio::test(threaded_scheduler)]
 async fn kvv_indicate_value_appends() -> DatabaseResult<()> {
     holochain_types::observability::test_run().ok();
@@ -12,12 +12,13 @@ use holochain_types::universal_map::{Key as UmKey, UniversalMap};
 use lazy_static::lazy_static;
 use parking_lot::RwLock;
 use rkv::{MultiStore, Rkv, SingleStore, StoreOptions};
+use serde::{Deserialize, Serialize};
 use std::collections::{hash_map, HashMap};
 use std::path::{Path, PathBuf};
 
 /// TODO This is incomplete
 /// Enumeration of all databases needed by Holochain
-#[derive(Clone, Debug, Hash, PartialEq, Eq, Display)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Display, Serialize, Deserialize)]
 pub enum DbName {
     /// Vault database: KV store of chain entries, keyed by address
     ElementVaultPublicEntries,
@@ -34,6 +35,21 @@ pub enum DbName {
     /// int KV store storing the sequence of committed headers,
     /// most notably allowing access to the chain head
     ChainSequence,
+    /// int KV store of the indices in the ChainSequence
+    /// whose headers haven't had their DhtOps produced yet
+    ChainSequenceIncompleteDhtOps,
+    /// Single store marking that the ChainSequenceIncompleteDhtOps index
+    /// has been built for any headers put before the index existed
+    ChainSequenceIncompleteDhtOpsBackfill,
+    /// KVV store indexing the headers of the source chain by their [EntryType],
+    /// where the key is the serialized EntryType and the values are [HeaderHash]es
+    ChainEntryTypes,
+    /// Single store marking that the ChainEntryTypes index
+    /// has been built for any headers put before the index existed
+    ChainEntryTypesBackfill,
+    /// KV store indexing the headers of the source chain by app-defined tags,
+    /// where the key is the tag followed by the [HeaderHash]
+    ChainTags,
     /// Cache database: KV store of chain entries, keyed by address
     ElementCacheEntries,
     /// Cache database: KV store of chain headers, keyed by address
@@ -47,8 +63,13 @@ pub enum DbName {
     /// database which stores a single key-value pair, encoding the
     /// mutable state for the entire Conductor
     ConductorState,
+    /// database which stores arbitrary metadata annotations on installed apps,
+    /// keyed by app id and metadata key
+    AppMetadata,
     /// database that stores wasm bytecode
     Wasm,
+    /// database that stores compiled wasm modules, keyed by [WasmHash]
+    WasmArtifact,
     /// database to store the [DnaDef]
     DnaDef,
     /// database to store the [EntryDef] Kvv store
@@ -57,14 +78,33 @@ pub enum DbName {
     AuthoredDhtOps,
     /// Integrated [DhtOp]s KV store
     IntegratedDhtOps,
+    /// Index of integrated [DhtOp]s by the location of their basis.
+    /// KV store where key is the location followed by the [DhtOpHash]
+    IntegratedDhtOpsLocations,
+    /// Single store marking that the [DhtOp] location index has been built
+    /// for any ops integrated before the index existed
+    IntegratedDhtOpsLocationsBackfill,
     /// Integration Queue of [DhtOp]s KV store where key is [DhtOpHash]
     IntegrationLimbo,
     /// Place for [DhtOp]s waiting to be validated to hang out. KV store where key is a [DhtOpHash]
     ValidationLimbo,
     /// KVV store to accumulate validation receipts for a published EntryHash
     ValidationReceipts,
+    /// KV store of the [DhtOp]s whose publisher asked for a validation receipt,
+    /// where the key is the [DhtOpHash] and the value is the publisher
+    PendingValidationReceipts,
     /// Single store for all known agents on the network
     Agent,
+    /// Single store of what this node has learned about each known agent,
+    /// keyed like [DbName::Agent]
+    PeerMeta,
+    /// Single store holding the report of the last integrity audit of a cell
+    IntegrityAudit,
+    /// Single store holding the journal of a cell's workflow errors
+    WorkflowErrors,
+    /// int KV store of the writes a cell has committed for the DHT environment
+    /// it shares with the other cells of its DNA, which are yet to be applied there
+    SharedDhtJournal,
 }
 
 impl DbName {
@@ -80,21 +120,35 @@ impl DbName {
             MetaVaultLinks => Single,
             MetaVaultMisc => Single,
             ChainSequence => SingleInt,
+            ChainSequenceIncompleteDhtOps => SingleInt,
+            ChainSequenceIncompleteDhtOpsBackfill => Single,
+            ChainEntryTypes => Multi,
+            ChainEntryTypesBackfill => Single,
+            ChainTags => Single,
             ElementCacheEntries => Single,
             ElementCacheHeaders => Single,
             MetaCacheSys => Multi,
             MetaCacheLinks => Single,
             MetaCacheStatus => Single,
             ConductorState => Single,
+            AppMetadata => Single,
             Wasm => Single,
+            WasmArtifact => Single,
             DnaDef => Single,
             EntryDef => Single,
             AuthoredDhtOps => Single,
             IntegratedDhtOps => Single,
+            IntegratedDhtOpsLocations => Single,
+            IntegratedDhtOpsLocationsBackfill => Single,
             IntegrationLimbo => Single,
             ValidationLimbo => Single,
             ValidationReceipts => Multi,
+            PendingValidationReceipts => Single,
             Agent => Single,
+            PeerMeta => Single,
+            IntegrityAudit => Single,
+            WorkflowErrors => Single,
+            SharedDhtJournal => SingleInt,
         }
     }
 }
@@ -135,6 +189,19 @@ lazy_static! {
     pub static ref META_VAULT_MISC: DbKey<SingleStore> = DbKey::new(DbName::MetaVaultMisc);
     /// The key to access the ChainSequence database
     pub static ref CHAIN_SEQUENCE: DbKey<IntegerStore> = DbKey::new(DbName::ChainSequence);
+    /// The key to access the ChainSequenceIncompleteDhtOps database
+    pub static ref CHAIN_SEQUENCE_INCOMPLETE_DHT_OPS: DbKey<IntegerStore> =
+    DbKey::new(DbName::ChainSequenceIncompleteDhtOps);
+    /// The key to access the ChainSequenceIncompleteDhtOpsBackfill database
+    pub static ref CHAIN_SEQUENCE_INCOMPLETE_DHT_OPS_BACKFILL: DbKey<SingleStore> =
+    DbKey::new(DbName::ChainSequenceIncompleteDhtOpsBackfill);
+    /// The key to access the ChainEntryTypes database
+    pub static ref CHAIN_ENTRY_TYPES: DbKey<MultiStore> = DbKey::new(DbName::ChainEntryTypes);
+    /// The key to access the ChainEntryTypesBackfill database
+    pub static ref CHAIN_ENTRY_TYPES_BACKFILL: DbKey<SingleStore> =
+    DbKey::new(DbName::ChainEntryTypesBackfill);
+    /// The key to access the ChainTags database
+    pub static ref CHAIN_TAGS: DbKey<SingleStore> = DbKey::new(DbName::ChainTags);
     /// The key to access the ChainEntries database
     pub static ref ELEMENT_CACHE_ENTRIES: DbKey<SingleStore> =
     DbKey::<SingleStore>::new(DbName::ElementCacheEntries);
@@ -149,8 +216,12 @@ lazy_static! {
     pub static ref CACHE_STATUS_META: DbKey<SingleStore> = DbKey::new(DbName::MetaCacheStatus);
     /// The key to access the ConductorState database
     pub static ref CONDUCTOR_STATE: DbKey<SingleStore> = DbKey::new(DbName::ConductorState);
+    /// The key to access the AppMetadata database
+    pub static ref APP_METADATA: DbKey<SingleStore> = DbKey::new(DbName::AppMetadata);
     /// The key to access the Wasm database
     pub static ref WASM: DbKey<SingleStore> = DbKey::new(DbName::Wasm);
+    /// The key to access the WasmArtifact database
+    pub static ref WASM_ARTIFACT: DbKey<SingleStore> = DbKey::new(DbName::WasmArtifact);
     /// The key to access the DnaDef database
     pub static ref DNA_DEF: DbKey<SingleStore> = DbKey::new(DbName::DnaDef);
     /// The key to access the EntryDef database
@@ -159,14 +230,32 @@ lazy_static! {
     pub static ref AUTHORED_DHT_OPS: DbKey<SingleStore> = DbKey::new(DbName::AuthoredDhtOps);
     /// The key to access the IntegratedDhtOps database
     pub static ref INTEGRATED_DHT_OPS: DbKey<SingleStore> = DbKey::new(DbName::IntegratedDhtOps);
+    /// The key to access the IntegratedDhtOpsLocations database
+    pub static ref INTEGRATED_DHT_OPS_LOCATIONS: DbKey<SingleStore> =
+    DbKey::new(DbName::IntegratedDhtOpsLocations);
+    /// The key to access the IntegratedDhtOpsLocationsBackfill database
+    pub static ref INTEGRATED_DHT_OPS_LOCATIONS_BACKFILL: DbKey<SingleStore> =
+    DbKey::new(DbName::IntegratedDhtOpsLocationsBackfill);
     /// The key to access the IntegrationLimbo database
     pub static ref INTEGRATION_LIMBO: DbKey<SingleStore> = DbKey::new(DbName::IntegrationLimbo);
     /// The key to access the IntegrationLimbo database
     pub static ref VALIDATION_LIMBO: DbKey<SingleStore> = DbKey::new(DbName::ValidationLimbo);
     /// The key to access the ValidationReceipts database
     pub static ref VALIDATION_RECEIPTS: DbKey<MultiStore> = DbKey::new(DbName::ValidationReceipts);
+    /// The key to access the PendingValidationReceipts database
+    pub static ref PENDING_VALIDATION_RECEIPTS: DbKey<SingleStore> =
+    DbKey::new(DbName::PendingValidationReceipts);
     /// The key to access the Agent database
     pub static ref AGENT: DbKey<SingleStore> = DbKey::new(DbName::Agent);
+    /// The key to access the PeerMeta database
+    pub static ref PEER_META: DbKey<SingleStore> = DbKey::new(DbName::PeerMeta);
+    /// The key to access the IntegrityAudit database
+    pub static ref INTEGRITY_AUDIT: DbKey<SingleStore> = DbKey::new(DbName::IntegrityAudit);
+    /// The key to access the WorkflowErrors database
+    pub static ref WORKFLOW_ERRORS: DbKey<SingleStore> = DbKey::new(DbName::WorkflowErrors);
+    /// The key to access the SharedDhtJournal database
+    pub static ref SHARED_DHT_JOURNAL: DbKey<IntegerStore> =
+    DbKey::new(DbName::SharedDhtJournal);
 }
 
 lazy_static! {
@@ -215,6 +304,11 @@ fn register_databases(env: &Rkv, kind: &EnvironmentKind, um: &mut DbMap) -> Data
             register_db(env, um, &*META_VAULT_LINKS)?;
             register_db(env, um, &*META_VAULT_MISC)?;
             register_db(env, um, &*CHAIN_SEQUENCE)?;
+            register_db(env, um, &*CHAIN_SEQUENCE_INCOMPLETE_DHT_OPS)?;
+            register_db(env, um, &*CHAIN_SEQUENCE_INCOMPLETE_DHT_OPS_BACKFILL)?;
+            register_db(env, um, &*CHAIN_ENTRY_TYPES)?;
+            register_db(env, um, &*CHAIN_ENTRY_TYPES_BACKFILL)?;
+            register_db(env, um, &*CHAIN_TAGS)?;
             register_db(env, um, &*ELEMENT_CACHE_ENTRIES)?;
             register_db(env, um, &*ELEMENT_CACHE_HEADERS)?;
             register_db(env, um, &*CACHE_SYSTEM_META)?;
@@ -222,20 +316,41 @@ fn register_databases(env: &Rkv, kind: &EnvironmentKind, um: &mut DbMap) -> Data
             register_db(env, um, &*CACHE_STATUS_META)?;
             register_db(env, um, &*AUTHORED_DHT_OPS)?;
             register_db(env, um, &*INTEGRATED_DHT_OPS)?;
+            register_db(env, um, &*INTEGRATED_DHT_OPS_LOCATIONS)?;
+            register_db(env, um, &*INTEGRATED_DHT_OPS_LOCATIONS_BACKFILL)?;
             register_db(env, um, &*INTEGRATION_LIMBO)?;
             register_db(env, um, &*VALIDATION_LIMBO)?;
             register_db(env, um, &*VALIDATION_RECEIPTS)?;
+            register_db(env, um, &*PENDING_VALIDATION_RECEIPTS)?;
+            register_db(env, um, &*INTEGRITY_AUDIT)?;
+            register_db(env, um, &*WORKFLOW_ERRORS)?;
+            register_db(env, um, &*SHARED_DHT_JOURNAL)?;
+        }
+        EnvironmentKind::Dht(_) => {
+            register_db(env, um, &*ELEMENT_VAULT_PUBLIC_ENTRIES)?;
+            register_db(env, um, &*ELEMENT_VAULT_HEADERS)?;
+            register_db(env, um, &*META_VAULT_SYS)?;
+            register_db(env, um, &*META_VAULT_LINKS)?;
+            register_db(env, um, &*META_VAULT_MISC)?;
+            register_db(env, um, &*ELEMENT_CACHE_ENTRIES)?;
+            register_db(env, um, &*ELEMENT_CACHE_HEADERS)?;
+            register_db(env, um, &*CACHE_SYSTEM_META)?;
+            register_db(env, um, &*CACHE_LINKS_META)?;
+            register_db(env, um, &*CACHE_STATUS_META)?;
         }
         EnvironmentKind::Conductor => {
             register_db(env, um, &*CONDUCTOR_STATE)?;
+            register_db(env, um, &*APP_METADATA)?;
         }
         EnvironmentKind::Wasm => {
             register_db(env, um, &*WASM)?;
+            register_db(env, um, &*WASM_ARTIFACT)?;
             register_db(env, um, &*DNA_DEF)?;
             register_db(env, um, &*ENTRY_DEF)?;
         }
         EnvironmentKind::P2P => {
             register_db(env, um, &*AGENT)?;
+            register_db(env, um, &*PEER_META)?;
             // @todo health metrics for the space
             // register_db(env, um, &*HEALTH)?;
         }
@@ -3,9 +3,11 @@
 use crate::{
     db::{get_db, initialize_databases, DbKey, GetDb},
     error::{DatabaseError, DatabaseResult},
+    shared_dht,
     transaction::{Reader, Writer},
 };
 use derive_more::Into;
+use holo_hash::DnaHash;
 use holochain_keystore::KeystoreSender;
 use holochain_types::cell::CellId;
 use lazy_static::lazy_static;
@@ -54,6 +56,51 @@ fn default_flags() -> EnvironmentFlags {
     EnvironmentFlags::WRITE_MAP | EnvironmentFlags::MAP_ASYNC
 }
 
+/// How data waits to reach the disk, either for a whole environment, as
+/// chosen when it is opened, or for a single run of commits.
+///
+/// By default environments are opened with asynchronous write-back of the
+/// memory map, see [default_flags], so a commit alone doesn't guarantee the
+/// data is on disk. Commits don't wait unless they ask to with
+/// [Durability::Sync], as zome calls do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Durability {
+    /// Flush data and metadata to disk on every commit
+    Sync,
+    /// Leave flushing to the environment's asynchronous write-back.
+    /// This is faster for bulk writes, which should finish with
+    /// an explicit [EnvironmentWrite::sync].
+    NoSync,
+    /// Don't flush on commit. The environment must be synced every
+    /// `interval_ms` instead, and a crash loses the commits since the last sync.
+    NoSyncPeriodic {
+        /// How often, in milliseconds, to sync the environment
+        interval_ms: u64,
+    },
+    /// Flush data but not metadata on every commit. A crash can lose the
+    /// last commit but leaves the environment consistent.
+    NoMetaSync,
+}
+
+impl Durability {
+    fn flags(&self) -> EnvironmentFlags {
+        match self {
+            Durability::Sync => EnvironmentFlags::WRITE_MAP,
+            Durability::NoSync => default_flags(),
+            Durability::NoSyncPeriodic { .. } => {
+                EnvironmentFlags::WRITE_MAP | EnvironmentFlags::NO_SYNC
+            }
+            Durability::NoMetaSync => EnvironmentFlags::WRITE_MAP | EnvironmentFlags::NO_META_SYNC,
+        }
+    }
+}
+
+impl Default for Durability {
+    fn default() -> Self {
+        Durability::NoSync
+    }
+}
+
 #[cfg(feature = "lmdb_no_tls")]
 fn required_flags() -> EnvironmentFlags {
     // NO_TLS associates read slots with the transaction object instead of the thread, which is crucial for us
@@ -90,6 +137,8 @@ pub struct EnvironmentRead {
     kind: EnvironmentKind,
     path: PathBuf,
     keystore: KeystoreSender,
+    durability: Option<Durability>,
+    dht: Option<Box<EnvironmentWrite>>,
 }
 
 impl EnvironmentRead {
@@ -117,6 +166,18 @@ impl EnvironmentRead {
     pub fn path(&self) -> &PathBuf {
         &self.path
     }
+
+    /// The [Durability] this environment was opened with, if any
+    pub fn durability(&self) -> Option<Durability> {
+        self.durability
+    }
+
+    /// The DHT environment shared by all Cells of this Cell's DNA, if the
+    /// conductor runs in shared DHT mode.
+    /// The integrated and cached data of the Cell live there instead of here.
+    pub fn dht_env(&self) -> Option<&EnvironmentWrite> {
+        self.dht.as_deref()
+    }
 }
 
 impl GetDb for EnvironmentWrite {
@@ -151,6 +212,17 @@ impl EnvironmentWrite {
         path_prefix: &Path,
         kind: EnvironmentKind,
         keystore: KeystoreSender,
+    ) -> DatabaseResult<EnvironmentWrite> {
+        Self::new_with_durability(path_prefix, kind, keystore, None)
+    }
+
+    /// Create an environment which writes with the given [Durability].
+    /// The mode is only applied when the environment is first opened.
+    pub fn new_with_durability(
+        path_prefix: &Path,
+        kind: EnvironmentKind,
+        keystore: KeystoreSender,
+        durability: Option<Durability>,
     ) -> DatabaseResult<EnvironmentWrite> {
         let mut map = ENVIRONMENTS.write();
         let path = path_prefix.join(kind.path());
@@ -162,7 +234,7 @@ impl EnvironmentWrite {
             hash_map::Entry::Occupied(e) => e.get().clone(),
             hash_map::Entry::Vacant(e) => e
                 .insert({
-                    let rkv = rkv_builder(None, None)(&path)?;
+                    let rkv = rkv_builder(None, durability.map(|d| d.flags()))(&path)?;
                     tracing::debug!("Initializing databases for path {:?}", path);
                     initialize_databases(&rkv, &kind)?;
                     EnvironmentWrite(EnvironmentRead {
@@ -170,6 +242,8 @@ impl EnvironmentWrite {
                         kind,
                         keystore,
                         path,
+                        durability,
+                        dht: None,
                     })
                 })
                 .clone(),
@@ -186,10 +260,43 @@ impl EnvironmentWrite {
         Self::new(path_prefix, EnvironmentKind::Cell(cell_id), keystore)
     }
 
+    /// Create the DHT environment shared by all Cells of a DNA (slight shorthand)
+    pub fn new_dht(
+        path_prefix: &Path,
+        dna_hash: DnaHash,
+        keystore: KeystoreSender,
+    ) -> DatabaseResult<Self> {
+        Self::new(path_prefix, EnvironmentKind::Dht(dna_hash), keystore)
+    }
+
+    /// Attach the DHT environment shared by all Cells of this Cell's DNA.
+    /// The returned handle routes the integrated and cached stores there.
+    pub fn with_dht_env(mut self, dht: EnvironmentWrite) -> Self {
+        self.0.dht = Some(Box::new(dht));
+        self
+    }
+
     /// Get a read-only lock guard on the environment.
     /// This reference can create read-write transactions.
     pub fn guard(&self) -> EnvironmentWriteRef<'_> {
-        EnvironmentWriteRef(self.0.guard())
+        EnvironmentWriteRef(self.0.guard(), self)
+    }
+
+    /// Apply the writes this Cell journaled for its shared DHT environment
+    /// and which weren't applied there before the conductor stopped.
+    /// Every commit applies the journal, so this only has work to do
+    /// when a Cell starts.
+    pub fn apply_shared_dht_journal(&self) -> DatabaseResult<()> {
+        match self.dht_env() {
+            Some(dht_env) => shared_dht::apply_journal(self, &self.guard(), dht_env),
+            None => Ok(()),
+        }
+    }
+
+    /// Flush everything committed to this environment to disk.
+    /// Use this to finish a run of commits made with [Durability::NoSync].
+    pub fn sync(&self) -> DatabaseResult<()> {
+        self.guard().sync()
     }
 
     /// Remove the db and directory
@@ -208,6 +315,9 @@ impl EnvironmentWrite {
 pub enum EnvironmentKind {
     /// Specifies the environment used by each Cell
     Cell(CellId),
+    /// Specifies the integrated and cached DHT data shared by all Cells
+    /// of a DNA, when the conductor runs in shared DHT mode
+    Dht(DnaHash),
     /// Specifies the environment used by a Conductor
     Conductor,
     /// Specifies the environment used to save wasm
@@ -221,6 +331,7 @@ impl EnvironmentKind {
     fn path(&self) -> PathBuf {
         match self {
             EnvironmentKind::Cell(cell_id) => PathBuf::from(cell_id.to_string()),
+            EnvironmentKind::Dht(dna_hash) => PathBuf::from(format!("dht-{}", dna_hash)),
             EnvironmentKind::Conductor => PathBuf::from("conductor"),
             EnvironmentKind::Wasm => PathBuf::from("wasm"),
             EnvironmentKind::P2P => PathBuf::from("p2p"),
@@ -239,6 +350,11 @@ impl<'e> EnvironmentReadRef<'e> {
     pub fn rkv(&self) -> &Rkv {
         &self.rkv
     }
+
+    /// Flush everything committed to this environment to disk
+    pub fn sync(&self) -> DatabaseResult<()> {
+        Ok(self.rkv.sync(true)?)
+    }
 }
 
 /// Implementors are able to create a new read-only LMDB transaction
@@ -289,6 +405,10 @@ impl<'e> WriteManager<'e> for EnvironmentWriteRef<'e> {
         let mut writer = Writer::from(self.rkv.write().map_err(Into::into)?);
         let result = f(&mut writer)?;
         writer.commit().map_err(Into::into)?;
+        // Writes to a shared DHT environment were journaled by this commit
+        if let Some(dht_env) = self.1.dht_env() {
+            shared_dht::apply_journal(self.1, self, dht_env)?;
+        }
         Ok(result)
     }
 }
@@ -310,8 +430,11 @@ impl<'e> EnvironmentWriteRef<'e> {
 }
 
 /// A reference to a EnvironmentWrite
-#[derive(Shrinkwrap, Into)]
-pub struct EnvironmentWriteRef<'e>(EnvironmentReadRef<'e>);
+#[derive(Shrinkwrap)]
+pub struct EnvironmentWriteRef<'e>(
+    #[shrinkwrap(main_field)] EnvironmentReadRef<'e>,
+    &'e EnvironmentWrite,
+);
 
 impl<'e> ReadManager<'e> for EnvironmentWriteRef<'e> {
     fn reader(&'e self) -> DatabaseResult<Reader<'e>> {
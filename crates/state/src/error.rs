@@ -81,6 +81,22 @@ pub enum DatabaseError {
     InvalidKeyRange,
 }
 
+impl DatabaseError {
+    /// Whether LMDB refused a write because the map is full or was resized
+    /// by another process. This can happen while an environment is growing,
+    /// so the write may succeed if it's retried.
+    pub fn is_map_full(&self) -> bool {
+        match self {
+            DatabaseError::LmdbStoreError(e) => matches!(
+                e.get_ref(),
+                rkv::StoreError::LmdbError(rkv::LmdbError::MapFull)
+                    | rkv::StoreError::LmdbError(rkv::LmdbError::MapResized)
+            ),
+            _ => false,
+        }
+    }
+}
+
 impl PartialEq for DatabaseError {
     fn eq(&self, other: &Self) -> bool {
         self.to_string() == other.to_string()
@@ -9,6 +9,8 @@ const PENDING_PREFIX: u8 = 0x1;
 const REJECTED_PREFIX: u8 = 0x2;
 /// Prefix for authored database
 const AUTHORED_PREFIX: u8 = 0x3;
+/// Prefix for data authored on the chain of a Dna the agent migrated from
+const MIGRATED_PREFIX: u8 = 0x4;
 
 /// Prefix length 1 + hash length 36
 // TODO: B-02112 change to 39 bytes
@@ -59,6 +61,10 @@ pub struct RejectedPrefix;
 /// Prefix key for data that has been authored
 pub struct AuthoredPrefix;
 
+#[derive(PartialOrd, Clone, Ord, PartialEq, Eq, Debug)]
+/// Prefix key for data authored on the chain of a Dna the agent migrated from
+pub struct MigratedPrefix;
+
 impl PrefixType for IntegratedPrefix {
     const PREFIX: u8 = INTEGRATED_PREFIX;
 }
@@ -75,6 +81,10 @@ impl PrefixType for AuthoredPrefix {
     const PREFIX: u8 = AUTHORED_PREFIX;
 }
 
+impl PrefixType for MigratedPrefix {
+    const PREFIX: u8 = MIGRATED_PREFIX;
+}
+
 impl<P: PrefixType> PrefixHashKey<P> {
     /// Create prefix key from a hash
     pub fn new<C>(hash: &HoloHash<C>) -> Self
@@ -86,6 +96,13 @@ impl<P: PrefixType> PrefixHashKey<P> {
         key
     }
 
+    /// The smallest key with this prefix, for iterating from the start of the prefix
+    pub(crate) fn first() -> Self {
+        let mut key = Self::empty();
+        key.prefix_and_hash[0] = P::PREFIX;
+        key
+    }
+
     fn empty() -> Self {
         Self {
             prefix_and_hash: [0; PREFIX_KEY_SIZE],
@@ -59,6 +59,7 @@ pub mod exports;
 pub mod fatal;
 pub mod key;
 pub mod prelude;
+pub mod shared_dht;
 pub mod transaction;
 
 // NB: would be nice to put this under cfg(test), but then it's not visible from other crates,
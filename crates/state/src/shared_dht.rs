@@ -0,0 +1,164 @@
+//! The journal of writes a Cell makes to the DHT environment it shares with
+//! the other Cells of its DNA.
+//!
+//! Two LMDB environments can't be written in one transaction, so the stores
+//! of a Cell which live in the shared environment aren't written to it when
+//! the Cell flushes. Their writes are journaled in the Cell's own environment
+//! instead, in the same transaction as the rest of the flush, and applied to
+//! the shared environment once that transaction has committed.
+//!
+//! Applying a journaled write a second time leaves the store as the first
+//! time did, so a journal left behind by a crash is replayed when the Cell
+//! starts again.
+
+use crate::{
+    buffer::{KvIntStore, KvStoreT},
+    db::{
+        DbName, GetDb, CACHE_LINKS_META, CACHE_STATUS_META, CACHE_SYSTEM_META,
+        ELEMENT_CACHE_ENTRIES, ELEMENT_CACHE_HEADERS, ELEMENT_VAULT_HEADERS,
+        ELEMENT_VAULT_PUBLIC_ENTRIES, META_VAULT_LINKS, META_VAULT_MISC, META_VAULT_SYS,
+        SHARED_DHT_JOURNAL,
+    },
+    env::{EnvironmentRead, EnvironmentWrite, EnvironmentWriteRef, WriteManager},
+    error::{DatabaseError, DatabaseResult},
+    prelude::*,
+};
+use rkv::{MultiStore, SingleStore};
+use serde::{Deserialize, Serialize};
+
+/// One write to a store of the shared DHT environment,
+/// with the key and value encoded as they are stored
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StoreWrite {
+    /// Put the value at a key of a single store
+    Put(Vec<u8>, Vec<u8>),
+    /// Delete the value at a key of a single store
+    Delete(Vec<u8>),
+    /// Insert a value at a key of a multi store
+    Insert(Vec<u8>, Vec<u8>),
+    /// Delete one value at a key of a multi store
+    DeleteValue(Vec<u8>, Vec<u8>),
+    /// Delete every value at a key of a multi store
+    DeleteAll(Vec<u8>),
+}
+
+/// The writes one flush makes to the stores of the shared DHT environment,
+/// in the order the stores would have made them
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SharedDhtWrites(Vec<(DbName, StoreWrite)>);
+
+impl SharedDhtWrites {
+    /// Record a write to one of the shared stores
+    pub fn push(&mut self, db: DbName, write: StoreWrite) {
+        self.0.push((db, write));
+    }
+
+    /// Journal the writes in the Cell's environment with the Cell's writer.
+    /// They are applied to the shared environment once the writer commits.
+    pub fn journal(self, env: &EnvironmentRead, writer: &mut Writer) -> DatabaseResult<()> {
+        if self.0.is_empty() {
+            return Ok(());
+        }
+        let journal: KvIntStore<SharedDhtWrites> =
+            KvIntStore::new(env.get_db(&*SHARED_DHT_JOURNAL)?);
+        // The journal is cleared whenever it is applied,
+        // so its length is the next free key
+        let next = journal.iter(&*writer)?.count()? as u32;
+        journal.put(writer, &next.into(), &self)
+    }
+
+    /// Make the writes with a writer of the shared environment
+    fn apply(self, dht_env: &EnvironmentWrite, writer: &mut Writer) -> DatabaseResult<()> {
+        for (db, write) in self.0 {
+            match write {
+                StoreWrite::Put(k, v) => {
+                    single_store(dht_env, &db)?.put(writer, k, &rkv::Value::Blob(&v))?
+                }
+                StoreWrite::Delete(k) => match single_store(dht_env, &db)?.delete(writer, k) {
+                    Err(rkv::StoreError::LmdbError(rkv::LmdbError::NotFound)) => (),
+                    r => r?,
+                },
+                StoreWrite::Insert(k, v) => match multi_store(dht_env, &db)?.put_with_flags(
+                    writer,
+                    k,
+                    &rkv::Value::Blob(&v),
+                    rkv::WriteFlags::NO_DUP_DATA,
+                ) {
+                    // The value is already there
+                    Err(rkv::StoreError::LmdbError(rkv::LmdbError::KeyExist)) => (),
+                    r => r?,
+                },
+                StoreWrite::DeleteValue(k, v) => {
+                    match multi_store(dht_env, &db)?.delete(writer, k, &rkv::Value::Blob(&v)) {
+                        Err(rkv::StoreError::LmdbError(rkv::LmdbError::NotFound)) => (),
+                        r => r?,
+                    }
+                }
+                StoreWrite::DeleteAll(k) => {
+                    match multi_store(dht_env, &db)?.delete_all(writer, k) {
+                        Err(rkv::StoreError::LmdbError(rkv::LmdbError::NotFound)) => (),
+                        r => r?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Apply a Cell's journal of writes to the shared DHT environment and clear it.
+///
+/// The Cell's environment stays locked for writing until the journal is
+/// cleared, so the journal is never applied twice over the writes of
+/// another Cell sharing the environment, except after a crash.
+pub(crate) fn apply_journal(
+    env: &EnvironmentWrite,
+    env_ref: &EnvironmentWriteRef,
+    dht_env: &EnvironmentWrite,
+) -> DatabaseResult<()> {
+    let mut journal: KvIntStore<SharedDhtWrites> =
+        KvIntStore::new(env.get_db(&*SHARED_DHT_JOURNAL)?);
+    let mut writer = env_ref.writer_unmanaged()?;
+    let entries: Vec<SharedDhtWrites> = journal.iter(&writer)?.map(|(_, w)| Ok(w)).collect()?;
+    if entries.is_empty() {
+        return Ok(());
+    }
+    dht_env.guard().with_commit(|dht_writer| {
+        for writes in entries {
+            writes.apply(dht_env, dht_writer)?;
+        }
+        Ok::<_, DatabaseError>(())
+    })?;
+    journal.delete_all(&mut writer)?;
+    writer.commit()
+}
+
+/// The single store of the shared environment a journaled write is for
+fn single_store(dht_env: &EnvironmentWrite, db: &DbName) -> DatabaseResult<SingleStore> {
+    match db {
+        DbName::ElementVaultPublicEntries => dht_env.get_db(&*ELEMENT_VAULT_PUBLIC_ENTRIES),
+        DbName::ElementVaultHeaders => dht_env.get_db(&*ELEMENT_VAULT_HEADERS),
+        DbName::MetaVaultLinks => dht_env.get_db(&*META_VAULT_LINKS),
+        DbName::MetaVaultMisc => dht_env.get_db(&*META_VAULT_MISC),
+        DbName::ElementCacheEntries => dht_env.get_db(&*ELEMENT_CACHE_ENTRIES),
+        DbName::ElementCacheHeaders => dht_env.get_db(&*ELEMENT_CACHE_HEADERS),
+        DbName::MetaCacheLinks => dht_env.get_db(&*CACHE_LINKS_META),
+        DbName::MetaCacheStatus => dht_env.get_db(&*CACHE_STATUS_META),
+        _ => Err(DatabaseError::StoreNotInitialized(
+            db.clone(),
+            dht_env.path().clone(),
+        )),
+    }
+}
+
+/// The multi store of the shared environment a journaled write is for
+fn multi_store(dht_env: &EnvironmentWrite, db: &DbName) -> DatabaseResult<MultiStore> {
+    match db {
+        DbName::MetaVaultSys => dht_env.get_db(&*META_VAULT_SYS),
+        DbName::MetaCacheSys => dht_env.get_db(&*CACHE_SYSTEM_META),
+        _ => Err(DatabaseError::StoreNotInitialized(
+            db.clone(),
+            dht_env.path().clone(),
+        )),
+    }
+}
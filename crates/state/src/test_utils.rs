@@ -4,7 +4,7 @@ use crate::{
     env::{EnvironmentKind, EnvironmentWrite},
     prelude::BufKey,
 };
-use holochain_types::test_utils::fake_cell_id;
+use holochain_types::test_utils::{fake_cell_id, fake_dna_hash};
 use shrinkwraprs::Shrinkwrap;
 use std::sync::Arc;
 use tempdir::TempDir;
@@ -15,6 +15,11 @@ pub fn test_cell_env() -> TestEnvironment {
     test_env(EnvironmentKind::Cell(cell_id))
 }
 
+/// Create a [TestEnvironment] of [EnvironmentKind::Dht], backed by a temp directory.
+pub fn test_dht_env() -> TestEnvironment {
+    test_env(EnvironmentKind::Dht(fake_dna_hash(1)))
+}
+
 /// Create a [TestEnvironment] of [EnvironmentKind::Conductor], backed by a temp directory.
 pub fn test_conductor_env() -> TestEnvironment {
     test_env(EnvironmentKind::Conductor)
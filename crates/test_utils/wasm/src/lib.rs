@@ -21,11 +21,14 @@ pub enum TestWasm {
     EmitSignal,
     HashEntry,
     Foo,
+    GenesisSelfCheck,
     HashPath,
     Imports,
+    Index,
     InitFail,
     InitPass,
     Link,
+    Metering,
     MigrateAgentFail,
     MigrateAgentPass,
     MultipleCalls,
@@ -41,6 +44,7 @@ pub enum TestWasm {
     ValidateLink,
     ValidateInvalid,
     ValidateCreateLinkInvalid,
+    ValidatePanic,
     ValidateValid,
     ValidateCreateLinkValid,
     ValidationPackageFail,
@@ -64,11 +68,14 @@ impl From<TestWasm> for ZomeName {
             TestWasm::EmitSignal => "emit_signal",
             TestWasm::HashEntry => "hash_entry",
             TestWasm::Foo => "foo",
+            TestWasm::GenesisSelfCheck => "genesis_self_check",
             TestWasm::HashPath => "hash_path",
             TestWasm::Imports => "imports",
+            TestWasm::Index => "index",
             TestWasm::InitFail => "init_fail",
             TestWasm::InitPass => "init_pass",
             TestWasm::Link => "link",
+            TestWasm::Metering => "metering",
             TestWasm::MigrateAgentFail => "migrate_agent_fail",
             TestWasm::MigrateAgentPass => "migrate_agent_pass",
             TestWasm::MultipleCalls => "multiple_calls",
@@ -84,6 +91,7 @@ impl From<TestWasm> for ZomeName {
             TestWasm::ValidateLink => "validate_link",
             TestWasm::ValidateInvalid => "validate_invalid",
             TestWasm::ValidateCreateLinkInvalid => "validate_link_add_invalid",
+            TestWasm::ValidatePanic => "validate_panic",
             TestWasm::ValidateValid => "validate_valid",
             TestWasm::ValidateCreateLinkValid => "validate_link_add_valid",
             TestWasm::ValidationPackageFail => "validation_package_fail",
@@ -121,10 +129,14 @@ impl From<TestWasm> for DnaWasm {
                 get_code("wasm32-unknown-unknown/release/test_wasm_hash_entry.wasm")
             }
             TestWasm::Foo => get_code("wasm32-unknown-unknown/release/test_wasm_foo.wasm"),
+            TestWasm::GenesisSelfCheck => {
+                get_code("wasm32-unknown-unknown/release/test_wasm_genesis_self_check.wasm")
+            }
             TestWasm::HashPath => {
                 get_code("wasm32-unknown-unknown/release/test_wasm_hash_path.wasm")
             }
             TestWasm::Imports => get_code("wasm32-unknown-unknown/release/test_wasm_imports.wasm"),
+            TestWasm::Index => get_code("wasm32-unknown-unknown/release/test_wasm_index.wasm"),
             TestWasm::InitFail => {
                 get_code("wasm32-unknown-unknown/release/test_wasm_init_fail.wasm")
             }
@@ -132,6 +144,9 @@ impl From<TestWasm> for DnaWasm {
                 get_code("wasm32-unknown-unknown/release/test_wasm_init_pass.wasm")
             }
             TestWasm::Link => get_code("wasm32-unknown-unknown/release/test_wasm_link.wasm"),
+            TestWasm::Metering => {
+                get_code("wasm32-unknown-unknown/release/test_wasm_metering.wasm")
+            }
             TestWasm::MigrateAgentFail => {
                 get_code("wasm32-unknown-unknown/release/test_wasm_migrate_agent_fail.wasm")
             }
@@ -171,6 +186,9 @@ impl From<TestWasm> for DnaWasm {
             TestWasm::ValidateCreateLinkInvalid => {
                 get_code("wasm32-unknown-unknown/release/test_wasm_validate_link_add_invalid.wasm")
             }
+            TestWasm::ValidatePanic => {
+                get_code("wasm32-unknown-unknown/release/test_wasm_validate_panic.wasm")
+            }
             TestWasm::ValidateValid => {
                 get_code("wasm32-unknown-unknown/release/test_wasm_validate_valid.wasm")
             }
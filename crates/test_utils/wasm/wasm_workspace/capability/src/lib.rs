@@ -67,6 +67,12 @@ fn accept_cap_claim(claim: CapClaim) -> ExternResult<HeaderHash> {
     Ok(create_cap_claim!(claim)?)
 }
 
+/// commits a cap claim through the host so call_remote can find it automatically
+#[hdk_extern]
+fn commit_claim(claim: CapClaim) -> ExternResult<HeaderHash> {
+    Ok(commit_cap_claim!(claim)?)
+}
+
 #[hdk_extern]
 fn needs_cap_claim(_: ()) -> ExternResult<()> {
     Ok(())
@@ -85,6 +91,20 @@ fn try_cap_claim(cap_for: CapFor) -> ExternResult<ZomeCallResponse> {
     Ok(result)
 }
 
+/// calls needs_cap_claim on the given agent with a secret from our committed claims
+#[hdk_extern]
+fn try_auto_cap_claim(agent: AgentPubKey) -> ExternResult<ZomeCallResponse> {
+    let result: ZomeCallResponse = call_remote!(
+        agent,
+        zome_info!()?.zome_name,
+        "needs_cap_claim".to_string().into(),
+        CallRemoteCap::Auto,
+        ().try_into()?
+    )?;
+
+    Ok(result)
+}
+
 #[hdk_extern]
 fn send_assigned_cap_claim(agent: AgentPubKey) -> ExternResult<()> {
     let tag = String::from("has_cap_claim");
@@ -98,7 +118,7 @@ fn send_assigned_cap_claim(agent: AgentPubKey) -> ExternResult<()> {
     functions.insert((this_zome.clone(), "needs_cap_claim".into()));
     create_cap_grant!(CapGrantEntry {
         access: (secret, agent.clone()).into(),
-        functions,
+        functions: functions.clone(),
         tag: tag.clone(),
     })?;
 
@@ -108,7 +128,7 @@ fn send_assigned_cap_claim(agent: AgentPubKey) -> ExternResult<()> {
         this_zome,
         "accept_cap_claim".into(),
         None,
-        CapClaim::new(tag, agent_info!()?.agent_latest_pubkey, secret,).try_into()?
+        CapClaim::new(tag, agent_info!()?.agent_latest_pubkey, secret, functions).try_into()?
     )?;
     Ok(())
 }
@@ -15,6 +15,13 @@ fn read(header_hash: HeaderHash) -> ExternResult<GetOutput> {
     Ok(GetOutput::new(get!(header_hash)?))
 }
 
+#[hdk_extern]
+fn read_all_live(_: ()) -> ExternResult<GetAllLiveHeadersOutput> {
+    Ok(GetAllLiveHeadersOutput::new(get_all_live_headers!(
+        hash_entry!(Thing)?
+    )?))
+}
+
 #[hdk_extern]
 fn delete(header_hash: HeaderHash) -> ExternResult<HeaderHash> {
     Ok(delete_entry!(header_hash)?)
@@ -41,6 +41,20 @@ fn create_entry(_: ()) -> ExternResult<HeaderHash> {
     Ok(create_entry!(post())?)
 }
 
+#[hdk_extern]
+fn get_chain_head(_: ()) -> ExternResult<ChainHeadOutput> {
+    Ok(ChainHeadOutput::new(chain_head!()?))
+}
+
+/// creates a post only if nothing has been committed since the zome read the given head
+#[hdk_extern]
+fn create_entry_guarded(expected_head: HeaderHash) -> ExternResult<CreateOutput> {
+    Ok(CreateOutput::new(create_entry!(
+        post(),
+        guard = CommitGuard::new(expected_head)
+    )?))
+}
+
 #[hdk_extern]
 fn get_entry(_: ()) -> ExternResult<GetOutput> {
     Ok(GetOutput::new(get!(hash_entry!(post())?)?))
@@ -0,0 +1,18 @@
+//! only lets agents in whose membrane proof is the password
+
+use hdk3::prelude::*;
+use test_wasm_common::TestString;
+
+const PASSWORD: &str = "let me in";
+
+#[hdk_extern]
+fn validate(data: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    let membrane_proof = match data.element.header() {
+        Header::AgentValidationPkg(pkg) => pkg.membrane_proof.clone(),
+        _ => return Ok(ValidateCallbackResult::Valid),
+    };
+    Ok(match membrane_proof.map(TestString::try_from) {
+        Some(Ok(TestString(proof))) if proof == PASSWORD => ValidateCallbackResult::Valid,
+        _ => ValidateCallbackResult::Invalid("wrong membrane proof".to_string()),
+    })
+}
@@ -20,8 +20,8 @@ fn ensure(path_string: TestString) -> ExternResult<()> {
 }
 
 #[hdk_extern]
-fn delete_link(delete_link: DeleteLinkInput) -> ExternResult<HeaderHash> {
-    Ok(delete_link!(delete_link.into_inner())?)
+fn delete_link(delete_link: HeaderHash) -> ExternResult<HeaderHash> {
+    Ok(delete_link!(delete_link)?)
 }
 
 #[hdk_extern]
@@ -0,0 +1,44 @@
+use hdk3::prelude::*;
+use test_wasm_common::*;
+
+#[hdk_entry(id = "post")]
+struct Post {
+    topic: String,
+    content: String,
+}
+
+entry_defs![Post::entry_def()];
+
+/// posts are indexed by their topic
+#[hdk_extern]
+fn index_hint_post(entry: Entry) -> ExternResult<IndexHintCallbackResult> {
+    Ok(match entry {
+        Entry::App(eb) => match Post::try_from(SerializedBytes::from(eb)) {
+            Ok(post) => {
+                IndexHintCallbackResult::Keys(vec![bytes::Bytes::from(post.topic.into_bytes())])
+            }
+            Err(e) => IndexHintCallbackResult::Fail(e.to_string()),
+        },
+        _ => IndexHintCallbackResult::Fail("Not a post".to_string()),
+    })
+}
+
+#[hdk_extern]
+fn create_post(post: Post) -> ExternResult<EntryHash> {
+    create_entry!(&post)?;
+    Ok(hash_entry!(&post)?)
+}
+
+#[hdk_extern]
+fn get_by_topic(topic: TestString) -> ExternResult<EntryHashes> {
+    Ok(EntryHashes(get_indexed!("post", topic.0.into_bytes())?))
+}
+
+/// delete the header that created a post, which takes it out of the index
+#[hdk_extern]
+fn delete_post(entry_hash: EntryHash) -> ExternResult<HeaderHash> {
+    match get!(entry_hash)? {
+        Some(element) => Ok(delete_entry!(element.header_address().clone())?),
+        None => Err(WasmError::Zome("post not found".to_string())),
+    }
+}
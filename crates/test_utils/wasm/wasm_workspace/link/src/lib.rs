@@ -22,8 +22,8 @@ fn create_link(_: ()) -> ExternResult<HeaderHash> {
 }
 
 #[hdk_extern]
-fn delete_link(input: DeleteLinkInput) -> ExternResult<HeaderHash> {
-    Ok(delete_link!(input.into_inner())?)
+fn delete_link(input: HeaderHash) -> ExternResult<HeaderHash> {
+    Ok(delete_link!(input)?)
 }
 
 #[hdk_extern]
@@ -0,0 +1,44 @@
+//! externs which use a known amount of wasm resources, to test metering
+
+use hdk3::prelude::*;
+use test_wasm_common::TestInt;
+
+#[hdk_entry(id = "val")]
+struct Val(u32);
+
+entry_defs![Val::entry_def()];
+
+/// as little work as an extern can do
+#[hdk_extern]
+fn trivial(_: ()) -> ExternResult<TestInt> {
+    Ok(TestInt(0))
+}
+
+/// loop n times over something the compiler can't fold away
+#[hdk_extern]
+fn spin(n: TestInt) -> ExternResult<TestInt> {
+    let mut x: u32 = n.0 | 1;
+    for _ in 0..n.0 {
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+    }
+    Ok(TestInt(x))
+}
+
+/// allocate and touch n MiB so the guest's memory has to grow
+#[hdk_extern]
+fn allocate(n: TestInt) -> ExternResult<TestInt> {
+    let mut bytes = vec![0_u8; n.0 as usize * 1024 * 1024];
+    for i in (0..bytes.len()).step_by(4096) {
+        bytes[i] = i as u8;
+    }
+    Ok(TestInt(bytes.iter().step_by(4096).map(|b| *b as u32).sum()))
+}
+
+/// commit an entry then loop n times, so a fuel limit stops the call after it committed
+#[hdk_extern]
+fn create_then_spin(n: TestInt) -> ExternResult<TestInt> {
+    create_entry!(Val(n.0))?;
+    spin(n)
+}
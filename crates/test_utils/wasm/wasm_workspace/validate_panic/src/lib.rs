@@ -0,0 +1,6 @@
+use hdk3::prelude::*;
+
+#[hdk_extern]
+fn validate(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    panic!("validate always panics")
+}
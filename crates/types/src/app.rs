@@ -2,7 +2,7 @@
 use crate::{cell::CellId, dna::JsonProperties};
 use derive_more::Into;
 use holo_hash::AgentPubKey;
-use holochain_serialized_bytes::SerializedBytes;
+use holochain_serialized_bytes::prelude::*;
 use std::path::PathBuf;
 
 /// Placeholder used to identify apps
@@ -20,6 +20,10 @@ pub struct InstallAppPayload {
     pub agent_key: AgentPubKey,
     /// The Dna paths in this app
     pub dnas: Vec<InstallAppDnaPayload>,
+    /// Whether the app's cells may belong to different agents.
+    /// Left out by clients which predate it, in which case they may not.
+    #[serde(default)]
+    pub allow_multiple_agents: bool,
 }
 
 /// Information needed to specify a Dna as part of an App
@@ -50,6 +54,17 @@ impl InstallAppDnaPayload {
 /// App-specific payload for proving membership in the membrane of the app
 pub type MembraneProof = SerializedBytes;
 
+/// A [MembraneProof] of work done for an agent to join an app, as spam
+/// prevention. The sha256 of the agent's public key followed by the nonce
+/// starts with `difficulty` zero bits.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, SerializedBytes)]
+pub struct ProofOfWork {
+    /// The nonce which was found
+    pub nonce: [u8; 32],
+    /// How many leading zero bits the hash has
+    pub difficulty: u8,
+}
+
 /// Data about an installed Cell
 #[derive(Clone, Debug, Into, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct InstalledCell(CellId, CellNick);
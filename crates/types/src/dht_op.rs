@@ -8,7 +8,12 @@ use crate::element::{Element, ElementGroup};
 use crate::{header::NewEntryHeader, prelude::*};
 use error::{DhtOpError, DhtOpResult};
 use holo_hash::{hash_type, HashableContentBytes};
-use holochain_zome_types::{header, Entry, Header};
+use holochain_zome_types::{
+    entry_def::{EntryDef, RequiredValidations},
+    header::{self, AppEntryType, EntryType},
+    validate::RequiredValidationType,
+    Entry, Header,
+};
 use serde::{Deserialize, Serialize};
 
 #[allow(missing_docs)]
@@ -80,6 +85,27 @@ pub enum DhtOp {
     RegisterRemoveLink(Signature, header::DeleteLink),
 }
 
+/// What the entry def of an app entry requires of the StoreElement and
+/// StoreEntry ops of that entry. The author publishes these alongside the
+/// ops so that authorities can check them against their own entry defs.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OpRequirements {
+    /// How many validation receipts the author collects before it
+    /// stops publishing the op
+    pub required_validations: RequiredValidations,
+    /// The validation package authorities request from the author
+    pub required_validation_type: RequiredValidationType,
+}
+
+impl From<&EntryDef> for OpRequirements {
+    fn from(entry_def: &EntryDef) -> Self {
+        Self {
+            required_validations: entry_def.required_validations,
+            required_validation_type: entry_def.required_validation_type,
+        }
+    }
+}
+
 /// Show that this type is used as the basis
 type DhtBasis = AnyDhtHash;
 
@@ -165,6 +191,20 @@ impl DhtOp {
         }
     }
 
+    /// The app entry type whose entry def's [OpRequirements] apply to this op.
+    /// Only StoreElement and StoreEntry ops of app entries have one.
+    pub fn requirements_entry_type(&self) -> Option<AppEntryType> {
+        match self {
+            DhtOp::StoreElement(_, _, _) | DhtOp::StoreEntry(_, _, _) => {
+                match self.header().entry_type() {
+                    Some(EntryType::App(app_entry_type)) => Some(app_entry_type.clone()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
     /// Get the signature for this op
     pub fn signature(&self) -> &Signature {
         match self {
@@ -61,6 +61,76 @@ impl DnaDef {
     }
 }
 
+/// The differences between two [DnaDef]s, which explain why their
+/// [DnaHash]es differ
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, SerializedBytes)]
+pub struct DnaDiff {
+    /// The names differ
+    pub name_changed: bool,
+    /// The UUIDs differ
+    pub uuid_changed: bool,
+    /// The properties differ
+    pub properties_changed: bool,
+    /// Zomes which are only in the second Dna
+    pub zomes_added: Vec<ZomeName>,
+    /// Zomes which are only in the first Dna
+    pub zomes_removed: Vec<ZomeName>,
+    /// Zomes which are in both Dnas but with different wasm
+    pub wasms_changed: Vec<ZomeName>,
+    /// The zomes which are in both Dnas are not in the same order
+    pub zome_order_changed: bool,
+}
+
+impl DnaDiff {
+    /// Compare two [DnaDef]s
+    pub fn new(a: &DnaDef, b: &DnaDef) -> Self {
+        let common_a: Vec<&ZomeName> = a
+            .zomes
+            .iter()
+            .map(|(name, _)| name)
+            .filter(|name| b.zomes.iter().any(|(n, _)| n == *name))
+            .collect();
+        let common_b: Vec<&ZomeName> = b
+            .zomes
+            .iter()
+            .map(|(name, _)| name)
+            .filter(|name| a.zomes.iter().any(|(n, _)| n == *name))
+            .collect();
+        Self {
+            name_changed: a.name != b.name,
+            uuid_changed: a.uuid != b.uuid,
+            properties_changed: a.properties != b.properties,
+            zomes_added: b
+                .zomes
+                .iter()
+                .filter(|(name, _)| a.get_zome(name).is_err())
+                .map(|(name, _)| name.clone())
+                .collect(),
+            zomes_removed: a
+                .zomes
+                .iter()
+                .filter(|(name, _)| b.get_zome(name).is_err())
+                .map(|(name, _)| name.clone())
+                .collect(),
+            wasms_changed: a
+                .zomes
+                .iter()
+                .filter(|(name, zome)| match b.get_zome(name) {
+                    Ok(other) => other.wasm_hash != zome.wasm_hash,
+                    Err(_) => false,
+                })
+                .map(|(name, _)| name.clone())
+                .collect(),
+            zome_order_changed: common_a != common_b,
+        }
+    }
+
+    /// True if there are no differences, i.e. the Dnas hash the same
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
 /// A DnaDef paired with its DnaHash
 pub type DnaDefHashed = HoloHashed<DnaDef>;
 
@@ -188,3 +258,39 @@ impl std::fmt::Debug for DnaFile {
         f.write_fmt(format_args!("DnaFile(dna_hash = {})", self.dna_hash))
     }
 }
+
+/// A DnaFile exported from a conductor, rendered as the content of a dna file,
+/// which [DnaFile::from_file_content] reads back in
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, SerializedBytes)]
+pub struct DnaFileBundle {
+    /// The hash of the exported Dna
+    pub dna_hash: holo_hash::DnaHash,
+    /// The content of the dna file, see [DnaFile::to_file_content]
+    #[serde(with = "serde_bytes")]
+    pub content: Vec<u8>,
+}
+
+impl DnaFileBundle {
+    /// Render a DnaFile as a bundle
+    pub async fn new(dna_file: &DnaFile) -> Result<Self, DnaError> {
+        Ok(Self {
+            dna_hash: dna_file.dna_hash().clone(),
+            content: dna_file.to_file_content().await?,
+        })
+    }
+
+    /// Read the DnaFile back out of the bundle
+    pub async fn dna_file(&self) -> Result<DnaFile, DnaError> {
+        DnaFile::from_file_content(&self.content).await
+    }
+}
+
+impl std::fmt::Debug for DnaFileBundle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "DnaFileBundle(dna_hash = {}, {} bytes)",
+            self.dna_hash,
+            self.content.len()
+        ))
+    }
+}
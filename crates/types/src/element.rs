@@ -113,6 +113,10 @@ pub enum GetElementResponse {
     /// Get a single element
     /// Can be combined with other metadata monotonically
     GetHeader(Option<Box<WireElement>>),
+    /// The responder's storage arc doesn't cover the requested hash,
+    /// so unlike an empty response, this says nothing about whether
+    /// the data exists or has been deleted
+    NotHeld,
 }
 
 /// This type gives full metadata that can be combined
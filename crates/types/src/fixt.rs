@@ -45,6 +45,7 @@ use holochain_zome_types::migrate_agent::MigrateAgent;
 use holochain_zome_types::signature::Signature;
 use holochain_zome_types::zome::FunctionName;
 use holochain_zome_types::zome::ZomeName;
+use holochain_zome_types::zome_info::ZomeInfo;
 use holochain_zome_types::Entry;
 use holochain_zome_types::{
     capability::CapAccess, element::Element, element::SignedHeaderHashed, header::HeaderHashed,
@@ -100,9 +101,14 @@ fixturator!(
     from u8;
 );
 
+fixturator!(
+    ZomeInfo;
+    constructor fn new(String, DnaHash, ZomeName, ZomeId, SerializedBytes);
+);
+
 fixturator!(
     CapClaim;
-    constructor fn new(String, AgentPubKey, CapSecret);
+    constructor fn new(String, AgentPubKey, CapSecret, GrantedFunctions);
 );
 
 fixturator!(
@@ -133,6 +139,28 @@ fixturator!(
     );
 );
 
+fixturator!(
+    GrantedFunctions;
+    curve Empty HashSet::new();
+    curve Unpredictable {
+        let mut rng = rand::thread_rng();
+        let number_of_functions = rng.gen_range(0, 5);
+
+        let mut granted_functions: GrantedFunctions = HashSet::new();
+        for _ in 0..number_of_functions {
+            granted_functions.insert(GrantedFunctionFixturator::new(Unpredictable).next().unwrap());
+        }
+        granted_functions
+    };
+    curve Predictable {
+        let mut granted_functions: GrantedFunctions = HashSet::new();
+        for _ in 0..self.0.index % 3 {
+            granted_functions.insert(GrantedFunctionFixturator::new(Predictable).next().unwrap());
+        }
+        granted_functions
+    };
+);
+
 fixturator!(
     CurryPayloads;
     curve Empty CurryPayloads(BTreeMap::new());
@@ -0,0 +1,95 @@
+//! Types for looking up entries by the keys their zome's `index_hint`
+//! callback gives them.
+
+use holo_hash::{
+    hash_type, AnyDhtHash, EntryHash, HashableContent, HashableContentBytes, HeaderHash,
+};
+use holochain_serialized_bytes::prelude::*;
+use holochain_zome_types::header::{AppEntryType, EntryDefIndex, ZomeId};
+
+/// A key that entries of one entry def are indexed under
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, SerializedBytes)]
+pub struct IndexKey {
+    /// The zome the entry def belongs to
+    pub zome_id: ZomeId,
+    /// The entry def, within that zome
+    pub entry_def_index: EntryDefIndex,
+    /// The key the index_hint callback gave
+    #[serde(with = "serde_bytes")]
+    pub key: Vec<u8>,
+}
+
+/// A change to an index, sent by the authority for an entry
+/// to the authorities for a key the entry is indexed under
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, SerializedBytes)]
+pub enum IndexOp {
+    /// The entry that a header created is indexed under the key
+    Register {
+        /// The key the entry is indexed under
+        index_key: IndexKey,
+        /// The entry
+        entry_hash: EntryHash,
+        /// The header which created the entry
+        header_hash: HeaderHash,
+    },
+    /// The header which created the entry has been deleted.
+    /// The entry stays indexed while any other header creating it is live.
+    Tombstone {
+        /// The key the entry is indexed under
+        index_key: IndexKey,
+        /// The entry
+        entry_hash: EntryHash,
+        /// The deleted header which created the entry
+        header_hash: HeaderHash,
+    },
+}
+
+impl IndexOp {
+    /// The key this op changes
+    pub fn index_key(&self) -> &IndexKey {
+        match self {
+            IndexOp::Register { index_key, .. } | IndexOp::Tombstone { index_key, .. } => index_key,
+        }
+    }
+}
+
+/// An authority's slice of an index: the hashes of the live entries
+/// it holds under a key, in hash order
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, SerializedBytes)]
+pub struct GetIndexedResponse {
+    /// The entries indexed under the key
+    pub entry_hashes: Vec<EntryHash>,
+}
+
+impl IndexKey {
+    /// A key for entries of an app entry type
+    pub fn new(app_entry_type: &AppEntryType, key: Vec<u8>) -> Self {
+        Self {
+            zome_id: app_entry_type.zome_id(),
+            entry_def_index: app_entry_type.id(),
+            key,
+        }
+    }
+
+    /// The basis of the authorities for this key.
+    /// The authorities for an entry publish [IndexOp]s for its keys
+    /// to this basis, and requests for the key are sent to it.
+    pub fn basis(&self) -> AnyDhtHash {
+        EntryHash::with_data_sync(self).into()
+    }
+}
+
+impl HashableContent for IndexKey {
+    type HashType = hash_type::Entry;
+
+    fn hash_type(&self) -> Self::HashType {
+        hash_type::Entry
+    }
+
+    fn hashable_content(&self) -> HashableContentBytes {
+        HashableContentBytes::Content(
+            self.try_into()
+                .expect("Could not serialize HashableContent"),
+        )
+    }
+}
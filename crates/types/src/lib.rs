@@ -14,6 +14,7 @@ pub mod element;
 pub mod entry;
 pub mod fixt;
 pub mod header;
+pub mod index;
 pub mod link;
 mod macros;
 pub mod metadata;
@@ -31,6 +32,6 @@ pub use entry::{Entry, EntryHashed};
 #[doc(inline)]
 pub use header::HeaderHashed;
 
-pub use timestamp::{Timestamp, TimestampKey};
+pub use timestamp::{Clock, SystemClock, Timestamp, TimestampKey};
 
 pub use observability;
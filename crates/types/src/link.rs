@@ -1,13 +1,16 @@
 //! Links interrelate entries in a source chain.
 
+use crate::metadata::TimedHeaderHash;
 use holo_hash::{AnyDhtHash, EntryHash, HeaderHash};
 use holochain_serialized_bytes::prelude::*;
 use holochain_zome_types::signature::Signature;
 use holochain_zome_types::{
     header::{CreateLink, DeleteLink, ZomeId},
     link::LinkTag,
+    Header,
 };
 use regex::Regex;
+use std::cmp::Ordering;
 
 /// Links interrelate entries in a source chain.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, SerializedBytes)]
@@ -33,7 +36,9 @@ pub enum WireLinkMetaKey {
 // TODO: Probably don't want to send the whole headers.
 // We could probably come up with a more compact
 // network Wire type in the future
-/// Link response to get links
+/// Link response to get links.
+/// Authorities send the links in the order of [link_order],
+/// see [GetLinksResponse::sort].
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, SerializedBytes)]
 pub struct GetLinksResponse {
     /// All the link adds on the key you searched for
@@ -42,6 +47,44 @@ pub struct GetLinksResponse {
     pub link_removes: Vec<(DeleteLink, Signature)>,
 }
 
+/// The canonical order of links: by the timestamp of the link's header,
+/// with ties broken by the header's hash.
+/// Every source of links (authorities, the cache and authored data)
+/// is ordered by this one comparator, so the same set of links always
+/// comes back in the same order, and a position in a list of links means
+/// the same thing to every agent.
+pub fn link_order(a: &TimedHeaderHash, b: &TimedHeaderHash) -> Ordering {
+    a.timestamp
+        .cmp(&b.timestamp)
+        .then_with(|| a.header_hash.cmp(&b.header_hash))
+}
+
+/// Sort headers by [link_order], hashing each header once
+fn sort_by_link_order<H: Clone + Into<Header>>(headers: &mut Vec<(H, Signature)>) {
+    let mut timed: Vec<_> = headers
+        .drain(..)
+        .map(|(h, s)| {
+            let header: Header = h.clone().into();
+            let timed = TimedHeaderHash {
+                timestamp: header.timestamp().into(),
+                header_hash: HeaderHash::with_data_sync(&header),
+            };
+            (timed, (h, s))
+        })
+        .collect();
+    timed.sort_by(|(a, _), (b, _)| link_order(a, b));
+    headers.extend(timed.into_iter().map(|(_, h)| h));
+}
+
+impl GetLinksResponse {
+    /// Put the link adds and link removes in the order of [link_order],
+    /// so that the response doesn't depend on how the authority stored them
+    pub fn sort(&mut self) {
+        sort_by_link_order(&mut self.link_adds);
+        sort_by_link_order(&mut self.link_removes);
+    }
+}
+
 impl WireLinkMetaKey {
     /// Get the basis of this key
     pub fn basis(&self) -> AnyDhtHash {
@@ -106,3 +149,70 @@ impl<S: Into<String>> LinkMatch<S> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixt::SignatureFixturator,
+        test_utils::{fake_agent_pubkey_1, fake_entry_hash, fake_header_hash},
+        Timestamp,
+    };
+    use ::fixt::prelude::*;
+    use std::convert::TryFrom;
+
+    fn create_link(seq: u32, timestamp: Timestamp) -> (CreateLink, Signature) {
+        let header = CreateLink {
+            author: fake_agent_pubkey_1(),
+            timestamp: timestamp.into(),
+            header_seq: seq,
+            prev_header: fake_header_hash(seq as u8),
+            base_address: fake_entry_hash(1),
+            target_address: fake_entry_hash(2),
+            zome_id: ZomeId::from(0),
+            tag: LinkTag::new(vec![seq as u8]),
+        };
+        (header, fixt!(Signature))
+    }
+
+    #[test]
+    fn get_links_response_order_is_independent_of_source_order() {
+        // Three of the links share a timestamp
+        let links: Vec<_> = [10, 10, 5, 10, 20]
+            .iter()
+            .enumerate()
+            .map(|(seq, secs)| create_link(seq as u32, Timestamp(*secs, 0)))
+            .collect();
+        let mut forwards = GetLinksResponse {
+            link_adds: links.clone(),
+            link_removes: vec![],
+        };
+        let mut backwards = GetLinksResponse {
+            link_adds: links.into_iter().rev().collect(),
+            link_removes: vec![],
+        };
+        forwards.sort();
+        backwards.sort();
+        assert_eq!(
+            SerializedBytes::try_from(forwards.clone()).unwrap(),
+            SerializedBytes::try_from(backwards).unwrap()
+        );
+
+        let timed: Vec<_> = forwards
+            .link_adds
+            .iter()
+            .map(|(h, _)| {
+                let header = Header::CreateLink(h.clone());
+                TimedHeaderHash {
+                    timestamp: header.timestamp().into(),
+                    header_hash: HeaderHash::with_data_sync(&header),
+                }
+            })
+            .collect();
+        assert!(timed
+            .windows(2)
+            .all(|w| link_order(&w[0], &w[1]) == Ordering::Less));
+        assert_eq!(timed[0].timestamp, Timestamp(5, 0));
+        assert_eq!(timed[4].timestamp, Timestamp(20, 0));
+    }
+}
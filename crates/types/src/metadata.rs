@@ -18,18 +18,26 @@ pub struct TimedHeaderHash {
 
 /// Metadata returned from a GetMeta request.
 /// The Ord derive on TimedHeaderHash means each set is ordered by time.
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, SerializedBytes)]
+/// A section is `None` when it was not requested, so callers can tell
+/// "not asked for" apart from "nothing there".
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, SerializedBytes)]
 pub struct MetadataSet {
     /// Headers that created or updated an entry.
     /// These are the headers that show the entry exists.
-    pub headers: BTreeSet<TimedHeaderHash>,
+    pub headers: Option<BTreeSet<TimedHeaderHash>>,
     // TODO: Implement after validation
     /// Placeholder
-    pub invalid_headers: BTreeSet<TimedHeaderHash>,
+    pub invalid_headers: Option<BTreeSet<TimedHeaderHash>>,
     /// Deletes on a header
-    pub deletes: BTreeSet<TimedHeaderHash>,
+    pub deletes: Option<BTreeSet<TimedHeaderHash>>,
     /// Updates on a header or entry
-    pub updates: BTreeSet<TimedHeaderHash>,
+    pub updates: Option<BTreeSet<TimedHeaderHash>>,
+    /// Link creates on a base
+    pub links: Option<BTreeSet<TimedHeaderHash>>,
+    /// Link deletes on the link creates of a base
+    pub link_removes: Option<BTreeSet<TimedHeaderHash>>,
+    /// Headers on an agent's source chain
+    pub activity: Option<BTreeSet<TimedHeaderHash>>,
     /// The status of an entry from an authority.
     /// This is simply a faster way of determining if
     /// there are any live headers on an entry.
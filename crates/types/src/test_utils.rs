@@ -5,6 +5,7 @@ use crate::{
     dna::{wasm::DnaWasm, zome::Zome, JsonProperties},
     dna::{DnaDef, DnaFile},
     prelude::*,
+    Clock,
 };
 use holochain_zome_types::capability::CapSecret;
 use holochain_zome_types::capability::CAP_SECRET_BYTES;
@@ -92,3 +93,32 @@ pub fn which_agent(key: &AgentPubKey) -> String {
 pub fn fake_cap_secret() -> CapSecret {
     [0; CAP_SECRET_BYTES].into()
 }
+
+/// A [Clock] which only moves when told to, for deterministic
+/// timestamps in tests, including ones which go backwards.
+#[derive(Debug)]
+pub struct FakeClock(std::sync::Mutex<Timestamp>);
+
+impl FakeClock {
+    /// A clock stopped at the given time
+    pub fn new(now: Timestamp) -> Self {
+        Self(std::sync::Mutex::new(now))
+    }
+
+    /// Move the clock to the given time
+    pub fn set(&self, now: Timestamp) {
+        *self.0.lock().expect("FakeClock lock poisoned") = now;
+    }
+
+    /// Move the clock forward, or backward for negative seconds
+    pub fn advance(&self, secs: i64) {
+        let mut now = self.0.lock().expect("FakeClock lock poisoned");
+        now.0 += secs;
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Timestamp {
+        *self.0.lock().expect("FakeClock lock poisoned")
+    }
+}
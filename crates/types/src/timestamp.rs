@@ -27,6 +27,24 @@ impl Timestamp {
     }
 }
 
+/// A source of the current time.
+/// Code which stamps data with the time can take a Clock instead of calling
+/// [Timestamp::now] directly, so that tests can control the time it sees.
+pub trait Clock: Send + Sync {
+    /// The current time according to this clock
+    fn now(&self) -> Timestamp;
+}
+
+/// The [Clock] which reads the system time
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::now()
+    }
+}
+
 impl std::fmt::Display for Timestamp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let t: chrono::DateTime<chrono::Utc> = self.into();
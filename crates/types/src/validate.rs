@@ -2,7 +2,11 @@
 //! c.f. _guest_ types for validation callbacks and packages across the wasm boudary in zome_types
 
 use holochain_serialized_bytes::prelude::*;
-use holochain_zome_types::validate::ValidationPackage;
+use holochain_zome_types::{
+    element::{Element, ElementEntry},
+    entry_def::EntryVisibility,
+    validate::{RequiredValidationType, ValidationPackage},
+};
 
 /// the validation status for an op
 /// much of this happens in the subconscious
@@ -31,3 +35,75 @@ pub enum ValidationStatus {
 )]
 /// Type for sending responses to `get_validation_package`
 pub struct ValidationPackageResponse(pub Option<ValidationPackage>);
+
+/// A validation package along with a summary of how it was built,
+/// for debugging authorities which disagree about an op's validity
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, SerializedBytes)]
+pub struct ValidationPackageReport {
+    /// The package, empty if none was built or sent
+    pub package: ValidationPackage,
+    /// What went into the package
+    pub summary: ValidationPackageSummary,
+}
+
+/// What went into a [ValidationPackageReport]'s package
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ValidationPackageSummary {
+    /// How many elements the package holds
+    pub element_count: usize,
+    /// The validation type required by the entry def of the header's entry,
+    /// or `None` if the header has no app entry
+    pub required_validation_type: Option<RequiredValidationType>,
+    /// Whether a package was built or sent at all
+    pub package_present: bool,
+    /// How many private entries the package doesn't carry, because they
+    /// were never sent or because the requester is not their author
+    pub redacted_entries: usize,
+}
+
+impl ValidationPackageReport {
+    /// Summarize a package, hiding its private entries
+    /// unless the requester is their author
+    pub fn new(
+        package: Option<ValidationPackage>,
+        required_validation_type: Option<RequiredValidationType>,
+        requester_is_author: bool,
+    ) -> Self {
+        let package_present = package.is_some();
+        let mut redacted_entries = 0;
+        let elements = package
+            .map(|p| p.0)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|element| {
+                if !is_private_entry(&element) {
+                    return element;
+                }
+                if requester_is_author && matches!(element.entry(), ElementEntry::Present(_)) {
+                    return element;
+                }
+                redacted_entries += 1;
+                let (signed_header, _) = element.into_inner();
+                Element::new(signed_header, None)
+            })
+            .collect::<Vec<_>>();
+        Self {
+            summary: ValidationPackageSummary {
+                element_count: elements.len(),
+                required_validation_type,
+                package_present,
+                redacted_entries,
+            },
+            package: ValidationPackage::new(elements),
+        }
+    }
+}
+
+/// Whether an element's header is for a private entry
+fn is_private_entry(element: &Element) -> bool {
+    element
+        .header()
+        .entry_data()
+        .map(|(_, entry_type)| *entry_type.visibility() == EntryVisibility::Private)
+        .unwrap_or(false)
+}
@@ -4,12 +4,40 @@ use crate::zome::ZomeName;
 use holo_hash::AgentPubKey;
 use holochain_serialized_bytes::prelude::SerializedBytes;
 
+/// The capability secret to send with a remote call.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum CallRemoteCap {
+    /// Send no secret, e.g. for functions with an unrestricted grant.
+    None,
+    /// Send the given secret.
+    Secret(CapSecret),
+    /// Have the host find the secret in the caller's CapClaims.
+    /// The most recent claim from the called agent for the called function is used,
+    /// skipping claims superseded by a newer claim with the same tag.
+    Auto,
+}
+
+impl From<Option<CapSecret>> for CallRemoteCap {
+    fn from(maybe_secret: Option<CapSecret>) -> Self {
+        match maybe_secret {
+            Some(secret) => Self::Secret(secret),
+            None => Self::None,
+        }
+    }
+}
+
+impl From<CapSecret> for CallRemoteCap {
+    fn from(secret: CapSecret) -> Self {
+        Self::Secret(secret)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct CallRemote {
     to_agent: AgentPubKey,
     zome_name: ZomeName,
     fn_name: FunctionName,
-    cap: Option<CapSecret>,
+    cap: CallRemoteCap,
     request: SerializedBytes,
 }
 
@@ -18,7 +46,7 @@ impl CallRemote {
         to_agent: AgentPubKey,
         zome_name: ZomeName,
         fn_name: FunctionName,
-        cap: Option<CapSecret>,
+        cap: CallRemoteCap,
         request: SerializedBytes,
     ) -> Self {
         Self {
@@ -42,7 +70,7 @@ impl CallRemote {
         self.fn_name.clone()
     }
 
-    pub fn cap(&self) -> Option<CapSecret> {
+    pub fn cap(&self) -> CallRemoteCap {
         self.cap
     }
 
@@ -1,4 +1,6 @@
 use super::CapSecret;
+use super::GrantedFunction;
+use super::GrantedFunctions;
 use holo_hash::*;
 use holochain_serialized_bytes::prelude::*;
 
@@ -17,15 +19,25 @@ pub struct CapClaim {
     /// Note that the grantor may have revoked the corresponding grant since we received the claim
     /// so claims are only ever a 'best effort' basis.
     secret: CapSecret,
+    /// The functions the corresponding CapGrant was made for.
+    /// Used to find a claim for a remote call without the caller naming it.
+    #[serde(default)]
+    functions: GrantedFunctions,
 }
 
 impl CapClaim {
     /// Constructor.
-    pub fn new(tag: String, grantor: AgentPubKey, secret: CapSecret) -> Self {
+    pub fn new(
+        tag: String,
+        grantor: AgentPubKey,
+        secret: CapSecret,
+        functions: GrantedFunctions,
+    ) -> Self {
         CapClaim {
             tag,
             grantor,
             secret,
+            functions,
         }
     }
 
@@ -43,4 +55,14 @@ impl CapClaim {
     pub fn grantor(&self) -> &AgentPubKey {
         &self.grantor
     }
+
+    /// Access the functions this claim was granted for
+    pub fn functions(&self) -> &GrantedFunctions {
+        &self.functions
+    }
+
+    /// Can this claim be used to call the given function on the given agent?
+    pub fn is_for(&self, agent: &AgentPubKey, function: &GrantedFunction) -> bool {
+        &self.grantor == agent && self.functions.contains(function)
+    }
 }
@@ -0,0 +1,40 @@
+//! Optimistic concurrency for zomes.
+//!
+//! A zome reads the source chain head, decides what to commit from what it
+//! has read, then commits with a [CommitGuard] on that head. If anything
+//! was committed in between, the commit is refused with [HeadMoved] and the
+//! zome can read again and retry its own logic.
+
+use holo_hash::HeaderHash;
+use holochain_serialized_bytes::prelude::*;
+
+/// Only commit if the source chain head is still `expected_head`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CommitGuard {
+    /// The chain head the commit was decided on
+    pub expected_head: HeaderHash,
+}
+
+impl CommitGuard {
+    /// Guard a commit on the given chain head
+    pub fn new(expected_head: HeaderHash) -> Self {
+        Self { expected_head }
+    }
+}
+
+/// A guarded commit was refused because the chain head has moved
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HeadMoved {
+    /// The chain head the commit was guarded on
+    pub expected: HeaderHash,
+    /// The chain head when the commit was attempted
+    pub actual: HeaderHash,
+}
+
+/// The header hash of a commit made without a [CommitGuard].
+///
+/// The host only refuses a commit with [HeadMoved] when it was guarded, so
+/// this never panics for the output of an unguarded commit.
+pub fn unguarded(committed: Result<HeaderHash, HeadMoved>) -> HeaderHash {
+    committed.expect("the host refused a commit made without a guard")
+}
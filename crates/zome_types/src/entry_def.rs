@@ -36,7 +36,7 @@ impl Default for EntryVisibility {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct RequiredValidations(u8);
 
 impl From<u8> for RequiredValidations {
@@ -114,3 +114,123 @@ impl<'a> TryFrom<&'a Header> for &'a DeleteLink {
         }
     }
 }
+
+impl TryFrom<Header> for Dna {
+    type Error = WrongHeaderError;
+    fn try_from(value: Header) -> Result<Self, Self::Error> {
+        match value {
+            Header::Dna(h) => Ok(h),
+            _ => Err(WrongHeaderError(format!("{:?}", value))),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a Header> for &'a Dna {
+    type Error = WrongHeaderError;
+    fn try_from(value: &'a Header) -> Result<Self, Self::Error> {
+        match value {
+            Header::Dna(h) => Ok(h),
+            _ => Err(WrongHeaderError(format!("{:?}", value))),
+        }
+    }
+}
+
+impl TryFrom<Header> for AgentValidationPkg {
+    type Error = WrongHeaderError;
+    fn try_from(value: Header) -> Result<Self, Self::Error> {
+        match value {
+            Header::AgentValidationPkg(h) => Ok(h),
+            _ => Err(WrongHeaderError(format!("{:?}", value))),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a Header> for &'a AgentValidationPkg {
+    type Error = WrongHeaderError;
+    fn try_from(value: &'a Header) -> Result<Self, Self::Error> {
+        match value {
+            Header::AgentValidationPkg(h) => Ok(h),
+            _ => Err(WrongHeaderError(format!("{:?}", value))),
+        }
+    }
+}
+
+impl TryFrom<Header> for InitZomesComplete {
+    type Error = WrongHeaderError;
+    fn try_from(value: Header) -> Result<Self, Self::Error> {
+        match value {
+            Header::InitZomesComplete(h) => Ok(h),
+            _ => Err(WrongHeaderError(format!("{:?}", value))),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a Header> for &'a InitZomesComplete {
+    type Error = WrongHeaderError;
+    fn try_from(value: &'a Header) -> Result<Self, Self::Error> {
+        match value {
+            Header::InitZomesComplete(h) => Ok(h),
+            _ => Err(WrongHeaderError(format!("{:?}", value))),
+        }
+    }
+}
+
+impl TryFrom<Header> for OpenChain {
+    type Error = WrongHeaderError;
+    fn try_from(value: Header) -> Result<Self, Self::Error> {
+        match value {
+            Header::OpenChain(h) => Ok(h),
+            _ => Err(WrongHeaderError(format!("{:?}", value))),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a Header> for &'a OpenChain {
+    type Error = WrongHeaderError;
+    fn try_from(value: &'a Header) -> Result<Self, Self::Error> {
+        match value {
+            Header::OpenChain(h) => Ok(h),
+            _ => Err(WrongHeaderError(format!("{:?}", value))),
+        }
+    }
+}
+
+impl TryFrom<Header> for CloseChain {
+    type Error = WrongHeaderError;
+    fn try_from(value: Header) -> Result<Self, Self::Error> {
+        match value {
+            Header::CloseChain(h) => Ok(h),
+            _ => Err(WrongHeaderError(format!("{:?}", value))),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a Header> for &'a CloseChain {
+    type Error = WrongHeaderError;
+    fn try_from(value: &'a Header) -> Result<Self, Self::Error> {
+        match value {
+            Header::CloseChain(h) => Ok(h),
+            _ => Err(WrongHeaderError(format!("{:?}", value))),
+        }
+    }
+}
+
+impl TryFrom<Header> for Create {
+    type Error = WrongHeaderError;
+    fn try_from(value: Header) -> Result<Self, Self::Error> {
+        match value {
+            Header::Create(h) => Ok(h),
+            _ => Err(WrongHeaderError(format!("{:?}", value))),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a Header> for &'a Create {
+    type Error = WrongHeaderError;
+    fn try_from(value: &'a Header) -> Result<Self, Self::Error> {
+        match value {
+            Header::Create(h) => Ok(h),
+            _ => Err(WrongHeaderError(format!("{:?}", value))),
+        }
+    }
+}
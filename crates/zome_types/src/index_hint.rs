@@ -0,0 +1,40 @@
+//! Indexing entries by keys of the zome's choosing.
+//!
+//! A zome may define an `index_hint_<entry_def_id>` callback, which is given
+//! an entry of that def and returns the keys to index it under. Authorities
+//! run the callback when they validate the entry, and record its hash under
+//! each key, so that other agents can look it up with `get_indexed`.
+//! Like validation, the callback must give the same keys for the same entry
+//! every time it is run.
+
+use crate::bytes::Bytes;
+use crate::zome_io::ExternOutput;
+use crate::CallbackResult;
+use holochain_serialized_bytes::prelude::*;
+
+/// The result of an `index_hint` callback
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, SerializedBytes)]
+pub enum IndexHintCallbackResult {
+    /// Index the entry under each of these keys
+    Keys(Vec<Bytes>),
+    /// The entry can't be indexed, so the op storing it is rejected
+    Fail(String),
+}
+
+impl CallbackResult for IndexHintCallbackResult {
+    fn is_definitive(&self) -> bool {
+        match self {
+            IndexHintCallbackResult::Fail(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<ExternOutput> for IndexHintCallbackResult {
+    fn from(guest_output: ExternOutput) -> Self {
+        match guest_output.into_inner().try_into() {
+            Ok(v) => v,
+            Err(e) => Self::Fail(format!("{:?}", e)),
+        }
+    }
+}
@@ -15,6 +15,7 @@ pub mod bytes;
 #[allow(missing_docs)]
 pub mod call_remote;
 pub mod capability;
+pub mod commit_guard;
 #[allow(missing_docs)]
 pub mod crdt;
 pub mod debug;
@@ -24,6 +25,7 @@ pub mod entry;
 pub mod entry_def;
 #[allow(missing_docs)]
 pub mod header;
+pub mod index_hint;
 #[allow(missing_docs)]
 pub mod init;
 #[allow(missing_docs)]
@@ -60,18 +60,40 @@ pub struct Link {
     pub tag: LinkTag,
 }
 
+/// How complete the view of a base's links returned by `get_links` is
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum LinkFreshness {
+    /// The base's authority answered, and already holds every link
+    /// and link delete this agent authored on the base
+    Complete,
+    /// The authority couldn't be reached, so only the links held locally
+    /// are returned
+    LocalOnly,
+    /// The authority answered, and links or link deletes this agent authored
+    /// which it doesn't hold yet were merged into its answer
+    Merged,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, SerializedBytes, PartialEq, Clone, Debug)]
-pub struct Links(Vec<Link>);
+pub struct Links {
+    links: Vec<Link>,
+    /// `None` if the links didn't come from a `get_links` call
+    #[serde(default)]
+    freshness: Option<LinkFreshness>,
+}
 
 impl From<Vec<Link>> for Links {
-    fn from(v: Vec<Link>) -> Self {
-        Self(v)
+    fn from(links: Vec<Link>) -> Self {
+        Self {
+            links,
+            freshness: None,
+        }
     }
 }
 
 impl From<Links> for Vec<Link> {
     fn from(links: Links) -> Self {
-        links.0
+        links.links
     }
 }
 
@@ -79,6 +101,17 @@ impl Links {
     pub fn into_inner(self) -> Vec<Link> {
         self.into()
     }
+
+    /// Mark how complete these links are
+    pub fn with_freshness(mut self, freshness: LinkFreshness) -> Self {
+        self.freshness = Some(freshness);
+        self
+    }
+
+    /// How complete these links are, if they came from a `get_links` call
+    pub fn freshness(&self) -> Option<LinkFreshness> {
+        self.freshness
+    }
 }
 
 type CreateLinkWithDeleteLinks = Vec<(SignedHeaderHashed, Vec<SignedHeaderHashed>)>;
@@ -58,6 +58,20 @@ pub struct EntryDetails {
     pub entry_dht_status: EntryDhtStatus,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, SerializedBytes)]
+/// Every live header which created an Entry.
+/// Useful when the same Entry is created by many authors.
+pub struct LiveHeaders {
+    /// The data
+    pub entry: Entry,
+    /// The [Create] and [Update] headers for the above
+    /// Entry which haven't been deleted.
+    pub headers: Vec<SignedHeaderHashed>,
+    /// The deletes that have the
+    /// `deletes_entry_address` set to the above Entry.
+    pub deletes: Vec<SignedHeaderHashed>,
+}
+
 /// The status of an [Entry] in the Dht
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EntryDhtStatus {
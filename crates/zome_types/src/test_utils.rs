@@ -4,6 +4,7 @@
 
 use crate::capability::CapSecret;
 use crate::capability::CAP_SECRET_BYTES;
+use crate::zome_info::ZomeInfo;
 use crate::ExternInput;
 use holo_hash::{hash_type, *};
 use holochain_serialized_bytes::prelude::*;
@@ -58,3 +59,16 @@ pub fn fake_cap_secret() -> CapSecret {
 pub fn fake_zome_invocation_payload() -> ExternInput {
     ExternInput::try_from(SerializedBytes::try_from(()).unwrap()).unwrap()
 }
+
+/// A fixture ZomeInfo for unit testing: zome 0, named "test_zome",
+/// of a Dna with no properties or network seed.
+/// Override just the fields under test with its `with_*` setters.
+pub fn fake_zome_info() -> ZomeInfo {
+    ZomeInfo::new(
+        "test_dna".to_string(),
+        fake_dna_hash(1),
+        "test_zome".into(),
+        0.into(),
+        SerializedBytes::try_from(()).unwrap(),
+    )
+}
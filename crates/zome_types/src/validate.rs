@@ -42,7 +42,7 @@ pub struct ValidationPackage(pub Vec<Element>);
 
 /// The level of validation package required by
 /// an entry.
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RequiredValidationType {
     /// Just the element (default)
     Element,
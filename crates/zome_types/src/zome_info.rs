@@ -12,4 +12,98 @@ pub struct ZomeInfo {
     /// The position of this zome in the `dna.json`
     pub zome_id: ZomeId,
     pub properties: crate::SerializedBytes,
+    /// The seed which separates this Dna's network from others built from
+    /// the same wasm, i.e. the Dna's uuid. `None` if the Dna has no seed.
+    #[serde(default)]
+    pub network_seed: Option<String>,
+}
+
+impl ZomeInfo {
+    /// Constructor, for a Dna without a network seed
+    pub fn new(
+        dna_name: String,
+        dna_hash: DnaHash,
+        zome_name: ZomeName,
+        zome_id: ZomeId,
+        properties: SerializedBytes,
+    ) -> Self {
+        Self {
+            dna_name,
+            dna_hash,
+            zome_name,
+            zome_id,
+            properties,
+            network_seed: None,
+        }
+    }
+
+    /// Set the Dna's name
+    pub fn with_dna_name(mut self, dna_name: impl Into<String>) -> Self {
+        self.dna_name = dna_name.into();
+        self
+    }
+
+    /// Set the Dna's hash
+    pub fn with_dna_hash(mut self, dna_hash: DnaHash) -> Self {
+        self.dna_hash = dna_hash;
+        self
+    }
+
+    /// Set the zome's name
+    pub fn with_zome_name(mut self, zome_name: impl Into<ZomeName>) -> Self {
+        self.zome_name = zome_name.into();
+        self
+    }
+
+    /// Set the zome's position in the Dna
+    pub fn with_zome_id(mut self, zome_id: impl Into<ZomeId>) -> Self {
+        self.zome_id = zome_id.into();
+        self
+    }
+
+    /// Set the Dna's properties
+    pub fn with_properties(mut self, properties: SerializedBytes) -> Self {
+        self.properties = properties;
+        self
+    }
+
+    /// Set the Dna's network seed
+    pub fn with_network_seed(mut self, network_seed: impl Into<String>) -> Self {
+        self.network_seed = Some(network_seed.into());
+        self
+    }
+
+    /// Whether both infos are of the same zome of the same Dna,
+    /// even if the zome's instances were configured differently.
+    /// Unlike `==`, the properties, network seed and Dna name are ignored.
+    pub fn same_zome(&self, other: &ZomeInfo) -> bool {
+        self.dna_hash == other.dna_hash
+            && self.zome_name == other.zome_name
+            && self.zome_id == other.zome_id
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "fixturators")]
+mod tests {
+    use super::*;
+    use crate::test_utils::fake_zome_info;
+    use ::fixt::prelude::*;
+    use holo_hash::fixt::DnaHashFixturator;
+
+    #[test]
+    fn same_zome_ignores_configuration() {
+        let info = fake_zome_info().with_dna_hash(fixt!(DnaHash));
+        let configured = info
+            .clone()
+            .with_dna_name("renamed")
+            .with_properties(SerializedBytes::from(UnsafeBytes::from(vec![1_u8])))
+            .with_network_seed("seed");
+        assert_ne!(info, configured);
+        assert!(info.same_zome(&configured));
+
+        assert!(!info.same_zome(&info.clone().with_zome_id(1)));
+        assert!(!info.same_zome(&info.clone().with_zome_name("other")));
+        assert!(!info.same_zome(&info.clone().with_dna_hash(fixt!(DnaHash))));
+    }
 }
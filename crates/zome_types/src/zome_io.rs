@@ -50,9 +50,24 @@ wasm_io_types!(
     // The EntryDefId determines how a create is handled on the host side.
     // CapGrant and CapClaim are handled natively.
     // App entries are referenced by entry defs then SerializedBytes stuffed into an Entry::App.
-    pub struct CreateInput((crate::entry_def::EntryDefId, crate::entry::Entry));
-    // Header hash of the newly created element.
-    pub struct CreateOutput(holo_hash::HeaderHash);
+    // With a guard, only committed if the chain head is still the guard's expected head.
+    pub struct CreateInput(
+        (
+            crate::entry_def::EntryDefId,
+            crate::entry::Entry,
+            Option<crate::commit_guard::CommitGuard>,
+        ),
+    );
+    // Header hash of the newly created element, or why the guard refused the commit.
+    pub struct CreateOutput(Result<holo_hash::HeaderHash, crate::commit_guard::HeadMoved>);
+    // The current chain head, including anything committed earlier in the call.
+    pub struct ChainHeadInput(());
+    // The header hash, sequence number and timestamp of the chain head.
+    pub struct ChainHeadOutput((holo_hash::HeaderHash, u32, crate::timestamp::Timestamp));
+    // Commit a CapClaim as a private entry so call_remote can look it up later.
+    pub struct CommitCapClaimInput(crate::capability::CapClaim);
+    // Header hash of the newly committed claim.
+    pub struct CommitCapClaimOutput(holo_hash::HeaderHash);
     // @todo
     pub struct DecryptInput(());
     pub struct DecryptOutput(());
@@ -71,10 +86,15 @@ wasm_io_types!(
     // the length of random bytes to create
     pub struct RandomBytesInput(u32);
     pub struct RandomBytesOutput(crate::bytes::Bytes);
-    // Header hash of the CreateLink element.
-    pub struct DeleteLinkInput(holo_hash::HeaderHash);
-    // Header hash of the DeleteLink element.
-    pub struct DeleteLinkOutput(holo_hash::HeaderHash);
+    // Header hash of the CreateLink element, and an optional commit guard.
+    pub struct DeleteLinkInput(
+        (
+            holo_hash::HeaderHash,
+            Option<crate::commit_guard::CommitGuard>,
+        ),
+    );
+    // Header hash of the DeleteLink element, or why the guard refused the commit.
+    pub struct DeleteLinkOutput(Result<holo_hash::HeaderHash, crate::commit_guard::HeadMoved>);
     pub struct CallRemoteInput(crate::call_remote::CallRemote);
     pub struct CallRemoteOutput(ZomeCallResponse);
     // @todo
@@ -95,35 +115,50 @@ wasm_io_types!(
             crate::entry_def::EntryDefId,
             crate::entry::Entry,
             holo_hash::HeaderHash,
+            Option<crate::commit_guard::CommitGuard>,
         ),
     );
-    // Header hash of the newly committed element.
-    pub struct UpdateOutput(holo_hash::HeaderHash);
+    // Header hash of the newly committed element, or why the guard refused the commit.
+    pub struct UpdateOutput(Result<holo_hash::HeaderHash, crate::commit_guard::HeadMoved>);
     // Emit a Signal::App to subscribers on the interface
     pub struct EmitSignalInput(SerializedBytes);
     pub struct EmitSignalOutput(());
-    // @todo
-    pub struct DeleteInput(holo_hash::HeaderHash);
-    pub struct DeleteOutput(holo_hash::HeaderHash);
+    // Header hash of the deleted element, and an optional commit guard.
+    pub struct DeleteInput(
+        (
+            holo_hash::HeaderHash,
+            Option<crate::commit_guard::CommitGuard>,
+        ),
+    );
+    // Header hash of the Delete element, or why the guard refused the commit.
+    pub struct DeleteOutput(Result<holo_hash::HeaderHash, crate::commit_guard::HeadMoved>);
     // Create a link between two entries.
     pub struct CreateLinkInput(
         (
             holo_hash::EntryHash,
             holo_hash::EntryHash,
             crate::link::LinkTag,
+            Option<crate::commit_guard::CommitGuard>,
         ),
     );
-    pub struct CreateLinkOutput(holo_hash::HeaderHash);
+    // Header hash of the CreateLink element, or why the guard refused the commit.
+    pub struct CreateLinkOutput(Result<holo_hash::HeaderHash, crate::commit_guard::HeadMoved>);
     // Get links by entry hash from the cascade.
     pub struct GetLinksInput((holo_hash::EntryHash, Option<crate::link::LinkTag>));
     pub struct GetLinksOutput(crate::link::Links);
     pub struct GetLinkDetailsInput((holo_hash::EntryHash, Option<crate::link::LinkTag>));
     pub struct GetLinkDetailsOutput(crate::link::LinkDetails);
+    // Get the hashes of live entries of an entry def, indexed under a key by its index_hint callback.
+    pub struct GetIndexedInput((crate::entry_def::EntryDefId, crate::bytes::Bytes));
+    pub struct GetIndexedOutput(Vec<holo_hash::EntryHash>);
     // Attempt to get a live entry from the cascade.
     pub struct GetInput((holo_hash::AnyDhtHash, crate::entry::GetOptions));
     pub struct GetOutput(Option<crate::element::Element>);
     pub struct GetDetailsInput((holo_hash::AnyDhtHash, crate::entry::GetOptions));
     pub struct GetDetailsOutput(Option<crate::metadata::Details>);
+    // Get every live header which created an entry, with the deletes on the entry.
+    pub struct GetAllLiveHeadersInput((holo_hash::EntryHash, crate::entry::GetOptions));
+    pub struct GetAllLiveHeadersOutput(Option<crate::metadata::LiveHeaders>);
     // @todo
     pub struct EntryTypePropertiesInput(());
     pub struct EntryTypePropertiesOutput(());